@@ -0,0 +1,34 @@
+use benchmarks::{
+    layout, new_painter, paint, parse, rc_layout_tree, style, traverse, traverse_rc, FIXTURES,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn pipeline_benchmark(c: &mut Criterion) {
+    for fixture in FIXTURES.iter() {
+        let mut group = c.benchmark_group(fixture.name);
+
+        group.bench_function("parse", |b| b.iter(|| parse(black_box(fixture.html))));
+
+        let document = parse(fixture.html);
+        group.bench_function("style", |b| b.iter(|| style(&document)));
+
+        style(&document);
+        group.bench_function("layout", |b| b.iter(|| layout(&document)));
+
+        let layout_tree = layout(&document).unwrap();
+        group.bench_function("traverse", |b| b.iter(|| traverse(&layout_tree)));
+
+        // The same traversal with every box allocated on its own
+        let rc_tree = rc_layout_tree(&layout_tree);
+        group.bench_function("traverse rc", |b| b.iter(|| traverse_rc(&rc_tree)));
+        rc_tree.teardown();
+
+        let mut painter = new_painter();
+        group.bench_function("paint", |b| b.iter(|| paint(&mut painter, &layout_tree)));
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, pipeline_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,132 @@
+//! Representative pages to measure the rendering pipeline with. Every stage
+//! runs on the CPU: painting tessellates shapes but nothing is rasterized.
+
+use dom::{
+    document::Document,
+    node::{Node, NodeData, NodePtr},
+};
+use futures::executor::block_on;
+use gfx::HeadlessGraphics;
+use layout::layout_box::{BoxData, LayoutBox, LayoutBoxPtr};
+use painting::Painter;
+use shared::{
+    primitive::Size,
+    tree_node::{TreeNode, TreeNodeHooks},
+};
+use std::hint::black_box;
+
+pub struct Fixture {
+    pub name: &'static str,
+    pub html: &'static str,
+}
+
+pub const FIXTURES: [Fixture; 3] = [
+    // Text heavy page, mostly line breaking
+    Fixture {
+        name: "article",
+        html: include_str!("../fixtures/article.html"),
+    },
+    // Many small bordered and rounded boxes
+    Fixture {
+        name: "cards",
+        html: include_str!("../fixtures/cards.html"),
+    },
+    // Large stylesheet with complex selectors
+    Fixture {
+        name: "selectors",
+        html: include_str!("../fixtures/selectors.html"),
+    },
+];
+
+/// Viewport the fixtures are laid out in, in CSS pixels
+pub fn viewport() -> Size {
+    Size::new(1280., 800.)
+}
+
+pub fn parse(html: &str) -> NodePtr {
+    let document = NodePtr(TreeNode::new(Node::new(
+        NodeData::Document(Document::new()),
+    )));
+    document
+        .as_document()
+        .insert_user_agent_stylesheet(style::user_agent::user_agent_stylesheet());
+
+    let tokenizer = html::tokenizer::Tokenizer::new(html.chars());
+    html::tree_builder::TreeBuilder::new(tokenizer, document).run()
+}
+
+pub fn style(document: &NodePtr) {
+    render::calculate_styles(document.clone());
+}
+
+pub fn layout(document: &NodePtr) -> Option<LayoutBoxPtr> {
+    render::calculate_layout(document.clone(), &viewport())
+}
+
+pub fn new_painter() -> Painter<HeadlessGraphics> {
+    let mut painter = Painter::new(HeadlessGraphics::new());
+    painter.resize(viewport());
+    painter
+}
+
+/// Walk every box of the layout tree, reading its geometry like layout
+/// passes do. Returns the number of boxes.
+pub fn traverse(layout_box: &LayoutBoxPtr) -> usize {
+    let mut count = 1;
+    black_box(layout_box.margin_box_height());
+    layout_box.for_each_child(|child| count += traverse(&child));
+    count
+}
+
+/// A layout box allocated on its own, like boxes were before they were
+/// allocated in the arena of their tree
+#[derive(Debug)]
+pub struct RcLayoutBox(LayoutBox);
+
+impl TreeNodeHooks<RcLayoutBox> for RcLayoutBox {}
+
+/// Tree of the same shape as the layout tree with every box allocated on its
+/// own, to compare its traversal with the traversal of the arena
+pub fn rc_layout_tree(layout_box: &LayoutBoxPtr) -> TreeNode<RcLayoutBox> {
+    let node = TreeNode::new(RcLayoutBox(LayoutBox::new_anonymous(BoxData::block_box())));
+    layout_box.for_each_child(|child| node.append_child(rc_layout_tree(&child)));
+    node
+}
+
+/// Walk every box of a tree built by `rc_layout_tree`, like `traverse`
+pub fn traverse_rc(node: &TreeNode<RcLayoutBox>) -> usize {
+    let mut count = 1;
+    // The margin box height, which `LayoutBoxPtr` computes
+    let margin_box = node.0.box_model.borrow().margin_box();
+    black_box(node.0.content_size.borrow().height + margin_box.top + margin_box.bottom);
+    node.for_each_child(|child| count += traverse_rc(&child));
+    count
+}
+
+/// Build the paint requests of the layout tree and tessellate them
+pub fn paint(painter: &mut Painter<HeadlessGraphics>, layout_tree: &LayoutBoxPtr) {
+    painter.paint(layout_tree);
+    block_on(painter.output());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures_go_through_pipeline() {
+        let mut painter = new_painter();
+        for fixture in FIXTURES.iter() {
+            let document = parse(fixture.html);
+            style(&document);
+            let layout_tree =
+                layout(&document).unwrap_or_else(|| panic!("{} has no layout tree", fixture.name));
+            assert!(traverse(&layout_tree) > 1);
+
+            let rc_tree = rc_layout_tree(&layout_tree);
+            assert_eq!(traverse_rc(&rc_tree), traverse(&layout_tree));
+            rc_tree.teardown();
+            paint(&mut painter, &layout_tree);
+        }
+    }
+}
@@ -1,6 +1,14 @@
+use super::font_face_rule::FontFaceRule;
+use super::import_rule::ImportRule;
+use super::keyframes_rule::KeyframesRule;
+use super::media_rule::MediaRule;
 use super::style_rule::StyleRule;
 
 #[derive(Debug, PartialEq)]
 pub enum CSSRule {
     Style(StyleRule),
+    Media(MediaRule),
+    Import(ImportRule),
+    FontFace(FontFaceRule),
+    Keyframes(KeyframesRule),
 }
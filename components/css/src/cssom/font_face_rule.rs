@@ -0,0 +1,103 @@
+use crate::parser::structs::{ComponentValue, Declaration};
+use crate::tokenizer::token::Token;
+
+/// A `@font-face` rule
+/// https://www.w3.org/TR/css-fonts-4/#font-face-rule
+#[derive(Debug, PartialEq, Clone)]
+pub struct FontFaceRule {
+    /// Name of the font family defined by the rule
+    pub family: String,
+    /// Font files in order of preference
+    pub sources: Vec<FontFaceSource>,
+}
+
+/// A font file in the `src` descriptor of a `@font-face` rule
+#[derive(Debug, PartialEq, Clone)]
+pub struct FontFaceSource {
+    pub url: String,
+    /// Format hint given with `format()`, in lowercase
+    pub format: Option<String>,
+}
+
+impl FontFaceRule {
+    /// Create the rule from its descriptors. Rules without a family name or
+    /// without any font file are invalid. Local fonts and invalid sources
+    /// are skipped.
+    pub fn from_declarations(declarations: &[Declaration]) -> Option<Self> {
+        let descriptor = |name: &str| {
+            declarations
+                .iter()
+                .rev()
+                .find(|declaration| declaration.name.eq_ignore_ascii_case(name))
+        };
+
+        let family = parse_family(&non_whitespace(&descriptor("font-family")?.value))?;
+        let sources = descriptor("src")?
+            .value
+            .split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)))
+            .filter_map(|source| parse_source(&non_whitespace(source)))
+            .collect::<Vec<FontFaceSource>>();
+
+        if sources.is_empty() {
+            return None;
+        }
+
+        Some(Self { family, sources })
+    }
+}
+
+fn parse_family(values: &[ComponentValue]) -> Option<String> {
+    match values {
+        [ComponentValue::PerservedToken(Token::Str(name))] => Some(name.clone()),
+        [] => None,
+        idents => {
+            let mut names = Vec::new();
+            for ident in idents {
+                match ident {
+                    ComponentValue::PerservedToken(Token::Ident(name)) => names.push(name.clone()),
+                    _ => return None,
+                }
+            }
+            Some(names.join(" "))
+        }
+    }
+}
+
+fn parse_source(values: &[ComponentValue]) -> Option<FontFaceSource> {
+    let url = match values.first()? {
+        ComponentValue::PerservedToken(Token::Url(url)) => url.clone(),
+        ComponentValue::Function(function) if function.name.eq_ignore_ascii_case("url") => {
+            match non_whitespace(&function.value).as_slice() {
+                [ComponentValue::PerservedToken(Token::Str(url))] => url.clone(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let format = match values.get(1) {
+        Some(ComponentValue::Function(function))
+            if function.name.eq_ignore_ascii_case("format") =>
+        {
+            match non_whitespace(&function.value).as_slice() {
+                [ComponentValue::PerservedToken(Token::Str(format))]
+                | [ComponentValue::PerservedToken(Token::Ident(format))] => {
+                    Some(format.to_ascii_lowercase())
+                }
+                _ => return None,
+            }
+        }
+        Some(_) => return None,
+        None => None,
+    };
+
+    Some(FontFaceSource { url, format })
+}
+
+fn non_whitespace(values: &[ComponentValue]) -> Vec<ComponentValue> {
+    values
+        .iter()
+        .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,26 @@
+use super::stylesheet::StyleSheet;
+use crate::media_query::structs::{MediaEnvironment, MediaQueryList};
+
+/// An `@import` rule. The imported stylesheet is fetched after parsing and
+/// stays `None` if it can't be loaded.
+/// https://www.w3.org/TR/css-cascade-4/#at-import
+#[derive(Debug, PartialEq)]
+pub struct ImportRule {
+    pub href: String,
+    pub media: MediaQueryList,
+    pub stylesheet: Option<StyleSheet>,
+}
+
+impl ImportRule {
+    pub fn new(href: String, media: MediaQueryList) -> Self {
+        Self {
+            href,
+            media,
+            stylesheet: None,
+        }
+    }
+
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        self.media.evaluate(environment)
+    }
+}
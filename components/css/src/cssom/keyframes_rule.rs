@@ -0,0 +1,83 @@
+use crate::parser::structs::{ComponentValue, Declaration};
+use crate::tokenizer::token::Token;
+
+/// A `@keyframes` rule
+/// https://www.w3.org/TR/css-animations-1/#keyframes
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeyframesRule {
+    pub name: String,
+    /// Keyframes sorted by their offset. A keyframe with many selectors is
+    /// repeated at each of its offsets.
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Keyframe {
+    /// Progress of the animation the keyframe applies at, between 0 and 1
+    pub offset: f32,
+    pub declarations: Vec<Declaration>,
+}
+
+impl KeyframesRule {
+    /// Create the rule from its prelude and the keyframe blocks with their
+    /// selectors. Keyframes with an invalid selector are skipped.
+    pub fn new(
+        prelude: &[ComponentValue],
+        blocks: Vec<(Vec<ComponentValue>, Vec<Declaration>)>,
+    ) -> Option<Self> {
+        let name = match non_whitespace(prelude).as_slice() {
+            [ComponentValue::PerservedToken(Token::Ident(name))]
+                if !name.eq_ignore_ascii_case("none") =>
+            {
+                name.clone()
+            }
+            [ComponentValue::PerservedToken(Token::Str(name))] => name.clone(),
+            _ => return None,
+        };
+
+        let mut keyframes = Vec::new();
+        for (selectors, declarations) in blocks {
+            let offsets = selectors
+                .split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)))
+                .map(|selector| parse_offset(&non_whitespace(selector)))
+                .collect::<Option<Vec<f32>>>();
+            for offset in offsets.into_iter().flatten() {
+                keyframes.push(Keyframe {
+                    offset,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+        // The sort is stable, so later keyframes at the same offset stay
+        // after the earlier ones and take precedence
+        keyframes.sort_by(|a, b| a.offset.total_cmp(&b.offset));
+
+        Some(Self { name, keyframes })
+    }
+}
+
+fn parse_offset(values: &[ComponentValue]) -> Option<f32> {
+    match values {
+        [ComponentValue::PerservedToken(Token::Ident(keyword))] => {
+            match keyword.to_ascii_lowercase().as_str() {
+                "from" => Some(0.),
+                "to" => Some(1.),
+                _ => None,
+            }
+        }
+        [ComponentValue::PerservedToken(Token::Percentage(percentage))]
+            if (0. ..=100.).contains(percentage) =>
+        {
+            Some(percentage / 100.)
+        }
+        _ => None,
+    }
+}
+
+fn non_whitespace(values: &[ComponentValue]) -> Vec<ComponentValue> {
+    values
+        .iter()
+        .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,20 @@
+use super::css_rule_list::CSSRuleList;
+use crate::media_query::structs::{MediaEnvironment, MediaQueryList};
+
+/// A `@media` rule and the rules it applies conditionally
+/// https://www.w3.org/TR/css-conditional-3/#at-media
+#[derive(Debug, PartialEq)]
+pub struct MediaRule {
+    pub media: MediaQueryList,
+    pub css_rules: CSSRuleList,
+}
+
+impl MediaRule {
+    pub fn new(media: MediaQueryList, css_rules: CSSRuleList) -> Self {
+        Self { media, css_rules }
+    }
+
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        self.media.evaluate(environment)
+    }
+}
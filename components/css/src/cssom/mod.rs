@@ -1,4 +1,8 @@
 pub mod css_rule;
 pub mod css_rule_list;
+pub mod font_face_rule;
+pub mod import_rule;
+pub mod keyframes_rule;
+pub mod media_rule;
 pub mod style_rule;
 pub mod stylesheet;
@@ -1,4 +1,5 @@
 pub mod cssom;
+pub mod media_query;
 pub mod parser;
 pub mod selector;
 pub mod tokenizer;
@@ -0,0 +1,249 @@
+pub mod structs;
+
+use super::parser::structs::ComponentValue;
+use super::tokenizer::token::Token;
+use shared::data_stream::DataStream;
+use structs::*;
+
+/// Font size used to resolve font-relative lengths in media queries
+const INITIAL_FONT_SIZE: f32 = 16.;
+
+macro_rules! token_value {
+    ($token:pat) => {
+        ComponentValue::PerservedToken($token)
+    };
+}
+
+/// Parse the prelude of a `@media` rule.
+/// Queries that fail to parse are replaced with a query that never matches.
+/// https://www.w3.org/TR/mediaqueries-4/#error-handling
+pub fn parse_media_query_list(values: &[ComponentValue]) -> MediaQueryList {
+    let queries = values
+        .split(|value| matches!(value, token_value!(Token::Comma)))
+        .map(|query| parse_media_query(query).unwrap_or_else(MediaQuery::not_all))
+        .collect::<Vec<MediaQuery>>();
+
+    // A prelude with only whitespaces is an empty list
+    if queries.len() == 1 && values.iter().all(is_whitespace) {
+        return MediaQueryList::default();
+    }
+
+    MediaQueryList(queries)
+}
+
+fn parse_media_query(values: &[ComponentValue]) -> Option<MediaQuery> {
+    let mut data_stream = DataStream::new(
+        values
+            .iter()
+            .filter(|value| !is_whitespace(value))
+            .cloned()
+            .collect(),
+    );
+
+    let mut query = MediaQuery {
+        negated: false,
+        media_type: MediaType::All,
+        features: Vec::new(),
+    };
+
+    match data_stream.peek_clone()? {
+        token_value!(Token::Ident(ident)) => {
+            data_stream.next();
+            let media_type = if ident.eq_ignore_ascii_case("not") {
+                query.negated = true;
+                expect_ident(&mut data_stream)?
+            } else if ident.eq_ignore_ascii_case("only") {
+                expect_ident(&mut data_stream)?
+            } else {
+                ident
+            };
+            query.media_type = parse_media_type(&media_type);
+
+            if data_stream.is_eos() {
+                return Some(query);
+            }
+            if !expect_ident(&mut data_stream)?.eq_ignore_ascii_case("and") {
+                return None;
+            }
+        }
+        ComponentValue::SimpleBlock(_) => {}
+        _ => return None,
+    }
+
+    loop {
+        match data_stream.next()? {
+            ComponentValue::SimpleBlock(block) if block.token == Token::ParentheseOpen => {
+                query.features.push(parse_media_feature(&block.value)?);
+            }
+            _ => return None,
+        }
+
+        if data_stream.is_eos() {
+            return Some(query);
+        }
+        if !expect_ident(&mut data_stream)?.eq_ignore_ascii_case("and") {
+            return None;
+        }
+    }
+}
+
+fn parse_media_type(media_type: &str) -> MediaType {
+    match media_type {
+        t if t.eq_ignore_ascii_case("all") => MediaType::All,
+        t if t.eq_ignore_ascii_case("screen") => MediaType::Screen,
+        t if t.eq_ignore_ascii_case("print") => MediaType::Print,
+        _ => MediaType::Unknown,
+    }
+}
+
+fn parse_media_feature(values: &[ComponentValue]) -> Option<MediaFeature> {
+    let values = values
+        .iter()
+        .filter(|value| !is_whitespace(value))
+        .collect::<Vec<&ComponentValue>>();
+
+    let (name, value) = match values.as_slice() {
+        [token_value!(Token::Ident(name)), token_value!(Token::Colon), token_value!(value)] => {
+            (name.to_ascii_lowercase(), value)
+        }
+        _ => return None,
+    };
+
+    match name.as_str() {
+        "width" => Some(MediaFeature::Width(parse_length(value)?)),
+        "min-width" => Some(MediaFeature::MinWidth(parse_length(value)?)),
+        "max-width" => Some(MediaFeature::MaxWidth(parse_length(value)?)),
+        "height" => Some(MediaFeature::Height(parse_length(value)?)),
+        "min-height" => Some(MediaFeature::MinHeight(parse_length(value)?)),
+        "max-height" => Some(MediaFeature::MaxHeight(parse_length(value)?)),
+        "orientation" => match value {
+            Token::Ident(v) if v.eq_ignore_ascii_case("portrait") => {
+                Some(MediaFeature::Orientation(Orientation::Portrait))
+            }
+            Token::Ident(v) if v.eq_ignore_ascii_case("landscape") => {
+                Some(MediaFeature::Orientation(Orientation::Landscape))
+            }
+            _ => None,
+        },
+        "prefers-color-scheme" => match value {
+            Token::Ident(v) if v.eq_ignore_ascii_case("light") => {
+                Some(MediaFeature::PrefersColorScheme(ColorScheme::Light))
+            }
+            Token::Ident(v) if v.eq_ignore_ascii_case("dark") => {
+                Some(MediaFeature::PrefersColorScheme(ColorScheme::Dark))
+            }
+            _ => None,
+        },
+        "prefers-reduced-motion" => match value {
+            Token::Ident(v) if v.eq_ignore_ascii_case("no-preference") => Some(
+                MediaFeature::PrefersReducedMotion(ReducedMotion::NoPreference),
+            ),
+            Token::Ident(v) if v.eq_ignore_ascii_case("reduce") => {
+                Some(MediaFeature::PrefersReducedMotion(ReducedMotion::Reduce))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Parse a length in media queries to CSS pixels
+fn parse_length(token: &Token) -> Option<f32> {
+    match token {
+        Token::Number { value, .. } if *value == 0. => Some(0.),
+        Token::Dimension { value, unit, .. } => match unit.to_ascii_lowercase().as_str() {
+            "px" => Some(*value),
+            "em" | "rem" => Some(*value * INITIAL_FONT_SIZE),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn expect_ident(data_stream: &mut DataStream<ComponentValue>) -> Option<String> {
+    match data_stream.next()? {
+        token_value!(Token::Ident(ident)) => Some(ident.clone()),
+        _ => None,
+    }
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+    matches!(value, token_value!(Token::Whitespace))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::tokenizer::Tokenizer;
+
+    fn parse(query: &str) -> MediaQueryList {
+        let tokenizer = Tokenizer::new(query.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        parse_media_query_list(&parser.parse_a_list_of_component_values())
+    }
+
+    #[test]
+    fn parse_media_queries() {
+        assert_eq!(
+            parse("screen and (min-width: 600px), not print"),
+            MediaQueryList(vec![
+                MediaQuery {
+                    negated: false,
+                    media_type: MediaType::Screen,
+                    features: vec![MediaFeature::MinWidth(600.)],
+                },
+                MediaQuery {
+                    negated: true,
+                    media_type: MediaType::Print,
+                    features: Vec::new(),
+                },
+            ])
+        );
+        assert_eq!(
+            parse("(orientation: landscape) and (max-width: 40em)"),
+            MediaQueryList(vec![MediaQuery {
+                negated: false,
+                media_type: MediaType::All,
+                features: vec![
+                    MediaFeature::Orientation(Orientation::Landscape),
+                    MediaFeature::MaxWidth(640.)
+                ],
+            }])
+        );
+        assert_eq!(parse(""), MediaQueryList::default());
+        assert_eq!(
+            parse("(min-width: 10%)"),
+            MediaQueryList(vec![MediaQuery::not_all()])
+        );
+    }
+
+    #[test]
+    fn evaluate_media_queries() {
+        let environment = MediaEnvironment::new(800., 600.);
+
+        assert!(parse("screen and (min-width: 600px)").evaluate(&environment));
+        assert!(!parse("(max-width: 600px)").evaluate(&environment));
+        assert!(parse("print, (orientation: landscape)").evaluate(&environment));
+        assert!(!parse("(prefers-color-scheme: dark)").evaluate(&environment));
+        assert!(parse("not print").evaluate(&environment));
+        assert!(!parse("(unknown-feature: 1)").evaluate(&environment));
+
+        let print = MediaEnvironment {
+            media_type: MediaType::Print,
+            ..environment
+        };
+        assert!(parse("print and (min-width: 600px)").evaluate(&print));
+        assert!(!parse("screen").evaluate(&print));
+        assert!(parse("all").evaluate(&print));
+
+        let preferences = MediaEnvironment {
+            color_scheme: ColorScheme::Dark,
+            reduced_motion: ReducedMotion::Reduce,
+            ..MediaEnvironment::new(800., 600.)
+        };
+        assert!(parse("(prefers-color-scheme: dark)").evaluate(&preferences));
+        assert!(parse("(prefers-reduced-motion: reduce)").evaluate(&preferences));
+        assert!(!parse("(prefers-reduced-motion: no-preference)").evaluate(&preferences));
+    }
+}
@@ -0,0 +1,132 @@
+/// A comma-separated list of media queries. The list matches if any of its
+/// queries matches. An empty list matches every environment.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct MediaQueryList(pub Vec<MediaQuery>);
+
+/// A single media query such as `screen and (min-width: 600px)`
+#[derive(Debug, PartialEq, Clone)]
+pub struct MediaQuery {
+    pub negated: bool,
+    pub media_type: MediaType,
+    pub features: Vec<MediaFeature>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum MediaType {
+    All,
+    Screen,
+    Print,
+    /// Unknown media types and queries that failed to parse. They never match.
+    Unknown,
+}
+
+/// Media features with their values in CSS pixels
+#[derive(Debug, PartialEq, Clone)]
+pub enum MediaFeature {
+    Width(f32),
+    MinWidth(f32),
+    MaxWidth(f32),
+    Height(f32),
+    MinHeight(f32),
+    MaxHeight(f32),
+    Orientation(Orientation),
+    PrefersColorScheme(ColorScheme),
+    PrefersReducedMotion(ReducedMotion),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// Whether the user asked for less motion in the interface
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReducedMotion {
+    NoPreference,
+    Reduce,
+}
+
+/// The environment media queries are evaluated against
+#[derive(Debug, PartialEq, Clone)]
+pub struct MediaEnvironment {
+    /// Width of the viewport in CSS pixels
+    pub viewport_width: f32,
+    /// Height of the viewport in CSS pixels
+    pub viewport_height: f32,
+    pub color_scheme: ColorScheme,
+    pub reduced_motion: ReducedMotion,
+    /// `Screen` or `Print`
+    pub media_type: MediaType,
+}
+
+impl MediaQueryList {
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        self.0.is_empty() || self.0.iter().any(|query| query.evaluate(environment))
+    }
+}
+
+impl MediaQuery {
+    /// A query that never matches, used in place of invalid queries
+    pub fn not_all() -> Self {
+        Self {
+            negated: false,
+            media_type: MediaType::Unknown,
+            features: Vec::new(),
+        }
+    }
+
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        let is_type_match = match self.media_type {
+            MediaType::All => true,
+            MediaType::Screen | MediaType::Print => self.media_type == environment.media_type,
+            MediaType::Unknown => false,
+        };
+
+        let is_match = is_type_match
+            && self
+                .features
+                .iter()
+                .all(|feature| feature.evaluate(environment));
+
+        is_match != self.negated
+    }
+}
+
+impl MediaFeature {
+    pub fn evaluate(&self, environment: &MediaEnvironment) -> bool {
+        let width = environment.viewport_width;
+        let height = environment.viewport_height;
+
+        match self {
+            MediaFeature::Width(value) => width == *value,
+            MediaFeature::MinWidth(value) => width >= *value,
+            MediaFeature::MaxWidth(value) => width <= *value,
+            MediaFeature::Height(value) => height == *value,
+            MediaFeature::MinHeight(value) => height >= *value,
+            MediaFeature::MaxHeight(value) => height <= *value,
+            MediaFeature::Orientation(Orientation::Portrait) => height >= width,
+            MediaFeature::Orientation(Orientation::Landscape) => width > height,
+            MediaFeature::PrefersColorScheme(scheme) => environment.color_scheme == *scheme,
+            MediaFeature::PrefersReducedMotion(motion) => environment.reduced_motion == *motion,
+        }
+    }
+}
+
+impl MediaEnvironment {
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Self {
+            viewport_width,
+            viewport_height,
+            color_scheme: ColorScheme::Light,
+            reduced_motion: ReducedMotion::NoPreference,
+            media_type: MediaType::Screen,
+        }
+    }
+}
@@ -1,8 +1,14 @@
 pub mod structs;
 
 use super::cssom::css_rule::CSSRule;
+use super::cssom::css_rule_list::CSSRuleList;
+use super::cssom::font_face_rule::FontFaceRule;
+use super::cssom::import_rule::ImportRule;
+use super::cssom::keyframes_rule::KeyframesRule;
+use super::cssom::media_rule::MediaRule;
 use super::cssom::style_rule::StyleRule;
 use super::cssom::stylesheet::StyleSheet;
+use super::media_query::parse_media_query_list;
 use super::selector::parse_selectors;
 use super::tokenizer::token::Token;
 use shared::data_stream::DataStream;
@@ -149,20 +155,16 @@ impl Parser<Token> {
                     result.push(DeclarationOrAtRule::AtRule(rule));
                 }
                 Token::Ident(_) => {
-                    let mut tmp = vec![self.current_token.clone().unwrap()];
+                    let mut tmp = vec![ComponentValue::PerservedToken(
+                        self.current_token.clone().unwrap(),
+                    )];
                     loop {
                         match self.peek_next_token() {
                             Token::Semicolon | Token::EOF => break,
-                            _ => {
-                                if let ComponentValue::PerservedToken(t) =
-                                    self.consume_a_component_value()
-                                {
-                                    tmp.push(t);
-                                }
-                            }
+                            _ => tmp.push(self.consume_a_component_value()),
                         }
                     }
-                    let mut parser = Parser::<Token>::new(DataStream::new(tmp));
+                    let mut parser = Parser::<ComponentValue>::new(DataStream::new(tmp));
                     if let Some(declaration) = parser.consume_a_declaration() {
                         result.push(DeclarationOrAtRule::Declaration(declaration));
                     }
@@ -329,22 +331,7 @@ impl Parser<Token> {
             declaration.append_value(self.consume_a_component_value());
         }
 
-        let last_two_tokens = declaration.last_values(2);
-
-        if last_two_tokens.len() == 2 {
-            if let ComponentValue::PerservedToken(Token::Delim('!')) = last_two_tokens[0] {
-                if let ComponentValue::PerservedToken(Token::Ident(data)) = last_two_tokens[1] {
-                    if data.eq_ignore_ascii_case("important") {
-                        declaration.pop_last(2);
-                        declaration.important();
-                    }
-                }
-            }
-        }
-
-        while let Some(Token::Whitespace) = declaration.last_token() {
-            declaration.pop_last(1);
-        }
+        declaration.consume_important();
 
         return Some(declaration);
     }
@@ -366,35 +353,8 @@ impl Parser<Token> {
     pub fn parse_a_css_stylesheet(&mut self) -> StyleSheet {
         let mut stylesheet = StyleSheet::new();
         let rules = self.parse_a_stylesheet();
-        for rule in rules {
-            if let Rule::QualifiedRule(rule) = rule {
-                let selectors = parse_selectors(&rule.prelude);
-                if selectors.len() == 0 {
-                    // invalid rule
-                    continue;
-                }
-                let content = if let Some(block) = rule.block {
-                    let mut parser =
-                        Parser::<ComponentValue>::new(DataStream::new(block.value.clone()));
-
-                    let declarations = parser.parse_a_list_of_declarations();
-
-                    // take only declaration
-                    declarations
-                        .into_iter()
-                        .filter_map(|declaration| match declaration {
-                            DeclarationOrAtRule::Declaration(d) => Some(d),
-                            _ => None,
-                        })
-                        .collect()
-                } else {
-                    Vec::new()
-                };
-                let style_rule = StyleRule::new(selectors, content);
-                stylesheet.append_rule(CSSRule::Style(style_rule));
-            } else {
-                continue;
-            }
+        for rule in create_css_rules(rules, true) {
+            stylesheet.append_rule(rule);
         }
         stylesheet
     }
@@ -492,6 +452,163 @@ impl Parser<Token> {
     }
 }
 
+/// Convert the rules of a stylesheet or of a block into CSSOM rules,
+/// dropping invalid and unsupported rules. `@import` rules are only valid
+/// at the top of a stylesheet, before any other rule.
+fn create_css_rules(rules: ListOfRules, allow_imports: bool) -> Vec<CSSRule> {
+    let mut allow_imports = allow_imports;
+    let mut css_rules = Vec::new();
+
+    for rule in rules {
+        let css_rule = match rule {
+            Rule::AtRule(rule) if rule.name.eq_ignore_ascii_case("import") => {
+                if !allow_imports {
+                    emit_error!("@import rule must precede all other rules");
+                    continue;
+                }
+                create_import_rule(rule).map(CSSRule::Import)
+            }
+            Rule::AtRule(rule) if rule.name.eq_ignore_ascii_case("charset") => continue,
+            Rule::QualifiedRule(rule) => create_style_rule(rule).map(CSSRule::Style),
+            Rule::AtRule(rule) if rule.name.eq_ignore_ascii_case("media") => {
+                create_media_rule(rule).map(CSSRule::Media)
+            }
+            Rule::AtRule(rule) if rule.name.eq_ignore_ascii_case("font-face") => {
+                create_font_face_rule(rule).map(CSSRule::FontFace)
+            }
+            Rule::AtRule(rule) if rule.name.eq_ignore_ascii_case("keyframes") => {
+                create_keyframes_rule(rule).map(CSSRule::Keyframes)
+            }
+            Rule::AtRule(_) => None,
+        };
+
+        if let Some(css_rule) = css_rule {
+            if !matches!(css_rule, CSSRule::Import(_)) {
+                allow_imports = false;
+            }
+            css_rules.push(css_rule);
+        }
+    }
+
+    css_rules
+}
+
+fn create_style_rule(rule: QualifiedRule) -> Option<StyleRule> {
+    let selectors = parse_selectors(&rule.prelude);
+    if selectors.is_empty() {
+        // invalid rule
+        return None;
+    }
+    let content = if let Some(block) = rule.block {
+        let mut parser = Parser::<ComponentValue>::new(DataStream::new(block.value.clone()));
+
+        let declarations = parser.parse_a_list_of_declarations();
+
+        // take only declaration
+        declarations
+            .into_iter()
+            .filter_map(|declaration| match declaration {
+                DeclarationOrAtRule::Declaration(d) => Some(d),
+                _ => None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Some(StyleRule::new(selectors, content))
+}
+
+fn create_import_rule(rule: AtRule) -> Option<ImportRule> {
+    if rule.block.is_some() {
+        emit_error!("Unexpected block in @import rule");
+        return None;
+    }
+
+    let mut prelude = rule
+        .prelude
+        .iter()
+        .skip_while(|value| matches!(value, ComponentValue::PerservedToken(Token::Whitespace)));
+
+    let href = match prelude.next()? {
+        ComponentValue::PerservedToken(Token::Url(url))
+        | ComponentValue::PerservedToken(Token::Str(url)) => url.clone(),
+        ComponentValue::Function(function) if function.name.eq_ignore_ascii_case("url") => {
+            match function.value.as_slice() {
+                [ComponentValue::PerservedToken(Token::Str(url))] => url.clone(),
+                _ => return None,
+            }
+        }
+        _ => {
+            emit_error!("Expected a URL in @import rule");
+            return None;
+        }
+    };
+
+    let media = parse_media_query_list(&prelude.cloned().collect::<Vec<ComponentValue>>());
+
+    Some(ImportRule::new(href, media))
+}
+
+fn create_font_face_rule(rule: AtRule) -> Option<FontFaceRule> {
+    let block = rule.block?;
+    let mut parser = Parser::<ComponentValue>::new(DataStream::new(block.value));
+    let declarations = parser
+        .parse_a_list_of_declarations()
+        .into_iter()
+        .filter_map(|declaration| match declaration {
+            DeclarationOrAtRule::Declaration(d) => Some(d),
+            _ => None,
+        })
+        .collect::<Vec<Declaration>>();
+
+    FontFaceRule::from_declarations(&declarations)
+}
+
+fn create_keyframes_rule(rule: AtRule) -> Option<KeyframesRule> {
+    let block = rule.block?;
+    let mut parser = Parser::<ComponentValue>::new(DataStream::new(block.value));
+    let blocks = parser
+        .parse_a_list_of_rules()
+        .into_iter()
+        .filter_map(|rule| match rule {
+            Rule::QualifiedRule(QualifiedRule {
+                prelude,
+                block: Some(block),
+            }) => {
+                let mut parser = Parser::<ComponentValue>::new(DataStream::new(block.value));
+                let declarations = parser
+                    .parse_a_list_of_declarations()
+                    .into_iter()
+                    .filter_map(|declaration| match declaration {
+                        DeclarationOrAtRule::Declaration(d) => Some(d),
+                        _ => None,
+                    })
+                    .collect::<Vec<Declaration>>();
+                Some((prelude, declarations))
+            }
+            _ => None,
+        })
+        .collect();
+
+    KeyframesRule::new(&rule.prelude, blocks)
+}
+
+fn create_media_rule(rule: AtRule) -> Option<MediaRule> {
+    let block = match rule.block {
+        Some(block) => block,
+        None => {
+            emit_error!("Expected a block in @media rule");
+            return None;
+        }
+    };
+    let media = parse_media_query_list(&rule.prelude);
+
+    let mut parser = Parser::<ComponentValue>::new(DataStream::new(block.value));
+    let css_rules = create_css_rules(parser.parse_a_list_of_rules(), false);
+
+    Some(MediaRule::new(media, CSSRuleList(css_rules)))
+}
+
 impl Parser<ComponentValue> {
     pub fn new(tokens: DataStream<ComponentValue>) -> Self {
         Self {
@@ -534,6 +651,49 @@ impl Parser<ComponentValue> {
         self.consume_a_list_of_declarations()
     }
 
+    fn parse_a_list_of_rules(&mut self) -> ListOfRules {
+        self.consume_a_list_of_rules()
+    }
+
+    fn consume_a_list_of_rules(&mut self) -> ListOfRules {
+        let mut rules = Vec::new();
+        loop {
+            match self.consume_next_token() {
+                ComponentValue::PerservedToken(Token::Whitespace) => continue,
+                ComponentValue::PerservedToken(Token::EOF) => return rules,
+                ComponentValue::PerservedToken(Token::AtKeyword(_)) => {
+                    self.reconsume();
+                    let at_rule = self.consume_an_at_rule();
+                    rules.push(Rule::AtRule(at_rule));
+                }
+                _ => {
+                    self.reconsume();
+                    if let Some(rule) = self.consume_a_qualified_rule() {
+                        rules.push(Rule::QualifiedRule(rule));
+                    }
+                }
+            }
+        }
+    }
+
+    fn consume_a_qualified_rule(&mut self) -> Option<QualifiedRule> {
+        let mut qualified_rule = QualifiedRule::new();
+
+        loop {
+            match self.consume_next_token() {
+                ComponentValue::PerservedToken(Token::EOF) => {
+                    emit_error!("Unexpected EOF while consuming a qualified rule");
+                    return None;
+                }
+                ComponentValue::SimpleBlock(block) if block.token == Token::BraceOpen => {
+                    qualified_rule.set_block(block);
+                    return Some(qualified_rule);
+                }
+                value => qualified_rule.append_prelude(value),
+            }
+        }
+    }
+
     fn consume_a_component_value(&mut self) -> ComponentValue {
         self.consume_next_token()
     }
@@ -558,6 +718,10 @@ impl Parser<ComponentValue> {
                     emit_error!("Unexpected EOF while consuming an at-rule");
                     return at_rule;
                 }
+                ComponentValue::SimpleBlock(block) if block.token == Token::BraceOpen => {
+                    at_rule.set_block(block);
+                    return at_rule;
+                }
                 // TODO: How is a simple block a token?
                 _ => {
                     self.reconsume();
@@ -647,22 +811,7 @@ impl Parser<ComponentValue> {
             declaration.append_value(self.consume_a_component_value());
         }
 
-        let last_two_tokens = declaration.last_values(2);
-
-        if last_two_tokens.len() == 2 {
-            if let ComponentValue::PerservedToken(Token::Delim('!')) = last_two_tokens[0] {
-                if let ComponentValue::PerservedToken(Token::Ident(data)) = last_two_tokens[1] {
-                    if data.eq_ignore_ascii_case("important") {
-                        declaration.pop_last(2);
-                        declaration.important();
-                    }
-                }
-            }
-        }
-
-        while let Some(Token::Whitespace) = declaration.last_token() {
-            declaration.pop_last(1);
-        }
+        declaration.consume_important();
 
         return Some(declaration);
     }
@@ -680,6 +829,7 @@ mod tests {
     use super::*;
     use crate::cssom::css_rule::CSSRule;
     use crate::cssom::css_rule_list::CSSRuleList;
+    use crate::cssom::font_face_rule::FontFaceSource;
     use crate::cssom::style_rule::StyleRule;
     use crate::selector::structs::*;
     use crate::tokenizer::token::HashType;
@@ -826,6 +976,143 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_important_with_whitespace() {
+        let css = "p { color: black ! IMPORTANT ; margin: 0 !important } a { color: red! }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        let declarations = |index: usize| match &stylesheet[index] {
+            CSSRule::Style(style) => style.declarations.clone(),
+            _ => unreachable!(),
+        };
+        let black = ComponentValue::PerservedToken(Token::Ident("black".to_string()));
+        assert!(declarations(0)
+            .iter()
+            .all(|declaration| declaration.important));
+        assert_eq!(declarations(0)[0].value, vec![black]);
+        assert_eq!(declarations(0)[1].value.len(), 1);
+        assert!(!declarations(1)[0].important);
+    }
+
+    #[test]
+    fn parse_media_rule() {
+        let css = "@media (max-width: 600px) { p { color: red; } } @font-feature-values Font { }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        assert_eq!(stylesheet.len(), 1);
+        match &stylesheet[0] {
+            CSSRule::Media(media_rule) => {
+                assert_eq!(media_rule.css_rules.len(), 1);
+                assert!(matches!(media_rule.css_rules[0], CSSRule::Style(_)));
+                assert_eq!(media_rule.media.0.len(), 1);
+            }
+            rule => panic!("Expected a media rule, got {:?}", rule),
+        }
+    }
+
+    #[test]
+    fn parse_import_rules() {
+        let css = r#"
+            @charset "utf-8";
+            @import url("base.css");
+            @import "print.css" print;
+            p { color: red; }
+            @import url(late.css);
+        "#;
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        assert_eq!(stylesheet.len(), 3);
+        match (&stylesheet[0], &stylesheet[1]) {
+            (CSSRule::Import(base), CSSRule::Import(print)) => {
+                assert_eq!(base.href, "base.css");
+                assert!(base.media.0.is_empty());
+                assert_eq!(print.href, "print.css");
+                assert_eq!(print.media.0.len(), 1);
+            }
+            rules => panic!("Expected import rules, got {:?}", rules),
+        }
+        assert!(matches!(stylesheet[2], CSSRule::Style(_)));
+    }
+
+    #[test]
+    fn parse_font_face_rule() {
+        let css = r#"
+            @font-face {
+                font-family: "Open Sans";
+                src: local(Open Sans), url("/fonts/OpenSans.woff2") format("woff2"),
+                     url(/fonts/OpenSans.ttf);
+            }
+        "#;
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        match &stylesheet[0] {
+            CSSRule::FontFace(font_face) => {
+                assert_eq!(font_face.family, "Open Sans");
+                assert_eq!(
+                    font_face.sources,
+                    vec![
+                        FontFaceSource {
+                            url: "/fonts/OpenSans.woff2".to_string(),
+                            format: Some("woff2".to_string()),
+                        },
+                        FontFaceSource {
+                            url: "/fonts/OpenSans.ttf".to_string(),
+                            format: None,
+                        },
+                    ]
+                );
+            }
+            rule => panic!("Expected a font-face rule, got {:?}", rule),
+        }
+    }
+
+    #[test]
+    fn parse_keyframes_rule() {
+        let css = "@keyframes slide { from { left: 0px } 50%, to { left: 100px } 120% { } }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        match &stylesheet[0] {
+            CSSRule::Keyframes(keyframes) => {
+                assert_eq!(keyframes.name, "slide");
+                let offsets = keyframes
+                    .keyframes
+                    .iter()
+                    .map(|keyframe| keyframe.offset)
+                    .collect::<Vec<f32>>();
+                // The keyframe with an offset out of range is dropped
+                assert_eq!(offsets, vec![0., 0.5, 1.]);
+                assert_eq!(keyframes.keyframes[1].declarations[0].name, "left");
+            }
+            rule => panic!("Expected a keyframes rule, got {:?}", rule),
+        }
+    }
+
+    #[test]
+    fn parse_trailing_function() {
+        let css = "p { border: 1px solid rgb(0 0 0) ; }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let stylesheet = parser.parse_a_css_stylesheet();
+        match &stylesheet[0] {
+            CSSRule::Style(rule) => {
+                let value = &rule.declarations[0].value;
+                assert_eq!(value.len(), 5);
+                assert!(matches!(value[4], ComponentValue::Function(_)));
+            }
+            rule => panic!("Expected a style rule, got {:?}", rule),
+        }
+    }
+
     #[test]
     fn parse_function() {
         let css = "#elementId { color: rgba(0 0 0 0); }";
@@ -874,4 +1161,93 @@ mod tests {
             ))])
         );
     }
+
+    #[test]
+    fn parse_list_of_declarations_with_functions() {
+        let css = "color: rgb(0, 0, 0); ; 12px; width: calc(1px)";
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        let declarations = parser
+            .parse_a_list_of_declarations()
+            .into_iter()
+            .filter_map(|declaration| match declaration {
+                DeclarationOrAtRule::Declaration(declaration) => Some(declaration),
+                _ => None,
+            })
+            .collect::<Vec<Declaration>>();
+
+        assert_eq!(declarations.len(), 2);
+        assert!(matches!(
+            declarations[0].value.as_slice(),
+            [ComponentValue::Function(function)] if function.name == "rgb"
+        ));
+        assert_eq!(declarations[1].name, "width");
+    }
+
+    /// Names of the declarations of each style rule of the stylesheet
+    fn declaration_names(css: &str) -> Vec<Vec<String>> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        parser
+            .parse_a_css_stylesheet()
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(
+                    style
+                        .declarations
+                        .iter()
+                        .map(|declaration| declaration.name.clone())
+                        .collect(),
+                ),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recover_from_invalid_declarations() {
+        assert_eq!(
+            declaration_names("p { color: red; ; color green; 12px; width: 1px }"),
+            vec![vec!["color", "width"]]
+        );
+        // Blocks and functions are skipped as a whole, even with semicolons
+        assert_eq!(
+            declaration_names("p { a: {x; y} b; c: f(;) ; [d;] e: 1; g: 2 } div { f: 2 }"),
+            vec![vec!["a", "c", "g"], vec!["f"]]
+        );
+        assert_eq!(
+            declaration_names("p { @unknown x { y; } color: red; @other; width: 0 }"),
+            vec![vec!["color", "width"]]
+        );
+    }
+
+    #[test]
+    fn recover_from_invalid_rules() {
+        assert_eq!(
+            declaration_names("@unknown foo { a { b: c } } p { color: red }"),
+            vec![vec!["color"]]
+        );
+        assert_eq!(
+            declaration_names("@unknown foo; p { color: red }"),
+            vec![vec!["color"]]
+        );
+        assert_eq!(
+            declaration_names("@media screen { p { color: red } @bad { } } a { b: c }"),
+            vec![vec!["b"]]
+        );
+        // A stray closing brace is part of the prelude of the next rule
+        assert_eq!(
+            declaration_names("p { color: red } } div { color: blue } a { b: c }"),
+            vec![vec!["color"], vec!["b"]]
+        );
+        // An unclosed block ends at the end of the stylesheet
+        assert_eq!(
+            declaration_names("p { color: red } div { color: blue; width: f("),
+            vec![vec!["color"], vec!["color", "width"]]
+        );
+        assert_eq!(
+            declaration_names("<!-- p { color: red } -->"),
+            vec![vec!["color"]]
+        );
+    }
 }
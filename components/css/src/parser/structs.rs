@@ -131,13 +131,12 @@ impl Declaration {
         self.value.iter().rev().take(len).rev().collect()
     }
 
+    /// The last value of the declaration if it is a preserved token
     pub fn last_token(&self) -> Option<&Token> {
-        for value in self.value.iter().rev() {
-            if let ComponentValue::PerservedToken(token) = value {
-                return Some(token);
-            }
+        match self.value.last() {
+            Some(ComponentValue::PerservedToken(token)) => Some(token),
+            _ => None,
         }
-        return None;
     }
 
     pub fn pop_last(&mut self, len: usize) {
@@ -154,6 +153,38 @@ impl Declaration {
         self.important = true;
     }
 
+    /// Remove the trailing whitespace of the value, and mark the declaration
+    /// as important if the last two non-whitespace tokens are `!` and
+    /// `important`
+    /// https://www.w3.org/TR/css-syntax-3/#consume-declaration
+    pub fn consume_important(&mut self) {
+        let is_whitespace =
+            |value: &ComponentValue| *value == ComponentValue::PerservedToken(Token::Whitespace);
+        let trim_whitespace = |value: &mut Vec<ComponentValue>| {
+            while value.last().is_some_and(is_whitespace) {
+                value.pop();
+            }
+        };
+
+        trim_whitespace(&mut self.value);
+        let mut tokens = self
+            .value
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, value)| !is_whitespace(value));
+        let bang = match (tokens.next(), tokens.next()) {
+            (
+                Some((_, ComponentValue::PerservedToken(Token::Ident(ident)))),
+                Some((index, ComponentValue::PerservedToken(Token::Delim('!')))),
+            ) if ident.eq_ignore_ascii_case("important") => index,
+            _ => return,
+        };
+        self.value.truncate(bang);
+        trim_whitespace(&mut self.value);
+        self.important();
+    }
+
     pub fn tokens(&self) -> Vec<Token> {
         self.value
             .clone()
@@ -21,161 +21,74 @@ pub fn parse_selector_str(selector: &str) -> Option<Selector> {
     parse_selector(&mut data_stream)
 }
 
+/// Parse a selector list. The list is invalid as a whole, and so is the rule
+/// using it, if one of its selectors is invalid or unsupported.
+/// https://www.w3.org/TR/selectors-4/#invalid
 pub fn parse_selectors(values: &Vec<ComponentValue>) -> Vec<Selector> {
-    let mut selectors = Vec::new();
-
-    let mut data_stream = DataStream::new(values.clone());
-
-    loop {
-        if let Some(selector) = parse_selector(&mut data_stream) {
-            selectors.push(selector);
-            loop {
-                // consume all white space
-                if let Some(token_value!(Token::Whitespace)) = data_stream.peek() {
-                    data_stream.next();
-                } else {
-                    break;
-                }
-            }
-            if let Some(token_value!(Token::Comma)) = data_stream.next() {
-                // there is a comma, let the parsing continue
-                continue;
+    values
+        .split(|value| matches!(value, token_value!(Token::Comma)))
+        .map(|values| {
+            let is_whitespace =
+                |value: &ComponentValue| matches!(value, token_value!(Token::Whitespace));
+            let start = values.iter().position(|value| !is_whitespace(value))?;
+            let end = values.iter().rposition(|value| !is_whitespace(value))?;
+            let mut data_stream = DataStream::new(values[start..=end].to_vec());
+            let selector = parse_selector(&mut data_stream)?;
+            if !data_stream.is_eos() {
+                return None;
             }
-            // no comma? the sequence ends here
-            break;
-        } else {
-            data_stream.next();
-        }
-
-        if data_stream.is_eos() {
-            break;
-        }
-    }
-    return selectors;
+            Some(selector)
+        })
+        .collect::<Option<Vec<Selector>>>()
+        .unwrap_or_default()
 }
 
+/// Parse a complex selector, stopping at the first value that can't be part
+/// of it
 pub fn parse_selector(data_stream: &mut DataStream<ComponentValue>) -> Option<Selector> {
     let mut selector_seqs: SelectorData = Vec::new();
     loop {
-        if let Some(selector_seq) = parse_simple_selector_seq(data_stream) {
-            if let Some(combinator) = parse_combinator(data_stream) {
-                selector_seqs.push((selector_seq, Some(combinator)));
-                continue;
-            }
-            selector_seqs.push((selector_seq, None));
-            break;
-        } else {
-            data_stream.next();
-        }
-
-        if data_stream.is_eos() {
-            break;
+        let selector_seq = parse_simple_selector_seq(data_stream)?;
+        if let Some(combinator) = parse_combinator(data_stream) {
+            selector_seqs.push((selector_seq, Some(combinator)));
+            continue;
         }
-    }
-
-    match selector_seqs.len() {
-        0 => None,
-        _ => Some(Selector::new(selector_seqs)),
+        selector_seqs.push((selector_seq, None));
+        return Some(Selector::new(selector_seqs));
     }
 }
 
+/// Parse the combinator between two compound selectors, with the whitespace
+/// around it. Whitespace alone is the descendant combinator.
 pub fn parse_combinator(data_stream: &mut DataStream<ComponentValue>) -> Option<Combinator> {
-    let next_values = data_stream.peek_next(4);
-
-    if next_values.len() == 4 {
-        return match (
-            next_values[0],
-            next_values[1],
-            next_values[2],
-            next_values[3],
-        ) {
-            // With space between combinator
-            (
-                token_value!(Token::Whitespace),
-                token_value!(Token::Delim('+')),
-                token_value!(Token::Whitespace),
-                _,
-            ) => {
-                data_stream.next();
-                data_stream.next();
-                data_stream.next();
-                Some(Combinator::NextSibling)
-            }
-            (
-                token_value!(Token::Whitespace),
-                token_value!(Token::Delim('~')),
-                token_value!(Token::Whitespace),
-                _,
-            ) => {
-                data_stream.next();
-                data_stream.next();
-                data_stream.next();
-                Some(Combinator::SubsequentSibling)
-            }
-            (
-                token_value!(Token::Whitespace),
-                token_value!(Token::Delim('>')),
-                token_value!(Token::Whitespace),
-                _,
-            ) => {
-                data_stream.next();
-                data_stream.next();
-                data_stream.next();
-                Some(Combinator::Child)
-            }
-            _ => None,
-        };
-    }
-
-    let next_values = data_stream.peek_next(2);
-
-    if next_values.len() == 2 {
-        return match (next_values[0], next_values[1]) {
-            // No space between combinator
-            (token_value!(Token::Whitespace), _) => {
-                data_stream.next();
-                Some(Combinator::Descendant)
-            }
-            (token_value!(Token::Delim('+')), _) => {
-                data_stream.next();
-                Some(Combinator::NextSibling)
-            }
-            (token_value!(Token::Delim('~')), _) => {
-                data_stream.next();
-                Some(Combinator::SubsequentSibling)
-            }
-            (token_value!(Token::Delim('>')), _) => {
-                data_stream.next();
-                Some(Combinator::Child)
-            }
-            _ => None,
-        };
-    }
+    let skip_whitespace = |data_stream: &mut DataStream<ComponentValue>| {
+        let mut skipped = false;
+        while let Some(token_value!(Token::Whitespace)) = data_stream.peek() {
+            data_stream.next();
+            skipped = true;
+        }
+        skipped
+    };
 
-    None
+    let has_whitespace = skip_whitespace(data_stream);
+    let combinator = match data_stream.peek() {
+        Some(token_value!(Token::Delim('+'))) => Combinator::NextSibling,
+        Some(token_value!(Token::Delim('~'))) => Combinator::SubsequentSibling,
+        Some(token_value!(Token::Delim('>'))) => Combinator::Child,
+        Some(_) if has_whitespace => return Some(Combinator::Descendant),
+        _ => return None,
+    };
+    data_stream.next();
+    skip_whitespace(data_stream);
+    Some(combinator)
 }
 
 pub fn parse_simple_selector_seq(
     data_stream: &mut DataStream<ComponentValue>,
 ) -> Option<SimpleSelectorSequence> {
     let mut seq = Vec::new();
-    loop {
-        if let Some(simple_selector) = parse_simple_selector(data_stream) {
-            seq.push(simple_selector);
-        } else {
-            if let Some(value) = data_stream.peek() {
-                match value {
-                    ComponentValue::PerservedToken(Token::Whitespace) => break,
-                    ComponentValue::PerservedToken(Token::Comma) => break,
-                    _ => {
-                        data_stream.next();
-                    }
-                }
-            }
-        }
-        if data_stream.is_eos() {
-            break;
-        }
+    while let Some(simple_selector) = parse_simple_selector(data_stream) {
+        seq.push(simple_selector);
     }
 
     match seq.len() {
@@ -213,6 +126,39 @@ pub fn parse_simple_selector(
             }
             None
         }
+        Some(token_value!(Token::Colon)) => {
+            let next_values = data_stream.peek_next(3);
+            if next_values.len() == 3 {
+                if let (token_value!(Token::Colon), token_value!(Token::Ident(data))) =
+                    (next_values[1].clone(), next_values[2].clone())
+                {
+                    data_stream.next();
+                    data_stream.next();
+                    data_stream.next();
+                    return Some(SimpleSelector::new(
+                        SimpleSelectorType::PseudoElement,
+                        Some(data.to_lowercase()),
+                    ));
+                }
+            }
+            if next_values.len() < 2 {
+                return None;
+            }
+            if let token_value!(Token::Ident(data)) = next_values[1].clone() {
+                data_stream.next();
+                data_stream.next();
+                let name = data.to_lowercase();
+                // Pseudo-elements from CSS 2 can use the single colon syntax
+                let selector_type = match name.as_str() {
+                    "before" | "after" | "first-line" | "first-letter" => {
+                        SimpleSelectorType::PseudoElement
+                    }
+                    _ => SimpleSelectorType::Pseudo,
+                };
+                return Some(SimpleSelector::new(selector_type, Some(name)));
+            }
+            None
+        }
         // TODO: Support other selectors too
         _ => None,
     }
@@ -252,6 +198,66 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_pseudo_class() {
+        let css = "input:Invalid { color: red; }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let tokens = tokenizer.run();
+        let mut parser = Parser::<Token>::new(tokens);
+        let rules = parser.parse_a_stylesheet();
+        let rule = rules.get(0).unwrap();
+
+        if let Rule::QualifiedRule(rule) = rule {
+            let selectors = parse_selectors(&rule.prelude);
+
+            let expected = Selector::new(vec![(
+                SimpleSelectorSequence::new(vec![
+                    SimpleSelector::new(SimpleSelectorType::Type, Some("input".to_string())),
+                    SimpleSelector::new(SimpleSelectorType::Pseudo, Some("invalid".to_string())),
+                ]),
+                None,
+            )]);
+
+            assert_eq!(selectors.get(0), Some(&expected));
+            assert_eq!(expected.specificity(), Specificity::new(0, 1, 1));
+        }
+    }
+
+    #[test]
+    fn parse_pseudo_element() {
+        let css = "p.note::Before, p:after { color: red; }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let tokens = tokenizer.run();
+        let mut parser = Parser::<Token>::new(tokens);
+        let rules = parser.parse_a_stylesheet();
+        let rule = rules.get(0).unwrap();
+
+        if let Rule::QualifiedRule(rule) = rule {
+            let selectors = parse_selectors(&rule.prelude);
+
+            let expected = Selector::new(vec![(
+                SimpleSelectorSequence::new(vec![
+                    SimpleSelector::new(SimpleSelectorType::Type, Some("p".to_string())),
+                    SimpleSelector::new(SimpleSelectorType::Class, Some("note".to_string())),
+                    SimpleSelector::new(
+                        SimpleSelectorType::PseudoElement,
+                        Some("before".to_string()),
+                    ),
+                ]),
+                None,
+            )]);
+
+            assert_eq!(selectors.get(0), Some(&expected));
+            assert_eq!(expected.specificity(), Specificity::new(0, 1, 2));
+            assert_eq!(
+                selectors
+                    .get(1)
+                    .and_then(|selector| selector.pseudo_element()),
+                Some("after")
+            );
+        }
+    }
+
     #[test]
     fn parse_simple_valid_with_combinator() {
         let css = "div.class #id { color: red; }";
@@ -299,26 +305,7 @@ mod tests {
         if let Rule::QualifiedRule(rule) = rule {
             let selectors = parse_selectors(&rule.prelude);
 
-            assert_eq!(selectors.len(), 1);
-
-            let expected = Selector::new(vec![
-                (
-                    SimpleSelectorSequence::new(vec![
-                        SimpleSelector::new(SimpleSelectorType::Type, Some("div".to_string())),
-                        SimpleSelector::new(SimpleSelectorType::Class, Some("class".to_string())),
-                    ]),
-                    Some(Combinator::Child),
-                ),
-                (
-                    SimpleSelectorSequence::new(vec![SimpleSelector::new(
-                        SimpleSelectorType::ID,
-                        Some("id".to_string()),
-                    )]),
-                    None,
-                ),
-            ]);
-
-            assert_eq!(selectors.get(0), Some(&expected));
+            assert!(selectors.is_empty());
         }
     }
 
@@ -430,4 +417,22 @@ mod tests {
 
         assert_eq!(specificity, Specificity::new(2, 1, 0));
     }
+
+    #[test]
+    fn parse_invalid_selector_list() {
+        let selectors = |css: &str| {
+            let tokenizer = Tokenizer::new(css.chars());
+            let mut parser = Parser::<Token>::new(tokenizer.run());
+            parse_selectors(&parser.parse_a_list_of_component_values())
+        };
+
+        assert_eq!(selectors("a  >b ,  .c~d").len(), 2);
+        assert_eq!(selectors(" a  b ").len(), 1);
+        // An invalid or unsupported selector invalidates the whole list
+        assert!(selectors("a, b!").is_empty());
+        assert!(selectors("a, ").is_empty());
+        assert!(selectors("input[type=text], p").is_empty());
+        assert!(selectors("} div").is_empty());
+        assert!(selectors("a >").is_empty());
+    }
 }
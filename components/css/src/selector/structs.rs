@@ -24,6 +24,7 @@ pub enum SimpleSelectorType {
     Class,
     ID,
     Pseudo,
+    PseudoElement,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -80,6 +81,17 @@ impl Selector {
         });
         Specificity::new(a, b, c)
     }
+
+    /// Name of the pseudo-element targeted by the selector, if any.
+    /// Pseudo-elements can only appear in the last compound selector.
+    pub fn pseudo_element(&self) -> Option<&str> {
+        let (last_sequence, _) = self.values().last()?;
+        last_sequence
+            .values()
+            .iter()
+            .find(|selector| *selector.selector_type() == SimpleSelectorType::PseudoElement)
+            .and_then(|selector| selector.value().as_deref())
+    }
 }
 
 impl SimpleSelectorSequence {
@@ -97,10 +109,12 @@ impl SimpleSelectorSequence {
                 .iter()
                 .fold((0, 0, 0), |acc, curr| match curr.selector_type() {
                     SimpleSelectorType::ID => (acc.0 + 1, acc.1, acc.2),
-                    SimpleSelectorType::Class | SimpleSelectorType::Attribute => {
-                        (acc.0, acc.1 + 1, acc.2)
+                    SimpleSelectorType::Class
+                    | SimpleSelectorType::Attribute
+                    | SimpleSelectorType::Pseudo => (acc.0, acc.1 + 1, acc.2),
+                    SimpleSelectorType::Type | SimpleSelectorType::PseudoElement => {
+                        (acc.0, acc.1, acc.2 + 1)
                     }
-                    SimpleSelectorType::Type => (acc.0, acc.1, acc.2 + 1),
                     _ => acc,
                 });
         Specificity(a, b, c)
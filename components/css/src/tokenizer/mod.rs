@@ -477,26 +477,26 @@ where
         let string = self.consume_name();
         if string.eq_ignore_ascii_case("url") {
             if let Some('(') = self.input.peek() {
-                self.consume_next();
-                loop {
-                    if let Some(next_2_chars) = self.input.peek_next_as::<String>(2) {
-                        let mut chars = next_2_chars.chars();
-                        let first = chars.next().unwrap();
-                        let second = chars.next().unwrap();
-                        if is_whitespace(first) && is_whitespace(second) {
-                            self.consume_next();
-                        } else {
-                            break;
-                        }
+                // A quoted URL is a function. Like other functions, the `(`
+                // is left for the parser to consume.
+                if let Some(next_3_chars) = self.input.peek_next_as::<String>(3) {
+                    let re = Regex::new("^\\(\\s?('|\")").unwrap();
+                    if re.is_match(&next_3_chars) {
+                        return Token::Function(string);
                     }
                 }
-                if let Some(next_2_chars) = self.input.peek_next_as::<String>(2) {
-                    let re = Regex::new("^ ?('|\")$").unwrap();
-                    if re.is_match(&next_2_chars) {
-                        return Token::Function(string);
+                self.consume_next();
+                while let Some(next_2_chars) = self.input.peek_next_as::<String>(2) {
+                    let mut chars = next_2_chars.chars();
+                    let first = chars.next().unwrap();
+                    let second = chars.next().unwrap();
+                    if is_whitespace(first) && is_whitespace(second) {
+                        self.consume_next();
+                    } else {
+                        break;
                     }
-                    return self.consume_url();
                 }
+                return self.consume_url();
             }
         }
         if let Some('(') = self.input.peek() {
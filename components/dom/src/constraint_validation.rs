@@ -0,0 +1,503 @@
+use regex::Regex;
+
+use crate::form_submission::is_disabled;
+use crate::node::NodePtr;
+use crate::{input, select, textarea};
+
+/// Validity state of a form control
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#validitystate
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidityState {
+    pub value_missing: bool,
+    pub type_mismatch: bool,
+    pub pattern_mismatch: bool,
+    pub too_long: bool,
+    pub range_underflow: bool,
+    pub range_overflow: bool,
+    pub step_mismatch: bool,
+}
+
+impl ValidityState {
+    pub fn valid(&self) -> bool {
+        *self == ValidityState::default()
+    }
+}
+
+const NON_VALIDATED_INPUT_TYPES: [&str; 5] = ["hidden", "submit", "reset", "button", "image"];
+
+/// Check if the node is a form control whose constraints should be validated
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#candidate-for-constraint-validation
+pub fn is_candidate_for_constraint_validation(control: &NodePtr) -> bool {
+    let element = match control.as_element_opt() {
+        Some(element) => element,
+        None => return false,
+    };
+    let is_candidate = match element.tag_name().as_str() {
+        "input" => {
+            !NON_VALIDATED_INPUT_TYPES.contains(&input_type(control).as_str())
+                && !element.has_attribute("readonly")
+        }
+        "textarea" => !element.has_attribute("readonly"),
+        "select" => true,
+        _ => false,
+    };
+    is_candidate && !is_disabled(control)
+}
+
+/// Validity of the current value of a form control
+pub fn validity(control: &NodePtr) -> ValidityState {
+    if !is_candidate_for_constraint_validation(control) {
+        return ValidityState::default();
+    }
+
+    match control.as_element().tag_name().as_str() {
+        "textarea" => textarea_validity(control),
+        "select" => ValidityState {
+            value_missing: control.as_element().has_attribute("required")
+                && is_select_value_missing(control),
+            ..ValidityState::default()
+        },
+        _ => input_validity(control),
+    }
+}
+
+fn input_validity(input: &NodePtr) -> ValidityState {
+    let mut validity = ValidityState::default();
+    let attributes = input.as_element().attributes();
+    let attributes = attributes.borrow();
+    let input_type = input_type(input);
+    let value = input::value(input);
+
+    if attributes.contains_key("required") {
+        validity.value_missing = match input_type.as_str() {
+            "checkbox" | "radio" => !attributes.contains_key("checked"),
+            _ => value.is_empty(),
+        };
+    }
+
+    if value.is_empty() {
+        return validity;
+    }
+
+    validity.type_mismatch = match input_type.as_str() {
+        "email" => !is_valid_email(&value),
+        "url" => !value.contains(':'),
+        _ => false,
+    };
+
+    if let Some(pattern) = attributes.get("pattern") {
+        match compile_pattern(pattern) {
+            Some(regex) => validity.pattern_mismatch = !regex.is_match(&value),
+            None => log::warn!("Invalid pattern attribute: {}", pattern),
+        }
+    }
+
+    if let Ok(max_length) = attributes.get_str("maxlength").parse::<usize>() {
+        validity.too_long = value.chars().count() > max_length;
+    }
+
+    if matches!(input_type.as_str(), "number" | "range") {
+        if let Ok(number) = value.trim().parse::<f64>() {
+            let min = attributes.get_str("min").parse::<f64>().ok();
+            let max = attributes.get_str("max").parse::<f64>().ok();
+
+            validity.range_underflow = min.map(|min| number < min).unwrap_or(false);
+            validity.range_overflow = max.map(|max| number > max).unwrap_or(false);
+
+            let step = match attributes.get_str("step").as_str() {
+                "any" => None,
+                step => Some(step.parse::<f64>().ok().filter(|s| *s > 0.).unwrap_or(1.)),
+            };
+
+            if let Some(step) = step {
+                let steps = (number - min.unwrap_or(0.)) / step;
+                validity.step_mismatch = (steps - steps.round()).abs() > 1e-7;
+            }
+        }
+    }
+
+    validity
+}
+
+fn textarea_validity(textarea: &NodePtr) -> ValidityState {
+    let attributes = textarea.as_element().attributes();
+    let attributes = attributes.borrow();
+    let value = textarea::value(textarea);
+    let too_long = match attributes.get_str("maxlength").parse::<usize>() {
+        Ok(max_length) => value.chars().count() > max_length,
+        Err(_) => false,
+    };
+
+    ValidityState {
+        value_missing: attributes.contains_key("required") && value.is_empty(),
+        too_long,
+        ..ValidityState::default()
+    }
+}
+
+/// A select is missing its value when no option is selected, or when the
+/// only selected option is its placeholder label option
+/// https://html.spec.whatwg.org/multipage/form-elements.html#placeholder-label-option
+fn is_select_value_missing(select: &NodePtr) -> bool {
+    let selected_options = select::selected_options(select);
+    let is_placeholder = |option: &NodePtr| {
+        select::is_drop_down(select)
+            && select::options(select).first().map(|first| first.id()) == Some(option.id())
+            && option.parent().map(|parent| NodePtr(parent).id()) == Some(select.id())
+            && select::option_value(option).is_empty()
+    };
+
+    match selected_options.as_slice() {
+        [] => true,
+        [option] => is_placeholder(option),
+        _ => false,
+    }
+}
+
+/// User-facing message describing the first failed constraint
+pub fn validation_message(control: &NodePtr) -> String {
+    let validity = validity(control);
+    let element = control.as_element();
+    let attributes = element.attributes();
+    let attributes = attributes.borrow();
+
+    if validity.value_missing {
+        return match (element.tag_name().as_str(), input_type(control).as_str()) {
+            ("select", _) => "Please select an item in the list.".to_string(),
+            ("input", "checkbox") => "Please check this box if you want to proceed.".to_string(),
+            ("input", "radio") => "Please select one of these options.".to_string(),
+            _ => "Please fill out this field.".to_string(),
+        };
+    }
+    if validity.type_mismatch {
+        return match input_type(control).as_str() {
+            "email" => "Please enter an email address.".to_string(),
+            _ => "Please enter a URL.".to_string(),
+        };
+    }
+    if validity.pattern_mismatch {
+        return match attributes.get("title") {
+            Some(title) => format!("Please match the requested format: {}", title),
+            None => "Please match the requested format.".to_string(),
+        };
+    }
+    if validity.too_long {
+        return format!(
+            "Please shorten this text to {} characters or less.",
+            attributes.get_str("maxlength")
+        );
+    }
+    if validity.range_underflow {
+        return format!(
+            "Value must be greater than or equal to {}.",
+            attributes.get_str("min")
+        );
+    }
+    if validity.range_overflow {
+        return format!(
+            "Value must be less than or equal to {}.",
+            attributes.get_str("max")
+        );
+    }
+    if validity.step_mismatch {
+        return "Please enter a valid value.".to_string();
+    }
+    String::new()
+}
+
+fn input_type(input: &NodePtr) -> String {
+    match input.as_element().attributes().borrow().get("type") {
+        Some(input_type) => input_type.to_lowercase(),
+        None => "text".to_string(),
+    }
+}
+
+fn is_valid_email(value: &str) -> bool {
+    match value.split_once('@') {
+        Some((local, domain)) => !local.is_empty() && !domain.is_empty() && !domain.contains('@'),
+        None => false,
+    }
+}
+
+/// Compile a `pattern` attribute, which is a JavaScript regular expression
+/// that has to match the whole value. Returns `None` for invalid patterns and
+/// for features without a Rust equivalent, like lookarounds and backreferences.
+/// https://html.spec.whatwg.org/multipage/input.html#compiled-pattern-regular-expression
+fn compile_pattern(pattern: &str) -> Option<Regex> {
+    let pattern = translate_pattern(pattern)?;
+    Regex::new(&format!("^(?:{})$", pattern)).ok()
+}
+
+const JS_WHITESPACE: &str =
+    r"\t\n\x0B\x0C\r \xA0\x{1680}\x{2000}-\x{200A}\x{2028}\x{2029}\x{202F}\x{205F}\x{3000}\x{FEFF}";
+
+/// Rewrite a JavaScript pattern, compiled with the `u` flag, in the syntax of
+/// the regex crate. Character classes like `\d` and `\w` are ASCII only in
+/// JavaScript, and `.` doesn't match line terminators.
+fn translate_pattern(pattern: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = pattern.chars().peekable();
+    let mut in_class = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push_str(&translate_escape(&mut chars, in_class)?),
+            '[' if in_class => result.push_str(r"\["),
+            '[' => {
+                in_class = true;
+                let negated = chars.next_if_eq(&'^').is_some();
+                if chars.next_if_eq(&']').is_some() {
+                    // `[]` matches nothing and `[^]` matches anything
+                    in_class = false;
+                    result.push_str(if negated { "(?s:.)" } else { r"(?:\b\B)" });
+                } else {
+                    result.push_str(if negated { "[^" } else { "[" });
+                }
+            }
+            ']' if in_class => {
+                in_class = false;
+                result.push(']');
+            }
+            // Set operators of the regex crate are literals in JavaScript
+            '&' | '~' | '-' if in_class && chars.peek() == Some(&c) => {
+                result.push('\\');
+                result.push(c);
+            }
+            '.' if !in_class => result.push_str(r"[^\n\r\x{2028}\x{2029}]"),
+            '(' if chars.peek() == Some(&'?') && !in_class => {
+                chars.next();
+                match chars.next()? {
+                    ':' => result.push_str("(?:"),
+                    // Named groups, but not lookbehinds
+                    '<' if !matches!(chars.peek(), Some('=') | Some('!')) => {
+                        result.push_str("(?P<")
+                    }
+                    _ => return None,
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    Some(result)
+}
+
+fn translate_escape(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+    in_class: bool,
+) -> Option<String> {
+    let escape = match chars.next()? {
+        'd' => "[0-9]".to_string(),
+        'D' => "[^0-9]".to_string(),
+        'w' => "[0-9A-Za-z_]".to_string(),
+        'W' => "[^0-9A-Za-z_]".to_string(),
+        's' => format!("[{}]", JS_WHITESPACE),
+        'S' => format!("[^{}]", JS_WHITESPACE),
+        'b' if in_class => r"\x08".to_string(),
+        // The regex crate has no ASCII word boundaries for text, so these
+        // also treat non-ASCII letters as word characters
+        c @ ('b' | 'B') => format!("\\{}", c),
+        c @ ('t' | 'n' | 'r' | 'f' | 'v') => format!("\\{}", c),
+        '0' if !chars.peek().is_some_and(char::is_ascii_digit) => r"\x00".to_string(),
+        'c' => {
+            let letter = chars.next().filter(char::is_ascii_alphabetic)?;
+            format!(r"\x{{{:X}}}", letter as u32 % 32)
+        }
+        'x' => {
+            let code = take_hex(chars, 2)?;
+            format!(r"\x{{{:X}}}", code)
+        }
+        'u' => format!(r"\x{{{:X}}}", unicode_escape(chars)?),
+        c @ ('p' | 'P') => format!("\\{}", c),
+        c if r"^$\.*+?()[]{}|".contains(c) || (c == '-' && in_class) => format!("\\{}", c),
+        '/' => "/".to_string(),
+        // Backreferences and other escapes are errors with the `u` flag, or
+        // can't be translated
+        _ => return None,
+    };
+    Some(escape)
+}
+
+/// Code point of a `\u{...}` or `\uXXXX` escape, where a surrogate pair is
+/// written as two escapes
+fn unicode_escape(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<u32> {
+    if chars.next_if_eq(&'{').is_some() {
+        let mut code = 0u32;
+        while let Some(c) = chars.next() {
+            if c == '}' {
+                return char::from_u32(code).map(u32::from);
+            }
+            code = code.checked_mul(16)?.checked_add(c.to_digit(16)?)?;
+        }
+        return None;
+    }
+
+    let code = take_hex(chars, 4)?;
+    if !(0xD800..0xDC00).contains(&code) {
+        return char::from_u32(code).map(u32::from);
+    }
+    if chars.next() != Some('\\') || chars.next() != Some('u') {
+        return None;
+    }
+    let low = take_hex(chars, 4).filter(|low| (0xDC00..0xE000).contains(low))?;
+    Some(0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00))
+}
+
+fn take_hex(chars: &mut std::iter::Peekable<std::str::Chars>, digits: usize) -> Option<u32> {
+    (0..digits).try_fold(0, |code, _| Some(code * 16 + chars.next()?.to_digit(16)?))
+}
+
+/// Check the validity of a form control, or of every control inside a form
+pub fn check_validity(node: &NodePtr) -> bool {
+    invalid_controls(node).is_empty()
+}
+
+/// Messages for every invalid control of a form, used to block its submission
+pub fn validation_messages(form: &NodePtr) -> Vec<String> {
+    invalid_controls(form)
+        .iter()
+        .map(|control| {
+            let message = validation_message(control);
+            match control.as_element().attributes().borrow().get("name") {
+                Some(name) => format!("{}: {}", name, message),
+                None => message,
+            }
+        })
+        .collect()
+}
+
+fn invalid_controls(node: &NodePtr) -> Vec<NodePtr> {
+    let mut result = Vec::new();
+
+    if is_candidate_for_constraint_validation(node) && !validity(node).valid() {
+        result.push(node.clone());
+    }
+
+    node.for_each_child(|child| result.extend(invalid_controls(&NodePtr(child))));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+
+    fn document() -> NodePtr {
+        NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )))
+    }
+
+    fn element(parent: &NodePtr, tag_name: &str, attrs: &[(&str, &str)]) -> NodePtr {
+        let document =
+            WeakTreeNode::from(&parent.owner_document().unwrap_or_else(|| parent.0.clone()));
+        let element = create_element(document, tag_name);
+        for (name, value) in attrs {
+            element.as_element().set_attribute(name, value);
+        }
+        parent.append_child(element.0.clone());
+        element
+    }
+
+    #[test]
+    fn test_edited_input() {
+        let document = document();
+        let input = element(&document, "input", &[("required", ""), ("value", "x")]);
+        assert!(validity(&input).valid());
+
+        assert!(input::set_value(&input, ""));
+        assert!(validity(&input).value_missing);
+        assert_eq!(validation_message(&input), "Please fill out this field.");
+
+        input.as_element().set_attribute("pattern", "[a-z]{3}");
+        input::set_value(&input, "abc");
+        assert!(validity(&input).valid());
+        input::set_value(&input, "abcd");
+        assert!(validity(&input).pattern_mismatch);
+        assert!(!check_validity(&document));
+
+        // The value attribute doesn't change the edited value
+        input.as_element().set_attribute("value", "abc");
+        assert!(validity(&input).pattern_mismatch);
+    }
+
+    #[test]
+    fn test_edited_textarea() {
+        let document = document();
+        let textarea = element(
+            &document,
+            "textarea",
+            &[("required", ""), ("maxlength", "3")],
+        );
+        assert!(validity(&textarea).value_missing);
+
+        textarea::edit(&textarea, |editor| editor.insert("abcd"));
+        assert_eq!(
+            validity(&textarea),
+            ValidityState {
+                too_long: true,
+                ..ValidityState::default()
+            }
+        );
+        textarea::edit(&textarea, |editor| editor.delete_backward());
+        assert!(validity(&textarea).valid());
+
+        textarea.as_element().set_attribute("readonly", "");
+        textarea::edit(&textarea, |editor| editor.select_all());
+        textarea::edit(&textarea, |editor| editor.delete_backward());
+        assert!(!is_candidate_for_constraint_validation(&textarea));
+        assert!(validity(&textarea).valid());
+    }
+
+    #[test]
+    fn test_selected_option() {
+        let document = document();
+        let select = element(&document, "select", &[("required", "")]);
+        element(&select, "option", &[("value", "")]);
+        element(&select, "option", &[("value", "a")]);
+
+        // The first option is a placeholder label
+        assert!(validity(&select).value_missing);
+        assert_eq!(
+            validation_message(&select),
+            "Please select an item in the list."
+        );
+
+        assert!(select::select_option(&select, 1));
+        assert!(validity(&select).valid());
+        assert!(select::select_option(&select, 0));
+        assert!(validity(&select).value_missing);
+
+        select.as_element().remove_attribute("required");
+        assert!(validity(&select).valid());
+    }
+
+    #[test]
+    fn test_javascript_pattern() {
+        let is_match =
+            |pattern: &str, value: &str| compile_pattern(pattern).unwrap().is_match(value);
+
+        assert!(is_match(r"\d+", "123"));
+        assert!(!is_match(r"\d+", "١٢٣"));
+        assert!(!is_match(r"\w+", "é"));
+        assert!(is_match(r"[\w-]+", "a-b_c"));
+        assert!(is_match(r"a.c", "abc"));
+        assert!(!is_match(r"a.c", "a\nc"));
+        assert!(is_match(r"é|\u{1F600}|😁", "😁"));
+        assert!(is_match(r"https?:\/\/.+", "https://a"));
+        assert!(is_match(r"[^]*", "a\nb"));
+        assert!(!is_match(r"[]", ""));
+        assert!(is_match(r"[a&&b]", "&"));
+        assert!(is_match(r"\bab\B.", "abc"));
+        assert!(!is_match("a|b", "ab"));
+
+        assert!(compile_pattern(r"(?=a)a").is_none());
+        assert!(compile_pattern(r"(a)\1").is_none());
+        assert!(compile_pattern(r"\q").is_none());
+        assert!(compile_pattern(r"(?<name>a)").is_some());
+    }
+}
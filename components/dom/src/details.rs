@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use crate::node::NodePtr;
+
+/// The summary of a details element, its first summary child
+/// https://html.spec.whatwg.org/multipage/interactive-elements.html#the-details-element
+pub fn summary(details: &NodePtr) -> Option<NodePtr> {
+    let mut summary = None;
+    details.for_each_child(|child| {
+        if summary.is_none() && has_tag_name(&NodePtr(child.clone()), "summary") {
+            summary = Some(NodePtr(child));
+        }
+    });
+    summary
+}
+
+/// The details element summarized by the node, if the node is the summary
+/// of its parent
+pub fn details_of_summary(node: &NodePtr) -> Option<NodePtr> {
+    let parent = node.parent().map(NodePtr)?;
+    if !has_tag_name(&parent, "details") {
+        return None;
+    }
+    summary(&parent)
+        .filter(|summary| Rc::ptr_eq(summary, node))
+        .map(|_| parent)
+}
+
+pub fn is_open(details: &NodePtr) -> bool {
+    details.as_element().has_attribute("open")
+}
+
+/// Whether the child of a details element is rendered. The summary always
+/// is, the rest of the content only while the details element is open.
+pub fn is_child_rendered(details: &NodePtr, child: &NodePtr) -> bool {
+    if !has_tag_name(details, "details") || is_open(details) {
+        return true;
+    }
+    summary(details).is_some_and(|summary| Rc::ptr_eq(&summary, child))
+}
+
+/// Open the details element if it is closed, close it otherwise
+/// https://html.spec.whatwg.org/multipage/interactive-elements.html#the-summary-element:activation-behaviour
+pub fn toggle(details: &NodePtr) {
+    if is_open(details) {
+        details.remove_attribute("open");
+    } else {
+        details.set_attribute("open", "");
+    }
+}
+
+fn has_tag_name(node: &NodePtr, tag_name: &str) -> bool {
+    node.as_element_opt()
+        .is_some_and(|element| element.tag_name() == tag_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+
+    #[test]
+    fn test_details() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let element = |parent: &NodePtr, tag_name: &str| {
+            let element = create_element(WeakTreeNode::from(&document.0), tag_name);
+            parent.append_child(element.0.clone());
+            element
+        };
+        let details = element(&document, "details");
+        let content = element(&details, "p");
+        let first_summary = element(&details, "summary");
+        let second_summary = element(&details, "summary");
+
+        assert!(details_of_summary(&first_summary).is_some());
+        assert!(details_of_summary(&second_summary).is_none());
+        assert!(is_child_rendered(&details, &first_summary));
+        assert!(!is_child_rendered(&details, &content));
+        assert!(!is_child_rendered(&details, &second_summary));
+
+        toggle(&details);
+        assert!(is_open(&details));
+        assert!(is_child_rendered(&details, &content));
+        toggle(&details);
+        assert!(!is_open(&details));
+    }
+}
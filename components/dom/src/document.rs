@@ -1,6 +1,13 @@
+use super::lifecycle::DocumentLifecycle;
+use super::mutation::MutationObservers;
 use super::node::NodeHooks;
+use super::node_id::NodeRegistry;
 use css::cssom::css_rule::CSSRule;
-use std::cell::RefCell;
+use css::cssom::font_face_rule::FontFaceRule;
+use css::cssom::keyframes_rule::KeyframesRule;
+use css::media_query::structs::MediaEnvironment;
+use shared::cancellation::CancellationToken;
+use std::cell::{Cell, RefCell};
 use std::ops::Deref;
 use std::rc::{Rc, Weak};
 use style_types::{ContextualRule, ContextualStyleSheet};
@@ -12,7 +19,22 @@ pub struct Document {
     mode: RefCell<QuirksMode>,
     stylesheets: RefCell<Vec<Rc<ContextualStyleSheet>>>,
     cached_style_rules: RefCell<Vec<(Weak<ContextualStyleSheet>, Vec<ContextualRule>)>>,
+    media_environment: RefCell<MediaEnvironment>,
     base: RefCell<Option<Url>>,
+    generation: Cell<u64>,
+    /// Time that CSS animations are sampled at, in milliseconds since the
+    /// document was shown
+    animation_time: Cell<f64>,
+    /// Whether an element had an animation in progress when the document
+    /// was last styled
+    has_running_animations: Cell<bool>,
+    /// Font size of the user in CSS pixels, inherited by the root element
+    default_font_size: Cell<f32>,
+    /// Token of the load in progress, `None` once the document is loaded
+    load_cancellation: RefCell<Option<CancellationToken>>,
+    node_registry: NodeRegistry,
+    mutation_observers: MutationObservers,
+    lifecycle: DocumentLifecycle,
 }
 
 pub struct DocumentType {
@@ -21,7 +43,7 @@ pub struct DocumentType {
     system_id: String,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum QuirksMode {
     Quirks,
     NoQuirks,
@@ -44,7 +66,16 @@ impl Document {
             mode: RefCell::new(QuirksMode::NoQuirks),
             stylesheets: RefCell::new(Vec::new()),
             cached_style_rules: RefCell::new(Vec::new()),
+            media_environment: RefCell::new(MediaEnvironment::new(0., 0.)),
             base: RefCell::new(None),
+            generation: Cell::new(0),
+            animation_time: Cell::new(0.),
+            has_running_animations: Cell::new(false),
+            default_font_size: Cell::new(16.),
+            load_cancellation: RefCell::new(None),
+            node_registry: NodeRegistry::new(),
+            mutation_observers: MutationObservers::new(),
+            lifecycle: DocumentLifecycle::new(),
         }
     }
 
@@ -52,6 +83,14 @@ impl Document {
         *self.doctype.borrow_mut() = Some(doctype);
     }
 
+    /// Name of the doctype of the document, `html` for HTML documents
+    pub fn doctype_name(&self) -> Option<String> {
+        self.doctype
+            .borrow()
+            .as_ref()
+            .map(|doctype| doctype.name.clone())
+    }
+
     pub fn set_mode(&self, mode: QuirksMode) {
         *self.mode.borrow_mut() = mode;
     }
@@ -74,6 +113,12 @@ impl Document {
         stylesheet_ptr
     }
 
+    /// Insert a stylesheet shared between documents before every other
+    /// stylesheet of the document
+    pub fn insert_user_agent_stylesheet(&self, stylesheet: Rc<ContextualStyleSheet>) {
+        self.stylesheets.borrow_mut().insert(0, stylesheet);
+    }
+
     pub fn remove_stylesheet(&self, stylesheet: &Rc<ContextualStyleSheet>) {
         let maybe_index = self
             .stylesheets
@@ -104,17 +149,13 @@ impl Document {
                 continue;
             }
 
-            let rules = stylesheet
-                .inner
-                .iter()
-                .map(move |rule| match rule {
-                    CSSRule::Style(style) => ContextualRule {
-                        inner: style.clone(),
-                        location: stylesheet.location.clone(),
-                        origin: stylesheet.origin.clone(),
-                    },
-                })
-                .collect();
+            let mut rules = Vec::new();
+            collect_contextual_rules(
+                &stylesheet.inner,
+                stylesheet,
+                &self.media_environment.borrow(),
+                &mut rules,
+            );
 
             append_rules.push((Rc::downgrade(stylesheet), rules));
         }
@@ -134,6 +175,150 @@ impl Document {
             .collect()
     }
 
+    pub fn media_environment(&self) -> MediaEnvironment {
+        self.media_environment.borrow().clone()
+    }
+
+    /// Update the environment that media queries are evaluated against.
+    /// Returns true if the style rules of the document changed and the
+    /// document needs to be restyled.
+    pub fn set_media_environment(&self, environment: MediaEnvironment) -> bool {
+        if *self.media_environment.borrow() == environment {
+            return false;
+        }
+
+        let previous_results = self.media_query_results();
+        *self.media_environment.borrow_mut() = environment;
+
+        if self.media_query_results() == previous_results {
+            return false;
+        }
+
+        self.cached_style_rules.borrow_mut().clear();
+        true
+    }
+
+    /// Results of every media query of the document in tree order
+    fn media_query_results(&self) -> Vec<bool> {
+        fn collect_results(rules: &[CSSRule], environment: &MediaEnvironment, out: &mut Vec<bool>) {
+            for rule in rules {
+                match rule {
+                    CSSRule::Media(media_rule) => {
+                        out.push(media_rule.evaluate(environment));
+                        collect_results(&media_rule.css_rules, environment, out);
+                    }
+                    CSSRule::Import(import_rule) => {
+                        out.push(import_rule.evaluate(environment));
+                        if let Some(imported) = &import_rule.stylesheet {
+                            collect_results(imported, environment, out);
+                        }
+                    }
+                    CSSRule::Style(_) | CSSRule::FontFace(_) | CSSRule::Keyframes(_) => {}
+                }
+            }
+        }
+
+        let environment = self.media_environment.borrow();
+        let mut results = Vec::new();
+        for stylesheet in self.stylesheets.borrow().iter() {
+            collect_results(&stylesheet.inner, &environment, &mut results);
+        }
+        results
+    }
+
+    /// `@font-face` rules of the document and of the stylesheets it imports
+    pub fn font_faces(&self) -> Vec<FontFaceRule> {
+        fn collect_font_faces(
+            rules: &[CSSRule],
+            environment: &MediaEnvironment,
+            out: &mut Vec<FontFaceRule>,
+        ) {
+            for rule in rules {
+                match rule {
+                    CSSRule::FontFace(font_face) => out.push(font_face.clone()),
+                    CSSRule::Import(import_rule) => {
+                        if let Some(imported) = &import_rule.stylesheet {
+                            if import_rule.evaluate(environment) {
+                                collect_font_faces(imported, environment, out);
+                            }
+                        }
+                    }
+                    CSSRule::Style(_) | CSSRule::Media(_) | CSSRule::Keyframes(_) => {}
+                }
+            }
+        }
+
+        let environment = self.media_environment.borrow();
+        let mut font_faces = Vec::new();
+        for stylesheet in self.stylesheets.borrow().iter() {
+            collect_font_faces(&stylesheet.inner, &environment, &mut font_faces);
+        }
+        font_faces
+    }
+
+    /// The `@keyframes` rule with the name that applies in the current
+    /// environment. The last one in the document wins when there are many.
+    pub fn keyframes(&self, name: &str) -> Option<KeyframesRule> {
+        fn find_keyframes(
+            rules: &[CSSRule],
+            name: &str,
+            environment: &MediaEnvironment,
+            found: &mut Option<KeyframesRule>,
+        ) {
+            for rule in rules {
+                match rule {
+                    CSSRule::Keyframes(keyframes) if keyframes.name == name => {
+                        *found = Some(keyframes.clone())
+                    }
+                    CSSRule::Media(media_rule) if media_rule.evaluate(environment) => {
+                        find_keyframes(&media_rule.css_rules, name, environment, found);
+                    }
+                    CSSRule::Import(import_rule) if import_rule.evaluate(environment) => {
+                        if let Some(imported) = &import_rule.stylesheet {
+                            find_keyframes(imported, name, environment, found);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let environment = self.media_environment.borrow();
+        let mut found = None;
+        for stylesheet in self.stylesheets.borrow().iter() {
+            find_keyframes(&stylesheet.inner, name, &environment, &mut found);
+        }
+        found
+    }
+
+    pub fn animation_time(&self) -> f64 {
+        self.animation_time.get()
+    }
+
+    pub fn set_animation_time(&self, time: f64) {
+        self.animation_time.set(time);
+    }
+
+    /// Whether the document has to be styled again for its animations to
+    /// progress
+    pub fn has_running_animations(&self) -> bool {
+        self.has_running_animations.get()
+    }
+
+    pub fn set_has_running_animations(&self, is_running: bool) {
+        self.has_running_animations.set(is_running);
+    }
+
+    pub fn default_font_size(&self) -> f32 {
+        self.default_font_size.get()
+    }
+
+    /// Returns true if the size changed, in which case the document needs
+    /// to be restyled
+    pub fn set_default_font_size(&self, size: f32) -> bool {
+        self.default_font_size.replace(size) != size
+    }
+
     fn gabarge_collect_values(&self) {
         let mut indexes_to_remove = Vec::new();
         for (index, (stylesheet, _)) in self.cached_style_rules.borrow().iter().enumerate() {
@@ -148,6 +333,15 @@ impl Document {
         }
     }
 
+    /// Origin of the document, web fonts it loads are only used by the
+    /// documents of the same origin
+    pub fn origin(&self) -> String {
+        match self.base.borrow().as_ref() {
+            Some(base) => base.origin(),
+            None => "null".to_string(),
+        }
+    }
+
     pub fn base(&self) -> Option<Url> {
         self.base.borrow().deref().clone()
     }
@@ -155,6 +349,52 @@ impl Document {
     pub fn set_base(&self, base: Option<Url>) {
         *self.base.borrow_mut() = base;
     }
+
+    /// Counter that changes whenever the tree or its computed styles change,
+    /// used to tell whether a snapshot of the document is still up to date.
+    pub fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
+    /// Nodes of the document by id. Use `NodePtr::node_by_id` to look up
+    /// a node.
+    pub fn node_registry(&self) -> &NodeRegistry {
+        &self.node_registry
+    }
+
+    /// Observers of the changes to the nodes of the document
+    pub fn mutation_observers(&self) -> &MutationObservers {
+        &self.mutation_observers
+    }
+
+    /// Pass the changes made to the document since the last call to the
+    /// observers interested in them
+    pub fn notify_mutation_observers(&self) {
+        self.mutation_observers.notify();
+    }
+
+    /// Ready state of the document and the listeners of its `DOMContentLoaded`
+    /// and `load` events
+    pub fn lifecycle(&self) -> &DocumentLifecycle {
+        &self.lifecycle
+    }
+
+    pub fn bump_generation(&self) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+    }
+
+    /// Set the token that cancels the loading of the document. Fetching,
+    /// parsing and styling stop early once it is cancelled.
+    pub fn set_load_cancellation(&self, token: Option<CancellationToken>) {
+        *self.load_cancellation.borrow_mut() = token;
+    }
+
+    pub fn is_load_cancelled(&self) -> bool {
+        match &*self.load_cancellation.borrow() {
+            Some(token) => token.is_cancelled(),
+            None => false,
+        }
+    }
 }
 
 impl core::fmt::Debug for DocumentType {
@@ -172,3 +412,69 @@ impl DocumentType {
         }
     }
 }
+
+/// Collect the style rules that apply in the environment, flattening the
+/// rules of matching `@media` rules in place
+pub(crate) fn collect_contextual_rules(
+    rules: &[CSSRule],
+    stylesheet: &ContextualStyleSheet,
+    environment: &MediaEnvironment,
+    out: &mut Vec<ContextualRule>,
+) {
+    for rule in rules {
+        match rule {
+            CSSRule::Style(style) => out.push(ContextualRule {
+                inner: style.clone(),
+                location: stylesheet.location.clone(),
+                origin: stylesheet.origin.clone(),
+            }),
+            CSSRule::Media(media_rule) => {
+                if media_rule.evaluate(environment) {
+                    collect_contextual_rules(&media_rule.css_rules, stylesheet, environment, out);
+                }
+            }
+            // Imported rules are spliced in place of the `@import` rule
+            CSSRule::Import(import_rule) => {
+                if let Some(imported) = &import_rule.stylesheet {
+                    if import_rule.evaluate(environment) {
+                        collect_contextual_rules(imported, stylesheet, environment, out);
+                    }
+                }
+            }
+            // Font faces are loaded by the page and keyframes are looked up
+            // by the animations, instead of being cascaded
+            CSSRule::FontFace(_) | CSSRule::Keyframes(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::{parser::Parser, tokenizer::token::Token, tokenizer::Tokenizer};
+    use style_types::{CSSLocation, CascadeOrigin};
+
+    use super::*;
+
+    #[test]
+    fn test_media_rules_follow_environment() {
+        let css = "p { color: red; } @media (max-width: 600px) { p { color: blue; } }";
+        let tokenizer = Tokenizer::new(css.chars());
+        let stylesheet = Parser::<Token>::new(tokenizer.run()).parse_a_css_stylesheet();
+
+        let document = Document::new();
+        document.append_stylesheet(ContextualStyleSheet::new(
+            stylesheet,
+            CascadeOrigin::Author,
+            CSSLocation::Embedded,
+        ));
+
+        assert!(!document.set_media_environment(MediaEnvironment::new(400., 300.)));
+        assert_eq!(document.style_rules().len(), 2);
+
+        // the media query result doesn't change
+        assert!(!document.set_media_environment(MediaEnvironment::new(500., 300.)));
+
+        assert!(document.set_media_environment(MediaEnvironment::new(800., 600.)));
+        assert_eq!(document.style_rules().len(), 1);
+    }
+}
@@ -1,12 +1,32 @@
-use crate::node::{ChildrenUpdateContext, InsertContext};
+use crate::node::{ChildrenUpdateContext, InsertContext, NodePtr};
 
 use super::dom_token_list::DOMTokenList;
 use super::elements::{ElementData, ElementMethods};
 use super::node::NodeHooks;
+use shared::primitive::Point;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ops::{Deref, DerefMut};
 
+/// Namespace of an element, as set by the parser
+/// https://infra.spec.whatwg.org/#namespaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    Html,
+    Svg,
+    MathML,
+}
+
+impl Namespace {
+    pub fn url(&self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::MathML => "http://www.w3.org/1998/Math/MathML",
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct AttributeMap(HashMap<String, String>);
 
@@ -14,6 +34,13 @@ pub struct Element {
     attributes: RefCell<AttributeMap>,
     id: RefCell<Option<String>>,
     class_list: RefCell<DOMTokenList>,
+    shadow_root: RefCell<Option<NodePtr>>,
+    /// Position of the content of a scroll container, in CSS pixels
+    scroll_offset: RefCell<Point>,
+    /// Name of the CSS animation of the element with the time it started,
+    /// in milliseconds on the timeline of the document
+    animation: RefCell<Option<(String, f64)>>,
+    namespace: Namespace,
     data: ElementData,
 }
 
@@ -75,10 +102,18 @@ impl NodeHooks for Element {
 
 impl Element {
     pub fn new(data: ElementData) -> Self {
+        Self::new_in_namespace(data, Namespace::Html)
+    }
+
+    pub fn new_in_namespace(data: ElementData, namespace: Namespace) -> Self {
         Self {
             attributes: RefCell::new(AttributeMap::new()),
             id: RefCell::new(None),
             class_list: RefCell::new(DOMTokenList::new()),
+            shadow_root: RefCell::new(None),
+            scroll_offset: RefCell::new(Point::default()),
+            animation: RefCell::new(None),
+            namespace,
             data,
         }
     }
@@ -87,6 +122,19 @@ impl Element {
         self.data.tag_name()
     }
 
+    pub fn namespace(&self) -> Namespace {
+        self.namespace
+    }
+
+    /// Check if the element is the HTML element with the tag name
+    pub fn is_html(&self, tag_name: &str) -> bool {
+        self.namespace == Namespace::Html && self.tag_name() == tag_name
+    }
+
+    pub fn data(&self) -> &ElementData {
+        &self.data
+    }
+
     pub fn set_attribute(&self, name: &str, value: &str) {
         if name == "id" {
             *self.id.borrow_mut() = Some(value.to_string());
@@ -102,6 +150,28 @@ impl Element {
         self.data.handle_attribute_change(name, value);
     }
 
+    /// Value of the attribute, including `id` and `class`
+    pub fn get_attribute(&self, name: &str) -> Option<String> {
+        match name {
+            "id" => self.id(),
+            "class" => {
+                let class_list = self.class_list.borrow();
+                (class_list.length() > 0).then(|| class_list.value())
+            }
+            _ => self.attributes.borrow().get(name).cloned(),
+        }
+    }
+
+    pub fn remove_attribute(&self, name: &str) {
+        match name {
+            "id" => *self.id.borrow_mut() = None,
+            "class" => *self.class_list.borrow_mut() = DOMTokenList::new(),
+            _ => {
+                self.attributes.borrow_mut().remove(name);
+            }
+        }
+    }
+
     pub fn attributes(&self) -> RefCell<AttributeMap> {
         self.attributes.clone()
     }
@@ -118,6 +188,45 @@ impl Element {
         self.id.borrow().clone()
     }
 
+    pub fn scroll_offset(&self) -> Point {
+        self.scroll_offset.borrow().clone()
+    }
+
+    pub fn set_scroll_offset(&self, offset: Point) {
+        *self.scroll_offset.borrow_mut() = offset;
+    }
+
+    /// Start time of the animation with the name. The animation starts at
+    /// `now` unless the element already runs it.
+    pub fn animation_start(&self, name: &str, now: f64) -> f64 {
+        let mut animation = self.animation.borrow_mut();
+        match &*animation {
+            Some((running, start)) if running == name => *start,
+            _ => {
+                *animation = Some((name.to_string(), now));
+                now
+            }
+        }
+    }
+
+    /// Forget the animation of the element, which starts over if it is
+    /// applied again
+    pub fn cancel_animation(&self) {
+        *self.animation.borrow_mut() = None;
+    }
+
+    pub fn shadow_root(&self) -> Option<NodePtr> {
+        self.shadow_root.borrow().clone()
+    }
+
+    pub(crate) fn set_shadow_root(&self, shadow_root: NodePtr) {
+        *self.shadow_root.borrow_mut() = Some(shadow_root);
+    }
+
+    pub(crate) fn take_shadow_root(&self) -> Option<NodePtr> {
+        self.shadow_root.borrow_mut().take()
+    }
+
     pub fn handle_on_inserted(&self, context: InsertContext) {
         self.data.handle_on_inserted(context);
     }
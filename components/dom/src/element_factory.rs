@@ -1,6 +1,6 @@
 use shared::tree_node::{TreeNode, WeakTreeNode};
 
-use crate::element::Element;
+use crate::element::{Element, Namespace};
 use crate::node::{Node, NodeData, NodePtr};
 
 use super::elements::*;
@@ -28,9 +28,34 @@ pub fn create_element(document: WeakTreeNode<Node>, tag_name: &str) -> NodePtr {
         "div" => Div > HTMLDivElement,
         "a" => Anchor > HTMLAnchorElement,
         "link" => Link > HTMLLinkElement,
-        "style" => Style > HTMLStyleElement
+        "style" => Style > HTMLStyleElement,
+        "form" => Form > HTMLFormElement,
+        "input" => Input > HTMLInputElement,
+        "iframe" => IFrame > HTMLIFrameElement,
+        "img" => Image > HTMLImageElement,
+        "select" => Select > HTMLSelectElement,
+        "textarea" => TextArea > HTMLTextAreaElement,
+        "video" => Video > HTMLVideoElement
     });
 
     node.set_document(document);
     NodePtr(TreeNode::new(node))
 }
+
+/// Create an element in a foreign namespace. Foreign elements have no
+/// behaviour of their own, so they keep the tag name as is.
+pub fn create_element_ns(
+    document: WeakTreeNode<Node>,
+    tag_name: &str,
+    namespace: Namespace,
+) -> NodePtr {
+    if namespace == Namespace::Html {
+        return create_element(document, tag_name);
+    }
+    let node = Node::new(NodeData::Element(Element::new_in_namespace(
+        ElementData::Unknown(HTMLUnknownElement::new(tag_name.to_string())),
+        namespace,
+    )));
+    node.set_document(document);
+    NodePtr(TreeNode::new(node))
+}
@@ -0,0 +1,22 @@
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+
+#[derive(Debug)]
+pub struct HTMLFormElement {}
+
+impl HTMLFormElement {
+    pub fn empty() -> Self {
+        Self {}
+    }
+}
+
+impl ElementHooks for HTMLFormElement {}
+
+impl NodeHooks for HTMLFormElement {}
+
+impl ElementMethods for HTMLFormElement {
+    fn tag_name(&self) -> String {
+        "form".to_string()
+    }
+}
@@ -0,0 +1,45 @@
+use std::cell::RefCell;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+use crate::node::NodePtr;
+
+#[derive(Debug)]
+pub struct HTMLIFrameElement {
+    /// The document of the nested browsing context, once it has loaded
+    content_document: RefCell<Option<NodePtr>>,
+}
+
+impl HTMLIFrameElement {
+    pub fn empty() -> Self {
+        Self {
+            content_document: RefCell::new(None),
+        }
+    }
+
+    pub fn content_document(&self) -> Option<NodePtr> {
+        self.content_document.borrow().clone()
+    }
+
+    pub fn set_content_document(&self, document: Option<NodePtr>) {
+        *self.content_document.borrow_mut() = document;
+    }
+}
+
+impl ElementHooks for HTMLIFrameElement {
+    fn on_attribute_change(&self, attr: &str, _: &str) {
+        // Navigating the frame replaces its document
+        if attr == "src" {
+            self.set_content_document(None);
+        }
+    }
+}
+
+impl NodeHooks for HTMLIFrameElement {}
+
+impl ElementMethods for HTMLIFrameElement {
+    fn tag_name(&self) -> String {
+        "iframe".to_string()
+    }
+}
@@ -0,0 +1,41 @@
+use std::cell::RefCell;
+
+use shared::image::ImageBitmap;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+
+#[derive(Debug)]
+pub struct HTMLImageElement {
+    /// The decoded image being shown. Images are loaded in the background
+    /// once their source is chosen, and the previous image is shown until
+    /// then.
+    current_image: RefCell<Option<ImageBitmap>>,
+}
+
+impl HTMLImageElement {
+    pub fn empty() -> Self {
+        Self {
+            current_image: RefCell::new(None),
+        }
+    }
+
+    pub fn current_image(&self) -> Option<ImageBitmap> {
+        self.current_image.borrow().clone()
+    }
+
+    pub fn set_current_image(&self, image: Option<ImageBitmap>) {
+        *self.current_image.borrow_mut() = image;
+    }
+}
+
+impl ElementHooks for HTMLImageElement {}
+
+impl NodeHooks for HTMLImageElement {}
+
+impl ElementMethods for HTMLImageElement {
+    fn tag_name(&self) -> String {
+        "img".to_string()
+    }
+}
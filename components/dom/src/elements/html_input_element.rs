@@ -0,0 +1,34 @@
+use std::cell::RefCell;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+
+#[derive(Debug)]
+pub struct HTMLInputElement {
+    /// The value set by the user. Until it is set, the value of the input is
+    /// its `value` attribute.
+    value: RefCell<Option<String>>,
+}
+
+impl HTMLInputElement {
+    pub fn empty() -> Self {
+        Self {
+            value: RefCell::new(None),
+        }
+    }
+
+    pub fn value(&self) -> &RefCell<Option<String>> {
+        &self.value
+    }
+}
+
+impl ElementHooks for HTMLInputElement {}
+
+impl NodeHooks for HTMLInputElement {}
+
+impl ElementMethods for HTMLInputElement {
+    fn tag_name(&self) -> String {
+        "input".to_string()
+    }
+}
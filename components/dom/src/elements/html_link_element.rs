@@ -3,13 +3,10 @@ use super::ElementMethods;
 use crate::node::InsertContext;
 use crate::node::NodeHooks;
 use crate::node::NodePtr;
-use loader::ResourceLoader;
-use shared::byte_string::ByteString;
+use crate::stylesheet_loader;
 use style_types::ContextualStyleSheet;
 use url::Url;
 
-use css::parser::Parser;
-use css::tokenizer::{token::Token, Tokenizer};
 use url::parser::URLParser;
 
 #[derive(Debug)]
@@ -21,25 +18,20 @@ impl HTMLLinkElement {
     }
 
     pub fn load_stylesheet(&self, url: &Url, document: NodePtr) {
-        log::info!("Loading stylesheet from: {}", url);
-
-        let response = ResourceLoader::current().load(url);
-        match response {
-            Ok(bytes) => {
-                let css = ByteString::new(&bytes);
-                let tokenizer = Tokenizer::new(css.chars());
-                let mut parser = Parser::<Token>::new(tokenizer.run());
-                let stylesheet = parser.parse_a_css_stylesheet();
+        if document.as_document().is_load_cancelled() {
+            log::info!("Loading cancelled, skipping stylesheet: {}", url);
+            return;
+        }
 
-                let stylesheet = ContextualStyleSheet::new(
-                    stylesheet,
-                    style_types::CascadeOrigin::Author,
-                    style_types::CSSLocation::External,
-                );
+        let document_url = document.as_document().base();
+        if let Some(stylesheet) = stylesheet_loader::load_stylesheet(url, document_url.as_ref()) {
+            let stylesheet = ContextualStyleSheet::new(
+                stylesheet,
+                style_types::CascadeOrigin::Author,
+                style_types::CSSLocation::External,
+            );
 
-                document.as_document().append_stylesheet(stylesheet);
-            }
-            Err(e) => log::error!("Unable to load CSS: {} ({})", e, url),
+            document.as_document().append_stylesheet(stylesheet);
         }
     }
 }
@@ -59,6 +51,10 @@ impl NodeHooks for HTMLLinkElement {
         match href_url {
             Some(url) => match rel_str.as_str() {
                 "stylesheet" => self.load_stylesheet(&url, document),
+                // The icon is loaded by the render engine once the page is loaded
+                rel if rel
+                    .split_ascii_whitespace()
+                    .any(|keyword| keyword.eq_ignore_ascii_case("icon")) => {}
                 _ => {
                     log::warn!("Unsupported link rel value: {}", rel_str);
                 }
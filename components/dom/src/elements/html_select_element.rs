@@ -0,0 +1,43 @@
+use std::cell::Cell;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::{ChildrenUpdateContext, NodeHooks};
+
+#[derive(Debug)]
+pub struct HTMLSelectElement {
+    /// Index of the option chosen by the user, which takes precedence over
+    /// the `selected` attributes of the options
+    chosen_index: Cell<Option<usize>>,
+}
+
+impl HTMLSelectElement {
+    pub fn empty() -> Self {
+        Self {
+            chosen_index: Cell::new(None),
+        }
+    }
+
+    pub fn chosen_index(&self) -> Option<usize> {
+        self.chosen_index.get()
+    }
+
+    pub fn set_chosen_index(&self, index: Option<usize>) {
+        self.chosen_index.set(index);
+    }
+}
+
+impl ElementHooks for HTMLSelectElement {}
+
+impl NodeHooks for HTMLSelectElement {
+    fn on_children_updated(&self, _: ChildrenUpdateContext) {
+        // The index refers to the previous list of options
+        self.set_chosen_index(None);
+    }
+}
+
+impl ElementMethods for HTMLSelectElement {
+    fn tag_name(&self) -> String {
+        "select".to_string()
+    }
+}
@@ -1,15 +1,14 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
-use css::parser::Parser;
-use css::tokenizer::token::Token;
-use css::tokenizer::Tokenizer;
 use style_types::ContextualStyleSheet;
 
 use super::ElementHooks;
 use super::ElementMethods;
+use crate::flat_tree;
 use crate::node::ChildrenUpdateContext;
 use crate::node::NodeHooks;
+use crate::stylesheet_loader;
 
 #[derive(Debug)]
 pub struct HTMLStyleElement {
@@ -30,9 +29,7 @@ impl NodeHooks for HTMLStyleElement {
     fn on_children_updated(&self, context: ChildrenUpdateContext) {
         let document = context.document.as_document();
         let css = context.current_node.descendant_text_content();
-        let tokenizer = Tokenizer::new(css.chars());
-        let mut parser = Parser::<Token>::new(tokenizer.run());
-        let stylesheet = parser.parse_a_css_stylesheet();
+        let stylesheet = stylesheet_loader::parse_stylesheet(&css, document.base());
 
         let stylesheet = ContextualStyleSheet::new(
             stylesheet,
@@ -40,11 +37,20 @@ impl NodeHooks for HTMLStyleElement {
             style_types::CSSLocation::Embedded,
         );
 
+        // Styles of a shadow tree are scoped to the shadow tree
+        let shadow_root = flat_tree::containing_shadow_root(&context.current_node);
+
         if let Some(sheet) = &*self.stylesheet.borrow() {
             document.remove_stylesheet(sheet);
+            if let Some(shadow_root) = &shadow_root {
+                shadow_root.as_shadow_root().remove_stylesheet(sheet);
+            }
         }
 
-        let stylesheet_ptr = document.append_stylesheet(stylesheet);
+        let stylesheet_ptr = match &shadow_root {
+            Some(shadow_root) => shadow_root.as_shadow_root().append_stylesheet(stylesheet),
+            None => document.append_stylesheet(stylesheet),
+        };
         self.stylesheet.replace(Some(stylesheet_ptr));
     }
 }
@@ -0,0 +1,35 @@
+use std::cell::RefCell;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+use crate::text_editor::TextEditor;
+
+#[derive(Debug)]
+pub struct HTMLTextAreaElement {
+    /// The text edited by the user. Until the first edit, the value of the
+    /// textarea is its text content.
+    editor: RefCell<Option<TextEditor>>,
+}
+
+impl HTMLTextAreaElement {
+    pub fn empty() -> Self {
+        Self {
+            editor: RefCell::new(None),
+        }
+    }
+
+    pub fn editor(&self) -> &RefCell<Option<TextEditor>> {
+        &self.editor
+    }
+}
+
+impl ElementHooks for HTMLTextAreaElement {}
+
+impl NodeHooks for HTMLTextAreaElement {}
+
+impl ElementMethods for HTMLTextAreaElement {
+    fn tag_name(&self) -> String {
+        "textarea".to_string()
+    }
+}
@@ -0,0 +1,47 @@
+use std::cell::RefCell;
+
+use shared::image::ImageBitmap;
+
+use super::ElementHooks;
+use super::ElementMethods;
+use crate::node::NodeHooks;
+
+#[derive(Debug)]
+pub struct HTMLVideoElement {
+    /// The frame being shown. Videos are decoded by the embedder, which
+    /// supplies their frames.
+    current_frame: RefCell<Option<ImageBitmap>>,
+}
+
+impl HTMLVideoElement {
+    pub fn empty() -> Self {
+        Self {
+            current_frame: RefCell::new(None),
+        }
+    }
+
+    pub fn current_frame(&self) -> Option<ImageBitmap> {
+        self.current_frame.borrow().clone()
+    }
+
+    pub fn set_current_frame(&self, frame: Option<ImageBitmap>) {
+        *self.current_frame.borrow_mut() = frame;
+    }
+}
+
+impl ElementHooks for HTMLVideoElement {
+    fn on_attribute_change(&self, attr: &str, _: &str) {
+        // Frames of the previous video aren't shown for another one
+        if attr == "src" {
+            self.set_current_frame(None);
+        }
+    }
+}
+
+impl NodeHooks for HTMLVideoElement {}
+
+impl ElementMethods for HTMLVideoElement {
+    fn tag_name(&self) -> String {
+        "video".to_string()
+    }
+}
@@ -6,22 +6,36 @@ use enum_dispatch::enum_dispatch;
 mod html_anchor_element;
 mod html_body_element;
 mod html_div_element;
+mod html_form_element;
 mod html_head_element;
 mod html_html_element;
+mod html_iframe_element;
+mod html_image_element;
+mod html_input_element;
 mod html_link_element;
+mod html_select_element;
 mod html_style_element;
+mod html_textarea_element;
 mod html_title_element;
 mod html_unknown_element;
+mod html_video_element;
 
 pub use html_anchor_element::*;
 pub use html_body_element::*;
 pub use html_div_element::*;
+pub use html_form_element::*;
 pub use html_head_element::*;
 pub use html_html_element::*;
+pub use html_iframe_element::*;
+pub use html_image_element::*;
+pub use html_input_element::*;
 pub use html_link_element::*;
+pub use html_select_element::*;
 pub use html_style_element::*;
+pub use html_textarea_element::*;
 pub use html_title_element::*;
 pub use html_unknown_element::*;
+pub use html_video_element::*;
 
 #[enum_dispatch(ElementHooks, NodeHooks, ElementMethods)]
 #[derive(Debug)]
@@ -35,6 +49,13 @@ pub enum ElementData {
     Unknown(HTMLUnknownElement),
     Link(HTMLLinkElement),
     Style(HTMLStyleElement),
+    Form(HTMLFormElement),
+    Input(HTMLInputElement),
+    IFrame(HTMLIFrameElement),
+    Image(HTMLImageElement),
+    Select(HTMLSelectElement),
+    TextArea(HTMLTextAreaElement),
+    Video(HTMLVideoElement),
 }
 
 #[enum_dispatch]
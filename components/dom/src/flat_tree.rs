@@ -0,0 +1,204 @@
+//! The flat tree is the tree that is rendered: shadow trees take the place
+//! of the children of their hosts and the children of hosts are rendered in
+//! the slots they are assigned to.
+//! https://drafts.csswg.org/css-scoping/#flat-tree
+
+use std::rc::Rc;
+
+use crate::node::NodePtr;
+
+/// Children of the node in the flat tree
+pub fn children(node: &NodePtr) -> Vec<NodePtr> {
+    if let Some(shadow_root) = shadow_root_of(node) {
+        return child_nodes(&shadow_root);
+    }
+    if is_slot(node) && containing_shadow_root(node).is_some() {
+        let assigned_nodes = assigned_nodes(node);
+        if !assigned_nodes.is_empty() {
+            return assigned_nodes;
+        }
+        // Slots without assigned nodes render their fallback content
+    }
+    child_nodes(node)
+}
+
+/// Parent of the node in the flat tree. Children of a shadow host that are
+/// not assigned to a slot are not rendered and have no parent.
+pub fn parent(node: &NodePtr) -> Option<NodePtr> {
+    let parent = NodePtr(node.parent()?);
+    if shadow_root_of(&parent).is_some() {
+        return assigned_slot(node);
+    }
+    match parent.as_shadow_root_opt() {
+        Some(shadow_root) => shadow_root.host().map(NodePtr),
+        None => Some(parent),
+    }
+}
+
+/// The slot that a child of a shadow host is rendered in
+/// https://dom.spec.whatwg.org/#find-a-slot
+pub fn assigned_slot(node: &NodePtr) -> Option<NodePtr> {
+    let host = NodePtr(node.parent()?);
+    let shadow_root = shadow_root_of(&host)?;
+    let name = slot_name_of_slottable(node)?;
+    find_slot(&shadow_root, &name)
+}
+
+/// Children of the shadow host that are assigned to the slot, in tree order
+/// https://dom.spec.whatwg.org/#find-slotables
+pub fn assigned_nodes(slot: &NodePtr) -> Vec<NodePtr> {
+    let host = match containing_shadow_root(slot)
+        .and_then(|shadow_root| shadow_root.as_shadow_root().host())
+    {
+        Some(host) => NodePtr(host),
+        None => return Vec::new(),
+    };
+
+    child_nodes(&host)
+        .into_iter()
+        .filter(|child| matches!(assigned_slot(child), Some(s) if Rc::ptr_eq(&s, slot)))
+        .collect()
+}
+
+/// The shadow root of the tree that contains the node, if the node is in a
+/// shadow tree
+pub fn containing_shadow_root(node: &NodePtr) -> Option<NodePtr> {
+    let mut current = node.clone();
+    while let Some(parent) = current.parent() {
+        current = NodePtr(parent);
+    }
+    if current.is_shadow_root() {
+        Some(current)
+    } else {
+        None
+    }
+}
+
+fn shadow_root_of(node: &NodePtr) -> Option<NodePtr> {
+    node.as_element_opt()?.shadow_root()
+}
+
+fn is_slot(node: &NodePtr) -> bool {
+    match node.as_element_opt() {
+        Some(element) => element.tag_name() == "slot",
+        None => false,
+    }
+}
+
+/// Name of the slot that the node is assigned to. Only elements and texts
+/// can be assigned to slots.
+fn slot_name_of_slottable(node: &NodePtr) -> Option<String> {
+    if let Some(element) = node.as_element_opt() {
+        return Some(element.attributes().borrow().get_str("slot"));
+    }
+    if node.is_text() {
+        return Some(String::new());
+    }
+    None
+}
+
+/// First slot of the shadow tree with the name, in tree order
+fn find_slot(node: &NodePtr, name: &str) -> Option<NodePtr> {
+    for child in child_nodes(node) {
+        if is_slot(&child) && child.as_element().attributes().borrow().get_str("name") == name {
+            return Some(child);
+        }
+        if let Some(slot) = find_slot(&child, name) {
+            return Some(slot);
+        }
+    }
+    None
+}
+
+fn child_nodes(node: &NodePtr) -> Vec<NodePtr> {
+    node.iterate_children().map(NodePtr).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    fn document() -> NodePtr {
+        NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )))
+    }
+
+    fn element(document: &NodePtr, tag_name: &str, attrs: &[(&str, &str)]) -> NodePtr {
+        let element = create_element(WeakTreeNode::from(&document.0), tag_name);
+        for (name, value) in attrs {
+            element.as_element().set_attribute(name, value);
+        }
+        element
+    }
+
+    fn text(document: &NodePtr, data: &str) -> NodePtr {
+        let text = Node::new(NodeData::Text(Text::new(data.to_string())));
+        text.set_document(WeakTreeNode::from(&document.0));
+        NodePtr(TreeNode::new(text))
+    }
+
+    fn is_same(a: &NodePtr, b: &NodePtr) -> bool {
+        Rc::ptr_eq(a, b)
+    }
+
+    #[test]
+    fn test_slot_assignment() {
+        let document = document();
+        let host = element(&document, "div", &[]);
+        let header = element(&document, "h1", &[("slot", "header")]);
+        let content = text(&document, "content");
+        let unassigned = element(&document, "p", &[("slot", "missing")]);
+        for child in [&header, &content, &unassigned] {
+            host.append_child(child.0.clone());
+        }
+
+        let shadow_root = host.attach_shadow().unwrap();
+        assert!(host.attach_shadow().is_none());
+
+        let header_slot = element(&document, "slot", &[("name", "header")]);
+        let default_slot = element(&document, "slot", &[]);
+        let fallback_slot = element(&document, "slot", &[("name", "footer")]);
+        let fallback = text(&document, "fallback");
+        fallback_slot.append_child(fallback.0.clone());
+        for slot in [&header_slot, &default_slot, &fallback_slot] {
+            shadow_root.append_child(slot.0.clone());
+        }
+
+        let host_children = children(&host);
+        assert_eq!(host_children.len(), 3);
+        assert!(is_same(&host_children[0], &header_slot));
+
+        assert!(is_same(&children(&header_slot)[0], &header));
+        assert!(is_same(&children(&default_slot)[0], &content));
+        assert!(is_same(&children(&fallback_slot)[0], &fallback));
+
+        assert!(is_same(&parent(&header).unwrap(), &header_slot));
+        assert!(is_same(&parent(&header_slot).unwrap(), &host));
+        assert!(parent(&unassigned).is_none());
+    }
+
+    #[test]
+    fn test_shadow_tree_styles_are_scoped() {
+        let document = document();
+        let host = element(&document, "div", &[]);
+        document.append_child(host.0.clone());
+        let shadow_root = host.attach_shadow().unwrap();
+
+        let style = element(&document, "style", &[]);
+        shadow_root.append_child(style.0.clone());
+        style.append_child(text(&document, "p { color: red; }").0);
+
+        assert!(document.as_document().style_rules().is_empty());
+        let shadow_rules = shadow_root
+            .as_shadow_root()
+            .style_rules(document.as_document());
+        assert_eq!(shadow_rules.len(), 1);
+    }
+}
@@ -0,0 +1,620 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use url::{parser::URLParser, Url};
+
+use crate::node::NodePtr;
+use crate::{input, select, textarea};
+
+/// Source of unique multipart boundaries
+static NEXT_BOUNDARY: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormMethod {
+    Get,
+    Post,
+}
+
+/// A request to navigate to, built from a form and its controls
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-submission-algorithm
+#[derive(Debug, Clone)]
+pub struct FormSubmission {
+    pub method: FormMethod,
+    pub url: Url,
+    /// Content type and body of `POST` requests
+    pub body: Option<(String, Vec<u8>)>,
+}
+
+/// Form that a control belongs to, either the form named by its `form`
+/// attribute or its nearest ancestor form
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#form-owner
+pub fn form_owner(control: &NodePtr) -> Option<NodePtr> {
+    let element = control.as_element_opt()?;
+    if let Some(form_id) = element.attributes().borrow().get("form").cloned() {
+        let document = NodePtr(control.owner_document()?);
+        return find_element(&document, &|node| {
+            let element = node.as_element();
+            element.tag_name() == "form" && element.id().as_ref() == Some(&form_id)
+        });
+    }
+
+    let mut ancestor = control.parent();
+    while let Some(node) = ancestor {
+        let node = NodePtr(node);
+        if matches!(node.as_element_opt(), Some(element) if element.tag_name() == "form") {
+            return Some(node);
+        }
+        ancestor = node.parent();
+    }
+    None
+}
+
+/// Whether the element submits its form when activated
+/// https://html.spec.whatwg.org/multipage/forms.html#concept-submit-button
+pub fn is_submit_button(node: &NodePtr) -> bool {
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return false,
+    };
+    let input_type = element.attributes().borrow().get_str("type").to_lowercase();
+    match element.tag_name().as_str() {
+        "input" => input_type == "submit" || input_type == "image",
+        "button" => input_type.is_empty() || input_type == "submit",
+        _ => false,
+    }
+}
+
+/// First submit button of the form, which is activated by implicit
+/// submission such as pressing Enter in a text field
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#default-button
+pub fn default_button(form: &NodePtr) -> Option<NodePtr> {
+    submittable_elements(form)
+        .into_iter()
+        .find(is_submit_button)
+}
+
+/// Input types whose presence prevents implicit submission of a form
+/// without a submit button, when there is more than one of them
+const IMPLICIT_SUBMISSION_BLOCKING_TYPES: [&str; 13] = [
+    // Inputs without a type are text fields
+    "",
+    "text",
+    "search",
+    "url",
+    "tel",
+    "email",
+    "password",
+    "date",
+    "month",
+    "week",
+    "time",
+    "datetime-local",
+    "number",
+];
+
+/// Form submitted by pressing Enter in the control, with its submitter.
+/// Returns `None` if the control is not a text field of a form, or if the
+/// form can't be submitted implicitly.
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#implicit-submission
+pub fn implicit_submission(control: &NodePtr) -> Option<(NodePtr, Option<NodePtr>)> {
+    if !blocks_implicit_submission(control) {
+        return None;
+    }
+    let form = form_owner(control)?;
+
+    match default_button(&form) {
+        Some(button) if is_disabled(&button) => None,
+        Some(button) => Some((form, Some(button))),
+        None => {
+            let blocking_fields = submittable_elements(&form)
+                .iter()
+                .filter(|control| blocks_implicit_submission(control))
+                .count();
+            (blocking_fields <= 1).then_some((form, None))
+        }
+    }
+}
+
+fn blocks_implicit_submission(control: &NodePtr) -> bool {
+    match control.as_element_opt() {
+        Some(element) if element.tag_name() == "input" => {
+            let input_type = element.attributes().borrow().get_str("type").to_lowercase();
+            IMPLICIT_SUBMISSION_BLOCKING_TYPES.contains(&input_type.as_str())
+        }
+        _ => false,
+    }
+}
+
+/// Whether the controls of the form should be validated before submitting
+/// it with `submitter`
+pub fn should_validate(form: &NodePtr, submitter: Option<&NodePtr>) -> bool {
+    let no_validate = |node: &NodePtr, attribute| {
+        node.as_element()
+            .attributes()
+            .borrow()
+            .contains_key(attribute)
+    };
+    !no_validate(form, "novalidate")
+        && !submitter.is_some_and(|submitter| no_validate(submitter, "formnovalidate"))
+}
+
+/// Build the request submitting the form. Returns `None` if the action URL
+/// is invalid or uses a scheme forms can't be submitted to.
+pub fn form_submission(form: &NodePtr, submitter: Option<&NodePtr>) -> Option<FormSubmission> {
+    // Attributes of the submitter override the ones of the form
+    let attribute = |form_attribute: &str, submitter_attribute: &str| {
+        submitter
+            .and_then(|submitter| {
+                submitter
+                    .as_element()
+                    .attributes()
+                    .borrow()
+                    .get(submitter_attribute)
+                    .cloned()
+            })
+            .or_else(|| {
+                form.as_element()
+                    .attributes()
+                    .borrow()
+                    .get(form_attribute)
+                    .cloned()
+            })
+            .unwrap_or_default()
+    };
+
+    let base = form
+        .owner_document()
+        .and_then(|document| document.as_document().base());
+    let action = attribute("action", "formaction");
+    let mut url = if action.trim().is_empty() {
+        base?
+    } else {
+        URLParser::parse(&action, base)?
+    };
+    if url.scheme != "http" && url.scheme != "https" && url.scheme != "file" {
+        log::warn!("Unsupported form action: {}", url);
+        return None;
+    }
+
+    let entries = form_entries(form, submitter);
+    let submission = match attribute("method", "formmethod").to_lowercase().as_str() {
+        "post" => {
+            let body = match attribute("enctype", "formenctype").to_lowercase().as_str() {
+                "multipart/form-data" => {
+                    let boundary = multipart_boundary();
+                    (
+                        format!("multipart/form-data; boundary={}", boundary),
+                        multipart_encode(&entries, &boundary),
+                    )
+                }
+                "text/plain" => ("text/plain".to_string(), text_plain_encode(&entries)),
+                _ => (
+                    "application/x-www-form-urlencoded".to_string(),
+                    urlencode(&entries).into_bytes(),
+                ),
+            };
+            FormSubmission {
+                method: FormMethod::Post,
+                url,
+                body: Some(body),
+            }
+        }
+        _ => {
+            url.query = Some(urlencode(&entries));
+            url.fragment = None;
+            FormSubmission {
+                method: FormMethod::Get,
+                url,
+                body: None,
+            }
+        }
+    };
+    Some(submission)
+}
+
+/// Names and values submitted by the controls of the form, in tree order
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#constructing-the-form-data-set
+pub fn form_entries(form: &NodePtr, submitter: Option<&NodePtr>) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+
+    for control in submittable_elements(form) {
+        if is_disabled(&control) {
+            continue;
+        }
+        let element = control.as_element();
+        let attributes = element.attributes();
+        let attributes = attributes.borrow();
+        let name = attributes.get_str("name");
+        let input_type = attributes.get_str("type").to_lowercase();
+        let is_submitter = submitter.is_some_and(|submitter| submitter.id() == control.id());
+
+        match element.tag_name().as_str() {
+            "button" if !is_submitter => continue,
+            "input" => match input_type.as_str() {
+                "submit" | "image" if !is_submitter => continue,
+                "button" | "reset" => continue,
+                "checkbox" | "radio" if !attributes.contains_key("checked") => continue,
+                // File uploads aren't supported
+                "file" => continue,
+                _ => {}
+            },
+            _ => {}
+        }
+
+        if input_type == "image" && element.tag_name() == "input" {
+            // The position of the click in the image isn't known
+            let prefix = if name.is_empty() {
+                String::new()
+            } else {
+                format!("{}.", name)
+            };
+            entries.push((format!("{}x", prefix), "0".to_string()));
+            entries.push((format!("{}y", prefix), "0".to_string()));
+            continue;
+        }
+        if name.is_empty() {
+            continue;
+        }
+
+        match element.tag_name().as_str() {
+            "select" => {
+                for option in select::selected_options(&control) {
+                    entries.push((name.clone(), select::option_value(&option)));
+                }
+            }
+            "textarea" => entries.push((name, textarea::value(&control))),
+            "input" if input_type == "hidden" && name.eq_ignore_ascii_case("_charset_") => {
+                entries.push((name, "UTF-8".to_string()))
+            }
+            "input" if matches!(input_type.as_str(), "checkbox" | "radio") => {
+                let value = attributes
+                    .get("value")
+                    .cloned()
+                    .unwrap_or_else(|| "on".to_string());
+                entries.push((name, value));
+            }
+            "input" => entries.push((name, input::value(&control))),
+            _ => entries.push((name, attributes.get_str("value"))),
+        }
+    }
+
+    // Line breaks are normalized to CRLF
+    entries
+        .into_iter()
+        .map(|(name, value)| (normalize_newlines(&name), normalize_newlines(&value)))
+        .collect()
+}
+
+/// Encode entries as `application/x-www-form-urlencoded`
+/// https://url.spec.whatwg.org/#concept-urlencoded-serializer
+pub fn urlencode(entries: &[(String, String)]) -> String {
+    let encode = |value: &str| {
+        let mut result = String::new();
+        for byte in value.bytes() {
+            match byte {
+                b'*' | b'-' | b'.' | b'_' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' => {
+                    result.push(byte as char)
+                }
+                b' ' => result.push('+'),
+                _ => result.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        result
+    };
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}", encode(name), encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Encode entries as `multipart/form-data`
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#multipart-form-data
+pub fn multipart_encode(entries: &[(String, String)], boundary: &str) -> Vec<u8> {
+    let escape = |name: &str| {
+        name.replace('\n', "%0A")
+            .replace('\r', "%0D")
+            .replace('"', "%22")
+    };
+    let mut body = String::new();
+    for (name, value) in entries {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary,
+            escape(name),
+            value
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    body.into_bytes()
+}
+
+fn text_plain_encode(entries: &[(String, String)]) -> Vec<u8> {
+    entries
+        .iter()
+        .map(|(name, value)| format!("{}={}\r\n", name, value))
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn multipart_boundary() -> String {
+    let seed = boundary_seed();
+    let count = NEXT_BOUNDARY.fetch_add(1, Ordering::Relaxed);
+    format!("----MoonFormBoundary{:08x}{:08x}", seed, count)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn boundary_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos() as u64
+}
+
+/// There is no clock in the browser without JavaScript, boundaries are
+/// told apart by their count alone
+#[cfg(target_arch = "wasm32")]
+fn boundary_seed() -> u64 {
+    0
+}
+
+fn normalize_newlines(value: &str) -> String {
+    value
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace('\n', "\r\n")
+}
+
+/// Controls owned by the form that can submit a value, in tree order
+fn submittable_elements(form: &NodePtr) -> Vec<NodePtr> {
+    let document = match form.owner_document() {
+        Some(document) => NodePtr(document),
+        None => return Vec::new(),
+    };
+    let mut controls = Vec::new();
+    collect_elements(&document, &mut controls, &|node| {
+        let element = node.as_element();
+        matches!(
+            element.tag_name().as_str(),
+            "button" | "input" | "select" | "textarea"
+        ) && form_owner(node).is_some_and(|owner| owner.id() == form.id())
+    });
+    controls
+}
+
+/// Disabled controls, including controls inside a disabled fieldset
+/// https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#concept-fe-disabled
+pub fn is_disabled(control: &NodePtr) -> bool {
+    if control
+        .as_element()
+        .attributes()
+        .borrow()
+        .contains_key("disabled")
+    {
+        return true;
+    }
+
+    let mut child = control.clone();
+    while let Some(parent) = child.parent() {
+        let parent = NodePtr(parent);
+        if let Some(element) = parent.as_element_opt() {
+            let is_disabled_fieldset = element.tag_name() == "fieldset"
+                && element.attributes().borrow().contains_key("disabled");
+            // Controls in the first legend of a disabled fieldset are enabled
+            if is_disabled_fieldset && !is_in_first_legend(&parent, &child) {
+                return true;
+            }
+        }
+        child = parent;
+    }
+    false
+}
+
+fn is_in_first_legend(fieldset: &NodePtr, child: &NodePtr) -> bool {
+    let mut first_legend = None;
+    fieldset.for_each_child(|node| {
+        let node = NodePtr(node);
+        let is_legend =
+            matches!(node.as_element_opt(), Some(element) if element.tag_name() == "legend");
+        if is_legend && first_legend.is_none() {
+            first_legend = Some(node.id());
+        }
+    });
+    first_legend == Some(child.id())
+}
+
+fn collect_elements(node: &NodePtr, result: &mut Vec<NodePtr>, filter: &dyn Fn(&NodePtr) -> bool) {
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        if child.is_element() && filter(&child) {
+            result.push(child.clone());
+        }
+        collect_elements(&child, result, filter);
+    });
+}
+
+fn find_element(node: &NodePtr, filter: &dyn Fn(&NodePtr) -> bool) -> Option<NodePtr> {
+    let mut result = Vec::new();
+    collect_elements(node, &mut result, filter);
+    result.into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    fn element(
+        parent: &NodePtr,
+        tag_name: &str,
+        attrs: &[(&str, &str)],
+        text: Option<&str>,
+    ) -> NodePtr {
+        let document =
+            WeakTreeNode::from(&parent.owner_document().unwrap_or_else(|| parent.0.clone()));
+        let element = create_element(document.clone(), tag_name);
+        for (name, value) in attrs {
+            element.as_element().set_attribute(name, value);
+        }
+        if let Some(text) = text {
+            let text = Node::new(NodeData::Text(Text::new(text.to_string())));
+            text.set_document(document);
+            element.append_child(TreeNode::new(text));
+        }
+        parent.append_child(element.0.clone());
+        element
+    }
+
+    /// The document is returned to keep it alive
+    fn search_form() -> (NodePtr, NodePtr, NodePtr) {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        document
+            .as_document()
+            .set_base(URLParser::parse("http://example.com/index.html", None));
+
+        let form = element(
+            &document,
+            "form",
+            &[("id", "search"), ("action", "/search")],
+            None,
+        );
+        element(
+            &form,
+            "input",
+            &[("name", "q"), ("value", "moon browser")],
+            None,
+        );
+        element(
+            &form,
+            "input",
+            &[("type", "checkbox"), ("name", "safe"), ("checked", "")],
+            None,
+        );
+        element(
+            &form,
+            "input",
+            &[("type", "checkbox"), ("name", "images")],
+            None,
+        );
+        element(
+            &form,
+            "input",
+            &[("name", "page"), ("value", "2"), ("disabled", "")],
+            None,
+        );
+        let fieldset = element(&form, "fieldset", &[("disabled", "")], None);
+        element(
+            &fieldset,
+            "input",
+            &[("name", "region"), ("value", "eu")],
+            None,
+        );
+        let select = element(&form, "select", &[("name", "lang")], None);
+        element(&select, "option", &[], Some("en"));
+        element(&select, "option", &[("selected", "")], Some(" French\n "));
+        element(&form, "textarea", &[("name", "note")], Some("a\nb"));
+        let submit = element(
+            &form,
+            "input",
+            &[("type", "submit"), ("name", "go"), ("value", "Go")],
+            None,
+        );
+        element(&form, "button", &[("name", "other")], Some("Other"));
+        element(
+            &document,
+            "input",
+            &[("name", "outside"), ("value", "1"), ("form", "search")],
+            None,
+        );
+        (document, form, submit)
+    }
+
+    #[test]
+    fn test_form_entries() {
+        let (_document, form, submit) = search_form();
+        let entries = form_entries(&form, Some(&submit));
+        let expected = [
+            ("q", "moon browser"),
+            ("safe", "on"),
+            ("lang", "French"),
+            ("note", "a\r\nb"),
+            ("go", "Go"),
+            ("outside", "1"),
+        ];
+        assert_eq!(
+            entries,
+            expected
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        );
+        assert!(is_submit_button(&submit));
+        assert_eq!(
+            default_button(&form).map(|button| button.id()),
+            Some(submit.id())
+        );
+    }
+
+    #[test]
+    fn test_form_submission() {
+        let (_document, form, submit) = search_form();
+        let submission = form_submission(&form, None).unwrap();
+        assert_eq!(submission.method, FormMethod::Get);
+        assert_eq!(
+            submission.url.as_str(),
+            "http://example.com/search?q=moon+browser&safe=on&lang=French&note=a%0D%0Ab&outside=1"
+        );
+
+        form.as_element().set_attribute("method", "POST");
+        submit
+            .as_element()
+            .set_attribute("formenctype", "multipart/form-data");
+        let submission = form_submission(&form, Some(&submit)).unwrap();
+        assert_eq!(submission.method, FormMethod::Post);
+        let (content_type, body) = submission.body.unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"q\"\r\n\r\nmoon browser\r\n",
+            boundary
+        )));
+        assert!(body.ends_with(&format!(
+            "name=\"outside\"\r\n\r\n1\r\n--{}--\r\n",
+            boundary
+        )));
+    }
+
+    #[test]
+    fn test_implicit_submission() {
+        let (document, form, submit) = search_form();
+        let field = element(&form, "input", &[("type", "email")], None);
+        let checkbox = element(&form, "input", &[("type", "checkbox")], None);
+        let (owner, submitter) = implicit_submission(&field).unwrap();
+        assert_eq!(owner.id(), form.id());
+        assert_eq!(submitter.map(|button| button.id()), Some(submit.id()));
+        assert!(implicit_submission(&checkbox).is_none());
+
+        // A form without a submit button is only submitted by its single field
+        let form = element(&document, "form", &[], None);
+        let field = element(&form, "input", &[], None);
+        assert!(matches!(implicit_submission(&field), Some((_, None))));
+        element(&form, "input", &[], None);
+        assert!(implicit_submission(&field).is_none());
+    }
+
+    #[test]
+    fn test_urlencode() {
+        let entries = vec![("a b".to_string(), "é&=*".to_string())];
+        assert_eq!(urlencode(&entries), "a+b=%C3%A9%26%3D*");
+    }
+}
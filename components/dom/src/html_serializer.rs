@@ -0,0 +1,186 @@
+//! Serialization of nodes back to HTML markup, like `innerHTML` and
+//! `outerHTML`. Attributes are written in the order of their names, after
+//! `id` and `class`, since elements don't keep the order they were set in.
+//! https://html.spec.whatwg.org/multipage/parsing.html#serialising-html-fragments
+
+use crate::element::Namespace;
+use crate::node::NodePtr;
+
+/// Elements without content or end tag
+/// https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+const VOID_ELEMENTS: [&str; 18] = [
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose text is written as it is, without escaping
+const RAW_TEXT_ELEMENTS: [&str; 7] = [
+    "style",
+    "script",
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "plaintext",
+];
+
+/// Markup of the children of the node. The doctype of a document comes
+/// before its children.
+pub fn serialize_children(node: &NodePtr) -> String {
+    let mut output = String::new();
+    if let Some(name) = node
+        .as_document_opt()
+        .and_then(|document| document.doctype_name())
+    {
+        output.push_str(&format!("<!DOCTYPE {}>", name));
+    }
+    write_children(node, &mut output);
+    output
+}
+
+/// Markup of the node with its children
+pub fn serialize_node(node: &NodePtr) -> String {
+    if node.is_document() || node.is_shadow_root() {
+        return serialize_children(node);
+    }
+    let mut output = String::new();
+    let is_raw_text = node
+        .parent()
+        .map(NodePtr)
+        .is_some_and(|parent| is_raw_text_element(&parent));
+    write_node(node, is_raw_text, &mut output);
+    output
+}
+
+fn write_children(node: &NodePtr, output: &mut String) {
+    let is_raw_text = node.is_element() && is_raw_text_element(node);
+    node.for_each_child(|child| write_node(&NodePtr(child), is_raw_text, output));
+}
+
+/// Write the node, escaping its text unless it is the child of a raw text
+/// element
+fn write_node(node: &NodePtr, is_raw_text: bool, output: &mut String) {
+    if let Some(text) = node.as_text_opt() {
+        let data = text.get_data();
+        match is_raw_text {
+            true => output.push_str(&data),
+            false => output.push_str(&escape(&data, false)),
+        }
+    } else if let Some(comment) = node.as_comment_opt() {
+        output.push_str(&format!("<!--{}-->", comment.get_data()));
+    } else if let Some(element) = node.as_element_opt() {
+        let tag_name = element.tag_name();
+        output.push('<');
+        output.push_str(&tag_name);
+        for (name, value) in attributes_of(node) {
+            output.push_str(&format!(" {}=\"{}\"", name, escape(&value, true)));
+        }
+        output.push('>');
+        if element.namespace() == Namespace::Html && VOID_ELEMENTS.contains(&tag_name.as_str()) {
+            return;
+        }
+        write_children(node, output);
+        output.push_str(&format!("</{}>", tag_name));
+    }
+}
+
+/// Attributes of the element, `id` and `class` first and the others in the
+/// order of their names
+fn attributes_of(element: &NodePtr) -> Vec<(String, String)> {
+    let element = element.as_element();
+    let mut attributes: Vec<(String, String)> = element
+        .attributes()
+        .borrow()
+        .iter()
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    attributes.sort();
+    for name in ["class", "id"] {
+        if let Some(value) = element.get_attribute(name) {
+            attributes.insert(0, (name.to_string(), value));
+        }
+    }
+    attributes
+}
+
+fn is_raw_text_element(element: &NodePtr) -> bool {
+    let element = element.as_element();
+    element.namespace() == Namespace::Html
+        && RAW_TEXT_ELEMENTS.contains(&element.tag_name().as_str())
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#escapingString
+fn escape(text: &str, is_attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '"' if is_attribute => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::comment::Comment;
+    use crate::create_element;
+    use crate::document::{Document, DocumentType};
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    #[test]
+    fn test_serialize_html() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        document
+            .as_document()
+            .set_doctype(DocumentType::new("html".to_string(), None, None));
+        let element = |parent: &NodePtr, tag_name: &str| {
+            let element = create_element(WeakTreeNode::from(&document.0), tag_name);
+            parent.append_child(element.0.clone());
+            element
+        };
+        let append = |parent: &NodePtr, data: NodeData| {
+            let node = TreeNode::new(Node::new(data));
+            node.set_document(WeakTreeNode::from(&document.0));
+            parent.append_child(node);
+        };
+        let text = |data: &str| NodeData::Text(Text::new(data.to_string()));
+
+        let body = element(&document, "body");
+        let div = element(&body, "div");
+        div.as_element().set_attribute("title", "\"a\" & <b>");
+        div.as_element().set_attribute("id", "main");
+        div.as_element().set_attribute("class", "x y");
+        div.as_element().set_attribute("data-a", "1");
+        append(&div, text("1 < 2 &\u{a0}3"));
+        element(&div, "br");
+        let input = element(&div, "input");
+        input.as_element().set_attribute("disabled", "");
+        append(&div, NodeData::Comment(Comment::new(" note ".to_string())));
+        let script = element(&body, "script");
+        append(&script, text("if (a < b && c) {}"));
+
+        assert_eq!(
+            div.outer_html(),
+            "<div id=\"main\" class=\"x y\" data-a=\"1\" title=\"&quot;a&quot; &amp; &lt;b&gt;\">\
+             1 &lt; 2 &amp;&nbsp;3<br><input disabled=\"\"><!-- note --></div>"
+        );
+        assert_eq!(script.inner_html(), "if (a < b && c) {}");
+        assert!(document
+            .inner_html()
+            .starts_with("<!DOCTYPE html><body><div id"));
+        assert!(body
+            .inner_html()
+            .ends_with("<script>if (a < b && c) {}</script>"));
+    }
+}
@@ -0,0 +1,391 @@
+//! Text of a node as it is rendered, like `innerText`: hidden content is left
+//! out, collapsible white space is collapsed and blocks are on lines of their
+//! own. Nodes that haven't been styled are read with the default styles of
+//! HTML, so that documents can be serialized without laying them out.
+//! https://html.spec.whatwg.org/multipage/dom.html#the-innertext-idl-attribute
+
+use style_types::property::Property;
+use style_types::value::Value;
+use style_types::values::display::{Display, DisplayBox, OuterDisplayType};
+use style_types::values::prelude::{Visibility, WhiteSpace};
+
+use crate::node::NodePtr;
+
+/// Elements that aren't rendered with the default styles of HTML
+const HIDDEN_ELEMENTS: [&str; 9] = [
+    "head", "script", "style", "template", "title", "meta", "link", "base", "datalist",
+];
+
+/// Elements that are blocks with the default styles of HTML
+const BLOCK_ELEMENTS: [&str; 34] = [
+    "html",
+    "body",
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "ul",
+];
+
+/// How an element takes part in the rendered text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rendering {
+    /// Neither the element nor its descendants are rendered
+    Hidden,
+    Inline,
+    Block,
+    TableRow,
+    TableCell,
+}
+
+/// Rendering of the element from its computed `display`, or from its tag
+/// name if it hasn't been styled
+pub(crate) fn rendering_of(element: &NodePtr) -> Rendering {
+    let tag_name = element.as_element().tag_name();
+    let display = match element.computed_styles().get(&Property::Display) {
+        Some(Value::Display(display)) => Some(display.clone()),
+        _ => None,
+    };
+    match display {
+        Some(Display::Box(DisplayBox::None)) => Rendering::Hidden,
+        None if HIDDEN_ELEMENTS.contains(&tag_name.as_str()) => Rendering::Hidden,
+        _ if tag_name == "tr" => Rendering::TableRow,
+        _ if tag_name == "td" || tag_name == "th" => Rendering::TableCell,
+        Some(Display::Full(OuterDisplayType::Block, _) | Display::ListItem(..)) => Rendering::Block,
+        Some(_) => Rendering::Inline,
+        None if BLOCK_ELEMENTS.contains(&tag_name.as_str()) || tag_name == "table" => {
+            Rendering::Block
+        }
+        None => Rendering::Inline,
+    }
+}
+
+/// Whether the text of the element is painted, descendants can still be
+/// visible inside of an element hidden with `visibility`
+fn is_visible(element: &NodePtr) -> bool {
+    !matches!(
+        element.computed_styles().get(&Property::Visibility),
+        Some(Value::Visibility(Visibility::Hidden | Visibility::Collapse))
+    )
+}
+
+/// `white-space` of the text in the element, inherited from its parent if
+/// it hasn't been styled
+pub(crate) fn white_space_of(element: &NodePtr, inherited: &WhiteSpace) -> WhiteSpace {
+    match element.computed_styles().get(&Property::WhiteSpace) {
+        Some(Value::WhiteSpace(white_space)) => white_space.clone(),
+        _ if is_preformatted_element(element) => WhiteSpace::Pre,
+        _ => inherited.clone(),
+    }
+}
+
+fn is_preformatted_element(element: &NodePtr) -> bool {
+    let element = element.as_element();
+    element.is_html("pre") || element.is_html("textarea") || element.is_html("listing")
+}
+
+/// Collapse each run of white space of the text into a single space
+pub(crate) fn collapse_white_space(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !c.is_ascii_whitespace() {
+            collapsed.push(c);
+        } else if !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+    }
+    collapsed
+}
+
+enum Item {
+    Text {
+        text: String,
+        is_collapsible: bool,
+    },
+    /// A `<br>` or a preserved line feed
+    LineBreak,
+    /// Lines that must separate the content before and after, like around
+    /// blocks. Consecutive ones are merged and they are dropped at the
+    /// start and the end of the text.
+    RequiredLineBreaks(usize),
+}
+
+/// The rendered text of the node. An element that isn't rendered gives its
+/// text content.
+pub fn inner_text(node: &NodePtr) -> String {
+    if node.is_element() && rendering_of(node) == Rendering::Hidden {
+        return node.descendant_text_content();
+    }
+    let white_space = match node.is_element() {
+        true => white_space_of(node, &WhiteSpace::Normal),
+        false => WhiteSpace::Normal,
+    };
+    let mut items = Vec::new();
+    collect_children_items(node, &white_space, &mut items);
+    join_items(items)
+}
+
+fn collect_children_items(node: &NodePtr, white_space: &WhiteSpace, items: &mut Vec<Item>) {
+    let mut children = Vec::new();
+    node.for_each_child(|child| children.push(NodePtr(child)));
+
+    for (index, child) in children.iter().enumerate() {
+        if child.is_text() {
+            if node.is_element() && !is_visible(node) {
+                continue;
+            }
+            collect_text_items(&child.as_text().get_data(), white_space, items);
+        } else if child.is_element() {
+            let rendering = rendering_of(child);
+            collect_element_items(child, rendering, white_space, items);
+            // Cells of a row are separated by tabs
+            let is_cell_followed_by_cell = rendering == Rendering::TableCell
+                && children[index + 1..].iter().any(|sibling| {
+                    sibling.is_element() && rendering_of(sibling) == Rendering::TableCell
+                });
+            if is_cell_followed_by_cell {
+                items.push(Item::Text {
+                    text: "\t".to_string(),
+                    is_collapsible: false,
+                });
+            }
+        }
+    }
+}
+
+fn collect_element_items(
+    element: &NodePtr,
+    rendering: Rendering,
+    white_space: &WhiteSpace,
+    items: &mut Vec<Item>,
+) {
+    if rendering == Rendering::Hidden {
+        return;
+    }
+    if element.as_element().is_html("br") {
+        items.push(Item::LineBreak);
+        return;
+    }
+
+    let line_breaks = match rendering {
+        _ if element.as_element().is_html("p") => 2,
+        Rendering::Block | Rendering::TableRow => 1,
+        _ => 0,
+    };
+    if line_breaks > 0 {
+        items.push(Item::RequiredLineBreaks(line_breaks));
+    }
+    let white_space = white_space_of(element, white_space);
+    collect_children_items(element, &white_space, items);
+    if line_breaks > 0 {
+        items.push(Item::RequiredLineBreaks(line_breaks));
+    }
+}
+
+fn collect_text_items(text: &str, white_space: &WhiteSpace, items: &mut Vec<Item>) {
+    if white_space.preserves_spaces() {
+        items.push(Item::Text {
+            text: text.to_string(),
+            is_collapsible: false,
+        });
+        return;
+    }
+    if !white_space.preserves_line_breaks() {
+        items.push(Item::Text {
+            text: collapse_white_space(text),
+            is_collapsible: true,
+        });
+        return;
+    }
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            items.push(Item::LineBreak);
+        }
+        items.push(Item::Text {
+            text: collapse_white_space(line),
+            is_collapsible: true,
+        });
+    }
+}
+
+fn join_items(items: Vec<Item>) -> String {
+    let mut output = String::new();
+    let mut pending_line_breaks = 0;
+    // Whether the output ends with a collapsible space, which is removed at
+    // the end of a line
+    let mut has_trailing_space = false;
+
+    for item in items {
+        match item {
+            Item::RequiredLineBreaks(count) => {
+                pending_line_breaks = usize::max(pending_line_breaks, count);
+            }
+            Item::LineBreak => {
+                end_line(&mut output, &mut has_trailing_space);
+                push_line_breaks(&mut output, &mut pending_line_breaks);
+                output.push('\n');
+            }
+            Item::Text {
+                text,
+                is_collapsible,
+            } => {
+                let is_line_start =
+                    output.is_empty() || output.ends_with('\n') || pending_line_breaks > 0;
+                let text = match is_collapsible && (is_line_start || has_trailing_space) {
+                    true => text.trim_start_matches(' '),
+                    false => text.as_str(),
+                };
+                if text.is_empty() {
+                    continue;
+                }
+                if pending_line_breaks > 0 {
+                    end_line(&mut output, &mut has_trailing_space);
+                    push_line_breaks(&mut output, &mut pending_line_breaks);
+                }
+                output.push_str(text);
+                has_trailing_space = is_collapsible && text.ends_with(' ');
+            }
+        }
+    }
+    end_line(&mut output, &mut has_trailing_space);
+    output
+}
+
+fn end_line(output: &mut String, has_trailing_space: &mut bool) {
+    if std::mem::take(has_trailing_space) {
+        output.pop();
+    }
+}
+
+/// Push the pending required line breaks, unless nothing was output yet
+fn push_line_breaks(output: &mut String, pending_line_breaks: &mut usize) {
+    let count = std::mem::take(pending_line_breaks);
+    if !output.is_empty() {
+        output.push_str(&"\n".repeat(count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    struct Builder {
+        document: NodePtr,
+    }
+
+    impl Builder {
+        fn new() -> Self {
+            Self {
+                document: NodePtr(TreeNode::new(Node::new(
+                    NodeData::Document(Document::new()),
+                ))),
+            }
+        }
+
+        fn element(&self, parent: &NodePtr, tag_name: &str) -> NodePtr {
+            let element = create_element(WeakTreeNode::from(&self.document.0), tag_name);
+            parent.append_child(element.0.clone());
+            element
+        }
+
+        fn text(&self, parent: &NodePtr, data: &str) {
+            let text = TreeNode::new(Node::new(NodeData::Text(Text::new(data.to_string()))));
+            text.set_document(WeakTreeNode::from(&self.document.0));
+            parent.append_child(text);
+        }
+    }
+
+    #[test]
+    fn test_inner_text_of_unstyled_document() {
+        let builder = Builder::new();
+        let body = builder.element(&builder.document, "body");
+        let head = builder.element(&body, "head");
+        builder.text(&builder.element(&head, "title"), "Title");
+        let heading = builder.element(&body, "h1");
+        builder.text(&heading, "  Moon\n  browser ");
+        let paragraph = builder.element(&body, "p");
+        builder.text(&paragraph, " A ");
+        builder.text(&builder.element(&paragraph, "b"), " web ");
+        builder.text(&paragraph, " browser");
+        builder.element(&paragraph, "br");
+        builder.text(&paragraph, "engine ");
+        builder.text(&builder.element(&body, "pre"), "  fn main() {\n  }");
+        let row = builder.element(&builder.element(&body, "table"), "tr");
+        builder.text(&builder.element(&row, "td"), "a");
+        builder.text(&builder.element(&row, "td"), "b");
+
+        assert_eq!(
+            inner_text(&body),
+            "Moon browser\n\nA web browser\nengine\n\n  fn main() {\n  }\na\tb"
+        );
+        assert_eq!(inner_text(&head), "Title");
+    }
+
+    #[test]
+    fn test_inner_text_follows_computed_styles() {
+        let builder = Builder::new();
+        let div = builder.element(&builder.document, "div");
+        let hidden = builder.element(&div, "span");
+        builder.text(&hidden, "hidden ");
+        let visible = builder.element(&hidden, "b");
+        builder.text(&visible, "shown");
+        let none = builder.element(&div, "p");
+        builder.text(&none, "none");
+        let block = builder.element(&div, "span");
+        builder.text(&block, "block");
+
+        hidden.set_computed_styles(HashMap::from([
+            (Property::Display, Value::Display(Display::new_inline())),
+            (Property::Visibility, Value::Visibility(Visibility::Hidden)),
+        ]));
+        visible.set_computed_styles(HashMap::from([
+            (Property::Display, Value::Display(Display::new_inline())),
+            (Property::Visibility, Value::Visibility(Visibility::Visible)),
+        ]));
+        none.set_computed_styles(HashMap::from([(
+            Property::Display,
+            Value::Display(Display::Box(DisplayBox::None)),
+        )]));
+        block.set_computed_styles(HashMap::from([(
+            Property::Display,
+            Value::Display(Display::new_block()),
+        )]));
+
+        assert_eq!(inner_text(&div), "shown\nblock");
+    }
+}
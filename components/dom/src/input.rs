@@ -0,0 +1,26 @@
+use crate::elements::ElementData;
+use crate::node::NodePtr;
+
+/// Current value of an input element, the value set by the user or else its
+/// `value` attribute
+/// https://html.spec.whatwg.org/multipage/input.html#concept-fe-value
+pub fn value(input: &NodePtr) -> String {
+    if let ElementData::Input(data) = input.as_element().data() {
+        if let Some(value) = data.value().borrow().as_ref() {
+            return value.clone();
+        }
+    }
+    input.as_element().attributes().borrow().get_str("value")
+}
+
+/// Set the value of an input element as if typed by the user. Returns false
+/// for other elements.
+pub fn set_value(input: &NodePtr, value: &str) -> bool {
+    match input.as_element_opt().map(|element| element.data()) {
+        Some(ElementData::Input(data)) => {
+            *data.value().borrow_mut() = Some(value.to_string());
+            true
+        }
+        _ => false,
+    }
+}
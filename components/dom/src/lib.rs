@@ -4,13 +4,31 @@ pub mod node_list;
 
 pub mod character_data;
 pub mod comment;
+pub mod constraint_validation;
+pub mod details;
 pub mod document;
 pub mod element;
+pub mod flat_tree;
+pub mod form_submission;
+pub mod input;
+pub mod lifecycle;
+pub mod mutation;
 pub mod node;
+pub mod node_id;
+pub mod select;
+pub mod shadow_root;
+pub mod snapshot;
+pub mod srcset;
+pub mod stylesheet_loader;
 pub mod text;
+pub mod text_editor;
+pub mod textarea;
 
 pub mod conversion;
 
 mod element_factory;
+mod html_serializer;
+mod inner_text;
+mod markdown;
 
-pub use element_factory::create_element;
+pub use element_factory::{create_element, create_element_ns};
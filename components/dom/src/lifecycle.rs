@@ -0,0 +1,112 @@
+//! Readiness of a document while it loads, and the events of its
+//! transitions: `DOMContentLoaded` once it is parsed and `load` once its
+//! resources are loaded. There are no scripts to receive them yet, so only
+//! the engine listens to them.
+//! https://html.spec.whatwg.org/multipage/dom.html#current-document-readiness
+
+use std::cell::{Cell, RefCell};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DocumentReadyState {
+    /// The document is being parsed
+    Loading,
+    /// The document is parsed, its resources are still loading
+    Interactive,
+    /// The document and its resources are loaded
+    Complete,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// Fired when the document becomes interactive
+    DOMContentLoaded,
+    /// Fired when the document is complete
+    Load,
+}
+
+/// Ready state of a document and the listeners of its lifecycle events
+pub struct DocumentLifecycle {
+    ready_state: Cell<DocumentReadyState>,
+    listeners: RefCell<Vec<Box<dyn Fn(LifecycleEvent)>>>,
+}
+
+impl DocumentLifecycle {
+    pub fn new() -> Self {
+        Self {
+            ready_state: Cell::new(DocumentReadyState::Loading),
+            listeners: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn ready_state(&self) -> DocumentReadyState {
+        self.ready_state.get()
+    }
+
+    /// Move the document on to the state, firing the events of the states
+    /// it goes through. A document never goes back to an earlier state.
+    /// Returns true if the state changed.
+    pub fn advance_to(&self, state: DocumentReadyState) -> bool {
+        let previous = self.ready_state.get();
+        if state <= previous {
+            return false;
+        }
+        self.ready_state.set(state);
+        if previous < DocumentReadyState::Interactive {
+            self.dispatch(LifecycleEvent::DOMContentLoaded);
+        }
+        if state == DocumentReadyState::Complete {
+            self.dispatch(LifecycleEvent::Load);
+        }
+        true
+    }
+
+    /// Call `listener` with each lifecycle event fired from now on
+    pub fn add_listener(&self, listener: impl Fn(LifecycleEvent) + 'static) {
+        self.listeners.borrow_mut().push(Box::new(listener));
+    }
+
+    fn dispatch(&self, event: LifecycleEvent) {
+        for listener in self.listeners.borrow().iter() {
+            listener(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn test_ready_state_transitions() {
+        let lifecycle = DocumentLifecycle::new();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let received = events.clone();
+        lifecycle.add_listener(move |event| received.borrow_mut().push(event));
+        assert_eq!(lifecycle.ready_state(), DocumentReadyState::Loading);
+
+        assert!(lifecycle.advance_to(DocumentReadyState::Interactive));
+        assert_eq!(*events.borrow(), [LifecycleEvent::DOMContentLoaded]);
+        assert!(!lifecycle.advance_to(DocumentReadyState::Interactive));
+        assert!(!lifecycle.advance_to(DocumentReadyState::Loading));
+
+        assert!(lifecycle.advance_to(DocumentReadyState::Complete));
+        assert_eq!(lifecycle.ready_state(), DocumentReadyState::Complete);
+        assert_eq!(
+            *events.borrow(),
+            [LifecycleEvent::DOMContentLoaded, LifecycleEvent::Load]
+        );
+
+        // Skipping the interactive state still fires both events
+        let lifecycle = DocumentLifecycle::new();
+        let received = events.clone();
+        events.borrow_mut().clear();
+        lifecycle.add_listener(move |event| received.borrow_mut().push(event));
+        assert!(lifecycle.advance_to(DocumentReadyState::Complete));
+        assert_eq!(
+            *events.borrow(),
+            [LifecycleEvent::DOMContentLoaded, LifecycleEvent::Load]
+        );
+    }
+}
@@ -0,0 +1,373 @@
+//! Conversion of a node to Markdown, for copying as Markdown and for reader
+//! mode. Headings, paragraphs, lists, quotes, code, tables, links, images
+//! and emphasis are kept, the other elements give their content. Hidden
+//! elements are left out like in `inner_text`.
+//! https://spec.commonmark.org/
+
+use crate::inner_text::{collapse_white_space, rendering_of, Rendering};
+use crate::node::NodePtr;
+
+/// Markdown of the content of the node, with blocks separated by blank lines
+pub fn to_markdown(node: &NodePtr) -> String {
+    blocks_of(node).join("\n\n")
+}
+
+/// Markdown blocks of the children of the node. Runs of inline children
+/// between blocks make paragraphs.
+fn blocks_of(node: &NodePtr) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut inline = String::new();
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        match block_of(&child) {
+            Some(child_blocks) => {
+                push_paragraph(&mut blocks, &std::mem::take(&mut inline));
+                blocks.extend(child_blocks);
+            }
+            None => inline.push_str(&inline_of(&child)),
+        }
+    });
+    push_paragraph(&mut blocks, &inline);
+    blocks
+}
+
+fn push_paragraph(blocks: &mut Vec<String>, inline: &str) {
+    let paragraph = normalize_inline(inline);
+    if !paragraph.is_empty() {
+        blocks.push(paragraph);
+    }
+}
+
+/// Blocks of the node, or `None` if it is inline content
+fn block_of(node: &NodePtr) -> Option<Vec<String>> {
+    if !node.is_element() {
+        return None;
+    }
+    let rendering = rendering_of(node);
+    if rendering == Rendering::Hidden {
+        return Some(Vec::new());
+    }
+
+    let tag_name = node.as_element().tag_name();
+    let block = match tag_name.as_str() {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = tag_name[1..].parse().unwrap_or(1);
+            let text = normalize_inline(&inline_children(node)).replace('\n', " ");
+            (!text.is_empty()).then(|| format!("{} {}", "#".repeat(level), text))
+        }
+        "ul" => list(node, None),
+        "ol" => {
+            let start = node.as_element().get_attribute("start");
+            let start = start.and_then(|start| start.trim().parse().ok());
+            list(node, Some(start.unwrap_or(1)))
+        }
+        "pre" => Some(code_block(node)),
+        "blockquote" => quote(node),
+        "hr" => Some("---".to_string()),
+        "table" => table(node),
+        _ if rendering == Rendering::Inline => return None,
+        _ => return Some(blocks_of(node)),
+    };
+    Some(block.into_iter().collect())
+}
+
+/// Items of the list, numbered from `start` if it is ordered
+fn list(node: &NodePtr, start: Option<usize>) -> Option<String> {
+    let mut items = Vec::new();
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        if !child.is_element() || rendering_of(&child) == Rendering::Hidden {
+            return;
+        }
+        let marker = match start {
+            Some(start) => format!("{}. ", start + items.len()),
+            None => "- ".to_string(),
+        };
+        // The blocks of an item are kept together so that the list is tight
+        let content = blocks_of(&child).join("\n");
+        let indent = " ".repeat(marker.len());
+        let mut item = marker.trim_end().to_string();
+        for (index, line) in content.lines().enumerate() {
+            match (index, line.is_empty()) {
+                (0, _) => item = format!("{}{}", marker, line),
+                (_, true) => item.push('\n'),
+                (_, false) => item.push_str(&format!("\n{}{}", indent, line)),
+            }
+        }
+        items.push(item);
+    });
+    (!items.is_empty()).then(|| items.join("\n"))
+}
+
+/// Fenced code block of a `<pre>`, with the language of a `<code
+/// class="language-…">` in it
+fn code_block(node: &NodePtr) -> String {
+    let mut language = String::new();
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        let class = child
+            .as_element_opt()
+            .filter(|element| element.is_html("code"))
+            .and_then(|element| element.get_attribute("class"))
+            .unwrap_or_default();
+        if let Some(name) = class
+            .split_ascii_whitespace()
+            .find_map(|class| class.strip_prefix("language-"))
+        {
+            language = name.to_string();
+        }
+    });
+
+    let code = node.descendant_text_content();
+    let code = code.strip_suffix('\n').unwrap_or(&code);
+    let fence = "`".repeat(usize::max(longest_backtick_run(code) + 1, 3));
+    format!("{}{}\n{}\n{}", fence, language, code, fence)
+}
+
+fn quote(node: &NodePtr) -> Option<String> {
+    let content = blocks_of(node).join("\n\n");
+    if content.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = content
+        .lines()
+        .map(|line| match line.is_empty() {
+            true => ">".to_string(),
+            false => format!("> {}", line),
+        })
+        .collect();
+    Some(lines.join("\n"))
+}
+
+/// Table with its first row as the header
+/// https://github.github.com/gfm/#tables-extension-
+fn table(node: &NodePtr) -> Option<String> {
+    let rows: Vec<Vec<String>> = node
+        .get_elements_by_tag_name("tr")
+        .iter()
+        .filter(|row| rendering_of(row) != Rendering::Hidden)
+        .map(|row| {
+            let mut cells = Vec::new();
+            row.for_each_child(|child| {
+                let child = NodePtr(child);
+                if child.is_element() && rendering_of(&child) == Rendering::TableCell {
+                    let text = normalize_inline(&inline_children(&child));
+                    cells.push(text.replace("\\\n", " ").replace('|', "\\|"));
+                }
+            });
+            cells
+        })
+        .filter(|cells| !cells.is_empty())
+        .collect();
+    let columns = rows.iter().map(|cells| cells.len()).max()?;
+
+    let format_row = |cells: &[String]| {
+        let mut row = String::from("|");
+        for column in 0..columns {
+            let cell = cells.get(column).map(String::as_str).unwrap_or_default();
+            row.push_str(&format!(" {} |", cell));
+        }
+        row
+    };
+    let mut lines = vec![
+        format_row(&rows[0]),
+        format_row(&vec!["---".to_string(); columns]),
+    ];
+    lines.extend(rows[1..].iter().map(|cells| format_row(cells)));
+    Some(lines.join("\n"))
+}
+
+/// Markdown of inline content, with white space collapsed but not trimmed
+fn inline_of(node: &NodePtr) -> String {
+    if let Some(text) = node.as_text_opt() {
+        return escape(&collapse_white_space(&text.get_data()));
+    }
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return String::new(),
+    };
+    if rendering_of(node) == Rendering::Hidden {
+        return String::new();
+    }
+
+    match element.tag_name().as_str() {
+        // A backslash at the end of a line is a hard line break
+        "br" => "\\\n".to_string(),
+        "strong" | "b" => emphasize(&inline_children(node), "**"),
+        "em" | "i" => emphasize(&inline_children(node), "*"),
+        "del" | "s" | "strike" => emphasize(&inline_children(node), "~~"),
+        "code" | "kbd" | "samp" => code_span(&node.descendant_text_content()),
+        "a" => {
+            let content = inline_children(node);
+            match element.get_attribute("href") {
+                Some(href) => {
+                    let text = normalize_inline(&content);
+                    let text = match text.is_empty() {
+                        true => escape(&href),
+                        false => text,
+                    };
+                    format!("[{}]({})", text, link_destination(&href))
+                }
+                None => content,
+            }
+        }
+        "img" => match element.get_attribute("src") {
+            Some(src) => {
+                let alt = element.get_attribute("alt").unwrap_or_default();
+                format!(
+                    "![{}]({})",
+                    escape(&collapse_white_space(alt.trim())),
+                    link_destination(&src)
+                )
+            }
+            None => String::new(),
+        },
+        _ => inline_children(node),
+    }
+}
+
+fn inline_children(node: &NodePtr) -> String {
+    let mut content = String::new();
+    node.for_each_child(|child| content.push_str(&inline_of(&NodePtr(child))));
+    content
+}
+
+/// Wrap the content in the emphasis markers, which must be next to the
+/// text so the spaces around it are kept outside of them
+fn emphasize(content: &str, marker: &str) -> String {
+    let text = content.trim_matches(' ');
+    if text.is_empty() {
+        return content.to_string();
+    }
+    let before = if content.starts_with(' ') { " " } else { "" };
+    let after = if content.ends_with(' ') { " " } else { "" };
+    format!("{}{}{}{}{}", before, marker, text, marker, after)
+}
+
+/// Code span of the text, delimited by more backticks than it contains
+/// https://spec.commonmark.org/0.30/#code-spans
+fn code_span(text: &str) -> String {
+    let text = collapse_white_space(text);
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+    let delimiter = "`".repeat(longest_backtick_run(text) + 1);
+    // Spaces keep the backticks of the text from joining the delimiters
+    let padding = match text.starts_with('`') || text.ends_with('`') {
+        true => " ",
+        false => "",
+    };
+    format!("{}{}{}{}{}", delimiter, padding, text, padding, delimiter)
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Destination of a link, in angle brackets if it has spaces or parentheses
+fn link_destination(url: &str) -> String {
+    match url.contains(|c: char| c.is_ascii_whitespace() || c == '(' || c == ')') {
+        true => format!("<{}>", url.replace('<', "%3C").replace('>', "%3E")),
+        false => url.to_string(),
+    }
+}
+
+/// Escape the characters of the text that would start inline Markdown
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Trim the inline content and the spaces around its line breaks, and
+/// collapse the spaces left between the children
+fn normalize_inline(content: &str) -> String {
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| collapse_white_space(line).trim().to_string())
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    #[test]
+    fn test_to_markdown() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let element = |parent: &NodePtr, tag_name: &str| {
+            let element = create_element(WeakTreeNode::from(&document.0), tag_name);
+            parent.append_child(element.0.clone());
+            element
+        };
+        let text = |parent: &NodePtr, data: &str| {
+            let text = TreeNode::new(Node::new(NodeData::Text(Text::new(data.to_string()))));
+            text.set_document(WeakTreeNode::from(&document.0));
+            parent.append_child(text);
+        };
+
+        let body = element(&document, "body");
+        text(&element(&element(&body, "head"), "title"), "Title");
+        text(&element(&body, "h2"), " Moon ");
+        let paragraph = element(&body, "p");
+        text(&paragraph, "A ");
+        text(&element(&paragraph, "em"), "fast ");
+        text(&paragraph, " *web* browser");
+        element(&paragraph, "br");
+        let link = element(&paragraph, "a");
+        link.as_element()
+            .set_attribute("href", "https://example.com");
+        text(&link, "Home");
+
+        let list = element(&body, "ol");
+        text(&list, "\n  ");
+        let item = element(&list, "li");
+        text(&item, "First");
+        let nested = element(&element(&item, "ul"), "li");
+        text(&element(&nested, "code"), "a`b");
+        text(&element(&list, "li"), "Second");
+
+        let quote = element(&body, "blockquote");
+        text(&element(&quote, "p"), "Quoted");
+        text(&element(&quote, "p"), "text");
+
+        let code = element(&element(&body, "pre"), "code");
+        code.as_element().set_attribute("class", "language-rust");
+        text(&code, "fn main() {}\n");
+
+        let table = element(&body, "table");
+        let header = element(&table, "tr");
+        text(&element(&header, "th"), "Name");
+        text(&element(&header, "th"), "Value");
+        let row = element(&table, "tr");
+        text(&element(&row, "td"), "a|b");
+
+        assert_eq!(
+            to_markdown(&body),
+            "## Moon\n\n\
+             A *fast* \\*web\\* browser\\\n[Home](https://example.com)\n\n\
+             1. First\n   - ``a`b``\n2. Second\n\n\
+             > Quoted\n>\n> text\n\n\
+             ```rust\nfn main() {}\n```\n\n\
+             | Name | Value |\n| --- | --- |\n| a\\|b |  |"
+        );
+    }
+}
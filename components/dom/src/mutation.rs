@@ -0,0 +1,303 @@
+//! Notifications of the changes to the DOM tree, modeled on
+//! `MutationObserver`. Records are queued while the tree changes and are
+//! handed to the callbacks of the observers when
+//! `Document::notify_mutation_observers` is called, so that observers never
+//! run in the middle of a mutation.
+//! https://dom.spec.whatwg.org/#mutation-observers
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use shared::tree_node::WeakTreeNode;
+
+use crate::node::{Node, NodePtr};
+
+#[derive(Debug, Clone)]
+pub enum MutationKind {
+    /// Children were added to or removed from the target
+    ChildList {
+        added_nodes: Vec<NodePtr>,
+        removed_nodes: Vec<NodePtr>,
+    },
+    /// An attribute of the target was set or removed
+    Attributes {
+        name: String,
+        old_value: Option<String>,
+    },
+    /// The data of the target text or comment changed
+    CharacterData { old_value: String },
+}
+
+/// https://dom.spec.whatwg.org/#mutationrecord
+#[derive(Debug, Clone)]
+pub struct MutationRecord {
+    pub target: NodePtr,
+    pub kind: MutationKind,
+}
+
+/// Changes an observer is notified of
+/// https://dom.spec.whatwg.org/#dictdef-mutationobserverinit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MutationObserverInit {
+    pub child_list: bool,
+    pub attributes: bool,
+    pub character_data: bool,
+    /// Observe the descendants of the target too
+    pub subtree: bool,
+}
+
+impl MutationObserverInit {
+    /// Every change to the target and its descendants
+    pub fn all() -> Self {
+        Self {
+            child_list: true,
+            attributes: true,
+            character_data: true,
+            subtree: true,
+        }
+    }
+}
+
+struct Registration {
+    target: WeakTreeNode<Node>,
+    options: MutationObserverInit,
+}
+
+struct ObserverState {
+    callback: Box<dyn Fn(Vec<MutationRecord>)>,
+    registrations: RefCell<Vec<Registration>>,
+    records: RefCell<Vec<MutationRecord>>,
+}
+
+impl ObserverState {
+    fn is_interested(&self, record: &MutationRecord) -> bool {
+        self.registrations.borrow().iter().any(|registration| {
+            let is_selected = match record.kind {
+                MutationKind::ChildList { .. } => registration.options.child_list,
+                MutationKind::Attributes { .. } => registration.options.attributes,
+                MutationKind::CharacterData { .. } => registration.options.character_data,
+            };
+            let target = match registration.target.upgrade() {
+                Some(target) if is_selected => target,
+                _ => return false,
+            };
+            Rc::ptr_eq(&target, &record.target)
+                || (registration.options.subtree
+                    && record
+                        .target
+                        .find_first_ancestor(|ancestor| Rc::ptr_eq(&ancestor, &target))
+                        .is_some())
+        })
+    }
+}
+
+/// Observer of the changes to some nodes. The observer isn't notified
+/// anymore once it is disconnected or dropped.
+pub struct MutationObserver(Rc<ObserverState>);
+
+impl MutationObserver {
+    pub fn new(callback: impl Fn(Vec<MutationRecord>) + 'static) -> Self {
+        Self(Rc::new(ObserverState {
+            callback: Box::new(callback),
+            registrations: RefCell::new(Vec::new()),
+            records: RefCell::new(Vec::new()),
+        }))
+    }
+
+    /// Observe the changes to `target` selected by `options`. Observing the
+    /// same target again replaces its options.
+    /// https://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    pub fn observe(&self, target: &NodePtr, options: MutationObserverInit) {
+        let document = match target.owner_document() {
+            Some(document) => NodePtr(document),
+            None if target.is_document() => target.clone(),
+            None => return,
+        };
+
+        let mut registrations = self.0.registrations.borrow_mut();
+        let existing = registrations.iter_mut().find(|registration| {
+            registration
+                .target
+                .upgrade()
+                .is_some_and(|node| Rc::ptr_eq(&node, target))
+        });
+        match existing {
+            Some(registration) => registration.options = options,
+            None => registrations.push(Registration {
+                target: WeakTreeNode::from(&target.0),
+                options,
+            }),
+        }
+        document
+            .as_document()
+            .mutation_observers()
+            .register(&self.0);
+    }
+
+    /// Stop observing every target and drop the pending records
+    pub fn disconnect(&self) {
+        self.0.registrations.borrow_mut().clear();
+        self.0.records.borrow_mut().clear();
+    }
+
+    /// Records queued since the last notification, which won't be passed to
+    /// the callback anymore
+    pub fn take_records(&self) -> Vec<MutationRecord> {
+        self.0.records.take()
+    }
+}
+
+/// Observers of the nodes of a document. Only weak references are kept,
+/// dropping a `MutationObserver` unregisters it.
+pub struct MutationObservers {
+    observers: RefCell<Vec<Weak<ObserverState>>>,
+}
+
+impl MutationObservers {
+    pub fn new() -> Self {
+        Self {
+            observers: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn register(&self, observer: &Rc<ObserverState>) {
+        let mut observers = self.observers.borrow_mut();
+        observers.retain(|observer| observer.strong_count() > 0);
+        if !observers
+            .iter()
+            .any(|registered| registered.as_ptr() == Rc::as_ptr(observer))
+        {
+            observers.push(Rc::downgrade(observer));
+        }
+    }
+
+    /// Queue the record for the observers interested in it. The record is
+    /// only created if the document has observers.
+    pub(crate) fn queue(&self, record: impl FnOnce() -> MutationRecord) {
+        let observers = self.live_observers();
+        if observers.is_empty() {
+            return;
+        }
+        let record = record();
+        for observer in observers {
+            if observer.is_interested(&record) {
+                observer.records.borrow_mut().push(record.clone());
+            }
+        }
+    }
+
+    /// Pass the queued records to the callbacks of the observers. Changes
+    /// made by the callbacks are queued for the next notification.
+    /// https://dom.spec.whatwg.org/#notify-mutation-observers
+    pub(crate) fn notify(&self) {
+        for observer in self.live_observers() {
+            let records = observer.records.take();
+            if !records.is_empty() {
+                (observer.callback)(records);
+            }
+        }
+    }
+
+    fn live_observers(&self) -> Vec<Rc<ObserverState>> {
+        self.observers
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::NodeData;
+    use crate::text::Text;
+
+    fn observe(
+        target: &NodePtr,
+        options: MutationObserverInit,
+    ) -> (MutationObserver, Rc<RefCell<Vec<MutationRecord>>>) {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let observed = records.clone();
+        let observer = MutationObserver::new(move |new_records| {
+            observed.borrow_mut().extend(new_records);
+        });
+        observer.observe(target, options);
+        (observer, records)
+    }
+
+    #[test]
+    fn notify_observed_changes() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let div = create_element(WeakTreeNode::from(&document.0), "div");
+        document.append_child(div.0.clone());
+
+        let (_observer, records) = observe(&document, MutationObserverInit::all());
+
+        let text = Node::new(NodeData::Text(Text::new("moon".to_string())));
+        text.set_document(WeakTreeNode::from(&document.0));
+        let text = NodePtr(TreeNode::new(text));
+        div.append_child(text.0.clone());
+        div.set_attribute("title", "first");
+        div.set_attribute("title", "second");
+        text.set_character_data("sun");
+        text.detach();
+        assert!(records.borrow().is_empty());
+
+        document.as_document().notify_mutation_observers();
+        let records = records.borrow();
+        assert_eq!(records.len(), 5);
+        assert!(matches!(
+            &records[0].kind,
+            MutationKind::ChildList { added_nodes, .. } if Rc::ptr_eq(&added_nodes[0], &text)
+        ));
+        assert!(matches!(
+            &records[2].kind,
+            MutationKind::Attributes { name, old_value: Some(old_value) }
+                if name == "title" && old_value == "first"
+        ));
+        assert!(matches!(
+            &records[3].kind,
+            MutationKind::CharacterData { old_value } if old_value == "moon"
+        ));
+        assert!(Rc::ptr_eq(&records[4].target, &div));
+    }
+
+    #[test]
+    fn filter_changes_by_options() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let div = create_element(WeakTreeNode::from(&document.0), "div");
+        let span = create_element(WeakTreeNode::from(&document.0), "span");
+        document.append_child(div.0.clone());
+        div.append_child(span.0.clone());
+
+        let options = MutationObserverInit {
+            attributes: true,
+            ..Default::default()
+        };
+        let (observer, records) = observe(&div, options);
+
+        // Descendants aren't observed without `subtree`
+        span.set_attribute("class", "a");
+        div.append_child(create_element(WeakTreeNode::from(&document.0), "p").0);
+        div.set_attribute("class", "b");
+        assert_eq!(observer.take_records().len(), 1);
+
+        div.remove_attribute("class");
+        observer.disconnect();
+        div.set_attribute("class", "c");
+        document.as_document().notify_mutation_observers();
+        assert!(records.borrow().is_empty());
+    }
+}
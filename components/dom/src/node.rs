@@ -1,23 +1,32 @@
+use crate::mutation::{MutationKind, MutationRecord};
 use crate::node_list::NodeList;
+use crate::snapshot::ComputedStyleSnapshot;
+use crate::{html_serializer, inner_text, markdown};
 
 use super::comment::Comment;
 use super::document::Document;
 use super::element::Element;
 use super::elements::ElementData;
+use super::node_id::NodeId;
+use super::shadow_root::ShadowRoot;
 use super::text::Text;
 use enum_dispatch::enum_dispatch;
 use shared::tree_node::{TreeNode, TreeNodeHooks, WeakTreeNode};
 use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
 use std::ops::Deref;
-use style_types::{Property, Value};
+use style_types::{values::prelude::Color, Property, PseudoElement, Value};
 
 pub struct NodePtr(pub TreeNode<Node>);
 
 pub struct Node {
+    id: NodeId,
     owner_document: RefCell<Option<WeakTreeNode<Node>>>,
     data: Option<NodeData>,
     computed_styles: RefCell<HashMap<Property, Value>>,
+    /// Generated content of the element. These nodes are not part of the
+    /// DOM tree, they only exist to be rendered.
+    pseudo_elements: RefCell<HashMap<PseudoElement, NodePtr>>,
 }
 
 #[enum_dispatch(NodeHooks)]
@@ -26,6 +35,7 @@ pub enum NodeData {
     Text(Text),
     Document(Document),
     Comment(Comment),
+    ShadowRoot(ShadowRoot),
 }
 
 pub struct InsertContext {
@@ -71,6 +81,20 @@ impl Clone for NodePtr {
 
 impl TreeNodeHooks<Node> for Node {
     fn on_inserted(&self, current: TreeNode<Node>, parent: TreeNode<Node>) {
+        self.bump_document_generation();
+        self.queue_mutation_record(|| MutationRecord {
+            target: NodePtr(parent.clone()),
+            kind: MutationKind::ChildList {
+                added_nodes: vec![NodePtr(current.clone())],
+                removed_nodes: Vec::new(),
+            },
+        });
+        if let Some(document) = self.owner_document() {
+            document
+                .as_document()
+                .node_registry()
+                .register(&NodePtr(current.clone()));
+        }
         if let Some(data) = &self.data {
             if let Some(document) = self.owner_document() {
                 let context = InsertContext {
@@ -84,6 +108,7 @@ impl TreeNodeHooks<Node> for Node {
     }
 
     fn on_children_updated(&self, current: TreeNode<Node>) {
+        self.bump_document_generation();
         if let Some(data) = &self.data {
             if let Some(document) = self.owner_document() {
                 let context = ChildrenUpdateContext {
@@ -94,6 +119,35 @@ impl TreeNodeHooks<Node> for Node {
             }
         }
     }
+
+    fn on_removed(&self, current: TreeNode<Node>, parent: TreeNode<Node>) {
+        self.bump_document_generation();
+        self.queue_mutation_record(|| MutationRecord {
+            target: NodePtr(parent),
+            kind: MutationKind::ChildList {
+                added_nodes: Vec::new(),
+                removed_nodes: vec![NodePtr(current)],
+            },
+        });
+    }
+
+    fn on_teardown(&self) {
+        let pseudo_elements = std::mem::take(&mut *self.pseudo_elements.borrow_mut());
+        for pseudo_node in pseudo_elements.values() {
+            pseudo_node.teardown();
+        }
+        if let Some(element) = self.as_element_opt() {
+            if let Some(shadow_root) = element.take_shadow_root() {
+                shadow_root.teardown();
+            }
+            if let ElementData::IFrame(iframe) = element.data() {
+                if let Some(content_document) = iframe.content_document() {
+                    iframe.set_content_document(None);
+                    content_document.teardown();
+                }
+            }
+        }
+    }
 }
 
 impl NodeData {
@@ -112,6 +166,7 @@ impl core::fmt::Debug for NodeData {
             NodeData::Text(text) => write!(f, "Text({:?})", text.get_data()),
             NodeData::Comment(comment) => write!(f, "Comment({:?})", comment.get_data()),
             NodeData::Document(_) => write!(f, "Document"),
+            NodeData::ShadowRoot(_) => write!(f, "ShadowRoot"),
             NodeData::Element(element) => write!(f, "{:?}", element),
         }
     }
@@ -138,6 +193,30 @@ impl NodePtr {
         result
     }
 
+    /// Text of the node as it is rendered, with hidden content left out and
+    /// blocks on lines of their own
+    /// https://html.spec.whatwg.org/multipage/dom.html#the-innertext-idl-attribute
+    pub fn inner_text(&self) -> String {
+        inner_text::inner_text(self)
+    }
+
+    /// Markup of the node with its children
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-outerhtml
+    pub fn outer_html(&self) -> String {
+        html_serializer::serialize_node(self)
+    }
+
+    /// Markup of the children of the node
+    /// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+    pub fn inner_html(&self) -> String {
+        html_serializer::serialize_children(self)
+    }
+
+    /// Content of the node converted to Markdown
+    pub fn to_markdown(&self) -> String {
+        markdown::to_markdown(self)
+    }
+
     /// Child text content of the node
     /// https://dom.spec.whatwg.org/#concept-child-text-content
     pub fn child_text_content(&self) -> String {
@@ -150,9 +229,124 @@ impl NodePtr {
         result
     }
 
+    /// Resolved values of the computed styles, like `getComputedStyle()`.
+    /// `currentColor` is resolved to the node's `color`. The snapshot is
+    /// empty if the node hasn't been styled.
+    /// https://www.w3.org/TR/cssom-1/#resolved-values
+    pub fn computed_style_snapshot(&self) -> ComputedStyleSnapshot {
+        let computed_styles = self.computed_styles();
+        let current_color = computed_styles.get(&Property::Color);
+
+        computed_styles
+            .iter()
+            .map(|(property, value)| {
+                let value = match (value, current_color) {
+                    (Value::Color(Color::CurrentColor), Some(color)) => color,
+                    _ => value,
+                };
+                (property.name().to_string(), value.to_string())
+            })
+            .collect()
+    }
+
+    /// Set an attribute of the element, notifying the mutation observers
+    pub fn set_attribute(&self, name: &str, value: &str) {
+        let element = self.as_element();
+        let old_value = element.get_attribute(name);
+        element.set_attribute(name, value);
+        self.queue_attribute_mutation(name, old_value);
+    }
+
+    /// Remove an attribute of the element, notifying the mutation observers
+    pub fn remove_attribute(&self, name: &str) {
+        let element = self.as_element();
+        let old_value = element.get_attribute(name);
+        if old_value.is_none() {
+            return;
+        }
+        element.remove_attribute(name);
+        self.queue_attribute_mutation(name, old_value);
+    }
+
+    /// Replace the data of a text or comment node, notifying the mutation
+    /// observers
+    pub fn set_character_data(&self, data: &str) {
+        let character_data = match self.data() {
+            Some(NodeData::Text(text)) => &text.character_data,
+            Some(NodeData::Comment(comment)) => &comment.character_data,
+            _ => return,
+        };
+        let old_value = character_data.get_data();
+        character_data.set_data(data);
+        self.bump_document_generation();
+        self.queue_mutation_record(|| MutationRecord {
+            target: self.clone(),
+            kind: MutationKind::CharacterData { old_value },
+        });
+    }
+
+    fn queue_attribute_mutation(&self, name: &str, old_value: Option<String>) {
+        self.bump_document_generation();
+        self.queue_mutation_record(|| MutationRecord {
+            target: self.clone(),
+            kind: MutationKind::Attributes {
+                name: name.to_string(),
+                old_value,
+            },
+        });
+    }
+
     pub fn child_nodes(&self) -> NodeList {
         NodeList::new(self.first_child())
     }
+
+    /// Attach a shadow root to the element. The shadow tree is rendered in
+    /// place of the children of the element. Returns `None` if the node is
+    /// not an element or already has a shadow root.
+    /// https://dom.spec.whatwg.org/#dom-element-attachshadow
+    pub fn attach_shadow(&self) -> Option<NodePtr> {
+        let element = self.as_element_opt()?;
+        if element.shadow_root().is_some() {
+            return None;
+        }
+
+        let shadow_root = Node::new(NodeData::ShadowRoot(ShadowRoot::new(WeakTreeNode::from(
+            &self.0,
+        ))));
+        if let Some(document) = self.owner_document() {
+            shadow_root.set_document(WeakTreeNode::from(&document));
+        }
+        let shadow_root = NodePtr(TreeNode::new(shadow_root));
+        element.set_shadow_root(shadow_root.clone());
+        if let Some(document) = self.owner_document() {
+            document
+                .as_document()
+                .node_registry()
+                .register(&shadow_root);
+        }
+        Some(shadow_root)
+    }
+
+    /// Node of this document with the given id, including the nodes of
+    /// shadow trees. Returns `None` if the node is not a document.
+    pub fn node_by_id(&self, id: NodeId) -> Option<NodePtr> {
+        self.as_document_opt()?.node_registry().get(self, id)
+    }
+
+    /// Descendant elements with the given tag name, in tree order
+    pub fn get_elements_by_tag_name(&self, tag_name: &str) -> Vec<NodePtr> {
+        let mut result = Vec::new();
+        self.for_each_child(|child| {
+            let child = NodePtr(child);
+            if let Some(element) = child.as_element_opt() {
+                if element.tag_name() == tag_name {
+                    result.push(child.clone());
+                }
+            }
+            result.extend(child.get_elements_by_tag_name(tag_name));
+        });
+        result
+    }
 }
 
 impl Node {
@@ -164,12 +358,19 @@ impl Node {
 
     pub fn empty() -> Self {
         Self {
+            id: NodeId::next(),
             owner_document: RefCell::new(None),
             data: None,
             computed_styles: RefCell::new(HashMap::new()),
+            pseudo_elements: RefCell::new(HashMap::new()),
         }
     }
 
+    /// Id of the node, stable for its whole lifetime
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
     /// Set the owner document for node
     pub fn set_document(&self, doc: WeakTreeNode<Node>) {
         self.owner_document.replace(Some(doc));
@@ -211,6 +412,13 @@ impl Node {
         }
     }
 
+    pub fn as_shadow_root_opt(&self) -> Option<&ShadowRoot> {
+        match &self.data {
+            Some(NodeData::ShadowRoot(shadow_root)) => Some(shadow_root),
+            _ => None,
+        }
+    }
+
     pub fn as_element(&self) -> &Element {
         self.as_element_opt().expect("Node is not an Element")
     }
@@ -227,6 +435,10 @@ impl Node {
         self.as_text_opt().expect("Node is not a Text")
     }
 
+    pub fn as_shadow_root(&self) -> &ShadowRoot {
+        self.as_shadow_root_opt().expect("Node is not a ShadowRoot")
+    }
+
     pub fn is_element(&self) -> bool {
         self.as_element_opt().is_some()
     }
@@ -243,12 +455,33 @@ impl Node {
         self.as_text_opt().is_some()
     }
 
+    pub fn is_shadow_root(&self) -> bool {
+        self.as_shadow_root_opt().is_some()
+    }
+
     pub fn data(&self) -> &Option<NodeData> {
         &self.data
     }
 
     pub fn set_computed_styles(&self, computed_styles: HashMap<Property, Value>) {
         *self.computed_styles.borrow_mut() = computed_styles;
+        self.bump_document_generation();
+    }
+
+    fn queue_mutation_record(&self, record: impl FnOnce() -> MutationRecord) {
+        if let Some(document) = self.owner_document() {
+            document.as_document().mutation_observers().queue(record);
+        } else if let Some(document) = self.as_document_opt() {
+            document.mutation_observers().queue(record);
+        }
+    }
+
+    fn bump_document_generation(&self) {
+        if let Some(document) = self.owner_document() {
+            document.as_document().bump_generation();
+        } else if let Some(document) = self.as_document_opt() {
+            document.bump_generation();
+        }
     }
 
     pub fn computed_styles(&self) -> Ref<HashMap<Property, Value>> {
@@ -261,4 +494,16 @@ impl Node {
             .expect(&format!("Unavailable style for :{:?}", property))
             .clone()
     }
+
+    pub fn set_pseudo_element(&self, pseudo_element: PseudoElement, node: Option<NodePtr>) {
+        let mut pseudo_elements = self.pseudo_elements.borrow_mut();
+        match node {
+            Some(node) => pseudo_elements.insert(pseudo_element, node),
+            None => pseudo_elements.remove(&pseudo_element),
+        };
+    }
+
+    pub fn pseudo_element(&self, pseudo_element: &PseudoElement) -> Option<NodePtr> {
+        self.pseudo_elements.borrow().get(pseudo_element).cloned()
+    }
 }
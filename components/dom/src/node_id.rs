@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use shared::tree_node::WeakTreeNode;
+
+use crate::node::{Node, NodePtr};
+
+/// Source of node ids. Ids are unique in the process, so nodes of different
+/// documents never share an id.
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Identifier of a node, assigned when the node is created and kept for
+/// its whole lifetime.
+///
+/// Unlike `NodePtr`, an id is a plain number that can be sent to another
+/// thread or process, so external consumers such as devtools or embedders
+/// can refer to a node across events and look it up again later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub u64);
+
+impl NodeId {
+    pub(crate) fn next() -> Self {
+        NodeId(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl core::fmt::Display for NodeId {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Nodes of a document by id. The registry only holds weak references, a
+/// node that has been dropped can't be looked up anymore.
+pub struct NodeRegistry {
+    nodes: RefCell<HashMap<NodeId, WeakTreeNode<Node>>>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> Self {
+        Self {
+            nodes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Register the node and its descendants, including shadow trees
+    pub fn register(&self, node: &NodePtr) {
+        self.nodes
+            .borrow_mut()
+            .insert(node.id(), WeakTreeNode::from(&node.0));
+
+        if let Some(shadow_root) = node.as_element_opt().and_then(|e| e.shadow_root()) {
+            self.register(&shadow_root);
+        }
+        node.for_each_child(|child| self.register(&NodePtr(child)));
+    }
+
+    /// Find a node of `document` by id. Nodes inserted without notifying
+    /// the registry are found by indexing the document again on a miss.
+    pub fn get(&self, document: &NodePtr, id: NodeId) -> Option<NodePtr> {
+        if document.id() == id {
+            return Some(document.clone());
+        }
+        if let Some(node) = self.lookup(id) {
+            return Some(node);
+        }
+        self.nodes
+            .borrow_mut()
+            .retain(|_, node| node.upgrade().is_some());
+        self.register(document);
+        self.lookup(id)
+    }
+
+    fn lookup(&self, id: NodeId) -> Option<NodePtr> {
+        self.nodes
+            .borrow()
+            .get(&id)
+            .and_then(|node| node.upgrade())
+            .map(NodePtr)
+    }
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::TreeNode;
+
+    use super::*;
+    use crate::{create_element, document::Document, node::NodeData, text::Text};
+
+    #[test]
+    fn test_node_registry() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let div = create_element(WeakTreeNode::from(&document.0), "div");
+        let span = create_element(WeakTreeNode::from(&document.0), "span");
+        let text = TreeNode::new(Node::new(NodeData::Text(Text::new("hello".to_string()))));
+        text.set_document(WeakTreeNode::from(&document.0));
+        span.append_child(text.clone());
+        // The span is inserted with its child
+        div.append_child(span.0.clone());
+        document.append_child(div.0.clone());
+        let shadow_root = div.attach_shadow().unwrap();
+
+        assert_ne!(div.id(), span.id());
+        assert_ne!(span.id(), text.id());
+
+        for node in [&document, &div, &span, &NodePtr(text.clone()), &shadow_root] {
+            assert_eq!(
+                document.node_by_id(node.id()).map(|n| n.id()),
+                Some(node.id())
+            );
+        }
+
+        // Inserted before a sibling, which doesn't trigger the hooks
+        let p = create_element(WeakTreeNode::from(&document.0), "p");
+        div.insert_before(p.0.clone(), Some(span.0.clone()));
+        assert_eq!(document.node_by_id(p.id()).map(|n| n.id()), Some(p.id()));
+
+        let removed = span.id();
+        span.detach();
+        drop(span);
+        drop(text);
+        assert!(document.node_by_id(removed).is_none());
+        assert!(document.node_by_id(NodeId(u64::MAX)).is_none());
+    }
+}
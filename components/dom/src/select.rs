@@ -0,0 +1,191 @@
+use crate::elements::ElementData;
+use crate::node::NodePtr;
+
+/// Option children of the select element and of its optgroup children
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-select-option-list
+pub fn options(select: &NodePtr) -> Vec<NodePtr> {
+    let mut options = Vec::new();
+    select.for_each_child(|child| {
+        let child = NodePtr(child);
+        match tag_name(&child).as_deref() {
+            Some("option") => options.push(child),
+            Some("optgroup") => child.for_each_child(|grandchild| {
+                let grandchild = NodePtr(grandchild);
+                if tag_name(&grandchild).as_deref() == Some("option") {
+                    options.push(grandchild);
+                }
+            }),
+            _ => {}
+        }
+    });
+    options
+}
+
+/// Whether the select element shows a single option, with the others in a
+/// popup, instead of a list box
+pub fn is_drop_down(select: &NodePtr) -> bool {
+    let attributes = select.as_element().attributes();
+    let attributes = attributes.borrow();
+    !attributes.contains_key("multiple")
+        && attributes.get_str("size").parse::<u32>().unwrap_or(1) <= 1
+}
+
+/// Options that are selected. A drop-down select has a single selected
+/// option: the one chosen by the user, else the last option with a
+/// `selected` attribute, else its first enabled option.
+/// https://html.spec.whatwg.org/multipage/form-elements.html#selectedness-setting-algorithm
+pub fn selected_options(select: &NodePtr) -> Vec<NodePtr> {
+    let options = options(select);
+    let is_enabled = |option: &&NodePtr| !is_option_disabled(option);
+
+    if is_drop_down(select) {
+        let chosen = chosen_index(select).and_then(|index| options.get(index));
+        let last_selected = options
+            .iter()
+            .rev()
+            .find(|option| has_selected_attribute(option));
+        return chosen
+            .or(last_selected)
+            .or_else(|| options.iter().find(is_enabled))
+            .filter(is_enabled)
+            .cloned()
+            .into_iter()
+            .collect();
+    }
+
+    options
+        .iter()
+        .filter(|option| has_selected_attribute(option))
+        .filter(is_enabled)
+        .cloned()
+        .collect()
+}
+
+/// Choose the option at `index` of a drop-down select, as if picked by the
+/// user. Returns false if there is no enabled option at `index`.
+pub fn select_option(select: &NodePtr, index: usize) -> bool {
+    let is_selectable = options(select)
+        .get(index)
+        .is_some_and(|option| !is_option_disabled(option));
+    match select.as_element().data() {
+        ElementData::Select(data) if is_selectable && is_drop_down(select) => {
+            data.set_chosen_index(Some(index));
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Text shown for an option, its `label` attribute or else its text
+pub fn option_label(option: &NodePtr) -> String {
+    let label = option.as_element().attributes().borrow().get_str("label");
+    if !label.is_empty() {
+        return label;
+    }
+    option
+        .descendant_text_content()
+        .split_ascii_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Value of an option, which defaults to its text
+pub fn option_value(option: &NodePtr) -> String {
+    match option
+        .as_element()
+        .attributes()
+        .borrow()
+        .get("value")
+        .cloned()
+    {
+        Some(value) => value,
+        None => option
+            .descendant_text_content()
+            .split_ascii_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// Options are disabled by their own `disabled` attribute or by the one of
+/// their optgroup
+pub fn is_option_disabled(option: &NodePtr) -> bool {
+    let has_disabled = |node: &NodePtr| {
+        node.as_element_opt()
+            .is_some_and(|element| element.has_attribute("disabled"))
+    };
+    let optgroup = option
+        .parent()
+        .map(NodePtr)
+        .filter(|parent| tag_name(parent).as_deref() == Some("optgroup"));
+    has_disabled(option) || optgroup.is_some_and(|optgroup| has_disabled(&optgroup))
+}
+
+fn chosen_index(select: &NodePtr) -> Option<usize> {
+    match select.as_element().data() {
+        ElementData::Select(data) => data.chosen_index(),
+        _ => None,
+    }
+}
+
+fn has_selected_attribute(option: &NodePtr) -> bool {
+    option.as_element().has_attribute("selected")
+}
+
+fn tag_name(node: &NodePtr) -> Option<String> {
+    node.as_element_opt().map(|element| element.tag_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+
+    fn element(parent: &NodePtr, tag_name: &str, attrs: &[(&str, &str)]) -> NodePtr {
+        let document =
+            WeakTreeNode::from(&parent.owner_document().unwrap_or_else(|| parent.0.clone()));
+        let element = create_element(document, tag_name);
+        for (name, value) in attrs {
+            element.as_element().set_attribute(name, value);
+        }
+        parent.append_child(element.0.clone());
+        element
+    }
+
+    #[test]
+    fn test_selected_options() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let select = element(&document, "select", &[]);
+        let first = element(&select, "option", &[("disabled", "")]);
+        let group = element(&select, "optgroup", &[]);
+        let second = element(&group, "option", &[("label", "Second")]);
+        let third = element(&group, "option", &[]);
+        let disabled_group = element(&select, "optgroup", &[("disabled", "")]);
+        element(&disabled_group, "option", &[]);
+
+        let ids = |options: Vec<NodePtr>| options.iter().map(|o| o.id()).collect::<Vec<_>>();
+        assert_eq!(options(&select).len(), 4);
+        assert_eq!(ids(selected_options(&select)), vec![second.id()]);
+        assert_eq!(option_label(&second), "Second");
+
+        third.as_element().set_attribute("selected", "");
+        assert_eq!(ids(selected_options(&select)), vec![third.id()]);
+
+        assert!(!select_option(&select, 0));
+        assert!(!select_option(&select, 3));
+        assert!(select_option(&select, 1));
+        assert_eq!(ids(selected_options(&select)), vec![second.id()]);
+
+        // List boxes only select the options with a selected attribute
+        first.as_element().set_attribute("selected", "");
+        select.as_element().set_attribute("multiple", "");
+        assert_eq!(ids(selected_options(&select)), vec![third.id()]);
+        assert!(!select_option(&select, 2));
+    }
+}
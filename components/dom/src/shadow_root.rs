@@ -0,0 +1,67 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use shared::tree_node::{TreeNode, WeakTreeNode};
+use style_types::{CascadeOrigin, ContextualRule, ContextualStyleSheet};
+
+use super::document::{collect_contextual_rules, Document};
+use super::node::{Node, NodeHooks};
+
+/// Root of a shadow tree attached to an element, its host.
+/// https://dom.spec.whatwg.org/#interface-shadowroot
+pub struct ShadowRoot {
+    host: WeakTreeNode<Node>,
+    /// Stylesheets of the `style` elements in the shadow tree. They only
+    /// apply to the elements of the shadow tree.
+    stylesheets: RefCell<Vec<Rc<ContextualStyleSheet>>>,
+}
+
+impl core::fmt::Debug for ShadowRoot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "ShadowRoot")
+    }
+}
+
+impl NodeHooks for ShadowRoot {}
+
+impl ShadowRoot {
+    pub fn new(host: WeakTreeNode<Node>) -> Self {
+        Self {
+            host,
+            stylesheets: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn host(&self) -> Option<TreeNode<Node>> {
+        self.host.upgrade()
+    }
+
+    pub fn append_stylesheet(&self, stylesheet: ContextualStyleSheet) -> Rc<ContextualStyleSheet> {
+        let stylesheet_ptr = Rc::new(stylesheet);
+        self.stylesheets.borrow_mut().push(stylesheet_ptr.clone());
+        stylesheet_ptr
+    }
+
+    pub fn remove_stylesheet(&self, stylesheet: &Rc<ContextualStyleSheet>) {
+        self.stylesheets
+            .borrow_mut()
+            .retain(|sheet| !Rc::ptr_eq(sheet, stylesheet));
+    }
+
+    /// Style rules that apply to the elements of the shadow tree: the
+    /// user-agent rules of the document, then the rules of the shadow tree.
+    /// Other rules of the document don't cross the shadow boundary.
+    pub fn style_rules(&self, document: &Document) -> Vec<ContextualRule> {
+        let mut rules = document
+            .style_rules()
+            .into_iter()
+            .filter(|rule| rule.origin == CascadeOrigin::UserAgent)
+            .collect::<Vec<ContextualRule>>();
+
+        let environment = document.media_environment();
+        for stylesheet in self.stylesheets.borrow().iter() {
+            collect_contextual_rules(&stylesheet.inner, stylesheet, &environment, &mut rules);
+        }
+        rules
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use style_types::{Property, Value};
+
+use crate::node::{NodeData, NodePtr};
+use crate::node_id::NodeId;
+
+/// Resolved style values of a node as CSS text, keyed by property name.
+/// Plain strings keep it easy to serialize and to send across threads.
+pub type ComputedStyleSnapshot = BTreeMap<String, String>;
+
+/// An immutable copy of a document and its computed styles.
+///
+/// Unlike the live `Rc`-based tree, a snapshot can be sent across threads,
+/// so consumers such as the devtools server or the search indexer can read
+/// the document without touching the render thread.
+#[derive(Debug, Clone)]
+pub struct DomSnapshot {
+    generation: u64,
+    root: Arc<SnapshotNode>,
+}
+
+#[derive(Debug)]
+pub struct SnapshotNode {
+    pub id: NodeId,
+    pub data: SnapshotNodeData,
+    pub computed_styles: HashMap<Property, Value>,
+    pub children: Vec<Arc<SnapshotNode>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SnapshotNodeData {
+    Document {
+        title: String,
+    },
+    Element {
+        tag_name: String,
+        id: Option<String>,
+        class_name: String,
+        attributes: HashMap<String, String>,
+    },
+    Text(String),
+    Comment(String),
+    ShadowRoot,
+}
+
+impl DomSnapshot {
+    /// Freeze the current state of the document
+    pub fn capture(document: &NodePtr) -> Self {
+        Self {
+            generation: document.as_document().generation(),
+            root: Arc::new(SnapshotNode::capture(document)),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn root(&self) -> &SnapshotNode {
+        &self.root
+    }
+
+    /// Check if the document has changed since this snapshot was taken
+    pub fn is_stale(&self, document: &NodePtr) -> bool {
+        document.as_document().generation() != self.generation
+    }
+}
+
+impl SnapshotNode {
+    fn capture(node: &NodePtr) -> Self {
+        let data = match node.data() {
+            Some(NodeData::Document(document)) => SnapshotNodeData::Document {
+                title: document.title(),
+            },
+            Some(NodeData::Element(element)) => SnapshotNodeData::Element {
+                tag_name: element.tag_name(),
+                id: element.id(),
+                class_name: element.class_list().borrow().value(),
+                attributes: HashMap::clone(&element.attributes().borrow()),
+            },
+            Some(NodeData::Text(text)) => SnapshotNodeData::Text(text.get_data()),
+            Some(NodeData::Comment(comment)) => SnapshotNodeData::Comment(comment.get_data()),
+            Some(NodeData::ShadowRoot(_)) => SnapshotNodeData::ShadowRoot,
+            None => SnapshotNodeData::Comment(String::new()),
+        };
+
+        let mut children = Vec::new();
+        node.for_each_child(|child| children.push(Arc::new(Self::capture(&NodePtr(child)))));
+
+        Self {
+            id: node.id(),
+            data,
+            computed_styles: node.computed_styles().clone(),
+            children,
+        }
+    }
+
+    pub fn tag_name(&self) -> Option<&str> {
+        match &self.data {
+            SnapshotNodeData::Element { tag_name, .. } => Some(tag_name),
+            _ => None,
+        }
+    }
+
+    /// Descendant text content of the node
+    /// https://dom.spec.whatwg.org/#concept-descendant-text-content
+    pub fn descendant_text_content(&self) -> String {
+        if let SnapshotNodeData::Text(text) = &self.data {
+            return text.clone();
+        }
+        let mut result = String::new();
+        for child in &self.children {
+            result.push_str(&child.descendant_text_content());
+        }
+        result
+    }
+
+    /// Visit this node and all of its descendants in tree order
+    pub fn walk<F: FnMut(&SnapshotNode)>(&self, callback: &mut F) {
+        callback(self);
+        for child in &self.children {
+            child.walk(callback);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use style_types::values::prelude::{Color, Length};
+
+    use super::*;
+    use crate::{create_element, document::Document, node::Node, text::Text};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_snapshot_is_thread_safe() {
+        assert_send_sync::<DomSnapshot>();
+    }
+
+    #[test]
+    fn test_capture_and_staleness() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let div = create_element(WeakTreeNode::from(&document.0), "div");
+        let text = TreeNode::new(Node::new(NodeData::Text(Text::new("hello".to_string()))));
+        text.set_document(WeakTreeNode::from(&document.0));
+        div.append_child(text);
+        document.append_child(div.0.clone());
+
+        let snapshot = DomSnapshot::capture(&document);
+        assert!(!snapshot.is_stale(&document));
+        assert_eq!(snapshot.root().children[0].tag_name(), Some("div"));
+        assert_eq!(snapshot.root().children[0].id, div.id());
+        assert_eq!(snapshot.root().descendant_text_content(), "hello");
+
+        div.append_child(create_element(WeakTreeNode::from(&document.0), "div").0);
+        assert!(snapshot.is_stale(&document));
+    }
+
+    #[test]
+    fn test_computed_style_snapshot() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let div = create_element(WeakTreeNode::from(&document.0), "div");
+        assert!(div.computed_style_snapshot().is_empty());
+
+        let red = Color::Rgba(255.0.into(), 0.0.into(), 0.0.into(), 255.0.into());
+        div.set_computed_styles(HashMap::from([
+            (Property::Color, Value::Color(red)),
+            (Property::BorderTopColor, Value::Color(Color::CurrentColor)),
+            (Property::FontSize, Value::Length(Length::new_px(16.))),
+        ]));
+
+        let style = div.computed_style_snapshot();
+        assert_eq!(style["color"], "rgb(255, 0, 0)");
+        assert_eq!(style["border-top-color"], "rgb(255, 0, 0)");
+        assert_eq!(style["font-size"], "16px");
+    }
+}
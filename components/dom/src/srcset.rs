@@ -0,0 +1,311 @@
+//! Choice of the source of an image among the candidates of its `srcset`,
+//! for the density of the display and the width the image is shown at
+//! https://html.spec.whatwg.org/multipage/images.html#srcset-attributes
+
+use css::media_query::parse_media_query_list;
+use css::media_query::structs::MediaEnvironment;
+use css::parser::{structs::ComponentValue, Parser};
+use css::tokenizer::{token::Token, Tokenizer};
+
+use crate::node::NodePtr;
+
+/// Font size used to resolve font-relative lengths of `sizes`
+const INITIAL_FONT_SIZE: f32 = 16.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Descriptor {
+    /// Pixels of the image per CSS pixel, like `2x`
+    Density(f32),
+    /// Width of the image in pixels, like `800w`
+    Width(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageCandidate {
+    pub url: String,
+    pub descriptor: Descriptor,
+}
+
+/// Candidates of a `srcset` attribute. Candidates with invalid descriptors
+/// are left out, and a candidate without descriptor is `1x`.
+pub fn parse_srcset(srcset: &str) -> Vec<ImageCandidate> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+        if rest.is_empty() {
+            return candidates;
+        }
+        let url_end = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let (url, after_url) = rest.split_at(url_end);
+
+        // A URL ending with commas has no descriptors, otherwise they run
+        // until the next comma outside of parentheses
+        let descriptors = if url.ends_with(',') {
+            rest = after_url;
+            ""
+        } else {
+            let end = descriptors_end(after_url);
+            rest = &after_url[end..];
+            &after_url[..end]
+        };
+        let url = url.trim_end_matches(',');
+        if let Some(descriptor) = parse_descriptors(descriptors) {
+            candidates.push(ImageCandidate {
+                url: url.to_string(),
+                descriptor,
+            });
+        }
+    }
+}
+
+fn descriptors_end(descriptors: &str) -> usize {
+    let mut depth = 0usize;
+    for (index, c) in descriptors.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => return index,
+            _ => {}
+        }
+    }
+    descriptors.len()
+}
+
+fn parse_descriptors(descriptors: &str) -> Option<Descriptor> {
+    let mut descriptor = None;
+    let mut has_height = false;
+    for token in descriptors.split_ascii_whitespace() {
+        let (value, kind) = token.split_at(token.len() - token.chars().last()?.len_utf8());
+        match kind {
+            "x" | "w" if descriptor.is_some() => return None,
+            "x" => {
+                let density = value.parse::<f32>().ok()?;
+                if !density.is_finite() || density < 0. {
+                    return None;
+                }
+                descriptor = Some(Descriptor::Density(density));
+            }
+            "w" => match value.parse::<u32>().ok()? {
+                0 => return None,
+                width => descriptor = Some(Descriptor::Width(width as f32)),
+            },
+            // The height is only a hint for the layout before the image
+            // is loaded
+            "h" if !has_height && value.parse::<u32>().ok()? > 0 => has_height = true,
+            _ => return None,
+        }
+    }
+    match descriptor {
+        Some(Descriptor::Density(_)) if has_height => None,
+        Some(descriptor) => Some(descriptor),
+        None if has_height => None,
+        None => Some(Descriptor::Density(1.)),
+    }
+}
+
+/// Width in CSS pixels the image is shown at according to `sizes`, the
+/// size of the first entry whose media condition matches. `auto` is the
+/// width the image is laid out at, if it is known. The width of the
+/// viewport is used when no entry applies.
+/// https://html.spec.whatwg.org/multipage/images.html#sizes-attributes
+pub fn source_size(sizes: &str, environment: &MediaEnvironment, layout_width: Option<f32>) -> f32 {
+    // The tokenizer looks 3 characters ahead for the unit of a dimension,
+    // which a size at the end of the attribute doesn't have
+    let tokenizer = Tokenizer::new(sizes.chars().chain("  ".chars()));
+    let mut parser = Parser::<Token>::new(tokenizer.run());
+    for entry in parser.parse_a_comma_separated_list_of_component_values() {
+        let mut values: Vec<ComponentValue> = entry
+            .into_iter()
+            .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+            .collect();
+        let size = match values
+            .pop()
+            .and_then(|size| size_value(&size, environment, layout_width))
+        {
+            Some(size) => size,
+            None => continue,
+        };
+        if values.is_empty() || parse_media_query_list(&values).evaluate(environment) {
+            return size;
+        }
+    }
+    environment.viewport_width
+}
+
+fn size_value(
+    value: &ComponentValue,
+    environment: &MediaEnvironment,
+    layout_width: Option<f32>,
+) -> Option<f32> {
+    match value {
+        ComponentValue::PerservedToken(Token::Dimension { value, unit, .. }) if *value >= 0. => {
+            let viewport_min = f32::min(environment.viewport_width, environment.viewport_height);
+            let viewport_max = f32::max(environment.viewport_width, environment.viewport_height);
+            match unit.to_ascii_lowercase().as_str() {
+                "px" => Some(*value),
+                "em" | "rem" => Some(value * INITIAL_FONT_SIZE),
+                "vw" => Some(value * environment.viewport_width / 100.),
+                "vh" => Some(value * environment.viewport_height / 100.),
+                "vmin" => Some(value * viewport_min / 100.),
+                "vmax" => Some(value * viewport_max / 100.),
+                _ => None,
+            }
+        }
+        ComponentValue::PerservedToken(Token::Number { value, .. }) if *value == 0. => Some(0.),
+        ComponentValue::PerservedToken(Token::Ident(ident))
+            if ident.eq_ignore_ascii_case("auto") =>
+        {
+            layout_width
+        }
+        _ => None,
+    }
+}
+
+/// URL of the candidate suited to a display of `device_pixel_ratio`: the
+/// least dense candidate that is at least as dense as the display, or the
+/// densest one if none is. Widths are turned into densities by dividing
+/// them by the source size. `src` is a `1x` candidate when `srcset` has no
+/// widths and no other `1x` candidate.
+pub fn select_source(
+    src: &str,
+    srcset: &str,
+    sizes: &str,
+    environment: &MediaEnvironment,
+    device_pixel_ratio: f32,
+    layout_width: Option<f32>,
+) -> Option<String> {
+    let candidates = parse_srcset(srcset);
+    let has_widths = candidates
+        .iter()
+        .any(|candidate| matches!(candidate.descriptor, Descriptor::Width(_)));
+    let source_size = match has_widths {
+        true => source_size(sizes, environment, layout_width),
+        false => 0.,
+    };
+
+    let mut densities: Vec<(f32, &str)> = candidates
+        .iter()
+        .map(|candidate| match candidate.descriptor {
+            Descriptor::Density(density) => (density, candidate.url.as_str()),
+            Descriptor::Width(width) => (width / source_size, candidate.url.as_str()),
+        })
+        .collect();
+    let has_1x = densities.iter().any(|(density, _)| *density == 1.);
+    if !src.is_empty() && !has_widths && !has_1x {
+        densities.push((1., src));
+    }
+    // The first candidate wins among those of the same density
+    densities.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    densities
+        .iter()
+        .find(|(density, _)| *density >= device_pixel_ratio)
+        .or(densities.last())
+        .map(|(_, url)| url.to_string())
+}
+
+/// URL of the source of the `img` element, see `select_source`
+pub fn select_image_source(
+    image: &NodePtr,
+    environment: &MediaEnvironment,
+    device_pixel_ratio: f32,
+    layout_width: Option<f32>,
+) -> Option<String> {
+    let element = image.as_element();
+    let attribute = |name: &str| element.get_attribute(name).unwrap_or_default();
+    select_source(
+        attribute("src").trim(),
+        &attribute("srcset"),
+        &attribute("sizes"),
+        environment,
+        device_pixel_ratio,
+        layout_width,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srcset() {
+        let candidates = parse_srcset(
+            " small.jpg 480w, data:image/png;base64,AAA=, large.jpg 2x,\
+             ,invalid.jpg 2q, both.jpg 1x 100w, tall.jpg 600w 400h,\nplain.jpg",
+        );
+        let candidate = |url: &str, descriptor| ImageCandidate {
+            url: url.to_string(),
+            descriptor,
+        };
+        assert_eq!(
+            candidates,
+            [
+                candidate("small.jpg", Descriptor::Width(480.)),
+                candidate("data:image/png;base64,AAA=", Descriptor::Density(1.)),
+                candidate("large.jpg", Descriptor::Density(2.)),
+                candidate("tall.jpg", Descriptor::Width(600.)),
+                candidate("plain.jpg", Descriptor::Density(1.)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_source() {
+        let environment = MediaEnvironment::new(800., 600.);
+        let select = |src, srcset, sizes, device_pixel_ratio, layout_width| {
+            select_source(
+                src,
+                srcset,
+                sizes,
+                &environment,
+                device_pixel_ratio,
+                layout_width,
+            )
+        };
+
+        // Densities, with `src` as the 1x candidate
+        let srcset = "photo@2x.jpg 2x, photo@3x.jpg 3x";
+        assert_eq!(
+            select("photo.jpg", srcset, "", 1., None).unwrap(),
+            "photo.jpg"
+        );
+        assert_eq!(
+            select("photo.jpg", srcset, "", 1.5, None).unwrap(),
+            "photo@2x.jpg"
+        );
+        assert_eq!(
+            select("photo.jpg", srcset, "", 4., None).unwrap(),
+            "photo@3x.jpg"
+        );
+        assert_eq!(select("", "", "", 1., None), None);
+
+        // Widths, relative to the first matching size
+        let srcset = "400.jpg 400w, 800.jpg 800w, 1600.jpg 1600w";
+        let sizes = "(max-width: 500px) 100vw, (min-width: 1000px) 50em, 400px";
+        assert_eq!(select("", srcset, sizes, 1., None).unwrap(), "400.jpg");
+        assert_eq!(select("", srcset, sizes, 2., None).unwrap(), "800.jpg");
+        // The whole viewport without `sizes`
+        assert_eq!(select("", srcset, "", 1., None).unwrap(), "800.jpg");
+        assert_eq!(select("", srcset, "", 1.5, None).unwrap(), "1600.jpg");
+        // `auto` is the layout width, when it is known
+        assert_eq!(
+            select("", srcset, "auto, 100vw", 1., Some(300.)).unwrap(),
+            "400.jpg"
+        );
+        assert_eq!(
+            select("", srcset, "auto, 100vw", 1., None).unwrap(),
+            "800.jpg"
+        );
+
+        let narrow = MediaEnvironment::new(480., 800.);
+        assert_eq!(source_size(sizes, &narrow, None), 480.);
+        assert_eq!(source_size(sizes, &environment, None), 400.);
+        assert_eq!(
+            source_size(sizes, &MediaEnvironment::new(1200., 800.), None),
+            800.
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use css::cssom::{css_rule::CSSRule, font_face_rule::FontFaceRule, stylesheet::StyleSheet};
+use css::parser::Parser;
+use css::tokenizer::{token::Token, Tokenizer};
+#[cfg(not(target_arch = "wasm32"))]
+use loader::{ResourceLoader, ResourceType};
+#[cfg(not(target_arch = "wasm32"))]
+use shared::byte_string::ByteString;
+use url::{parser::URLParser, Url};
+
+/// Maximum number of nested `@import` rules that are followed
+const MAX_IMPORT_DEPTH: usize = 16;
+
+/// Fetch a stylesheet of the document at `document_url` and the
+/// stylesheets it imports
+pub fn load_stylesheet(url: &Url, document_url: Option<&Url>) -> Option<StyleSheet> {
+    log::info!("Loading stylesheet from: {}", url);
+
+    let fetch = |url: &Url| fetch_css(url, document_url);
+    let css = fetch(url)?;
+    Some(parse_stylesheet_with(&css, Some(url.clone()), &fetch))
+}
+
+/// Parse a stylesheet of the document at `base` and load the stylesheets it
+/// imports. Imported URLs are resolved against `base`.
+pub fn parse_stylesheet(css: &str, base: Option<Url>) -> StyleSheet {
+    let document_url = base.clone();
+    parse_stylesheet_with(css, base, &|url| fetch_css(url, document_url.as_ref()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fetch_css(url: &Url, document_url: Option<&Url>) -> Option<String> {
+    match ResourceLoader::current().load_subresource(url, ResourceType::Stylesheet, document_url) {
+        Ok(bytes) => Some(ByteString::new(&bytes).to_string()),
+        Err(e) => {
+            log::error!("Unable to load CSS: {} ({})", e, url);
+            None
+        }
+    }
+}
+
+/// There is no resource loader in the browser, pages there inline their
+/// stylesheets
+#[cfg(target_arch = "wasm32")]
+fn fetch_css(url: &Url, _document_url: Option<&Url>) -> Option<String> {
+    log::warn!("Unable to load CSS without a resource loader: {}", url);
+    None
+}
+
+fn parse_stylesheet_with(
+    css: &str,
+    base: Option<Url>,
+    fetch: &dyn Fn(&Url) -> Option<String>,
+) -> StyleSheet {
+    let mut stylesheet = parse_css(css);
+    let mut import_chain = base.iter().map(|url| url.as_str()).collect();
+    resolve_imports(&mut stylesheet, base, fetch, &mut import_chain);
+    stylesheet
+}
+
+/// Load the stylesheets imported by `stylesheet` and resolve the URLs of its
+/// font files. `import_chain` holds the URLs of the stylesheets that lead to
+/// this one, to break import cycles.
+fn resolve_imports(
+    stylesheet: &mut StyleSheet,
+    base: Option<Url>,
+    fetch: &dyn Fn(&Url) -> Option<String>,
+    import_chain: &mut Vec<String>,
+) {
+    for rule in stylesheet.css_rules.0.iter_mut() {
+        let import_rule = match rule {
+            CSSRule::Import(import_rule) => import_rule,
+            CSSRule::FontFace(font_face) => {
+                resolve_font_sources(font_face, &base);
+                continue;
+            }
+            _ => continue,
+        };
+
+        let url = match URLParser::parse(&import_rule.href, base.clone()) {
+            Some(url) => url,
+            None => {
+                log::warn!("Invalid @import URL: {}", import_rule.href);
+                continue;
+            }
+        };
+
+        if import_chain.contains(&url.as_str()) {
+            log::warn!("Ignoring @import cycle at: {}", url);
+            continue;
+        }
+        if import_chain.len() >= MAX_IMPORT_DEPTH {
+            log::warn!("Ignoring @import nested too deeply: {}", url);
+            continue;
+        }
+
+        let css = match fetch(&url) {
+            Some(css) => css,
+            None => continue,
+        };
+
+        let mut imported = parse_css(&css);
+        import_chain.push(url.as_str());
+        resolve_imports(&mut imported, Some(url), fetch, import_chain);
+        import_chain.pop();
+
+        import_rule.stylesheet = Some(imported);
+    }
+}
+
+/// Font files are relative to the stylesheet that declares them
+fn resolve_font_sources(font_face: &mut FontFaceRule, base: &Option<Url>) {
+    for source in font_face.sources.iter_mut() {
+        match URLParser::parse(&source.url, base.clone()) {
+            Some(url) => source.url = url.as_str(),
+            None => log::warn!("Invalid font URL: {}", source.url),
+        }
+    }
+}
+
+fn parse_css(css: &str) -> StyleSheet {
+    let tokenizer = Tokenizer::new(css.chars());
+    let mut parser = Parser::<Token>::new(tokenizer.run());
+    parser.parse_a_css_stylesheet()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        URLParser::parse(url, None).unwrap()
+    }
+
+    fn imported_stylesheet(stylesheet: &StyleSheet, index: usize) -> Option<&StyleSheet> {
+        match &stylesheet[index] {
+            CSSRule::Import(import_rule) => import_rule.stylesheet.as_ref(),
+            rule => panic!("Expected an import rule, got {:?}", rule),
+        }
+    }
+
+    #[test]
+    fn test_resolve_imports() {
+        let files = HashMap::from([
+            (
+                "http://example.com/css/theme.css",
+                "@import 'colors.css'; @import '/main.css'; h1 { color: red; } \
+                 @font-face { font-family: Lato; src: url(../fonts/lato.ttf); }",
+            ),
+            ("http://example.com/css/colors.css", "p { color: blue; }"),
+            ("http://example.com/main.css", "@import 'css/theme.css';"),
+        ]);
+        let fetch = |url: &Url| files.get(url.as_str().as_str()).map(|css| css.to_string());
+
+        let stylesheet = parse_stylesheet_with(
+            "@import url(css/theme.css); @import 'missing.css';",
+            Some(url("http://example.com/index.html")),
+            &fetch,
+        );
+
+        let theme = imported_stylesheet(&stylesheet, 0).unwrap();
+        assert!(imported_stylesheet(&stylesheet, 1).is_none());
+
+        match &theme[3] {
+            CSSRule::FontFace(font_face) => assert_eq!(
+                font_face.sources[0].url,
+                "http://example.com/fonts/lato.ttf"
+            ),
+            rule => panic!("Expected a font-face rule, got {:?}", rule),
+        }
+
+        let colors = imported_stylesheet(theme, 0).unwrap();
+        assert!(matches!(colors[0], CSSRule::Style(_)));
+
+        // main.css imports theme.css again, which is a cycle
+        let main = imported_stylesheet(theme, 1).unwrap();
+        assert!(imported_stylesheet(main, 0).is_none());
+    }
+}
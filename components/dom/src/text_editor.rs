@@ -0,0 +1,213 @@
+use std::ops::Range;
+
+/// Text being edited in a form control, with its caret and selection.
+/// Offsets are byte offsets in the text, always at character boundaries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEditor {
+    text: String,
+    caret: usize,
+    /// Where the selection started, equal to the caret when nothing is
+    /// selected
+    anchor: usize,
+    /// Vertical scroll position of the content in the control, in CSS pixels
+    scroll_top: f32,
+}
+
+impl TextEditor {
+    /// Edit the text with the caret at its end
+    pub fn new(text: String) -> Self {
+        let end = text.len();
+        Self {
+            text,
+            caret: end,
+            anchor: end,
+            scroll_top: 0.,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// Selected range of the text, empty if nothing is selected
+    pub fn selection(&self) -> Range<usize> {
+        usize::min(self.anchor, self.caret)..usize::max(self.anchor, self.caret)
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    pub fn scroll_top(&self) -> f32 {
+        self.scroll_top
+    }
+
+    pub fn set_scroll_top(&mut self, scroll_top: f32) {
+        self.scroll_top = f32::max(scroll_top, 0.);
+    }
+
+    /// Replace the selection with the text, moving the caret after it
+    pub fn insert(&mut self, text: &str) {
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), text);
+        self.caret = selection.start + text.len();
+        self.anchor = self.caret;
+    }
+
+    /// Delete the selection, or the character before the caret
+    pub fn delete_backward(&mut self) {
+        if self.selection().is_empty() {
+            self.anchor = self.previous_boundary(self.caret);
+        }
+        self.insert("");
+    }
+
+    /// Delete the selection, or the character after the caret
+    pub fn delete_forward(&mut self) {
+        if self.selection().is_empty() {
+            self.anchor = self.next_boundary(self.caret);
+        }
+        self.insert("");
+    }
+
+    /// Move the caret one character to the left. Without `extend`, a
+    /// selection collapses to its start instead.
+    pub fn move_left(&mut self, extend: bool) {
+        let selection = self.selection();
+        let offset = if !extend && !selection.is_empty() {
+            selection.start
+        } else {
+            self.previous_boundary(self.caret)
+        };
+        self.move_to(offset, extend);
+    }
+
+    /// Move the caret one character to the right. Without `extend`, a
+    /// selection collapses to its end instead.
+    pub fn move_right(&mut self, extend: bool) {
+        let selection = self.selection();
+        let offset = if !extend && !selection.is_empty() {
+            selection.end
+        } else {
+            self.next_boundary(self.caret)
+        };
+        self.move_to(offset, extend);
+    }
+
+    /// Move the caret to the start of the word before it
+    pub fn move_word_left(&mut self, extend: bool) {
+        let before = &self.text[..self.caret];
+        let word_end = before.trim_end_matches(|ch: char| !is_word_char(ch)).len();
+        let word_start = before[..word_end].trim_end_matches(is_word_char).len();
+        self.move_to(word_start, extend);
+    }
+
+    /// Move the caret to the end of the word after it
+    pub fn move_word_right(&mut self, extend: bool) {
+        let after = &self.text[self.caret..];
+        let word_start = after.len() - after.trim_start_matches(|ch: char| !is_word_char(ch)).len();
+        let word_end = after.len() - after[word_start..].trim_start_matches(is_word_char).len();
+        self.move_to(self.caret + word_end, extend);
+    }
+
+    /// Move the caret to the offset, extending the selection to it if
+    /// `extend` is true
+    pub fn move_to(&mut self, offset: usize, extend: bool) {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.text.len();
+    }
+
+    fn previous_boundary(&self, offset: usize) -> usize {
+        self.text[..offset]
+            .char_indices()
+            .next_back()
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn next_boundary(&self, offset: usize) -> usize {
+        self.text[offset..]
+            .chars()
+            .next()
+            .map_or(offset, |ch| offset + ch.len_utf8())
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_text() {
+        let mut editor = TextEditor::new("héllo".to_string());
+        editor.delete_backward();
+        assert_eq!(editor.text(), "héll");
+
+        editor.move_to(3, false);
+        editor.move_left(false);
+        editor.delete_forward();
+        assert_eq!(editor.text(), "hll");
+        assert_eq!(editor.caret(), 1);
+
+        editor.insert("e\n");
+        assert_eq!(editor.text(), "he\nll");
+        assert_eq!(editor.caret(), 3);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut editor = TextEditor::new("one two".to_string());
+        editor.move_to(4, false);
+        editor.move_right(true);
+        editor.move_right(true);
+        assert_eq!(editor.selected_text(), "tw");
+
+        editor.move_left(false);
+        assert_eq!(editor.caret(), 4);
+        assert!(editor.selection().is_empty());
+
+        editor.move_to(0, true);
+        editor.insert("1");
+        assert_eq!(editor.text(), "1two");
+
+        editor.select_all();
+        editor.delete_backward();
+        assert_eq!(editor.text(), "");
+    }
+
+    #[test]
+    fn test_move_by_word() {
+        let mut editor = TextEditor::new("one, two_2  three".to_string());
+        editor.move_word_left(false);
+        assert_eq!(editor.caret(), 12);
+        editor.move_word_left(true);
+        assert_eq!(editor.selected_text(), "two_2  ");
+
+        editor.move_to(0, false);
+        editor.move_word_right(false);
+        assert_eq!(editor.caret(), 3);
+        editor.move_word_right(false);
+        assert_eq!(editor.caret(), 10);
+        editor.move_to(17, false);
+        editor.move_word_right(false);
+        assert_eq!(editor.caret(), 17);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::elements::ElementData;
+use crate::node::NodePtr;
+use crate::text_editor::TextEditor;
+
+/// Current text of a textarea element
+/// https://html.spec.whatwg.org/multipage/form-elements.html#concept-fe-api-value
+pub fn value(textarea: &NodePtr) -> String {
+    if let ElementData::TextArea(data) = textarea.as_element().data() {
+        if let Some(editor) = data.editor().borrow().as_ref() {
+            return editor.text().to_string();
+        }
+    }
+    default_value(textarea)
+}
+
+/// Vertical scroll position of the text in the textarea, in CSS pixels
+pub fn scroll_top(textarea: &NodePtr) -> f32 {
+    match textarea.as_element().data() {
+        ElementData::TextArea(data) => data
+            .editor()
+            .borrow()
+            .as_ref()
+            .map_or(0., |editor| editor.scroll_top()),
+        _ => 0.,
+    }
+}
+
+/// Run `f` with the editor of a textarea element, which starts with the
+/// text content of the textarea. Returns `None` for other elements.
+pub fn edit<R>(textarea: &NodePtr, f: impl FnOnce(&mut TextEditor) -> R) -> Option<R> {
+    let data = match textarea.as_element_opt()?.data() {
+        ElementData::TextArea(data) => data,
+        _ => return None,
+    };
+    let mut editor = data.editor().borrow_mut();
+    let editor = editor.get_or_insert_with(|| TextEditor::new(default_value(textarea)));
+    Some(f(editor))
+}
+
+/// Number of visible lines, from the `rows` attribute
+pub fn rows(textarea: &NodePtr) -> u32 {
+    positive_attribute(textarea, "rows").unwrap_or(2)
+}
+
+/// Number of characters per line, from the `cols` attribute
+pub fn cols(textarea: &NodePtr) -> u32 {
+    positive_attribute(textarea, "cols").unwrap_or(20)
+}
+
+/// The text content, with line breaks normalized to LF
+fn default_value(textarea: &NodePtr) -> String {
+    textarea
+        .descendant_text_content()
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+fn positive_attribute(element: &NodePtr, name: &str) -> Option<u32> {
+    element
+        .as_element()
+        .attributes()
+        .borrow()
+        .get_str(name)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value| *value > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::tree_node::{TreeNode, WeakTreeNode};
+
+    use super::*;
+    use crate::create_element;
+    use crate::document::Document;
+    use crate::node::{Node, NodeData};
+    use crate::text::Text;
+
+    #[test]
+    fn test_textarea_value() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let textarea = create_element(WeakTreeNode::from(&document.0), "textarea");
+        textarea.as_element().set_attribute("rows", "0");
+        textarea.as_element().set_attribute("cols", "40");
+        let text = Node::new(NodeData::Text(Text::new("a\r\nb".to_string())));
+        text.set_document(WeakTreeNode::from(&document.0));
+        textarea.append_child(TreeNode::new(text));
+
+        assert_eq!(value(&textarea), "a\nb");
+        assert_eq!((rows(&textarea), cols(&textarea)), (2, 40));
+
+        edit(&textarea, |editor| editor.insert("c")).unwrap();
+        assert_eq!(value(&textarea), "a\nbc");
+        assert_eq!(textarea.descendant_text_content(), "a\r\nb");
+    }
+}
@@ -0,0 +1,38 @@
+struct VertexOutput {
+  [[location(0)]] tex_coords: vec2<f32>;
+  [[builtin(position)]] position: vec4<f32>;
+};
+
+[[block]]
+struct Uniforms {
+  screen_size: vec2<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> uniforms: Uniforms;
+
+[[group(1), binding(0)]]
+var image_texture: texture_2d<f32>;
+
+[[group(1), binding(1)]]
+var image_sampler: sampler;
+
+[[stage(vertex)]]
+fn vs_main(
+  [[location(0)]] position: vec2<f32>,
+  [[location(1)]] tex_coords: vec2<f32>,
+) -> VertexOutput {
+  // map position to NDC
+  let x = position.x / uniforms.screen_size.x * 2.0 - 1.0;
+  let y = 1.0 - position.y / uniforms.screen_size.y * 2.0;
+
+  var out: VertexOutput;
+  out.tex_coords = tex_coords;
+  out.position = vec4<f32>(x, y, 0.0, 1.0);
+  return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+  return textureSample(image_texture, image_sampler, in.tex_coords);
+}
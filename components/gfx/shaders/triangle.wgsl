@@ -17,9 +17,14 @@ fn map(value: f32, min1: f32, max1: f32, min2: f32, max2: f32) -> f32 {
 
 [[stage(vertex)]]
 fn vs_main(
-  [[location(0)]] position: vec2<f32>,
-  [[location(1)]] color: vec4<f32>,
+  [[location(0)]] vertex_position: vec2<f32>,
+  [[location(1)]] vertex_color: vec4<f32>,
+  [[location(2)]] instance_offset: vec2<f32>,
+  [[location(3)]] instance_color: vec4<f32>,
 ) -> VertexOutput {
+  let position = vertex_position + instance_offset;
+  let color = vertex_color * instance_color;
+
   // map position to NDC
   let x = map(position.x, 0.0, uniforms.screen_size.x, -1.0, 1.0);
   let y = map(position.y, 0.0, uniforms.screen_size.y, 1.0, -1.0);
@@ -32,7 +37,18 @@ fn vs_main(
   return out;
 }
 
+// CSS colors are in sRGB, while the render target blends in linear light
+fn srgb_to_linear(value: f32) -> f32 {
+  return select(pow((value + 0.055) / 1.055, 2.4), value / 12.92, value <= 0.04045);
+}
+
 [[stage(fragment)]]
 fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
-  return in.color;
+  // Colors are interpolated in sRGB, like CSS gradients
+  return vec4<f32>(
+    srgb_to_linear(in.color.r),
+    srgb_to_linear(in.color.g),
+    srgb_to_linear(in.color.b),
+    in.color.a,
+  );
 }
@@ -0,0 +1,112 @@
+/// Packs rects into a square texture in shelves: rows as tall as their
+/// tallest rect, filled from left to right. Space isn't reclaimed when a
+/// rect is released, the whole atlas is cleared instead.
+pub struct AtlasAllocator {
+    size: u32,
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// Width of the shelf taken by its rects
+    width: u32,
+}
+
+impl AtlasAllocator {
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// Position of the top left corner of a free rect of the size, `None`
+    /// if the atlas is too full to fit it
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        if width > self.size || height > self.size {
+            return None;
+        }
+        let size = self.size;
+
+        // The shelf that wastes the least height is picked, so that short
+        // rects don't fill up the tall shelves
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && size - shelf.width >= width)
+            .min_by_key(|shelf| shelf.height - height);
+        if let Some(shelf) = shelf {
+            let position = (shelf.width, shelf.y);
+            shelf.width += width;
+            return Some(position);
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map_or(0, |shelf| shelf.y + shelf.height);
+        if size - y < height {
+            return None;
+        }
+        self.shelves.push(Shelf { y, height, width });
+        Some((0, y))
+    }
+
+    /// Release every rect
+    pub fn clear(&mut self) {
+        self.shelves.clear();
+    }
+}
+
+/// RGBA pixels of the bitmap surrounded by a pixel wide border that repeats
+/// its edges. Sampling between pixels at the edges of the bitmap in an atlas
+/// then doesn't blend in its neighbours.
+pub fn extrude_edges(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let padded_width = width + 2;
+    let mut padded = Vec::with_capacity(padded_width * (height + 2) * 4);
+    for y in 0..height + 2 {
+        let row = y.saturating_sub(1).min(height - 1);
+        for x in 0..padded_width {
+            let column = x.saturating_sub(1).min(width - 1);
+            let start = (row * width + column) * 4;
+            padded.extend_from_slice(&data[start..start + 4]);
+        }
+    }
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_in_shelves() {
+        let mut atlas = AtlasAllocator::new(100);
+        assert_eq!(atlas.allocate(60, 20), Some((0, 0)));
+        assert_eq!(atlas.allocate(30, 10), Some((60, 0)));
+        // Too wide for the first shelf
+        assert_eq!(atlas.allocate(20, 10), Some((0, 20)));
+        // The shelf of the same height is preferred
+        assert_eq!(atlas.allocate(10, 10), Some((20, 20)));
+        assert_eq!(atlas.allocate(10, 80), None);
+        assert_eq!(atlas.allocate(200, 10), None);
+
+        atlas.clear();
+        assert_eq!(atlas.allocate(10, 80), Some((0, 0)));
+    }
+
+    #[test]
+    fn test_extrude_edges() {
+        let pixel = |value: u8| [value; 4];
+        let data = [pixel(1), pixel(2), pixel(3), pixel(4)].concat();
+        let padded = extrude_edges(&data, 2, 2);
+        let rows = [[1, 1, 2, 2], [1, 1, 2, 2], [3, 3, 4, 4], [3, 3, 4, 4]];
+        let expected = rows
+            .iter()
+            .flat_map(|row| row.iter().flat_map(|value| pixel(*value)))
+            .collect::<Vec<_>>();
+        assert_eq!(padded, expected);
+    }
+}
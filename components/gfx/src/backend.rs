@@ -1,71 +1,302 @@
+use super::image;
+use super::tessellator::Mesh;
 use super::text;
 use super::triangle;
-use lyon_tessellation::VertexBuffers;
+use shared::memory::MemoryUsage;
+use shared::primitive::Rect;
 
 pub struct Backend {
     triangle_pipeline: triangle::Pipeline,
+    image_pipeline: image::Pipeline,
     text_pipeline: text::Pipeline,
 }
 
+/// Texture views that a frame is drawn to
+#[derive(Clone, Copy)]
+pub struct RenderTarget<'a> {
+    /// Single-sampled frame texture
+    pub frame: &'a wgpu::TextureView,
+    /// Multisampled texture resolved into `frame`, if anti-aliasing is enabled
+    pub multisampled: Option<&'a wgpu::TextureView>,
+    /// Size of the textures in device pixels
+    pub size: (u32, u32),
+}
+
+impl<'a> RenderTarget<'a> {
+    /// View that render passes should draw to
+    pub fn view(&self) -> &'a wgpu::TextureView {
+        self.multisampled.unwrap_or(self.frame)
+    }
+
+    pub fn resolve_target(&self) -> Option<&'a wgpu::TextureView> {
+        self.multisampled.map(|_| self.frame)
+    }
+}
+
+/// Rect of the target that draws are limited to, in device pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScissorRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
 pub struct DrawRequest<'a> {
-    pub triangles: &'a [VertexBuffers<triangle::Vertex, triangle::Index>],
+    pub meshes: &'a [Mesh],
+    /// Images are drawn over the meshes and under the texts
+    pub images: &'a [image::Image],
     pub texts: &'a [text::Text],
+    /// Number of device pixels per CSS pixel
+    pub scale: f32,
 }
 
 impl Backend {
-    pub fn new(device: &wgpu::Device, texture_format: wgpu::TextureFormat) -> Self {
+    pub fn new(
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
         Self {
-            triangle_pipeline: triangle::Pipeline::new(device, texture_format),
+            triangle_pipeline: triangle::Pipeline::new(device, texture_format, sample_count),
+            image_pipeline: image::Pipeline::new(device, texture_format, sample_count),
             text_pipeline: text::Pipeline::new(device, texture_format, None),
         }
     }
 
+    pub fn image_memory(&self) -> MemoryUsage {
+        self.image_pipeline.memory_usage()
+    }
+
+    pub fn glyph_atlas_memory(&self) -> MemoryUsage {
+        self.text_pipeline.atlas_memory()
+    }
+
     pub fn draw(
         &mut self,
         device: &wgpu::Device,
+        queue: &wgpu::Queue,
         encoder: &mut wgpu::CommandEncoder,
         staging_belt: &mut wgpu::util::StagingBelt,
-        target: &wgpu::TextureView,
-        size: (u32, u32),
+        target: RenderTarget,
         request: DrawRequest,
     ) {
-        if !request.triangles.is_empty() {
+        let size = target.size;
+        // Vertices are in CSS pixels, so the projection maps them onto a
+        // screen that is `scale` times smaller than the target texture
+        let screen_size = (size.0 as f32 / request.scale, size.1 as f32 / request.scale);
+        if !request.meshes.is_empty() {
+            // Meshes clipped out of the target are not drawn
+            let meshes = request
+                .meshes
+                .iter()
+                .filter_map(|mesh| Some((mesh, scissor_region(&mesh.clip, request.scale, size)?)))
+                .collect::<Vec<_>>();
             self.triangle_pipeline.draw(
                 device,
                 encoder,
                 staging_belt,
-                &request.triangles,
-                target,
-                size,
+                &meshes,
+                &target,
+                screen_size,
             );
         }
 
+        // The image pipeline also runs without images, to release the
+        // textures of the images that aren't drawn anymore
+        let images = request
+            .images
+            .iter()
+            .filter_map(|image| Some((image, scissor_region(&image.clip, request.scale, size)?)))
+            .collect::<Vec<_>>();
+        self.image_pipeline
+            .draw(device, queue, encoder, &images, &target, screen_size);
+
+        // Glyphs are anti-aliased by the rasterizer, so text is drawn
+        // directly to the resolved frame
         if !request.texts.is_empty() {
-            for text in request.texts {
-                let section = wgpu_glyph::Section {
-                    screen_position: (text.bounds.x, text.bounds.y),
-                    bounds: (text.bounds.width, text.bounds.height),
-                    text: vec![wgpu_glyph::Text {
-                        text: &text.content,
-                        scale: wgpu_glyph::ab_glyph::PxScale {
-                            x: text.size,
-                            y: text.size,
-                        },
-                        font_id: wgpu_glyph::FontId(0),
-                        extra: wgpu_glyph::Extra {
-                            color: text.color.clone().into(),
-                            z: 0.0,
-                        },
-                    }],
-                    layout: wgpu_glyph::Layout::default()
-                        .h_align(wgpu_glyph::HorizontalAlign::Left)
-                        .v_align(wgpu_glyph::VerticalAlign::Top),
-                    ..Default::default()
+            let scale = request.scale;
+            let projection = wgpu_glyph::orthographic_projection(size.0, size.1);
+            self.text_pipeline.sync_fonts();
+
+            // Texts are drawn in runs with the same clip and orientation
+            let mut start = 0;
+            while start < request.texts.len() {
+                let first = &request.texts[start];
+                let end = request.texts[start..]
+                    .iter()
+                    .position(|text| {
+                        text.clip != first.clip || text.is_vertical != first.is_vertical
+                    })
+                    .map_or(request.texts.len(), |length| start + length);
+                let region = match scissor_region(&first.clip, scale, size) {
+                    Some(region) => region,
+                    None => {
+                        start = end;
+                        continue;
+                    }
+                };
+
+                for text in &request.texts[start..end] {
+                    let bounds = &text.bounds;
+                    let section = match text.is_vertical {
+                        // The text is laid out horizontally where the
+                        // rotation moves it onto its bounds
+                        true => text_section(
+                            text,
+                            (bounds.y * scale, -(bounds.x + bounds.width) * scale),
+                            (bounds.height * scale, bounds.width * scale),
+                            scale,
+                        ),
+                        false => text_section(
+                            text,
+                            (bounds.x * scale, bounds.y * scale),
+                            (bounds.width * scale, bounds.height * scale),
+                            scale,
+                        ),
+                    };
+                    self.text_pipeline.queue(section);
+                }
+                let transform = match first.is_vertical {
+                    true => rotate_clockwise(projection),
+                    false => projection,
                 };
-                self.text_pipeline.queue(section)
+                self.text_pipeline.draw_queued(
+                    device,
+                    staging_belt,
+                    encoder,
+                    target.frame,
+                    transform,
+                    wgpu_glyph::Region {
+                        x: region.x,
+                        y: region.y,
+                        width: region.width,
+                        height: region.height,
+                    },
+                );
+                start = end;
             }
-            self.text_pipeline
-                .draw_queued(device, staging_belt, encoder, target, size);
         }
     }
 }
+
+/// Part of the target that draws clipped to `clip` are limited to, in
+/// device pixels. The whole target without a clip, `None` if nothing of the
+/// clip is on the target.
+pub fn scissor_region(clip: &Option<Rect>, scale: f32, size: (u32, u32)) -> Option<ScissorRegion> {
+    let target = ScissorRegion {
+        x: 0,
+        y: 0,
+        width: size.0,
+        height: size.1,
+    };
+    let clip = match clip {
+        Some(clip) => clip,
+        None => return Some(target),
+    };
+
+    let left = (clip.x * scale).round().clamp(0., size.0 as f32) as u32;
+    let top = (clip.y * scale).round().clamp(0., size.1 as f32) as u32;
+    let right = ((clip.x + clip.width) * scale)
+        .round()
+        .clamp(0., size.0 as f32) as u32;
+    let bottom = ((clip.y + clip.height) * scale)
+        .round()
+        .clamp(0., size.1 as f32) as u32;
+    if right <= left || bottom <= top {
+        return None;
+    }
+    Some(ScissorRegion {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
+
+/// Section of the text at the position with the bounds, in device pixels.
+/// Glyphs are rasterized at the scaled size instead of being transformed
+/// afterward so that the text stays crisp.
+fn text_section(
+    text: &text::Text,
+    position: (f32, f32),
+    bounds: (f32, f32),
+    scale: f32,
+) -> wgpu_glyph::Section<'_> {
+    wgpu_glyph::Section {
+        screen_position: position,
+        bounds,
+        text: vec![wgpu_glyph::Text {
+            text: &text.content,
+            scale: wgpu_glyph::ab_glyph::PxScale {
+                x: text.size * scale,
+                y: text.size * scale,
+            },
+            font_id: wgpu_glyph::FontId(text.font),
+            extra: wgpu_glyph::Extra {
+                color: text.color.to_linear_rgba(),
+                z: 0.0,
+            },
+        }],
+        layout: wgpu_glyph::Layout::default()
+            .h_align(wgpu_glyph::HorizontalAlign::Left)
+            .v_align(wgpu_glyph::VerticalAlign::Top),
+    }
+}
+
+/// Rotate what the projection draws by a quarter turn clockwise, moving
+/// `(x, y)` to `(-y, x)` before projecting it. Matrices are column-major.
+fn rotate_clockwise(projection: [f32; 16]) -> [f32; 16] {
+    let mut transform = projection;
+    for row in 0..4 {
+        transform[row] = projection[4 + row];
+        transform[4 + row] = -projection[row];
+    }
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(transform: &[f32; 16], x: f32, y: f32) -> (f32, f32) {
+        (
+            transform[0] * x + transform[4] * y + transform[12],
+            transform[1] * x + transform[5] * y + transform[13],
+        )
+    }
+
+    #[test]
+    fn test_scissor_region() {
+        let region = |x, y, width, height| ScissorRegion {
+            x,
+            y,
+            width,
+            height,
+        };
+        assert_eq!(
+            scissor_region(&None, 2., (200, 100)),
+            Some(region(0, 0, 200, 100))
+        );
+        // The clip is scaled to device pixels and cut at the edges of the
+        // target
+        assert_eq!(
+            scissor_region(&Some(Rect::new(10., -5., 200., 20.)), 2., (200, 100)),
+            Some(region(20, 0, 180, 30))
+        );
+        assert_eq!(
+            scissor_region(&Some(Rect::new(150., 0., 10., 10.)), 2., (200, 100)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rotate_clockwise() {
+        let projection = wgpu_glyph::orthographic_projection(200, 100);
+        let transform = rotate_clockwise(projection);
+        assert_eq!(
+            project(&transform, 30., -50.),
+            project(&projection, 50., 30.)
+        );
+    }
+}
@@ -1,52 +1,184 @@
-use super::backend::{Backend, DrawRequest};
+use super::backend::{scissor_region, Backend, DrawRequest, RenderTarget};
+#[cfg(not(target_arch = "wasm32"))]
 use super::Bitmap;
+use crate::config::GfxConfig;
+use crate::error::GfxError;
+use crate::font_database::FontIndex;
+use crate::image::{self, TexturedQuad};
+use crate::layer::{Layer, LayerId, LayerTree};
+use crate::painters::image::ImagePainter;
 use crate::painters::polygon::PolygonPainter;
 use crate::painters::rect::RectPainter;
+use crate::painters::shadow::ShadowPainter;
 use crate::painters::text::TextPainter;
 use crate::tessellator::Tessellator;
+#[cfg(target_arch = "wasm32")]
+use crate::web::Presenter;
 use crate::Graphics;
 use async_trait::async_trait;
 use futures::task::SpawnExt;
-use shared::color::Color;
+use shared::color::{linear_to_srgb, srgb_to_linear, Color};
+use shared::image::ImageBitmap;
+use shared::memory::MemoryUsage;
 use shared::primitive::*;
 
-pub struct Canvas<'a> {
+/// Draws recorded for the frame or for the content of a layer
+struct Surface {
     tessellator: Tessellator,
+    text_painter: TextPainter,
+    image_painter: ImagePainter,
+}
+
+impl Surface {
+    fn new() -> Self {
+        Self {
+            tessellator: Tessellator::new(),
+            text_painter: TextPainter::new(),
+            image_painter: ImagePainter::new(),
+        }
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.tessellator.set_clip(clip.clone());
+        self.image_painter.set_clip(clip.clone());
+        self.text_painter.set_clip(clip);
+    }
+
+    fn draw_request(&self, scale: f32) -> DrawRequest<'_> {
+        DrawRequest {
+            meshes: self.tessellator.meshes(),
+            images: self.image_painter.images(),
+            texts: self.text_painter.texts(),
+            scale,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.text_painter.clear();
+        self.image_painter.clear();
+        self.tessellator.clear();
+    }
+}
+
+/// Texture that a layer, or a scroll bar thumb, is composited from
+struct LayerTexture {
+    _texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Content of a layer painted in this frame, to be rasterized into the
+/// texture of the layer
+struct PaintedLayer {
+    id: LayerId,
+    size: (u32, u32),
+    content: Surface,
+}
+
+/// Textures and buffer that depend on the size of the frame. Pipelines and
+/// their layouts and samplers don't, and are kept across resizes.
+struct Frame {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    multisampled: Option<(wgpu::Texture, wgpu::TextureView)>,
+    /// Pixels of the frame are read back from there, while frames in the
+    /// browser are presented on an HTML canvas instead
+    #[cfg(not(target_arch = "wasm32"))]
+    output_buffer: wgpu::Buffer,
+}
+
+pub struct Canvas<'a> {
+    /// Draws of the frame, or of the layer being painted
+    surface: Surface,
+    /// Surfaces that layers being painted were started from, `None` for
+    /// the layers that kept their content
+    layer_stack: Vec<Option<(PaintedLayer, Surface)>>,
+    painted_layers: Vec<PaintedLayer>,
+    layer_tree: LayerTree<LayerTexture>,
+    compositor: image::Pipeline,
+    scrollbar_thumb: LayerTexture,
     polygon_painter: PolygonPainter,
     rect_painter: RectPainter,
-    text_painter: TextPainter,
+    shadow_painter: ShadowPainter,
     backend: Backend,
     device: wgpu::Device,
     queue: wgpu::Queue,
     staging_belt: wgpu::util::StagingBelt,
     local_pool: futures::executor::LocalPool,
     frame_desc: wgpu::TextureDescriptor<'a>,
-    frame: wgpu::Texture,
-    frame_texture_view: wgpu::TextureView,
-    output_buffer: wgpu::Buffer,
+    /// Textures of the frame, created when it is painted after a resize
+    frame: Option<Frame>,
+    config: GfxConfig,
+    #[cfg(not(target_arch = "wasm32"))]
     output_buffer_desc: wgpu::BufferDescriptor<'a>,
+    #[cfg(target_arch = "wasm32")]
+    presenter: Option<Presenter>,
+    scale: f32,
 }
 
-pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+/// Format of the frame and of the textures of layers. Draws are blended in
+/// linear light and stored sRGB encoded, which is the format of the output.
+pub const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// The frame is copied to the output buffer, or sampled to be presented on
+/// an HTML canvas
+#[cfg(not(target_arch = "wasm32"))]
+const FRAME_USAGES: wgpu::TextureUsages =
+    wgpu::TextureUsages::COPY_SRC.union(wgpu::TextureUsages::RENDER_ATTACHMENT);
+#[cfg(target_arch = "wasm32")]
+const FRAME_USAGES: wgpu::TextureUsages =
+    wgpu::TextureUsages::TEXTURE_BINDING.union(wgpu::TextureUsages::RENDER_ATTACHMENT);
+
+/// Largest width or height of the texture of a layer, in device pixels.
+/// Larger content is painted in place.
+const MAX_LAYER_DIMENSION: u32 = 4096;
+
+/// Color of the scroll bar thumbs of layers, with premultiplied alpha
+const SCROLLBAR_THUMB_COLOR: [u8; 4] = [0, 0, 0, 128];
 
 impl<'a> Canvas<'a> {
     const CHUNK_SIZE: u64 = 10 * 1024;
 
-    pub async fn new() -> Canvas<'a> {
+    pub async fn new(config: GfxConfig) -> Result<Canvas<'a>, GfxError> {
         let instance = wgpu::Instance::new(wgpu::Backends::PRIMARY);
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        let adapter = request_adapter(&instance, None).await?;
+        Self::with_adapter(adapter, config).await
+    }
+
+    /// Create a canvas that presents its frames on the HTML canvas, with
+    /// WebGPU. Its pixels can't be read back.
+    #[cfg(target_arch = "wasm32")]
+    pub async fn from_html_canvas(
+        html_canvas: &web_sys::HtmlCanvasElement,
+        config: GfxConfig,
+    ) -> Result<Canvas<'a>, GfxError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::BROWSER_WEBGPU);
+        let surface = unsafe { instance.create_surface_from_canvas(html_canvas) };
+        let adapter = request_adapter(&instance, Some(&surface)).await?;
+        let format = surface
+            .get_preferred_format(&adapter)
+            .ok_or_else(|| GfxError::UnsupportedTextureFormat("HTML canvas".to_string()))?;
+        let mut canvas = Self::with_adapter(adapter, config).await?;
+        canvas.presenter = Some(Presenter::new(&canvas.device, surface, format));
+        Ok(canvas)
+    }
+
+    async fn with_adapter(
+        adapter: wgpu::Adapter,
+        config: GfxConfig,
+    ) -> Result<Canvas<'a>, GfxError> {
+        let format_features = adapter.get_texture_format_features(TEXTURE_FORMAT);
+        if !format_features.allowed_usages.contains(FRAME_USAGES) {
+            return Err(GfxError::UnsupportedTextureFormat(format!(
+                "{:?}",
+                TEXTURE_FORMAT
+            )));
+        }
 
         let (device, queue) = adapter
             .request_device(&Default::default(), None)
             .await
-            .unwrap();
+            .map_err(|e| GfxError::RequestDevice(e.to_string()))?;
 
         let staging_belt = wgpu::util::StagingBelt::new(Self::CHUNK_SIZE);
         let local_pool = futures::executor::LocalPool::new();
@@ -62,55 +194,101 @@ impl<'a> Canvas<'a> {
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: TEXTURE_FORMAT,
-            usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: FRAME_USAGES,
         };
 
-        let frame = device.create_texture(&frame_desc);
-
-        let frame_texture_view = frame.create_view(&Default::default());
+        #[cfg(not(target_arch = "wasm32"))]
         let output_buffer_desc = wgpu::BufferDescriptor {
             label: Some("moon output buffer"),
-            size: 1,
+            // A single row of the 1x1 frame
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64,
             usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
             mapped_at_creation: false,
         };
-        let output_buffer = device.create_buffer(&output_buffer_desc);
 
-        Self {
-            backend: Backend::new(&device, TEXTURE_FORMAT),
-            tessellator: Tessellator::new(),
+        let compositor = image::Pipeline::compositor(&device, TEXTURE_FORMAT, 1);
+        let scrollbar_thumb = Self::create_scrollbar_thumb(&device, &queue, &compositor);
+
+        Ok(Self {
+            backend: Backend::new(&device, TEXTURE_FORMAT, config.antialiasing.sample_count()),
+            surface: Surface::new(),
+            layer_stack: Vec::new(),
+            painted_layers: Vec::new(),
+            layer_tree: LayerTree::new(),
+            compositor,
+            scrollbar_thumb,
             polygon_painter: PolygonPainter::new(),
             rect_painter: RectPainter::new(),
-            text_painter: TextPainter::new(),
+            shadow_painter: ShadowPainter::new(),
             device,
             queue,
             staging_belt,
             local_pool,
             frame_desc,
-            frame,
-            frame_texture_view,
-            output_buffer,
+            frame: None,
+            config,
+            #[cfg(not(target_arch = "wasm32"))]
             output_buffer_desc,
-        }
+            #[cfg(target_arch = "wasm32")]
+            presenter: None,
+            scale: 1.,
+        })
     }
 
+    /// Textures of the images drawn in the last frame
+    pub fn image_memory(&self) -> MemoryUsage {
+        self.backend.image_memory()
+    }
+
+    /// Texture the glyphs of texts are rasterized into
+    pub fn glyph_atlas_memory(&self) -> MemoryUsage {
+        self.backend.glyph_atlas_memory()
+    }
+
+    /// Set the color the frame is cleared to
+    pub fn set_background(&mut self, color: Color) {
+        self.config.background = color;
+    }
+
+    /// Change the size of the frame. Its textures are only created again
+    /// when it is painted, so resizing many times in a row, like while a
+    /// window is dragged, doesn't allocate them for every size.
     pub fn resize(&mut self, size: (u32, u32)) {
         let (width, height) = size;
+        if (self.frame_desc.size.width, self.frame_desc.size.height) == (width, height) {
+            return;
+        }
         self.frame_desc.size.width = width;
         self.frame_desc.size.height = height;
 
-        self.output_buffer_desc.size = (self.get_bytes_per_row() * height) as u64;
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.output_buffer_desc.size = (self.get_bytes_per_row() * height) as u64;
+        }
+        self.frame = None;
+    }
 
-        self.frame = self.device.create_texture(&self.frame_desc);
-        self.frame_texture_view = self.frame.create_view(&Default::default());
-        self.output_buffer = self.device.create_buffer(&self.output_buffer_desc);
+    fn create_frame(&self) -> Frame {
+        let texture = self.device.create_texture(&self.frame_desc);
+        let view = texture.create_view(&Default::default());
+        Frame {
+            texture,
+            view,
+            multisampled: Self::create_multisampled_frame(
+                &self.device,
+                &self.frame_desc,
+                &self.config,
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            output_buffer: self.device.create_buffer(&self.output_buffer_desc),
+        }
     }
 
     pub fn paint(&mut self) {
-        let triangles = self.tessellator.vertex_buffers();
-        let texts = self.text_painter.texts();
-
-        let request = DrawRequest { triangles, texts };
+        // Layers are rasterized before the frame they are composited onto
+        for layer in std::mem::take(&mut self.painted_layers) {
+            self.rasterize_layer(layer);
+        }
 
         let mut encoder = self
             .device
@@ -118,38 +296,81 @@ impl<'a> Canvas<'a> {
                 label: Some("moon wgpu encoder"),
             });
 
-        // Background clear
-        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("moon::gfx clear bg render pass"),
-            color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &self.frame_texture_view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
-                    store: true,
-                },
-            }],
-            depth_stencil_attachment: None,
-        });
+        if self.frame.is_none() {
+            self.frame = Some(self.create_frame());
+        }
+        let frame = self.frame.as_ref().expect("The frame is created");
+        let target = RenderTarget {
+            frame: &frame.view,
+            multisampled: frame.multisampled.as_ref().map(|(_, view)| view),
+            size: (self.frame_desc.size.width, self.frame_desc.size.height),
+        };
 
+        clear(&mut encoder, &target, clear_color(&self.config.background));
         self.backend.draw(
             &self.device,
+            &self.queue,
             &mut encoder,
             &mut self.staging_belt,
-            &self.frame.create_view(&Default::default()),
-            (self.frame_desc.size.width, self.frame_desc.size.height),
-            request,
+            target,
+            self.surface.draw_request(self.scale),
+        );
+
+        // Layers are composited over the content of the frame, straight
+        // onto the resolved frame which the texts are drawn to as well
+        let thumb_texture = &self.scrollbar_thumb.bind_group;
+        let mut quads = Vec::new();
+        for layer in self.layer_tree.layers() {
+            let (texture, scissor) = match (
+                self.layer_tree.texture(layer.id),
+                scissor_region(&Some(layer.clip.clone()), self.scale, target.size),
+            ) {
+                (Some(texture), Some(scissor)) => (texture, scissor),
+                _ => continue,
+            };
+            let size = layer_size(&layer.content_size, self.scale);
+            quads.push(TexturedQuad {
+                bind_group: &texture.bind_group,
+                bounds: Rect::new(
+                    layer.position.x,
+                    layer.position.y,
+                    size.0 as f32 / self.scale,
+                    size.1 as f32 / self.scale,
+                ),
+                scissor: scissor.clone(),
+            });
+            quads.extend(layer.scrollbars.iter().map(|thumb| TexturedQuad {
+                bind_group: thumb_texture,
+                bounds: thumb.clone(),
+                scissor: scissor.clone(),
+            }));
+        }
+        let screen_size = (
+            target.size.0 as f32 / self.scale,
+            target.size.1 as f32 / self.scale,
+        );
+        self.compositor.composite(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &quads,
+            &RenderTarget {
+                multisampled: None,
+                ..target
+            },
+            screen_size,
         );
 
+        #[cfg(not(target_arch = "wasm32"))]
         encoder.copy_texture_to_buffer(
             wgpu::ImageCopyTexture {
-                texture: &self.frame,
+                texture: &frame.texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
             wgpu::ImageCopyBuffer {
-                buffer: &self.output_buffer,
+                buffer: &frame.output_buffer,
                 layout: wgpu::ImageDataLayout {
                     offset: 0,
                     bytes_per_row: core::num::NonZeroU32::new(self.get_bytes_per_row()),
@@ -158,7 +379,77 @@ impl<'a> Canvas<'a> {
             },
             self.frame_desc.size,
         );
+        #[cfg(target_arch = "wasm32")]
+        let canvas_texture = match self.presenter.as_mut() {
+            Some(presenter) => presenter.draw(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &frame.view,
+                target.size,
+            ),
+            None => None,
+        };
+        self.submit(encoder);
+        #[cfg(target_arch = "wasm32")]
+        if let Some(canvas_texture) = canvas_texture {
+            canvas_texture.present();
+        }
+
+        // clean up for next draw
+        self.surface.clear();
+        self.layer_tree.clear();
+    }
+
+    /// Draw the content painted for the layer into its texture, in a
+    /// submission of its own since the pipelines rewrite their buffers
+    /// for every target
+    fn rasterize_layer(&mut self, layer: PaintedLayer) {
+        let texture = match self.layer_tree.take_texture(layer.id, layer.size) {
+            Some(texture) => texture,
+            None => {
+                let texture = self.device.create_texture(&layer_texture_desc(layer.size));
+                let view = texture.create_view(&Default::default());
+                let bind_group = self.compositor.texture_bind_group(&self.device, &view);
+                LayerTexture {
+                    _texture: texture,
+                    view,
+                    bind_group,
+                }
+            }
+        };
+        let multisampled = Self::create_multisampled_frame(
+            &self.device,
+            &layer_texture_desc(layer.size),
+            &self.config,
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("moon layer encoder"),
+            });
+        let target = RenderTarget {
+            frame: &texture.view,
+            multisampled: multisampled.as_ref().map(|(_, view)| view),
+            size: layer.size,
+        };
+        clear(&mut encoder, &target, wgpu::Color::TRANSPARENT);
+        self.backend.draw(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            &mut self.staging_belt,
+            target,
+            layer.content.draw_request(self.scale),
+        );
+        self.submit(encoder);
 
+        self.layer_tree
+            .insert_texture(layer.id, layer.size, texture);
+    }
+
+    fn submit(&mut self, encoder: wgpu::CommandEncoder) {
         self.staging_belt.finish();
         self.queue.submit(Some(encoder.finish()));
         self.local_pool
@@ -167,12 +458,73 @@ impl<'a> Canvas<'a> {
             .expect("Recall staging belt");
 
         self.local_pool.run_until_stalled();
+    }
 
-        // clean up for next draw
-        self.text_painter.clear();
-        self.tessellator.clear();
+    /// 1x1 texture stretched over the scroll bar thumbs of layers
+    fn create_scrollbar_thumb(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        compositor: &image::Pipeline,
+    ) -> LayerTexture {
+        let size = wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("moon scroll bar thumb texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &SCROLLBAR_THUMB_COLOR,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: core::num::NonZeroU32::new(4),
+                rows_per_image: core::num::NonZeroU32::new(1),
+            },
+            size,
+        );
+        let view = texture.create_view(&Default::default());
+        let bind_group = compositor.texture_bind_group(device, &view);
+        LayerTexture {
+            _texture: texture,
+            view,
+            bind_group,
+        }
+    }
+
+    fn create_multisampled_frame(
+        device: &wgpu::Device,
+        frame_desc: &wgpu::TextureDescriptor,
+        config: &GfxConfig,
+    ) -> Option<(wgpu::Texture, wgpu::TextureView)> {
+        let sample_count = config.antialiasing.sample_count();
+        if sample_count == 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("moon multisampled output texture"),
+            sample_count,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ..frame_desc.clone()
+        });
+        let view = texture.create_view(&Default::default());
+        Some((texture, view))
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
     fn get_bytes_per_row(&self) -> u32 {
         let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
         let unpadded_bytes_per_row = 4 * self.frame_desc.size.width;
@@ -182,8 +534,19 @@ impl<'a> Canvas<'a> {
         bytes_per_row
     }
 
+    /// RGBA pixels of the painted frame, row by row, sRGB encoded with
+    /// straight alpha. A frame resized since it was last painted only has
+    /// the background color.
+    #[cfg(not(target_arch = "wasm32"))]
     pub async fn output(&mut self) -> Bitmap {
-        let buffer_slice = self.output_buffer.slice(..);
+        let output_buffer = match &self.frame {
+            Some(frame) => &frame.output_buffer,
+            None => {
+                let size = (self.frame_desc.size.width, self.frame_desc.size.height);
+                return cleared_bitmap(size, &self.config.background);
+            }
+        };
+        let buffer_slice = output_buffer.slice(..);
 
         // NOTE: We have to create the mapping THEN device.poll() before await
         // the future. Otherwise the application will freeze.
@@ -205,39 +568,273 @@ impl<'a> Canvas<'a> {
             row_pointer += self.get_bytes_per_row() as usize;
         }
 
-        self.output_buffer.unmap();
+        output_buffer.unmap();
 
+        // The frame has premultiplied alpha, while bitmaps have straight
+        // alpha. Opaque frames are the same either way.
+        if self.config.background.a != 255 {
+            unpremultiply(&mut output);
+        }
         output
     }
 }
 
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+) -> Result<wgpu::Adapter, GfxError> {
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: surface,
+            force_fallback_adapter: false,
+        })
+        .await
+        .ok_or(GfxError::NoAdapter)
+}
+
+/// Clear color of the frame for the background, in linear light with
+/// premultiplied alpha
+fn clear_color(background: &Color) -> wgpu::Color {
+    let [r, g, b, alpha] = background.to_linear_rgba().map(|value| value as f64);
+    wgpu::Color {
+        r: r * alpha,
+        g: g * alpha,
+        b: b * alpha,
+        a: alpha,
+    }
+}
+
+/// Pixels of a frame of the size that is only cleared to the background
+#[cfg(not(target_arch = "wasm32"))]
+fn cleared_bitmap(size: (u32, u32), background: &Color) -> Bitmap {
+    let (width, height) = size;
+    [background.r, background.g, background.b, background.a].repeat((width * height) as usize)
+}
+
+/// Convert sRGB encoded pixels with premultiplied alpha to straight alpha.
+/// Colors were premultiplied in linear light, where they are divided.
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+fn unpremultiply(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3];
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        let alpha = alpha as f32 / 255.;
+        for channel in &mut pixel[..3] {
+            let linear = srgb_to_linear(*channel as f32 / 255.) / alpha;
+            *channel = (linear_to_srgb(linear.min(1.)) * 255.).round() as u8;
+        }
+    }
+}
+
+/// Size of the texture of a layer with content of the size, in device
+/// pixels
+fn layer_size(content_size: &Size, scale: f32) -> (u32, u32) {
+    let device_pixels = |length: f32| f32::max((length * scale).ceil(), 1.) as u32;
+    (
+        device_pixels(content_size.width),
+        device_pixels(content_size.height),
+    )
+}
+
+fn layer_texture_desc(size: (u32, u32)) -> wgpu::TextureDescriptor<'static> {
+    wgpu::TextureDescriptor {
+        label: Some("moon layer texture"),
+        size: wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    }
+}
+
+/// Clear the target before drawing onto it
+pub(crate) fn clear(encoder: &mut wgpu::CommandEncoder, target: &RenderTarget, color: wgpu::Color) {
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("moon::gfx clear bg render pass"),
+        color_attachments: &[wgpu::RenderPassColorAttachment {
+            view: target.view(),
+            resolve_target: target.resolve_target(),
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(color),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    });
+}
+
 #[async_trait(?Send)]
 impl<'a> Graphics for Canvas<'a> {
     fn fill_rect(&mut self, rect: Rect, color: Color) {
         self.rect_painter
-            .draw_solid_rect(&mut self.tessellator, &rect, &color);
+            .draw_solid_rect(&mut self.surface.tessellator, &rect, &color);
     }
 
     fn fill_rrect(&mut self, rect: RRect, color: Color) {
         self.rect_painter
-            .draw_solid_rrect(&mut self.tessellator, &rect, &color);
+            .draw_solid_rrect(&mut self.surface.tessellator, &rect, &color);
     }
 
-    fn fill_text(&mut self, content: String, bounds: Rect, color: Color, size: f32) {
-        self.text_painter.fill_text(content, bounds, color, size);
+    fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        self.surface
+            .text_painter
+            .fill_text(content, bounds, color, size, font, false);
+    }
+
+    fn fill_vertical_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        self.surface
+            .text_painter
+            .fill_text(content, bounds, color, size, font, true);
     }
 
     fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
         self.polygon_painter
-            .fill_polygon(&mut self.tessellator, &points, &color);
+            .fill_polygon(&mut self.surface.tessellator, &points, &color);
+    }
+
+    fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap) {
+        self.surface.image_painter.draw_image(bounds, bitmap);
+    }
+
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect) {
+        self.shadow_painter
+            .draw_shadow(&mut self.surface.tessellator, &rect, blur, &color, caster);
+    }
+
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color) {
+        self.shadow_painter.draw_inset_shadow(
+            &mut self.surface.tessellator,
+            bounds,
+            &hole,
+            blur,
+            &color,
+        );
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.surface.set_clip(clip);
+    }
+
+    fn can_composite_layer(&self, content_size: &Size) -> bool {
+        let max_dimension = u32::min(
+            MAX_LAYER_DIMENSION,
+            self.device.limits().max_texture_dimension_2d,
+        );
+        let size = layer_size(content_size, self.scale);
+        size.0 <= max_dimension && size.1 <= max_dimension
+    }
+
+    fn begin_layer(&mut self, layer: Layer) -> bool {
+        let id = layer.id;
+        let size = layer_size(&layer.content_size, self.scale);
+        if !self.layer_tree.push(layer, size) {
+            self.layer_stack.push(None);
+            return false;
+        }
+        let parent = std::mem::replace(&mut self.surface, Surface::new());
+        let painted = PaintedLayer {
+            id,
+            size,
+            content: Surface::new(),
+        };
+        self.layer_stack.push(Some((painted, parent)));
+        true
+    }
+
+    fn end_layer(&mut self) {
+        if let Some(Some((mut painted, parent))) = self.layer_stack.pop() {
+            painted.content = std::mem::replace(&mut self.surface, parent);
+            self.painted_layers.push(painted);
+        }
+    }
+
+    fn invalidate_layers(&mut self) {
+        self.layer_tree.invalidate();
     }
 
     fn resize(&mut self, size: Size) {
         self.resize((size.width as u32, size.height as u32));
     }
 
+    fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
     async fn output(&mut self) -> Vec<u8> {
         self.paint();
         self.output().await
     }
+
+    /// Frames are presented on the HTML canvas, which has no pixels to
+    /// read back
+    #[cfg(target_arch = "wasm32")]
+    async fn output(&mut self) -> Vec<u8> {
+        self.paint();
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_after_resize() {
+        let background = Color::new(0, 0, 255, 255);
+        assert_eq!(
+            cleared_bitmap((2, 1), &background),
+            vec![0, 0, 255, 255, 0, 0, 255, 255]
+        );
+
+        let config = GfxConfig {
+            background: background.clone(),
+            ..GfxConfig::default()
+        };
+        let mut canvas = match futures::executor::block_on(Canvas::new(config)) {
+            Ok(canvas) => canvas,
+            // Machines without a GPU adapter only check the cleared bitmap
+            Err(GfxError::NoAdapter) => return,
+            Err(error) => panic!("{:?}", error),
+        };
+        canvas.resize((3, 2));
+        canvas.paint();
+        canvas.resize((2, 2));
+        let output = futures::executor::block_on(canvas.output());
+        assert_eq!(output, cleared_bitmap((2, 2), &background));
+    }
+
+    #[test]
+    fn test_unpremultiply() {
+        let mut pixels = vec![100, 50, 0, 128, 255, 0, 0, 255, 10, 20, 30, 0];
+        unpremultiply(&mut pixels);
+        assert_eq!(pixels, vec![138, 71, 0, 128, 255, 0, 0, 255, 10, 20, 30, 0]);
+
+        // Half transparent mid gray, premultiplied in linear light
+        let color = clear_color(&Color::new(128, 128, 128, 128));
+        assert!((color.r - 0.108).abs() < 0.001);
+        assert!((color.a - 0.502).abs() < 0.001);
+    }
 }
@@ -0,0 +1,36 @@
+use shared::color::Color;
+
+/// Options for creating a `Canvas`
+#[derive(Debug, Clone)]
+pub struct GfxConfig {
+    pub antialiasing: Antialiasing,
+    /// Color the frame is cleared to before painting. A transparent color
+    /// lets the output be drawn over other content of the embedder.
+    pub background: Color,
+}
+
+impl Default for GfxConfig {
+    fn default() -> Self {
+        Self {
+            antialiasing: Antialiasing::default(),
+            background: Color::new(255, 255, 255, 255),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Antialiasing {
+    None,
+    /// Multisample anti-aliasing with 4 samples per pixel
+    #[default]
+    Msaa4x,
+}
+
+impl Antialiasing {
+    pub fn sample_count(&self) -> u32 {
+        match self {
+            Antialiasing::None => 1,
+            Antialiasing::Msaa4x => 4,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+#[derive(Debug)]
+pub enum GfxError {
+    /// No graphics adapter is available on this machine
+    NoAdapter,
+    RequestDevice(String),
+    /// The adapter can't render to the output texture format
+    UnsupportedTextureFormat(String),
+    /// Font data that can't be parsed
+    InvalidFont(String),
+}
+
+impl std::fmt::Display for GfxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GfxError::NoAdapter => write!(f, "Unable to find a suitable graphics adapter"),
+            GfxError::RequestDevice(error) => {
+                write!(f, "Unable to request a graphics device: {}", error)
+            }
+            GfxError::UnsupportedTextureFormat(format) => write!(
+                f,
+                "The graphics adapter can't render to texture format: {}",
+                format
+            ),
+            GfxError::InvalidFont(error) => write!(f, "Unable to load font: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GfxError {}
@@ -0,0 +1,155 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use glyph_brush::ab_glyph::FontArc;
+
+use crate::error::GfxError;
+use crate::fonts;
+
+/// Index of a font in the text brushes. The default font is always `0`, the
+/// bundled monospace font `1` and web fonts follow in registration order, so
+/// indexes stay valid as more fonts are registered.
+pub type FontIndex = usize;
+
+pub const DEFAULT_FONT: FontIndex = 0;
+
+struct WebFont {
+    family: String,
+    /// Origin of the documents that can use the font, `None` for the
+    /// bundled fonts that every document can use
+    origin: Option<String>,
+    font: FontArc,
+    /// The font file, kept to embed the font in documents such as PDFs
+    data: Arc<[u8]>,
+}
+
+/// Fonts bundled with the engine for generic families, besides the default
+/// font
+const BUNDLED_FONTS: &[(&str, &[u8])] = &[("monospace", fonts::MONOSPACE)];
+
+/// Bundled fonts, then fonts registered by pages with `@font-face`. They are
+/// shared by every thread that measures or draws text, but a web font is
+/// only found by the documents of the origin that registered it.
+static WEB_FONTS: Mutex<Vec<WebFont>> = Mutex::new(Vec::new());
+
+fn web_fonts() -> MutexGuard<'static, Vec<WebFont>> {
+    let mut web_fonts = WEB_FONTS.lock().unwrap();
+    if web_fonts.is_empty() {
+        for (family, data) in BUNDLED_FONTS {
+            web_fonts.push(WebFont {
+                family: family.to_string(),
+                origin: None,
+                font: FontArc::try_from_slice(data).expect("Unable to load bundled font"),
+                data: Arc::from(*data),
+            });
+        }
+    }
+    web_fonts
+}
+
+/// Register a font file under a family name for the documents of the origin
+pub fn register_font(family: &str, data: Vec<u8>, origin: &str) -> Result<FontIndex, GfxError> {
+    let data: Arc<[u8]> = data.into();
+    let font =
+        FontArc::try_from_vec(data.to_vec()).map_err(|e| GfxError::InvalidFont(e.to_string()))?;
+    let mut web_fonts = web_fonts();
+    web_fonts.push(WebFont {
+        family: family.to_string(),
+        origin: Some(origin.to_string()),
+        font,
+        data,
+    });
+    Ok(web_fonts.len())
+}
+
+/// A font registered by a page
+pub struct FontFile {
+    pub family: String,
+    pub origin: String,
+    pub data: Arc<[u8]>,
+}
+
+/// Fonts registered with `register_font` after the first `skip` ones, in
+/// registration order. Registering them in the same order in another
+/// process gives them the same indexes there.
+pub fn registered_fonts_from(skip: usize) -> Vec<FontFile> {
+    let web_fonts = web_fonts();
+    web_fonts
+        .iter()
+        .filter_map(|web_font| {
+            Some(FontFile {
+                family: web_font.family.clone(),
+                origin: web_font.origin.clone()?,
+                data: web_font.data.clone(),
+            })
+        })
+        .skip(skip)
+        .collect()
+}
+
+/// Find the first of the font families that is registered for documents of
+/// the origin. Family names are compared case-insensitively. The default font
+/// is used when none of the families are registered.
+pub fn find_font<S: AsRef<str>>(families: &[S], origin: &str) -> FontIndex {
+    let web_fonts = web_fonts();
+    families
+        .iter()
+        .find_map(|family| {
+            // the latest registration of a family wins
+            web_fonts.iter().rposition(|font| {
+                font.family.eq_ignore_ascii_case(family.as_ref())
+                    && (font.origin.is_none() || font.origin.as_deref() == Some(origin))
+            })
+        })
+        .map(|index| index + 1)
+        .unwrap_or(DEFAULT_FONT)
+}
+
+/// File of the font. Unknown fonts fall back to the default font.
+pub(crate) fn font_data(font: FontIndex) -> Arc<[u8]> {
+    let web_fonts = web_fonts();
+    match font.checked_sub(1).and_then(|index| web_fonts.get(index)) {
+        Some(web_font) => web_font.data.clone(),
+        None => fonts::FALLBACK.into(),
+    }
+}
+
+/// Web fonts registered after the first `skip` ones
+pub(crate) fn web_fonts_from(skip: usize) -> Vec<FontArc> {
+    let web_fonts = web_fonts();
+    web_fonts
+        .iter()
+        .skip(skip)
+        .map(|web_font| web_font.font.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::FALLBACK;
+
+    #[test]
+    fn test_find_registered_font() {
+        let origin = "https://fonts.test";
+        assert!(register_font("Broken Font", vec![0; 16], origin).is_err());
+
+        let index = register_font("Test Sans", FALLBACK.to_vec(), origin).unwrap();
+        assert_eq!(find_font(&["Missing", "test sans"], origin), index);
+        assert_eq!(find_font(&["Missing", "serif"], origin), DEFAULT_FONT);
+        assert_eq!(web_fonts_from(index - 1).len(), 1);
+        let registered = registered_fonts_from(index - 1 - BUNDLED_FONTS.len());
+        assert_eq!(registered[0].family, "Test Sans");
+        // Documents of other origins don't see the font
+        assert_eq!(
+            find_font(&["Test Sans"], "https://other.test"),
+            DEFAULT_FONT
+        );
+    }
+
+    #[test]
+    fn test_find_bundled_font() {
+        let monospace = find_font(&["Missing", "monospace"], "null");
+        assert_ne!(monospace, DEFAULT_FONT);
+        assert_eq!(&*font_data(monospace), fonts::MONOSPACE);
+    }
+}
@@ -1 +1,2 @@
 pub const FALLBACK: &[u8] = include_bytes!("../fonts/Lato-Regular.ttf");
+pub const MONOSPACE: &[u8] = include_bytes!("../fonts/Inconsolata-Regular.ttf");
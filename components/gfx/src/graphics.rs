@@ -1,13 +1,62 @@
 use async_trait::async_trait;
 use shared::color::Color;
+use shared::image::ImageBitmap;
 use shared::primitive::*;
 
+use crate::font_database::FontIndex;
+use crate::layer::Layer;
+
 #[async_trait(?Send)]
 pub trait Graphics {
     fn fill_rect(&mut self, rect: Rect, color: Color);
     fn fill_rrect(&mut self, rect: RRect, color: Color);
-    fn fill_text(&mut self, content: String, bounds: Rect, color: Color, size: f32);
+    fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    );
+    /// Fill text that runs from the top to the bottom of `bounds`, with its
+    /// glyphs rotated clockwise so that their tops face right
+    fn fill_vertical_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    );
     fn fill_polygon(&mut self, points: Vec<Point>, color: Color);
+    /// Draw the bitmap stretched to `bounds`
+    fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap);
+    /// Fill the shadow of a box, blurred by `blur`, outside of `caster`,
+    /// the border box of the box
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect);
+    /// Fill the inner shadow of a box inside `bounds`, cast around `hole`
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color);
+    /// Clip the draws that follow to the rect, or stop clipping with `None`
+    fn set_clip(&mut self, clip: Option<Rect>);
+    /// Whether content of the size, in CSS pixels, can be painted into a
+    /// layer. Graphics that don't composite layers have all content painted
+    /// in place.
+    fn can_composite_layer(&self, _content_size: &Size) -> bool {
+        false
+    }
+    /// Paint the draws that follow, until `end_layer`, into the layer
+    /// instead of the frame, positioned relative to the content of the
+    /// layer. Returns false if the layer kept its content from a previous
+    /// frame and only needs to be composited again, in which case nothing
+    /// is drawn until `end_layer`.
+    fn begin_layer(&mut self, _layer: Layer) -> bool {
+        true
+    }
+    fn end_layer(&mut self) {}
+    /// Paint the content of the layers again, which changed
+    fn invalidate_layers(&mut self) {}
     fn resize(&mut self, size: Size);
+    /// Set the number of device pixels per CSS pixel for subsequent draws
+    fn set_scale(&mut self, scale: f32);
     async fn output(&mut self) -> Vec<u8>;
 }
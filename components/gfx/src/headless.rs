@@ -0,0 +1,110 @@
+use async_trait::async_trait;
+use shared::color::Color;
+use shared::image::ImageBitmap;
+use shared::primitive::*;
+
+use crate::font_database::FontIndex;
+use crate::painters::polygon::PolygonPainter;
+use crate::painters::rect::RectPainter;
+use crate::painters::shadow::ShadowPainter;
+use crate::tessellator::Tessellator;
+use crate::Graphics;
+
+/// Graphics that tessellates shapes like `Canvas` does but never touches the
+/// GPU. Nothing is rasterized, which makes it suitable for benchmarking the
+/// CPU side of painting.
+pub struct HeadlessGraphics {
+    tessellator: Tessellator,
+    rect_painter: RectPainter,
+    shadow_painter: ShadowPainter,
+    polygon_painter: PolygonPainter,
+    vertex_count: usize,
+    images: usize,
+    image_count: usize,
+}
+
+impl HeadlessGraphics {
+    pub fn new() -> Self {
+        Self {
+            tessellator: Tessellator::new(),
+            rect_painter: RectPainter::new(),
+            shadow_painter: ShadowPainter::new(),
+            polygon_painter: PolygonPainter::new(),
+            vertex_count: 0,
+            images: 0,
+            image_count: 0,
+        }
+    }
+
+    /// Number of vertices tessellated for the last output
+    pub fn vertex_count(&self) -> usize {
+        self.vertex_count
+    }
+
+    /// Number of images drawn for the last output
+    pub fn image_count(&self) -> usize {
+        self.image_count
+    }
+}
+
+impl Default for HeadlessGraphics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Graphics for HeadlessGraphics {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.rect_painter
+            .draw_solid_rect(&mut self.tessellator, &rect, &color);
+    }
+
+    fn fill_rrect(&mut self, rect: RRect, color: Color) {
+        self.rect_painter
+            .draw_solid_rrect(&mut self.tessellator, &rect, &color);
+    }
+
+    fn fill_text(&mut self, _: String, _: Rect, _: Color, _: f32, _: FontIndex) {}
+
+    fn fill_vertical_text(&mut self, _: String, _: Rect, _: Color, _: f32, _: FontIndex) {}
+
+    fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
+        self.polygon_painter
+            .fill_polygon(&mut self.tessellator, &points, &color);
+    }
+
+    fn draw_image(&mut self, _: Rect, _: ImageBitmap) {
+        self.images += 1;
+    }
+
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect) {
+        self.shadow_painter
+            .draw_shadow(&mut self.tessellator, &rect, blur, &color, caster);
+    }
+
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color) {
+        self.shadow_painter
+            .draw_inset_shadow(&mut self.tessellator, bounds, &hole, blur, &color);
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.tessellator.set_clip(clip);
+    }
+
+    fn resize(&mut self, _: Size) {}
+
+    fn set_scale(&mut self, _: f32) {}
+
+    async fn output(&mut self) -> Vec<u8> {
+        self.vertex_count = self
+            .tessellator
+            .meshes()
+            .iter()
+            .map(|mesh| mesh.buffers.vertices.len())
+            .sum();
+        self.image_count = std::mem::take(&mut self.images);
+        self.tessellator.clear();
+        Vec::new()
+    }
+}
@@ -0,0 +1,647 @@
+use bytemuck::{Pod, Zeroable};
+use shared::image::ImageBitmap;
+use shared::memory::MemoryUsage;
+use shared::primitive::Rect;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::atlas::{extrude_edges, AtlasAllocator};
+use crate::backend::{RenderTarget, ScissorRegion};
+use crate::triangle::Buffer;
+use ultraviolet as uv;
+
+const VERTEX_BUFFER_SIZE: usize = 60;
+
+/// Two triangles covering the bounds of an image
+const VERTICES_PER_IMAGE: usize = 6;
+
+/// Size of the texture small images are packed into, in pixels
+const ATLAS_SIZE: u32 = 1024;
+
+/// Images up to this size are packed into the atlas, larger ones get a
+/// texture of their own
+const MAX_ATLAS_IMAGE_DIMENSION: u32 = 256;
+
+/// A texture stretched over `bounds`, in CSS pixels, like the texture of a
+/// layer
+pub struct TexturedQuad<'a> {
+    pub bind_group: &'a wgpu::BindGroup,
+    pub bounds: Rect,
+    pub scissor: ScissorRegion,
+}
+
+pub struct Image {
+    pub bitmap: ImageBitmap,
+    /// Rect the image is stretched to, in CSS pixels
+    pub bounds: Rect,
+    /// Area the image is clipped to, in CSS pixels
+    pub clip: Option<Rect>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Vertex {
+    pos: uv::Vec2,
+    tex_coords: uv::Vec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Uniforms {
+    screen_size: uv::Vec2,
+}
+
+unsafe impl Pod for Vertex {}
+unsafe impl Zeroable for Vertex {}
+
+unsafe impl Pod for Uniforms {}
+unsafe impl Zeroable for Uniforms {}
+
+/// Texture of a bitmap, kept while the bitmap is drawn in every frame. The
+/// bitmap is held so that the key of its pixels isn't reused by another one,
+/// and to measure the texture.
+struct CachedTexture {
+    bitmap: ImageBitmap,
+    placement: Placement,
+    is_used: bool,
+}
+
+enum Placement {
+    Texture {
+        _texture: wgpu::Texture,
+        bind_group: wgpu::BindGroup,
+    },
+    /// Texture coordinates of the bitmap in the atlas
+    Atlas(Rect),
+}
+
+/// Texture that small images share, so that consecutive images are drawn
+/// together
+struct Atlas {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    allocator: AtlasAllocator,
+}
+
+pub struct Pipeline {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Buffer<Vertex>,
+    constants: wgpu::BindGroup,
+    uniforms_buffer: Buffer<Uniforms>,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    textures: HashMap<usize, CachedTexture>,
+    atlas: Option<Atlas>,
+}
+
+impl Pipeline {
+    /// Pipeline for bitmaps, which don't have premultiplied alpha
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        let blend = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+        Self::with_blend(device, format, sample_count, blend)
+    }
+
+    /// Pipeline for textures that were rendered to, like the textures of
+    /// layers. Drawing onto a transparent texture premultiplies its colors
+    /// by their alpha.
+    pub fn compositor(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        Self::with_blend(
+            device,
+            format,
+            sample_count,
+            wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+        )
+    }
+
+    fn with_blend(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        blend: wgpu::BlendState,
+    ) -> Self {
+        let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("image shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/shaders/image.wgsl"
+            )))),
+        });
+
+        let constants_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("moon::gfx::image uniforms layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniforms_buffer = Buffer::new(
+            "moon::gfx::image uniforms buffer",
+            device,
+            1,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+
+        let constants = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("moon::gfx::image uniforms bind group"),
+            layout: &constants_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &uniforms_buffer.raw,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<Uniforms>() as u64),
+                }),
+            }],
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("moon::gfx::image texture layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler {
+                        filtering: true,
+                        comparison: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("moon::gfx::image sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("moon::gfx::image pipeline layout"),
+            bind_group_layouts: &[&constants_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("moon::gfx::image pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x2,
+                        1 => Float32x2
+                    ],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer: Buffer::new(
+                "moon::gfx::image vertex buffer",
+                device,
+                VERTEX_BUFFER_SIZE,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            ),
+            constants,
+            uniforms_buffer,
+            texture_layout,
+            sampler,
+            textures: HashMap::new(),
+            atlas: None,
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        images: &[(&Image, ScissorRegion)],
+        target: &RenderTarget,
+        screen_size: (f32, f32),
+    ) {
+        for texture in self.textures.values_mut() {
+            texture.is_used = false;
+        }
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let images = images
+            .iter()
+            .filter(|(image, _)| {
+                let bitmap = &image.bitmap;
+                let fits = bitmap.width() <= max_dimension && bitmap.height() <= max_dimension;
+                if !fits {
+                    log::warn!(
+                        "Unable to draw an image of {}x{} pixels, larger than the textures of the device",
+                        bitmap.width(),
+                        bitmap.height()
+                    );
+                }
+                fits
+            })
+            .collect::<Vec<_>>();
+        let mut is_atlas_full = false;
+        for (image, _) in &images {
+            is_atlas_full |= !self.upload(device, queue, &image.bitmap);
+        }
+        if is_atlas_full {
+            // The atlas may be full of images that are not drawn anymore, so
+            // it is packed again with the images of this frame only
+            self.textures
+                .retain(|_, texture| matches!(texture.placement, Placement::Texture { .. }));
+            if let Some(atlas) = &mut self.atlas {
+                atlas.allocator.clear();
+            }
+            for (image, _) in &images {
+                if !self.upload(device, queue, &image.bitmap) {
+                    self.upload_texture(device, queue, &image.bitmap);
+                }
+            }
+        }
+        // Textures of images that are not drawn anymore are released
+        self.textures.retain(|_, texture| texture.is_used);
+
+        if images.is_empty() {
+            return;
+        }
+
+        let vertices = images
+            .iter()
+            .flat_map(|(image, _)| {
+                let (_, tex_coords) = self.texture(&image.bitmap);
+                quad(&image.bounds, &tex_coords)
+            })
+            .collect::<Vec<_>>();
+        self.write_vertices(device, queue, &vertices, screen_size);
+        let quads = images
+            .iter()
+            .map(|(image, scissor)| (self.texture(&image.bitmap).0, scissor));
+        self.render(encoder, target, quads);
+    }
+
+    /// Draw the textures onto the target in order
+    pub fn composite(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        quads: &[TexturedQuad],
+        target: &RenderTarget,
+        screen_size: (f32, f32),
+    ) {
+        if quads.is_empty() {
+            return;
+        }
+        let vertices = quads
+            .iter()
+            .flat_map(|textured| quad(&textured.bounds, &Rect::new(0., 0., 1., 1.)))
+            .collect::<Vec<_>>();
+        self.write_vertices(device, queue, &vertices, screen_size);
+        let quads = quads.iter().map(|quad| (quad.bind_group, &quad.scissor));
+        self.render(encoder, target, quads);
+    }
+
+    /// Bind group that samples the texture, to composite it
+    pub fn texture_bind_group(
+        &self,
+        device: &wgpu::Device,
+        view: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("moon::gfx::image texture bind group"),
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// Textures of the images drawn in the last frame, with the atlas
+    /// small images are packed into
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let own_textures = self
+            .textures
+            .values()
+            .filter(|texture| matches!(texture.placement, Placement::Texture { .. }))
+            .map(|texture| texture_bytes(texture.bitmap.width(), texture.bitmap.height()))
+            .sum::<usize>();
+        let atlas_bytes = match self.atlas {
+            Some(_) => texture_bytes(ATLAS_SIZE, ATLAS_SIZE),
+            None => 0,
+        };
+        MemoryUsage::new(self.textures.len(), own_textures + atlas_bytes)
+    }
+
+    /// Bind group and texture coordinates of an uploaded bitmap
+    fn texture(&self, bitmap: &ImageBitmap) -> (&wgpu::BindGroup, Rect) {
+        match &self.textures[&bitmap.key()].placement {
+            Placement::Texture { bind_group, .. } => (bind_group, Rect::new(0., 0., 1., 1.)),
+            Placement::Atlas(tex_coords) => {
+                let atlas = self
+                    .atlas
+                    .as_ref()
+                    .expect("Images are packed into the atlas");
+                (&atlas.bind_group, tex_coords.clone())
+            }
+        }
+    }
+
+    /// Write the quads, in the order they are rendered
+    fn write_vertices(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        vertices: &[Vertex],
+        screen_size: (f32, f32),
+    ) {
+        self.vertex_buffer.expand(device, vertices.len());
+        // Like the textures, the buffers are written by the queue before the
+        // commands of the frame run
+        queue.write_buffer(&self.vertex_buffer.raw, 0, bytemuck::cast_slice(vertices));
+        let uniforms = [Uniforms {
+            screen_size: uv::Vec2::new(screen_size.0, screen_size.1),
+        }];
+        queue.write_buffer(
+            &self.uniforms_buffer.raw,
+            0,
+            bytemuck::cast_slice(&uniforms),
+        );
+    }
+
+    /// Render the quads written by `write_vertices`, each sampling the
+    /// texture of its bind group. Consecutive quads with the same texture
+    /// and scissor are drawn together.
+    fn render<'b>(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &RenderTarget,
+        quads: impl Iterator<Item = (&'b wgpu::BindGroup, &'b ScissorRegion)>,
+    ) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("moon::gfx::image renderpass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: target.view(),
+                resolve_target: target.resolve_target(),
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.constants, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.raw.slice(..));
+
+        let quads = quads.collect::<Vec<_>>();
+        let mut start = 0;
+        while start < quads.len() {
+            let (bind_group, scissor) = quads[start];
+            let end = quads[start..]
+                .iter()
+                .position(|(other_bind_group, other_scissor)| {
+                    !std::ptr::eq(*other_bind_group, bind_group) || *other_scissor != scissor
+                })
+                .map_or(quads.len(), |length| start + length);
+
+            render_pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+            render_pass.set_bind_group(1, bind_group, &[]);
+            render_pass.draw(
+                (start * VERTICES_PER_IMAGE) as u32..(end * VERTICES_PER_IMAGE) as u32,
+                0..1,
+            );
+            start = end;
+        }
+    }
+
+    /// Upload the bitmap unless it is uploaded already. Small bitmaps are
+    /// packed into the atlas, returns false if it is too full to fit the
+    /// bitmap.
+    fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, bitmap: &ImageBitmap) -> bool {
+        if let Some(texture) = self.textures.get_mut(&bitmap.key()) {
+            texture.is_used = true;
+            return true;
+        }
+        if bitmap.width() > MAX_ATLAS_IMAGE_DIMENSION || bitmap.height() > MAX_ATLAS_IMAGE_DIMENSION
+        {
+            self.upload_texture(device, queue, bitmap);
+            return true;
+        }
+
+        let atlas = match &mut self.atlas {
+            Some(atlas) => atlas,
+            None => {
+                let atlas = self.create_atlas(device);
+                self.atlas.insert(atlas)
+            }
+        };
+        // The bitmap is surrounded by its extruded edges
+        let (width, height) = (bitmap.width() + 2, bitmap.height() + 2);
+        let (x, y) = match atlas.allocator.allocate(width, height) {
+            Some(position) => position,
+            None => return false,
+        };
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &atlas.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &extrude_edges(bitmap.data(), bitmap.width(), bitmap.height()),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: core::num::NonZeroU32::new(4 * width),
+                rows_per_image: core::num::NonZeroU32::new(height),
+            },
+            size,
+        );
+
+        let atlas_size = ATLAS_SIZE as f32;
+        let tex_coords = Rect::new(
+            (x + 1) as f32 / atlas_size,
+            (y + 1) as f32 / atlas_size,
+            bitmap.width() as f32 / atlas_size,
+            bitmap.height() as f32 / atlas_size,
+        );
+        self.textures.insert(
+            bitmap.key(),
+            CachedTexture {
+                bitmap: bitmap.clone(),
+                placement: Placement::Atlas(tex_coords),
+                is_used: true,
+            },
+        );
+        true
+    }
+
+    fn create_atlas(&self, device: &wgpu::Device) -> Atlas {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("moon::gfx::image atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        let view = texture.create_view(&Default::default());
+        let bind_group = self.texture_bind_group(device, &view);
+        Atlas {
+            texture,
+            bind_group,
+            allocator: AtlasAllocator::new(ATLAS_SIZE),
+        }
+    }
+
+    /// Upload the bitmap to a texture of its own
+    fn upload_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, bitmap: &ImageBitmap) {
+        let size = wgpu::Extent3d {
+            width: bitmap.width(),
+            height: bitmap.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("moon::gfx::image texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bitmap.data(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: core::num::NonZeroU32::new(4 * bitmap.width()),
+                rows_per_image: core::num::NonZeroU32::new(bitmap.height()),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&Default::default());
+        let bind_group = self.texture_bind_group(device, &view);
+
+        self.textures.insert(
+            bitmap.key(),
+            CachedTexture {
+                bitmap: bitmap.clone(),
+                placement: Placement::Texture {
+                    _texture: texture,
+                    bind_group,
+                },
+                is_used: true,
+            },
+        );
+    }
+}
+
+/// Vertices of the two triangles that map the part of the texture at
+/// `tex_coords` onto the rect
+fn quad(rect: &Rect, tex_coords: &Rect) -> [Vertex; VERTICES_PER_IMAGE] {
+    let (left, top) = (rect.x, rect.y);
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    let (u_left, v_top) = (tex_coords.x, tex_coords.y);
+    let (u_right, v_bottom) = (
+        tex_coords.x + tex_coords.width,
+        tex_coords.y + tex_coords.height,
+    );
+    let vertex = |x, y, u, v| Vertex {
+        pos: uv::Vec2::new(x, y),
+        tex_coords: uv::Vec2::new(u, v),
+    };
+    [
+        vertex(left, top, u_left, v_top),
+        vertex(right, top, u_right, v_top),
+        vertex(left, bottom, u_left, v_bottom),
+        vertex(right, top, u_right, v_top),
+        vertex(right, bottom, u_right, v_bottom),
+        vertex(left, bottom, u_left, v_bottom),
+    ]
+}
+
+/// Size of an RGBA8 texture
+fn texture_bytes(width: u32, height: u32) -> usize {
+    width as usize * height as usize * 4
+}
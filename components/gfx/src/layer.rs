@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use shared::primitive::{Point, Rect, Size};
+
+/// Identifies a layer across frames, like the node it is painted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(pub u64);
+
+/// Content painted into a texture of its own, which is composited onto the
+/// frame. Moving the layer, like scrolling it, doesn't paint it again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub id: LayerId,
+    /// Size of the content, in CSS pixels
+    pub content_size: Size,
+    /// Position of the top left corner of the content in the viewport, in
+    /// CSS pixels
+    pub position: Point,
+    /// Part of the viewport the layer is visible in, in CSS pixels
+    pub clip: Rect,
+    /// Thumbs of the scroll bars drawn over the layer, in viewport
+    /// coordinates in CSS pixels
+    pub scrollbars: Vec<Rect>,
+}
+
+/// Layers of the frame with the textures they are painted into. The
+/// layers are composited in the order they are added, with nested layers
+/// after the layer they are in. Textures are kept across frames for the
+/// layers that are composited again, until the layers are invalidated.
+pub struct LayerTree<T> {
+    layers: Vec<Layer>,
+    textures: HashMap<LayerId, CachedTexture<T>>,
+}
+
+struct CachedTexture<T> {
+    texture: T,
+    /// Size of the texture in device pixels
+    size: (u32, u32),
+    /// Whether the texture holds the current content of the layer
+    is_valid: bool,
+    is_used: bool,
+}
+
+impl<T> LayerTree<T> {
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            textures: HashMap::new(),
+        }
+    }
+
+    /// Add the layer to the frame. Returns true if the content of the layer
+    /// has to be painted, false if its texture already holds it.
+    pub fn push(&mut self, layer: Layer, size: (u32, u32)) -> bool {
+        let is_cached = match self.textures.get_mut(&layer.id) {
+            Some(cached) => {
+                cached.is_used = true;
+                cached.is_valid && cached.size == size
+            }
+            None => false,
+        };
+        self.layers.push(layer);
+        !is_cached
+    }
+
+    /// Take the texture of the layer to paint its content again, if it has
+    /// one of the size
+    pub fn take_texture(&mut self, id: LayerId, size: (u32, u32)) -> Option<T> {
+        match self.textures.get(&id) {
+            Some(cached) if cached.size == size => self.textures.remove(&id).map(|c| c.texture),
+            _ => None,
+        }
+    }
+
+    /// Keep the texture the content of the layer was painted into
+    pub fn insert_texture(&mut self, id: LayerId, size: (u32, u32), texture: T) {
+        self.textures.insert(
+            id,
+            CachedTexture {
+                texture,
+                size,
+                is_valid: true,
+                is_used: true,
+            },
+        );
+    }
+
+    pub fn texture(&self, id: LayerId) -> Option<&T> {
+        self.textures.get(&id).map(|cached| &cached.texture)
+    }
+
+    /// Layers of the frame in the order they are composited
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Paint the content of every layer again in the next frames
+    pub fn invalidate(&mut self) {
+        for cached in self.textures.values_mut() {
+            cached.is_valid = false;
+        }
+    }
+
+    /// Prepare for the next frame. Textures of the layers that were not
+    /// composited in this frame are released.
+    pub fn clear(&mut self) {
+        self.layers.clear();
+        self.textures.retain(|_, cached| cached.is_used);
+        for cached in self.textures.values_mut() {
+            cached.is_used = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(id: u64) -> Layer {
+        Layer {
+            id: LayerId(id),
+            content_size: Size::new(100., 400.),
+            position: Point::new(0., 0.),
+            clip: Rect::new(0., 0., 100., 100.),
+            scrollbars: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_layer_textures_are_reused_until_invalidated() {
+        let mut tree = LayerTree::new();
+        assert!(tree.push(layer(1), (100, 400)));
+        tree.insert_texture(LayerId(1), (100, 400), "first");
+        tree.clear();
+
+        // Scrolling moves the layer without painting it again
+        let mut scrolled = layer(1);
+        scrolled.position = Point::new(0., -50.);
+        assert!(!tree.push(scrolled.clone(), (100, 400)));
+        assert_eq!(tree.layers(), &[scrolled]);
+        assert_eq!(tree.texture(LayerId(1)), Some(&"first"));
+        tree.clear();
+
+        // A texture of another size can't hold the content
+        assert!(tree.push(layer(1), (200, 800)));
+        assert_eq!(tree.take_texture(LayerId(1), (200, 800)), None);
+        tree.clear();
+
+        tree.invalidate();
+        assert!(tree.push(layer(1), (100, 400)));
+        assert_eq!(tree.take_texture(LayerId(1), (100, 400)), Some("first"));
+    }
+
+    #[test]
+    fn test_textures_of_removed_layers_are_released() {
+        let mut tree = LayerTree::new();
+        tree.push(layer(1), (100, 400));
+        tree.insert_texture(LayerId(1), (100, 400), ());
+        tree.clear();
+
+        tree.push(layer(2), (100, 400));
+        tree.clear();
+        assert!(tree.texture(LayerId(1)).is_none());
+    }
+}
@@ -1,15 +1,35 @@
+mod atlas;
 mod backend;
 mod canvas;
+mod config;
+mod error;
+mod font_database;
 mod fonts;
 mod graphics;
+mod headless;
+mod image;
+mod layer;
 mod painters;
+mod pdf;
+mod raster;
 mod tessellator;
 mod text;
 mod text_measure;
 mod triangle;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
 pub type Bitmap = Vec<u8>;
 
 pub use canvas::Canvas;
+pub use config::{Antialiasing, GfxConfig};
+pub use error::GfxError;
+pub use font_database::{
+    find_font, register_font, registered_fonts_from, FontFile, FontIndex, DEFAULT_FONT,
+};
 pub use graphics::Graphics;
-pub use text_measure::TextMeasure;
+pub use headless::HeadlessGraphics;
+pub use layer::{Layer, LayerId};
+pub use pdf::PdfGraphics;
+pub use raster::RasterGraphics;
+pub use text_measure::{FontMetrics, TextMeasure};
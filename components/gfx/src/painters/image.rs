@@ -0,0 +1,39 @@
+use shared::{image::ImageBitmap, primitive::Rect};
+
+use crate::image::Image;
+
+pub struct ImagePainter {
+    images: Vec<Image>,
+    clip: Option<Rect>,
+}
+
+impl ImagePainter {
+    pub fn new() -> Self {
+        Self {
+            images: Vec::new(),
+            clip: None,
+        }
+    }
+
+    /// Clip the images drawn from now on to the rect
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    pub fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap) {
+        self.images.push(Image {
+            bitmap,
+            bounds,
+            clip: self.clip.clone(),
+        })
+    }
+
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.clip = None;
+    }
+}
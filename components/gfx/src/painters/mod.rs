@@ -1,3 +1,5 @@
+pub mod image;
 pub mod polygon;
 pub mod rect;
+pub mod shadow;
 pub mod text;
@@ -39,76 +39,64 @@ impl RectPainter {
             color.a.into(),
         ];
 
+        tessellator.tessellate_rrect(rect, color_arr, || Self::rrect_path_at_origin(rect));
+    }
+
+    /// Path of the rounded rect moved to the origin, so that its tessellation
+    /// can be reused for rounded rects with the same shape
+    fn rrect_path_at_origin(rect: &RRect) -> Path {
+        // White, the vertex shader multiplies it with the color of each rrect
+        let color_arr = [255.; 4];
         let corners = &rect.corners;
 
         let mut path_builder = Path::builder_with_attributes(4);
-        path_builder.begin(
-            point(rect.x + rect.corners.top_left.horizontal_r(), rect.y),
-            &color_arr,
-        );
+        path_builder.begin(point(corners.top_left.horizontal_r(), 0.), &color_arr);
 
         path_builder.line_to(
-            point(
-                rect.x + rect.width - corners.top_right.horizontal_r(),
-                rect.y,
-            ),
+            point(rect.width - corners.top_right.horizontal_r(), 0.),
             &color_arr,
         );
 
         path_builder.quadratic_bezier_to(
-            point(rect.x + rect.width, rect.y),
-            point(rect.x + rect.width, rect.y + corners.top_right.vertical_r()),
+            point(rect.width, 0.),
+            point(rect.width, corners.top_right.vertical_r()),
             &color_arr,
         );
 
         path_builder.line_to(
-            point(
-                rect.x + rect.width,
-                rect.y + rect.height - corners.bottom_right.vertical_r(),
-            ),
+            point(rect.width, rect.height - corners.bottom_right.vertical_r()),
             &color_arr,
         );
 
         path_builder.quadratic_bezier_to(
-            point(rect.x + rect.width, rect.y + rect.height),
+            point(rect.width, rect.height),
             point(
-                rect.x + rect.width - corners.bottom_right.horizontal_r(),
-                rect.y + rect.height,
+                rect.width - corners.bottom_right.horizontal_r(),
+                rect.height,
             ),
             &color_arr,
         );
 
         path_builder.line_to(
-            point(
-                rect.x + corners.bottom_left.horizontal_r(),
-                rect.y + rect.height,
-            ),
+            point(corners.bottom_left.horizontal_r(), rect.height),
             &color_arr,
         );
 
         path_builder.quadratic_bezier_to(
-            point(rect.x, rect.y + rect.height),
-            point(
-                rect.x,
-                rect.y + rect.height - corners.bottom_left.vertical_r(),
-            ),
+            point(0., rect.height),
+            point(0., rect.height - corners.bottom_left.vertical_r()),
             &color_arr,
         );
 
-        path_builder.line_to(
-            point(rect.x, rect.y + corners.top_left.vertical_r()),
-            &color_arr,
-        );
+        path_builder.line_to(point(0., corners.top_left.vertical_r()), &color_arr);
 
         path_builder.quadratic_bezier_to(
-            point(rect.x, rect.y),
-            point(rect.x + corners.top_left.horizontal_r(), rect.y),
+            point(0., 0.),
+            point(corners.top_left.horizontal_r(), 0.),
             &color_arr,
         );
 
         path_builder.end(true);
-
-        let path = path_builder.build();
-        tessellator.tessellate_path(path);
+        path_builder.build()
     }
 }
@@ -0,0 +1,427 @@
+use lyon_tessellation::{geom::point, path::Path};
+use shared::{
+    color::Color,
+    primitive::{Point, RRect, Rect},
+};
+
+use crate::tessellator::Tessellator;
+
+/// Number of segments of the polygons that approximate rounded corners
+const CORNER_SEGMENTS: usize = 8;
+
+/// A convex polygon with a color at each vertex
+type Polygon = Vec<(Point, [f32; 4])>;
+
+/// Paints blurred shadows without a blur pass. The blur is approximated by
+/// a frame around the edges of the shadow whose opacity fades linearly from
+/// the shadow color to transparent over `2 * blur`.
+///
+/// The shapes of a shadow are clipped on the CPU: outer shadows to the
+/// outside of the box that casts them, inner shadows to the inside of the
+/// box.
+pub struct ShadowPainter;
+
+impl ShadowPainter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Paint the shadow of `rect` outside of `caster`, the border box of
+    /// the box that casts it
+    pub fn draw_shadow(
+        &self,
+        tessellator: &mut Tessellator,
+        rect: &Rect,
+        blur: f32,
+        color: &Color,
+        caster: &RRect,
+    ) {
+        let solid = inset_rect(rect, blur);
+        let outer = inset_rect(rect, -blur);
+
+        let mut polygons = Vec::new();
+        if solid.width > 0. && solid.height > 0. {
+            polygons.push(rect_polygon(&solid, color));
+        }
+        if blur > 0. {
+            polygons.extend(frame(&outer, &solid, &transparent(color), color));
+        }
+
+        let regions = outside_regions(caster, &union_rect(&outer, caster));
+        for polygon in &polygons {
+            for region in &regions {
+                fill_polygon(tessellator, &clip_polygon(polygon, region));
+            }
+        }
+    }
+
+    /// Paint the shadow inside of `bounds` cast by the edges of `hole`. The
+    /// shadow covers `bounds` except the hole.
+    pub fn draw_inset_shadow(
+        &self,
+        tessellator: &mut Tessellator,
+        bounds: &RRect,
+        hole: &Rect,
+        blur: f32,
+        color: &Color,
+    ) {
+        let blur_start = inset_rect(hole, -blur);
+        let blur_end = inset_rect(hole, blur);
+        let outer = union_rect(bounds, &blur_start);
+
+        let mut polygons = frame(&outer, &blur_start, color, color);
+        polygons.extend(frame(&blur_start, &blur_end, color, &transparent(color)));
+
+        let region = rrect_polygon(bounds);
+        for polygon in &polygons {
+            fill_polygon(tessellator, &clip_polygon(polygon, &region));
+        }
+    }
+}
+
+/// The area between `outer` and `inner` as a trapezoid per edge. The color
+/// blends from `outer_color` at the outer edge to `inner_color` at the inner
+/// edge.
+fn frame(outer: &Rect, inner: &Rect, outer_color: &Color, inner_color: &Color) -> Vec<Polygon> {
+    let outer_corners = corners(outer);
+    let inner_corners = corners(inner);
+    let (outer_color, inner_color) = (to_array(outer_color), to_array(inner_color));
+
+    (0..4)
+        .map(|index| {
+            let next = (index + 1) % 4;
+            vec![
+                (outer_corners[index].clone(), outer_color),
+                (outer_corners[next].clone(), outer_color),
+                (inner_corners[next].clone(), inner_color),
+                (inner_corners[index].clone(), inner_color),
+            ]
+        })
+        .collect()
+}
+
+fn rect_polygon(rect: &Rect, color: &Color) -> Polygon {
+    let color = to_array(color);
+    corners(rect)
+        .iter()
+        .map(|corner| (corner.clone(), color))
+        .collect()
+}
+
+fn fill_polygon(tessellator: &mut Tessellator, polygon: &Polygon) {
+    if polygon.len() < 3 {
+        return;
+    }
+    let mut path_builder = Path::builder_with_attributes(4);
+    let (first, color) = &polygon[0];
+    path_builder.begin(to_point(first), color);
+    for (position, color) in &polygon[1..] {
+        path_builder.line_to(to_point(position), color);
+    }
+    path_builder.end(true);
+    tessellator.tessellate_path(path_builder.build());
+}
+
+/// Convex regions that cover the part of `bounds` outside of the rounded
+/// rect: a band on each side of its rect, and a fan of triangles between
+/// each rounded corner and the corner of its rect
+fn outside_regions(rrect: &RRect, bounds: &Rect) -> Vec<Vec<Point>> {
+    let (left, top) = (rrect.x, rrect.y);
+    let (right, bottom) = (rrect.x + rrect.width, rrect.y + rrect.height);
+    let (bounds_right, bounds_bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+    let bands = [
+        Rect::new(bounds.x, bounds.y, bounds.width, top - bounds.y),
+        Rect::new(bounds.x, bottom, bounds.width, bounds_bottom - bottom),
+        Rect::new(bounds.x, top, left - bounds.x, rrect.height),
+        Rect::new(right, top, bounds_right - right, rrect.height),
+    ];
+
+    let mut regions = bands
+        .iter()
+        .filter(|band| band.width > 0. && band.height > 0.)
+        .map(|band| corners(band).to_vec())
+        .collect::<Vec<_>>();
+    for (corner, arc) in corner_arcs(rrect) {
+        for segment in arc.windows(2) {
+            regions.push(vec![corner.clone(), segment[0].clone(), segment[1].clone()]);
+        }
+    }
+    regions
+}
+
+/// Polygon of the rounded rect, which is convex
+fn rrect_polygon(rrect: &RRect) -> Vec<Point> {
+    let mut polygon: Vec<Point> = Vec::new();
+    for (_, arc) in corner_arcs(rrect) {
+        for point in arc {
+            if polygon.last() != Some(&point) {
+                polygon.push(point);
+            }
+        }
+    }
+    polygon
+}
+
+/// Each corner of the rect of the rounded rect, with the points of its
+/// rounded corner in clockwise order. The curve is the quadratic bezier
+/// that rounded rects are filled with. Square corners have a single point.
+fn corner_arcs(rrect: &RRect) -> Vec<(Point, Vec<Point>)> {
+    let corners = &rrect.corners;
+    let (left, top) = (rrect.x, rrect.y);
+    let (right, bottom) = (rrect.x + rrect.width, rrect.y + rrect.height);
+    let arcs = [
+        (
+            Point::new(left, top),
+            Point::new(left, top + corners.top_left.vertical_r()),
+            Point::new(left + corners.top_left.horizontal_r(), top),
+        ),
+        (
+            Point::new(right, top),
+            Point::new(right - corners.top_right.horizontal_r(), top),
+            Point::new(right, top + corners.top_right.vertical_r()),
+        ),
+        (
+            Point::new(right, bottom),
+            Point::new(right, bottom - corners.bottom_right.vertical_r()),
+            Point::new(right - corners.bottom_right.horizontal_r(), bottom),
+        ),
+        (
+            Point::new(left, bottom),
+            Point::new(left + corners.bottom_left.horizontal_r(), bottom),
+            Point::new(left, bottom - corners.bottom_left.vertical_r()),
+        ),
+    ];
+
+    arcs.iter()
+        .map(|(corner, from, to)| {
+            if from == corner && to == corner {
+                return (corner.clone(), vec![corner.clone()]);
+            }
+            let arc = (0..=CORNER_SEGMENTS)
+                .map(|segment| {
+                    let t = segment as f32 / CORNER_SEGMENTS as f32;
+                    let (a, b, c) = ((1. - t) * (1. - t), 2. * t * (1. - t), t * t);
+                    Point::new(
+                        a * from.x + b * corner.x + c * to.x,
+                        a * from.y + b * corner.y + c * to.y,
+                    )
+                })
+                .collect();
+            (corner.clone(), arc)
+        })
+        .collect()
+}
+
+/// The part of the convex polygon inside of the convex region, with the
+/// colors interpolated at the new vertices (Sutherland-Hodgman)
+fn clip_polygon(polygon: &Polygon, region: &[Point]) -> Polygon {
+    let orientation = signed_area(region).signum();
+    if orientation == 0. {
+        return Vec::new();
+    }
+
+    let mut output = polygon.clone();
+    for (index, from) in region.iter().enumerate() {
+        let to = &region[(index + 1) % region.len()];
+        // Positive on the inner side of the edge
+        let side = |point: &Point| {
+            orientation
+                * ((to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x))
+        };
+
+        let input = std::mem::take(&mut output);
+        for (vertex_index, current) in input.iter().enumerate() {
+            let previous = &input[(vertex_index + input.len() - 1) % input.len()];
+            let (current_side, previous_side) = (side(&current.0), side(&previous.0));
+            if (current_side >= 0.) != (previous_side >= 0.) {
+                let t = previous_side / (previous_side - current_side);
+                output.push(interpolate(previous, current, t));
+            }
+            if current_side >= 0. {
+                output.push(current.clone());
+            }
+        }
+        if output.is_empty() {
+            break;
+        }
+    }
+    output
+}
+
+fn interpolate(from: &(Point, [f32; 4]), to: &(Point, [f32; 4]), t: f32) -> (Point, [f32; 4]) {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let mut color = from.1;
+    for (channel, target) in color.iter_mut().zip(to.1) {
+        *channel = lerp(*channel, target);
+    }
+    (
+        Point::new(lerp(from.0.x, to.0.x), lerp(from.0.y, to.0.y)),
+        color,
+    )
+}
+
+fn signed_area(polygon: &[Point]) -> f32 {
+    let mut area = 0.;
+    for (index, a) in polygon.iter().enumerate() {
+        let b = &polygon[(index + 1) % polygon.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.
+}
+
+/// Move the edges of the rect inward by `distance`, or outward when it is
+/// negative. The rect shrinks at most to its center.
+fn inset_rect(rect: &Rect, distance: f32) -> Rect {
+    let dx = f32::min(distance, rect.width / 2.);
+    let dy = f32::min(distance, rect.height / 2.);
+    Rect::new(
+        rect.x + dx,
+        rect.y + dy,
+        rect.width - 2. * dx,
+        rect.height - 2. * dy,
+    )
+}
+
+/// The smallest rect that contains both rects
+fn union_rect(a: &Rect, b: &Rect) -> Rect {
+    let left = f32::min(a.x, b.x);
+    let top = f32::min(a.y, b.y);
+    let right = f32::max(a.x + a.width, b.x + b.width);
+    let bottom = f32::max(a.y + a.height, b.y + b.height);
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+/// Corners of the rect in clockwise order, starting from the top left
+fn corners(rect: &Rect) -> [Point; 4] {
+    [
+        Point::new(rect.x, rect.y),
+        Point::new(rect.x + rect.width, rect.y),
+        Point::new(rect.x + rect.width, rect.y + rect.height),
+        Point::new(rect.x, rect.y + rect.height),
+    ]
+}
+
+fn transparent(color: &Color) -> Color {
+    Color {
+        a: 0,
+        ..color.clone()
+    }
+}
+
+fn to_point(p: &Point) -> lyon_tessellation::geom::Point<f32> {
+    point(p.x, p.y)
+}
+
+fn to_array(color: &Color) -> [f32; 4] {
+    [
+        color.r.into(),
+        color.g.into(),
+        color.b.into(),
+        color.a.into(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::primitive::{Corners, Radii};
+
+    use crate::triangle::Index;
+
+    use super::*;
+
+    const BLACK: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+
+    fn rrect(rect: Rect, radius: f32) -> RRect {
+        let radii = || Radii::new(radius, radius);
+        RRect {
+            rect,
+            corners: Corners::new(radii(), radii(), radii(), radii()),
+        }
+    }
+
+    /// Positions of the vertices of every triangle that was tessellated
+    fn triangles(tessellator: &Tessellator) -> Vec<[Point; 3]> {
+        let mut triangles = Vec::new();
+        for mesh in tessellator.meshes() {
+            let position = |index: Index| {
+                let vertex = &mesh.buffers.vertices[index as usize];
+                Point::new(vertex.pos.x, vertex.pos.y)
+            };
+            for triangle in mesh.buffers.indices.chunks(3) {
+                triangles.push([
+                    position(triangle[0]),
+                    position(triangle[1]),
+                    position(triangle[2]),
+                ]);
+            }
+        }
+        triangles
+    }
+
+    fn area(triangles: &[[Point; 3]]) -> f32 {
+        triangles
+            .iter()
+            .map(|triangle| signed_area(triangle).abs())
+            .sum()
+    }
+
+    #[test]
+    fn test_outer_shadow_is_clipped_to_the_outside_of_the_box() {
+        let mut tessellator = Tessellator::new();
+        let caster = rrect(Rect::new(10., 10., 20., 20.), 0.);
+        ShadowPainter::new().draw_shadow(
+            &mut tessellator,
+            &Rect::new(15., 15., 20., 20.),
+            0.,
+            &BLACK,
+            &caster,
+        );
+
+        let triangles = triangles(&tessellator);
+        // Only the L-shaped part of the shadow that the box doesn't cover
+        assert!((area(&triangles) - (400. - 225.)).abs() < 0.01);
+        let is_under_box = |point: &Point| {
+            point.x > 10.01 && point.x < 29.99 && point.y > 10.01 && point.y < 29.99
+        };
+        assert!(triangles.iter().flatten().all(|point| !is_under_box(point)));
+    }
+
+    #[test]
+    fn test_outer_shadow_follows_rounded_corners() {
+        let mut tessellator = Tessellator::new();
+        let caster = rrect(Rect::new(0., 0., 40., 40.), 10.);
+        ShadowPainter::new().draw_shadow(&mut tessellator, &caster.rect, 0., &BLACK, &caster);
+
+        // The shadow only shows in the rounded corners, between the curve
+        // and the corner of the rect, which is a third of the triangle of
+        // the curve
+        let corner_area = area(&triangles(&tessellator)) / 4.;
+        assert!((corner_area - 50. / 3.).abs() < 1.);
+    }
+
+    #[test]
+    fn test_inset_shadow_is_clipped_to_the_box() {
+        let mut tessellator = Tessellator::new();
+        let bounds = rrect(Rect::new(0., 0., 20., 20.), 0.);
+        ShadowPainter::new().draw_inset_shadow(
+            &mut tessellator,
+            &bounds,
+            &Rect::new(5., 5., 20., 20.),
+            4.,
+            &BLACK,
+        );
+
+        let triangles = triangles(&tessellator);
+        assert!(triangles.iter().flatten().all(|point| {
+            (-0.01..=20.01).contains(&point.x) && (-0.01..=20.01).contains(&point.y)
+        }));
+        // The shadow fades out at the hole shrunk by the blur
+        let transparent_area = 11. * 11.;
+        assert!((area(&triangles) - (400. - transparent_area)).abs() < 0.01);
+    }
+}
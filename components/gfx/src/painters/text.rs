@@ -1,22 +1,43 @@
 use shared::{color::Color, primitive::Rect};
 
+use crate::font_database::FontIndex;
 use crate::text::Text;
 
 pub struct TextPainter {
     texts: Vec<Text>,
+    clip: Option<Rect>,
 }
 
 impl TextPainter {
     pub fn new() -> Self {
-        Self { texts: Vec::new() }
+        Self {
+            texts: Vec::new(),
+            clip: None,
+        }
     }
 
-    pub fn fill_text(&mut self, content: String, bounds: Rect, color: Color, size: f32) {
+    /// Clip the text filled from now on to the rect
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    pub fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+        is_vertical: bool,
+    ) {
         self.texts.push(Text {
             content,
             bounds,
             color,
             size,
+            font,
+            is_vertical,
+            clip: self.clip.clone(),
         })
     }
 
@@ -26,5 +47,6 @@ impl TextPainter {
 
     pub fn clear(&mut self) {
         self.texts.clear();
+        self.clip = None;
     }
 }
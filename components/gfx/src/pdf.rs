@@ -0,0 +1,734 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use async_trait::async_trait;
+use glyph_brush::ab_glyph::{Font, FontArc, ScaleFont};
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect as PdfRect, Ref, Str};
+use shared::color::Color;
+use shared::image::ImageBitmap;
+use shared::primitive::*;
+
+use crate::font_database::{self, FontIndex};
+use crate::Graphics;
+
+/// Number of PDF points per CSS pixel. A CSS pixel is 1/96 inch and a
+/// point is 1/72 inch.
+const POINTS_PER_PX: f32 = 0.75;
+
+/// Number of layers used to approximate the blur of a shadow
+const SHADOW_BLUR_LAYERS: usize = 4;
+
+/// Weight of a cubic bezier control point to approximate a quarter circle
+const KAPPA: f32 = 0.552_284_8;
+
+/// Graphics that records drawing commands as the vector pages of a PDF
+/// document instead of rasterizing them.
+///
+/// Every page is painted in CSS pixels relative to the top left corner of
+/// its content area, the page without its margins. Call `begin_page` to
+/// start a new page, drawing without one starts a page of the full height.
+/// Text is drawn with the fonts it was laid out with, which are embedded in
+/// the document, and so are the images drawn.
+pub struct PdfGraphics {
+    page_size: Size,
+    margins: EdgeSizes,
+    /// Content streams of the finished pages
+    pages: Vec<Vec<u8>>,
+    /// Content stream of the current page
+    content: Option<Content>,
+    /// Alpha of the fill color of the current page
+    fill_alpha: u8,
+    fonts: BTreeMap<FontIndex, EmbeddedFont>,
+    /// Alpha values that need a graphics state
+    alphas: BTreeSet<u8>,
+    /// Bitmaps of the images drawn, each embedded once
+    images: Vec<ImageBitmap>,
+    /// Clip of the current page, applied in a nested graphics state
+    clip: Option<Rect>,
+    /// Alpha of the fill color outside of the clip's graphics state
+    unclipped_alpha: u8,
+}
+
+/// A font and the glyphs of it used in the document
+struct EmbeddedFont {
+    font: FontArc,
+    /// Unicode text of each glyph, for text extraction
+    glyphs: BTreeMap<u16, char>,
+}
+
+impl PdfGraphics {
+    /// Create a document with pages of `page_size` CSS pixels
+    pub fn new(page_size: Size, margins: EdgeSizes) -> Self {
+        Self {
+            page_size,
+            margins,
+            pages: Vec::new(),
+            content: None,
+            fill_alpha: u8::MAX,
+            fonts: BTreeMap::new(),
+            alphas: BTreeSet::new(),
+            images: Vec::new(),
+            clip: None,
+            unclipped_alpha: u8::MAX,
+        }
+    }
+
+    /// Size of the area of a page that is painted, in CSS pixels
+    pub fn content_size(&self) -> Size {
+        Size::new(
+            self.page_size.width - self.margins.left - self.margins.right,
+            self.page_size.height - self.margins.top - self.margins.bottom,
+        )
+    }
+
+    /// Finish the current page and start painting the next one. Drawing is
+    /// clipped to the first `height` CSS pixels of the content area.
+    pub fn begin_page(&mut self, height: f32) {
+        self.finish_page();
+        self.start_page(height);
+    }
+
+    fn start_page(&mut self, height: f32) -> &mut Content {
+        let width = self.content_size().width;
+        self.fill_alpha = u8::MAX;
+        let mut content = Content::new();
+        content.save_state();
+        // Flip the y axis so that content is drawn top down like on screen
+        content.transform([
+            POINTS_PER_PX,
+            0.,
+            0.,
+            -POINTS_PER_PX,
+            self.margins.left * POINTS_PER_PX,
+            (self.page_size.height - self.margins.top) * POINTS_PER_PX,
+        ]);
+        content.rect(0., 0., width, height);
+        content.clip_nonzero();
+        content.end_path();
+        self.content.insert(content)
+    }
+
+    fn finish_page(&mut self) {
+        self.remove_clip();
+        if let Some(mut content) = self.content.take() {
+            content.restore_state();
+            self.pages.push(content.finish());
+        }
+    }
+
+    fn content(&mut self) -> &mut Content {
+        match self.content {
+            Some(ref mut content) => content,
+            None => self.start_page(self.content_size().height),
+        }
+    }
+
+    /// Leave the graphics state of the clip, which restores the fill alpha
+    /// from before it
+    fn remove_clip(&mut self) {
+        if self.clip.take().is_none() {
+            return;
+        }
+        if let Some(content) = &mut self.content {
+            content.restore_state();
+        }
+        self.fill_alpha = self.unclipped_alpha;
+    }
+
+    /// Draw clipped to the path that `clip` adds and intersects with the
+    /// clip, in a nested graphics state
+    fn with_clip<C: FnOnce(&mut Content), F: FnOnce(&mut Self)>(&mut self, clip: C, draw: F) {
+        let alpha = self.fill_alpha;
+        let content = self.content();
+        content.save_state();
+        clip(content);
+        content.end_path();
+        draw(self);
+        self.content().restore_state();
+        self.fill_alpha = alpha;
+    }
+
+    fn set_fill_color(&mut self, color: &Color) {
+        self.content().set_fill_rgb(
+            color.r as f32 / 255.,
+            color.g as f32 / 255.,
+            color.b as f32 / 255.,
+        );
+        self.set_fill_alpha(color.a);
+    }
+
+    fn set_fill_alpha(&mut self, alpha: u8) {
+        if alpha != self.fill_alpha {
+            self.alphas.insert(alpha);
+            self.content()
+                .set_parameters(Name(alpha_name(alpha).as_bytes()));
+            self.fill_alpha = alpha;
+        }
+    }
+
+    /// Show the text with the text matrix for the ascent of its font
+    fn show_text<F: FnOnce(f32) -> [f32; 6]>(
+        &mut self,
+        text: &str,
+        color: &Color,
+        size: f32,
+        font: FontIndex,
+        text_matrix: F,
+    ) {
+        if color.a == 0 || text.is_empty() {
+            return;
+        }
+        let embedded_font = self.embedded_font(font);
+        let ascent = embedded_font.font.as_scaled(size).ascent();
+        let glyphs = embedded_font.encode(text);
+
+        self.set_fill_color(color);
+        let content = self.content();
+        content.begin_text();
+        content.set_font(Name(font_name(font).as_bytes()), size);
+        content.set_text_matrix(text_matrix(ascent));
+        content.show(Str(&glyphs));
+        content.end_text();
+    }
+
+    fn fill_path<F: FnOnce(&mut Content)>(&mut self, color: &Color, even_odd: bool, path: F) {
+        if color.a == 0 {
+            return;
+        }
+        self.set_fill_color(color);
+        let content = self.content();
+        path(content);
+        if even_odd {
+            content.fill_even_odd();
+        } else {
+            content.fill_nonzero();
+        }
+    }
+
+    fn embedded_font(&mut self, font: FontIndex) -> &mut EmbeddedFont {
+        self.fonts.entry(font).or_insert_with(|| EmbeddedFont {
+            font: FontArc::try_from_vec(font_database::font_data(font).to_vec())
+                .expect("Registered fonts are valid"),
+            glyphs: BTreeMap::new(),
+        })
+    }
+
+    /// Write the document with the pages painted so far
+    fn write_document(&mut self) -> Vec<u8> {
+        let mut next_id = 1;
+        let mut new_id = || {
+            let id = Ref::new(next_id);
+            next_id += 1;
+            id
+        };
+
+        let mut pdf = Pdf::new();
+        let catalog_id = new_id();
+        let page_tree_id = new_id();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+
+        // Resources are shared by every page
+        let font_ids = self
+            .fonts
+            .keys()
+            .map(|font| (*font, new_id()))
+            .collect::<BTreeMap<FontIndex, Ref>>();
+        let alpha_ids = self
+            .alphas
+            .iter()
+            .map(|alpha| (*alpha, new_id()))
+            .collect::<BTreeMap<u8, Ref>>();
+        // Ids of each image and of its soft mask
+        let image_ids = self
+            .images
+            .iter()
+            .map(|_| (new_id(), new_id()))
+            .collect::<Vec<_>>();
+
+        let media_box = PdfRect::new(
+            0.,
+            0.,
+            self.page_size.width * POINTS_PER_PX,
+            self.page_size.height * POINTS_PER_PX,
+        );
+        let mut page_ids = Vec::new();
+        for content in &self.pages {
+            let page_id = new_id();
+            let content_id = new_id();
+            page_ids.push(page_id);
+
+            let mut page = pdf.page(page_id);
+            page.media_box(media_box)
+                .parent(page_tree_id)
+                .contents(content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            for (font, id) in &font_ids {
+                fonts.pair(Name(font_name(*font).as_bytes()), *id);
+            }
+            fonts.finish();
+            let mut states = resources.ext_g_states();
+            for (alpha, id) in &alpha_ids {
+                states.pair(Name(alpha_name(*alpha).as_bytes()), *id);
+            }
+            states.finish();
+            let mut x_objects = resources.x_objects();
+            for (index, (id, _)) in image_ids.iter().enumerate() {
+                x_objects.pair(Name(image_name(index).as_bytes()), *id);
+            }
+            x_objects.finish();
+            resources.finish();
+            page.finish();
+
+            pdf.stream(content_id, content);
+        }
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+
+        for (alpha, id) in &alpha_ids {
+            pdf.ext_graphics(*id)
+                .non_stroking_alpha(*alpha as f32 / 255.);
+        }
+        for (font, id) in &font_ids {
+            let data = font_database::font_data(*font);
+            self.fonts[font].write(&mut pdf, *id, &data, &mut new_id);
+        }
+        for (bitmap, (id, mask_id)) in self.images.iter().zip(&image_ids) {
+            write_image(&mut pdf, bitmap, *id, *mask_id);
+        }
+
+        pdf.finish()
+    }
+}
+
+impl EmbeddedFont {
+    /// Glyph ids of the text as the big-endian bytes expected by the
+    /// `Identity-H` encoding
+    fn encode(&mut self, text: &str) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for c in text.chars() {
+            let glyph = self.font.glyph_id(c).0;
+            self.glyphs.entry(glyph).or_insert(c);
+            bytes.extend_from_slice(&glyph.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Units of the font in a thousandth of the font size, the unit of
+    /// glyph metrics in PDF
+    fn to_pdf_units(&self, value: f32) -> f32 {
+        value * 1000. / self.font.units_per_em().unwrap_or(1000.)
+    }
+
+    fn write(&self, pdf: &mut Pdf, id: Ref, data: &[u8], new_id: &mut dyn FnMut() -> Ref) {
+        let cid_font_id = new_id();
+        let descriptor_id = new_id();
+        let file_id = new_id();
+        let to_unicode_id = new_id();
+        let base_font = Name(b"EmbeddedFont");
+        let system_info = SystemInfo {
+            registry: Str(b"Adobe"),
+            ordering: Str(b"Identity"),
+            supplement: 0,
+        };
+
+        pdf.type0_font(id)
+            .base_font(base_font)
+            .encoding_predefined(Name(b"Identity-H"))
+            .descendant_font(cid_font_id)
+            .to_unicode(to_unicode_id);
+
+        let mut cid_font = pdf.cid_font(cid_font_id);
+        cid_font
+            .subtype(CidFontType::Type2)
+            .base_font(base_font)
+            .system_info(system_info)
+            .font_descriptor(descriptor_id)
+            .cid_to_gid_map_predefined(Name(b"Identity"));
+        let mut widths = cid_font.widths();
+        for glyph in self.glyphs.keys() {
+            let advance = self
+                .font
+                .h_advance_unscaled(glyph_brush::ab_glyph::GlyphId(*glyph));
+            widths.consecutive(*glyph, [self.to_pdf_units(advance)]);
+        }
+        widths.finish();
+        cid_font.finish();
+
+        let ascent = self.to_pdf_units(self.font.ascent_unscaled());
+        let descent = self.to_pdf_units(self.font.descent_unscaled());
+        pdf.font_descriptor(descriptor_id)
+            .name(base_font)
+            .flags(FontFlags::NON_SYMBOLIC)
+            .bbox(PdfRect::new(0., descent, 1000., ascent))
+            .italic_angle(0.)
+            .ascent(ascent)
+            .descent(descent)
+            .cap_height(ascent)
+            .stem_v(80.)
+            .font_file2(file_id);
+        pdf.stream(file_id, data)
+            .pair(Name(b"Length1"), data.len() as i32);
+
+        let mut cmap = pdf_writer::types::UnicodeCmap::new(Name(b"Custom"), system_info);
+        for (glyph, c) in &self.glyphs {
+            cmap.pair(*glyph, *c);
+        }
+        pdf.cmap(to_unicode_id, &cmap.finish());
+    }
+}
+
+#[async_trait(?Send)]
+impl Graphics for PdfGraphics {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.fill_path(&color, false, |content| {
+            content.rect(rect.x, rect.y, rect.width, rect.height);
+        });
+    }
+
+    fn fill_rrect(&mut self, rect: RRect, color: Color) {
+        self.fill_path(&color, false, |content| rrect_path(content, &rect));
+    }
+
+    fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        // Flip the glyphs back up, the text is laid out from its top edge
+        self.show_text(&content, &color, size, font, |ascent| {
+            [1., 0., 0., -1., bounds.x, bounds.y + ascent]
+        });
+    }
+
+    fn fill_vertical_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        // The text advances down the page, with the tops of the glyphs
+        // against the right edge
+        self.show_text(&content, &color, size, font, |ascent| {
+            [0., 1., 1., 0., bounds.x + bounds.width - ascent, bounds.y]
+        });
+    }
+
+    fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        self.fill_path(&color, false, |content| {
+            content.move_to(points[0].x, points[0].y);
+            for point in &points[1..] {
+                content.line_to(point.x, point.y);
+            }
+            content.close_path();
+        });
+    }
+
+    /// PDF has no blur, it is approximated by layers that grow from the
+    /// solid part of the shadow to its blurred edge
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect) {
+        let outer = inset_rect(&rect, -blur);
+        // Everything around the border box of the caster
+        let clip = |content: &mut Content| {
+            let bounds = union_rect(&outer, caster);
+            content.rect(bounds.x, bounds.y, bounds.width, bounds.height);
+            rrect_path(content, caster);
+            content.clip_even_odd();
+        };
+        self.with_clip(clip, |graphics| {
+            if blur <= 0. {
+                return graphics.fill_rect(rect, color);
+            }
+            let color = layer_color(&color);
+            for layer in 0..SHADOW_BLUR_LAYERS {
+                let layer_rect = inset_rect(&rect, blur - 2. * blur * layer_fraction(layer));
+                graphics.fill_rect(layer_rect, color.clone());
+            }
+        });
+    }
+
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color) {
+        let (layers, color) = match blur > 0. {
+            true => (SHADOW_BLUR_LAYERS, layer_color(&color)),
+            false => (1, color),
+        };
+        let outer = union_rect(bounds, &inset_rect(&hole, -blur));
+        let clip = |content: &mut Content| {
+            rrect_path(content, bounds);
+            content.clip_nonzero();
+        };
+        self.with_clip(clip, |graphics| {
+            for layer in 0..layers {
+                let hole = match blur > 0. {
+                    true => inset_rect(&hole, 2. * blur * layer_fraction(layer) - blur),
+                    false => hole.clone(),
+                };
+                graphics.fill_path(&color, true, |content| {
+                    content.rect(outer.x, outer.y, outer.width, outer.height);
+                    content.rect(hole.x, hole.y, hole.width, hole.height);
+                });
+            }
+        });
+    }
+
+    fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap) {
+        let index = match self.images.iter().position(|image| image.ptr_eq(&bitmap)) {
+            Some(index) => index,
+            None => {
+                self.images.push(bitmap);
+                self.images.len() - 1
+            }
+        };
+        // Images are painted with the alpha of the fill color
+        self.set_fill_alpha(u8::MAX);
+        let content = self.content();
+        content.save_state();
+        // An image fills the unit square with its first row at the top, and
+        // the y axis of the page points down
+        content.transform([
+            bounds.width,
+            0.,
+            0.,
+            -bounds.height,
+            bounds.x,
+            bounds.y + bounds.height,
+        ]);
+        content.x_object(Name(image_name(index).as_bytes()));
+        content.restore_state();
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        if self.clip == clip {
+            return;
+        }
+        self.remove_clip();
+        if let Some(rect) = &clip {
+            self.unclipped_alpha = self.fill_alpha;
+            let content = self.content();
+            content.save_state();
+            content.rect(rect.x, rect.y, rect.width, rect.height);
+            content.clip_nonzero();
+            content.end_path();
+        }
+        self.clip = clip;
+    }
+
+    fn resize(&mut self, _: Size) {}
+
+    fn set_scale(&mut self, _: f32) {}
+
+    /// Finish the document. The graphics start over with an empty document.
+    async fn output(&mut self) -> Vec<u8> {
+        self.finish_page();
+        let document = self.write_document();
+        self.pages.clear();
+        self.fonts.clear();
+        self.alphas.clear();
+        self.images.clear();
+        document
+    }
+}
+
+fn font_name(font: FontIndex) -> String {
+    format!("F{}", font)
+}
+
+fn alpha_name(alpha: u8) -> String {
+    format!("A{}", alpha)
+}
+
+fn image_name(index: usize) -> String {
+    format!("Im{}", index)
+}
+
+/// Write the bitmap as an RGB image with its alpha channel as soft mask
+fn write_image(pdf: &mut Pdf, bitmap: &ImageBitmap, id: Ref, mask_id: Ref) {
+    let pixels = bitmap.data().chunks_exact(4);
+    let colors = pixels
+        .clone()
+        .flat_map(|pixel| pixel[..3].iter().copied())
+        .collect::<Vec<_>>();
+    let alphas = pixels.map(|pixel| pixel[3]).collect::<Vec<_>>();
+
+    let mut image = pdf.image_xobject(id, &colors);
+    image
+        .width(bitmap.width() as i32)
+        .height(bitmap.height() as i32)
+        .bits_per_component(8)
+        .s_mask(mask_id);
+    image.color_space().device_rgb();
+    image.finish();
+
+    let mut mask = pdf.image_xobject(mask_id, &alphas);
+    mask.width(bitmap.width() as i32)
+        .height(bitmap.height() as i32)
+        .bits_per_component(8);
+    mask.color_space().device_gray();
+    mask.finish();
+}
+
+/// Position of a blur layer between the inner and the outer edge of a blur
+fn layer_fraction(layer: usize) -> f32 {
+    (layer as f32 + 0.5) / SHADOW_BLUR_LAYERS as f32
+}
+
+/// Color of a blur layer, so that the stacked layers are about as opaque as
+/// the shadow
+fn layer_color(color: &Color) -> Color {
+    let alpha = color.a as f32 / 255.;
+    let layer_alpha = 1. - (1. - alpha).powf(1. / SHADOW_BLUR_LAYERS as f32);
+    Color {
+        a: (layer_alpha * 255.).round() as u8,
+        ..color.clone()
+    }
+}
+
+fn rrect_path(content: &mut Content, rect: &RRect) {
+    let corners = &rect.corners;
+    let (left, top) = (rect.x, rect.y);
+    let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+    let top_left = &corners.top_left;
+    let top_right = &corners.top_right;
+    let bottom_right = &corners.bottom_right;
+    let bottom_left = &corners.bottom_left;
+
+    content.move_to(left + top_left.horizontal_r(), top);
+    content.line_to(right - top_right.horizontal_r(), top);
+    content.cubic_to(
+        right - top_right.horizontal_r() * (1. - KAPPA),
+        top,
+        right,
+        top + top_right.vertical_r() * (1. - KAPPA),
+        right,
+        top + top_right.vertical_r(),
+    );
+    content.line_to(right, bottom - bottom_right.vertical_r());
+    content.cubic_to(
+        right,
+        bottom - bottom_right.vertical_r() * (1. - KAPPA),
+        right - bottom_right.horizontal_r() * (1. - KAPPA),
+        bottom,
+        right - bottom_right.horizontal_r(),
+        bottom,
+    );
+    content.line_to(left + bottom_left.horizontal_r(), bottom);
+    content.cubic_to(
+        left + bottom_left.horizontal_r() * (1. - KAPPA),
+        bottom,
+        left,
+        bottom - bottom_left.vertical_r() * (1. - KAPPA),
+        left,
+        bottom - bottom_left.vertical_r(),
+    );
+    content.line_to(left, top + top_left.vertical_r());
+    content.cubic_to(
+        left,
+        top + top_left.vertical_r() * (1. - KAPPA),
+        left + top_left.horizontal_r() * (1. - KAPPA),
+        top,
+        left + top_left.horizontal_r(),
+        top,
+    );
+    content.close_path();
+}
+
+/// Move the edges of the rect inward by `distance`, or outward when it is
+/// negative. The rect shrinks at most to its center.
+fn inset_rect(rect: &Rect, distance: f32) -> Rect {
+    let dx = f32::min(distance, rect.width / 2.);
+    let dy = f32::min(distance, rect.height / 2.);
+    Rect::new(
+        rect.x + dx,
+        rect.y + dy,
+        rect.width - 2. * dx,
+        rect.height - 2. * dy,
+    )
+}
+
+/// The smallest rect that contains both rects
+fn union_rect(a: &Rect, b: &Rect) -> Rect {
+    let left = f32::min(a.x, b.x);
+    let top = f32::min(a.y, b.y);
+    let right = f32::max(a.x + a.width, b.x + b.width);
+    let bottom = f32::max(a.y + a.height, b.y + b.height);
+    Rect::new(left, top, right - left, bottom - top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(haystack: &[u8], needle: &str) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle.as_bytes())
+            .count()
+    }
+
+    #[test]
+    fn test_pdf_pages() {
+        let mut graphics = PdfGraphics::new(Size::new(800., 600.), EdgeSizes::uniform(50.));
+        assert_eq!(graphics.content_size(), Size::new(700., 500.));
+        graphics.begin_page(500.);
+
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let translucent = Color {
+            a: 128,
+            ..red.clone()
+        };
+        graphics.fill_rect(Rect::new(0., 0., 100., 100.), red.clone());
+        graphics.fill_text(
+            "Hello".to_string(),
+            Rect::new(0., 0., 100., 20.),
+            red,
+            16.,
+            crate::DEFAULT_FONT,
+        );
+        graphics.begin_page(300.);
+        let radii = || Radii::new(4., 4.);
+        let caster = RRect {
+            rect: Rect::new(5., 5., 50., 50.),
+            corners: Corners::new(radii(), radii(), radii(), radii()),
+        };
+        graphics.fill_shadow(Rect::new(10., 10., 50., 50.), 4., translucent, &caster);
+
+        let document = futures::executor::block_on(graphics.output());
+        assert!(document.starts_with(b"%PDF-"));
+        assert_eq!(count(&document, "/Type /Page\n"), 2);
+        assert_eq!(count(&document, "/Count 2"), 1);
+        assert_eq!(count(&document, "/FontFile2"), 1);
+        assert_eq!(count(&document, "/Identity-H"), 1);
+        // The blur layers share a graphics state
+        assert_eq!(count(&document, "/Type /ExtGState"), 1);
+        // The shadow is clipped to the outside of the caster
+        assert_eq!(count(&document, "W*\nn"), 1);
+    }
+
+    #[test]
+    fn test_images_are_embedded_once() {
+        let mut graphics = PdfGraphics::new(Size::new(800., 600.), EdgeSizes::uniform(50.));
+        let bitmap = ImageBitmap::from_rgba(2, 1, vec![255, 0, 0, 255, 0, 0, 255, 128]).unwrap();
+        graphics.draw_image(Rect::new(0., 0., 20., 10.), bitmap.clone());
+        graphics.draw_image(Rect::new(0., 50., 40., 20.), bitmap);
+
+        let document = futures::executor::block_on(graphics.output());
+        // The image and its soft mask
+        assert_eq!(count(&document, "/Subtype /Image"), 2);
+        assert_eq!(count(&document, "/SMask"), 1);
+        assert_eq!(count(&document, "/Im0 Do"), 2);
+    }
+}
@@ -0,0 +1,474 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use glyph_brush::ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use shared::color::{linear_to_srgb, Color};
+use shared::image::ImageBitmap;
+use shared::primitive::*;
+
+use crate::font_database::{self, FontIndex};
+use crate::Graphics;
+
+/// Graphics that rasterizes drawing commands on the CPU into an RGBA
+/// bitmap, to render without a GPU such as when running conformance tests.
+///
+/// Shapes are sampled once at the center of each pixel, without
+/// anti-aliasing, and glyphs are covered by their outlines. Colors are
+/// blended in linear light like `Canvas` does. Blurs are approximated by
+/// linear ramps across the blurred edges.
+pub struct RasterGraphics {
+    /// Size of the bitmap in CSS pixels
+    size: Size,
+    scale: f32,
+    /// Size of the bitmap in device pixels
+    width: u32,
+    height: u32,
+    /// Premultiplied colors in linear light, row by row
+    pixels: Vec<[f32; 4]>,
+    background: Color,
+    clip: Option<Rect>,
+    fonts: HashMap<FontIndex, FontArc>,
+}
+
+impl RasterGraphics {
+    pub fn new() -> Self {
+        Self {
+            size: Size::new(0., 0.),
+            scale: 1.,
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            background: Color::new(255, 255, 255, 255),
+            clip: None,
+            fonts: HashMap::new(),
+        }
+    }
+
+    /// Set the color the bitmap is cleared to
+    pub fn set_background(&mut self, color: Color) {
+        self.background = color;
+        self.clear();
+    }
+
+    /// Size of the bitmap in device pixels
+    pub fn bitmap_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn clear(&mut self) {
+        let [r, g, b, a] = self.background.to_linear_rgba();
+        self.pixels = vec![[r * a, g * a, b * a, a]; (self.width * self.height) as usize];
+    }
+
+    fn allocate(&mut self) {
+        self.width = (self.size.width * self.scale).round().max(0.) as u32;
+        self.height = (self.size.height * self.scale).round().max(0.) as u32;
+        self.clear();
+    }
+
+    fn font(&mut self, font: FontIndex) -> FontArc {
+        self.fonts
+            .entry(font)
+            .or_insert_with(|| {
+                FontArc::try_from_vec(font_database::font_data(font).to_vec())
+                    .expect("Registered fonts are valid")
+            })
+            .clone()
+    }
+
+    /// Blend the color over the device pixel, with the part of the pixel
+    /// that is covered
+    fn blend(&mut self, x: i32, y: i32, color: &[f32; 4], coverage: f32) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return;
+        }
+        if let Some(clip) = &self.clip {
+            let center = self.to_css(x, y);
+            if !contains(clip, &center) {
+                return;
+            }
+        }
+        let alpha = color[3] * coverage.clamp(0., 1.);
+        let pixel = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        for channel in 0..3 {
+            pixel[channel] = color[channel] * alpha + pixel[channel] * (1. - alpha);
+        }
+        pixel[3] = alpha + pixel[3] * (1. - alpha);
+    }
+
+    /// Center of the device pixel, in CSS pixels
+    fn to_css(&self, x: i32, y: i32) -> Point {
+        Point::new((x as f32 + 0.5) / self.scale, (y as f32 + 0.5) / self.scale)
+    }
+
+    /// Fill the pixels within `bounds`, each with the part of it that
+    /// `coverage` returns for its center
+    fn fill_with<F: Fn(&Point) -> f32>(&mut self, bounds: &Rect, color: &Color, coverage: F) {
+        if color.a == 0 {
+            return;
+        }
+        let linear_color = color.to_linear_rgba();
+        let left = (bounds.x * self.scale).floor() as i32;
+        let top = (bounds.y * self.scale).floor() as i32;
+        let right = ((bounds.x + bounds.width) * self.scale).ceil() as i32;
+        let bottom = ((bounds.y + bounds.height) * self.scale).ceil() as i32;
+
+        for y in top.max(0)..bottom.min(self.height as i32) {
+            for x in left.max(0)..right.min(self.width as i32) {
+                let pixel_coverage = coverage(&self.to_css(x, y));
+                if pixel_coverage > 0. {
+                    self.blend(x, y, &linear_color, pixel_coverage);
+                }
+            }
+        }
+    }
+
+    /// Draw the glyphs of the text laid out on a line from the origin,
+    /// with `place` mapping the position of each covered pixel on the line
+    /// to a device pixel
+    fn draw_glyphs<P: Fn(i32, i32) -> (i32, i32)>(
+        &mut self,
+        content: &str,
+        color: &Color,
+        size: f32,
+        font: FontIndex,
+        place: P,
+    ) {
+        if color.a == 0 {
+            return;
+        }
+        let linear_color = color.to_linear_rgba();
+        let font = self.font(font);
+        let scaled_font = font.as_scaled(PxScale::from(size * self.scale));
+        let ascent = scaled_font.ascent();
+
+        let mut caret = 0.;
+        let mut previous = None;
+        for c in content.chars() {
+            let glyph_id = scaled_font.glyph_id(c);
+            if let Some(previous) = previous {
+                caret += scaled_font.kern(previous, glyph_id);
+            }
+            previous = Some(glyph_id);
+            let glyph = glyph_id.with_scale_and_position(scaled_font.scale(), point(caret, ascent));
+            caret += scaled_font.h_advance(glyph_id);
+
+            let outline = match scaled_font.outline_glyph(glyph) {
+                Some(outline) => outline,
+                None => continue,
+            };
+            let bounds = outline.px_bounds();
+            let mut coverages = Vec::new();
+            outline.draw(|x, y, coverage| {
+                let line_x = bounds.min.x as i32 + x as i32;
+                let line_y = bounds.min.y as i32 + y as i32;
+                coverages.push((place(line_x, line_y), coverage));
+            });
+            for ((x, y), coverage) in coverages {
+                self.blend(x, y, &linear_color, coverage);
+            }
+        }
+    }
+}
+
+impl Default for RasterGraphics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Graphics for RasterGraphics {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.fill_with(&rect, &color, |center| contains(&rect, center) as u8 as f32);
+    }
+
+    fn fill_rrect(&mut self, rect: RRect, color: Color) {
+        self.fill_with(&rect.rect, &color, |center| {
+            rrect_contains(&rect, center) as u8 as f32
+        });
+    }
+
+    fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        let origin_x = (bounds.x * self.scale).round() as i32;
+        let origin_y = (bounds.y * self.scale).round() as i32;
+        self.draw_glyphs(&content, &color, size, font, |x, y| {
+            (origin_x + x, origin_y + y)
+        });
+    }
+
+    fn fill_vertical_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        // The line runs down from the top right corner, with the tops of
+        // the glyphs against the right edge
+        let right = ((bounds.x + bounds.width) * self.scale).round() as i32;
+        let top = (bounds.y * self.scale).round() as i32;
+        self.draw_glyphs(&content, &color, size, font, |x, y| {
+            (right - 1 - y, top + x)
+        });
+    }
+
+    fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let left = points.iter().map(|point| point.x).fold(f32::MAX, f32::min);
+        let top = points.iter().map(|point| point.y).fold(f32::MAX, f32::min);
+        let right = points.iter().map(|point| point.x).fold(f32::MIN, f32::max);
+        let bottom = points.iter().map(|point| point.y).fold(f32::MIN, f32::max);
+        let bounds = Rect::new(left, top, right - left, bottom - top);
+        self.fill_with(&bounds, &color, |center| {
+            polygon_contains(&points, center) as u8 as f32
+        });
+    }
+
+    fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap) {
+        if bounds.width <= 0. || bounds.height <= 0. {
+            return;
+        }
+        let left = (bounds.x * self.scale).floor() as i32;
+        let top = (bounds.y * self.scale).floor() as i32;
+        let right = ((bounds.x + bounds.width) * self.scale).ceil() as i32;
+        let bottom = ((bounds.y + bounds.height) * self.scale).ceil() as i32;
+
+        // Each pixel takes the color of the nearest pixel of the image
+        for y in top.max(0)..bottom.min(self.height as i32) {
+            for x in left.max(0)..right.min(self.width as i32) {
+                let center = self.to_css(x, y);
+                if !contains(&bounds, &center) {
+                    continue;
+                }
+                let image_x = ((center.x - bounds.x) / bounds.width * bitmap.width() as f32) as u32
+                    % bitmap.width();
+                let image_y = ((center.y - bounds.y) / bounds.height * bitmap.height() as f32)
+                    as u32
+                    % bitmap.height();
+                let index = ((image_y * bitmap.width() + image_x) * 4) as usize;
+                let rgba = &bitmap.data()[index..index + 4];
+                let color = Color::new(rgba[0], rgba[1], rgba[2], rgba[3]).to_linear_rgba();
+                self.blend(x, y, &color, 1.);
+            }
+        }
+    }
+
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect) {
+        let bounds = inflate(&rect, blur);
+        self.fill_with(&bounds, &color, |center| {
+            match rrect_contains(caster, center) {
+                true => 0.,
+                false => blurred_coverage(&rect, blur, center),
+            }
+        });
+    }
+
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color) {
+        self.fill_with(&bounds.rect, &color, |center| {
+            match rrect_contains(bounds, center) {
+                true => 1. - blurred_coverage(&hole, blur, center),
+                false => 0.,
+            }
+        });
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    fn resize(&mut self, size: Size) {
+        self.size = size;
+        self.allocate();
+    }
+
+    fn set_scale(&mut self, scale: f32) {
+        if scale != self.scale {
+            self.scale = scale;
+            self.allocate();
+        }
+    }
+
+    /// The pixels in RGBA8 without premultiplied alpha, after which the
+    /// bitmap is cleared for the next frame
+    async fn output(&mut self) -> Vec<u8> {
+        let mut bitmap = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            let alpha = pixel[3];
+            for channel in &pixel[..3] {
+                let value = match alpha > 0. {
+                    true => linear_to_srgb(channel / alpha),
+                    false => 0.,
+                };
+                bitmap.push((value * 255.).round() as u8);
+            }
+            bitmap.push((alpha * 255.).round() as u8);
+        }
+        self.clip = None;
+        self.clear();
+        bitmap
+    }
+}
+
+fn contains(rect: &Rect, point: &Point) -> bool {
+    point.x >= rect.x
+        && point.x < rect.x + rect.width
+        && point.y >= rect.y
+        && point.y < rect.y + rect.height
+}
+
+/// Whether the point is inside the rect and the ellipses of its corners
+fn rrect_contains(rrect: &RRect, point: &Point) -> bool {
+    if !contains(&rrect.rect, point) {
+        return false;
+    }
+    let corners = &rrect.corners;
+    let right = rrect.x + rrect.width;
+    let bottom = rrect.y + rrect.height;
+    let corners = [
+        (&corners.top_left, rrect.x, rrect.y, 1., 1.),
+        (&corners.top_right, right, rrect.y, -1., 1.),
+        (&corners.bottom_left, rrect.x, bottom, 1., -1.),
+        (&corners.bottom_right, right, bottom, -1., -1.),
+    ];
+    corners
+        .iter()
+        .all(|(radii, corner_x, corner_y, direction_x, direction_y)| {
+            let (radius_x, radius_y) = (radii.horizontal_r(), radii.vertical_r());
+            if radius_x <= 0. || radius_y <= 0. {
+                return true;
+            }
+            // Distance into the rect from the corner, along each axis
+            let dx = (point.x - corner_x) * direction_x;
+            let dy = (point.y - corner_y) * direction_y;
+            if dx >= radius_x || dy >= radius_y {
+                return true;
+            }
+            let x = (radius_x - dx) / radius_x;
+            let y = (radius_y - dy) / radius_y;
+            x * x + y * y <= 1.
+        })
+}
+
+/// Whether the point is inside the polygon, by the even-odd rule
+fn polygon_contains(points: &[Point], point: &Point) -> bool {
+    let mut is_inside = false;
+    let mut previous = &points[points.len() - 1];
+    for current in points {
+        let crosses = (current.y > point.y) != (previous.y > point.y);
+        if crosses {
+            let x = current.x
+                + (point.y - current.y) / (previous.y - current.y) * (previous.x - current.x);
+            if point.x < x {
+                is_inside = !is_inside;
+            }
+        }
+        previous = current;
+    }
+    is_inside
+}
+
+fn inflate(rect: &Rect, distance: f32) -> Rect {
+    Rect::new(
+        rect.x - distance,
+        rect.y - distance,
+        rect.width + 2. * distance,
+        rect.height + 2. * distance,
+    )
+}
+
+/// Coverage of the rect with its edges blurred, which fades linearly from
+/// `blur` inside of each edge to `blur` outside of it
+fn blurred_coverage(rect: &Rect, blur: f32, point: &Point) -> f32 {
+    if blur <= 0. {
+        return contains(rect, point) as u8 as f32;
+    }
+    let ramp = |distance: f32| ((distance + blur) / (2. * blur)).clamp(0., 1.);
+    ramp(point.x - rect.x)
+        * ramp(rect.x + rect.width - point.x)
+        * ramp(point.y - rect.y)
+        * ramp(rect.y + rect.height - point.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use shared::primitive::{Corners, Radii};
+
+    use super::*;
+
+    fn pixel(bitmap: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * width + x) * 4) as usize;
+        [
+            bitmap[index],
+            bitmap[index + 1],
+            bitmap[index + 2],
+            bitmap[index + 3],
+        ]
+    }
+
+    #[test]
+    fn test_fill_shapes() {
+        let mut graphics = RasterGraphics::new();
+        graphics.resize(Size::new(20., 10.));
+        graphics.set_scale(2.);
+        assert_eq!(graphics.bitmap_size(), (40, 20));
+
+        let red = Color::new(255, 0, 0, 255);
+        graphics.set_clip(Some(Rect::new(0., 0., 15., 10.)));
+        graphics.fill_rect(Rect::new(5., 0., 15., 5.), red.clone());
+        graphics.set_clip(None);
+        let radii = || Radii::new(5., 5.);
+        graphics.fill_rrect(
+            RRect::new(
+                0.,
+                0.,
+                10.,
+                10.,
+                Corners::new(radii(), radii(), radii(), radii()),
+            ),
+            Color::new(0, 0, 255, 128),
+        );
+        let bitmap = block_on(graphics.output());
+
+        assert_eq!(pixel(&bitmap, 40, 24, 2), [255, 0, 0, 255]);
+        // Outside of the clip
+        assert_eq!(pixel(&bitmap, 40, 32, 2), [255, 255, 255, 255]);
+        // Outside of the rounded corner
+        assert_eq!(pixel(&bitmap, 40, 0, 19), [255, 255, 255, 255]);
+        // Half transparent blue over white, blended in linear light
+        assert_eq!(pixel(&bitmap, 40, 10, 15), [187, 187, 255, 255]);
+
+        // The bitmap is cleared after its output
+        let bitmap = block_on(graphics.output());
+        assert_eq!(pixel(&bitmap, 40, 24, 2), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_fill_text() {
+        let mut graphics = RasterGraphics::new();
+        graphics.resize(Size::new(40., 20.));
+        graphics.fill_text(
+            "Hi".to_string(),
+            Rect::new(0., 0., 40., 20.),
+            Color::new(0, 0, 0, 255),
+            16.,
+            crate::DEFAULT_FONT,
+        );
+        let bitmap = block_on(graphics.output());
+        let inked = bitmap.chunks(4).filter(|pixel| pixel[0] < 128).count();
+        assert!(inked > 10);
+        // Nothing is drawn past the end of the text
+        assert!((0..20).all(|y| pixel(&bitmap, 40, 35, y) == [255, 255, 255, 255]));
+    }
+}
@@ -1,44 +1,241 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use lyon_tessellation::{path::Path, BuffersBuilder, FillOptions, FillTessellator, VertexBuffers};
+use shared::primitive::{RRect, Rect};
+use ultraviolet as uv;
+
+use crate::triangle::{Index, Instance, Vertex, VertexConstructor};
+
+/// Identifies the shape of a rounded rect regardless of its position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RRectKey([u32; 10]);
 
-use crate::triangle::{Index, Vertex, VertexConstructor};
+impl RRectKey {
+    pub fn new(rrect: &RRect) -> Self {
+        let corners = &rrect.corners;
+        Self(
+            [
+                rrect.width,
+                rrect.height,
+                corners.top_left.horizontal_r(),
+                corners.top_left.vertical_r(),
+                corners.top_right.horizontal_r(),
+                corners.top_right.vertical_r(),
+                corners.bottom_left.horizontal_r(),
+                corners.bottom_left.vertical_r(),
+                corners.bottom_right.horizontal_r(),
+                corners.bottom_right.vertical_r(),
+            ]
+            .map(f32::to_bits),
+        )
+    }
+}
+
+/// Triangles of a shape, moved and colored by `instance` and drawn clipped
+/// to `clip` in CSS pixels. The triangles are shared with the other meshes
+/// drawn from the same cached geometry.
+pub struct Mesh {
+    pub buffers: Arc<VertexBuffers<Vertex, Index>>,
+    pub instance: Instance,
+    pub clip: Option<Rect>,
+}
+
+struct CachedGeometry {
+    buffers: Arc<VertexBuffers<Vertex, Index>>,
+    is_used: bool,
+}
 
 pub struct Tessellator {
     fill_tess: FillTessellator,
-    vertex_buffers: Vec<VertexBuffers<Vertex, Index>>,
+    meshes: Vec<Mesh>,
+    rrect_cache: HashMap<RRectKey, CachedGeometry>,
+    clip: Option<Rect>,
 }
 
 impl Tessellator {
     pub fn new() -> Self {
         Self {
             fill_tess: FillTessellator::new(),
-            vertex_buffers: Vec::new(),
+            meshes: Vec::new(),
+            rrect_cache: HashMap::new(),
+            clip: None,
         }
     }
 
-    pub fn vertex_buffers(&self) -> &[VertexBuffers<Vertex, Index>] {
-        &self.vertex_buffers
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    /// Clip the shapes tessellated from now on to the rect
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
     }
 
+    /// Prepare for the next frame. Cached shapes that were not drawn in
+    /// this frame are dropped.
     pub fn clear(&mut self) {
-        self.vertex_buffers.clear();
+        self.meshes.clear();
+        self.clip = None;
+        self.rrect_cache.retain(|_, geometry| geometry.is_used);
+        for geometry in self.rrect_cache.values_mut() {
+            geometry.is_used = false;
+        }
     }
 
     pub fn tessellate_path(&mut self, path: Path) {
+        if let Some(buffers) = self.fill_path(&path) {
+            self.push_mesh(Arc::new(buffers), Instance::identity());
+        }
+    }
+
+    /// Tessellate a rounded rect, reusing the geometry of a previous rounded
+    /// rect with the same size and radii. `build_path` builds the white path of
+    /// the shape at the origin and is only called when the shape is not cached.
+    /// The vertex shader moves and colors the geometry, so it isn't copied.
+    pub fn tessellate_rrect<F: FnOnce() -> Path>(
+        &mut self,
+        rrect: &RRect,
+        color: [f32; 4],
+        build_path: F,
+    ) {
+        let key = RRectKey::new(rrect);
+
+        if !self.rrect_cache.contains_key(&key) {
+            let buffers = match self.fill_path(&build_path()) {
+                Some(buffers) => buffers,
+                None => return,
+            };
+            self.rrect_cache.insert(
+                key,
+                CachedGeometry {
+                    buffers: Arc::new(buffers),
+                    is_used: false,
+                },
+            );
+        }
+
+        let geometry = self.rrect_cache.get_mut(&key).unwrap();
+        geometry.is_used = true;
+
+        let instance = Instance {
+            offset: uv::Vec2::new(rrect.x, rrect.y),
+            color: uv::Vec4::from(color.map(|channel| channel / 255.0)),
+        };
+        let buffers = geometry.buffers.clone();
+
+        self.push_mesh(buffers, instance);
+    }
+
+    fn push_mesh(&mut self, buffers: Arc<VertexBuffers<Vertex, Index>>, instance: Instance) {
+        self.meshes.push(Mesh {
+            buffers,
+            instance,
+            clip: self.clip.clone(),
+        });
+    }
+
+    fn fill_path(&mut self, path: &Path) -> Option<VertexBuffers<Vertex, Index>> {
         let mut buffer: VertexBuffers<Vertex, Index> = VertexBuffers::new();
 
         let result = self.fill_tess.tessellate_with_ids(
             path.id_iter(),
-            &path,
-            Some(&path),
+            path,
+            Some(path),
             &FillOptions::DEFAULT,
             &mut BuffersBuilder::new(&mut buffer, VertexConstructor),
         );
 
         if let Err(e) = result {
             log::error!("Tessellation failed: {:?}", e);
-            return;
+            return None;
         }
 
-        self.vertex_buffers.push(buffer);
+        Some(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon_tessellation::geom::point;
+    use shared::primitive::{Corners, Radii};
+
+    use super::*;
+
+    fn rrect(x: f32, y: f32) -> RRect {
+        let radii = || Radii::new(4., 4.);
+        RRect::new(
+            x,
+            y,
+            100.,
+            50.,
+            Corners::new(radii(), radii(), radii(), radii()),
+        )
+    }
+
+    fn rect_path() -> Path {
+        let color = [0., 0., 0., 0.];
+        let mut path_builder = Path::builder_with_attributes(4);
+        path_builder.begin(point(0., 0.), &color);
+        path_builder.line_to(point(100., 0.), &color);
+        path_builder.line_to(point(100., 50.), &color);
+        path_builder.line_to(point(0., 50.), &color);
+        path_builder.end(true);
+        path_builder.build()
+    }
+
+    #[test]
+    fn test_rrect_geometry_is_reused() {
+        let mut tessellator = Tessellator::new();
+        let mut build_count = 0;
+        let color = [255., 0., 0., 255.];
+
+        tessellator.tessellate_rrect(&rrect(0., 0.), color, || {
+            build_count += 1;
+            rect_path()
+        });
+        tessellator.tessellate_rrect(&rrect(10., 20.), color, || {
+            build_count += 1;
+            rect_path()
+        });
+        assert_eq!(build_count, 1);
+
+        let meshes = tessellator.meshes();
+        assert_eq!(meshes.len(), 2);
+        assert!(Arc::ptr_eq(&meshes[0].buffers, &meshes[1].buffers));
+        assert_eq!(
+            meshes[1].instance,
+            Instance {
+                offset: uv::Vec2::new(10., 20.),
+                color: uv::Vec4::new(1., 0., 0., 1.),
+            }
+        );
+    }
+
+    #[test]
+    fn test_meshes_keep_their_clip() {
+        let mut tessellator = Tessellator::new();
+        tessellator.tessellate_path(rect_path());
+        tessellator.set_clip(Some(Rect::new(0., 0., 10., 10.)));
+        tessellator.tessellate_rrect(&rrect(0., 0.), [0.; 4], rect_path);
+
+        let clips = tessellator
+            .meshes()
+            .iter()
+            .map(|mesh| mesh.clip.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(clips, vec![None, Some(Rect::new(0., 0., 10., 10.))]);
+    }
+
+    #[test]
+    fn test_unused_geometry_is_dropped() {
+        let mut tessellator = Tessellator::new();
+        tessellator.tessellate_rrect(&rrect(0., 0.), [0.; 4], rect_path);
+
+        tessellator.clear();
+        assert_eq!(tessellator.rrect_cache.len(), 1);
+
+        tessellator.clear();
+        assert!(tessellator.rrect_cache.is_empty());
     }
 }
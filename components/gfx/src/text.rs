@@ -1,12 +1,28 @@
+use crate::font_database::{self, FontIndex};
 use crate::fonts::FALLBACK;
-use shared::{color::Color, primitive::rect::Rect};
+use shared::{color::Color, memory::MemoryUsage, primitive::rect::Rect};
 use wgpu_glyph::ab_glyph;
 
+/// Size of the texture that the brush caches rasterized glyphs in. Glyphs
+/// stay cached between frames, the brush only grows the texture when the
+/// glyphs of a single frame don't fit in it.
+const ATLAS_SIZE: (u32, u32) = (2048, 2048);
+/// Scale steps, in pixels, that share a single rasterized glyph
+const SCALE_TOLERANCE: f32 = 0.1;
+/// Subpixel position steps, in pixels, that share a single rasterized glyph
+const POSITION_TOLERANCE: f32 = 0.1;
+
 pub struct Text {
     pub content: String,
     pub bounds: Rect,
     pub size: f32,
     pub color: Color,
+    pub font: FontIndex,
+    /// Whether the text runs from the top to the bottom of its bounds,
+    /// with its glyphs rotated clockwise
+    pub is_vertical: bool,
+    /// Area the text is clipped to, in CSS pixels
+    pub clip: Option<Rect>,
 }
 
 pub struct Pipeline {
@@ -32,28 +48,53 @@ impl Pipeline {
         });
 
         let draw_brush = wgpu_glyph::GlyphBrushBuilder::using_font(font)
-            .initial_cache_size((2048, 2048))
+            .initial_cache_size(ATLAS_SIZE)
+            .draw_cache_scale_tolerance(SCALE_TOLERANCE)
+            .draw_cache_position_tolerance(POSITION_TOLERANCE)
             .draw_cache_multithread(true)
             .build(device, format);
 
         Self { draw_brush }
     }
 
+    /// The texture glyphs are cached in, one byte per pixel, at the size
+    /// it starts with
+    pub fn atlas_memory(&self) -> MemoryUsage {
+        MemoryUsage::new(1, ATLAS_SIZE.0 as usize * ATLAS_SIZE.1 as usize)
+    }
+
+    /// Add the web fonts registered since the last call to the brush
+    pub fn sync_fonts(&mut self) {
+        let known_web_fonts = self.draw_brush.fonts().len() - 1;
+        for web_font in font_database::web_fonts_from(known_web_fonts) {
+            self.draw_brush.add_font(web_font);
+        }
+    }
+
     pub fn queue(&mut self, section: wgpu_glyph::Section<'_>) {
         self.draw_brush.queue(section);
     }
 
+    /// Draw the queued text, placed on the target by the transform and
+    /// clipped to the region in device pixels
     pub fn draw_queued(
         &mut self,
         device: &wgpu::Device,
         staging_belt: &mut wgpu::util::StagingBelt,
         encoder: &mut wgpu::CommandEncoder,
         target: &wgpu::TextureView,
-        size: (u32, u32),
+        transform: [f32; 16],
+        region: wgpu_glyph::Region,
     ) {
-        let (width, height) = size;
         self.draw_brush
-            .draw_queued(device, staging_belt, encoder, target, width, height)
+            .draw_queued_with_transform_and_scissoring(
+                device,
+                staging_belt,
+                encoder,
+                target,
+                transform,
+                region,
+            )
             .expect("Draw text");
     }
 }
@@ -1,24 +1,62 @@
+use crate::font_database::{self, FontIndex, DEFAULT_FONT};
 use crate::fonts;
-use glyph_brush::{ab_glyph::FontArc, Extra, FontId, GlyphCruncher, Section, Text};
+use glyph_brush::{
+    ab_glyph::{Font, FontArc, ScaleFont},
+    Extra, FontId, GlyphCruncher, Section, Text,
+};
 use shared::primitive::Size;
 
 pub struct TextMeasure {
     brush: glyph_brush::GlyphBrush<()>,
 }
 
+/// Vertical metrics of a font at a font size
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMetrics {
+    /// Distance from the top of the text to the baseline
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the text
+    pub descent: f32,
+    /// Extra space the font recommends between lines
+    pub line_gap: f32,
+}
+
+impl FontMetrics {
+    /// Height of the text, from the top of its ascent to the bottom of its
+    /// descent
+    pub fn height(&self) -> f32 {
+        self.ascent + self.descent
+    }
+
+    /// Line height for `line-height: normal`
+    pub fn normal_line_height(&self) -> f32 {
+        self.height() + self.line_gap
+    }
+}
+
 impl TextMeasure {
     pub fn new() -> Self {
         let font = FontArc::try_from_slice(fonts::FALLBACK).expect("Unable to load default font");
-        let brush = glyph_brush::GlyphBrushBuilder::using_font(font).build();
-        Self { brush }
+        let mut brush_builder = glyph_brush::GlyphBrushBuilder::using_font(font);
+        for web_font in font_database::web_fonts_from(0) {
+            brush_builder.add_font(web_font);
+        }
+        Self {
+            brush: brush_builder.build(),
+        }
     }
 
     pub fn measure(&mut self, content: &str, font_size: f32) -> Size {
+        self.measure_with_font(content, font_size, DEFAULT_FONT)
+    }
+
+    pub fn measure_with_font(&mut self, content: &str, font_size: f32, font: FontIndex) -> Size {
+        let font = self.known_font(font);
         let section = Section {
             text: vec![Text {
                 text: content,
                 scale: font_size.into(),
-                font_id: FontId(0),
+                font_id: FontId(font),
                 extra: Extra::default(),
             }],
             bounds: (f32::MAX, f32::MAX),
@@ -30,4 +68,23 @@ impl TextMeasure {
             Size::new(0., 0.)
         }
     }
+
+    pub fn font_metrics(&self, font_size: f32, font: FontIndex) -> FontMetrics {
+        let font = &self.brush.fonts()[self.known_font(font)];
+        let scaled_font = font.as_scaled(font_size);
+        FontMetrics {
+            ascent: scaled_font.ascent(),
+            descent: -scaled_font.descent(),
+            line_gap: scaled_font.line_gap(),
+        }
+    }
+
+    fn known_font(&self, font: FontIndex) -> FontIndex {
+        // fonts registered after the measurer was created are not known yet
+        if font < self.brush.fonts().len() {
+            font
+        } else {
+            DEFAULT_FONT
+        }
+    }
 }
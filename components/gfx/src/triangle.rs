@@ -1,10 +1,16 @@
 use bytemuck::{Pod, Zeroable};
 use lyon_tessellation::{FillVertex, FillVertexConstructor, VertexBuffers};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::backend::{RenderTarget, ScissorRegion};
+use crate::tessellator::Mesh;
 use ultraviolet as uv;
 
 const VERTEX_BUFFER_SIZE: usize = 10_000;
 const INDEX_BUFFER_SIZE: usize = 10_000;
+const INSTANCE_BUFFER_SIZE: usize = 1_000;
 const UNIFORM_BUFFER_SIZE: usize = 50;
 
 const INDEX_FORMAT: wgpu::IndexFormat = wgpu::IndexFormat::Uint32;
@@ -17,6 +23,26 @@ pub struct Vertex {
     pub color: uv::Vec4,
 }
 
+/// Offset and color applied to every vertex of a mesh by the vertex shader,
+/// so that the geometry of a mesh can be drawn at several places
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instance {
+    pub offset: uv::Vec2,
+    /// Multiplied with the color of the vertices
+    pub color: uv::Vec4,
+}
+
+impl Instance {
+    /// Draws the vertices as they are
+    pub fn identity() -> Self {
+        Self {
+            offset: uv::Vec2::zero(),
+            color: uv::Vec4::one(),
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct Uniforms {
@@ -26,13 +52,16 @@ pub struct Uniforms {
 unsafe impl Pod for Vertex {}
 unsafe impl Zeroable for Vertex {}
 
+unsafe impl Pod for Instance {}
+unsafe impl Zeroable for Instance {}
+
 unsafe impl Pod for Uniforms {}
 unsafe impl Zeroable for Uniforms {}
 
 #[derive(Debug)]
-struct Buffer<T> {
+pub(crate) struct Buffer<T> {
     label: &'static str,
-    raw: wgpu::Buffer,
+    pub raw: wgpu::Buffer,
     size: usize,
     usage: wgpu::BufferUsages,
     _type: std::marker::PhantomData<T>,
@@ -42,6 +71,7 @@ pub struct Pipeline {
     pipeline: wgpu::RenderPipeline,
     vertex_buffer: Buffer<Vertex>,
     index_buffer: Buffer<Index>,
+    instance_buffer: Buffer<Instance>,
     constants: wgpu::BindGroup,
     uniforms_buffer: Buffer<Uniforms>,
 }
@@ -88,7 +118,7 @@ impl<T> Buffer<T> {
 }
 
 impl Pipeline {
-    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32) -> Self {
         let shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
             label: Some("triangle shader"),
             source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(concat!(
@@ -145,14 +175,24 @@ impl Pipeline {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: "vs_main",
-                buffers: &[wgpu::VertexBufferLayout {
-                    array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-                    step_mode: wgpu::VertexStepMode::Vertex,
-                    attributes: &wgpu::vertex_attr_array![
-                        0 => Float32x2,
-                        1 => Float32x4
-                    ],
-                }],
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![
+                            0 => Float32x2,
+                            1 => Float32x4
+                        ],
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![
+                            2 => Float32x2,
+                            3 => Float32x4
+                        ],
+                    },
+                ],
             },
 
             // Fragment shader
@@ -169,7 +209,7 @@ impl Pipeline {
                         },
                         alpha: wgpu::BlendComponent {
                             src_factor: wgpu::BlendFactor::One,
-                            dst_factor: wgpu::BlendFactor::One,
+                            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
                             operation: wgpu::BlendOperation::Add,
                         },
                     }),
@@ -182,7 +222,7 @@ impl Pipeline {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -204,6 +244,12 @@ impl Pipeline {
                 INDEX_BUFFER_SIZE,
                 wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             ),
+            instance_buffer: Buffer::new(
+                "moon::gfx::triangle instance buffer",
+                device,
+                INSTANCE_BUFFER_SIZE,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            ),
         }
     }
 
@@ -212,11 +258,28 @@ impl Pipeline {
         device: &wgpu::Device,
         encoder: &mut wgpu::CommandEncoder,
         staging_belt: &mut wgpu::util::StagingBelt,
-        triangles: &[VertexBuffers<Vertex, Index>],
-        target: &wgpu::TextureView,
-        size: (u32, u32),
+        meshes: &[(&Mesh, ScissorRegion)],
+        target: &RenderTarget,
+        screen_size: (f32, f32),
     ) {
-        let (total_vertices, total_indices) = triangles
+        // Meshes drawn from the same cached geometry share its vertices, which
+        // are uploaded once
+        let mut geometries: Vec<&VertexBuffers<Vertex, Index>> = Vec::new();
+        let mut geometry_indices: HashMap<*const VertexBuffers<Vertex, Index>, usize> =
+            HashMap::new();
+        let mesh_geometries = meshes
+            .iter()
+            .map(|(mesh, _)| {
+                *geometry_indices
+                    .entry(Arc::as_ptr(&mesh.buffers))
+                    .or_insert_with(|| {
+                        geometries.push(&mesh.buffers);
+                        geometries.len() - 1
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let (total_vertices, total_indices) = geometries
             .iter()
             .map(|buffers| (buffers.vertices.len(), buffers.indices.len()))
             .fold((0, 0), |(total_v, total_i), (v, i)| {
@@ -227,14 +290,16 @@ impl Pipeline {
         // necessary
         self.vertex_buffer.expand(device, total_vertices);
         self.index_buffer.expand(device, total_indices);
+        self.instance_buffer.expand(device, meshes.len());
 
-        let mut offsets: Vec<(wgpu::BufferAddress, wgpu::BufferAddress, usize)> =
-            Vec::with_capacity(triangles.len());
+        // Vertex offset, index offset and index count of each geometry
+        let mut offsets: Vec<Option<(wgpu::BufferAddress, wgpu::BufferAddress, usize)>> =
+            Vec::with_capacity(geometries.len());
 
         let mut last_vertex = 0;
         let mut last_index = 0;
 
-        for buffers in triangles {
+        for buffers in geometries {
             let vertices = bytemuck::cast_slice(&buffers.vertices);
 
             // Align indices by 4 (COPY_BUFFER_ALIGNMENT)
@@ -274,17 +339,39 @@ impl Pipeline {
                         index_buffer.copy_from_slice(indices);
                     }
 
-                    offsets.push((last_vertex as u64, last_index as u64, buffers.indices.len()));
+                    offsets.push(Some((
+                        last_vertex as u64,
+                        last_index as u64,
+                        buffers.indices.len(),
+                    )));
 
                     last_vertex += buffers.vertices.len();
                     last_index += buffers.indices.len();
                 }
-                _ => {}
+                _ => offsets.push(None),
             }
         }
 
+        let instances = meshes
+            .iter()
+            .map(|(mesh, _)| mesh.instance)
+            .collect::<Vec<_>>();
+        let instances: &[u8] = bytemuck::cast_slice(&instances);
+
+        if let Some(instances_size) = wgpu::BufferSize::new(instances.len() as u64) {
+            let mut instance_buffer = staging_belt.write_buffer(
+                encoder,
+                &self.instance_buffer.raw,
+                0,
+                instances_size,
+                device,
+            );
+
+            instance_buffer.copy_from_slice(instances);
+        }
+
         let uniforms = [Uniforms {
-            screen_size: uv::Vec2::new(size.0 as f32, size.1 as f32),
+            screen_size: uv::Vec2::new(screen_size.0, screen_size.1),
         }];
 
         let uniforms = bytemuck::cast_slice(&uniforms);
@@ -304,8 +391,8 @@ impl Pipeline {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("moon::gfx::triangle renderpass"),
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: target,
-                resolve_target: None,
+                view: target.view(),
+                resolve_target: target.resolve_target(),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
                     store: true,
@@ -317,7 +404,17 @@ impl Pipeline {
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, &self.constants, &[]);
 
-        for (vertex_offset, index_offset, indices) in offsets {
+        render_pass.set_vertex_buffer(1, self.instance_buffer.raw.slice(..));
+
+        for (instance, ((_, scissor), geometry)) in meshes.iter().zip(mesh_geometries).enumerate() {
+            let (vertex_offset, index_offset, indices) = match offsets[geometry] {
+                Some(offsets) => offsets,
+                None => continue,
+            };
+            let instance = instance as u32;
+
+            render_pass.set_scissor_rect(scissor.x, scissor.y, scissor.width, scissor.height);
+
             let start_index = index_offset * std::mem::size_of::<Index>() as u64;
             let start_vertex = vertex_offset * std::mem::size_of::<Vertex>() as u64;
 
@@ -325,7 +422,7 @@ impl Pipeline {
 
             render_pass.set_vertex_buffer(0, self.vertex_buffer.raw.slice(start_vertex..));
 
-            render_pass.draw_indexed(0..indices as u32, 0, 0..1);
+            render_pass.draw_indexed(0..indices as u32, 0, instance..instance + 1);
         }
     }
 }
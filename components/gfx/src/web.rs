@@ -0,0 +1,81 @@
+use super::backend::{RenderTarget, ScissorRegion};
+use super::canvas::clear;
+use super::image::{self, TexturedQuad};
+use shared::primitive::Rect;
+
+/// Presents frames on an HTML canvas. The frame is rendered to a texture of
+/// its own as usual, then composited onto the texture of the canvas, whose
+/// format is chosen by the browser.
+pub(crate) struct Presenter {
+    surface: wgpu::Surface,
+    format: wgpu::TextureFormat,
+    compositor: image::Pipeline,
+    /// Size the surface was configured for, in device pixels
+    size: (u32, u32),
+}
+
+impl Presenter {
+    pub fn new(device: &wgpu::Device, surface: wgpu::Surface, format: wgpu::TextureFormat) -> Self {
+        Self {
+            surface,
+            format,
+            compositor: image::Pipeline::compositor(device, format, 1),
+            size: (0, 0),
+        }
+    }
+
+    /// Draw the frame onto the texture of the canvas, which is shown once
+    /// the returned texture is presented after the encoder is submitted
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &wgpu::TextureView,
+        size: (u32, u32),
+    ) -> Option<wgpu::SurfaceTexture> {
+        if self.size != size {
+            self.surface.configure(
+                device,
+                &wgpu::SurfaceConfiguration {
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    format: self.format,
+                    width: size.0,
+                    height: size.1,
+                    present_mode: wgpu::PresentMode::Fifo,
+                },
+            );
+            self.size = size;
+        }
+        let output = match self.surface.get_current_texture() {
+            Ok(output) => output,
+            Err(e) => {
+                log::error!("Unable to get the texture of the canvas: {}", e);
+                return None;
+            }
+        };
+        let view = output.texture.create_view(&Default::default());
+        let target = RenderTarget {
+            frame: &view,
+            multisampled: None,
+            size,
+        };
+        clear(encoder, &target, wgpu::Color::TRANSPARENT);
+
+        let bind_group = self.compositor.texture_bind_group(device, frame);
+        let screen_size = (size.0 as f32, size.1 as f32);
+        let quad = TexturedQuad {
+            bind_group: &bind_group,
+            bounds: Rect::new(0., 0., screen_size.0, screen_size.1),
+            scissor: ScissorRegion {
+                x: 0,
+                y: 0,
+                width: size.0,
+                height: size.1,
+            },
+        };
+        self.compositor
+            .composite(device, queue, encoder, &[quad], &target, screen_size);
+        Some(output)
+    }
+}
@@ -0,0 +1,137 @@
+//! Parsing of markup in the context of an element, for `innerHTML`
+//! https://html.spec.whatwg.org/multipage/parsing.html#html-fragment-parsing-algorithm
+
+use dom::node::{ChildrenUpdateContext, Node, NodePtr};
+use shared::tree_node::{TreeNode, WeakTreeNode};
+
+use crate::tokenizer::Tokenizer;
+use crate::tree_builder::TreeBuilder;
+
+/// Nodes of the markup parsed as children of `context`. They belong to the
+/// document of `context` but aren't inserted anywhere.
+pub fn parse_fragment(markup: &str, context: &NodePtr) -> Vec<NodePtr> {
+    let tokenizer = Tokenizer::new(markup.chars());
+    let document = TreeBuilder::new_fragment(tokenizer, context.clone()).run();
+    let root = match document.first_child() {
+        Some(root) => NodePtr(root),
+        None => return Vec::new(),
+    };
+
+    let mut nodes = Vec::new();
+    root.for_each_child(|child| nodes.push(NodePtr(child)));
+    for node in &nodes {
+        node.detach();
+        if let Some(owner_document) = context.owner_document() {
+            adopt(node, &owner_document);
+        }
+    }
+    nodes
+}
+
+/// Replace the children of the element with the nodes of the markup
+/// https://html.spec.whatwg.org/multipage/dynamic-markup-insertion.html#dom-element-innerhtml
+pub fn set_inner_html(element: &NodePtr, markup: &str) {
+    let nodes = parse_fragment(markup, element);
+
+    let mut children = Vec::new();
+    element.for_each_child(|child| children.push(child));
+    for child in children {
+        child.detach();
+    }
+    for node in nodes {
+        element.append_child(node.0.clone());
+        if let Some(document) = element.owner_document() {
+            notify_children_updated(&node, &NodePtr(document));
+        }
+    }
+}
+
+/// Make the document the owner of the node and its descendants
+fn adopt(node: &NodePtr, document: &TreeNode<Node>) {
+    node.set_document(WeakTreeNode::from(document));
+    node.for_each_child(|child| adopt(&NodePtr(child), document));
+}
+
+/// Let the elements of the inserted subtree react to their content like
+/// when the parser inserts them, so that `<style>` elements apply their
+/// styles to the document they were moved to
+fn notify_children_updated(node: &NodePtr, document: &NodePtr) {
+    node.for_each_child(|child| notify_children_updated(&NodePtr(child), document));
+    if node.has_no_child() {
+        return;
+    }
+    if let Some(data) = node.data() {
+        data.handle_on_children_updated(ChildrenUpdateContext {
+            document: document.clone(),
+            current_node: node.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(html: &str) -> NodePtr {
+        let tokenizer = Tokenizer::new(html.chars());
+        TreeBuilder::default(tokenizer).run()
+    }
+
+    fn tag_names(node: &NodePtr) -> Vec<String> {
+        let mut tag_names = Vec::new();
+        node.for_each_child(|child| match child.as_element_opt() {
+            Some(element) => tag_names.push(element.tag_name()),
+            None => tag_names.push("#text".to_string()),
+        });
+        tag_names
+    }
+
+    #[test]
+    fn test_set_inner_html() {
+        let document = parse("<body><div id=target><p>old</p></div></body>");
+        let body = document.get_elements_by_tag_name("body")[0].clone();
+        let target = document.get_elements_by_tag_name("div")[0].clone();
+
+        set_inner_html(&target, "<b>bold <i>text</b> after</i><img src=a.png>tail");
+        assert_eq!(tag_names(&target), ["b", "i", "img", "#text"]);
+        assert_eq!(target.descendant_text_content(), "bold text aftertail");
+        assert!(target.get_elements_by_tag_name("p").is_empty());
+
+        // Nodes belong to the document of the context element
+        let bold = target.get_elements_by_tag_name("b")[0].clone();
+        assert_eq!(NodePtr(bold.owner_document().unwrap()).id(), document.id());
+        assert_eq!(
+            document.node_by_id(bold.id()).map(|node| node.id()),
+            Some(bold.id())
+        );
+
+        // Document-level tags are dropped in a body context
+        set_inner_html(
+            &body,
+            "<html><head><title>t</title></head><body class=x>content",
+        );
+        assert_eq!(tag_names(&body), ["title", "#text"]);
+    }
+
+    #[test]
+    fn test_parse_fragment_in_context() {
+        let document =
+            parse("<table><tbody><tr><td>cell</td></tr></tbody></table><textarea></textarea>");
+        let row = document.get_elements_by_tag_name("tr")[0].clone();
+        let textarea = document.get_elements_by_tag_name("textarea")[0].clone();
+
+        // Cells are parsed in row insertion mode instead of being dropped
+        let cells = parse_fragment("<td>a</td><td>b", &row);
+        let tag_names: Vec<String> = cells
+            .iter()
+            .map(|cell| cell.as_element().tag_name())
+            .collect();
+        assert_eq!(tag_names, ["td", "td"]);
+
+        // The content of a textarea is text
+        let nodes = parse_fragment("<b>not bold</b>", &textarea);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].as_text().get_data(), "<b>not bold</b>");
+        assert!(parse_fragment("", &row).is_empty());
+    }
+}
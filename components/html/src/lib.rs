@@ -1,3 +1,4 @@
 pub mod entities;
+pub mod fragment;
 pub mod tokenizer;
 pub mod tree_builder;
@@ -154,11 +154,16 @@ where
 
     // Code for a character reference. Example: &#228;
     character_reference_code: u32,
+
+    // Whether the adjusted current node of the tree builder is a foreign
+    // element, where CDATA sections are allowed
+    in_foreign_content: bool,
 }
 
 pub trait Tokenizing {
     fn next_token(&mut self) -> Token;
     fn switch_to(&mut self, state: State);
+    fn set_in_foreign_content(&mut self, in_foreign_content: bool);
 }
 
 impl<T> Tokenizing for Tokenizer<T>
@@ -1120,8 +1125,13 @@ where
                     } else if self.consume_if_match("doctype", true) {
                         self.switch_to(State::DOCTYPE);
                     } else if self.consume_if_match("[CDATA[", false) {
-                        // TODO: implement this
-                        unimplemented!();
+                        if self.in_foreign_content {
+                            self.switch_to(State::CDATASection);
+                        } else {
+                            emit_error!("cdata-in-html-content");
+                            self.new_token(Token::new_comment("[CDATA["));
+                            self.switch_to(State::BogusComment);
+                        }
                     } else {
                         emit_error!("incorrectly-opened-comment");
                         self.new_token(Token::new_comment(""));
@@ -2346,6 +2356,10 @@ where
         }
         self.state = state;
     }
+
+    fn set_in_foreign_content(&mut self, in_foreign_content: bool) {
+        self.in_foreign_content = in_foreign_content;
+    }
 }
 
 impl<T> Tokenizer<T>
@@ -2364,6 +2378,7 @@ where
             temp_buffer: String::new(),
             last_emitted_start_tag: None,
             character_reference_code: 0,
+            in_foreign_content: false,
         }
     }
 
@@ -114,6 +114,7 @@ impl Token {
         } = self
         {
             *tag_name = new_name.to_owned();
+            return;
         }
         panic!("Token is not a tag");
     }
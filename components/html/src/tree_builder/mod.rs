@@ -1,6 +1,7 @@
 mod insert_mode;
 mod list_of_active_formatting_elements;
 mod open_element_types;
+mod quirks;
 mod stack_of_open_elements;
 
 use super::tokenizer::state::State;
@@ -9,7 +10,8 @@ use super::tokenizer::token::Token;
 use crate::tokenizer::Tokenizing;
 use dom::comment::Comment;
 use dom::document::{Document, DocumentType, QuirksMode};
-use dom::element::Element;
+use dom::element::{Element, Namespace};
+use dom::lifecycle::DocumentReadyState;
 use dom::node::ChildrenUpdateContext;
 use dom::node::NodePtr;
 use dom::node::{Node, NodeData};
@@ -17,7 +19,9 @@ use dom::text::Text;
 use insert_mode::InsertMode;
 use list_of_active_formatting_elements::Entry;
 use list_of_active_formatting_elements::ListOfActiveFormattingElements;
-use open_element_types::is_special_element;
+use open_element_types::{
+    is_html_integration_point, is_mathml_text_integration_point, is_special_element,
+};
 use phf::phf_map;
 use shared::tree_node::TreeNode;
 use shared::tree_node::WeakTreeNode;
@@ -207,6 +211,120 @@ static SVG_ATTRIBUTE_MAP: phf::Map<&str, &str> = phf_map! {
     "zoomandpan" => "zoomAndPan",
 };
 
+static SVG_TAG_NAME_MAP: phf::Map<&str, &str> = phf_map! {
+    "altglyph" => "altGlyph",
+    "altglyphdef" => "altGlyphDef",
+    "altglyphitem" => "altGlyphItem",
+    "animatecolor" => "animateColor",
+    "animatemotion" => "animateMotion",
+    "animatetransform" => "animateTransform",
+    "clippath" => "clipPath",
+    "feblend" => "feBlend",
+    "fecolormatrix" => "feColorMatrix",
+    "fecomponenttransfer" => "feComponentTransfer",
+    "fecomposite" => "feComposite",
+    "feconvolvematrix" => "feConvolveMatrix",
+    "fediffuselighting" => "feDiffuseLighting",
+    "fedisplacementmap" => "feDisplacementMap",
+    "fedistantlight" => "feDistantLight",
+    "fedropshadow" => "feDropShadow",
+    "feflood" => "feFlood",
+    "fefunca" => "feFuncA",
+    "fefuncb" => "feFuncB",
+    "fefuncg" => "feFuncG",
+    "fefuncr" => "feFuncR",
+    "fegaussianblur" => "feGaussianBlur",
+    "feimage" => "feImage",
+    "femerge" => "feMerge",
+    "femergenode" => "feMergeNode",
+    "femorphology" => "feMorphology",
+    "feoffset" => "feOffset",
+    "fepointlight" => "fePointLight",
+    "fespecularlighting" => "feSpecularLighting",
+    "fespotlight" => "feSpotLight",
+    "fetile" => "feTile",
+    "feturbulence" => "feTurbulence",
+    "foreignobject" => "foreignObject",
+    "glyphref" => "glyphRef",
+    "lineargradient" => "linearGradient",
+    "radialgradient" => "radialGradient",
+    "textpath" => "textPath",
+};
+
+fn adjust_svg_tag_name(token: &mut Token) {
+    if let Some(rname) = SVG_TAG_NAME_MAP.get(token.tag_name().as_str()) {
+        token.set_tag_name(rname);
+    }
+}
+
+fn adjust_mathml_attributes(token: &mut Token) {
+    for attr in token.attributes_mut() {
+        if attr.name == "definitionurl" {
+            attr.name = "definitionURL".to_string();
+        }
+    }
+}
+
+/// Start tags that close the foreign elements they appear in
+/// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+fn is_foreign_content_breakout(token: &Token) -> bool {
+    if token.is_end_tag() {
+        return match_any!(token.tag_name(), "br", "p");
+    }
+    if token.tag_name() == "font" {
+        return ["color", "face", "size"]
+            .iter()
+            .any(|name| token.attribute(name).is_some());
+    }
+    match_any!(
+        token.tag_name(),
+        "b",
+        "big",
+        "blockquote",
+        "body",
+        "br",
+        "center",
+        "code",
+        "dd",
+        "div",
+        "dl",
+        "dt",
+        "em",
+        "embed",
+        "h1",
+        "h2",
+        "h3",
+        "h4",
+        "h5",
+        "h6",
+        "head",
+        "hr",
+        "i",
+        "img",
+        "li",
+        "listing",
+        "menu",
+        "meta",
+        "nobr",
+        "ol",
+        "p",
+        "pre",
+        "ruby",
+        "s",
+        "small",
+        "span",
+        "strong",
+        "strike",
+        "sub",
+        "sup",
+        "table",
+        "tt",
+        "u",
+        "ul",
+        "var"
+    )
+}
+
 fn adjust_svg_attributes(token: &mut Token) {
     for attr in token.attributes_mut() {
         if let Some(rname) = SVG_ATTRIBUTE_MAP.get(attr.name.as_str()) {
@@ -277,9 +395,64 @@ impl<T: Tokenizing> TreeBuilder<T> {
         Self::new(tokenizer, document)
     }
 
+    /// Create a tree builder for the HTML fragment parsing algorithm. The
+    /// nodes are parsed as children of `context`, into the root element of
+    /// a new document that `run` returns.
+    /// https://html.spec.whatwg.org/multipage/parsing.html#html-fragment-parsing-algorithm
+    pub fn new_fragment(mut tokenizer: T, context: NodePtr) -> Self {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        if let Some(context_document) = context.owner_document() {
+            let context_document = context_document.as_document();
+            document.as_document().set_mode(context_document.get_mode());
+            document.as_document().set_base(context_document.base());
+        }
+
+        let context_element = context.as_element();
+        if context_element.namespace() == Namespace::Html {
+            match context_element.tag_name().as_str() {
+                "title" | "textarea" => tokenizer.switch_to(State::RCDATA),
+                "style" | "xmp" | "iframe" | "noembed" | "noframes" => {
+                    tokenizer.switch_to(State::RAWTEXT)
+                }
+                "script" => tokenizer.switch_to(State::ScriptData),
+                "plaintext" => tokenizer.switch_to(State::PLAINTEXT),
+                _ => {}
+            }
+        }
+
+        let mut builder = Self::new(tokenizer, document);
+        let root = builder.create_element_from_tag_name("html");
+        builder.document.append_child(root.0.clone());
+        builder.open_elements.push(root);
+        builder.is_fragment_case = true;
+        builder.context_element = Some(context.clone());
+        builder.reset_insertion_mode_appropriately();
+        builder.form_pointer = match context_element.is_html("form") {
+            true => Some(context.clone()),
+            false => context
+                .find_first_ancestor(|node| {
+                    node.as_element_opt()
+                        .is_some_and(|element| element.is_html("form"))
+                })
+                .map(NodePtr),
+        };
+        builder
+    }
+
     /// Start the main loop for parsing DOM tree
     pub fn run(mut self) -> NodePtr {
         loop {
+            if self.document.as_document().is_load_cancelled() {
+                log::info!("Parsing cancelled");
+                break;
+            }
+
+            let in_foreign_content = self.adjusted_current_node().map_or(false, |node| {
+                node.as_element().namespace() != Namespace::Html
+            });
+            self.tokenizer.set_in_foreign_content(in_foreign_content);
             let token = self.tokenizer.next_token();
 
             self.process(token);
@@ -289,11 +462,30 @@ impl<T: Tokenizing> TreeBuilder<T> {
             }
         }
         self.flush_text_insertion();
+        // The parsed document is interactive, its resources may still be
+        // loading
+        // https://html.spec.whatwg.org/multipage/parsing.html#the-end
+        if !self.document.as_document().is_load_cancelled() {
+            self.document
+                .as_document()
+                .lifecycle()
+                .advance_to(DocumentReadyState::Interactive);
+        }
         return self.document;
     }
 
-    /// (Re)process a token in the current insert mode
+    /// (Re)process a token following the tree construction dispatcher
+    /// https://html.spec.whatwg.org/multipage/parsing.html#tree-construction-dispatcher
     pub fn process(&mut self, token: Token) {
+        if self.is_foreign_content_token(&token) {
+            self.handle_in_foreign_content(token);
+        } else {
+            self.process_in_insert_mode(token);
+        }
+    }
+
+    /// (Re)process a token in the current insert mode
+    fn process_in_insert_mode(&mut self, token: Token) {
         match self.insert_mode {
             InsertMode::Initial => self.handle_initial(token),
             InsertMode::BeforeHtml => self.handle_before_html(token),
@@ -318,6 +510,46 @@ impl<T: Tokenizing> TreeBuilder<T> {
         }
     }
 
+    /// Check if the token should be processed following the rules for
+    /// parsing tokens in foreign content
+    fn is_foreign_content_token(&self, token: &Token) -> bool {
+        let node = match self.adjusted_current_node() {
+            Some(node) => node,
+            None => return false,
+        };
+        let element = node.as_element();
+        if element.namespace() == Namespace::Html || token.is_eof() {
+            return false;
+        }
+
+        let is_character = matches!(token, Token::Character(_));
+        if is_mathml_text_integration_point(element)
+            && (is_character
+                || (token.is_start_tag() && !match_any!(token.tag_name(), "mglyph", "malignmark")))
+        {
+            return false;
+        }
+        if element.namespace() == Namespace::MathML
+            && element.tag_name() == "annotation-xml"
+            && token.is_start_tag()
+            && token.tag_name() == "svg"
+        {
+            return false;
+        }
+        if is_html_integration_point(element) && (is_character || token.is_start_tag()) {
+            return false;
+        }
+        true
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#adjusted-current-node
+    fn adjusted_current_node(&self) -> Option<NodePtr> {
+        if self.is_fragment_case && self.open_elements.len() == 1 {
+            return self.context_element.clone();
+        }
+        self.open_elements.current_node()
+    }
+
     /// Get the current parsing document
     pub fn get_document(&self) -> NodePtr {
         self.document.clone()
@@ -325,13 +557,18 @@ impl<T: Tokenizing> TreeBuilder<T> {
 
     fn which_quirks_mode(&self, token: Token) -> QuirksMode {
         if let Token::DOCTYPE {
-            name, force_quirks, ..
+            name,
+            public_identifier,
+            system_identifier,
+            force_quirks,
         } = token
         {
-            // TODO: Implement full stpecs detection
-            if force_quirks || name.unwrap_or_default() != "html" {
-                return QuirksMode::Quirks;
-            }
+            return quirks::quirks_mode_from_doctype(
+                name.as_deref(),
+                public_identifier.as_deref(),
+                system_identifier.as_deref(),
+                force_quirks,
+            );
         }
         QuirksMode::NoQuirks
     }
@@ -348,6 +585,10 @@ impl<T: Tokenizing> TreeBuilder<T> {
     }
 
     fn create_element(&self, tag_token: Token) -> NodePtr {
+        self.create_element_in_namespace(tag_token, Namespace::Html)
+    }
+
+    fn create_element_in_namespace(&self, tag_token: Token, namespace: Namespace) -> NodePtr {
         let (tag_name, attributes) = if let Token::Tag {
             tag_name,
             attributes,
@@ -358,11 +599,17 @@ impl<T: Tokenizing> TreeBuilder<T> {
         } else {
             ("".to_string(), Vec::new())
         };
-        let element_ref = dom::create_element(WeakTreeNode::from(&self.document.0), &tag_name);
+        let element_ref =
+            dom::create_element_ns(WeakTreeNode::from(&self.document.0), &tag_name, namespace);
         for attribute in attributes {
+            let name = if attribute.prefix.is_empty() {
+                attribute.name
+            } else {
+                format!("{}:{}", attribute.prefix, attribute.name)
+            };
             element_ref
                 .as_element()
-                .set_attribute(&attribute.name, &attribute.value);
+                .set_attribute(&name, &attribute.value);
         }
         element_ref
     }
@@ -425,8 +672,13 @@ impl<T: Tokenizing> TreeBuilder<T> {
     }
 
     fn insert_html_element(&mut self, token: Token) -> NodePtr {
+        self.insert_foreign_element(token, Namespace::Html)
+    }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#insert-a-foreign-element
+    fn insert_foreign_element(&mut self, token: Token, namespace: Namespace) -> NodePtr {
         let insert_position = self.get_appropriate_place_for_inserting_a_node(None);
-        let element = self.create_element(token);
+        let element = self.create_element_in_namespace(token, namespace);
         let return_ref = element.clone();
 
         // TODO: check if location is possible to insert node (Idk why so we just leave it for now)
@@ -560,7 +812,7 @@ impl<T: Tokenizing> TreeBuilder<T> {
         for (index, node) in self.open_elements.0.iter().enumerate().rev() {
             let last = index == 0;
 
-            let node = if self.is_fragment_case {
+            let node = if last && self.is_fragment_case {
                 self.context_element.clone().unwrap()
             } else {
                 node.clone()
@@ -613,12 +865,16 @@ impl<T: Tokenizing> TreeBuilder<T> {
                 return;
             }
 
+            // A template context has no template insertion mode when its
+            // content is parsed as a fragment
             if element.tag_name() == "template" {
-                self.switch_to(self.stack_of_template_insert_mode.last().unwrap().clone());
-                return;
+                if let Some(mode) = self.stack_of_template_insert_mode.last().cloned() {
+                    self.switch_to(mode);
+                    return;
+                }
             }
 
-            if element.tag_name() == "head" {
+            if element.tag_name() == "head" && !last {
                 self.switch_to(InsertMode::InHead);
                 return;
             }
@@ -709,7 +965,7 @@ impl<T: Tokenizing> TreeBuilder<T> {
                     if Rc::ptr_eq(&element, &fmt_element) {
                         break;
                     }
-                    if is_special_element(&element.as_element().tag_name()) {
+                    if is_special_element(element.as_element()) {
                         found_element = Some(element.clone());
                         found_index = Some(index);
                     }
@@ -1030,7 +1286,11 @@ impl<T: Tokenizing> TreeBuilder<T> {
             return;
         }
 
+        // Documents without a DOCTYPE are rendered in quirks mode
         self.unexpected(&token);
+        if let Some(doc) = self.document.as_document_opt() {
+            doc.set_mode(QuirksMode::Quirks);
+        }
         self.switch_to(InsertMode::BeforeHtml);
         self.process(token)
     }
@@ -1424,8 +1684,8 @@ impl<T: Tokenizing> TreeBuilder<T> {
         fn any_other_end_tags<T: Tokenizing>(this: &mut TreeBuilder<T>, token: Token) {
             let mut index: Option<usize> = None;
             for (idx, node) in this.open_elements.0.iter().enumerate().rev() {
-                let current_tag_name = node.as_element().tag_name();
-                if current_tag_name == *token.tag_name() {
+                let element = node.as_element();
+                if element.is_html(token.tag_name()) {
                     if Rc::ptr_eq(&node, &this.current_node()) {
                         this.unexpected(&token);
                     }
@@ -1433,7 +1693,7 @@ impl<T: Tokenizing> TreeBuilder<T> {
                     break;
                 }
 
-                if is_special_element(&current_tag_name) {
+                if is_special_element(element) {
                     emit_error!("Unexpected special element");
                     return;
                 }
@@ -1750,12 +2010,9 @@ impl<T: Tokenizing> TreeBuilder<T> {
 
             self.frameset_ok = false;
 
-            if let Token::Character(c) = next_token {
-                if c == '\n' {
-                    // ignore the token
-                } else {
-                    self.process(next_token);
-                }
+            // A newline right after the start tag is ignored
+            if !matches!(next_token, Token::Character('\n')) {
+                self.process(next_token);
             }
             return;
         }
@@ -1792,7 +2049,7 @@ impl<T: Tokenizing> TreeBuilder<T> {
                 }
 
                 if !match_any!(element_tag_name, "address", "div", "p")
-                    && is_special_element(&element_tag_name)
+                    && is_special_element(node.as_element())
                 {
                     break;
                 }
@@ -1828,7 +2085,7 @@ impl<T: Tokenizing> TreeBuilder<T> {
                 }
 
                 if !match_any!(element_tag_name, "address", "div", "p")
-                    && is_special_element(&element_tag_name)
+                    && is_special_element(node.as_element())
                 {
                     break;
                 }
@@ -2300,23 +2557,23 @@ impl<T: Tokenizing> TreeBuilder<T> {
             return;
         }
 
-        if token.is_start_tag() && token.tag_name() == "math" {
-            // TODO: support math
-            unimplemented!();
-        }
-
-        if token.is_start_tag() && token.tag_name() == "svg" {
+        if token.is_start_tag() && match_any!(token.tag_name(), "math", "svg") {
             self.reconstruct_active_formatting_elements();
-            adjust_svg_attributes(&mut token);
+            let namespace = if token.tag_name() == "math" {
+                adjust_mathml_attributes(&mut token);
+                Namespace::MathML
+            } else {
+                adjust_svg_attributes(&mut token);
+                Namespace::Svg
+            };
             adjust_foreign_attributes(&mut token);
 
-            if token.is_self_closing() {
+            let self_closing = token.is_self_closing();
+            token.acknowledge_self_closing_if_set();
+            self.insert_foreign_element(token, namespace);
+            if self_closing {
                 self.open_elements.pop();
-                token.acknowledge_self_closing_if_set();
             }
-
-            // TODO: change this to insert foreign element
-            self.insert_html_element(token);
             return;
         }
 
@@ -3270,12 +3527,97 @@ impl<T: Tokenizing> TreeBuilder<T> {
             return self.process(token);
         }
     }
+
+    /// https://html.spec.whatwg.org/multipage/parsing.html#parsing-main-inforeign
+    fn handle_in_foreign_content(&mut self, mut token: Token) {
+        if let Token::Character(c) = token {
+            if c == '\0' {
+                emit_error!("Unexpected null character");
+                self.insert_character('\u{FFFD}');
+                return;
+            }
+            if !is_whitespace(c) {
+                self.frameset_ok = false;
+            }
+            self.insert_character(c);
+            return;
+        }
+
+        if let Token::Comment(data) = token {
+            self.insert_comment(data);
+            return;
+        }
+
+        if let Token::DOCTYPE { .. } = token {
+            emit_error!("Unexpected DOCTYPE");
+            return;
+        }
+
+        if (token.is_start_tag() || token.is_end_tag()) && is_foreign_content_breakout(&token) {
+            self.unexpected(&token);
+            while let Some(node) = self.open_elements.current_node() {
+                let element = node.as_element();
+                if element.namespace() == Namespace::Html
+                    || is_mathml_text_integration_point(element)
+                    || is_html_integration_point(element)
+                {
+                    break;
+                }
+                self.open_elements.pop();
+            }
+            return self.process_in_insert_mode(token);
+        }
+
+        if token.is_start_tag() {
+            let namespace = self
+                .adjusted_current_node()
+                .unwrap()
+                .as_element()
+                .namespace();
+            match namespace {
+                Namespace::MathML => adjust_mathml_attributes(&mut token),
+                Namespace::Svg => {
+                    adjust_svg_tag_name(&mut token);
+                    adjust_svg_attributes(&mut token);
+                }
+                Namespace::Html => {}
+            }
+            adjust_foreign_attributes(&mut token);
+
+            let self_closing = token.is_self_closing();
+            token.acknowledge_self_closing_if_set();
+            self.insert_foreign_element(token, namespace);
+            if self_closing {
+                self.open_elements.pop();
+            }
+            return;
+        }
+
+        if token.is_end_tag() {
+            let mut index = self.open_elements.len() - 1;
+            let tag_name = |node: &NodePtr| node.as_element().tag_name().to_ascii_lowercase();
+            if tag_name(&self.open_elements.get(index)) != *token.tag_name() {
+                self.unexpected(&token);
+            }
+            while index > 0 {
+                if tag_name(&self.open_elements.get(index)) == *token.tag_name() {
+                    self.open_elements.truncate(index);
+                    return;
+                }
+                index -= 1;
+                if self.open_elements.get(index).as_element().namespace() == Namespace::Html {
+                    return self.process_in_insert_mode(token);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::tokenizer::Tokenizer;
+    use shared::cancellation::CancellationToken;
 
     #[test]
     fn handle_initial_correctly() {
@@ -3294,6 +3636,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn detect_document_mode() {
+        let mode = |html: &str| {
+            let tokenizer = Tokenizer::new(html.chars());
+            let document = TreeBuilder::default(tokenizer).run();
+            document.as_document().get_mode()
+        };
+
+        assert_eq!(mode("<!DOCTYPE html><p>"), QuirksMode::NoQuirks);
+        assert_eq!(mode("<p>"), QuirksMode::Quirks);
+        assert_eq!(
+            mode(r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN">"#),
+            QuirksMode::Quirks
+        );
+        assert_eq!(
+            mode(
+                r#"<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 4.01 Transitional//EN" "http://www.w3.org/TR/html4/loose.dtd">"#
+            ),
+            QuirksMode::LimitedQuirks
+        );
+        assert_eq!(
+            mode(
+                r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">"#
+            ),
+            QuirksMode::LimitedQuirks
+        );
+    }
+
     #[test]
     fn handle_parsing_children_correctly() {
         let html = "<div><div></div><div></div><div></div></div>";
@@ -3329,4 +3699,90 @@ mod test {
             "This is a link".to_string()
         );
     }
+
+    #[test]
+    fn parse_inline_svg() {
+        let html = "<div><svg viewbox=\"0 0 10 10\"><foreignobject><p>Text</p></foreignobject>\
+            <clippath><rect/></clippath><![CDATA[x<y]]></svg><p>After</p></div>";
+        let tokenizer = Tokenizer::new(html.chars());
+        let document = TreeBuilder::default(tokenizer).run();
+
+        let html = document.first_child().unwrap();
+        let body = html.last_child().unwrap();
+        let div = body.first_child().unwrap();
+        let svg = NodePtr(div.first_child().unwrap());
+        assert_eq!(svg.as_element().namespace(), Namespace::Svg);
+        assert_eq!(
+            svg.as_element().attributes().borrow().get_str("viewBox"),
+            "0 0 10 10"
+        );
+
+        let foreign_object = NodePtr(svg.first_child().unwrap());
+        assert_eq!(foreign_object.as_element().tag_name(), "foreignObject");
+        let p = foreign_object.first_child().unwrap();
+        assert!(p.as_element().is_html("p"));
+
+        let clip_path = NodePtr(foreign_object.next_sibling().unwrap());
+        assert_eq!(clip_path.as_element().tag_name(), "clipPath");
+        let rect = clip_path.first_child().unwrap();
+        assert_eq!(rect.as_element().namespace(), Namespace::Svg);
+        assert!(rect.first_child().is_none());
+        let cdata = clip_path.next_sibling().unwrap();
+        assert_eq!(cdata.as_text().get_data(), "x<y");
+
+        let after = NodePtr(svg.next_sibling().unwrap());
+        assert!(after.as_element().is_html("p"));
+    }
+
+    #[test]
+    fn break_out_of_foreign_content() {
+        let html = "<math><mi>x</mi><mtext><b>y</b></mtext></math><svg><g><div>z</div>";
+        let tokenizer = Tokenizer::new(html.chars());
+        let document = TreeBuilder::default(tokenizer).run();
+
+        let html = document.first_child().unwrap();
+        let body = html.last_child().unwrap();
+        let math = NodePtr(body.first_child().unwrap());
+        assert_eq!(math.as_element().namespace(), Namespace::MathML);
+        let mtext = math.last_child().unwrap();
+        let b = mtext.first_child().unwrap();
+        assert!(b.as_element().is_html("b"));
+
+        let svg = NodePtr(math.next_sibling().unwrap());
+        assert_eq!(svg.as_element().namespace(), Namespace::Svg);
+        let div = NodePtr(svg.next_sibling().unwrap());
+        assert!(div.as_element().is_html("div"));
+        assert_eq!(div.child_text_content(), "z");
+    }
+
+    #[test]
+    fn stop_parsing_when_cancelled() {
+        let document = NodePtr(TreeNode::new(Node::new(
+            NodeData::Document(Document::new()),
+        )));
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        document
+            .as_document()
+            .set_load_cancellation(Some(cancellation));
+
+        let tokenizer = Tokenizer::new("<div>Never parsed</div>".chars());
+        let document = TreeBuilder::new(tokenizer, document).run();
+
+        assert!(document.first_child().is_none());
+        assert_eq!(
+            document.as_document().lifecycle().ready_state(),
+            DocumentReadyState::Loading
+        );
+    }
+
+    #[test]
+    fn parsed_document_is_interactive() {
+        let tokenizer = Tokenizer::new("<p>Parsed".chars());
+        let document = TreeBuilder::default(tokenizer).run();
+        assert_eq!(
+            document.as_document().lifecycle().ready_state(),
+            DocumentReadyState::Interactive
+        );
+    }
 }
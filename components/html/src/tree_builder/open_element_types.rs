@@ -1,3 +1,5 @@
+use dom::element::{Element, Namespace};
+
 const SPECIAL_TAGS: [&str; 82] = [
     "address",
     "applet",
@@ -83,6 +85,45 @@ const SPECIAL_TAGS: [&str; 82] = [
     "xmp",
 ];
 
-pub fn is_special_element(name: &str) -> bool {
-    SPECIAL_TAGS.contains(&name)
+/// https://html.spec.whatwg.org/multipage/parsing.html#special
+pub fn is_special_element(element: &Element) -> bool {
+    match element.namespace() {
+        Namespace::Html => SPECIAL_TAGS.contains(&element.tag_name().as_str()),
+        _ => is_foreign_scope_boundary(element),
+    }
+}
+
+/// Foreign elements that bound the scopes of the stack of open elements
+pub fn is_foreign_scope_boundary(element: &Element) -> bool {
+    is_mathml_text_integration_point(element)
+        || is_html_integration_point(element)
+        || (element.namespace() == Namespace::MathML && element.tag_name() == "annotation-xml")
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#mathml-text-integration-point
+pub fn is_mathml_text_integration_point(element: &Element) -> bool {
+    element.namespace() == Namespace::MathML
+        && matches!(
+            element.tag_name().as_str(),
+            "mi" | "mo" | "mn" | "ms" | "mtext"
+        )
+}
+
+/// https://html.spec.whatwg.org/multipage/parsing.html#html-integration-point
+pub fn is_html_integration_point(element: &Element) -> bool {
+    match element.namespace() {
+        Namespace::MathML if element.tag_name() == "annotation-xml" => {
+            let encoding = element
+                .attributes()
+                .borrow()
+                .get_str("encoding")
+                .to_ascii_lowercase();
+            encoding == "text/html" || encoding == "application/xhtml+xml"
+        }
+        Namespace::Svg => matches!(
+            element.tag_name().as_str(),
+            "foreignObject" | "desc" | "title"
+        ),
+        _ => false,
+    }
 }
@@ -1,6 +1,8 @@
 use dom::node::NodePtr;
 
+use super::open_element_types::is_foreign_scope_boundary;
 use super::Element;
+use dom::element::Namespace;
 use std::{
     ops::{Deref, DerefMut},
     rc::Rc,
@@ -45,7 +47,7 @@ impl StackOfOpenElements {
     pub fn last_element_with_tag_name(&self, tag_name: &str) -> Option<(&NodePtr, usize)> {
         for (i, node) in self.0.iter().rev().enumerate() {
             let element = node.as_element();
-            if element.tag_name() == tag_name {
+            if element.is_html(tag_name) {
                 return Some((&node, i));
             }
         }
@@ -55,7 +57,7 @@ impl StackOfOpenElements {
     pub fn pop_until(&mut self, tag_name: &str) {
         while let Some(node) = self.current_node() {
             let element = node.as_element();
-            if element.tag_name() == tag_name {
+            if element.is_html(tag_name) {
                 self.0.pop();
                 break;
             }
@@ -113,11 +115,11 @@ impl StackOfOpenElements {
     pub fn has_element_name_in_specific_scope(&self, target: &str, list: Vec<&str>) -> bool {
         for node in self.0.iter().rev() {
             let element = node.as_element();
-            if element.tag_name() == target {
+            if element.is_html(target) {
                 return true;
             }
 
-            if list.contains(&element.tag_name().as_str()) {
+            if is_scope_boundary(element, &list) {
                 return false;
             }
         }
@@ -167,7 +169,7 @@ impl StackOfOpenElements {
 
             let element = node.as_element();
 
-            if list.contains(&element.tag_name().as_str()) {
+            if is_scope_boundary(element, &list) {
                 return false;
             }
         }
@@ -181,7 +183,7 @@ impl StackOfOpenElements {
     pub fn contains(&self, tag_name: &str) -> bool {
         self.any(|node| {
             let element = node.as_element();
-            if element.tag_name() == tag_name {
+            if element.is_html(tag_name) {
                 return true;
             }
             return false;
@@ -196,3 +198,10 @@ impl StackOfOpenElements {
         self.0.len()
     }
 }
+
+fn is_scope_boundary(element: &Element, list: &[&str]) -> bool {
+    match element.namespace() {
+        Namespace::Html => list.contains(&element.tag_name().as_str()),
+        _ => is_foreign_scope_boundary(element),
+    }
+}
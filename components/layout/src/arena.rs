@@ -0,0 +1,466 @@
+//! Storage of layout trees. The boxes of a tree are allocated next to each
+//! other in an arena and refer to each other by index, so that layout passes
+//! walking the tree touch few cache lines and the tree is freed at once.
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Debug,
+    mem::size_of,
+    ops::Deref,
+    rc::{Rc, Weak},
+};
+
+use crate::layout_box::LayoutBox;
+
+/// Number of boxes in a chunk of an arena
+const CHUNK_SIZE: usize = 256;
+
+thread_local! {
+    /// Number of boxes alive on the thread, in every arena
+    static LIVE_BOXES: Cell<usize> = Cell::new(0);
+}
+
+/// The boxes of a layout tree, including the boxes detached from it. The
+/// boxes live as long as any pointer to one of them.
+pub struct LayoutArena {
+    /// Each chunk is allocated with room for `CHUNK_SIZE` boxes and is never
+    /// pushed to past that, so its buffer is never reallocated. Boxes are
+    /// only dropped with the arena. A box therefore keeps its address while
+    /// the arena is borrowed, even while more boxes are allocated, which is
+    /// what makes the references returned by `node` sound.
+    chunks: RefCell<Vec<Vec<ArenaNode>>>,
+}
+
+struct ArenaNode {
+    data: LayoutBox,
+    parent: Cell<Option<u32>>,
+    first_child: Cell<Option<u32>>,
+    last_child: Cell<Option<u32>>,
+    next_sibling: Cell<Option<u32>>,
+    prev_sibling: Cell<Option<u32>>,
+}
+
+impl LayoutArena {
+    pub fn new() -> Rc<Self> {
+        Rc::new(Self {
+            chunks: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Allocate a box, detached from the tree
+    pub fn alloc(self: &Rc<Self>, data: LayoutBox) -> LayoutBoxPtr {
+        let mut chunks = self.chunks.borrow_mut();
+        // The capacity of a chunk may be larger than asked for, but boxes
+        // are found by index assuming `CHUNK_SIZE` boxes per chunk
+        if chunks
+            .last()
+            .map_or(true, |chunk| chunk.len() == CHUNK_SIZE)
+        {
+            chunks.push(Vec::with_capacity(CHUNK_SIZE));
+        }
+        let index = (chunks.len() - 1) * CHUNK_SIZE + chunks.last().unwrap().len();
+        chunks.last_mut().unwrap().push(ArenaNode {
+            data,
+            parent: Cell::new(None),
+            first_child: Cell::new(None),
+            last_child: Cell::new(None),
+            next_sibling: Cell::new(None),
+            prev_sibling: Cell::new(None),
+        });
+        LIVE_BOXES.with(|count| count.set(count.get() + 1));
+        LayoutBoxPtr {
+            arena: self.clone(),
+            index: index as u32,
+        }
+    }
+
+    /// Number of boxes allocated in the arena
+    pub fn len(&self) -> usize {
+        let chunks = self.chunks.borrow();
+        chunks.iter().map(|chunk| chunk.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Size of the chunks of the arena, in bytes
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks.borrow().len() * CHUNK_SIZE * size_of::<ArenaNode>()
+    }
+
+    /// Every box of the arena, in allocation order
+    pub fn boxes(self: &Rc<Self>) -> impl Iterator<Item = LayoutBoxPtr> + '_ {
+        (0..self.len()).map(move |index| LayoutBoxPtr {
+            arena: self.clone(),
+            index: index as u32,
+        })
+    }
+
+    /// Release the references the boxes hold to each other and to the
+    /// trees of nested documents, so that the arena is freed once the last
+    /// pointer to one of its boxes outside of the tree is dropped
+    pub fn teardown(self: &Rc<Self>) {
+        for layout_box in self.boxes() {
+            layout_box.release_references();
+        }
+    }
+
+    /// Number of boxes alive on the current thread, to find trees that are
+    /// never freed
+    pub fn live_count() -> usize {
+        LIVE_BOXES.with(|count| count.get())
+    }
+
+    fn node(&self, index: u32) -> &ArenaNode {
+        let chunks = self.chunks.borrow();
+        let chunk = &chunks[index as usize / CHUNK_SIZE];
+        let offset = index as usize % CHUNK_SIZE;
+        assert!(offset < chunk.len(), "No box at index {}", index);
+        // SAFETY: the box stays at this address for as long as `self` is
+        // borrowed, see `chunks`. The pointer is taken with `as_ptr`, which
+        // doesn't borrow the other boxes of the chunk that `alloc` may push
+        // next to this one.
+        unsafe { &*chunk.as_ptr().add(offset) }
+    }
+}
+
+impl Drop for LayoutArena {
+    fn drop(&mut self) {
+        let len = self.len();
+        LIVE_BOXES.with(|count| count.set(count.get() - len));
+    }
+}
+
+/// A box of a layout tree, which keeps the arena of the tree alive
+pub struct LayoutBoxPtr {
+    arena: Rc<LayoutArena>,
+    index: u32,
+}
+
+/// A box of a layout tree that doesn't keep the tree alive
+pub struct WeakLayoutBoxPtr {
+    arena: Weak<LayoutArena>,
+    index: u32,
+}
+
+impl LayoutBoxPtr {
+    /// Allocate a box in a new arena, as the root of a new tree
+    pub fn new(data: LayoutBox) -> Self {
+        LayoutArena::new().alloc(data)
+    }
+
+    /// Allocate a box in the arena of this box, so that it can be inserted
+    /// in its tree
+    pub fn new_box(&self, data: LayoutBox) -> Self {
+        self.arena.alloc(data)
+    }
+
+    pub fn arena(&self) -> &Rc<LayoutArena> {
+        &self.arena
+    }
+
+    pub fn downgrade(&self) -> WeakLayoutBoxPtr {
+        WeakLayoutBoxPtr {
+            arena: Rc::downgrade(&self.arena),
+            index: self.index,
+        }
+    }
+
+    /// Check if both pointers point to the same box
+    pub fn ptr_eq(&self, other: &LayoutBoxPtr) -> bool {
+        self.index == other.index && Rc::ptr_eq(&self.arena, &other.arena)
+    }
+
+    fn links(&self) -> &ArenaNode {
+        self.arena.node(self.index)
+    }
+
+    fn at(&self, index: Option<u32>) -> Option<LayoutBoxPtr> {
+        index.map(|index| LayoutBoxPtr {
+            arena: self.arena.clone(),
+            index,
+        })
+    }
+
+    /// First child of the box
+    pub fn first_child(&self) -> Option<LayoutBoxPtr> {
+        self.at(self.links().first_child.get())
+    }
+
+    /// Last child of the box
+    pub fn last_child(&self) -> Option<LayoutBoxPtr> {
+        self.at(self.links().last_child.get())
+    }
+
+    /// Next sibling of the box
+    pub fn next_sibling(&self) -> Option<LayoutBoxPtr> {
+        self.at(self.links().next_sibling.get())
+    }
+
+    /// Previous sibling of the box
+    pub fn prev_sibling(&self) -> Option<LayoutBoxPtr> {
+        self.at(self.links().prev_sibling.get())
+    }
+
+    /// Parent of the box
+    pub fn parent(&self) -> Option<LayoutBoxPtr> {
+        self.at(self.links().parent.get())
+    }
+
+    /// Detach box from the parent
+    pub fn detach(&self) {
+        let node = self.links();
+        let prev_sibling = node.prev_sibling.take();
+        let next_sibling = node.next_sibling.take();
+        if let Some(prev_sibling) = self.at(prev_sibling) {
+            prev_sibling.links().next_sibling.set(next_sibling);
+        }
+        if let Some(next_sibling) = self.at(next_sibling) {
+            next_sibling.links().prev_sibling.set(prev_sibling);
+        }
+        if let Some(parent) = self.at(node.parent.take()) {
+            let parent = parent.links();
+            if parent.first_child.get() == Some(self.index) {
+                parent.first_child.set(next_sibling);
+            }
+            if parent.last_child.get() == Some(self.index) {
+                parent.last_child.set(prev_sibling);
+            }
+        }
+    }
+
+    pub fn for_each_child<F>(&self, mut callback: F)
+    where
+        F: FnMut(LayoutBoxPtr),
+    {
+        let mut maybe_child = self.first_child();
+        while let Some(child) = maybe_child {
+            callback(child.clone());
+            maybe_child = child.next_sibling();
+        }
+    }
+
+    pub fn find_first_ancestor<F>(&self, callback: F) -> Option<LayoutBoxPtr>
+    where
+        F: Fn(LayoutBoxPtr) -> bool,
+    {
+        let mut parent = self.parent();
+        while let Some(node) = parent {
+            if callback(node.clone()) {
+                return Some(node);
+            }
+            parent = node.parent();
+        }
+        None
+    }
+
+    pub fn iterate_children(&self) -> ChildrenIterator {
+        ChildrenIterator {
+            front: self.first_child(),
+            back: self.last_child(),
+        }
+    }
+
+    pub fn has_no_child(&self) -> bool {
+        self.links().first_child.get().is_none()
+    }
+
+    pub fn children_count(&self) -> usize {
+        self.iterate_children().count()
+    }
+
+    pub fn nth_child(&self, n: usize) -> Option<LayoutBoxPtr> {
+        self.iterate_children().nth(n)
+    }
+
+    /// Move the children of the box to the end of the children of the new
+    /// parent
+    pub fn transfer_children_to_node(&self, new_parent: LayoutBoxPtr) {
+        while let Some(child) = self.first_child() {
+            new_parent.append_child(child);
+        }
+    }
+
+    /// Append a child box to the box, detaching it from its parent
+    pub fn append_child(&self, child: LayoutBoxPtr) {
+        self.insert_before(child, None);
+    }
+
+    /// Insert a child box before the reference child, or at the end of the
+    /// children when there is none
+    pub fn insert_before(&self, child: LayoutBoxPtr, ref_child: Option<LayoutBoxPtr>) {
+        assert!(
+            Rc::ptr_eq(&self.arena, &child.arena),
+            "Cannot insert a box of another layout tree: {:?}",
+            child
+        );
+        if child.ptr_eq(self)
+            || self
+                .find_first_ancestor(|parent| parent.ptr_eq(&child))
+                .is_some()
+        {
+            panic!("Cannot append parent: {:?}", child);
+        }
+        child.detach();
+
+        let node = self.links();
+        let child_node = child.links();
+        child_node.parent.set(Some(self.index));
+        let prev_sibling = match &ref_child {
+            Some(ref_child) => {
+                let ref_node = ref_child.links();
+                let prev_sibling = ref_node.prev_sibling.replace(Some(child.index));
+                child_node.next_sibling.set(Some(ref_child.index));
+                prev_sibling
+            }
+            None => node.last_child.replace(Some(child.index)),
+        };
+        child_node.prev_sibling.set(prev_sibling);
+        match self.at(prev_sibling) {
+            Some(prev_sibling) => prev_sibling.links().next_sibling.set(Some(child.index)),
+            None => node.first_child.set(Some(child.index)),
+        }
+    }
+
+    /// Release the references the boxes of the tree hold to each other, see
+    /// `LayoutArena::teardown`
+    pub fn teardown(&self) {
+        self.arena.teardown();
+    }
+}
+
+impl Deref for LayoutBoxPtr {
+    type Target = LayoutBox;
+    fn deref(&self) -> &Self::Target {
+        &self.links().data
+    }
+}
+
+impl Clone for LayoutBoxPtr {
+    fn clone(&self) -> Self {
+        LayoutBoxPtr {
+            arena: self.arena.clone(),
+            index: self.index,
+        }
+    }
+}
+
+impl Debug for LayoutBoxPtr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.links().data)
+    }
+}
+
+impl WeakLayoutBoxPtr {
+    pub fn upgrade(&self) -> Option<LayoutBoxPtr> {
+        self.arena.upgrade().map(|arena| LayoutBoxPtr {
+            arena,
+            index: self.index,
+        })
+    }
+}
+
+impl Debug for WeakLayoutBoxPtr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
+
+/// The children of a box, from the first or from the last
+pub struct ChildrenIterator {
+    front: Option<LayoutBoxPtr>,
+    back: Option<LayoutBoxPtr>,
+}
+
+impl Iterator for ChildrenIterator {
+    type Item = LayoutBoxPtr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.front.take()?;
+        if self.back.as_ref().is_some_and(|back| back.ptr_eq(&current)) {
+            self.back = None;
+        } else {
+            self.front = current.next_sibling();
+        }
+        Some(current)
+    }
+}
+
+impl DoubleEndedIterator for ChildrenIterator {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let current = self.back.take()?;
+        if self
+            .front
+            .as_ref()
+            .is_some_and(|front| front.ptr_eq(&current))
+        {
+            self.front = None;
+        } else {
+            self.back = current.prev_sibling();
+        }
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout_box::BoxData;
+
+    fn new_box(arena: &Rc<LayoutArena>) -> LayoutBoxPtr {
+        arena.alloc(LayoutBox::new_anonymous(BoxData::block_box()))
+    }
+
+    #[test]
+    fn test_link_boxes() {
+        let arena = LayoutArena::new();
+        let parent = new_box(&arena);
+        let (first, second, third) = (new_box(&arena), new_box(&arena), new_box(&arena));
+
+        parent.append_child(second.clone());
+        parent.insert_before(first.clone(), Some(second.clone()));
+        parent.append_child(third.clone());
+        let children = |parent: &LayoutBoxPtr| parent.iterate_children().collect::<Vec<_>>();
+        assert_eq!(children(&parent).len(), 3);
+        assert!(children(&parent)[0].ptr_eq(&first));
+        assert!(parent
+            .iterate_children()
+            .rev()
+            .next()
+            .unwrap()
+            .ptr_eq(&third));
+        assert!(second.parent().unwrap().ptr_eq(&parent));
+        assert!(second.prev_sibling().unwrap().ptr_eq(&first));
+
+        let wrapper = new_box(&arena);
+        parent.transfer_children_to_node(wrapper.clone());
+        parent.append_child(wrapper.clone());
+        assert!(parent.first_child().unwrap().ptr_eq(&wrapper));
+        assert!(third.parent().unwrap().ptr_eq(&wrapper));
+        assert_eq!(wrapper.children_count(), 3);
+
+        third.detach();
+        second.detach();
+        first.detach();
+        assert!(wrapper.has_no_child());
+        assert!(wrapper.last_child().is_none());
+        assert_eq!(arena.len(), 5);
+    }
+
+    #[test]
+    fn test_free_arena() {
+        let live_before = LayoutArena::live_count();
+        let arena = LayoutArena::new();
+        let boxes = (0..CHUNK_SIZE + 1)
+            .map(|_| new_box(&arena))
+            .collect::<Vec<_>>();
+        let weak_box = boxes[0].downgrade();
+        assert_eq!(LayoutArena::live_count(), live_before + CHUNK_SIZE + 1);
+
+        drop(arena);
+        drop(boxes);
+        assert!(weak_box.upgrade().is_none());
+        assert_eq!(LayoutArena::live_count(), live_before);
+    }
+}
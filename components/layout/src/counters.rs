@@ -0,0 +1,140 @@
+use dom::node::NodePtr;
+use style_types::{
+    values::{
+        display::Display,
+        prelude::{ContentItem, CounterList, ListStyleType},
+    },
+    Property, Value,
+};
+
+/// Name of the counter that numbers list items
+const LIST_ITEM_COUNTER: &str = "list-item";
+
+struct Counter {
+    name: String,
+    value: i32,
+    /// Depth in the tree of the element that created the counter
+    depth: usize,
+}
+
+/// The CSS counters in scope while the box tree is built in tree order.
+///
+/// A counter created by an element is in scope for the element, its
+/// descendants, its following siblings and their descendants.
+/// https://www.w3.org/TR/css-lists-3/#creating-a-counter
+#[derive(Default)]
+pub struct CounterScopes {
+    counters: Vec<Counter>,
+}
+
+impl CounterScopes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the `counter-reset` and `counter-increment` of an element at
+    /// `depth`. List items also increment the `list-item` counter.
+    pub fn update(&mut self, node: &NodePtr, depth: usize) {
+        for (name, value) in counter_list(node, &Property::CounterReset) {
+            self.reset(name, value, depth);
+        }
+
+        let increments = counter_list(node, &Property::CounterIncrement);
+        let increments_list_item = increments.iter().any(|(name, _)| name == LIST_ITEM_COUNTER);
+        for (name, value) in increments {
+            self.increment(name, value, depth);
+        }
+        if is_list_item(node) && !increments_list_item {
+            self.increment(LIST_ITEM_COUNTER.to_string(), 1, depth);
+        }
+    }
+
+    /// Remove the counters created by the children of an element at `depth`
+    /// once all of its children are built
+    pub fn leave(&mut self, depth: usize) {
+        self.counters.retain(|counter| counter.depth <= depth);
+    }
+
+    /// Text of the strings and counters of a `content` value
+    pub fn resolve(&self, items: &[ContentItem]) -> String {
+        let mut text = String::new();
+        for item in items {
+            match item {
+                ContentItem::Text(value) => text.push_str(value),
+                ContentItem::Counter { name, style } => {
+                    let value = self.innermost(name).map(|c| c.value).unwrap_or(0);
+                    text.push_str(&format_counter(value, style));
+                }
+                ContentItem::Counters {
+                    name,
+                    separator,
+                    style,
+                } => {
+                    let values = self
+                        .counters
+                        .iter()
+                        .filter(|counter| counter.name == *name)
+                        .map(|counter| format_counter(counter.value, style))
+                        .collect::<Vec<String>>();
+                    if values.is_empty() {
+                        text.push_str(&format_counter(0, style));
+                    } else {
+                        text.push_str(&values.join(separator));
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    fn reset(&mut self, name: String, value: i32, depth: usize) {
+        // A reset replaces the counter of the same name created by a sibling
+        self.counters
+            .retain(|counter| !(counter.name == name && counter.depth == depth));
+        self.counters.push(Counter { name, value, depth });
+    }
+
+    fn increment(&mut self, name: String, value: i32, depth: usize) {
+        // Incrementing a counter that doesn't exist creates it
+        if self.innermost(&name).is_none() {
+            self.reset(name.clone(), 0, depth);
+        }
+        if let Some(counter) = self.counters.iter_mut().rev().find(|c| c.name == name) {
+            counter.value += value;
+        }
+    }
+
+    fn innermost(&self, name: &str) -> Option<&Counter> {
+        self.counters
+            .iter()
+            .rev()
+            .find(|counter| counter.name == name)
+    }
+}
+
+fn counter_list(node: &NodePtr, property: &Property) -> Vec<(String, i32)> {
+    match node.computed_styles().get(property) {
+        Some(Value::CounterList(CounterList(counters))) => counters.clone(),
+        _ => Vec::new(),
+    }
+}
+
+fn is_list_item(node: &NodePtr) -> bool {
+    matches!(
+        node.computed_styles().get(&Property::Display),
+        Some(Value::Display(Display::ListItem(..)))
+    )
+}
+
+/// Represent the value of a counter in a counter style
+fn format_counter(value: i32, style: &ListStyleType) -> String {
+    match style {
+        ListStyleType::Decimal => value.to_string(),
+        ListStyleType::Disc => "•".to_string(),
+        ListStyleType::Circle => "◦".to_string(),
+        ListStyleType::Square => "▪".to_string(),
+        ListStyleType::DisclosureOpen => "▾".to_string(),
+        ListStyleType::DisclosureClosed => "▸".to_string(),
+        ListStyleType::None => String::new(),
+    }
+}
@@ -0,0 +1,100 @@
+use dom::node::NodePtr;
+use shared::primitive::{Rect, Size};
+use style_types::values::prelude::WritingMode;
+
+use crate::{
+    formatting_context::{establish_context, FormattingContextType, LayoutContext},
+    layout_box::{BoxData, LayoutBox, LayoutBoxPtr},
+    replaced::content_document,
+    scroll::clamp_scroll_offsets,
+};
+
+/// Build and lay out the layout tree of the document for a viewport of
+/// `size` CSS pixels
+pub fn layout_document(document_node: NodePtr, size: &Size) -> Option<LayoutBoxPtr> {
+    let quirks_mode = document_node.as_document().get_mode();
+    let layout_tree = crate::tree_builder::TreeBuilder::new().build(document_node);
+
+    if let Some(root) = &layout_tree {
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., size.width, size.height),
+            quirks_mode,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        initial_block_box.append_child(root.clone());
+
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+        clamp_scroll_offsets(root);
+    }
+
+    layout_tree
+}
+
+/// Lay out the document loaded in an iframe box. The content box of the
+/// iframe is the viewport of its document.
+pub fn layout_nested_document(layout_box: &LayoutBoxPtr) {
+    let document = match layout_box.node().as_ref().and_then(content_document) {
+        Some(document) => document,
+        None => return,
+    };
+    let nested_layout = layout_document(document, &layout_box.content_size());
+    layout_box.nested_document.replace(nested_layout);
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::elements::ElementData;
+    use test_utils::dom_creator::{document, element};
+
+    use super::*;
+    use crate::utils::{build_tree, SHARED_CSS};
+
+    #[test]
+    fn test_layout_iframes() {
+        let nested_document = document();
+        let nested_root = element(
+            "html",
+            nested_document.clone(),
+            vec![element("div", nested_document.clone(), vec![])],
+        );
+        build_tree(nested_root, SHARED_CSS);
+
+        let document = document();
+        let sized = element("iframe", document.clone(), vec![]);
+        sized.as_element().set_attribute("width", "200");
+        sized.as_element().set_attribute("height", "100");
+        if let ElementData::IFrame(iframe) = sized.as_element().data() {
+            iframe.set_content_document(Some(nested_document));
+        }
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![
+                sized,
+                element("iframe", document.clone(), vec![]),
+                element("iframe#styled", document.clone(), vec![]),
+            ],
+        );
+        let css = format!("{} #styled {{ width: 50px; height: 40px; }}", SHARED_CSS);
+        build_tree(dom, &css);
+
+        let root = layout_document(document, &Size::new(800., 600.)).unwrap();
+        let iframes = root.iterate_children().collect::<Vec<LayoutBoxPtr>>();
+
+        assert_eq!(iframes[0].content_size(), Size::new(200., 100.));
+        assert_eq!(iframes[1].content_size(), Size::new(300., 150.));
+        assert_eq!(iframes[2].content_size(), Size::new(50., 40.));
+
+        let nested_root = iframes[0].nested_document().unwrap();
+        assert_eq!(nested_root.content_size().width, 200.);
+        assert!(iframes[1].nested_document().is_none());
+    }
+}
@@ -0,0 +1,405 @@
+//! Reordering of line fragments with the Unicode Bidirectional Algorithm
+//! https://www.unicode.org/reports/tr9/
+
+use std::ops::Range;
+
+use gfx::TextMeasure;
+use shared::primitive::{Point, Size};
+use style_types::{
+    values::prelude::{Direction, UnicodeBidi},
+    Property, Value,
+};
+use unicode_bidi::{BidiInfo, Level};
+
+use super::line_box::{LineBox, LineFragment, LineFragmentData};
+use crate::layout_box::LayoutBoxPtr;
+use crate::text_fragments::{font_of, TextSpacing};
+
+const LRE: char = '\u{202A}';
+const RLE: char = '\u{202B}';
+const PDF: char = '\u{202C}';
+const LRO: char = '\u{202D}';
+const RLO: char = '\u{202E}';
+const LRI: char = '\u{2066}';
+const RLI: char = '\u{2067}';
+const FSI: char = '\u{2068}';
+const PDI: char = '\u{2069}';
+/// Stands in for atomic inlines and markers, which are neutral
+const OBJECT_REPLACEMENT: char = '\u{FFFC}';
+
+/// Direction of the block that lays out the lines
+pub fn direction_of(block: &LayoutBoxPtr) -> Direction {
+    match block
+        .get_non_anonymous_parent()
+        .node()
+        .map(|node| node.get_style(&Property::Direction))
+    {
+        Some(Value::Direction(direction)) => direction,
+        _ => Direction::Ltr,
+    }
+}
+
+/// A part of a fragment at a single embedding level
+struct Piece {
+    fragment: usize,
+    /// Bytes of the fragment text, or `None` for boxes and markers
+    range: Option<Range<usize>>,
+    level: Level,
+}
+
+/// Reorder the fragments of a line from logical to visual order. Text
+/// fragments that mix directions are split, and right-to-left text is
+/// reversed so it can be painted from left to right.
+///
+/// Levels are resolved for each line rather than for the whole paragraph.
+pub fn reorder_line(line: &mut LineBox, block: &LayoutBoxPtr) {
+    if line.fragments.is_empty() {
+        return;
+    }
+
+    let (text, fragment_ranges) = line_text(line, block);
+    let para_level = match paragraph_direction(block) {
+        Some(Direction::Rtl) => Some(Level::rtl()),
+        Some(Direction::Ltr) => Some(Level::ltr()),
+        None => None,
+    };
+    let bidi_info = BidiInfo::new(&text, para_level);
+    if !bidi_info.has_rtl() {
+        return;
+    }
+    let paragraph = &bidi_info.paragraphs[0];
+    let levels = bidi_info.reordered_levels(paragraph, 0..text.len());
+
+    let mut pieces = Vec::new();
+    for (index, fragment) in line.fragments.iter().enumerate() {
+        let range = fragment_ranges[index].clone();
+        match &fragment.data {
+            LineFragmentData::Text(_, content) => {
+                for (start, ch) in content.char_indices() {
+                    let level = levels[range.start + start];
+                    let end = start + ch.len_utf8();
+                    match pieces.last_mut() {
+                        Some(Piece {
+                            fragment,
+                            range: Some(piece_range),
+                            level: piece_level,
+                        }) if *fragment == index && *piece_level == level => {
+                            piece_range.end = end;
+                        }
+                        _ => pieces.push(Piece {
+                            fragment: index,
+                            range: Some(start..end),
+                            level,
+                        }),
+                    }
+                }
+            }
+            _ => pieces.push(Piece {
+                fragment: index,
+                range: None,
+                level: levels[range.start],
+            }),
+        }
+    }
+
+    reverse_levels(&mut pieces);
+
+    let start_x = line
+        .fragments
+        .iter()
+        .map(|fragment| fragment.offset.x)
+        .fold(f32::INFINITY, f32::min);
+    let mut fragments = line
+        .fragments
+        .drain(..)
+        .map(Some)
+        .collect::<Vec<Option<LineFragment>>>();
+    let mut text_measurer = TextMeasure::new();
+    let mut x = start_x;
+
+    for piece in pieces {
+        let fragment = match &fragments[piece.fragment] {
+            Some(fragment) => fragment,
+            None => continue,
+        };
+        match (&fragment.data, piece.range) {
+            (LineFragmentData::Text(layout_box, content), Some(range)) => {
+                let mut piece_text = content[range.clone()].to_string();
+                let width = if range.len() == content.len() {
+                    fragment.size.width
+                } else {
+                    measure_text(layout_box, &piece_text, &mut text_measurer)
+                };
+                if piece.level.is_rtl() {
+                    piece_text = piece_text.chars().rev().map(mirror).collect();
+                }
+                let piece_fragment = LineFragment::new_text(
+                    layout_box.clone(),
+                    piece_text,
+                    Point::new(x, fragment.offset.y),
+                    Size::new(width, fragment.size.height),
+                )
+                .with_baseline(fragment.baseline);
+                x += width;
+                line.fragments.push(piece_fragment);
+            }
+            _ => {
+                let mut fragment = fragments[piece.fragment].take().unwrap();
+                let (margin_left, margin_right) = match &fragment.data {
+                    LineFragmentData::Box(layout_box) => {
+                        let box_model = layout_box.box_model().borrow();
+                        (box_model.margin.left, box_model.margin.right)
+                    }
+                    _ => (0., 0.),
+                };
+                x += margin_left;
+                fragment.offset.x = x;
+                x += fragment.size.width + margin_right;
+                line.fragments.push(fragment);
+            }
+        }
+    }
+}
+
+/// Text of the line with the fragments in logical order. Inline boxes with
+/// `unicode-bidi` are wrapped with the explicit formatting characters that
+/// the CSS property maps to.
+/// https://www.w3.org/TR/css-writing-modes-3/#bidi-control-codes-injection-table
+fn line_text(line: &LineBox, block: &LayoutBoxPtr) -> (String, Vec<Range<usize>>) {
+    let mut text = String::new();
+    let mut ranges = Vec::new();
+    let mut open_boxes: Vec<(LayoutBoxPtr, Vec<char>)> = Vec::new();
+
+    for fragment in &line.fragments {
+        let layout_box = match &fragment.data {
+            LineFragmentData::Box(layout_box) | LineFragmentData::Text(layout_box, _) => {
+                Some(layout_box)
+            }
+            LineFragmentData::Marker(_) => None,
+        };
+        let embeddings = layout_box
+            .map(|layout_box| embeddings_of(layout_box, block))
+            .unwrap_or_default();
+
+        let common = open_boxes
+            .iter()
+            .zip(embeddings.iter())
+            .take_while(|((open, _), (embedding, _))| open.ptr_eq(embedding))
+            .count();
+        while open_boxes.len() > common {
+            let (_, closing) = open_boxes.pop().unwrap();
+            text.extend(closing);
+        }
+        for (layout_box, (opening, closing)) in embeddings.into_iter().skip(common) {
+            text.extend(opening);
+            open_boxes.push((layout_box, closing));
+        }
+
+        let start = text.len();
+        match &fragment.data {
+            LineFragmentData::Text(_, content) => text.push_str(content),
+            _ => text.push(OBJECT_REPLACEMENT),
+        }
+        ranges.push(start..text.len());
+    }
+
+    (text, ranges)
+}
+
+/// Opening and closing formatting characters of an inline box
+type Controls = (Vec<char>, Vec<char>);
+
+/// Explicit formatting characters of the inline ancestors of a box inside
+/// of the block, from the outermost ancestor
+fn embeddings_of(layout_box: &LayoutBoxPtr, block: &LayoutBoxPtr) -> Vec<(LayoutBoxPtr, Controls)> {
+    let mut embeddings = Vec::new();
+    let mut current = layout_box.parent();
+
+    while let Some(ancestor) = current {
+        if ancestor.ptr_eq(block) || ancestor.is_block() {
+            break;
+        }
+        if let Some(node) = ancestor.node() {
+            let is_rtl = matches!(
+                node.get_style(&Property::Direction),
+                Value::Direction(Direction::Rtl)
+            );
+            let (embed, isolate, override_) = match is_rtl {
+                true => (RLE, RLI, RLO),
+                false => (LRE, LRI, LRO),
+            };
+            let controls = match node.get_style(&Property::UnicodeBidi) {
+                Value::UnicodeBidi(UnicodeBidi::Embed) => Some((vec![embed], vec![PDF])),
+                Value::UnicodeBidi(UnicodeBidi::Isolate) => Some((vec![isolate], vec![PDI])),
+                Value::UnicodeBidi(UnicodeBidi::BidiOverride) => Some((vec![override_], vec![PDF])),
+                Value::UnicodeBidi(UnicodeBidi::IsolateOverride) => {
+                    Some((vec![isolate, override_], vec![PDF, PDI]))
+                }
+                Value::UnicodeBidi(UnicodeBidi::Plaintext) => Some((vec![FSI], vec![PDI])),
+                _ => None,
+            };
+            if let Some(controls) = controls {
+                embeddings.push((ancestor.clone(), controls));
+            }
+        }
+        current = ancestor.parent();
+    }
+
+    embeddings.reverse();
+    embeddings
+}
+
+/// Base direction of the lines of the block, or `None` when it is detected
+/// from the first strong character of the text
+fn paragraph_direction(block: &LayoutBoxPtr) -> Option<Direction> {
+    let node = block.get_non_anonymous_parent().node()?;
+    match node.get_style(&Property::UnicodeBidi) {
+        Value::UnicodeBidi(UnicodeBidi::Plaintext) => None,
+        _ => Some(direction_of(block)),
+    }
+}
+
+/// Reverse every sequence of pieces at a level or higher, from the highest
+/// level down to the lowest odd level
+/// https://www.unicode.org/reports/tr9/#L2
+fn reverse_levels(pieces: &mut [Piece]) {
+    let max_level = match pieces.iter().map(|piece| piece.level.number()).max() {
+        Some(level) => level,
+        None => return,
+    };
+    let lowest_odd_level = match pieces
+        .iter()
+        .map(|piece| piece.level.number())
+        .filter(|level| level % 2 == 1)
+        .min()
+    {
+        Some(level) => level,
+        None => return,
+    };
+
+    for level in (lowest_odd_level..=max_level).rev() {
+        let mut index = 0;
+        while index < pieces.len() {
+            if pieces[index].level.number() < level {
+                index += 1;
+                continue;
+            }
+            let start = index;
+            while index < pieces.len() && pieces[index].level.number() >= level {
+                index += 1;
+            }
+            pieces[start..index].reverse();
+        }
+    }
+}
+
+fn measure_text(layout_box: &LayoutBoxPtr, text: &str, text_measurer: &mut TextMeasure) -> f32 {
+    let node = layout_box.node().unwrap();
+    let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+    text_measurer
+        .measure_with_font(text, font_size, font_of(&node))
+        .width
+        + TextSpacing::of(&node).extra_width(text)
+}
+
+/// Characters with a mirrored glyph in right-to-left text
+/// https://www.unicode.org/reports/tr9/#L4
+fn mirror(ch: char) -> char {
+    match ch {
+        '(' => ')',
+        ')' => '(',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '<' => '>',
+        '>' => '<',
+        '«' => '»',
+        '»' => '«',
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::document::QuirksMode;
+    use shared::primitive::Rect;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
+
+    use crate::{
+        formatting_context::{establish_context, FormattingContextType, LayoutContext},
+        utils::{build_tree, SHARED_CSS},
+    };
+
+    use super::*;
+
+    fn layout_line(dom: dom::node::NodePtr, css: &str) -> Vec<(String, f32, f32)> {
+        let root = build_tree(dom, &format!("{}{}", SHARED_CSS, css));
+        root.set_content_width(500.);
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+        root.formatting_context().run(&layout_context, root.clone());
+
+        let lines = root.lines().borrow();
+        assert_eq!(lines.len(), 1);
+        lines[0]
+            .fragments
+            .iter()
+            .filter_map(|fragment| match &fragment.data {
+                LineFragmentData::Text(_, content) => Some((
+                    content.clone(),
+                    fragment.offset.x,
+                    fragment.offset.x + fragment.size.width,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reorder_rtl_paragraph() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![text("abc אבג", document.clone())],
+        );
+        dom.as_element().set_attribute("dir", "rtl");
+
+        let fragments = layout_line(dom, "");
+        let contents = fragments
+            .iter()
+            .map(|(content, _, _)| content.as_str())
+            .collect::<Vec<&str>>();
+        // The Hebrew word is reversed and comes first from the left, the
+        // embedded Latin word keeps its order
+        assert_eq!(contents, vec![" גבא ", "abc"]);
+        // Right-to-left lines start at the right
+        let (_, _, right) = fragments.last().unwrap();
+        assert!((right - 500.).abs() < 1.);
+    }
+
+    #[test]
+    fn test_bidi_override() {
+        let document = document();
+        let span = element(
+            "span",
+            document.clone(),
+            vec![text("abc", document.clone())],
+        );
+        let dom = element("div", document.clone(), vec![span]);
+
+        let fragments = layout_line(dom, "span { direction: rtl; unicode-bidi: bidi-override; }");
+        // The trailing space is reset to the paragraph level
+        assert_eq!(fragments[0].0, "cba");
+        assert_eq!(fragments[1].0, " ");
+        assert_eq!(fragments[0].1, 0.);
+        assert_eq!(fragments[0].2, fragments[1].1);
+    }
+}
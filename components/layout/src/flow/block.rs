@@ -1,11 +1,15 @@
 use crate::{
     box_model::BoxComponent,
+    document::layout_nested_document,
+    flow::multicol::{move_into_columns, ColumnLayout},
     formatting_context::{
         create_independent_formatting_context_if_needed, use_context, BaseFormattingContext,
         FormattingContext, LayoutContext,
     },
     layout_box::LayoutBoxPtr,
+    writing_mode::{flow_relative_style, is_orthogonal_flow_root, to_physical, writing_mode_of},
 };
+use dom::document::QuirksMode;
 use shared::primitive::edge::Edge;
 use std::cell::RefCell;
 use style_types::{values::prelude::Position, Property};
@@ -52,14 +56,18 @@ impl BlockFormattingContext {
 
     fn layout_block_level_children(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
         layout_node.for_each_child(|child| {
-            let child = LayoutBoxPtr(child);
             if child.is_positioned(Position::Absolute) {
                 return;
             }
-            self.compute_width(child.clone());
-            self.place_box_in_flow(child.clone());
-
-            if let Some(independent_formatting_context) =
+            self.compute_width(context, child.clone());
+            self.place_box_in_flow(context, child.clone());
+
+            let is_orthogonal_flow_root = is_orthogonal_flow_root(&child, context.writing_mode);
+            if is_orthogonal_flow_root {
+                self.layout_orthogonal_flow(context, child.clone());
+            } else if child.is_multicol_container() {
+                self.layout_multicol(context, child.clone());
+            } else if let Some(independent_formatting_context) =
                 create_independent_formatting_context_if_needed(child.clone())
             {
                 use_context(independent_formatting_context.clone(), child.clone());
@@ -68,11 +76,20 @@ impl BlockFormattingContext {
                 self.layout_block_level_children(context, child.clone());
             }
 
-            if !child.children_are_inline() {
-                self.compute_height(child.clone());
+            if !child.children_are_inline()
+                && !is_orthogonal_flow_root
+                && !child.is_grid_container()
+                && !child.is_multicol_container()
+            {
+                self.compute_height(context, child.clone());
             }
 
-            child.apply_explicit_sizes();
+            child.apply_explicit_sizes(context.writing_mode);
+            layout_nested_document(&child);
+
+            if context.quirks_mode == QuirksMode::Quirks {
+                self.apply_body_height_quirk(context, child.clone());
+            }
 
             if child.border_box_absolute().height > 0. {
                 self.last_sibling.replace(Some(child.clone()));
@@ -80,8 +97,114 @@ impl BlockFormattingContext {
         });
     }
 
-    fn place_box_in_flow(&self, layout_node: LayoutBoxPtr) {
-        self.apply_vertical_box_model_values(layout_node.clone());
+    /// Lay out the content of a vertical box in a horizontal flow in its
+    /// own writing mode. Its inline size is its height, or the height of
+    /// the viewport if it is auto, and its block size is its width, or the
+    /// extent of its content.
+    /// https://www.w3.org/TR/css-writing-modes-3/#orthogonal-auto
+    fn layout_orthogonal_flow(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        let node = layout_node.node().unwrap();
+        let writing_mode = writing_mode_of(&node);
+        let containing_block = layout_node.containing_block().unwrap().content_size();
+        let edges = layout_node.box_model().borrow().margin_box();
+
+        let computed_height = node.get_style(&Property::Height);
+        let inline_size = if computed_height.is_auto() {
+            context.viewport.height - edges.top - edges.bottom
+        } else {
+            computed_height.to_px(containing_block.height)
+        };
+        layout_node.set_content_width(f32::max(inline_size, 0.));
+        layout_node.set_content_height(0.);
+
+        let flow_context = LayoutContext {
+            viewport: context.viewport.clone(),
+            quirks_mode: context.quirks_mode.clone(),
+            writing_mode,
+        };
+        if let Some(formatting_context) =
+            create_independent_formatting_context_if_needed(layout_node.clone())
+        {
+            use_context(formatting_context.clone(), layout_node.clone());
+            formatting_context.run(&flow_context, layout_node.clone());
+        }
+        if !layout_node.children_are_inline() {
+            let block_size = self.compute_auto_height(layout_node.clone());
+            layout_node.set_content_height(block_size);
+        }
+
+        let computed_width = node.get_style(&Property::Width);
+        if !computed_width.is_auto() {
+            layout_node.set_content_height(computed_width.to_px(containing_block.width));
+        }
+        to_physical(&layout_node, writing_mode);
+    }
+
+    /// Lay out the content of a multi-column container as a strip at the
+    /// width of a column, then split it into columns. Its auto height is
+    /// the height of the balanced columns.
+    /// https://www.w3.org/TR/css-multicol-1/#the-multi-column-model
+    fn layout_multicol(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        let node = layout_node.node().unwrap();
+        let available_width = layout_node.content_size().width;
+        let columns = ColumnLayout::of(&layout_node);
+
+        layout_node.set_content_width(columns.width);
+        if let Some(formatting_context) =
+            create_independent_formatting_context_if_needed(layout_node.clone())
+        {
+            use_context(formatting_context.clone(), layout_node.clone());
+            formatting_context.run(context, layout_node.clone());
+        }
+        if !layout_node.children_are_inline() {
+            let strip_height = self.compute_auto_height(layout_node.clone());
+            layout_node.set_content_height(strip_height);
+        }
+        layout_node.set_content_width(available_width);
+
+        let containing_block = layout_node.containing_block().unwrap().content_size();
+        let computed_height = flow_relative_style(&node, &Property::Height, context.writing_mode);
+        let max_height = match computed_height.is_auto() {
+            true => None,
+            false => Some(computed_height.to_px(containing_block.height)),
+        };
+        let column_height = move_into_columns(&layout_node, &columns, max_height);
+        layout_node.set_content_height(column_height);
+    }
+
+    /// In quirks mode, a `body` with an auto height fills the viewport
+    /// https://quirks.spec.whatwg.org/#the-body-element-fills-the-html-element-quirk
+    fn apply_body_height_quirk(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        if !layout_node.is_body_element() {
+            return;
+        }
+        let has_auto_height = layout_node
+            .node()
+            .map(|node| node.get_style(&Property::Height).is_auto())
+            .unwrap_or(false);
+        if !has_auto_height {
+            return;
+        }
+
+        let html_edges = match layout_node.parent() {
+            Some(parent) => parent.box_model().borrow().margin_box(),
+            None => return,
+        };
+        let body_edges = layout_node.box_model().borrow().margin_box();
+
+        let min_height = context.viewport.height
+            - html_edges.top
+            - html_edges.bottom
+            - body_edges.top
+            - body_edges.bottom;
+
+        if layout_node.content_size().height < min_height {
+            layout_node.set_content_height(min_height);
+        }
+    }
+
+    fn place_box_in_flow(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        self.apply_vertical_box_model_values(context, layout_node.clone());
 
         let box_model = layout_node.box_model().borrow();
         let x = box_model.margin_box().left + box_model.offset.left;
@@ -126,7 +249,7 @@ impl BlockFormattingContext {
         layout_node.set_offset(x, y);
     }
 
-    fn compute_width(&self, layout_node: LayoutBoxPtr) {
+    fn compute_width(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
         let containing_block = layout_node.containing_block().unwrap().content_size();
 
         let node = match layout_node.node() {
@@ -134,13 +257,19 @@ impl BlockFormattingContext {
             _ => return,
         };
 
-        let computed_width = node.get_style(&Property::Width);
-        let computed_margin_left = node.get_style(&Property::MarginLeft);
-        let computed_margin_right = node.get_style(&Property::MarginRight);
-        let computed_border_left = node.get_style(&Property::BorderLeftWidth);
-        let computed_border_right = node.get_style(&Property::BorderRightWidth);
-        let computed_padding_left = node.get_style(&Property::PaddingLeft);
-        let computed_padding_right = node.get_style(&Property::PaddingRight);
+        let computed_width = flow_relative_style(&node, &Property::Width, context.writing_mode);
+        let computed_margin_left =
+            flow_relative_style(&node, &Property::MarginLeft, context.writing_mode);
+        let computed_margin_right =
+            flow_relative_style(&node, &Property::MarginRight, context.writing_mode);
+        let computed_border_left =
+            flow_relative_style(&node, &Property::BorderLeftWidth, context.writing_mode);
+        let computed_border_right =
+            flow_relative_style(&node, &Property::BorderRightWidth, context.writing_mode);
+        let computed_padding_left =
+            flow_relative_style(&node, &Property::PaddingLeft, context.writing_mode);
+        let computed_padding_right =
+            flow_relative_style(&node, &Property::PaddingRight, context.writing_mode);
         let containing_width = containing_block.width;
 
         let box_width = computed_margin_left.to_px(containing_width)
@@ -263,33 +392,31 @@ impl BlockFormattingContext {
         );
     }
 
-    fn apply_vertical_box_model_values(&self, layout_node: LayoutBoxPtr) {
+    fn apply_vertical_box_model_values(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
         if layout_node.is_anonymous() {
             return;
         }
 
         let node = layout_node.node().unwrap();
         let containing_block = layout_node.containing_block().unwrap().content_size();
-        let margin_top = node
-            .get_style(&Property::MarginTop)
-            .to_px(containing_block.width);
-        let margin_bottom = node
-            .get_style(&Property::MarginBottom)
+        let margin_top = flow_relative_style(&node, &Property::MarginTop, context.writing_mode)
             .to_px(containing_block.width);
+        let margin_bottom =
+            flow_relative_style(&node, &Property::MarginBottom, context.writing_mode)
+                .to_px(containing_block.width);
 
-        let padding_top = node
-            .get_style(&Property::PaddingTop)
-            .to_px(containing_block.width);
-        let padding_bottom = node
-            .get_style(&Property::PaddingBottom)
+        let padding_top = flow_relative_style(&node, &Property::PaddingTop, context.writing_mode)
             .to_px(containing_block.width);
+        let padding_bottom =
+            flow_relative_style(&node, &Property::PaddingBottom, context.writing_mode)
+                .to_px(containing_block.width);
 
-        let border_top = node
-            .get_style(&Property::BorderTopWidth)
-            .to_px(containing_block.width);
-        let border_bottom = node
-            .get_style(&Property::BorderBottomWidth)
-            .to_px(containing_block.width);
+        let border_top =
+            flow_relative_style(&node, &Property::BorderTopWidth, context.writing_mode)
+                .to_px(containing_block.width);
+        let border_bottom =
+            flow_relative_style(&node, &Property::BorderBottomWidth, context.writing_mode)
+                .to_px(containing_block.width);
 
         let mut box_model = layout_node.box_model.borrow_mut();
         box_model.set(BoxComponent::Margin, Edge::Top, margin_top);
@@ -300,18 +427,22 @@ impl BlockFormattingContext {
         box_model.set(BoxComponent::Border, Edge::Bottom, border_bottom);
     }
 
-    fn compute_height(&self, layout_node: LayoutBoxPtr) {
-        let height = self.compute_box_height(layout_node.clone());
+    fn compute_height(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        let height = self.compute_box_height(context, layout_node.clone());
         layout_node.set_content_height(height);
     }
 
-    fn compute_box_height(&self, layout_node: LayoutBoxPtr) -> f32 {
+    fn compute_box_height(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) -> f32 {
         if layout_node.is_anonymous() {
             return self.compute_auto_height(layout_node);
         }
 
         let containing_block = layout_node.containing_block().unwrap().content_size();
-        let computed_height = layout_node.node().unwrap().get_style(&Property::Height);
+        let computed_height = flow_relative_style(
+            &layout_node.node().unwrap(),
+            &Property::Height,
+            context.writing_mode,
+        );
 
         if computed_height.is_auto() {
             self.compute_auto_height(layout_node)
@@ -321,19 +452,20 @@ impl BlockFormattingContext {
     }
 
     fn compute_auto_height(&self, layout_node: LayoutBoxPtr) -> f32 {
-        layout_node.iterate_children().fold(0.0, |acc, child| {
-            acc + LayoutBoxPtr(child).margin_box_height()
-        })
+        layout_node
+            .iterate_children()
+            .fold(0.0, |acc, child| acc + child.margin_box_height())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::formatting_context::{establish_context, FormattingContextType, LayoutContext};
-    use crate::layout_box::{BoxData, LayoutBox, LayoutBoxPtr};
+    use crate::layout_box::{BoxData, LayoutBox};
     use crate::utils::*;
+    use dom::document::QuirksMode;
     use shared::primitive::*;
-    use shared::tree_node::TreeNode;
+    use style_types::values::prelude::WritingMode;
     use test_utils::dom_creator::*;
 
     #[test]
@@ -369,16 +501,16 @@ mod tests {
                 width: 500.,
                 height: 300.,
             },
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
         };
 
-        let initial_block_box = LayoutBoxPtr(TreeNode::new(LayoutBox::new_anonymous(
-            BoxData::block_box(),
-        )));
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
         establish_context(
             FormattingContextType::BlockFormattingContext,
             initial_block_box.clone(),
         );
-        initial_block_box.append_child(root.0.clone());
+        initial_block_box.append_child(root.clone());
 
         initial_block_box
             .formatting_context()
@@ -387,4 +519,100 @@ mod tests {
         assert_eq!(root.content_size().height, 40.);
         assert_eq!(root.content_size().width, layout_context.viewport.width);
     }
+
+    #[test]
+    fn test_body_fills_viewport_in_quirks_mode() {
+        let layout_body = |quirks_mode: QuirksMode| {
+            let document = document();
+            let dom = element(
+                "html",
+                document.clone(),
+                vec![element("body", document.clone(), vec![])],
+            );
+            let css = format!("{} body {{ margin: 8px; }}", SHARED_CSS);
+            let root = build_tree(dom, &css);
+
+            let layout_context = LayoutContext {
+                viewport: Rect::new(0., 0., 500., 300.),
+                quirks_mode,
+                writing_mode: WritingMode::HorizontalTb,
+            };
+            let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+            establish_context(
+                FormattingContextType::BlockFormattingContext,
+                initial_block_box.clone(),
+            );
+            initial_block_box.append_child(root.clone());
+            initial_block_box
+                .formatting_context()
+                .run(&layout_context, initial_block_box.clone());
+
+            root.first_child().unwrap()
+        };
+
+        assert_eq!(layout_body(QuirksMode::NoQuirks).content_size().height, 0.);
+        assert_eq!(layout_body(QuirksMode::Quirks).content_size().height, 284.);
+    }
+
+    #[test]
+    fn test_vertical_rl() {
+        let document = document();
+        let dom = element(
+            "html",
+            document.clone(),
+            vec![element(
+                "body",
+                document.clone(),
+                vec![
+                    element("div.first", document.clone(), vec![]),
+                    element(
+                        "div.second",
+                        document.clone(),
+                        vec![text("abc", document.clone())],
+                    ),
+                ],
+            )],
+        );
+        let css = format!(
+            "{} body {{ writing-mode: vertical-rl; }}
+            .first {{ width: 30px; padding-top: 5px; margin-right: 10px; }}",
+            SHARED_CSS
+        );
+        let root = build_tree(dom, &css);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box.append_child(root.clone());
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+
+        let body = root.first_child().unwrap();
+        let first = body.first_child().unwrap();
+        let second = body.last_child().unwrap();
+        let line_width = second.content_size().width;
+        assert!(line_width > 0.);
+
+        // Blocks stack from the right and lines run from the top
+        assert_eq!(body.content_size(), Size::new(10. + 30. + line_width, 300.));
+        assert_eq!(first.offset(), Point::new(line_width, 5.));
+        assert_eq!(first.content_size(), Size::new(30., 295.));
+        assert_eq!(first.box_model().borrow().padding.top, 5.);
+        assert_eq!(first.box_model().borrow().margin.right, 10.);
+        assert_eq!(second.offset(), Point::new(0., 0.));
+
+        let lines = second.lines().borrow();
+        let fragment = &lines[0].fragments[0];
+        assert!(lines[0].is_vertical);
+        assert_eq!(fragment.offset.y, 0.);
+        assert!(fragment.size.height > fragment.size.width);
+    }
 }
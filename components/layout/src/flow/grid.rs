@@ -0,0 +1,820 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::{
+    box_model::BoxComponent,
+    document::layout_nested_document,
+    formatting_context::{
+        create_independent_formatting_context_if_needed, use_context, BaseFormattingContext,
+        FormattingContext, LayoutContext,
+    },
+    layout_box::LayoutBoxPtr,
+};
+use dom::node::NodePtr;
+use shared::primitive::edge::Edge;
+use style_types::{
+    values::prelude::{GridLine, Position, TrackList, TrackSize},
+    Property, Value,
+};
+
+/// Lays out the children of a grid container as grid items, placed on the
+/// lines of the explicit grid or auto-placed in row order.
+/// https://www.w3.org/TR/css-grid-1/#layout-algorithm
+///
+/// Boxes have no intrinsic sizes yet, so the content contribution of an
+/// item to an auto column is its explicit width. Auto rows fit the items
+/// laid out in them.
+#[derive(Debug)]
+pub struct GridFormattingContext {
+    base: BaseFormattingContext,
+}
+
+/// Tracks covered by a grid item, as indices of the columns and rows
+#[derive(Debug, Clone, PartialEq)]
+struct GridArea {
+    columns: Range<usize>,
+    rows: Range<usize>,
+}
+
+/// Position of a grid item on an axis, before auto-placement: its first
+/// track if it is definite, and the number of tracks it spans
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AxisPlacement {
+    start: Option<usize>,
+    span: usize,
+}
+
+impl FormattingContext for GridFormattingContext {
+    fn run(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        let node = match layout_node.node() {
+            Some(node) => node,
+            None => return,
+        };
+        let items = layout_node
+            .iterate_children()
+            .filter(|item| !item.is_positioned(Position::Absolute))
+            .collect::<Vec<_>>();
+
+        let column_tracks = track_list(&node, &Property::GridTemplateColumns);
+        let row_tracks = track_list(&node, &Property::GridTemplateRows);
+        let placements = items
+            .iter()
+            .map(|item| {
+                (
+                    item_placement(item, &Property::GridColumnStart, column_tracks.len()),
+                    item_placement(item, &Property::GridRowStart, row_tracks.len()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let areas = place_items(&placements, column_tracks.len());
+
+        let width = layout_node.content_size().width;
+        let computed_height = node.get_style(&Property::Height);
+        let height = match computed_height.is_auto() {
+            true => None,
+            false => {
+                let containing_block = layout_node.containing_block().unwrap().content_size();
+                Some(computed_height.to_px(containing_block.height))
+            }
+        };
+        let column_gap = gap(&node, &Property::ColumnGap, width);
+        let row_gap = gap(&node, &Property::RowGap, height.unwrap_or(0.));
+
+        let column_count = areas
+            .iter()
+            .map(|area| area.columns.end)
+            .fold(column_tracks.len(), usize::max);
+        let column_contributions = items
+            .iter()
+            .zip(&areas)
+            .map(|(item, area)| (area.columns.clone(), explicit_width(item)))
+            .collect::<Vec<_>>();
+        let columns = size_tracks(
+            &implicit_tracks(&column_tracks, column_count),
+            &column_contributions,
+            Some(width),
+            column_gap,
+        );
+
+        for (item, area) in items.iter().zip(&areas) {
+            let area_width = area_size(&columns, &area.columns, column_gap);
+            self.layout_item(context, item.clone(), area_width);
+        }
+
+        let row_count = areas
+            .iter()
+            .map(|area| area.rows.end)
+            .fold(row_tracks.len(), usize::max);
+        let row_contributions = items
+            .iter()
+            .zip(&areas)
+            .map(|(item, area)| (area.rows.clone(), item.margin_box_height()))
+            .collect::<Vec<_>>();
+        let rows = size_tracks(
+            &implicit_tracks(&row_tracks, row_count),
+            &row_contributions,
+            height,
+            row_gap,
+        );
+
+        let column_starts = track_starts(&columns, column_gap);
+        let row_starts = track_starts(&rows, row_gap);
+        for (item, area) in items.iter().zip(&areas) {
+            let area_height = area_size(&rows, &area.rows, row_gap);
+            self.align_item(item.clone(), area_height);
+
+            let edges = item.box_model().borrow().margin_box();
+            item.set_offset(
+                column_starts[area.columns.start] + edges.left,
+                row_starts[area.rows.start] + edges.top,
+            );
+        }
+
+        let content_height = area_size(&rows, &(0..rows.len()), row_gap);
+        layout_node.set_content_height(height.unwrap_or(content_height));
+    }
+
+    fn base(&self) -> &BaseFormattingContext {
+        &self.base
+    }
+}
+
+impl GridFormattingContext {
+    pub fn new(base: BaseFormattingContext) -> Self {
+        Self { base }
+    }
+
+    /// Lay out the item in the width of its grid area. Auto margins are
+    /// zero, and an auto width stretches the item to fill the area.
+    fn layout_item(&self, context: &LayoutContext, item: LayoutBoxPtr, area_width: f32) {
+        let mut width = area_width;
+        if let Some(node) = item.node() {
+            let mut box_model = item.box_model().borrow_mut();
+            for (component, edge, property) in box_properties() {
+                let size = node.get_style(&property).to_px(area_width);
+                box_model.set(component, edge, size);
+            }
+            let edges = box_model.margin_box();
+
+            let computed_width = node.get_style(&Property::Width);
+            width = match computed_width.is_auto() {
+                true => f32::max(area_width - edges.left - edges.right, 0.),
+                false => computed_width.to_px(area_width),
+            };
+        }
+        item.set_content_width(width);
+        item.set_content_height(0.);
+
+        if let Some(formatting_context) =
+            create_independent_formatting_context_if_needed(item.clone())
+        {
+            use_context(formatting_context.clone(), item.clone());
+            formatting_context.run(context, item.clone());
+        }
+        if !item.children_are_inline() && !item.is_grid_container() {
+            let height = item
+                .iterate_children()
+                .fold(0., |acc, child| acc + child.margin_box_height());
+            item.set_content_height(height);
+        }
+
+        item.apply_explicit_sizes(context.writing_mode);
+        layout_nested_document(&item);
+    }
+
+    /// Stretch an item with an auto height to the height of its grid area
+    fn align_item(&self, item: LayoutBoxPtr, area_height: f32) {
+        let has_auto_height = match item.node() {
+            Some(node) => item.is_non_replaced() && node.get_style(&Property::Height).is_auto(),
+            None => true,
+        };
+        if !has_auto_height {
+            return;
+        }
+        let edges = item.box_model().borrow().margin_box();
+        let height = area_height - edges.top - edges.bottom;
+        if height > item.content_size().height {
+            item.set_content_height(height);
+        }
+    }
+}
+
+/// Properties of the margins, borders and padding of a box
+fn box_properties() -> [(BoxComponent, Edge, Property); 12] {
+    [
+        (BoxComponent::Margin, Edge::Top, Property::MarginTop),
+        (BoxComponent::Margin, Edge::Right, Property::MarginRight),
+        (BoxComponent::Margin, Edge::Bottom, Property::MarginBottom),
+        (BoxComponent::Margin, Edge::Left, Property::MarginLeft),
+        (BoxComponent::Border, Edge::Top, Property::BorderTopWidth),
+        (
+            BoxComponent::Border,
+            Edge::Right,
+            Property::BorderRightWidth,
+        ),
+        (
+            BoxComponent::Border,
+            Edge::Bottom,
+            Property::BorderBottomWidth,
+        ),
+        (BoxComponent::Border, Edge::Left, Property::BorderLeftWidth),
+        (BoxComponent::Padding, Edge::Top, Property::PaddingTop),
+        (BoxComponent::Padding, Edge::Right, Property::PaddingRight),
+        (BoxComponent::Padding, Edge::Bottom, Property::PaddingBottom),
+        (BoxComponent::Padding, Edge::Left, Property::PaddingLeft),
+    ]
+}
+
+fn track_list(node: &NodePtr, property: &Property) -> Vec<TrackSize> {
+    match node.get_style(property) {
+        Value::TrackList(TrackList(tracks)) => tracks,
+        _ => Vec::new(),
+    }
+}
+
+/// Gap between tracks, `normal` gaps are empty outside of multi-column
+/// containers
+fn gap(node: &NodePtr, property: &Property, relative_to: f32) -> f32 {
+    match node.get_style(property) {
+        Value::Normal => 0.,
+        value => value.to_px(relative_to),
+    }
+}
+
+/// The explicit tracks followed by auto tracks up to `count`
+fn implicit_tracks(tracks: &[TrackSize], count: usize) -> Vec<TrackSize> {
+    let mut tracks = tracks.to_vec();
+    tracks.resize(usize::max(count, tracks.len()), TrackSize::Auto);
+    tracks
+}
+
+/// Width of the margin box of an item with an explicit length as width,
+/// zero otherwise. Percentages of the grid area are not known yet.
+fn explicit_width(item: &LayoutBoxPtr) -> f32 {
+    let node = match item.node() {
+        Some(node) => node,
+        None => return 0.,
+    };
+    match node.get_style(&Property::Width) {
+        Value::Length(length) => {
+            let edges = [
+                Property::MarginLeft,
+                Property::BorderLeftWidth,
+                Property::PaddingLeft,
+                Property::PaddingRight,
+                Property::BorderRightWidth,
+                Property::MarginRight,
+            ];
+            edges.iter().fold(length.to_px(), |acc, edge| {
+                acc + node.get_style(edge).to_px(0.)
+            })
+        }
+        _ => 0.,
+    }
+}
+
+/// Placement on the axis of the start property from the `*-start` and
+/// `*-end` properties of the item
+fn item_placement(
+    item: &LayoutBoxPtr,
+    start_property: &Property,
+    explicit_tracks: usize,
+) -> AxisPlacement {
+    let node = match item.node() {
+        Some(node) => node,
+        None => {
+            return AxisPlacement {
+                start: None,
+                span: 1,
+            }
+        }
+    };
+    let end_property = match start_property {
+        Property::GridColumnStart => Property::GridColumnEnd,
+        _ => Property::GridRowEnd,
+    };
+    let grid_line = |property: &Property| match node.get_style(property) {
+        Value::GridLine(line) => line,
+        _ => GridLine::Auto,
+    };
+    resolve_lines(
+        &grid_line(start_property),
+        &grid_line(&end_property),
+        explicit_tracks,
+    )
+}
+
+/// Resolve the lines of an item to its first track and span. A span
+/// against a definite line extends away from it, and lines before the
+/// start of the explicit grid are clamped to it.
+/// https://www.w3.org/TR/css-grid-1/#line-placement
+fn resolve_lines(start: &GridLine, end: &GridLine, explicit_tracks: usize) -> AxisPlacement {
+    let line_index = |line: i32| -> usize {
+        match line > 0 {
+            true => line as usize - 1,
+            false => (explicit_tracks + 1).saturating_sub(line.unsigned_abs() as usize),
+        }
+    };
+    let definite = |start: usize, end: usize| match start.cmp(&end) {
+        std::cmp::Ordering::Less => AxisPlacement {
+            start: Some(start),
+            span: end - start,
+        },
+        std::cmp::Ordering::Greater => AxisPlacement {
+            start: Some(end),
+            span: start - end,
+        },
+        std::cmp::Ordering::Equal => AxisPlacement {
+            start: Some(start),
+            span: 1,
+        },
+    };
+
+    match (start, end) {
+        (GridLine::Line(start), GridLine::Line(end)) => {
+            definite(line_index(*start), line_index(*end))
+        }
+        (GridLine::Line(start), GridLine::Span(span)) => AxisPlacement {
+            start: Some(line_index(*start)),
+            span: *span as usize,
+        },
+        (GridLine::Line(start), GridLine::Auto) => AxisPlacement {
+            start: Some(line_index(*start)),
+            span: 1,
+        },
+        (GridLine::Span(span), GridLine::Line(end)) => {
+            let end = line_index(*end);
+            definite(end.saturating_sub(*span as usize), end)
+        }
+        (GridLine::Auto, GridLine::Line(end)) => {
+            let end = line_index(*end);
+            definite(end.saturating_sub(1), end)
+        }
+        (GridLine::Span(span), _) | (_, GridLine::Span(span)) => AxisPlacement {
+            start: None,
+            span: *span as usize,
+        },
+        (GridLine::Auto, GridLine::Auto) => AxisPlacement {
+            start: None,
+            span: 1,
+        },
+    }
+}
+
+/// Grid cells covered by the items placed so far
+struct OccupiedCells(HashSet<(usize, usize)>);
+
+impl OccupiedCells {
+    fn is_free(&self, columns: &Range<usize>, rows: &Range<usize>) -> bool {
+        rows.clone().all(|row| {
+            columns
+                .clone()
+                .all(|column| !self.0.contains(&(row, column)))
+        })
+    }
+
+    fn occupy(&mut self, area: &GridArea) {
+        for row in area.rows.clone() {
+            for column in area.columns.clone() {
+                self.0.insert((row, column));
+            }
+        }
+    }
+}
+
+/// Find the grid area of every item from its `(column, row)` placement.
+/// Items with a definite row are placed first, then the others are
+/// auto-placed in row order, each after the previous one.
+/// https://www.w3.org/TR/css-grid-1/#auto-placement-algo
+fn place_items(
+    placements: &[(AxisPlacement, AxisPlacement)],
+    explicit_columns: usize,
+) -> Vec<GridArea> {
+    let mut areas: Vec<Option<GridArea>> = vec![None; placements.len()];
+    let mut occupied = OccupiedCells(HashSet::new());
+
+    // Items with definite positions on both axes
+    for (index, (column, row)) in placements.iter().enumerate() {
+        if let (Some(column_start), Some(row_start)) = (column.start, row.start) {
+            let area = GridArea {
+                columns: column_start..column_start + column.span,
+                rows: row_start..row_start + row.span,
+            };
+            occupied.occupy(&area);
+            areas[index] = Some(area);
+        }
+    }
+
+    let mut column_count = placements
+        .iter()
+        .map(|(column, _)| column.start.unwrap_or(0) + column.span)
+        .fold(explicit_columns, usize::max);
+
+    // Items locked to a row take the first columns free in it, after the
+    // items placed in the row before them
+    let mut row_cursors = std::collections::HashMap::new();
+    for (index, (column, row)) in placements.iter().enumerate() {
+        let row_start = match (column.start, row.start) {
+            (None, Some(row_start)) => row_start,
+            _ => continue,
+        };
+        let rows = row_start..row_start + row.span;
+        let mut column_start = *row_cursors.get(&row_start).unwrap_or(&0);
+        while !occupied.is_free(&(column_start..column_start + column.span), &rows) {
+            column_start += 1;
+        }
+        let area = GridArea {
+            columns: column_start..column_start + column.span,
+            rows,
+        };
+        column_count = usize::max(column_count, area.columns.end);
+        row_cursors.insert(row_start, area.columns.end);
+        occupied.occupy(&area);
+        areas[index] = Some(area);
+    }
+
+    // Other items follow the cursor through the rows
+    let (mut cursor_row, mut cursor_column) = (0, 0);
+    for (index, (column, row)) in placements.iter().enumerate() {
+        if areas[index].is_some() {
+            continue;
+        }
+        match column.start {
+            Some(column_start) => {
+                if column_start < cursor_column {
+                    cursor_row += 1;
+                }
+                cursor_column = column_start;
+                let columns = column_start..column_start + column.span;
+                while !occupied.is_free(&columns, &(cursor_row..cursor_row + row.span)) {
+                    cursor_row += 1;
+                }
+            }
+            None => loop {
+                if cursor_column + column.span > column_count {
+                    cursor_row += 1;
+                    cursor_column = 0;
+                    continue;
+                }
+                let columns = cursor_column..cursor_column + column.span;
+                if occupied.is_free(&columns, &(cursor_row..cursor_row + row.span)) {
+                    break;
+                }
+                cursor_column += 1;
+            },
+        }
+        let area = GridArea {
+            columns: cursor_column..cursor_column + column.span,
+            rows: cursor_row..cursor_row + row.span,
+        };
+        occupied.occupy(&area);
+        areas[index] = Some(area);
+    }
+
+    areas.into_iter().map(Option::unwrap).collect()
+}
+
+/// Sizes of the tracks of an axis, given the size of the items in them
+/// as `(tracks, size)`. Fixed tracks have their length and auto tracks fit
+/// their items, then flexible tracks share the space left in `available`.
+/// Without flexible tracks, auto tracks stretch to fill it instead. With
+/// an indefinite `available` size, flexible tracks fit their items in
+/// proportion to their flex factors.
+/// https://www.w3.org/TR/css-grid-1/#algo-track-sizing
+fn size_tracks(
+    tracks: &[TrackSize],
+    contributions: &[(Range<usize>, f32)],
+    available: Option<f32>,
+    gap: f32,
+) -> Vec<f32> {
+    let is_intrinsic = |track: &TrackSize| match track {
+        TrackSize::Auto => true,
+        TrackSize::Fraction(_) => available.is_none(),
+        TrackSize::Length(_) => false,
+    };
+    let mut sizes = tracks
+        .iter()
+        .map(|track| match track {
+            TrackSize::Length(length) => length.to_px(),
+            _ => 0.,
+        })
+        .collect::<Vec<f32>>();
+
+    // Items spanning one track first, then the extra size of items spanning
+    // several tracks goes to the last intrinsic track they span
+    let mut contributions = contributions.to_vec();
+    contributions.sort_by_key(|(tracks, _)| tracks.len());
+    for (span, size) in contributions {
+        let intrinsic_track = span
+            .clone()
+            .rev()
+            .find(|index| is_intrinsic(&tracks[*index]));
+        if let Some(index) = intrinsic_track {
+            let extra = size - area_size(&sizes, &span, gap);
+            if extra > 0. {
+                sizes[index] += extra;
+            }
+        }
+    }
+
+    let fraction = |track: &TrackSize| match track {
+        TrackSize::Fraction(fraction) => Some(**fraction),
+        _ => None,
+    };
+    let flex_factors = tracks.iter().filter_map(fraction).sum::<f32>();
+    let has_flexible_tracks = tracks.iter().any(|track| fraction(track).is_some());
+
+    match available {
+        Some(available) => {
+            let free_space = available - area_size(&sizes, &(0..sizes.len()), gap);
+            if has_flexible_tracks {
+                let fraction_size = f32::max(free_space, 0.) / f32::max(flex_factors, 1.);
+                for (size, track) in sizes.iter_mut().zip(tracks) {
+                    if let Some(fraction) = fraction(track) {
+                        *size = fraction * fraction_size;
+                    }
+                }
+            } else if free_space > 0. {
+                let auto_count = tracks
+                    .iter()
+                    .filter(|track| **track == TrackSize::Auto)
+                    .count();
+                for (size, track) in sizes.iter_mut().zip(tracks) {
+                    if *track == TrackSize::Auto {
+                        *size += free_space / auto_count as f32;
+                    }
+                }
+            }
+        }
+        None => {
+            let fraction_size = sizes
+                .iter()
+                .zip(tracks)
+                .filter_map(|(size, track)| fraction(track).map(|f| size / f32::max(f, 1.)))
+                .fold(0., f32::max);
+            for (size, track) in sizes.iter_mut().zip(tracks) {
+                if let Some(fraction) = fraction(track) {
+                    *size = fraction * fraction_size;
+                }
+            }
+        }
+    }
+    sizes
+}
+
+/// Size of an area spanning the tracks, with the gaps between them
+fn area_size(sizes: &[f32], tracks: &Range<usize>, gap: f32) -> f32 {
+    if tracks.is_empty() {
+        return 0.;
+    }
+    sizes[tracks.clone()].iter().sum::<f32>() + gap * (tracks.len() - 1) as f32
+}
+
+/// Offsets of the tracks from the start of the grid
+fn track_starts(sizes: &[f32], gap: f32) -> Vec<f32> {
+    let mut start = 0.;
+    sizes
+        .iter()
+        .map(|size| {
+            let track_start = start;
+            start += size + gap;
+            track_start
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatting_context::{establish_context, FormattingContextType};
+    use crate::layout_box::{BoxData, LayoutBox};
+    use crate::utils::*;
+    use dom::document::QuirksMode;
+    use shared::primitive::*;
+    use style_types::values::prelude::{Length, WritingMode};
+    use test_utils::dom_creator::*;
+
+    fn auto(span: usize) -> AxisPlacement {
+        AxisPlacement { start: None, span }
+    }
+
+    fn at(start: usize, span: usize) -> AxisPlacement {
+        AxisPlacement {
+            start: Some(start),
+            span,
+        }
+    }
+
+    #[test]
+    fn test_resolve_lines() {
+        use GridLine::*;
+        assert_eq!(resolve_lines(&Line(1), &Line(3), 3), at(0, 2));
+        assert_eq!(resolve_lines(&Line(3), &Line(1), 3), at(0, 2));
+        assert_eq!(resolve_lines(&Line(2), &Auto, 3), at(1, 1));
+        assert_eq!(resolve_lines(&Line(1), &Line(-1), 3), at(0, 3));
+        assert_eq!(resolve_lines(&Span(2), &Line(4), 3), at(1, 2));
+        assert_eq!(resolve_lines(&Auto, &Span(2), 3), auto(2));
+        assert_eq!(resolve_lines(&Auto, &Auto, 3), auto(1));
+    }
+
+    #[test]
+    fn test_auto_placement() {
+        let areas = place_items(
+            &[
+                (auto(1), auto(1)),
+                (at(0, 1), at(0, 1)),
+                (auto(2), auto(1)),
+                (auto(1), at(1, 1)),
+                (at(2, 1), auto(1)),
+            ],
+            3,
+        );
+        let area = |columns: Range<usize>, rows: Range<usize>| GridArea { columns, rows };
+        assert_eq!(
+            areas,
+            vec![
+                area(1..2, 0..1),
+                area(0..1, 0..1),
+                area(1..3, 1..2),
+                area(0..1, 1..2),
+                area(2..3, 2..3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_size_tracks() {
+        let px = |value| TrackSize::Length(Length::new_px(value));
+        let fr = |value: f32| TrackSize::Fraction(value.into());
+        let tracks = [px(100.), fr(1.), fr(2.), TrackSize::Auto];
+        let contributions = [(3..4, 50.), (0..2, 180.)];
+
+        assert_eq!(
+            size_tracks(&tracks, &contributions, Some(480.), 10.),
+            vec![100., 100., 200., 50.]
+        );
+        // Auto tracks stretch without flexible tracks
+        assert_eq!(
+            size_tracks(&[px(100.), TrackSize::Auto], &[], Some(300.), 0.),
+            vec![100., 200.]
+        );
+        // Flexible tracks fit their items with an indefinite size
+        assert_eq!(
+            size_tracks(&tracks, &contributions, None, 10.),
+            vec![100., 70., 140., 50.]
+        );
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let document = document();
+        let item = |class: &str| element(&format!("div.{}", class), document.clone(), vec![]);
+        let dom = element(
+            "div.grid",
+            document.clone(),
+            vec![
+                item("a"),
+                item("b"),
+                item("c"),
+                text(" ", document.clone()),
+                element("span", document.clone(), vec![]),
+            ],
+        );
+        let css = format!(
+            "{}
+            .grid {{
+                display: grid;
+                grid-template-columns: 100px 1fr 2fr;
+                grid-template-rows: 50px;
+                column-gap: 20px;
+                row-gap: 5px;
+            }}
+            .a {{ grid-column: 2 / -1; }}
+            .b {{ grid-row: 2; height: 20px; }}",
+            SHARED_CSS
+        );
+        let root = build_tree(dom, &css);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box.append_child(root.clone());
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+
+        // The white space is not rendered and the span gets an anonymous
+        // block as its grid item
+        assert_eq!(root.children_count(), 4);
+        let child = |index| root.nth_child(index).unwrap();
+        let rect = |item: LayoutBoxPtr| {
+            let offset = item.offset();
+            let size = item.content_size();
+            Rect::new(offset.x, offset.y, size.width, size.height)
+        };
+
+        // Columns are 100px, 120px and 240px wide. Auto-placed items never
+        // go back to cells before the previous one.
+        assert_eq!(rect(child(0)), Rect::new(120., 0., 380., 50.));
+        assert_eq!(rect(child(1)), Rect::new(0., 55., 100., 20.));
+        assert_eq!(rect(child(2)), Rect::new(120., 55., 120., 20.));
+        assert_eq!(rect(child(3)), Rect::new(260., 55., 240., 20.));
+        assert_eq!(root.content_size().height, 75.);
+    }
+
+    /// Lay out four items in a grid of two 100px columns and two 50px rows
+    /// with the given gap declarations
+    fn layout_grid_items(gap_css: &str) -> Vec<Rect> {
+        let document = document();
+        let dom = element(
+            "div.grid",
+            document.clone(),
+            (0..4)
+                .map(|_| element("div", document.clone(), vec![]))
+                .collect(),
+        );
+        let css = format!(
+            "{}
+            .grid {{
+                display: grid;
+                grid-template-columns: 100px 100px;
+                grid-template-rows: 50px 50px;
+                {}
+            }}",
+            SHARED_CSS, gap_css
+        );
+        let root = build_tree(dom, &css);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box.append_child(root.clone());
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+
+        root.iterate_children()
+            .map(|child| {
+                let item = child;
+                let offset = item.offset();
+                let size = item.content_size();
+                Rect::new(offset.x, offset.y, size.width, size.height)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gap_shorthand_with_one_value() {
+        assert_eq!(
+            layout_grid_items("gap: 10px;"),
+            vec![
+                Rect::new(0., 0., 100., 50.),
+                Rect::new(110., 0., 100., 50.),
+                Rect::new(0., 60., 100., 50.),
+                Rect::new(110., 60., 100., 50.),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gap_shorthand_with_two_values() {
+        // The row gap comes first
+        assert_eq!(
+            layout_grid_items("gap: 5px 20px;"),
+            vec![
+                Rect::new(0., 0., 100., 50.),
+                Rect::new(120., 0., 100., 50.),
+                Rect::new(0., 55., 100., 50.),
+                Rect::new(120., 55., 100., 50.),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_legacy_grid_gap_aliases() {
+        let expected = vec![
+            Rect::new(0., 0., 100., 50.),
+            Rect::new(120., 0., 100., 50.),
+            Rect::new(0., 55., 100., 50.),
+            Rect::new(120., 55., 100., 50.),
+        ];
+        assert_eq!(layout_grid_items("grid-gap: 5px 20px;"), expected);
+        assert_eq!(
+            layout_grid_items("grid-row-gap: 5px; grid-column-gap: 20px;"),
+            expected
+        );
+    }
+}
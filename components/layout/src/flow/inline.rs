@@ -1,14 +1,18 @@
-use std::rc::Rc;
-
 use crate::{
     box_model::BoxComponent,
+    document::layout_nested_document,
     formatting_context::{BaseFormattingContext, FormattingContext, LayoutContext},
     layout_box::LayoutBoxPtr,
+    list_marker::marker_owner_of_first_line,
+    writing_mode::flow_relative_style,
 };
 use dom::node::NodeData;
 use regex::Regex;
 use shared::primitive::edge::Edge;
-use style_types::Property;
+use style_types::{
+    values::prelude::{ListStylePosition, WhiteSpace},
+    Property, Value,
+};
 
 use super::line_box::LineBoxBuilder;
 
@@ -31,15 +35,16 @@ impl Iterator for InlineBoxIterator {
     fn next(&mut self) -> Option<Self::Item> {
         if let Some(node) = self.stack.last() {
             let mut maybe_found_node = None;
-            // if top of the stack is a leaf then remove it and skip to the next element
-            if node.has_no_child() {
+            // if top of the stack is a leaf then remove it and skip to the next element.
+            // Math boxes lay out their own descendants.
+            if node.has_no_child() || node.is_math() {
                 self.stack.pop();
                 return self.next();
             }
 
             for child in node.iterate_children() {
-                if !self.visited.iter().any(|n| Rc::ptr_eq(&n.0, &child)) {
-                    let child_box = LayoutBoxPtr(child.clone());
+                if !self.visited.iter().any(|n| n.ptr_eq(&child)) {
+                    let child_box = child.clone();
                     self.stack.push(child_box.clone());
                     self.visited.push(child_box.clone());
                     maybe_found_node = Some(child_box);
@@ -93,16 +98,43 @@ impl InlineFormattingContext {
     }
 
     fn generate_line_boxes(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
-        let mut line_box_builder = LineBoxBuilder::new(layout_node.clone());
+        let mut line_box_builder =
+            LineBoxBuilder::new(layout_node.clone(), context.quirks_mode.clone());
         layout_node.lines().borrow_mut().clear();
 
+        if let Some(list_item) = marker_owner_of_first_line(&layout_node) {
+            let is_inside = matches!(
+                list_item.marker().map(|marker| &marker.position),
+                Some(ListStylePosition::Inside)
+            );
+            if is_inside {
+                line_box_builder.add_marker_fragment(list_item);
+            }
+        }
+
         let inline_child_iter = InlineBoxIterator::new(layout_node.clone());
 
         for child in inline_child_iter {
             match child.node() {
                 Some(node) => match node.data() {
                     Some(NodeData::Text(content)) => {
-                        let text_content = content.get_data();
+                        let text_content = match node.get_style(&Property::TextTransform) {
+                            Value::TextTransform(transform) => transform.apply(&content.get_data()),
+                            _ => content.get_data(),
+                        };
+                        let white_space = match node.get_style(&Property::WhiteSpace) {
+                            Value::WhiteSpace(white_space) => white_space,
+                            _ => WhiteSpace::Normal,
+                        };
+                        if white_space.preserves_line_breaks() {
+                            self.add_preformatted_text(
+                                &mut line_box_builder,
+                                child.clone(),
+                                &text_content,
+                                &white_space,
+                            );
+                            continue;
+                        }
                         if text_content.trim().is_empty() {
                             continue;
                         }
@@ -131,16 +163,48 @@ impl InlineFormattingContext {
         *layout_node.lines().borrow_mut() = line_box_builder.finish();
     }
 
+    /// Add text whose line breaks start new lines. With `pre-line`, other
+    /// white space collapses into single spaces.
+    fn add_preformatted_text(
+        &self,
+        line_box_builder: &mut LineBoxBuilder,
+        layout_box: LayoutBoxPtr,
+        text: &str,
+        white_space: &WhiteSpace,
+    ) {
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                line_box_builder.add_line_break();
+            }
+            if white_space.preserves_spaces() {
+                line_box_builder.add_preserved_text(layout_box.clone(), line, white_space.wraps());
+                continue;
+            }
+
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            for (index, word) in words.iter().enumerate() {
+                let is_last = index + 1 == words.len();
+                let word = if is_last {
+                    word.to_string()
+                } else {
+                    format!("{} ", word)
+                };
+                line_box_builder.add_text_fragment(layout_box.clone(), word);
+            }
+        }
+    }
+
     fn layout_dimension_box(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
-        self.calculate_width_for_element(layout_node.clone());
+        self.calculate_width_for_element(context, layout_node.clone());
 
         self.layout_inside(context, layout_node.clone());
 
-        self.apply_vertical_spacing(layout_node.clone());
-        layout_node.apply_explicit_sizes();
+        self.apply_vertical_spacing(context, layout_node.clone());
+        layout_node.apply_explicit_sizes(context.writing_mode);
+        layout_nested_document(&layout_node);
     }
 
-    fn calculate_width_for_element(&self, layout_node: LayoutBoxPtr) {
+    fn calculate_width_for_element(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
         let containing_block = layout_node.containing_block().unwrap().content_size();
 
         let node = match layout_node.node() {
@@ -148,9 +212,11 @@ impl InlineFormattingContext {
             _ => return,
         };
 
-        let computed_width = node.get_style(&Property::Width);
-        let computed_margin_left = node.get_style(&Property::MarginLeft);
-        let computed_margin_right = node.get_style(&Property::MarginRight);
+        let computed_width = flow_relative_style(&node, &Property::Width, context.writing_mode);
+        let computed_margin_left =
+            flow_relative_style(&node, &Property::MarginLeft, context.writing_mode);
+        let computed_margin_right =
+            flow_relative_style(&node, &Property::MarginRight, context.writing_mode);
         let containing_width = containing_block.width;
 
         let mut used_width = computed_width.to_px(containing_width);
@@ -182,33 +248,32 @@ impl InlineFormattingContext {
         box_model.set(BoxComponent::Margin, Edge::Right, used_margin_right);
     }
 
-    fn apply_vertical_spacing(&self, layout_node: LayoutBoxPtr) {
+    fn apply_vertical_spacing(&self, context: &LayoutContext, layout_node: LayoutBoxPtr) {
         let containing_block = layout_node.containing_block().unwrap().content_size();
 
         let node = layout_node.node();
         let mut box_model = layout_node.box_model.borrow_mut();
 
         if let Some(node) = node {
-            let margin_top = node
-                .get_style(&Property::MarginTop)
-                .to_px(containing_block.width);
-            let margin_bottom = node
-                .get_style(&Property::MarginBottom)
-                .to_px(containing_block.width);
-
-            let border_top = node
-                .get_style(&Property::BorderTopWidth)
-                .to_px(containing_block.width);
-            let border_bottom = node
-                .get_style(&Property::BorderBottomWidth)
-                .to_px(containing_block.width);
-
-            let padding_top = node
-                .get_style(&Property::PaddingTop)
-                .to_px(containing_block.width);
-            let padding_bottom = node
-                .get_style(&Property::PaddingBottom)
+            let margin_top = flow_relative_style(&node, &Property::MarginTop, context.writing_mode)
                 .to_px(containing_block.width);
+            let margin_bottom =
+                flow_relative_style(&node, &Property::MarginBottom, context.writing_mode)
+                    .to_px(containing_block.width);
+
+            let border_top =
+                flow_relative_style(&node, &Property::BorderTopWidth, context.writing_mode)
+                    .to_px(containing_block.width);
+            let border_bottom =
+                flow_relative_style(&node, &Property::BorderBottomWidth, context.writing_mode)
+                    .to_px(containing_block.width);
+
+            let padding_top =
+                flow_relative_style(&node, &Property::PaddingTop, context.writing_mode)
+                    .to_px(containing_block.width);
+            let padding_bottom =
+                flow_relative_style(&node, &Property::PaddingBottom, context.writing_mode)
+                    .to_px(containing_block.width);
 
             box_model.set(BoxComponent::Margin, Edge::Top, margin_top);
             box_model.set(BoxComponent::Margin, Edge::Bottom, margin_bottom);
@@ -224,10 +289,13 @@ impl InlineFormattingContext {
 
 #[cfg(test)]
 mod tests {
+    use dom::document::QuirksMode;
     use shared::primitive::Rect;
-    use test_utils::dom_creator::{document, element};
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
 
     use crate::{
+        flow::line_box::LineFragmentData,
         formatting_context::{establish_context, FormattingContextType, LayoutContext},
         layout_box::LayoutBoxPtr,
         utils::{build_tree, SHARED_CSS},
@@ -341,6 +409,8 @@ mod tests {
 
         let layout_context = LayoutContext {
             viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
         };
 
         establish_context(FormattingContextType::InlineFormattingContext, root.clone());
@@ -352,4 +422,103 @@ mod tests {
             Some(3)
         );
     }
+
+    #[test]
+    fn test_line_height() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![
+                text("small", document.clone()),
+                element(
+                    "span",
+                    document.clone(),
+                    vec![text("large", document.clone())],
+                ),
+            ],
+        );
+
+        let root = build_tree(
+            dom,
+            &format!(
+                "{}{}",
+                SHARED_CSS, "div { line-height: 40px; } span { font-size: 32px; line-height: 1; }"
+            ),
+        );
+        root.set_content_width(500.);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+        root.formatting_context().run(&layout_context, root.clone());
+
+        let lines = root.lines().borrow();
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        let texts = line
+            .fragments
+            .iter()
+            .filter(|fragment| matches!(fragment.data, LineFragmentData::Text(..)))
+            .collect::<Vec<_>>();
+        assert_eq!(texts.len(), 2);
+
+        // The large text has no leading, so it sets the top of the line.
+        // The small text has 12px of leading below its descent.
+        let (small, large) = (texts[0], texts[1]);
+        assert_eq!(small.size.height, 16.);
+        assert_eq!(large.size.height, 32.);
+        assert_eq!(large.offset.y, 0.);
+        assert_eq!(line.baseline, large.baseline);
+        assert_eq!(small.offset.y + small.baseline, line.baseline);
+        assert_eq!(
+            line.size.height,
+            line.baseline + (small.size.height - small.baseline) + 12.
+        );
+    }
+
+    #[test]
+    fn test_preformatted_text() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![text("a\tb\n\n  c\n", document.clone())],
+        );
+
+        let root = build_tree(
+            dom,
+            &format!("{}{}", SHARED_CSS, "div { white-space: pre; tab-size: 4; }"),
+        );
+        root.set_content_width(500.);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+        root.formatting_context().run(&layout_context, root.clone());
+
+        let lines = root.lines().borrow();
+        let texts = lines
+            .iter()
+            .map(|line| {
+                line.fragments
+                    .iter()
+                    .filter_map(|fragment| match &fragment.data {
+                        LineFragmentData::Text(_, text) => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        // The trailing line break doesn't start a new line
+        assert_eq!(texts, vec!["a   b", "", "  c"]);
+    }
 }
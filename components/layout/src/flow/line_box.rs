@@ -1,34 +1,104 @@
-use std::rc::Rc;
-
-use gfx::TextMeasure;
+use dom::document::QuirksMode;
+use dom::node::NodePtr;
+use gfx::{FontIndex, TextMeasure, DEFAULT_FONT};
 use shared::primitive::{Point, Size};
-use style_types::{values::prelude::TextAlign, Property, Value};
+use style_types::{
+    values::prelude::{Direction, LineHeight, TabSize, TextAlign},
+    Property, Value,
+};
 
+use super::bidi;
 use crate::layout_box::LayoutBoxPtr;
+use crate::text_fragments::{font_of, TextSpacing};
 
 #[derive(Debug)]
 pub struct LineFragment {
     pub data: LineFragmentData,
     pub offset: Point,
     pub size: Size,
+    /// Distance from the top of the fragment to its baseline
+    pub baseline: f32,
+}
+
+/// How a fragment sits on the baseline of its line. Text is centered in
+/// its line height by adding half of the leading above and below it.
+/// https://www.w3.org/TR/CSS2/visudet.html#leading
+#[derive(Debug, Clone)]
+struct InlineMetrics {
+    height: f32,
+    /// Distance from the top of the fragment to its baseline
+    baseline: f32,
+    half_leading: f32,
+}
+
+impl InlineMetrics {
+    /// Metrics of text in the font and `line-height` of the node
+    fn of_text(node: &NodePtr, font: FontIndex, text_measurer: &TextMeasure) -> Self {
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let font_metrics = text_measurer.font_metrics(font_size, font);
+        let line_height = match node.get_style(&Property::LineHeight) {
+            Value::LineHeight(line_height) => line_height,
+            _ => LineHeight::Normal,
+        }
+        .to_px(font_size, font_metrics.normal_line_height());
+
+        Self {
+            height: font_metrics.height(),
+            baseline: font_metrics.ascent,
+            half_leading: (line_height - font_metrics.height()) / 2.,
+        }
+    }
+
+    /// Atomic inline boxes sit on the baseline with their bottom edge,
+    /// unless they have a baseline of their own
+    fn of_box(height: f32, baseline: Option<f32>) -> Self {
+        Self {
+            height,
+            baseline: baseline.unwrap_or(height),
+            half_leading: 0.,
+        }
+    }
+
+    fn above_baseline(&self) -> f32 {
+        self.baseline + self.half_leading
+    }
+
+    fn below_baseline(&self) -> f32 {
+        self.height - self.baseline + self.half_leading
+    }
 }
 
 #[derive(Debug)]
 pub enum LineFragmentData {
     Box(LayoutBoxPtr),
     Text(LayoutBoxPtr, String),
+    /// The inside marker of a list item
+    Marker(LayoutBoxPtr),
 }
 
 pub struct LineBoxBuilder {
     line_boxes: Vec<LineBox>,
     parent: LayoutBoxPtr,
     current_offset_y: f32,
+    quirks_mode: QuirksMode,
+    /// Characters of text on the current line, for tab stops
+    column: usize,
+    /// Whether the current line was started by a preserved line break and
+    /// is still empty
+    is_after_line_break: bool,
 }
 
 #[derive(Debug)]
 pub struct LineBox {
     pub fragments: Vec<LineFragment>,
     pub size: Size,
+    /// Distance from the top of the line to its baseline
+    pub baseline: f32,
+    /// Distance from the baseline to the bottom of the line
+    descent: f32,
+    /// Whether the line runs from top to bottom in a vertical writing mode,
+    /// with the tops of its glyphs facing right
+    pub is_vertical: bool,
 }
 
 impl LineBox {
@@ -36,6 +106,9 @@ impl LineBox {
         Self {
             fragments: Vec::new(),
             size: Size::new(0., 0.),
+            baseline: 0.,
+            descent: 0.,
+            is_vertical: false,
         }
     }
 
@@ -46,32 +119,36 @@ impl LineBox {
         child: LayoutBoxPtr,
     ) {
         let box_model = child.box_model().borrow();
+        let baseline = child.math().map(|math| math.baseline);
+        let metrics = InlineMetrics::of_box(fragment_height, baseline);
         let fragment = LineFragment::new_box(
             child.clone(),
             Point::new(self.size.width + box_model.margin.left, 0.),
             Size::new(fragment_width, fragment_height),
-        );
+        )
+        .with_baseline(metrics.baseline);
+        self.extend_vertically(&metrics);
         self.fragments.push(fragment);
         self.size.width += fragment_width + box_model.margin.right;
-        self.size.height = f32::max(self.size.height, fragment_height);
     }
 
-    pub fn add_text_fragment(
+    fn add_text_fragment(
         &mut self,
         fragment_width: f32,
-        fragment_height: f32,
+        metrics: &InlineMetrics,
         layout_box: LayoutBoxPtr,
         text: String,
     ) {
+        self.extend_vertically(metrics);
+
         if !self.fragments.is_empty() {
             let last_fragment = self.fragments.last_mut().unwrap();
 
             if let LineFragmentData::Text(last_box, ref mut content) = &mut last_fragment.data {
-                if Rc::ptr_eq(last_box, &layout_box) {
+                if last_box.ptr_eq(&layout_box) {
                     content.push_str(&text);
                     last_fragment.size.width += fragment_width;
                     self.size.width += fragment_width;
-                    self.size.height = f32::max(self.size.height, fragment_height);
                     return;
                 }
             }
@@ -80,11 +157,25 @@ impl LineBox {
             layout_box,
             text,
             Point::new(self.size.width, 0.),
-            Size::new(fragment_width, fragment_height),
-        );
+            Size::new(fragment_width, metrics.height),
+        )
+        .with_baseline(metrics.baseline);
         self.fragments.push(fragment);
         self.size.width += fragment_width;
-        self.size.height = f32::max(self.size.height, fragment_height);
+    }
+
+    /// Grow the line to fit a fragment with the metrics on its baseline
+    fn extend_vertically(&mut self, metrics: &InlineMetrics) {
+        // The first fragment sets the extents, which may be negative when
+        // the line height is smaller than the text
+        if self.fragments.is_empty() && self.size.height == 0. {
+            self.baseline = metrics.above_baseline();
+            self.descent = metrics.below_baseline();
+        } else {
+            self.baseline = f32::max(self.baseline, metrics.above_baseline());
+            self.descent = f32::max(self.descent, metrics.below_baseline());
+        }
+        self.size.height = self.baseline + self.descent;
     }
 
     pub fn dump(&self, level: usize) -> String {
@@ -108,7 +199,17 @@ impl LineBox {
 
 impl LineFragment {
     pub fn new(data: LineFragmentData, offset: Point, size: Size) -> Self {
-        Self { data, offset, size }
+        Self {
+            data,
+            offset,
+            size,
+            baseline: 0.,
+        }
+    }
+
+    pub fn with_baseline(mut self, baseline: f32) -> Self {
+        self.baseline = baseline;
+        self
     }
 
     pub fn set_offset(&mut self, offset: Point) {
@@ -127,6 +228,7 @@ impl LineFragment {
         let fragment_type = match &self.data {
             LineFragmentData::Box(_) => "[Box Fragment]".to_string(),
             LineFragmentData::Text(_, content) => format!("[Text Fragment] {:?}", content),
+            LineFragmentData::Marker(_) => "[Marker Fragment]".to_string(),
         };
 
         let fragment_info = format!(
@@ -137,22 +239,30 @@ impl LineFragment {
         let mut result = format!("{}{}{}\n", "  ".repeat(level), fragment_type, fragment_info);
         match &self.data {
             LineFragmentData::Box(node) => result.push_str(&node.dump(level + 1)),
-            LineFragmentData::Text(_, _) => {}
+            LineFragmentData::Text(_, _) | LineFragmentData::Marker(_) => {}
         }
         result
     }
 }
 
 impl LineBoxBuilder {
-    pub fn new(parent: LayoutBoxPtr) -> Self {
+    pub fn new(parent: LayoutBoxPtr, quirks_mode: QuirksMode) -> Self {
         Self {
             line_boxes: Vec::new(),
             parent,
             current_offset_y: 0.,
+            quirks_mode,
+            column: 0,
+            is_after_line_break: false,
         }
     }
 
     pub fn finish(mut self) -> Vec<LineBox> {
+        // A line break at the end of the block doesn't start a new line
+        if self.is_after_line_break {
+            self.line_boxes.pop();
+            return self.line_boxes;
+        }
         self.update_last_line();
         self.line_boxes
     }
@@ -171,21 +281,103 @@ impl LineBoxBuilder {
         let fragment_width = layout_box.content_size().width;
         let fragment_height = layout_box.content_size().height;
         self.break_line_if_needed(layout_box.margin_box_width());
+        self.is_after_line_break = false;
 
         self.current_line()
             .add_box_fragment(fragment_width, fragment_height, layout_box);
     }
 
     pub fn add_text_fragment(&mut self, layout_box: LayoutBoxPtr, text: String) {
+        let can_wrap = match layout_box.node().unwrap().get_style(&Property::WhiteSpace) {
+            Value::WhiteSpace(white_space) => white_space.wraps(),
+            _ => true,
+        };
+        self.add_text(layout_box, text, can_wrap);
+    }
+
+    /// Add text whose spaces are preserved, with its tabs expanded to the
+    /// next tab stop. If `can_wrap` is true, the text can wrap after spaces,
+    /// which hang at the end of the line.
+    pub fn add_preserved_text(&mut self, layout_box: LayoutBoxPtr, text: &str, can_wrap: bool) {
+        let node = layout_box.node().unwrap();
+        let tab_size = match node.get_style(&Property::TabSize) {
+            Value::TabSize(TabSize(tab_size)) => tab_size as usize,
+            _ => 8,
+        };
+        let words = match can_wrap {
+            true => split_after_spaces(text),
+            false => vec![text],
+        };
+
+        for word in words {
+            let mut expanded = expand_tabs(word, self.column, tab_size);
+            if can_wrap && word.contains('\t') {
+                // Tab stops move if the word starts a new line
+                let width = self.measure_text(&node, &expanded).0;
+                self.break_line_if_needed(width);
+                expanded = expand_tabs(word, self.column, tab_size);
+            }
+            self.add_text(layout_box.clone(), expanded, can_wrap);
+        }
+    }
+
+    /// Start a new line for a preserved line break
+    pub fn add_line_break(&mut self) {
+        self.current_line();
+        self.break_line();
+        self.is_after_line_break = true;
+    }
+
+    fn add_text(&mut self, layout_box: LayoutBoxPtr, text: String, can_wrap: bool) {
+        if text.is_empty() {
+            return;
+        }
         let node = layout_box.node().unwrap();
+        let (fragment_width, metrics) = self.measure_text(&node, &text);
+        if can_wrap {
+            self.break_line_if_needed(fragment_width);
+        }
+        self.column += text.chars().count();
+        self.is_after_line_break = false;
+        self.current_line()
+            .add_text_fragment(fragment_width, &metrics, layout_box, text);
+    }
+
+    fn measure_text(&self, node: &NodePtr, text: &str) -> (f32, InlineMetrics) {
         let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let font = font_of(node);
         let mut text_measurer = TextMeasure::new();
-        let text_size = text_measurer.measure(&text, font_size);
-        let fragment_width = text_size.width;
-        let fragment_height = text_size.height;
-        self.break_line_if_needed(fragment_width);
-        self.current_line()
-            .add_text_fragment(fragment_width, fragment_height, layout_box, text);
+        let text_size = text_measurer.measure_with_font(text, font_size, font);
+        let fragment_width = text_size.width + TextSpacing::of(node).extra_width(text);
+        (
+            fragment_width,
+            InlineMetrics::of_text(node, font, &text_measurer),
+        )
+    }
+
+    pub fn add_marker_fragment(&mut self, list_item: LayoutBoxPtr) {
+        let marker = match list_item.marker() {
+            Some(marker) => marker,
+            None => return,
+        };
+        let node = list_item.node().unwrap();
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let mut text_measurer = TextMeasure::new();
+        let marker_size = marker.size(font_size, &mut text_measurer);
+        let metrics = InlineMetrics::of_text(&node, DEFAULT_FONT, &text_measurer);
+
+        self.break_line_if_needed(marker_size.width);
+        self.is_after_line_break = false;
+        let line = self.current_line();
+        let fragment = LineFragment::new(
+            LineFragmentData::Marker(list_item.clone()),
+            Point::new(line.size.width, 0.),
+            marker_size.clone(),
+        )
+        .with_baseline(metrics.baseline);
+        line.extend_vertically(&metrics);
+        line.fragments.push(fragment);
+        line.size.width += marker_size.width;
     }
 
     fn break_line_if_needed(&mut self, next_fragment_width: f32) {
@@ -210,6 +402,7 @@ impl LineBoxBuilder {
         }
 
         self.line_boxes.push(LineBox::new());
+        self.column = 0;
     }
 
     fn update_last_line(&mut self) {
@@ -217,21 +410,17 @@ impl LineBoxBuilder {
             return;
         }
 
+        let strut = self.strut();
         let last_line = self.line_boxes.last_mut().unwrap();
 
-        if last_line.fragments.is_empty() {
-            let parent = self.parent.get_non_anonymous_parent();
-            let font_size = parent
-                .node()
-                .unwrap()
-                .get_style(&Property::FontSize)
-                .to_absolute_px();
-            let mut text_measurer = TextMeasure::new();
-            let text_size = text_measurer.measure("H", font_size);
-
-            last_line.size.height = text_size.height;
+        // In standards mode every line contains the strut of its block.
+        // Quirks modes let lines shrink to their content.
+        if last_line.fragments.is_empty() || self.quirks_mode == QuirksMode::NoQuirks {
+            last_line.extend_vertically(&strut);
         }
 
+        bidi::reorder_line(last_line, &self.parent);
+
         let mut x_offset = last_line
             .fragments
             .iter()
@@ -239,23 +428,45 @@ impl LineBoxBuilder {
             .fold(f32::INFINITY, |a, b| a.min(b));
 
         let remaining_space = self.parent.content_size().width - last_line.size.width;
+        let is_rtl = bidi::direction_of(&self.parent) == Direction::Rtl;
 
-        if let Some(node) = self.parent.node() {
+        // Anonymous blocks inherit the alignment of their parent
+        if let Some(node) = self.parent.get_non_anonymous_parent().node() {
             match node.get_style(&Property::TextAlign) {
                 Value::TextAlign(TextAlign::Center) => {
                     x_offset += remaining_space / 2.;
                 }
+                Value::TextAlign(TextAlign::Right) => {
+                    x_offset += remaining_space;
+                }
+                Value::TextAlign(TextAlign::Start | TextAlign::Justify) if is_rtl => {
+                    x_offset += remaining_space;
+                }
+                Value::TextAlign(TextAlign::End) if !is_rtl => {
+                    x_offset += remaining_space;
+                }
                 _ => {}
             }
         }
 
         for fragment in &mut last_line.fragments {
-            let mut used_offset = Point::new(fragment.offset.x, self.current_offset_y);
+            // Fragments are aligned on the baseline of the line
+            let offset_y = self.current_offset_y + last_line.baseline - fragment.baseline;
+            let mut used_offset = Point::new(fragment.offset.x, offset_y);
             used_offset.translate(x_offset, 0.);
             fragment.set_offset(used_offset);
         }
     }
 
+    /// An empty inline box with the font and line height of the block that
+    /// every line starts with
+    /// https://www.w3.org/TR/CSS2/visudet.html#strut
+    fn strut(&self) -> InlineMetrics {
+        let parent = self.parent.get_non_anonymous_parent();
+        let node = parent.node().unwrap();
+        InlineMetrics::of_text(&node, font_of(&node), &TextMeasure::new())
+    }
+
     fn current_line(&mut self) -> &mut LineBox {
         if self.line_boxes.is_empty() {
             self.line_boxes.push(LineBox::new());
@@ -263,3 +474,61 @@ impl LineBoxBuilder {
         self.line_boxes.last_mut().unwrap()
     }
 }
+
+/// Replace the tabs of the text with spaces up to the next tab stop, every
+/// `tab_size` columns. The text starts at `column` on its line.
+/// https://www.w3.org/TR/css-text-3/#white-space-phase-2
+fn expand_tabs(text: &str, column: usize, tab_size: usize) -> String {
+    if !text.contains('\t') {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut column = column;
+    for ch in text.chars() {
+        if ch != '\t' {
+            result.push(ch);
+            column += 1;
+            continue;
+        }
+        // A tab with a zero tab size is rendered as nothing
+        let spaces = match tab_size {
+            0 => 0,
+            _ => tab_size - column % tab_size,
+        };
+        result.extend(std::iter::repeat_n(' ', spaces));
+        column += spaces;
+    }
+    result
+}
+
+/// Split the text after every run of spaces and tabs, where lines can wrap
+fn split_after_spaces(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous_is_space = false;
+    for (offset, ch) in text.char_indices() {
+        let is_space = ch == ' ' || ch == '\t';
+        if previous_is_space && !is_space {
+            words.push(&text[start..offset]);
+            start = offset;
+        }
+        previous_is_space = is_space;
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("a\tbc\td", 0, 4), "a   bc  d");
+        assert_eq!(expand_tabs("\tx", 6, 8), "  x");
+        assert_eq!(expand_tabs("a\tb", 0, 0), "ab");
+        assert_eq!(split_after_spaces("if  x\ty "), vec!["if  ", "x\t", "y "]);
+    }
+}
@@ -0,0 +1,428 @@
+use gfx::TextMeasure;
+use shared::primitive::Rect;
+use style_types::Property;
+
+use crate::{
+    formatting_context::{BaseFormattingContext, FormattingContext, LayoutContext},
+    layout_box::LayoutBoxPtr,
+    text_fragments::font_of,
+};
+
+/// Height of the math axis above the baseline, where fraction bars are
+/// centered, in em
+const AXIS_HEIGHT: f32 = 0.25;
+/// Thickness of fraction bars, in em
+const FRACTION_RULE_THICKNESS: f32 = 0.06;
+/// Gap between a fraction bar and its numerator or denominator, in em
+const FRACTION_GAP: f32 = 0.15;
+/// Space on each side of a fraction, in em
+const FRACTION_PADDING: f32 = 0.1;
+/// Minimum distance from the baseline of the base to the baseline of its
+/// superscript, in em
+const SUPERSCRIPT_SHIFT_UP: f32 = 0.4;
+/// How far below the top of a tall base its superscript is placed, in em
+const SUPERSCRIPT_BASELINE_DROP: f32 = 0.5;
+/// Minimum distance from the baseline of the base to the baseline of its
+/// subscript, in em
+const SUBSCRIPT_SHIFT_DOWN: f32 = 0.25;
+/// Space after scripts, in em
+const SCRIPT_SPACE: f32 = 0.05;
+/// Space on each side of binary operators and relations, in em
+const OPERATOR_SPACE: f32 = 4. / 18.;
+
+/// Lays out the content of a math box: rows of token elements with
+/// superscripts, subscripts and fractions. The text of tokens is measured
+/// like other text, and the laid out text and fraction bars are stored on
+/// the math box to be painted.
+/// https://w3c.github.io/mathml-core/#layout-algorithms
+///
+/// Other MathML elements lay out their children in a row.
+#[derive(Debug)]
+pub struct MathFormattingContext {
+    base: BaseFormattingContext,
+}
+
+/// Text and fraction bars of a math box, relative to its content box
+#[derive(Debug, Clone, Default)]
+pub struct MathLayout {
+    /// Distance from the top of the content box to the baseline of the math
+    pub baseline: f32,
+    pub fragments: Vec<MathFragment>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MathFragment {
+    pub data: MathFragmentData,
+    pub rect: Rect,
+}
+
+#[derive(Debug, Clone)]
+pub enum MathFragmentData {
+    /// Text of a token, drawn with the style of its text run
+    Text(LayoutBoxPtr, String),
+    /// Bar of a fraction, drawn in the color of the `mfrac` element
+    FractionBar(LayoutBoxPtr),
+}
+
+/// A laid out part of the math, with fragments relative to its left edge
+/// and its baseline
+#[derive(Debug, Default)]
+struct MathBox {
+    width: f32,
+    /// Distance from the top of the box to its baseline
+    ascent: f32,
+    /// Distance from the baseline to the bottom of the box
+    descent: f32,
+    fragments: Vec<MathFragment>,
+}
+
+impl MathBox {
+    /// Place `other` at `x`, with its baseline raised `shift` above the
+    /// baseline of this box. The width of this box is left to the caller.
+    fn place(&mut self, other: MathBox, x: f32, shift: f32) {
+        self.ascent = f32::max(self.ascent, other.ascent + shift);
+        self.descent = f32::max(self.descent, other.descent - shift);
+        for mut fragment in other.fragments {
+            fragment.rect.translate(x, -shift);
+            self.fragments.push(fragment);
+        }
+    }
+}
+
+impl FormattingContext for MathFormattingContext {
+    fn run(&self, _context: &LayoutContext, layout_node: LayoutBoxPtr) {
+        let mut text_measurer = TextMeasure::new();
+        let math = layout_math(&layout_node, &mut text_measurer);
+
+        // Block math is centered in its line
+        let offset_x = match layout_node.is_block() {
+            true => f32::max((layout_node.content_size().width - math.width) / 2., 0.),
+            false => {
+                layout_node.set_content_width(math.width);
+                0.
+            }
+        };
+        layout_node.set_content_height(math.ascent + math.descent);
+
+        let mut fragments = math.fragments;
+        for fragment in &mut fragments {
+            fragment.rect.translate(offset_x, math.ascent);
+        }
+        layout_node.math.replace(Some(MathLayout {
+            baseline: math.ascent,
+            fragments,
+        }));
+    }
+
+    fn base(&self) -> &BaseFormattingContext {
+        &self.base
+    }
+}
+
+impl MathFormattingContext {
+    pub fn new(base: BaseFormattingContext) -> Self {
+        Self { base }
+    }
+}
+
+fn layout_math(layout_box: &LayoutBoxPtr, text_measurer: &mut TextMeasure) -> MathBox {
+    let children = layout_box
+        .iterate_children()
+        .filter(|child| !is_collapsible_whitespace(child))
+        .collect::<Vec<_>>();
+    let node = match layout_box.node() {
+        Some(node) => node,
+        None => return layout_row(&children, text_measurer),
+    };
+    if let Some(text) = node.as_text_opt() {
+        return layout_text(layout_box, &text.get_data(), text_measurer);
+    }
+
+    let tag_name = node
+        .as_element_opt()
+        .map(|element| element.tag_name())
+        .unwrap_or_default();
+    let em = node.get_style(&Property::FontSize).to_absolute_px();
+    let mut layout = |child: &LayoutBoxPtr| layout_math(child, text_measurer);
+    match (tag_name.as_str(), children.as_slice()) {
+        ("mfrac", [numerator, denominator]) => {
+            let numerator = layout(numerator);
+            let denominator = layout(denominator);
+            layout_fraction(layout_box, numerator, denominator, em)
+        }
+        ("msup", [base, superscript]) => {
+            let base = layout(base);
+            let superscript = layout(superscript);
+            layout_scripts(base, None, Some(superscript), em)
+        }
+        ("msub", [base, subscript]) => {
+            let base = layout(base);
+            let subscript = layout(subscript);
+            layout_scripts(base, Some(subscript), None, em)
+        }
+        ("msubsup", [base, subscript, superscript]) => {
+            let base = layout(base);
+            let subscript = layout(subscript);
+            let superscript = layout(superscript);
+            layout_scripts(base, Some(subscript), Some(superscript), em)
+        }
+        ("mo", _) if is_spaced_operator(&node.descendant_text_content()) => {
+            let operator = layout_row(&children, text_measurer);
+            let space = OPERATOR_SPACE * em;
+            let mut spaced = MathBox {
+                width: operator.width + 2. * space,
+                ..Default::default()
+            };
+            spaced.place(operator, space, 0.);
+            spaced
+        }
+        _ => layout_row(&children, text_measurer),
+    }
+}
+
+fn layout_row(children: &[LayoutBoxPtr], text_measurer: &mut TextMeasure) -> MathBox {
+    let mut row = MathBox::default();
+    for child in children {
+        let child = layout_math(child, text_measurer);
+        let x = row.width;
+        row.width += child.width;
+        row.place(child, x, 0.);
+    }
+    row
+}
+
+/// Text with its white space collapsed, sitting on the baseline with the
+/// ascent and descent of its font
+fn layout_text(
+    layout_box: &LayoutBoxPtr,
+    content: &str,
+    text_measurer: &mut TextMeasure,
+) -> MathBox {
+    let text = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return MathBox::default();
+    }
+    let node = layout_box.node().unwrap();
+    let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+    let font = font_of(&node);
+    let width = text_measurer
+        .measure_with_font(&text, font_size, font)
+        .width;
+    let metrics = text_measurer.font_metrics(font_size, font);
+
+    MathBox {
+        width,
+        ascent: metrics.ascent,
+        descent: metrics.descent,
+        fragments: vec![MathFragment {
+            data: MathFragmentData::Text(layout_box.clone(), text),
+            rect: Rect::new(0., -metrics.ascent, width, metrics.height()),
+        }],
+    }
+}
+
+/// The numerator and the denominator centered above and below a bar on the
+/// math axis
+/// https://w3c.github.io/mathml-core/#fraction-with-nonzero-line-thickness
+fn layout_fraction(
+    mfrac: &LayoutBoxPtr,
+    numerator: MathBox,
+    denominator: MathBox,
+    em: f32,
+) -> MathBox {
+    let thickness = f32::max(FRACTION_RULE_THICKNESS * em, 1.);
+    let axis = AXIS_HEIGHT * em;
+    let gap = FRACTION_GAP * em;
+    let padding = FRACTION_PADDING * em;
+    let width = f32::max(numerator.width, denominator.width) + 2. * padding;
+
+    let numerator_shift = axis + thickness / 2. + gap + numerator.descent;
+    let denominator_shift = axis - thickness / 2. - gap - denominator.ascent;
+    let bar = Rect::new(0., -(axis + thickness / 2.), width, thickness);
+
+    let mut fraction = MathBox {
+        width,
+        ascent: axis + thickness / 2.,
+        descent: f32::max(thickness / 2. - axis, 0.),
+        fragments: Vec::new(),
+    };
+    let numerator_x = (width - numerator.width) / 2.;
+    let denominator_x = (width - denominator.width) / 2.;
+    fraction.place(numerator, numerator_x, numerator_shift);
+    fraction.place(denominator, denominator_x, denominator_shift);
+    fraction.fragments.push(MathFragment {
+        data: MathFragmentData::FractionBar(mfrac.clone()),
+        rect: bar,
+    });
+    fraction
+}
+
+/// The base followed by its scripts, the superscript raised and the
+/// subscript lowered to clear the base
+/// https://w3c.github.io/mathml-core/#base-with-subscript
+fn layout_scripts(
+    base: MathBox,
+    subscript: Option<MathBox>,
+    superscript: Option<MathBox>,
+    em: f32,
+) -> MathBox {
+    let mut scripts = MathBox::default();
+    let (base_width, base_ascent, base_descent) = (base.width, base.ascent, base.descent);
+    scripts.place(base, 0., 0.);
+
+    let mut scripts_width: f32 = 0.;
+    if let Some(subscript) = subscript {
+        let shift_down = f32::max(SUBSCRIPT_SHIFT_DOWN * em, base_descent);
+        scripts_width = scripts_width.max(subscript.width);
+        scripts.place(subscript, base_width, -shift_down);
+    }
+    if let Some(superscript) = superscript {
+        let shift_up = f32::max(
+            SUPERSCRIPT_SHIFT_UP * em,
+            base_ascent - SUPERSCRIPT_BASELINE_DROP * em,
+        );
+        scripts_width = scripts_width.max(superscript.width);
+        scripts.place(superscript, base_width, shift_up);
+    }
+    scripts.width = base_width + scripts_width + SCRIPT_SPACE * em;
+    scripts
+}
+
+/// Binary operators and relations have space around them, other operators
+/// such as parentheses and separators don't
+fn is_spaced_operator(operator: &str) -> bool {
+    matches!(
+        operator.trim(),
+        "+" | "-" | "−" | "=" | "<" | ">" | "±" | "×" | "÷" | "·" | "≤" | "≥" | "≠" | "≈" | "→"
+    )
+}
+
+fn is_collapsible_whitespace(layout_box: &LayoutBoxPtr) -> bool {
+    match layout_box.node() {
+        Some(node) => match node.as_text_opt() {
+            Some(text) => text.get_data().trim().is_empty(),
+            None => false,
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::document::QuirksMode;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
+
+    use super::*;
+    use crate::formatting_context::{establish_context, FormattingContextType};
+    use crate::utils::build_tree;
+
+    const MATH_CSS: &str = r#"
+div {
+    display: block;
+}
+math {
+    display: math;
+}
+msup > * + * {
+    font-size: 0.71em;
+}
+"#;
+
+    fn fragment_rect(math: &MathLayout, content: &str) -> Rect {
+        math.fragments
+            .iter()
+            .find_map(|fragment| match &fragment.data {
+                MathFragmentData::Text(_, text) if text == content => Some(fragment.rect.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_layout_math() {
+        let doc = document();
+        let dom = element(
+            "div",
+            doc.clone(),
+            vec![element(
+                "math",
+                doc.clone(),
+                vec![
+                    element(
+                        "msup",
+                        doc.clone(),
+                        vec![
+                            element("mi", doc.clone(), vec![text("x", doc.clone())]),
+                            element("mn", doc.clone(), vec![text("2", doc.clone())]),
+                        ],
+                    ),
+                    element("mo", doc.clone(), vec![text("+", doc.clone())]),
+                    element(
+                        "mfrac",
+                        doc.clone(),
+                        vec![
+                            element("mi", doc.clone(), vec![text("a", doc.clone())]),
+                            element("mn", doc.clone(), vec![text(" 10 ", doc.clone())]),
+                        ],
+                    ),
+                ],
+            )],
+        );
+        let root = build_tree(dom, MATH_CSS);
+        let math_box = root.first_child().unwrap();
+        assert!(math_box.is_math());
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        establish_context(
+            FormattingContextType::MathFormattingContext,
+            math_box.clone(),
+        );
+        math_box
+            .formatting_context()
+            .run(&layout_context, math_box.clone());
+        let math = math_box.math().unwrap();
+
+        let base = fragment_rect(&math, "x");
+        let superscript = fragment_rect(&math, "2");
+        let operator = fragment_rect(&math, "+");
+        let numerator = fragment_rect(&math, "a");
+        let denominator = fragment_rect(&math, "10");
+
+        // The superscript is smaller, raised and after the base
+        assert!(superscript.height < base.height);
+        assert!(superscript.y < base.y);
+        assert!(superscript.x >= base.x + base.width);
+        // The operator has space around it
+        assert!(operator.x > superscript.x + superscript.width);
+        assert!(numerator.x > operator.x + operator.width);
+
+        // The bar of the fraction is between its numerator and denominator,
+        // above the baseline
+        let bar = math
+            .fragments
+            .iter()
+            .find_map(|fragment| match fragment.data {
+                MathFragmentData::FractionBar(_) => Some(fragment.rect.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert!(numerator.y + numerator.height <= bar.y);
+        assert!(bar.y + bar.height <= denominator.y);
+        assert!(bar.y + bar.height < math.baseline);
+        assert!(bar.width >= denominator.width);
+        // The narrower numerator is centered over the denominator
+        let center = |rect: &Rect| rect.x + rect.width / 2.;
+        assert!((center(&numerator) - center(&denominator)).abs() < 0.01);
+
+        // The math box is as large as its content, and its baseline is
+        // that of the base
+        assert!(base.y < math.baseline && math.baseline < base.y + base.height);
+        assert_eq!(math_box.content_size().width, bar.x + bar.width);
+        assert!(denominator.y + denominator.height <= math_box.content_size().height);
+    }
+}
@@ -1,3 +1,7 @@
+pub mod bidi;
 pub mod block;
+pub mod grid;
 pub mod inline;
 pub mod line_box;
+pub mod math;
+pub mod multicol;
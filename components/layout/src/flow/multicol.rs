@@ -0,0 +1,307 @@
+use shared::primitive::{Point, Rect};
+use style_types::{
+    values::prelude::{ColumnCount, Position},
+    Property, Value,
+};
+
+use crate::{
+    fragmentation::{BreakOpportunities, FragmentArea, FragmentationContext},
+    layout_box::LayoutBoxPtr,
+};
+
+/// Steps of the search for the height of balanced columns, enough for a
+/// precision under a hundredth of a pixel on long documents
+const BALANCING_STEPS: usize = 24;
+
+/// Number and width of the columns of a multi-column container
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnLayout {
+    pub count: usize,
+    pub width: f32,
+    pub gap: f32,
+}
+
+impl ColumnLayout {
+    /// Used column count and width for the content width of the container.
+    /// `column-width` is the minimum width of a column, `column-count` the
+    /// maximum number of columns. A `normal` gap is `1em`.
+    /// https://www.w3.org/TR/css-multicol-1/#pseudo-algorithm
+    pub fn of(layout_box: &LayoutBoxPtr) -> Self {
+        let node = layout_box.node().unwrap();
+        let available = layout_box.content_size().width;
+        let gap = match node.get_style(&Property::ColumnGap) {
+            Value::Normal => node.get_style(&Property::FontSize).to_absolute_px(),
+            value => value.to_px(available),
+        };
+        let count = match node.get_style(&Property::ColumnCount) {
+            Value::ColumnCount(ColumnCount(count)) => Some(count as usize),
+            _ => None,
+        };
+        let width = match node.get_style(&Property::ColumnWidth) {
+            Value::Auto => None,
+            value => Some(f32::max(value.to_px(available), 1.)),
+        };
+
+        let count = match (count, width) {
+            (Some(count), None) => count,
+            (count, Some(width)) => {
+                let fitting = f32::max(((available + gap) / (width + gap)).floor(), 1.) as usize;
+                count.map_or(fitting, |count| usize::min(count, fitting))
+            }
+            (None, None) => 1,
+        };
+        let width = (available - (count - 1) as f32 * gap) / count as f32;
+
+        Self {
+            count,
+            width: f32::max(width, 0.),
+            gap,
+        }
+    }
+}
+
+/// Split the content of a multi-column container, laid out as a strip at
+/// the width of a column, into balanced columns of at most `max_height`,
+/// and move it into them. Returns the height of the columns.
+///
+/// Boxes that cross the end of a column move with the column they start
+/// in, and only their lines and children are split between columns, so
+/// their backgrounds and borders are not painted in the next columns.
+/// https://www.w3.org/TR/css-multicol-1/#cf
+pub fn move_into_columns(
+    container: &LayoutBoxPtr,
+    columns: &ColumnLayout,
+    max_height: Option<f32>,
+) -> f32 {
+    let top = container.absolute_location().y;
+    let bottom = top + container.content_size().height;
+    let opportunities = BreakOpportunities::collect(container, FragmentationContext::Column);
+    let areas = balance(&opportunities, top, bottom, columns.count, max_height);
+
+    let column_height = areas.iter().map(|area| area.height).fold(0., f32::max);
+    container.columns.replace(
+        (0..areas.len())
+            .map(|index| {
+                let x = index as f32 * (columns.width + columns.gap);
+                Rect::new(x, 0., columns.width, column_height)
+            })
+            .collect(),
+    );
+
+    let shifts = ColumnShifts::new(&areas, columns);
+    let no_shift = Point::new(0., 0.);
+    move_lines(container, &shifts, &no_shift);
+    container.for_each_child(|child| move_box(&child, &shifts, &no_shift));
+    column_height
+}
+
+/// The smallest column height that fits the content from `top` to
+/// `bottom` in `count` columns. Content that doesn't fit in columns of
+/// `max_height` overflows in additional columns.
+fn balance(
+    opportunities: &BreakOpportunities,
+    top: f32,
+    bottom: f32,
+    count: usize,
+    max_height: Option<f32>,
+) -> Vec<FragmentArea> {
+    let content_height = bottom - top;
+    let max_height = max_height.unwrap_or(content_height);
+    let fits = |height: f32| opportunities.fragment(top, bottom, height).len() <= count;
+    if content_height <= 0. || !fits(max_height) {
+        return opportunities.fragment(top, bottom, max_height);
+    }
+
+    let mut low = f32::min(content_height / count as f32, max_height);
+    let mut high = max_height;
+    for _ in 0..BALANCING_STEPS {
+        let middle = (low + high) / 2.;
+        if fits(middle) {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+    // Columns are filled again at the height of the tallest one, which
+    // rounding may have made taller than the height searched
+    let column_height = opportunities
+        .fragment(top, bottom, high)
+        .iter()
+        .map(|area| area.height)
+        .fold(0., f32::max);
+    opportunities.fragment(top, bottom, column_height)
+}
+
+/// Where the content of each column moves from the strip it was laid out
+/// in: right by whole columns, and up to the top of the container
+struct ColumnShifts {
+    tops: Vec<f32>,
+    shifts: Vec<Point>,
+}
+
+impl ColumnShifts {
+    fn new(areas: &[FragmentArea], columns: &ColumnLayout) -> Self {
+        let strip_top = areas.first().map_or(0., |area| area.top);
+        Self {
+            tops: areas.iter().map(|area| area.top).collect(),
+            shifts: areas
+                .iter()
+                .enumerate()
+                .map(|(index, area)| {
+                    let x = index as f32 * (columns.width + columns.gap);
+                    Point::new(x, strip_top - area.top)
+                })
+                .collect(),
+        }
+    }
+
+    /// Column of the content starting at `y` in the strip
+    fn column_at(&self, y: f32) -> usize {
+        self.tops.iter().rposition(|top| *top <= y).unwrap_or(0)
+    }
+
+    /// Column of the content ending at `y` in the strip
+    fn column_before(&self, y: f32) -> usize {
+        self.tops.iter().rposition(|top| *top < y).unwrap_or(0)
+    }
+}
+
+/// Move the box with the column it starts in, given the shift already
+/// applied to its containing block, then split its content if it crosses
+/// the end of the column. Nested multi-column containers are not split.
+fn move_box(layout_box: &LayoutBoxPtr, shifts: &ColumnShifts, applied: &Point) {
+    if !layout_box.is_block() || layout_box.is_positioned(Position::Absolute) {
+        return;
+    }
+    let margin = layout_box.box_model().borrow().margin.clone();
+    let border_box = layout_box.border_box_absolute();
+    let top = border_box.y - margin.top - applied.y;
+    let bottom = border_box.y + border_box.height + margin.bottom - applied.y;
+
+    let first_column = shifts.column_at(top);
+    let last_column = usize::max(shifts.column_before(bottom), first_column);
+    let shift = shifts.shifts[first_column].clone();
+    let mut offset = layout_box.offset();
+    offset.translate(shift.x - applied.x, shift.y - applied.y);
+    layout_box.set_offset(offset.x, offset.y);
+
+    if last_column == first_column || layout_box.is_multicol_container() {
+        return;
+    }
+    move_lines(layout_box, shifts, &shift);
+    layout_box.for_each_child(|child| move_box(&child, shifts, &shift));
+}
+
+/// Move the lines of a block to the columns they start in
+fn move_lines(layout_box: &LayoutBoxPtr, shifts: &ColumnShifts, applied: &Point) {
+    if !layout_box.children_are_inline() {
+        return;
+    }
+    let mut line_top = layout_box.absolute_location().y - applied.y;
+    for line in layout_box.lines().borrow_mut().iter_mut() {
+        let shift = &shifts.shifts[shifts.column_at(line_top)];
+        for fragment in &mut line.fragments {
+            fragment
+                .offset
+                .translate(shift.x - applied.x, shift.y - applied.y);
+        }
+        line_top += line.size.height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatting_context::{establish_context, FormattingContextType, LayoutContext};
+    use crate::layout_box::{BoxData, LayoutBox};
+    use crate::utils::*;
+    use dom::document::QuirksMode;
+    use shared::primitive::Size;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::*;
+
+    /// Lay out a multi-column container with a one line paragraph followed
+    /// by a narrow paragraph of four 20px lines, the last one holding the
+    /// trailing space
+    fn layout_columns(extra_css: &str) -> LayoutBoxPtr {
+        let document = document();
+        let dom = element(
+            "div.columns",
+            document.clone(),
+            vec![
+                element("p.a", document.clone(), vec![text("one", document.clone())]),
+                element(
+                    "p.b",
+                    document.clone(),
+                    vec![text("a b c", document.clone())],
+                ),
+            ],
+        );
+        let css = format!(
+            "{}
+            .columns {{ column-count: 2; column-gap: 20px; }}
+            p {{ margin: 0; line-height: 20px; }}
+            .b {{ width: 10px; }}
+            {}",
+            SHARED_CSS, extra_css
+        );
+        let root = build_tree(dom, &css);
+
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box.append_child(root.clone());
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+        root
+    }
+
+    fn line_offsets(layout_box: &LayoutBoxPtr) -> Vec<Point> {
+        layout_box
+            .lines()
+            .borrow()
+            .iter()
+            .map(|line| line.fragments[0].offset.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_balance_columns() {
+        let root = layout_columns("");
+        let paragraph = |index| root.nth_child(index).unwrap();
+
+        // Two 240px columns of 60px. The last two lines of the second
+        // paragraph move to the top of the second column, text sits 2px
+        // below the top of its line.
+        assert_eq!(
+            root.columns(),
+            vec![Rect::new(0., 0., 240., 60.), Rect::new(260., 0., 240., 60.)]
+        );
+        assert_eq!(root.content_size(), Size::new(500., 60.));
+        assert_eq!(paragraph(1).offset(), Point::new(0., 20.));
+        let lines = line_offsets(&paragraph(1));
+        assert_eq!(lines[0], Point::new(0., 2.));
+        assert_eq!(lines[1], Point::new(0., 22.));
+        assert_eq!(lines[2], Point::new(260., -18.));
+    }
+
+    #[test]
+    fn test_forced_column_break() {
+        let root = layout_columns(".b { break-before: column; }");
+        let paragraph = |index| root.nth_child(index).unwrap();
+
+        // The second column starts with the second paragraph, and is as
+        // tall as it
+        assert_eq!(root.content_size().height, 80.);
+        assert_eq!(paragraph(0).offset(), Point::new(0., 0.));
+        assert_eq!(paragraph(1).offset(), Point::new(260., 0.));
+    }
+}
@@ -1,30 +1,43 @@
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
-use shared::{primitive::*, tree_node::WeakTreeNode};
+use dom::document::QuirksMode;
+use shared::primitive::*;
 use style_types::{
-    values::{display::InnerDisplayType, prelude::Display},
+    values::{
+        display::InnerDisplayType,
+        prelude::{Display, WritingMode},
+    },
     Property, Value,
 };
 
 use crate::{
-    flow::{block::BlockFormattingContext, inline::InlineFormattingContext},
-    layout_box::{LayoutBox, LayoutBoxPtr},
+    flow::{
+        block::BlockFormattingContext, grid::GridFormattingContext,
+        inline::InlineFormattingContext, math::MathFormattingContext,
+    },
+    layout_box::{LayoutBoxPtr, WeakLayoutBoxPtr},
 };
 
 pub struct LayoutContext {
     pub viewport: Rect,
+    /// Document mode that selects the layout quirks to apply
+    pub quirks_mode: QuirksMode,
+    /// Writing mode of the flow being laid out
+    pub writing_mode: WritingMode,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum FormattingContextType {
     BlockFormattingContext,
     InlineFormattingContext,
+    GridFormattingContext,
+    MathFormattingContext,
 }
 
 #[derive(Debug)]
 pub struct BaseFormattingContext {
     pub context_type: FormattingContextType,
-    pub establish_by: RefCell<WeakTreeNode<LayoutBox>>,
+    pub establish_by: RefCell<WeakLayoutBoxPtr>,
 }
 
 pub trait FormattingContext: Debug {
@@ -58,7 +71,7 @@ pub fn establish_context(
 ) -> Rc<dyn FormattingContext> {
     let base_context = BaseFormattingContext {
         context_type: context_type.clone(),
-        establish_by: RefCell::new(WeakTreeNode::from(&establish_by.0)),
+        establish_by: RefCell::new(establish_by.downgrade()),
     };
     let context: Rc<dyn FormattingContext> = match context_type {
         FormattingContextType::BlockFormattingContext => {
@@ -67,6 +80,12 @@ pub fn establish_context(
         FormattingContextType::InlineFormattingContext => {
             Rc::new(InlineFormattingContext::new(base_context))
         }
+        FormattingContextType::GridFormattingContext => {
+            Rc::new(GridFormattingContext::new(base_context))
+        }
+        FormattingContextType::MathFormattingContext => {
+            Rc::new(MathFormattingContext::new(base_context))
+        }
     };
     use_context(context.clone(), establish_by);
     context
@@ -88,7 +107,8 @@ fn get_formatting_context_type(layout_node: LayoutBoxPtr) -> FormattingContextTy
 
     let display = node.get_style(&Property::Display);
     let inner_display = match display {
-        Value::Display(Display::Full(_, ref inner)) => inner,
+        Value::Display(Display::Full(_, ref inner))
+        | Value::Display(Display::ListItem(_, ref inner)) => inner,
         _ => unreachable!(),
     };
 
@@ -101,6 +121,8 @@ fn get_formatting_context_type(layout_node: LayoutBoxPtr) -> FormattingContextTy
             }
         }
         InnerDisplayType::FlowRoot => FormattingContextType::BlockFormattingContext,
+        InnerDisplayType::Grid => FormattingContextType::GridFormattingContext,
+        InnerDisplayType::Math => FormattingContextType::MathFormattingContext,
         _ => unimplemented!("Unsupported display type: {:#?}", display),
     }
 }
@@ -114,10 +136,26 @@ pub fn create_independent_formatting_context_if_needed(
 
     let formatting_context_type = get_formatting_context_type(node.clone());
 
+    if let FormattingContextType::GridFormattingContext = formatting_context_type {
+        let base_context = BaseFormattingContext {
+            context_type: formatting_context_type,
+            establish_by: RefCell::new(node.downgrade()),
+        };
+        return Some(Rc::new(GridFormattingContext::new(base_context)));
+    }
+
+    if let FormattingContextType::MathFormattingContext = formatting_context_type {
+        let base_context = BaseFormattingContext {
+            context_type: formatting_context_type,
+            establish_by: RefCell::new(node.downgrade()),
+        };
+        return Some(Rc::new(MathFormattingContext::new(base_context)));
+    }
+
     if let FormattingContextType::BlockFormattingContext = formatting_context_type {
         let base_context = BaseFormattingContext {
             context_type: formatting_context_type,
-            establish_by: RefCell::new(WeakTreeNode::from(&node.0)),
+            establish_by: RefCell::new(node.downgrade()),
         };
         return Some(Rc::new(BlockFormattingContext::new(base_context)));
     }
@@ -125,7 +163,7 @@ pub fn create_independent_formatting_context_if_needed(
     if node.children_are_inline() {
         let base_context = BaseFormattingContext {
             context_type: FormattingContextType::InlineFormattingContext,
-            establish_by: RefCell::new(WeakTreeNode::from(&node.0)),
+            establish_by: RefCell::new(node.downgrade()),
         };
         return Some(Rc::new(InlineFormattingContext::new(base_context)));
     }
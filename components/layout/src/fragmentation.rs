@@ -0,0 +1,142 @@
+use style_types::{Property, Value};
+
+use crate::layout_box::LayoutBoxPtr;
+
+/// Kind of container that laid out content is split into
+/// https://www.w3.org/TR/css-break-3/#fragmentation-model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FragmentationContext {
+    Page,
+    Column,
+}
+
+/// Part of a continuous strip of laid out content shown in a fragment, like
+/// a page or a column, in page coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub struct FragmentArea {
+    pub top: f32,
+    pub height: f32,
+}
+
+/// Where fragments may or must end
+#[derive(Debug, Default)]
+pub struct BreakOpportunities {
+    /// Positions forced by `break-before` and `break-after`
+    forced: Vec<f32>,
+    /// Line boxes, which are moved to the next fragment instead of being
+    /// split when they cross the end of a fragment. Pairs of the top and
+    /// the bottom.
+    lines: Vec<(f32, f32)>,
+}
+
+impl BreakOpportunities {
+    /// Collect the break opportunities in the content of the box. The
+    /// content of nested multi-column containers is already split into
+    /// columns, so it is not broken again.
+    pub fn collect(layout_box: &LayoutBoxPtr, context: FragmentationContext) -> Self {
+        let mut opportunities = Self::default();
+        opportunities.collect_lines(layout_box);
+        layout_box.for_each_child(|child| {
+            opportunities.collect_box(&child, context);
+        });
+        opportunities
+    }
+
+    fn collect_box(&mut self, layout_box: &LayoutBoxPtr, context: FragmentationContext) {
+        let node = layout_box.node().filter(|_| layout_box.is_block());
+        let is_forced = |property: &Property| match &node {
+            Some(node) => match node.get_style(property) {
+                Value::BreakBetween(value) => match context {
+                    FragmentationContext::Page => value.is_forced_page_break(),
+                    FragmentationContext::Column => value.is_forced_column_break(),
+                },
+                _ => false,
+            },
+            None => false,
+        };
+        let margin = layout_box.box_model().borrow().margin.clone();
+        let border_box = layout_box.border_box_absolute();
+
+        if is_forced(&Property::BreakBefore) {
+            self.forced.push(border_box.y - margin.top);
+        }
+
+        if !layout_box.is_multicol_container() {
+            self.collect_lines(layout_box);
+            layout_box.for_each_child(|child| self.collect_box(&child, context));
+        }
+
+        if is_forced(&Property::BreakAfter) {
+            self.forced
+                .push(border_box.y + border_box.height + margin.bottom);
+        }
+    }
+
+    fn collect_lines(&mut self, layout_box: &LayoutBoxPtr) {
+        if !layout_box.is_block() || !layout_box.children_are_inline() {
+            return;
+        }
+        let mut line_top = layout_box.absolute_location().y;
+        for line in layout_box.lines().borrow().iter() {
+            self.lines.push((line_top, line_top + line.size.height));
+            line_top += line.size.height;
+        }
+    }
+
+    /// Split the content from `top` to `bottom` into fragments of at most
+    /// `height` CSS pixels.
+    ///
+    /// A fragment ends early when a line would cross its bottom edge or
+    /// when a block forces a break. Content that doesn't fit in a fragment,
+    /// like a tall image, is sliced. Without a positive height, the content
+    /// stays in a single fragment.
+    pub fn fragment(&self, top: f32, bottom: f32, height: f32) -> Vec<FragmentArea> {
+        if height <= 0. {
+            return vec![FragmentArea {
+                top,
+                height: bottom - top,
+            }];
+        }
+
+        let mut fragments = Vec::new();
+        let mut fragment_top = top;
+        loop {
+            let mut fragment_bottom = fragment_top + height;
+
+            let forced_break = self
+                .forced
+                .iter()
+                .filter(|position| **position > fragment_top && **position < fragment_bottom)
+                .fold(None, |result: Option<f32>, position| {
+                    Some(result.map_or(*position, |result| result.min(*position)))
+                });
+
+            if let Some(forced_break) = forced_break {
+                fragment_bottom = forced_break;
+            } else if let Some(line_top) = self
+                .lines
+                .iter()
+                .filter(|(line_top, line_bottom)| {
+                    *line_top > fragment_top
+                        && *line_top < fragment_bottom
+                        && *line_bottom > fragment_bottom
+                })
+                .map(|(line_top, _)| *line_top)
+                .fold(None, |result: Option<f32>, line_top| {
+                    Some(result.map_or(line_top, |result| result.min(line_top)))
+                })
+            {
+                fragment_bottom = line_top;
+            }
+
+            fragments.push(FragmentArea {
+                top: fragment_top,
+                height: fragment_bottom - fragment_top,
+            });
+            if fragment_bottom >= bottom {
+                return fragments;
+            }
+            fragment_top = fragment_bottom;
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::rc::Rc;
+
+use dom::node::NodePtr;
+use shared::primitive::{Point, Rect};
+
+use crate::{flow::line_box::LineFragmentData, layout_box::LayoutBoxPtr, scroll::overflow_clip};
+
+/// Find the element painted on top at the point, in page coordinates.
+/// Text is attributed to the element that contains it.
+pub fn hit_test(root: &LayoutBoxPtr, point: &Point) -> Option<NodePtr> {
+    let layout_box = hit_test_box(root, point)?;
+    let node = layout_box.node()?;
+
+    if node.is_element() {
+        return Some(node);
+    }
+    node.parent()
+        .map(NodePtr)
+        .filter(|parent| parent.is_element())
+}
+
+pub(crate) fn hit_test_box(layout_box: &LayoutBoxPtr, point: &Point) -> Option<LayoutBoxPtr> {
+    // The content clipped by the box can't be hit outside the clip
+    let is_clipped = overflow_clip(layout_box).is_some_and(|clip| !clip.contains(point));
+    if !is_clipped {
+        if let Some(hit) = hit_test_content(layout_box, point) {
+            return Some(hit);
+        }
+    }
+
+    let is_hit = !layout_box.is_anonymous()
+        && layout_box.is_visible()
+        && layout_box.border_box_absolute().contains(point);
+    if is_hit {
+        return Some(layout_box.clone());
+    }
+    None
+}
+
+fn hit_test_content(layout_box: &LayoutBoxPtr, point: &Point) -> Option<LayoutBoxPtr> {
+    // Later siblings are painted on top of earlier ones
+    let mut children = Vec::new();
+    layout_box.for_each_child(|child| children.push(child));
+    for child in children.iter().rev() {
+        if let Some(hit) = hit_test_box(child, point) {
+            return Some(hit);
+        }
+    }
+
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        return hit_test_lines(layout_box, point);
+    }
+    None
+}
+
+fn hit_test_lines(containing_block: &LayoutBoxPtr, point: &Point) -> Option<LayoutBoxPtr> {
+    let location = containing_block.content_origin();
+
+    for line in containing_block.lines().borrow().iter().rev() {
+        for fragment in line.fragments.iter().rev() {
+            let layout_box = match &fragment.data {
+                LineFragmentData::Box(layout_box) | LineFragmentData::Text(layout_box, _) => {
+                    layout_box
+                }
+                LineFragmentData::Marker(_) => continue,
+            };
+            if layout_box.is_anonymous() || !layout_box.is_visible() {
+                continue;
+            }
+
+            let mut rect = Rect::from((location.clone(), fragment.size.clone()));
+            rect.translate(fragment.offset.x, fragment.offset.y);
+            if rect.contains(point) {
+                return Some(layout_box.clone());
+            }
+        }
+    }
+    None
+}
+
+/// The first box generated by the node, like the box of a form control
+pub fn node_box(root: &LayoutBoxPtr, node: &NodePtr) -> Option<LayoutBoxPtr> {
+    if matches!(root.node(), Some(box_node) if Rc::ptr_eq(&box_node, node)) {
+        return Some(root.clone());
+    }
+    let mut result = None;
+    root.for_each_child(|child| {
+        if result.is_none() {
+            result = node_box(&child, node);
+        }
+    });
+    result
+}
+
+/// Border boxes generated by the node, in page coordinates. Inline content
+/// is made of one rect per line fragment.
+pub fn node_rects(root: &LayoutBoxPtr, node: &NodePtr) -> Vec<Rect> {
+    let mut rects = Vec::new();
+    collect_node_rects(root, node, &mut rects);
+    rects
+}
+
+fn collect_node_rects(layout_box: &LayoutBoxPtr, node: &NodePtr, rects: &mut Vec<Rect>) {
+    let is_node_box = matches!(layout_box.node(), Some(box_node) if Rc::ptr_eq(&box_node, node));
+    // Inline boxes are split across lines, so they are reported per line
+    let is_split_across_lines = layout_box.is_inline() && !layout_box.is_inline_block();
+    if is_node_box && !is_split_across_lines {
+        rects.push(layout_box.border_box_absolute());
+        return;
+    }
+
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        let location = layout_box.content_origin();
+        for line in layout_box.lines().borrow().iter() {
+            let mut line_rect: Option<Rect> = None;
+            for fragment in &line.fragments {
+                let fragment_box = match &fragment.data {
+                    LineFragmentData::Box(fragment_box)
+                    | LineFragmentData::Text(fragment_box, _) => fragment_box,
+                    LineFragmentData::Marker(_) => continue,
+                };
+                // Atomic inlines are reported through their own box
+                let is_in_node = match fragment_box.node() {
+                    Some(fragment_node) => {
+                        Rc::ptr_eq(&fragment_node, node)
+                            || fragment_node
+                                .find_first_ancestor(|ancestor| Rc::ptr_eq(&ancestor, node))
+                                .is_some()
+                    }
+                    None => false,
+                };
+                if !is_in_node || fragment_box.is_inline_block() || fragment.size.width == 0. {
+                    continue;
+                }
+
+                let mut rect = Rect::from((location.clone(), fragment.size.clone()));
+                rect.translate(fragment.offset.x, fragment.offset.y);
+                line_rect = Some(match line_rect {
+                    Some(line_rect) => union(&line_rect, &rect),
+                    None => rect,
+                });
+            }
+            rects.extend(line_rect);
+        }
+    }
+
+    layout_box.for_each_child(|child| collect_node_rects(&child, node, rects));
+}
+
+/// The smallest rect that contains both rects
+pub fn union(a: &Rect, b: &Rect) -> Rect {
+    let x = f32::min(a.x, b.x);
+    let y = f32::min(a.y, b.y);
+    let right = f32::max(a.x + a.width, b.x + b.width);
+    let bottom = f32::max(a.y + a.height, b.y + b.height);
+    Rect::new(x, y, right - x, bottom - y)
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::document::QuirksMode;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
+
+    use super::*;
+    use crate::{
+        formatting_context::{establish_context, FormattingContextType, LayoutContext},
+        layout_box::{BoxData, LayoutBox},
+        utils::{build_tree, SHARED_CSS},
+    };
+
+    #[test]
+    fn test_hit_test() {
+        let document = document();
+        let dom = element(
+            "div#outer",
+            document.clone(),
+            vec![
+                element("div#first", document.clone(), vec![]),
+                element(
+                    "p",
+                    document.clone(),
+                    vec![element(
+                        "span#label",
+                        document.clone(),
+                        vec![text("hello", document.clone())],
+                    )],
+                ),
+            ],
+        );
+        let css = format!(
+            "{} #outer {{ padding-bottom: 20px; }} #first {{ height: 10px; }}",
+            SHARED_CSS
+        );
+
+        let root = build_tree(dom, &css);
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+        let initial_block_box = root.new_box(LayoutBox::new_anonymous(BoxData::block_box()));
+        establish_context(
+            FormattingContextType::BlockFormattingContext,
+            initial_block_box.clone(),
+        );
+        initial_block_box.append_child(root.clone());
+        initial_block_box
+            .formatting_context()
+            .run(&layout_context, initial_block_box.clone());
+
+        let id_at = |x: f32, y: f32| {
+            hit_test(&initial_block_box, &Point::new(x, y)).map(|node| node.as_element().id())
+        };
+
+        assert_eq!(id_at(5., 5.), Some(Some("first".to_string())));
+        assert_eq!(id_at(2., 12.), Some(Some("label".to_string())));
+        // the padding of the outer box below its children
+        let bottom_padding_y = root.content_size().height + 5.;
+        assert_eq!(
+            id_at(400., bottom_padding_y),
+            Some(Some("outer".to_string()))
+        );
+        assert_eq!(id_at(600., 5.), None);
+
+        let outer = root.node().unwrap();
+        let outer_rects = node_rects(&initial_block_box, &outer);
+        assert_eq!(outer_rects, vec![root.border_box_absolute()]);
+
+        let label = hit_test(&initial_block_box, &Point::new(2., 12.)).unwrap();
+        let label_rects = node_rects(&initial_block_box, &label);
+        assert_eq!(label_rects.len(), 1);
+        assert!(label_rects[0].contains(&Point::new(2., 12.)));
+    }
+}
@@ -1,25 +1,28 @@
-use std::{cell::RefCell, fmt::Debug, ops::Deref, rc::Rc};
+use std::{cell::RefCell, fmt::Debug, rc::Rc};
 
 use dom::node::NodePtr;
-use shared::{
-    primitive::{Point, Rect, Size},
-    tree_node::{TreeNode, TreeNodeHooks},
-};
+use shared::primitive::{Point, Rect, Size};
 use style_types::{
     values::{
         display::Display,
         display::{InnerDisplayType, OuterDisplayType},
-        prelude::Position,
+        prelude::{Position, Visibility, WritingMode},
     },
     Property, Value,
 };
 
 use crate::{
     box_model::BoxModel,
-    flow::line_box::LineBox,
+    flow::{line_box::LineBox, math::MathLayout},
     formatting_context::{FormattingContext, FormattingContextType},
+    list_marker::ListMarker,
+    replaced::replaced_size,
+    scroll::scroll_offset,
+    writing_mode::flow_relative_style,
 };
 
+pub use crate::arena::{LayoutBoxPtr, WeakLayoutBoxPtr};
+
 #[derive(Debug)]
 pub struct LayoutBox {
     pub data: BoxData,
@@ -28,25 +31,28 @@ pub struct LayoutBox {
     pub offset: RefCell<Point>,
     pub content_size: RefCell<Size>,
     pub formatting_context: RefCell<Option<Rc<dyn FormattingContext>>>,
+    pub marker: Option<ListMarker>,
+    /// Layout tree of the document loaded in an iframe
+    pub nested_document: RefCell<Option<LayoutBoxPtr>>,
+    /// Column boxes of a multi-column container, relative to its content
+    /// box
+    pub columns: RefCell<Vec<Rect>>,
+    /// Laid out content of a math box
+    pub math: RefCell<Option<MathLayout>>,
 }
 
-pub struct LayoutBoxPtr(pub TreeNode<LayoutBox>);
-
-impl TreeNodeHooks<LayoutBox> for LayoutBox {}
-impl Debug for LayoutBoxPtr {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
-    }
-}
-impl Deref for LayoutBoxPtr {
-    type Target = TreeNode<LayoutBox>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-impl Clone for LayoutBoxPtr {
-    fn clone(&self) -> Self {
-        LayoutBoxPtr(self.0.clone())
+impl LayoutBox {
+    /// Drop the boxes held by the line fragments, formatting context and
+    /// math fragments of the box, which are boxes of the same tree
+    pub(crate) fn release_references(&self) {
+        if let BoxData::BlockBox { lines } = &self.data {
+            lines.borrow_mut().clear();
+        }
+        self.formatting_context.replace(None);
+        self.math.replace(None);
+        if let Some(nested_document) = self.nested_document.replace(None) {
+            nested_document.teardown();
+        }
     }
 }
 
@@ -88,12 +94,17 @@ impl LayoutBox {
             } else {
                 match node.get_style(&Property::Display) {
                     Value::Display(d) => match d {
-                        Display::Full(ref outer, ref inner) => match (outer, inner) {
-                            (OuterDisplayType::Block, InnerDisplayType::Flow) => {
+                        Display::Full(ref outer, ref inner)
+                        | Display::ListItem(ref outer, ref inner) => match (outer, inner) {
+                            (OuterDisplayType::Block, InnerDisplayType::Flow)
+                            | (OuterDisplayType::Block, InnerDisplayType::Grid)
+                            | (OuterDisplayType::Block, InnerDisplayType::Math) => {
                                 BoxData::block_box()
                             }
                             (OuterDisplayType::Inline, InnerDisplayType::Flow)
-                            | (OuterDisplayType::Inline, InnerDisplayType::FlowRoot) => {
+                            | (OuterDisplayType::Inline, InnerDisplayType::FlowRoot)
+                            | (OuterDisplayType::Inline, InnerDisplayType::Grid)
+                            | (OuterDisplayType::Inline, InnerDisplayType::Math) => {
                                 BoxData::inline_box()
                             }
                             _ => unimplemented!("Unsupport display type: {:#?}", d),
@@ -110,6 +121,10 @@ impl LayoutBox {
             offset: Default::default(),
             content_size: Default::default(),
             formatting_context: RefCell::new(None),
+            marker: None,
+            nested_document: RefCell::new(None),
+            columns: RefCell::new(Vec::new()),
+            math: RefCell::new(None),
             data: box_data,
             node: Some(node),
         }
@@ -121,6 +136,10 @@ impl LayoutBox {
             offset: Default::default(),
             content_size: Default::default(),
             formatting_context: RefCell::new(None),
+            marker: None,
+            nested_document: RefCell::new(None),
+            columns: RefCell::new(Vec::new()),
+            math: RefCell::new(None),
             data,
             node: None,
         }
@@ -153,14 +172,13 @@ impl LayoutBoxPtr {
     }
 
     pub fn children_are_inline(&self) -> bool {
-        self.iterate_children()
-            .all(|child| LayoutBoxPtr(child).is_inline())
+        self.iterate_children().all(|child| child.is_inline())
     }
 
     pub fn is_block_container(&self) -> bool {
         let is_block = !self.children_are_inline();
         let is_inline_block = self.children_are_inline()
-            && match self.formatting_context.borrow().deref() {
+            && match self.formatting_context.borrow().as_deref() {
                 Some(context) => {
                     context.base().context_type == FormattingContextType::InlineFormattingContext
                 }
@@ -172,29 +190,20 @@ impl LayoutBoxPtr {
 
     pub fn containing_block(&self) -> Option<LayoutBoxPtr> {
         if self.is_positioned(Position::Static) || self.is_positioned(Position::Relative) {
-            return self
-                .find_first_ancestor(|parent| {
-                    let parent = LayoutBoxPtr(parent);
-                    parent.is_block_container() || parent.formatting_context.borrow().is_some()
-                })
-                .map(|node| LayoutBoxPtr(node));
+            return self.find_first_ancestor(|parent| {
+                parent.is_block_container() || parent.formatting_context.borrow().is_some()
+            });
         }
 
         if self.is_positioned(Position::Absolute) {
-            return self
-                .find_first_ancestor(|parent| !LayoutBoxPtr(parent).is_positioned(Position::Static))
-                .map(|node| LayoutBoxPtr(node));
+            return self.find_first_ancestor(|parent| !parent.is_positioned(Position::Static));
         }
 
         if self.is_positioned(Position::Fixed) {
-            return self
-                .find_first_ancestor(|parent| parent.parent().is_none())
-                .map(|node| LayoutBoxPtr(node));
+            return self.find_first_ancestor(|parent| parent.parent().is_none());
         }
 
-        return self
-            .find_first_ancestor(|parent| LayoutBoxPtr(parent).is_block_container())
-            .map(|node| LayoutBoxPtr(node));
+        return self.find_first_ancestor(|parent| parent.is_block_container());
     }
 
     pub fn can_have_children(&self) -> bool {
@@ -218,16 +227,79 @@ impl LayoutBoxPtr {
         }
     }
 
+    /// Check if the box is an atomic inline, like `inline-block` and
+    /// `inline-grid` boxes
     pub fn is_inline_block(&self) -> bool {
         match self.node() {
             Some(node) => match node.get_style(&Property::Display) {
-                Value::Display(Display::Full(_, InnerDisplayType::FlowRoot)) => self.is_inline(),
+                Value::Display(Display::Full(_, InnerDisplayType::FlowRoot))
+                | Value::Display(Display::ListItem(_, InnerDisplayType::FlowRoot))
+                | Value::Display(Display::Full(_, InnerDisplayType::Grid))
+                | Value::Display(Display::Full(_, InnerDisplayType::Math)) => self.is_inline(),
                 _ => false,
             },
             _ => false,
         }
     }
 
+    /// Check if the box lays out its children as grid items
+    pub fn is_grid_container(&self) -> bool {
+        match self.node() {
+            Some(node) => matches!(
+                node.get_style(&Property::Display),
+                Value::Display(Display::Full(_, InnerDisplayType::Grid))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Check if the box lays out its descendants as math
+    pub fn is_math(&self) -> bool {
+        match self.node() {
+            Some(node) => matches!(
+                node.get_style(&Property::Display),
+                Value::Display(Display::Full(_, InnerDisplayType::Math))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Check if the block box lays out its content in columns
+    pub fn is_multicol_container(&self) -> bool {
+        match self.node() {
+            Some(node) if self.is_block() && !self.is_grid_container() => {
+                !node.get_style(&Property::ColumnCount).is_auto()
+                    || !node.get_style(&Property::ColumnWidth).is_auto()
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_list_item(&self) -> bool {
+        match self.node() {
+            Some(node) => matches!(
+                node.get_style(&Property::Display),
+                Value::Display(Display::ListItem(..))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Check if the box should be painted. Invisible boxes still affect layout.
+    pub fn is_visible(&self) -> bool {
+        match self.node() {
+            Some(node) => matches!(
+                node.get_style(&Property::Visibility),
+                Value::Visibility(Visibility::Visible)
+            ),
+            _ => true,
+        }
+    }
+
+    pub fn marker(&self) -> Option<&ListMarker> {
+        self.marker.as_ref()
+    }
+
     pub fn is_positioned(&self, position: Position) -> bool {
         match self.node() {
             Some(node) => match node.get_style(&Property::Position) {
@@ -242,7 +314,9 @@ impl LayoutBoxPtr {
         match &self.node() {
             Some(node) => match node.as_element_opt() {
                 Some(e) => match e.tag_name().as_str() {
-                    "video" | "image" | "img" | "canvas" => false,
+                    "video" | "image" | "img" | "canvas" | "iframe" | "select" | "textarea" => {
+                        false
+                    }
                     _ => true,
                 },
                 _ => true,
@@ -251,6 +325,18 @@ impl LayoutBoxPtr {
         }
     }
 
+    pub fn nested_document(&self) -> Option<LayoutBoxPtr> {
+        self.nested_document.borrow().clone()
+    }
+
+    pub fn columns(&self) -> Vec<Rect> {
+        self.columns.borrow().clone()
+    }
+
+    pub fn math(&self) -> Option<MathLayout> {
+        self.math.borrow().clone()
+    }
+
     pub fn box_model(&self) -> &RefCell<BoxModel> {
         &self.box_model
     }
@@ -292,7 +378,11 @@ impl LayoutBoxPtr {
         let mut containing_block = self.containing_block();
 
         while let Some(block) = containing_block {
-            rect.translate(block.offset().x, block.offset().y);
+            let scroll_offset = scroll_offset(&block);
+            rect.translate(
+                block.offset().x - scroll_offset.x,
+                block.offset().y - scroll_offset.y,
+            );
             containing_block = block.containing_block();
         }
 
@@ -304,6 +394,14 @@ impl LayoutBoxPtr {
         Point::new(absolute_rect.x, absolute_rect.y)
     }
 
+    /// Where the lines and markers of the box are placed, which moves with
+    /// the scroll offset of a scroll container
+    pub fn content_origin(&self) -> Point {
+        let location = self.absolute_location();
+        let scroll_offset = scroll_offset(self);
+        Point::new(location.x - scroll_offset.x, location.y - scroll_offset.y)
+    }
+
     pub fn border_box_absolute(&self) -> Rect {
         let border_box = self.box_model.borrow().border_box();
         self.padding_box_absolute().add_outer_edges(&border_box)
@@ -329,21 +427,37 @@ impl LayoutBoxPtr {
     pub fn formatting_context(&self) -> Rc<dyn FormattingContext> {
         self.formatting_context.borrow().clone().unwrap_or_else(|| {
             self.parent()
-                .map(|parent| LayoutBoxPtr(parent).formatting_context())
+                .map(|parent| parent.formatting_context())
                 .expect("Unable to obtain formatting context")
         })
     }
 
-    pub fn apply_explicit_sizes(&self) {
+    /// Apply the `width` and `height` of the box, which are flow-relative
+    /// in the writing mode of its flow
+    pub fn apply_explicit_sizes(&self, writing_mode: WritingMode) {
         let containing_block = self.containing_block().unwrap().content_size();
 
+        if !self.is_non_replaced() {
+            let node = self.node().unwrap();
+            let size = if writing_mode.is_vertical() {
+                let containing_block = Size::new(containing_block.height, containing_block.width);
+                let size = replaced_size(&node, &containing_block);
+                Size::new(size.height, size.width)
+            } else {
+                replaced_size(&node, &containing_block)
+            };
+            self.set_content_width(size.width);
+            self.set_content_height(size.height);
+            return;
+        }
+
         if self.is_inline() && !self.is_inline_block() {
             return;
         }
 
         if let Some(node) = self.node() {
-            let computed_width = node.get_style(&Property::Width);
-            let computed_height = node.get_style(&Property::Height);
+            let computed_width = flow_relative_style(&node, &Property::Width, writing_mode);
+            let computed_height = flow_relative_style(&node, &Property::Height, writing_mode);
 
             if !computed_width.is_auto() {
                 let used_width = computed_width.to_px(containing_block.width);
@@ -371,7 +485,7 @@ impl LayoutBoxPtr {
             if result.node.is_some() {
                 break;
             }
-            result = LayoutBoxPtr(result.parent().unwrap());
+            result = result.parent().unwrap();
         }
 
         return result;
@@ -416,7 +530,7 @@ impl LayoutBoxPtr {
             }
         } else {
             self.for_each_child(|node| {
-                result.push_str(&LayoutBoxPtr(node).dump(level + 1));
+                result.push_str(&node.dump(level + 1));
             });
         }
 
@@ -1,8 +1,22 @@
+pub mod arena;
 pub mod box_model;
+pub mod counters;
+pub mod document;
 pub mod flow;
 pub mod formatting_context;
+pub mod fragmentation;
+pub mod hit_test;
 pub mod layout_box;
+pub mod list_marker;
+pub mod pagination;
+pub mod replaced;
+pub mod scroll;
+pub mod styles;
+pub mod text_control;
+pub mod text_fragments;
+pub mod text_search;
 pub mod tree_builder;
+pub mod writing_mode;
 
 #[macro_export]
 macro_rules! dump_layout {
@@ -0,0 +1,99 @@
+use dom::node::NodePtr;
+use gfx::TextMeasure;
+use shared::primitive::Size;
+use style_types::{
+    values::prelude::{ListStylePosition, ListStyleType},
+    Property, Value,
+};
+
+use crate::layout_box::LayoutBoxPtr;
+
+/// Ratio between the diameter of a bullet and the font size
+const BULLET_SIZE_RATIO: f32 = 0.35;
+/// Ratio between the space after a bullet and the font size
+const BULLET_GAP_RATIO: f32 = 0.5;
+
+/// The `::marker` generated by a list item
+#[derive(Debug, Clone)]
+pub struct ListMarker {
+    pub style_type: ListStyleType,
+    pub position: ListStylePosition,
+    pub ordinal: i32,
+}
+
+impl ListMarker {
+    /// Text of the marker, or `None` if the marker is painted as a shape
+    pub fn text(&self) -> Option<String> {
+        match self.style_type {
+            ListStyleType::Decimal => Some(format!("{}. ", self.ordinal)),
+            _ => None,
+        }
+    }
+
+    /// Size of the marker box, including the space between the marker and
+    /// the content of the list item
+    pub fn size(&self, font_size: f32, text_measurer: &mut TextMeasure) -> Size {
+        let line_height = text_measurer.measure("H", font_size).height;
+
+        match self.text() {
+            Some(text) => {
+                let text_size = text_measurer.measure(&text, font_size);
+                Size::new(text_size.width, line_height)
+            }
+            None => Size::new(
+                font_size * (BULLET_SIZE_RATIO + BULLET_GAP_RATIO),
+                line_height,
+            ),
+        }
+    }
+
+    /// Diameter of the bullet for shape markers
+    pub fn bullet_size(&self, font_size: f32) -> f32 {
+        font_size * BULLET_SIZE_RATIO
+    }
+}
+
+/// Create the marker for a list item, or `None` if it doesn't have one
+pub fn create_marker(node: &NodePtr, ordinal: i32) -> Option<ListMarker> {
+    let style_type = match node.get_style(&Property::ListStyleType) {
+        Value::ListStyleType(ListStyleType::None) => return None,
+        Value::ListStyleType(style_type) => style_type,
+        _ => return None,
+    };
+
+    let position = match node.get_style(&Property::ListStylePosition) {
+        Value::ListStylePosition(position) => position,
+        _ => ListStylePosition::Outside,
+    };
+
+    Some(ListMarker {
+        style_type,
+        position,
+        ordinal,
+    })
+}
+
+/// Find the list item whose marker sits on the first line of the block.
+///
+/// The marker of a list item is placed on its first line, which can belong
+/// to a descendant block if the first child of the list item is a block.
+pub fn marker_owner_of_first_line(block: &LayoutBoxPtr) -> Option<LayoutBoxPtr> {
+    let mut current = block.clone();
+
+    loop {
+        if current.marker().is_some() {
+            return Some(current);
+        }
+
+        let parent = current.parent()?;
+        let is_first_child = parent
+            .first_child()
+            .map(|first| first.ptr_eq(&current))
+            .unwrap_or(false);
+
+        if !is_first_child {
+            return None;
+        }
+        current = parent;
+    }
+}
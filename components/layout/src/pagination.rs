@@ -0,0 +1,72 @@
+use crate::fragmentation::{BreakOpportunities, FragmentArea, FragmentationContext};
+use crate::layout_box::LayoutBoxPtr;
+
+/// Part of the laid out document shown on a page, in page coordinates
+pub type PageArea = FragmentArea;
+
+/// Split the document laid out at the width of a page into pages of at
+/// most `page_height` CSS pixels.
+///
+/// The document is laid out once as a continuous strip, then cut between
+/// line boxes. A page ends early when a line would cross its bottom edge or
+/// when a block forces a page break with `break-before` or `break-after`.
+/// Content that doesn't fit on a page, like a tall image, is sliced.
+pub fn paginate(root: &LayoutBoxPtr, page_height: f32) -> Vec<PageArea> {
+    let opportunities = BreakOpportunities::collect(root, FragmentationContext::Page);
+
+    let margin = root.box_model().borrow().margin.clone();
+    let border_box = root.border_box_absolute();
+    let document_height = border_box.y + border_box.height + margin.bottom;
+
+    opportunities.fragment(0., document_height, page_height)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::primitive::Size;
+    use test_utils::dom_creator::{document, element, text};
+
+    use super::*;
+    use crate::document::layout_document;
+    use crate::utils::SHARED_CSS;
+
+    fn page_tops(pages: &[PageArea]) -> Vec<f32> {
+        pages.iter().map(|page| page.top).collect()
+    }
+
+    #[test]
+    fn test_paginate() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![
+                element("div#first", document.clone(), vec![]),
+                element("div#second", document.clone(), vec![]),
+                element(
+                    "p",
+                    document.clone(),
+                    vec![text("hello world", document.clone())],
+                ),
+            ],
+        );
+        let css = format!(
+            "{} #first {{ height: 30px; }} #second {{ height: 50px; break-after: page; }} \
+             p {{ margin-top: 10px; line-height: 20px; width: 10px; }}",
+            SHARED_CSS
+        );
+        crate::utils::build_tree(dom.clone(), &css);
+        let root = layout_document(document.clone(), &Size::new(500., 500.)).unwrap();
+
+        // The first page ends after #second, the paragraph follows on the
+        // next page
+        let pages = paginate(&root, 100.);
+        assert_eq!(page_tops(&pages), vec![0., 80.]);
+        assert_eq!(pages[0].height, 80.);
+
+        // The narrow paragraph is made of 20px lines from 90px to 170px.
+        // Lines that cross the end of a page are moved to the next page.
+        let pages = paginate(&root, 35.);
+        assert_eq!(page_tops(&pages), vec![0., 35., 70., 80., 110., 130., 150.]);
+    }
+}
@@ -0,0 +1,377 @@
+use dom::{elements::ElementData, node::NodePtr, select, textarea};
+use gfx::TextMeasure;
+use shared::{
+    image::ImageBitmap,
+    primitive::{Rect, Size},
+};
+use style_types::{values::prelude::ObjectFit, Property, Value};
+
+use crate::text_fragments::font_of;
+
+/// Size of replaced elements that have no size of their own
+/// https://www.w3.org/TR/css-images-3/#default-object-size
+const DEFAULT_OBJECT_WIDTH: f32 = 300.;
+const DEFAULT_OBJECT_HEIGHT: f32 = 150.;
+
+/// Used size of the content box of a replaced element. CSS `width` and
+/// `height` take precedence over the element's `width` and `height`
+/// attributes, which take precedence over its natural size or else the
+/// default object size. When only one of them is specified, the other one
+/// follows the aspect ratio of the element if it has one.
+/// https://www.w3.org/TR/CSS2/visudet.html#inline-replaced-width
+pub fn replaced_size(node: &NodePtr, containing_block: &Size) -> Size {
+    let natural_size = natural_size(node)
+        .unwrap_or_else(|| Size::new(DEFAULT_OBJECT_WIDTH, DEFAULT_OBJECT_HEIGHT));
+    let width = specified_length(node, &Property::Width, containing_block.width);
+    let height = specified_length(node, &Property::Height, containing_block.height);
+
+    match (width, height, aspect_ratio(node)) {
+        (Some(width), Some(height), _) => Size::new(width, height),
+        (Some(width), None, Some(ratio)) => Size::new(width, width / ratio),
+        (None, Some(height), Some(ratio)) => Size::new(height * ratio, height),
+        (Some(width), None, None) => Size::new(width, natural_size.height),
+        (None, Some(height), None) => Size::new(natural_size.width, height),
+        (None, None, Some(ratio)) => Size::new(natural_size.width, natural_size.width / ratio),
+        (None, None, None) => natural_size,
+    }
+}
+
+/// Width divided by height of a replaced element, from its `aspect-ratio`
+/// or else from its natural aspect ratio
+/// https://www.w3.org/TR/css-sizing-4/#aspect-ratio
+pub fn aspect_ratio(node: &NodePtr) -> Option<f32> {
+    match node.get_style(&Property::AspectRatio) {
+        Value::AspectRatio(ratio) if ratio.prefers_natural => {
+            natural_aspect_ratio(node).or_else(|| ratio.ratio())
+        }
+        Value::AspectRatio(ratio) => ratio.ratio(),
+        _ => natural_aspect_ratio(node),
+    }
+}
+
+/// Rect of the content of a replaced element, whose natural size is
+/// `object_size`, once fitted to the content box by `object-fit`. The
+/// content is centered in the box and overflows it with `cover` and `none`,
+/// so it should be clipped to the box.
+/// https://www.w3.org/TR/css-images-3/#the-object-fit
+pub fn object_fit_rect(fit: &ObjectFit, content_box: &Rect, object_size: &Size) -> Rect {
+    if object_size.width <= 0. || object_size.height <= 0. {
+        return content_box.clone();
+    }
+
+    let contain_scale = f32::min(
+        content_box.width / object_size.width,
+        content_box.height / object_size.height,
+    );
+    let scale = match fit {
+        ObjectFit::Fill => return content_box.clone(),
+        ObjectFit::Contain => contain_scale,
+        ObjectFit::Cover => f32::max(
+            content_box.width / object_size.width,
+            content_box.height / object_size.height,
+        ),
+        ObjectFit::None => 1.,
+        ObjectFit::ScaleDown => contain_scale.min(1.),
+    };
+
+    let width = object_size.width * scale;
+    let height = object_size.height * scale;
+    Rect::new(
+        content_box.x + (content_box.width - width) / 2.,
+        content_box.y + (content_box.height - height) / 2.,
+        width,
+        height,
+    )
+}
+
+pub fn object_fit(node: &NodePtr) -> ObjectFit {
+    match node.get_style(&Property::ObjectFit) {
+        Value::ObjectFit(fit) => fit,
+        _ => ObjectFit::Fill,
+    }
+}
+
+/// The document loaded in an `iframe` element, if any
+pub fn content_document(node: &NodePtr) -> Option<NodePtr> {
+    match node.as_element_opt()?.data() {
+        ElementData::IFrame(iframe) => iframe.content_document(),
+        _ => None,
+    }
+}
+
+/// The frame shown by a `video` element, if the embedder supplied one
+pub fn video_frame(node: &NodePtr) -> Option<ImageBitmap> {
+    match node.as_element_opt()?.data() {
+        ElementData::Video(video) => video.current_frame(),
+        _ => None,
+    }
+}
+
+/// The image shown by an `img` element, once it is loaded
+pub fn current_image(node: &NodePtr) -> Option<ImageBitmap> {
+    match node.as_element_opt()?.data() {
+        ElementData::Image(image) => image.current_image(),
+        _ => None,
+    }
+}
+
+/// Size of the content of replaced elements: form controls depend on their
+/// content and font, videos on their current frame and images on their
+/// loaded image
+fn natural_size(node: &NodePtr) -> Option<Size> {
+    let bitmap = match node.as_element_opt()?.tag_name().as_str() {
+        "select" => return Some(select_size(node)),
+        "textarea" => return Some(textarea_size(node)),
+        "video" => video_frame(node),
+        "img" => current_image(node),
+        _ => None,
+    };
+    bitmap.map(|bitmap| Size::new(bitmap.width() as f32, bitmap.height() as f32))
+}
+
+/// Aspect ratio of the content of a replaced element. The natural size of
+/// form controls depends on their font and doesn't make a ratio.
+fn natural_aspect_ratio(node: &NodePtr) -> Option<f32> {
+    match node.as_element_opt()?.tag_name().as_str() {
+        "select" | "textarea" => None,
+        _ => natural_size(node).filter(|size| size.width > 0. && size.height > 0.),
+    }
+    .map(|size| size.width / size.height)
+}
+
+/// Length of the `width` or `height` of a replaced element from its style or
+/// else from its attribute, `None` if it is `auto`
+fn specified_length(node: &NodePtr, property: &Property, containing_block: f32) -> Option<f32> {
+    let computed = node.get_style(property);
+    if !computed.is_auto() {
+        return Some(computed.to_px(containing_block));
+    }
+    attribute_length(node, property.name())
+}
+
+/// Width of the area holding the arrow of a select element
+pub fn select_arrow_width(font_size: f32) -> f32 {
+    font_size
+}
+
+/// Natural size of a select element: one line wide enough for its longest
+/// option and its arrow
+fn select_size(node: &NodePtr) -> Size {
+    let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+    let font = font_of(node);
+    let mut text_measure = TextMeasure::new();
+
+    let label_width = select::options(node)
+        .iter()
+        .map(|option| {
+            text_measure
+                .measure_with_font(&select::option_label(option), font_size, font)
+                .width
+        })
+        .fold(0., f32::max);
+    let line_height = text_measure
+        .font_metrics(font_size, font)
+        .normal_line_height();
+    Size::new(label_width + select_arrow_width(font_size), line_height)
+}
+
+/// Natural size of a textarea: `cols` characters wide and `rows` lines high
+fn textarea_size(node: &NodePtr) -> Size {
+    let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+    let font = font_of(node);
+    let mut text_measure = TextMeasure::new();
+
+    let char_width = text_measure.measure_with_font("0", font_size, font).width;
+    let line_height = text_measure
+        .font_metrics(font_size, font)
+        .normal_line_height();
+    Size::new(
+        textarea::cols(node) as f32 * char_width,
+        textarea::rows(node) as f32 * line_height,
+    )
+}
+
+fn attribute_length(node: &NodePtr, attr: &str) -> Option<f32> {
+    let value = node.as_element_opt()?.attributes().borrow().get_str(attr);
+    value
+        .trim()
+        .trim_end_matches("px")
+        .parse::<f32>()
+        .ok()
+        .filter(|length| *length >= 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::dom_creator::{document, element, text};
+
+    use super::*;
+    use crate::utils::{build_tree, SHARED_CSS};
+
+    #[test]
+    fn test_select_size() {
+        let document = document();
+        let option = |label: &str| {
+            element(
+                "option",
+                document.clone(),
+                vec![text(label, document.clone())],
+            )
+        };
+        let select = element(
+            "select",
+            document.clone(),
+            vec![option("One"), option("A much longer option")],
+        );
+        let dom = element("div", document.clone(), vec![select.clone()]);
+        let css = format!(
+            "{} select {{ display: inline-block; font-size: 16px; }}",
+            SHARED_CSS
+        );
+        let root = build_tree(dom, &css);
+
+        let select_box = root.first_child().unwrap();
+        assert!(!select_box.is_non_replaced());
+        // The label of the selected option is painted by the select box
+        assert!(select_box.has_no_child());
+
+        let mut text_measure = TextMeasure::new();
+        let size = replaced_size(&select, &Size::new(500., 500.));
+        let longest = text_measure.measure("A much longer option", 16.).width;
+        assert_eq!(size.width, longest + select_arrow_width(16.));
+        assert_eq!(
+            size.height,
+            text_measure
+                .font_metrics(16., font_of(&select))
+                .normal_line_height()
+        );
+    }
+
+    #[test]
+    fn test_textarea_size() {
+        let document = document();
+        let textarea = element("textarea", document.clone(), vec![]);
+        textarea.as_element().set_attribute("rows", "3");
+        textarea.as_element().set_attribute("cols", "10");
+        let dom = element("div", document.clone(), vec![textarea.clone()]);
+        let css = format!(
+            "{} textarea {{ display: inline-block; font-size: 16px; }}",
+            SHARED_CSS
+        );
+        build_tree(dom, &css);
+
+        let mut text_measure = TextMeasure::new();
+        let size = replaced_size(&textarea, &Size::new(500., 500.));
+        let line_height = text_measure
+            .font_metrics(16., font_of(&textarea))
+            .normal_line_height();
+        assert_eq!(size.width, 10. * text_measure.measure("0", 16.).width);
+        assert_eq!(size.height, 3. * line_height);
+    }
+
+    fn sized_element(tag: &str, attributes: &[(&str, &str)], style: &str) -> (NodePtr, Size) {
+        let document = document();
+        let replaced = element(tag, document.clone(), vec![]);
+        for (name, value) in attributes {
+            replaced.as_element().set_attribute(name, value);
+        }
+        let dom = element("div", document.clone(), vec![replaced.clone()]);
+        let css = format!("{} {} {{ display: block; {} }}", SHARED_CSS, tag, style);
+        build_tree(dom, &css);
+        let size = replaced_size(&replaced, &Size::new(500., 500.));
+        (replaced, size)
+    }
+
+    #[test]
+    fn test_aspect_ratio_sizing() {
+        let size =
+            |attributes: &[(&str, &str)], style: &str| sized_element("canvas", attributes, style).1;
+
+        assert_eq!(
+            size(&[], "width: 320px; aspect-ratio: 16 / 9;"),
+            Size::new(320., 180.)
+        );
+        assert_eq!(
+            size(&[], "height: 100px; aspect-ratio: 2;"),
+            Size::new(200., 100.)
+        );
+        // Without a specified size, the default object width is kept
+        assert_eq!(size(&[], "aspect-ratio: 3 / 1;"), Size::new(300., 100.));
+        // Both sizes are specified, the ratio is ignored
+        assert_eq!(
+            size(&[], "width: 10px; height: 10px; aspect-ratio: 2;"),
+            Size::new(10., 10.)
+        );
+
+        // The width attribute is used like CSS width, and without a
+        // natural aspect ratio, `auto <ratio>` uses the ratio
+        assert_eq!(
+            size(&[("width", "200")], "aspect-ratio: auto 4 / 1;"),
+            Size::new(200., 50.)
+        );
+    }
+
+    #[test]
+    fn test_form_controls_have_no_natural_aspect_ratio() {
+        let (textarea, size) = sized_element("textarea", &[], "width: 10px;");
+        assert_eq!(aspect_ratio(&textarea), None);
+        assert_eq!(size.width, 10.);
+    }
+
+    #[test]
+    fn test_video_is_sized_by_its_frame() {
+        let video_size = |frame: Option<(u32, u32)>, style: &str| {
+            let document = document();
+            let video = element("video", document.clone(), vec![]);
+            if let ElementData::Video(data) = video.as_element().data() {
+                data.set_current_frame(frame.and_then(|(width, height)| {
+                    let data = vec![0; (width * height * 4) as usize];
+                    ImageBitmap::from_rgba(width, height, data)
+                }));
+            }
+            let dom = element("div", document.clone(), vec![video.clone()]);
+            build_tree(dom, &format!("{} video {{ {} }}", SHARED_CSS, style));
+            replaced_size(&video, &Size::new(500., 500.))
+        };
+
+        assert_eq!(video_size(None, ""), Size::new(300., 150.));
+        assert_eq!(video_size(Some((64, 36)), ""), Size::new(64., 36.));
+        assert_eq!(
+            video_size(Some((64, 36)), "width: 320px;"),
+            Size::new(320., 180.)
+        );
+    }
+
+    #[test]
+    fn test_object_fit_rect() {
+        let content_box = Rect::new(10., 20., 200., 100.);
+        let object = Size::new(100., 100.);
+        let fit = |fit: ObjectFit, object: &Size| object_fit_rect(&fit, &content_box, object);
+
+        assert_eq!(fit(ObjectFit::Fill, &object), content_box);
+        assert_eq!(
+            fit(ObjectFit::Contain, &object),
+            Rect::new(60., 20., 100., 100.)
+        );
+        assert_eq!(
+            fit(ObjectFit::Cover, &object),
+            Rect::new(10., -30., 200., 200.)
+        );
+        assert_eq!(
+            fit(ObjectFit::None, &Size::new(20., 10.)),
+            Rect::new(100., 65., 20., 10.)
+        );
+        // Scaled down like `contain` when it's larger than the box...
+        assert_eq!(
+            fit(ObjectFit::ScaleDown, &object),
+            Rect::new(60., 20., 100., 100.)
+        );
+        // ...and left as is like `none` otherwise
+        assert_eq!(
+            fit(ObjectFit::ScaleDown, &Size::new(20., 10.)),
+            Rect::new(100., 65., 20., 10.)
+        );
+        // Content without a natural size is stretched
+        assert_eq!(fit(ObjectFit::Cover, &Size::new(0., 0.)), content_box);
+    }
+}
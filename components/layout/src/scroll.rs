@@ -0,0 +1,250 @@
+use shared::primitive::{Point, Rect, Size};
+use style_types::{values::prelude::Overflow, Property, Value};
+
+use crate::{
+    flow::line_box::LineFragmentData,
+    hit_test::{hit_test_box, union},
+    layout_box::LayoutBoxPtr,
+};
+
+/// Used `overflow-x` and `overflow-y` of the box. A `visible` axis is
+/// `auto` when the other axis is neither `visible` nor `clip`.
+/// https://www.w3.org/TR/css-overflow-3/#overflow-properties
+pub fn overflow(layout_box: &LayoutBoxPtr) -> (Overflow, Overflow) {
+    let node = match layout_box.node() {
+        Some(node) if layout_box.is_block() && layout_box.is_non_replaced() => node,
+        _ => return (Overflow::Visible, Overflow::Visible),
+    };
+    // The overflow of the root and body elements applies to the viewport
+    if layout_box.is_root_element() || layout_box.is_body_element() {
+        return (Overflow::Visible, Overflow::Visible);
+    }
+    let computed = |property| match node.get_style(property) {
+        Value::Overflow(overflow) => overflow,
+        _ => Overflow::Visible,
+    };
+    let (x, y) = (
+        computed(&Property::OverflowX),
+        computed(&Property::OverflowY),
+    );
+    let is_scrollable = |overflow: &Overflow| {
+        matches!(
+            overflow,
+            Overflow::Hidden | Overflow::Scroll | Overflow::Auto
+        )
+    };
+    match (x, y) {
+        (Overflow::Visible, y) if is_scrollable(&y) => (Overflow::Auto, y),
+        (x, Overflow::Visible) if is_scrollable(&x) => (x, Overflow::Auto),
+        overflow => overflow,
+    }
+}
+
+/// Whether the box clips its content to its padding box
+pub fn clips_overflow(layout_box: &LayoutBoxPtr) -> bool {
+    overflow(layout_box) != (Overflow::Visible, Overflow::Visible)
+}
+
+/// Whether the content of the box can be scrolled, which `clip` prevents
+pub fn is_scroll_container(layout_box: &LayoutBoxPtr) -> bool {
+    let (x, y) = overflow(layout_box);
+    x != Overflow::Clip && y != Overflow::Clip && x != Overflow::Visible
+}
+
+/// Whether the user can scroll the content of the box, unlike `hidden`
+/// which only scrolls programmatically
+pub fn is_user_scrollable(layout_box: &LayoutBoxPtr) -> bool {
+    is_scroll_container(layout_box) && overflow(layout_box).0 != Overflow::Hidden
+}
+
+/// How far the content of a scroll container is scrolled, in CSS pixels
+pub fn scroll_offset(layout_box: &LayoutBoxPtr) -> Point {
+    match layout_box.node() {
+        Some(node) if is_scroll_container(layout_box) => node.as_element().scroll_offset(),
+        _ => Point::default(),
+    }
+}
+
+/// Size of the content of a scroll container that can be scrolled into
+/// view: its padding box grown to the border boxes and lines of its
+/// content. The content of nested scroll containers is clipped by them and
+/// doesn't count.
+/// https://www.w3.org/TR/css-overflow-3/#scrollable
+pub fn scrollable_overflow(layout_box: &LayoutBoxPtr) -> Size {
+    let padding_box = layout_box.padding_box_absolute();
+    let padding = layout_box.box_model().borrow().padding.clone();
+    let scroll_offset = scroll_offset(layout_box);
+
+    let mut content = None;
+    collect_content_rects(layout_box, &mut |rect| {
+        content = Some(match &content {
+            Some(content) => union(content, &rect),
+            None => rect,
+        })
+    });
+
+    let (mut right, mut bottom) = (
+        padding_box.x + padding_box.width,
+        padding_box.y + padding_box.height,
+    );
+    if let Some(content) = content {
+        // The content is measured where it would be without scrolling, and
+        // the end padding stays visible after it
+        right = f32::max(
+            right,
+            content.x + content.width + scroll_offset.x + padding.right,
+        );
+        bottom = f32::max(
+            bottom,
+            content.y + content.height + scroll_offset.y + padding.bottom,
+        );
+    }
+    Size::new(right - padding_box.x, bottom - padding_box.y)
+}
+
+/// The largest scroll offset that keeps the padding box within the
+/// scrollable overflow
+pub fn max_scroll_offset(layout_box: &LayoutBoxPtr) -> Point {
+    let padding_box = layout_box.padding_box_absolute();
+    let overflow = scrollable_overflow(layout_box);
+    Point::new(
+        f32::max(overflow.width - padding_box.width, 0.),
+        f32::max(overflow.height - padding_box.height, 0.),
+    )
+}
+
+/// Scroll the content of a scroll container by `delta`, within its
+/// scrollable overflow. Returns the part of `delta` that was scrolled.
+pub fn scroll_by(layout_box: &LayoutBoxPtr, delta: &Point) -> Point {
+    let node = match layout_box.node() {
+        Some(node) if is_scroll_container(layout_box) => node,
+        _ => return Point::default(),
+    };
+    let current = scroll_offset(layout_box);
+    let max = max_scroll_offset(layout_box);
+    let scrolled = Point::new(
+        (current.x + delta.x).clamp(0., max.x),
+        (current.y + delta.y).clamp(0., max.y),
+    );
+    node.as_element().set_scroll_offset(scrolled.clone());
+    Point::new(scrolled.x - current.x, scrolled.y - current.y)
+}
+
+/// The innermost scroll container around the box at the point, in page
+/// coordinates, that the user can scroll by `delta`. `None` means the
+/// viewport scrolls.
+pub fn scroll_target(root: &LayoutBoxPtr, point: &Point, delta: &Point) -> Option<LayoutBoxPtr> {
+    let mut candidate = hit_test_box(root, point);
+
+    while let Some(layout_box) = candidate {
+        if is_user_scrollable(&layout_box) {
+            let offset = scroll_offset(&layout_box);
+            let max = max_scroll_offset(&layout_box);
+            let can_scroll = |delta: f32, offset: f32, max: f32| {
+                (delta < 0. && offset > 0.) || (delta > 0. && offset < max)
+            };
+            if can_scroll(delta.x, offset.x, max.x) || can_scroll(delta.y, offset.y, max.y) {
+                return Some(layout_box);
+            }
+        }
+        candidate = layout_box.parent();
+    }
+    None
+}
+
+/// Keep the scroll offset of every scroll container in the tree within its
+/// scrollable overflow, which shrinks when its content does
+pub fn clamp_scroll_offsets(layout_box: &LayoutBoxPtr) {
+    if is_scroll_container(layout_box) {
+        scroll_by(layout_box, &Point::default());
+    }
+    layout_box.for_each_child(|child| clamp_scroll_offsets(&child));
+}
+
+/// Clip of the content of the box in page coordinates, if it clips it
+pub fn overflow_clip(layout_box: &LayoutBoxPtr) -> Option<Rect> {
+    match clips_overflow(layout_box) {
+        true => Some(layout_box.padding_box_absolute()),
+        false => None,
+    }
+}
+
+fn collect_content_rects(layout_box: &LayoutBoxPtr, collect: &mut dyn FnMut(Rect)) {
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        let location = layout_box.content_origin();
+        for line in layout_box.lines().borrow().iter() {
+            for fragment in &line.fragments {
+                if let LineFragmentData::Marker(_) = fragment.data {
+                    continue;
+                }
+                let mut rect = Rect::from((location.clone(), fragment.size.clone()));
+                rect.translate(fragment.offset.x, fragment.offset.y);
+                collect(rect);
+            }
+        }
+    }
+
+    layout_box.for_each_child(|child| {
+        // Inline boxes are measured by their line fragments
+        if child.is_block() {
+            collect(child.border_box_absolute());
+        }
+        if !clips_overflow(&child) {
+            collect_content_rects(&child, collect);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::dom_creator::{document, element};
+
+    use super::*;
+    use crate::{
+        document::layout_document,
+        hit_test::hit_test,
+        utils::{build_tree, SHARED_CSS},
+    };
+
+    #[test]
+    fn test_scroll_container() {
+        let document = document();
+        let dom = element(
+            "div#container",
+            document.clone(),
+            vec![
+                element("div", document.clone(), vec![]),
+                element("div", document.clone(), vec![]),
+            ],
+        );
+        let css = format!(
+            "{} #container {{ width: 100px; height: 100px; overflow-y: auto; }}
+            #container div {{ height: 80px; }}",
+            SHARED_CSS
+        );
+        build_tree(dom.clone(), &css);
+        dom.as_element().set_scroll_offset(Point::new(0., 500.));
+
+        let container = layout_document(document, &Size::new(800., 600.)).unwrap();
+        let children = container.iterate_children().collect::<Vec<LayoutBoxPtr>>();
+
+        assert_eq!(overflow(&container), (Overflow::Auto, Overflow::Auto));
+        assert_eq!(max_scroll_offset(&container), Point::new(0., 60.));
+        // Offsets beyond the content are clamped by the layout
+        assert_eq!(scroll_offset(&container), Point::new(0., 60.));
+        let top = container.absolute_location().y;
+        assert_eq!(children[1].absolute_location().y - top, 20.);
+
+        // The content below the padding box is clipped
+        assert!(hit_test(&container, &Point::new(10., top + 150.)).is_none());
+
+        let inside = Point::new(10., top + 10.);
+        assert!(scroll_target(&container, &inside, &Point::new(0., 10.)).is_none());
+        assert!(scroll_target(&container, &inside, &Point::new(0., -10.)).is_some());
+        assert_eq!(
+            scroll_by(&container, &Point::new(0., -100.)),
+            Point::new(0., -60.)
+        );
+        assert_eq!(children[1].absolute_location().y - top, 80.);
+    }
+}
@@ -0,0 +1,108 @@
+//! Style computation of whole documents, shared by the pipelines of the
+//! engine and of the web build. It lives next to the layout since the
+//! media queries of a nested document depend on the size of its iframe.
+
+use dom::{document::Document, node::NodePtr};
+use shared::primitive::Size;
+use style::ancestor_filter::AncestorFilter;
+use style_types::ContextualRule;
+
+use crate::replaced::{content_document, replaced_size};
+
+/// Compute the styles of every node of the document and of the documents
+/// of its iframes. Stops early if the loading of the document is cancelled.
+pub fn calculate_styles(document_node: NodePtr) {
+    let document = document_node.as_document();
+    let style_rules = document.style_rules();
+    // Set again by the elements that are still animated
+    document.set_has_running_animations(false);
+
+    fn compute_styles(
+        element: NodePtr,
+        document: &Document,
+        style_rules: &[ContextualRule],
+        ancestors: &mut AncestorFilter,
+    ) {
+        if document.is_load_cancelled() {
+            return;
+        }
+        let computed_styles =
+            style::compute::compute_styles_with_ancestors(element.clone(), style_rules, ancestors);
+        element.set_computed_styles(computed_styles);
+        style::pseudo_element::update_pseudo_elements(&element, style_rules);
+
+        if let Some(content_document) = content_document(&element) {
+            calculate_nested_styles(&element, content_document, document);
+        }
+
+        // The shadow tree is styled first since slotted children inherit
+        // from their slots
+        if let Some(shadow_root) = element.as_element_opt().and_then(|e| e.shadow_root()) {
+            let shadow_rules = shadow_root.as_shadow_root().style_rules(document);
+            // Selectors of the shadow tree don't match across its root
+            let mut shadow_ancestors = AncestorFilter::new();
+            shadow_root.for_each_child(|child| {
+                compute_styles(
+                    NodePtr(child),
+                    document,
+                    &shadow_rules,
+                    &mut shadow_ancestors,
+                )
+            });
+        }
+
+        ancestors.push(&element);
+        element.for_each_child(|child| {
+            compute_styles(NodePtr(child), document, style_rules, ancestors)
+        });
+        ancestors.pop();
+    }
+
+    compute_styles(
+        document_node.clone(),
+        document,
+        &style_rules,
+        &mut AncestorFilter::new(),
+    );
+}
+
+/// Compute the styles of the document loaded in an iframe. Its media queries
+/// are evaluated against the size of the iframe.
+fn calculate_nested_styles(iframe: &NodePtr, content_document: NodePtr, parent: &Document) {
+    let mut environment = parent.media_environment();
+    let viewport = Size::new(environment.viewport_width, environment.viewport_height);
+    let size = replaced_size(iframe, &viewport);
+    environment.viewport_width = size.width;
+    environment.viewport_height = size.height;
+    content_document
+        .as_document()
+        .set_media_environment(environment);
+    calculate_styles(content_document);
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::cancellation::CancellationToken;
+    use test_utils::dom_creator::{document, element};
+
+    use super::*;
+
+    #[test]
+    fn test_stop_styling_cancelled_document() {
+        let document_node = document();
+        let div = element("div", document_node.clone(), vec![]);
+        document_node.append_child(div.0.clone());
+
+        let cancellation = CancellationToken::new();
+        cancellation.cancel();
+        document_node
+            .as_document()
+            .set_load_cancellation(Some(cancellation));
+        calculate_styles(document_node.clone());
+        assert!(div.computed_styles().is_empty());
+
+        document_node.as_document().set_load_cancellation(None);
+        calculate_styles(document_node.clone());
+        assert!(!div.computed_styles().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use std::ops::Range;
+
+use dom::node::NodePtr;
+use gfx::{FontIndex, TextMeasure};
+use shared::primitive::Point;
+use style_types::Property;
+
+use crate::text_fragments::font_of;
+
+/// Text of a textarea wrapped to the width of its content box. Positions
+/// are relative to the top left corner of the text, before scrolling.
+#[derive(Debug, Clone)]
+pub struct TextControlLayout {
+    /// Byte ranges of the lines in the text. Line breaks aren't part of any
+    /// line, spaces at a soft wrap are part of the line before it.
+    pub lines: Vec<Range<usize>>,
+    pub line_height: f32,
+    pub font_size: f32,
+    pub font: FontIndex,
+}
+
+impl TextControlLayout {
+    pub fn new(node: &NodePtr, text: &str, width: f32, text_measure: &mut TextMeasure) -> Self {
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let font = font_of(node);
+        let mut measure = |range: Range<usize>| {
+            text_measure
+                .measure_with_font(&text[range], font_size, font)
+                .width
+        };
+
+        let mut lines = Vec::new();
+        let mut paragraph_start = 0;
+        for paragraph in text.split('\n') {
+            let paragraph_end = paragraph_start + paragraph.len();
+            let mut start = paragraph_start;
+            loop {
+                let end = wrap_point(text, start..paragraph_end, width, &mut measure);
+                lines.push(start..end);
+                if end == paragraph_end {
+                    break;
+                }
+                start = end;
+            }
+            paragraph_start = paragraph_end + 1;
+        }
+
+        Self {
+            lines,
+            line_height: text_measure
+                .font_metrics(font_size, font)
+                .normal_line_height(),
+            font_size,
+            font,
+        }
+    }
+
+    /// Height of all the lines
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.line_height
+    }
+
+    /// Index of the line the caret is on at the offset. The caret at a soft
+    /// wrap is at the start of the next line.
+    pub fn line_at_offset(&self, offset: usize) -> usize {
+        self.lines
+            .iter()
+            .rposition(|line| line.start <= offset)
+            .unwrap_or(0)
+    }
+
+    /// Offsets where the caret goes when moved to the start or to the end of
+    /// the line at `index`
+    pub fn line_bounds(&self, text: &str, index: usize) -> Range<usize> {
+        let line = self.lines[index].clone();
+        let is_soft_wrapped = self
+            .lines
+            .get(index + 1)
+            .is_some_and(|next| next.start == line.end);
+        if !is_soft_wrapped || line.is_empty() {
+            return line;
+        }
+        // The end of a wrapped line is the start of the next one, so the
+        // caret stops before the last character instead
+        let last_char = text[line.clone()].char_indices().next_back().unwrap().0;
+        line.start..line.start + last_char
+    }
+
+    /// Top left corner of the caret at the offset
+    pub fn caret_position(
+        &self,
+        text: &str,
+        offset: usize,
+        text_measure: &mut TextMeasure,
+    ) -> Point {
+        let index = self.line_at_offset(offset);
+        let line_start = self.lines[index].start;
+        let x = text_measure
+            .measure_with_font(&text[line_start..offset], self.font_size, self.font)
+            .width;
+        Point::new(x, index as f32 * self.line_height)
+    }
+
+    /// Offset of the character boundary closest to the point
+    pub fn offset_at(&self, text: &str, point: &Point, text_measure: &mut TextMeasure) -> usize {
+        let index = (point.y / self.line_height).floor().max(0.) as usize;
+        let index = index.min(self.lines.len() - 1);
+        let bounds = self.line_bounds(text, index);
+
+        let boundaries = text[bounds.clone()]
+            .char_indices()
+            .map(|(offset, _)| bounds.start + offset)
+            .chain(std::iter::once(bounds.end));
+        let mut closest = (bounds.start, f32::MAX);
+        for offset in boundaries {
+            let x = text_measure
+                .measure_with_font(&text[bounds.start..offset], self.font_size, self.font)
+                .width;
+            let distance = (x - point.x).abs();
+            if distance < closest.1 {
+                closest = (offset, distance);
+            }
+        }
+        closest.0
+    }
+}
+
+/// End of the longest line starting the range that fits in `width`,
+/// preferably after a space. A line has at least one character, and spaces
+/// hang at the end of the line instead of wrapping. An empty range is an
+/// empty line.
+fn wrap_point(
+    text: &str,
+    range: Range<usize>,
+    width: f32,
+    measure: &mut dyn FnMut(Range<usize>) -> f32,
+) -> usize {
+    if range.is_empty() || measure(range.clone()) <= width {
+        return range.end;
+    }
+
+    let mut fit_end = None;
+    let mut after_last_space = None;
+    for (offset, ch) in text[range.clone()].char_indices() {
+        let next = range.start + offset + ch.len_utf8();
+        if ch == ' ' {
+            after_last_space = Some(next);
+        } else if measure(range.start..next) > width {
+            break;
+        }
+        fit_end = Some(next);
+    }
+
+    after_last_space
+        .or(fit_end)
+        .unwrap_or_else(|| range.start + text[range].chars().next().unwrap().len_utf8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every character is 10px wide
+    fn wrap(text: &str, width: f32) -> Vec<&str> {
+        let mut measure = |range: Range<usize>| text[range].chars().count() as f32 * 10.;
+        let mut lines = Vec::new();
+        let mut start = 0;
+        while start < text.len() {
+            let end = wrap_point(text, start..text.len(), width, &mut measure);
+            lines.push(&text[start..end]);
+            start = end;
+        }
+        lines
+    }
+
+    #[test]
+    fn test_wrap_point() {
+        assert_eq!(wrap("one two three", 80.), vec!["one two ", "three"]);
+        assert_eq!(wrap("one  two", 40.), vec!["one  ", "two"]);
+        assert_eq!(wrap("abcdefgh", 30.), vec!["abc", "def", "gh"]);
+        assert_eq!(wrap("ab", 5.), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_wrap_empty_line_at_negative_width() {
+        let text = "ab\n\ncd";
+        let mut measure = |range: Range<usize>| text[range].chars().count() as f32 * 10.;
+        assert_eq!(wrap_point(text, 3..3, -4., &mut measure), 3);
+        assert_eq!(wrap_point(text, 0..2, -4., &mut measure), 1);
+    }
+
+    #[test]
+    fn test_line_bounds() {
+        let text = "one two\nthree";
+        let text_layout = TextControlLayout {
+            lines: vec![0..4, 4..7, 8..13],
+            line_height: 10.,
+            font_size: 10.,
+            font: gfx::DEFAULT_FONT,
+        };
+        assert_eq!(text_layout.line_at_offset(4), 1);
+        assert_eq!(text_layout.line_at_offset(8), 2);
+        // The caret stays on the soft wrapped line, before its trailing space
+        assert_eq!(text_layout.line_bounds(text, 0), 0..3);
+        assert_eq!(text_layout.line_bounds(text, 1), 4..7);
+        assert_eq!(text_layout.height(), 30.);
+    }
+}
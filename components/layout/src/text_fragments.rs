@@ -0,0 +1,298 @@
+use dom::node::NodePtr;
+use gfx::{FontIndex, TextMeasure, DEFAULT_FONT};
+use shared::primitive::{Point, Rect};
+use style_types::{Property, Value};
+
+use crate::{flow::line_box::LineFragmentData, layout_box::LayoutBoxPtr};
+
+/// A run of text laid out on a single line, in absolute coordinates
+#[derive(Debug, Clone)]
+pub struct TextFragment {
+    pub content: String,
+    pub rect: Rect,
+    pub font_size: f32,
+    pub font: FontIndex,
+    pub spacing: TextSpacing,
+}
+
+/// Extra space added to text by `letter-spacing` and `word-spacing`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextSpacing {
+    pub letter: f32,
+    pub word: f32,
+}
+
+/// A character boundary within the text fragments of a layout tree.
+///
+/// Positions are ordered in layout order, which makes them suitable as
+/// selection endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+    pub fragment: usize,
+    pub offset: usize,
+}
+
+/// Collect all text fragments of the layout tree in layout order
+pub fn collect_text_fragments(root: &LayoutBoxPtr) -> Vec<TextFragment> {
+    let mut result = Vec::new();
+    collect_from_box(root, &mut result);
+    result
+}
+
+fn collect_from_box(layout_box: &LayoutBoxPtr, result: &mut Vec<TextFragment>) {
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        collect_from_lines(layout_box, result);
+    }
+
+    layout_box.for_each_child(|child| collect_from_box(&child, result));
+}
+
+fn collect_from_lines(containing_block: &LayoutBoxPtr, result: &mut Vec<TextFragment>) {
+    let location = containing_block.content_origin();
+
+    for line in containing_block.lines().borrow().iter() {
+        for fragment in &line.fragments {
+            if let LineFragmentData::Text(layout_box, content) = &fragment.data {
+                let node = layout_box.node().unwrap();
+                let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+
+                let mut rect = Rect::from((location.clone(), fragment.size.clone()));
+                rect.translate(fragment.offset.x, fragment.offset.y);
+
+                result.push(TextFragment {
+                    content: content.clone(),
+                    rect,
+                    font_size,
+                    font: font_of(&node),
+                    spacing: TextSpacing::of(&node),
+                });
+            }
+        }
+    }
+}
+
+/// Font that the text of a node is drawn with, among the fonts available to
+/// the origin of its document
+pub fn font_of(node: &NodePtr) -> FontIndex {
+    match node.get_style(&Property::FontFamily) {
+        Value::FontFamily(family) => {
+            let origin = match node.owner_document() {
+                Some(document) => NodePtr(document).as_document().origin(),
+                None => "null".to_string(),
+            };
+            gfx::find_font(family.names(), &origin)
+        }
+        _ => DEFAULT_FONT,
+    }
+}
+
+impl TextSpacing {
+    pub fn of(node: &NodePtr) -> Self {
+        Self {
+            letter: node.get_style(&Property::LetterSpacing).to_px(0.),
+            word: node.get_style(&Property::WordSpacing).to_px(0.),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.letter == 0. && self.word == 0.
+    }
+
+    /// Width added to the text. Letter spacing is added after every
+    /// character and word spacing after every space.
+    pub fn extra_width(&self, text: &str) -> f32 {
+        if self.is_zero() {
+            return 0.;
+        }
+        let characters = text.chars().count() as f32;
+        let spaces = text.chars().filter(|ch| *ch == ' ').count() as f32;
+        self.letter * characters + self.word * spaces
+    }
+}
+
+impl TextFragment {
+    pub fn char_count(&self) -> usize {
+        self.content.chars().count()
+    }
+
+    /// Text between two character offsets
+    pub fn substring(&self, start: usize, end: usize) -> String {
+        self.content.chars().skip(start).take(end - start).collect()
+    }
+
+    /// Absolute x coordinate of the character boundary at `offset`
+    pub fn offset_to_x(&self, offset: usize, text_measurer: &mut TextMeasure) -> f32 {
+        if offset == 0 {
+            return self.rect.x;
+        }
+        let prefix = self.substring(0, offset);
+        self.rect.x
+            + text_measurer
+                .measure_with_font(&prefix, self.font_size, self.font)
+                .width
+            + self.spacing.extra_width(&prefix)
+    }
+
+    /// Character boundary closest to the absolute x coordinate
+    pub fn x_to_offset(&self, x: f32, text_measurer: &mut TextMeasure) -> usize {
+        (0..=self.char_count())
+            .map(|offset| (offset, (self.offset_to_x(offset, text_measurer) - x).abs()))
+            .fold((0, f32::INFINITY), |closest, current| {
+                if current.1 < closest.1 {
+                    current
+                } else {
+                    closest
+                }
+            })
+            .0
+    }
+
+    /// Rect covering the characters between two offsets
+    pub fn range_rect(&self, start: usize, end: usize, text_measurer: &mut TextMeasure) -> Rect {
+        let start_x = self.offset_to_x(start, text_measurer);
+        let end_x = self.offset_to_x(end, text_measurer);
+        Rect::new(start_x, self.rect.y, end_x - start_x, self.rect.height)
+    }
+
+    fn distance_to(&self, point: &Point) -> (f32, f32) {
+        let dy = if point.y < self.rect.y {
+            self.rect.y - point.y
+        } else if point.y > self.rect.y + self.rect.height {
+            point.y - (self.rect.y + self.rect.height)
+        } else {
+            0.
+        };
+        let dx = if point.x < self.rect.x {
+            self.rect.x - point.x
+        } else if point.x > self.rect.x + self.rect.width {
+            point.x - (self.rect.x + self.rect.width)
+        } else {
+            0.
+        };
+        (dy, dx)
+    }
+}
+
+/// Find the text position closest to the point, preferring fragments on the same line
+pub fn hit_test_text(
+    fragments: &[TextFragment],
+    point: &Point,
+    text_measurer: &mut TextMeasure,
+) -> Option<TextPosition> {
+    let (index, fragment) = fragments.iter().enumerate().min_by(|(_, a), (_, b)| {
+        a.distance_to(point)
+            .partial_cmp(&b.distance_to(point))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+
+    Some(TextPosition {
+        fragment: index,
+        offset: fragment.x_to_offset(point.x, text_measurer),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::document::QuirksMode;
+    use shared::primitive::Rect;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
+
+    use crate::{
+        formatting_context::{establish_context, FormattingContextType, LayoutContext},
+        utils::{build_tree, SHARED_CSS},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_hit_test_text() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![text("hello world", document.clone())],
+        );
+
+        let root = build_tree(dom, SHARED_CSS);
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+        root.formatting_context().run(&layout_context, root.clone());
+
+        let fragments = collect_text_fragments(&root);
+        let mut text_measurer = TextMeasure::new();
+        let first = fragments.first().unwrap();
+
+        let start = hit_test_text(&fragments, &Point::new(-10., -10.), &mut text_measurer);
+        assert_eq!(
+            start,
+            Some(TextPosition {
+                fragment: 0,
+                offset: 0
+            })
+        );
+
+        let end_x = first.rect.x + first.rect.width + 100.;
+        let end = hit_test_text(
+            &fragments,
+            &Point::new(end_x, first.rect.y),
+            &mut text_measurer,
+        );
+        assert_eq!(
+            end.map(|position| position.offset),
+            Some(first.char_count())
+        );
+    }
+
+    #[test]
+    fn test_text_spacing_and_transform() {
+        let layout = |css: &str| {
+            let document = document();
+            let dom = element(
+                "div",
+                document.clone(),
+                vec![text("ab cd", document.clone())],
+            );
+            let root = build_tree(dom, &format!("{}{}", SHARED_CSS, css));
+            let layout_context = LayoutContext {
+                viewport: Rect::new(0., 0., 500., 300.),
+                quirks_mode: QuirksMode::NoQuirks,
+                writing_mode: WritingMode::HorizontalTb,
+            };
+
+            establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+            root.formatting_context().run(&layout_context, root.clone());
+            collect_text_fragments(&root)
+        };
+
+        let plain = layout("");
+        let spaced = layout("div { letter-spacing: 2px; word-spacing: 5px; }");
+        let uppercase = layout("div { text-transform: uppercase; }");
+
+        assert_eq!(uppercase[0].content, "AB");
+        assert_eq!(uppercase[2].content, "CD");
+        assert_eq!(
+            spaced[0].spacing,
+            TextSpacing {
+                letter: 2.,
+                word: 5.
+            }
+        );
+        // Every character is followed by the letter spacing and every space
+        // by the word spacing
+        assert_eq!(spaced[0].rect.width, plain[0].rect.width + 2. * 2.);
+        assert_eq!(spaced[1].content, " ");
+        assert_eq!(spaced[1].rect.width, plain[1].rect.width + 2. + 5.);
+
+        let mut text_measurer = TextMeasure::new();
+        assert_eq!(
+            spaced[0].offset_to_x(1, &mut text_measurer),
+            plain[0].offset_to_x(1, &mut text_measurer) + 2.
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use gfx::TextMeasure;
+use shared::primitive::Rect;
+
+use crate::{layout_box::LayoutBoxPtr, text_fragments::collect_text_fragments};
+
+/// Find every occurrence of `query` in the text fragments of the layout tree.
+///
+/// Matching is case-insensitive and the result is returned in layout order,
+/// with each rect in absolute coordinates.
+pub fn find_text(root: &LayoutBoxPtr, query: &str) -> Vec<Rect> {
+    let needle = query.chars().collect::<Vec<char>>();
+    let mut result = Vec::new();
+
+    if needle.is_empty() {
+        return result;
+    }
+
+    let mut text_measurer = TextMeasure::new();
+
+    for fragment in collect_text_fragments(root) {
+        let haystack = fragment.content.chars().collect::<Vec<char>>();
+
+        for start in find_matches(&haystack, &needle) {
+            result.push(fragment.range_rect(start, start + needle.len(), &mut text_measurer));
+        }
+    }
+
+    result
+}
+
+/// Find the start indices of all non-overlapping matches of `needle` in `haystack`.
+fn find_matches(haystack: &[char], needle: &[char]) -> Vec<usize> {
+    let mut matches = Vec::new();
+    let mut index = 0;
+
+    while index + needle.len() <= haystack.len() {
+        let is_match = haystack[index..index + needle.len()]
+            .iter()
+            .zip(needle)
+            .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()));
+
+        if is_match {
+            matches.push(index);
+            index += needle.len();
+        } else {
+            index += 1;
+        }
+    }
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::document::QuirksMode;
+    use shared::primitive::Rect;
+    use style_types::values::prelude::WritingMode;
+    use test_utils::dom_creator::{document, element, text};
+
+    use crate::{
+        formatting_context::{establish_context, FormattingContextType, LayoutContext},
+        utils::{build_tree, SHARED_CSS},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        let haystack = "Foo bar foo".chars().collect::<Vec<char>>();
+        let needle = "foo".chars().collect::<Vec<char>>();
+        assert_eq!(find_matches(&haystack, &needle), vec![0, 8]);
+
+        let haystack = "aaaa".chars().collect::<Vec<char>>();
+        let needle = "aa".chars().collect::<Vec<char>>();
+        assert_eq!(find_matches(&haystack, &needle), vec![0, 2]);
+    }
+
+    #[test]
+    fn test_find_text_in_layout_order() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![
+                text("hello world", document.clone()),
+                element(
+                    "span",
+                    document.clone(),
+                    vec![text("Hello", document.clone())],
+                ),
+            ],
+        );
+
+        let root = build_tree(dom, SHARED_CSS);
+        let layout_context = LayoutContext {
+            viewport: Rect::new(0., 0., 500., 300.),
+            quirks_mode: QuirksMode::NoQuirks,
+            writing_mode: WritingMode::HorizontalTb,
+        };
+
+        establish_context(FormattingContextType::InlineFormattingContext, root.clone());
+        root.formatting_context().run(&layout_context, root.clone());
+
+        let matches = find_text(&root, "hello");
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|rect| rect.width > 0.));
+
+        assert!(find_text(&root, "").is_empty());
+        assert!(find_text(&root, "moon").is_empty());
+    }
+}
@@ -1,20 +1,41 @@
-use dom::node::NodePtr;
-use shared::tree_node::TreeNode;
+use std::rc::Rc;
+
+use dom::{details, flat_tree, node::NodePtr};
 use style_types::{
-    values::{display::DisplayBox, prelude::Display},
-    Value,
+    values::{
+        display::DisplayBox,
+        prelude::{Content, Display},
+    },
+    Property, PseudoElement, Value,
 };
 
-use crate::layout_box::{BoxData, LayoutBox, LayoutBoxPtr};
+use crate::{
+    arena::LayoutArena,
+    counters::CounterScopes,
+    layout_box::{BoxData, LayoutBox, LayoutBoxPtr},
+    list_marker::create_marker,
+};
 
 pub struct TreeBuilder {
+    /// Arena the boxes of the tree are allocated in
+    arena: Rc<LayoutArena>,
     parent_stack: Vec<LayoutBoxPtr>,
+    /// Ordinal of the next list item for each list being built
+    list_ordinal_stack: Vec<i32>,
+    /// CSS counters in scope of the node being built
+    counters: CounterScopes,
+    /// Depth of the node being built in the flat tree
+    depth: usize,
 }
 
 impl TreeBuilder {
     pub fn new() -> Self {
         Self {
+            arena: LayoutArena::new(),
             parent_stack: Vec::new(),
+            list_ordinal_stack: Vec::new(),
+            counters: CounterScopes::new(),
+            depth: 0,
         }
     }
 
@@ -32,12 +53,10 @@ impl TreeBuilder {
             {
                 return None;
             }
-            let root_box = LayoutBoxPtr(TreeNode::new(LayoutBox::new(root_node.clone())));
+            let root_box = self.arena.alloc(LayoutBox::new(root_node.clone()));
 
             self.parent_stack.push(root_box.clone());
-            root_node.for_each_child(|child| {
-                self.build_layout_tree(NodePtr(child));
-            });
+            self.build_children(&root_node);
             self.parent_stack.pop();
 
             return Some(root_box);
@@ -51,9 +70,38 @@ impl TreeBuilder {
         {
             return;
         }
-        let layout_box = TreeNode::new(LayoutBox::new(node.clone()));
+        self.counters.update(&node, self.depth);
+        self.resolve_counters(&node);
+
+        if let Value::Display(Display::Box(DisplayBox::Contents)) =
+            node.get_style(&style_types::Property::Display)
+        {
+            // The element doesn't generate a box, its children take its place
+            self.build_children(&node);
+            return;
+        }
+        // White space between grid items is not rendered
+        if self.is_building_grid_items() && is_white_space_text(&node) {
+            return;
+        }
+        let mut layout_box = LayoutBox::new(node.clone());
 
-        let parent = if LayoutBoxPtr(layout_box.clone()).is_inline() {
+        if let Value::Display(Display::ListItem(..)) =
+            node.get_style(&style_types::Property::Display)
+        {
+            // Summaries don't count as list items of the enclosing list
+            let ordinal = match details::details_of_summary(&node) {
+                Some(_) => 0,
+                None => self.next_list_ordinal(&node),
+            };
+            layout_box.marker = create_marker(&node, ordinal);
+        }
+
+        let layout_box = self.arena.alloc(layout_box);
+
+        let parent = if self.is_building_grid_items() {
+            self.get_parent_for_grid_item(&layout_box)
+        } else if layout_box.is_inline() {
             self.get_parent_for_inline()
         } else {
             self.get_parent_for_block()
@@ -61,13 +109,82 @@ impl TreeBuilder {
 
         parent.append_child(layout_box.clone());
 
-        self.parent_stack.push(LayoutBoxPtr(layout_box));
-        node.for_each_child(|child| {
-            self.build_layout_tree(NodePtr(child));
-        });
+        // The contents of replaced elements are not rendered
+        if !layout_box.is_non_replaced() {
+            return;
+        }
+
+        self.parent_stack.push(layout_box);
+        self.build_children(&node);
         self.parent_stack.pop();
     }
 
+    /// Build the boxes of the children of the node in the flat tree,
+    /// including the boxes of its `::before` and `::after` pseudo-elements
+    fn build_children(&mut self, node: &NodePtr) {
+        let is_list = is_list_element(node);
+        if is_list {
+            self.list_ordinal_stack.push(list_start(node));
+        }
+        self.depth += 1;
+
+        if let Some(before) = node.pseudo_element(&PseudoElement::Before) {
+            self.build_layout_tree(before);
+        }
+        for child in flat_tree::children(node) {
+            // The content of a closed details element is not rendered
+            if details::is_child_rendered(node, &child) {
+                self.build_layout_tree(child);
+            }
+        }
+        if let Some(after) = node.pseudo_element(&PseudoElement::After) {
+            self.build_layout_tree(after);
+        }
+
+        self.depth -= 1;
+        self.counters.leave(self.depth);
+        if is_list {
+            self.list_ordinal_stack.pop();
+        }
+    }
+
+    /// Fill in the text of a pseudo-element whose `content` has counters
+    fn resolve_counters(&self, node: &NodePtr) {
+        let is_pseudo_element = node
+            .as_element_opt()
+            .is_some_and(|element| element.tag_name().starts_with("::"));
+        if !is_pseudo_element {
+            return;
+        }
+        if let Value::Content(Content::Items(items)) = node.get_style(&Property::Content) {
+            if let Some(text) = node.first_child() {
+                let text = NodePtr(text);
+                text.as_text()
+                    .character_data
+                    .set_data(&self.counters.resolve(&items));
+            }
+        }
+    }
+
+    /// Get the ordinal of a list item and advance the numbering of its list
+    ///
+    /// A `value` attribute on the list item overrides its ordinal and the
+    /// numbering of the following items continues from there.
+    fn next_list_ordinal(&mut self, node: &NodePtr) -> i32 {
+        if self.list_ordinal_stack.is_empty() {
+            self.list_ordinal_stack.push(1);
+        }
+        let next_ordinal = self.list_ordinal_stack.last_mut().unwrap();
+
+        let value = node
+            .as_element_opt()
+            .and_then(|element| element.attributes().borrow().get_str("value").parse().ok());
+
+        let ordinal = value.unwrap_or(*next_ordinal);
+        *next_ordinal = ordinal + 1;
+        ordinal
+    }
+
     /// Get a parent for an block-level box
     ///
     /// A block-level box can only be inserted into the nearest non-inline parent.
@@ -88,7 +205,9 @@ impl TreeBuilder {
             .expect(&format!("No parent in stack: {:?}", self.parent_stack));
 
         if !parent.has_no_child() && parent.children_are_inline() {
-            let anonymous = TreeNode::new(LayoutBox::new_anonymous(BoxData::block_box()));
+            let anonymous = self
+                .arena
+                .alloc(LayoutBox::new_anonymous(BoxData::block_box()));
 
             parent.transfer_children_to_node(anonymous.clone());
             parent.append_child(anonymous);
@@ -118,23 +237,94 @@ impl TreeBuilder {
             return parent;
         }
 
-        let require_anonymous_box = match parent.last_child().map(|child| LayoutBoxPtr(child)) {
+        let require_anonymous_box = match parent.last_child() {
             Some(last_node) => !(last_node.is_anonymous() && last_node.children_are_inline()),
             None => true,
         };
 
         if require_anonymous_box {
-            let anonymous = TreeNode::new(LayoutBox::new_anonymous(BoxData::block_box()));
+            let anonymous = self
+                .arena
+                .alloc(LayoutBox::new_anonymous(BoxData::block_box()));
             parent.append_child(anonymous);
         }
 
-        LayoutBoxPtr(parent.last_child().unwrap().clone())
+        parent.last_child().unwrap()
     }
+
+    fn is_building_grid_items(&self) -> bool {
+        self.parent_stack
+            .last()
+            .is_some_and(|parent| parent.is_grid_container())
+    }
+
+    /// Get a parent for a child of a grid container
+    ///
+    /// Every child of a grid container is a grid item. A block-level box is
+    /// inserted as a direct child of the container. An inline-level box
+    /// is wrapped in an anonymous block-level box, which is shared by text
+    /// runs next to each other.
+    fn get_parent_for_grid_item(&mut self, layout_box: &LayoutBoxPtr) -> LayoutBoxPtr {
+        let grid = self
+            .parent_stack
+            .last()
+            .expect("No parent in stack")
+            .clone();
+
+        if !layout_box.is_inline() {
+            return grid;
+        }
+
+        let is_text_run =
+            |layout_box: &LayoutBoxPtr| layout_box.node().is_some_and(|n| n.is_text());
+        if is_text_run(layout_box) {
+            if let Some(last_item) = grid.last_child() {
+                let wraps_text = last_item.is_anonymous()
+                    && last_item
+                        .last_child()
+                        .is_some_and(|child| is_text_run(&child));
+                if wraps_text {
+                    return last_item;
+                }
+            }
+        }
+
+        let anonymous = self
+            .arena
+            .alloc(LayoutBox::new_anonymous(BoxData::block_box()));
+        grid.append_child(anonymous.clone());
+        anonymous
+    }
+}
+
+fn is_white_space_text(node: &NodePtr) -> bool {
+    node.is_text()
+        && node
+            .as_text()
+            .get_data()
+            .chars()
+            .all(|ch| matches!(ch, ' ' | '\t' | '\n' | '\r' | '\x0c'))
+}
+
+/// Check if the element starts a new numbering for its list items
+fn is_list_element(node: &NodePtr) -> bool {
+    match node.as_element_opt() {
+        Some(element) => matches!(element.tag_name().as_str(), "ol" | "ul" | "menu"),
+        _ => false,
+    }
+}
+
+fn list_start(node: &NodePtr) -> i32 {
+    node.as_element_opt()
+        .and_then(|element| element.attributes().borrow().get_str("start").parse().ok())
+        .unwrap_or(1)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{layout_box::LayoutBoxPtr, utils::*};
+    use dom::node::NodePtr;
+    use style_types::{values::prelude::ListStyleType, PseudoElement};
     use test_utils::dom_creator::*;
 
     #[test]
@@ -170,9 +360,9 @@ mod tests {
 
         assert!(root.is_block());
 
-        assert!(LayoutBoxPtr(root.first_child().unwrap()).is_block());
-        assert!(LayoutBoxPtr(root.first_child().unwrap()).is_anonymous());
-        assert!(LayoutBoxPtr(root.nth_child(1).unwrap()).is_block());
+        assert!(root.first_child().unwrap().is_block());
+        assert!(root.first_child().unwrap().is_anonymous());
+        assert!(root.nth_child(1).unwrap().is_block());
     }
 
     #[test]
@@ -206,12 +396,270 @@ mod tests {
 
         assert_eq!(root.children_count(), 3);
 
-        assert!(LayoutBoxPtr(root.first_child().unwrap()).is_block());
-        assert!(LayoutBoxPtr(root.first_child().unwrap()).is_anonymous());
+        assert!(root.first_child().unwrap().is_block());
+        assert!(root.first_child().unwrap().is_anonymous());
 
-        assert!(LayoutBoxPtr(root.nth_child(1).unwrap()).is_block());
+        assert!(root.nth_child(1).unwrap().is_block());
+
+        assert!(root.nth_child(2).unwrap().is_block());
+        assert!(root.nth_child(2).unwrap().is_anonymous());
+    }
+
+    #[test]
+    fn test_nested_list_numbering() {
+        let document = document();
+        let dom = element(
+            "ol",
+            document.clone(),
+            vec![
+                element("li", document.clone(), vec![]),
+                element(
+                    "li",
+                    document.clone(),
+                    vec![element(
+                        "ol",
+                        document.clone(),
+                        vec![
+                            element("li", document.clone(), vec![]),
+                            element("li", document.clone(), vec![]),
+                        ],
+                    )],
+                ),
+                element("li", document.clone(), vec![]),
+            ],
+        );
 
-        assert!(LayoutBoxPtr(root.nth_child(2).unwrap()).is_block());
-        assert!(LayoutBoxPtr(root.nth_child(2).unwrap()).is_anonymous());
+        let root = build_tree(dom, SHARED_CSS);
+        let ordinal = |layout_box: &LayoutBoxPtr| layout_box.marker().map(|m| m.ordinal);
+
+        let second_item = root.nth_child(1).unwrap();
+        let nested_list = second_item.first_child().unwrap();
+
+        assert_eq!(ordinal(&root.first_child().unwrap()), Some(1));
+        assert_eq!(ordinal(&second_item), Some(2));
+        assert_eq!(ordinal(&root.nth_child(2).unwrap()), Some(3));
+        assert_eq!(ordinal(&nested_list.first_child().unwrap()), Some(1));
+        assert_eq!(ordinal(&nested_list.nth_child(1).unwrap()), Some(2));
+        assert_eq!(ordinal(&nested_list), None);
+    }
+
+    #[test]
+    fn test_build_pseudo_elements() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![text("hello", document.clone())],
+        );
+
+        let css = r#"
+        div { display: block; }
+        div::before { content: "before"; }
+        div::after { content: "after"; display: block; }
+        "#;
+        let root = build_tree(dom, css);
+
+        // The result box tree should look like this
+        // [Block] - Div
+        //   |- [Block Anonymous]
+        //        |- [Inline] - ::before
+        //             |- [TextRun]
+        //        |- [TextRun]
+        //   |- [Block] - ::after
+        //        |- [TextRun]
+
+        let tag_name =
+            |layout_box: LayoutBoxPtr| layout_box.node().unwrap().as_element().tag_name();
+
+        assert_eq!(root.children_count(), 2);
+
+        let anonymous = root.first_child().unwrap();
+        assert!(anonymous.is_anonymous());
+        assert_eq!(anonymous.children_count(), 2);
+        assert_eq!(tag_name(anonymous.first_child().unwrap()), "::before");
+
+        let after = root.nth_child(1).unwrap();
+        assert!(after.is_block());
+        assert_eq!(tag_name(after), "::after");
+    }
+
+    #[test]
+    fn test_display_contents() {
+        let document = document();
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![element(
+                "div.contents",
+                document.clone(),
+                vec![
+                    element("p", document.clone(), vec![]),
+                    element("p", document.clone(), vec![]),
+                ],
+            )],
+        );
+
+        let css = r#"
+        div, p { display: block; }
+        .contents { display: contents; }
+        "#;
+        let root = build_tree(dom, css);
+
+        // The result box tree should look like this
+        // [Block] - Div
+        //   |- [Block] - P
+        //   |- [Block] - P
+
+        assert_eq!(root.children_count(), 2);
+        for index in 0..2 {
+            let child = root.nth_child(index).unwrap();
+            assert_eq!(child.node().unwrap().as_element().tag_name(), "p");
+        }
+    }
+
+    #[test]
+    fn test_build_shadow_tree() {
+        let document = document();
+        let named = element("p#named", document.clone(), vec![]);
+        named.as_element().set_attribute("slot", "header");
+        let unassigned = element("p#unassigned", document.clone(), vec![]);
+        unassigned.as_element().set_attribute("slot", "missing");
+        let dom = element(
+            "div",
+            document.clone(),
+            vec![
+                element("p#light", document.clone(), vec![]),
+                unassigned,
+                named,
+            ],
+        );
+
+        let header_slot = element("slot", document.clone(), vec![]);
+        header_slot.as_element().set_attribute("name", "header");
+        let shadow_root = dom.attach_shadow().unwrap();
+        shadow_root.append_child(
+            element(
+                "div",
+                document.clone(),
+                vec![header_slot, element("slot", document.clone(), vec![])],
+            )
+            .0,
+        );
+
+        let css = r#"
+        div, p { display: block; }
+        slot { display: contents; }
+        "#;
+        let root = build_tree(dom, css);
+
+        // The result box tree should look like this
+        // [Block] - Div (host)
+        //   |- [Block] - Div (shadow tree)
+        //        |- [Block] - P#named
+        //        |- [Block] - P#light
+
+        let id = |layout_box: LayoutBoxPtr| layout_box.node().unwrap().as_element().id();
+
+        assert_eq!(root.children_count(), 1);
+        let shadow_div = root.first_child().unwrap();
+        assert_eq!(shadow_div.children_count(), 2);
+        assert_eq!(
+            id(shadow_div.first_child().unwrap()),
+            Some("named".to_string())
+        );
+        assert_eq!(
+            id(shadow_div.nth_child(1).unwrap()),
+            Some("light".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve_counters() {
+        let document = document();
+        let item = |children| element("li", document.clone(), children);
+        let dom = element(
+            "ol",
+            document.clone(),
+            vec![
+                item(vec![]),
+                item(vec![element(
+                    "ol",
+                    document.clone(),
+                    vec![item(vec![]), item(vec![])],
+                )]),
+                element("h2", document.clone(), vec![]),
+                element("h2", document.clone(), vec![]),
+            ],
+        );
+
+        let css = r#"
+        ol, li, h2 { display: block; }
+        ol { counter-reset: item; }
+        li { counter-increment: item; }
+        li::before { content: counters(item, ".") ". "; }
+        h2 { counter-increment: chapter 2; }
+        h2::before { content: "Chapter " counter(chapter) counter(missing); }
+        "#;
+        build_tree(dom.clone(), css);
+
+        let before_text = |node: &NodePtr| {
+            let before = node.pseudo_element(&PseudoElement::Before).unwrap();
+            NodePtr(before.first_child().unwrap()).as_text().get_data()
+        };
+        let child = |node: &NodePtr, index| NodePtr(node.nth_child(index).unwrap());
+
+        let second_item = child(&dom, 1);
+        let nested_list = child(&second_item, 0);
+        assert_eq!(before_text(&child(&dom, 0)), "1. ");
+        assert_eq!(before_text(&second_item), "2. ");
+        assert_eq!(before_text(&child(&nested_list, 0)), "2.1. ");
+        assert_eq!(before_text(&child(&nested_list, 1)), "2.2. ");
+        assert_eq!(before_text(&child(&dom, 2)), "Chapter 20");
+        assert_eq!(before_text(&child(&dom, 3)), "Chapter 40");
+    }
+
+    #[test]
+    fn test_details() {
+        let css = r#"
+        details, summary, p { display: block; }
+        details > summary:first-of-type {
+            display: list-item;
+            list-style: disclosure-closed inside;
+        }
+        details:open > summary:first-of-type { list-style-type: disclosure-open; }
+        "#;
+        let build = |is_open: bool| {
+            let document = document();
+            let dom = element(
+                "details",
+                document.clone(),
+                vec![
+                    element("p", document.clone(), vec![]),
+                    element("summary", document.clone(), vec![]),
+                    element("summary", document.clone(), vec![]),
+                ],
+            );
+            if is_open {
+                dom.as_element().set_attribute("open", "");
+            }
+            build_tree(dom, css)
+        };
+        let marker_type =
+            |summary: LayoutBoxPtr| summary.marker().map(|marker| marker.style_type.clone());
+
+        let closed = build(false);
+        assert_eq!(closed.children_count(), 1);
+        assert_eq!(
+            marker_type(closed.first_child().unwrap()),
+            Some(ListStyleType::DisclosureClosed)
+        );
+
+        let open = build(true);
+        assert_eq!(open.children_count(), 3);
+        assert_eq!(
+            marker_type(open.nth_child(1).unwrap()),
+            Some(ListStyleType::DisclosureOpen)
+        );
+        assert_eq!(marker_type(open.nth_child(2).unwrap()), None);
     }
 }
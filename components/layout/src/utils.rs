@@ -15,10 +15,16 @@ p, div {
 span, a {
     display: inline;
 }
+ul, ol {
+    display: block;
+}
+li {
+    display: list-item;
+}
 .inline-block {
     display: inline-block;
 }
-}"#;
+"#;
 
 pub fn build_tree(dom: NodePtr, css: &str) -> LayoutBoxPtr {
     let document = dom.owner_document().unwrap();
@@ -27,19 +33,24 @@ pub fn build_tree(dom: NodePtr, css: &str) -> LayoutBoxPtr {
 
     let rules = stylesheet
         .iter()
-        .map(|rule| match rule {
-            CSSRule::Style(style) => ContextualRule {
+        .filter_map(|rule| match rule {
+            CSSRule::Style(style) => Some(ContextualRule {
                 inner: style.clone(),
                 location: CSSLocation::Embedded,
                 origin: CascadeOrigin::User,
-            },
+            }),
+            _ => None,
         })
         .collect::<Vec<ContextualRule>>();
 
     fn compute_styles(element: NodePtr, style_rules: &[ContextualRule]) {
         let computed_styles = style::compute::compute_styles(element.clone(), &style_rules);
         element.set_computed_styles(computed_styles);
+        style::pseudo_element::update_pseudo_elements(&element, style_rules);
 
+        if let Some(shadow_root) = element.as_element_opt().and_then(|e| e.shadow_root()) {
+            shadow_root.for_each_child(|child| compute_styles(NodePtr(child), style_rules));
+        }
         element.for_each_child(|child| compute_styles(NodePtr(child), style_rules))
     }
 
@@ -0,0 +1,183 @@
+use dom::node::NodePtr;
+use shared::primitive::{EdgeSizes, Point, Size};
+use style_types::{values::prelude::WritingMode, Property, Value};
+
+use crate::layout_box::LayoutBoxPtr;
+
+pub fn writing_mode_of(node: &NodePtr) -> WritingMode {
+    match node.get_style(&Property::WritingMode) {
+        Value::WritingMode(writing_mode) => writing_mode,
+        _ => WritingMode::HorizontalTb,
+    }
+}
+
+/// Whether the block box starts a vertical flow in a horizontal one. Its
+/// content is laid out in flow-relative coordinates, then converted to
+/// physical ones. Horizontal boxes in vertical flows are laid out in the
+/// writing mode of their flow.
+/// https://www.w3.org/TR/css-writing-modes-3/#orthogonal-flows
+pub fn is_orthogonal_flow_root(layout_box: &LayoutBoxPtr, flow_writing_mode: WritingMode) -> bool {
+    if flow_writing_mode.is_vertical() || !layout_box.is_block() {
+        return false;
+    }
+    match layout_box.node() {
+        Some(node) => writing_mode_of(&node).is_vertical(),
+        None => false,
+    }
+}
+
+/// Computed value of a property of the node named after its direction in
+/// `horizontal-tb`, which the flow algorithms are written for. In vertical
+/// writing modes, `width` is the height of the box, `margin-left` is its
+/// top margin and `margin-top` is its right margin in `vertical-rl`.
+/// https://www.w3.org/TR/css-writing-modes-3/#logical-to-physical
+pub fn flow_relative_style(
+    node: &NodePtr,
+    property: &Property,
+    writing_mode: WritingMode,
+) -> Value {
+    node.get_style(&physical_property(property, writing_mode))
+}
+
+fn physical_property(property: &Property, writing_mode: WritingMode) -> Property {
+    if !writing_mode.is_vertical() {
+        return property.clone();
+    }
+    for sides in &SIDE_PROPERTIES {
+        if let Some(side) = sides.iter().position(|side| side == property) {
+            return sides[physical_side(side, writing_mode)].clone();
+        }
+    }
+    match property {
+        Property::Width => Property::Height,
+        Property::Height => Property::Width,
+        property => property.clone(),
+    }
+}
+
+/// Properties of the sides of a box in the order top, right, bottom, left
+const SIDE_PROPERTIES: [[Property; 4]; 3] = [
+    [
+        Property::MarginTop,
+        Property::MarginRight,
+        Property::MarginBottom,
+        Property::MarginLeft,
+    ],
+    [
+        Property::PaddingTop,
+        Property::PaddingRight,
+        Property::PaddingBottom,
+        Property::PaddingLeft,
+    ],
+    [
+        Property::BorderTopWidth,
+        Property::BorderRightWidth,
+        Property::BorderBottomWidth,
+        Property::BorderLeftWidth,
+    ],
+];
+
+/// Physical side of a box in a vertical writing mode for the side named
+/// after its direction in `horizontal-tb`, both in the order top, right,
+/// bottom, left. Inline-start is on the top, block-start is on the right
+/// in `vertical-rl` and on the left in `vertical-lr`.
+fn physical_side(side: usize, writing_mode: WritingMode) -> usize {
+    let is_rl = writing_mode == WritingMode::VerticalRl;
+    match side {
+        0 if is_rl => 1,
+        0 => 3,
+        1 => 2,
+        2 if is_rl => 3,
+        2 => 1,
+        _ => 0,
+    }
+}
+
+/// Move the flow-relative edges to their physical sides
+fn to_physical_edges(edges: &EdgeSizes, writing_mode: WritingMode) -> EdgeSizes {
+    let sides = [edges.top, edges.right, edges.bottom, edges.left];
+    let mut physical = [0.; 4];
+    for (side, size) in sides.iter().enumerate() {
+        physical[physical_side(side, writing_mode)] = *size;
+    }
+    EdgeSizes {
+        top: physical[0],
+        right: physical[1],
+        bottom: physical[2],
+        left: physical[3],
+    }
+}
+
+/// Convert the layout of the content of an orthogonal flow root from the
+/// flow-relative coordinates it was laid out in to physical ones. Sizes
+/// are transposed, and blocks stack from the right in `vertical-rl`.
+pub fn to_physical(root: &LayoutBoxPtr, writing_mode: WritingMode) {
+    let size = root.content_size();
+    convert_children(root, size.height, writing_mode);
+    root.set_content_width(size.height);
+    root.set_content_height(size.width);
+}
+
+/// Convert the lines and children of a box whose containing block for
+/// them has the flow-relative `block_size`
+fn convert_children(layout_box: &LayoutBoxPtr, block_size: f32, writing_mode: WritingMode) {
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        convert_lines(layout_box, block_size, writing_mode);
+    }
+    layout_box.for_each_child(|child| {
+        convert_box(&child, block_size, writing_mode);
+    });
+}
+
+fn convert_box(layout_box: &LayoutBoxPtr, containing_block_size: f32, writing_mode: WritingMode) {
+    let size = layout_box.content_size();
+    // Inline-level boxes are placed by the fragments of their lines
+    if layout_box.is_block() {
+        let offset = layout_box.offset();
+        let x = match writing_mode {
+            WritingMode::VerticalRl => containing_block_size - offset.y - size.height,
+            _ => offset.y,
+        };
+        layout_box.set_offset(x, offset.x);
+    }
+    {
+        let mut box_model = layout_box.box_model().borrow_mut();
+        box_model.margin = to_physical_edges(&box_model.margin, writing_mode);
+        box_model.border = to_physical_edges(&box_model.border, writing_mode);
+        box_model.padding = to_physical_edges(&box_model.padding, writing_mode);
+        box_model.offset = to_physical_edges(&box_model.offset, writing_mode);
+    }
+
+    let block_size = match layout_box.is_block() || layout_box.is_inline_block() {
+        true => size.height,
+        false => containing_block_size,
+    };
+    convert_children(layout_box, block_size, writing_mode);
+    layout_box.set_content_width(size.height);
+    layout_box.set_content_height(size.width);
+}
+
+/// Lines stack like blocks, but the over side of every line, where the
+/// tops of the glyphs are, is on the right in both vertical writing modes
+/// https://www.w3.org/TR/css-writing-modes-3/#line-orientation
+fn convert_lines(layout_box: &LayoutBoxPtr, block_size: f32, writing_mode: WritingMode) {
+    let mut line_top = 0.;
+    for line in layout_box.lines().borrow_mut().iter_mut() {
+        let line_height = line.size.height;
+        let line_left = match writing_mode {
+            WritingMode::VerticalRl => block_size - line_top - line_height,
+            _ => line_top,
+        };
+        for fragment in &mut line.fragments {
+            let over_offset = fragment.offset.y - line_top;
+            fragment.offset = Point::new(
+                line_left + line_height - over_offset - fragment.size.height,
+                fragment.offset.x,
+            );
+            fragment.size = Size::new(fragment.size.height, fragment.size.width);
+        }
+        line.size = Size::new(line_height, line.size.width);
+        line.is_vertical = true;
+        line_top += line_height;
+    }
+}
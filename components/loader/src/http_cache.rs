@@ -0,0 +1,618 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use net::http::Response;
+
+/// Size of the cached bodies kept in memory, in bytes
+const DEFAULT_CAPACITY: usize = 32 * 1024 * 1024;
+/// Part of the time since a response was last modified during which it's
+/// considered fresh, when it has no explicit expiration time
+const HEURISTIC_FRESHNESS_FACTOR: f64 = 0.1;
+/// Longest heuristic freshness lifetime
+const MAX_HEURISTIC_FRESHNESS: Duration = Duration::from_secs(24 * 60 * 60);
+/// Largest number of seconds of `max-age` and `Age`, larger ones are
+/// clamped to it as required by RFC 9111 §1.2.2
+const MAX_DELTA_SECONDS: u64 = 2147483648;
+
+/// Counters of the cache, for debugging
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    /// Requests served from the cache without contacting the server
+    pub hits: usize,
+    /// Requests sent to the server, including revalidations
+    pub misses: usize,
+    /// Stale responses the server confirmed with `304 Not Modified`, so
+    /// their body wasn't downloaded again
+    pub revalidations: usize,
+    /// Number of responses in memory
+    pub entries: usize,
+    /// Size of the bodies in memory, in bytes
+    pub size: usize,
+}
+
+/// What to do with a request according to the cache
+#[derive(Debug, PartialEq)]
+pub enum CacheLookup {
+    /// The cached body can be used as is
+    Fresh(Vec<u8>),
+    /// The cached response is stale. The request should be sent with these
+    /// conditional headers, see `HttpCache::revalidated`.
+    Stale(Vec<(String, String)>),
+    Miss,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    body: Vec<u8>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fresh_until: SystemTime,
+    /// Value of `HttpCache::clock` when the entry was last used, for
+    /// evicting the least recently used entries
+    last_used: u64,
+}
+
+impl CacheEntry {
+    fn validators(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = &self.etag {
+            headers.push(("if-none-match".to_string(), etag.clone()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push(("if-modified-since".to_string(), last_modified.clone()));
+        }
+        headers
+    }
+}
+
+/// Cache of HTTP responses following the caching rules of RFC 9111 for a
+/// private cache. Responses are kept in memory, and optionally on disk so
+/// they survive restarts.
+pub struct HttpCache {
+    entries: HashMap<String, CacheEntry>,
+    capacity: usize,
+    disk: Option<DiskCache>,
+    stats: CacheStats,
+    clock: u64,
+}
+
+impl HttpCache {
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    /// Cache keeping at most `capacity` bytes of bodies in memory
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            capacity,
+            disk: None,
+            stats: CacheStats::default(),
+            clock: 0,
+        }
+    }
+
+    /// Store responses in `dir` too. Responses evicted from memory are read
+    /// back from there.
+    pub fn set_disk_dir(&mut self, dir: PathBuf) {
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            log::warn!("Unable to create cache directory: {} ({:?})", e, dir);
+        }
+        self.disk = Some(DiskCache { dir });
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.entries.len(),
+            size: self.entries.values().map(|entry| entry.body.len()).sum(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Remove every response, from memory and disk
+    pub fn clear(&mut self) {
+        if let Some(disk) = &self.disk {
+            disk.clear();
+        }
+        self.entries.clear();
+    }
+
+    pub fn lookup(&mut self, key: &str, now: SystemTime) -> CacheLookup {
+        if !self.entries.contains_key(key) {
+            if let Some(entry) = self.disk.as_ref().and_then(|disk| disk.read(key)) {
+                self.insert(key, entry);
+            }
+        }
+
+        self.clock += 1;
+        let clock = self.clock;
+        match self.entries.get_mut(key) {
+            Some(entry) if now < entry.fresh_until => {
+                entry.last_used = clock;
+                self.stats.hits += 1;
+                CacheLookup::Fresh(entry.body.clone())
+            }
+            Some(entry) => {
+                entry.last_used = clock;
+                self.stats.misses += 1;
+                CacheLookup::Stale(entry.validators())
+            }
+            None => {
+                self.stats.misses += 1;
+                CacheLookup::Miss
+            }
+        }
+    }
+
+    /// Store a response received from the server, if it can be cached
+    pub fn store(&mut self, key: &str, response: &Response, now: SystemTime) {
+        if response.status != 200 || !is_storable(&response.headers) {
+            self.invalidate(key);
+            return;
+        }
+
+        let etag = response.header("etag").map(str::to_string);
+        let last_modified = response.header("last-modified").map(str::to_string);
+        let fresh_until = fresh_until(now, freshness_lifetime(&response.headers, now));
+        // A stale response without validators would never be used
+        if fresh_until <= now && etag.is_none() && last_modified.is_none() {
+            self.invalidate(key);
+            return;
+        }
+
+        let entry = CacheEntry {
+            body: response.body.clone(),
+            etag,
+            last_modified,
+            fresh_until,
+            last_used: 0,
+        };
+        if let Some(disk) = &self.disk {
+            disk.write(key, &entry);
+        }
+        self.insert(key, entry);
+    }
+
+    /// Refresh a stale response the server answered with `304 Not Modified`
+    /// and return its body. Returns `None` if the response isn't cached
+    /// anymore.
+    pub fn revalidated(
+        &mut self,
+        key: &str,
+        response: &Response,
+        now: SystemTime,
+    ) -> Option<Vec<u8>> {
+        let entry = self.entries.get_mut(key)?;
+
+        // The headers of the 304 response replace the stored ones
+        let mut headers = response.headers.clone();
+        if let Some(etag) = response.header("etag") {
+            entry.etag = Some(etag.to_string());
+        }
+        match response.header("last-modified") {
+            Some(last_modified) => entry.last_modified = Some(last_modified.to_string()),
+            None => {
+                if let Some(last_modified) = &entry.last_modified {
+                    headers.push(("last-modified".to_string(), last_modified.clone()));
+                }
+            }
+        }
+        entry.fresh_until = fresh_until(now, freshness_lifetime(&headers, now));
+
+        self.stats.revalidations += 1;
+        if let Some(disk) = &self.disk {
+            disk.write(key, entry);
+        }
+        Some(entry.body.clone())
+    }
+
+    fn insert(&mut self, key: &str, mut entry: CacheEntry) {
+        if entry.body.len() > self.capacity {
+            self.entries.remove(key);
+            return;
+        }
+        self.clock += 1;
+        entry.last_used = self.clock;
+        self.entries.insert(key.to_string(), entry);
+
+        let mut size: usize = self.entries.values().map(|entry| entry.body.len()).sum();
+        while size > self.capacity {
+            let least_recently_used = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match least_recently_used.and_then(|key| self.entries.remove(&key)) {
+                Some(entry) => size -= entry.body.len(),
+                None => break,
+            }
+        }
+    }
+
+    /// Drop the response of `key`, for example because a request changed
+    /// the resource
+    pub fn invalidate(&mut self, key: &str) {
+        self.entries.remove(key);
+        if let Some(disk) = &self.disk {
+            disk.remove(key);
+        }
+    }
+}
+
+impl Default for HttpCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Directives of the `Cache-Control` header, with their optional argument
+fn cache_control(headers: &[(String, String)]) -> Vec<(String, Option<String>)> {
+    headers
+        .iter()
+        .filter(|(name, _)| name == "cache-control")
+        .flat_map(|(_, value)| value.split(','))
+        .map(|directive| {
+            let mut parts = directive.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim().to_lowercase();
+            let argument = parts
+                .next()
+                .map(|arg| arg.trim().trim_matches('"').to_string());
+            (name, argument)
+        })
+        .collect()
+}
+
+fn is_storable(headers: &[(String, String)]) -> bool {
+    let no_store = cache_control(headers)
+        .iter()
+        .any(|(name, _)| name == "no-store");
+    // Responses varying on every request header can't be matched
+    let varies_on_everything = header(headers, "vary")
+        .is_some_and(|vary| vary.split(',').any(|field| field.trim() == "*"));
+    !no_store && !varies_on_everything
+}
+
+/// How long a response stays fresh from `now`, the time it was received
+fn freshness_lifetime(headers: &[(String, String)], now: SystemTime) -> Duration {
+    let directives = cache_control(headers);
+    if directives.iter().any(|(name, _)| name == "no-cache") {
+        return Duration::ZERO;
+    }
+
+    let date = header(headers, "date")
+        .and_then(|date| httpdate::parse_http_date(date).ok())
+        .unwrap_or(now);
+    let age = header(headers, "age")
+        .and_then(|age| delta_seconds(age.trim()))
+        .unwrap_or_default();
+
+    let max_age = directives
+        .iter()
+        .find(|(name, _)| name == "max-age")
+        .and_then(|(_, argument)| delta_seconds(argument.as_ref()?));
+    let lifetime = match (max_age, header(headers, "expires")) {
+        (Some(max_age), _) => max_age,
+        // Invalid dates, like "0", mean the response has already expired
+        (None, Some(expires)) => httpdate::parse_http_date(expires)
+            .ok()
+            .and_then(|expires| expires.duration_since(date).ok())
+            .unwrap_or_default(),
+        (None, None) => header(headers, "last-modified")
+            .and_then(|last_modified| httpdate::parse_http_date(last_modified).ok())
+            .and_then(|last_modified| date.duration_since(last_modified).ok())
+            .map(|modified_since| {
+                modified_since
+                    .mul_f64(HEURISTIC_FRESHNESS_FACTOR)
+                    .min(MAX_HEURISTIC_FRESHNESS)
+            })
+            .unwrap_or_default(),
+    };
+    lifetime.saturating_sub(age)
+}
+
+/// Duration of a number of seconds, clamped to `MAX_DELTA_SECONDS`
+fn delta_seconds(value: &str) -> Option<Duration> {
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let seconds = value.parse().unwrap_or(u64::MAX).min(MAX_DELTA_SECONDS);
+    Some(Duration::from_secs(seconds))
+}
+
+/// When a response received at `now` stops being fresh. A time that can't
+/// be represented makes it stale right away.
+fn fresh_until(now: SystemTime, lifetime: Duration) -> SystemTime {
+    now.checked_add(lifetime).unwrap_or(now)
+}
+
+/// Entries stored as a file per URL. A file starts with a line for each of
+/// the URL, the expiration time in seconds since the epoch, the ETag and
+/// the last modification date, followed by the body.
+struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+
+    fn read(&self, key: &str) -> Option<CacheEntry> {
+        let content = std::fs::read(self.path(key)).ok()?;
+        let mut parts = content.splitn(5, |byte| *byte == b'\n');
+        let mut line = || {
+            parts
+                .next()
+                .map(|line| String::from_utf8_lossy(line).to_string())
+        };
+
+        // Files of different URLs with the same hash are ignored
+        if line()? != key {
+            return None;
+        }
+        // A time that can't be represented is a corrupt file
+        let fresh_until = UNIX_EPOCH.checked_add(Duration::from_secs(line()?.parse().ok()?))?;
+        let etag = Some(line()?).filter(|etag| !etag.is_empty());
+        let last_modified = Some(line()?).filter(|date| !date.is_empty());
+        let body = parts.next()?.to_vec();
+
+        Some(CacheEntry {
+            body,
+            etag,
+            last_modified,
+            fresh_until,
+            last_used: 0,
+        })
+    }
+
+    fn write(&self, key: &str, entry: &CacheEntry) {
+        let fresh_until = entry
+            .fresh_until
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut content = format!(
+            "{}\n{}\n{}\n{}\n",
+            key,
+            fresh_until,
+            entry.etag.as_deref().unwrap_or_default(),
+            entry.last_modified.as_deref().unwrap_or_default()
+        )
+        .into_bytes();
+        content.extend_from_slice(&entry.body);
+
+        if let Err(e) = std::fs::write(self.path(key), content) {
+            log::warn!("Unable to write cache entry: {} ({})", e, key);
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let _ = std::fs::remove_file(self.path(key));
+    }
+
+    fn clear(&self) {
+        if let Ok(files) = std::fs::read_dir(&self.dir) {
+            for file in files.flatten() {
+                let _ = std::fs::remove_file(file.path());
+            }
+        }
+    }
+}
+
+/// FNV-1a hash, which unlike the hasher of the standard library is stable
+/// across builds, so file names stay the same
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "http://example.com/style.css";
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Response {
+        Response {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap()
+            + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_max_age_and_revalidation() {
+        let mut cache = HttpCache::new();
+        assert_eq!(cache.lookup(URL, time(0)), CacheLookup::Miss);
+
+        let headers = [("cache-control", "public, max-age=60"), ("etag", "\"v1\"")];
+        cache.store(URL, &response(200, &headers, "body"), time(0));
+        assert_eq!(
+            cache.lookup(URL, time(59)),
+            CacheLookup::Fresh(b"body".to_vec())
+        );
+        assert_eq!(
+            cache.lookup(URL, time(60)),
+            CacheLookup::Stale(vec![("if-none-match".to_string(), "\"v1\"".to_string())])
+        );
+
+        let not_modified = response(304, &[("cache-control", "max-age=10")], "");
+        assert_eq!(
+            cache.revalidated(URL, &not_modified, time(60)),
+            Some(b"body".to_vec())
+        );
+        assert!(matches!(cache.lookup(URL, time(69)), CacheLookup::Fresh(_)));
+        assert!(matches!(cache.lookup(URL, time(70)), CacheLookup::Stale(_)));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.revalidations), (2, 3, 1));
+        assert_eq!((stats.entries, stats.size), (1, 4));
+    }
+
+    #[test]
+    fn test_freshness_lifetime() {
+        let headers = |headers: &[(&str, &str)]| response(200, headers, "").headers;
+        let date = httpdate::fmt_http_date(time(0));
+        let expires = httpdate::fmt_http_date(time(300));
+        let last_modified = httpdate::fmt_http_date(time(0) - Duration::from_secs(1000));
+
+        assert_eq!(
+            freshness_lifetime(&headers(&[("cache-control", "max-age=60")]), time(0)),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            freshness_lifetime(
+                &headers(&[("cache-control", "max-age=60"), ("age", "20")]),
+                time(0)
+            ),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[("date", &date), ("expires", &expires)]), time(5)),
+            Duration::from_secs(300)
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[("expires", "0")]), time(0)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            freshness_lifetime(&headers(&[("last-modified", &last_modified)]), time(0)),
+            Duration::from_secs(100)
+        );
+        assert_eq!(
+            freshness_lifetime(
+                &headers(&[("cache-control", "no-cache, max-age=60")]),
+                time(0)
+            ),
+            Duration::ZERO
+        );
+        // Delta seconds too large for the clock are clamped
+        assert_eq!(
+            freshness_lifetime(
+                &headers(&[("cache-control", "max-age=18446744073709551615")]),
+                time(0)
+            ),
+            Duration::from_secs(MAX_DELTA_SECONDS)
+        );
+        assert_eq!(
+            freshness_lifetime(
+                &headers(&[
+                    ("cache-control", "max-age=60"),
+                    ("age", "99999999999999999999")
+                ]),
+                time(0)
+            ),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_huge_max_age() {
+        let mut cache = HttpCache::new();
+        let headers = [
+            ("cache-control", "max-age=18446744073709551615"),
+            ("etag", "\"v1\""),
+        ];
+        cache.store(URL, &response(200, &headers, "body"), time(0));
+        assert!(matches!(cache.lookup(URL, time(0)), CacheLookup::Fresh(_)));
+        let expiry = time(MAX_DELTA_SECONDS);
+        assert!(matches!(cache.lookup(URL, expiry), CacheLookup::Stale(_)));
+
+        let not_modified = response(304, &headers, "");
+        let revalidated = cache.revalidated(URL, &not_modified, expiry);
+        assert_eq!(revalidated, Some(b"body".to_vec()));
+        assert!(matches!(cache.lookup(URL, expiry), CacheLookup::Fresh(_)));
+    }
+
+    #[test]
+    fn test_uncacheable_responses() {
+        let mut cache = HttpCache::new();
+        let cases: [(u16, &[(&str, &str)]); 4] = [
+            (200, &[("cache-control", "no-store, max-age=60")]),
+            (200, &[("cache-control", "max-age=60"), ("vary", "*")]),
+            (404, &[("cache-control", "max-age=60")]),
+            // Stale right away and can't be revalidated
+            (200, &[]),
+        ];
+        for (status, headers) in cases {
+            cache.store(URL, &response(status, headers, "body"), time(0));
+            assert_eq!(cache.lookup(URL, time(0)), CacheLookup::Miss);
+        }
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = HttpCache::with_capacity(10);
+        let fresh = response(200, &[("cache-control", "max-age=60")], "12345");
+        cache.store("a", &fresh, time(0));
+        cache.store("b", &fresh, time(0));
+        // "a" is used more recently than "b"
+        cache.lookup("a", time(0));
+        cache.store("c", &fresh, time(0));
+
+        assert!(matches!(cache.lookup("a", time(0)), CacheLookup::Fresh(_)));
+        assert_eq!(cache.lookup("b", time(0)), CacheLookup::Miss);
+        assert!(matches!(cache.lookup("c", time(0)), CacheLookup::Fresh(_)));
+        assert_eq!(cache.stats().size, 10);
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("moon-http-cache-{}", std::process::id()));
+        let headers = [
+            ("cache-control", "max-age=60"),
+            ("last-modified", "Sat, 05 Nov 1994 08:49:37 GMT"),
+        ];
+
+        let mut cache = HttpCache::new();
+        cache.set_disk_dir(dir.clone());
+        cache.store(URL, &response(200, &headers, "line\nbody"), time(0));
+
+        // A new cache reads the responses stored by the previous one
+        let mut cache = HttpCache::new();
+        cache.set_disk_dir(dir.clone());
+        assert_eq!(
+            cache.lookup(URL, time(0)),
+            CacheLookup::Fresh(b"line\nbody".to_vec())
+        );
+        assert_eq!(
+            cache.lookup(URL, time(60)),
+            CacheLookup::Stale(vec![(
+                "if-modified-since".to_string(),
+                "Sat, 05 Nov 1994 08:49:37 GMT".to_string()
+            )])
+        );
+
+        cache.clear();
+        let mut cache = HttpCache::new();
+        cache.set_disk_dir(dir.clone());
+        assert_eq!(cache.lookup(URL, time(0)), CacheLookup::Miss);
+
+        // An expiration time that overflows the clock is a miss
+        let disk = DiskCache { dir: dir.clone() };
+        let content = format!("{}\n18446744073709551615\n\n\nbody", URL);
+        std::fs::write(disk.path(URL), content).unwrap();
+        assert!(disk.read(URL).is_none());
+        let mut cache = HttpCache::new();
+        cache.set_disk_dir(dir.clone());
+        assert_eq!(cache.lookup(URL, time(0)), CacheLookup::Miss);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
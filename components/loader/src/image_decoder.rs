@@ -0,0 +1,181 @@
+use std::io::Cursor;
+use std::sync::{Arc, RwLock};
+
+use image::codecs::{gif::GifDecoder as GifCodec, webp::WebPDecoder as WebPCodec};
+use image::{DynamicImage, ImageError};
+
+/// Decoders registered by the embedder, tried before the built-in ones
+static REGISTERED_DECODERS: RwLock<Vec<Arc<dyn ImageDecoder>>> = RwLock::new(Vec::new());
+
+/// Bitmap of a decoded image, in RGBA8 without premultiplied alpha
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    /// None of the decoders recognize the format of the image
+    UnsupportedFormat,
+    InvalidData(String),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[DecodeError] {:?}", self)
+    }
+}
+
+impl From<ImageError> for DecodeError {
+    fn from(error: ImageError) -> Self {
+        DecodeError::InvalidData(error.to_string())
+    }
+}
+
+/// Decoder of an image format
+pub trait ImageDecoder: Send + Sync {
+    /// Whether the bytes are in the format of this decoder, usually by
+    /// checking their signature
+    fn can_decode(&self, bytes: &[u8]) -> bool;
+
+    fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, DecodeError>;
+}
+
+/// GIF images. Only the first frame of animated images is decoded.
+pub struct GifDecoder;
+
+impl ImageDecoder for GifDecoder {
+    fn can_decode(&self, bytes: &[u8]) -> bool {
+        bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a")
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+        // Reading a single image from the decoder composes the first frame
+        let image = DynamicImage::from_decoder(GifCodec::new(Cursor::new(bytes))?)?;
+        Ok(to_decoded_image(image))
+    }
+}
+
+/// WebP images using lossy compression
+pub struct WebPDecoder;
+
+impl ImageDecoder for WebPDecoder {
+    fn can_decode(&self, bytes: &[u8]) -> bool {
+        bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP"
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+        let image = DynamicImage::from_decoder(WebPCodec::new(Cursor::new(bytes))?)?;
+        Ok(to_decoded_image(image))
+    }
+}
+
+fn to_decoded_image(image: DynamicImage) -> DecodedImage {
+    let image = image.into_rgba8();
+    DecodedImage {
+        width: image.width(),
+        height: image.height(),
+        data: image.into_raw(),
+    }
+}
+
+/// Add support for an image format. Decoders registered later take
+/// precedence, so a format supported by the engine can be overridden too.
+pub fn register_image_decoder(decoder: Arc<dyn ImageDecoder>) {
+    REGISTERED_DECODERS.write().unwrap().push(decoder);
+}
+
+/// Decode an image with the first decoder that recognizes its format
+pub fn decode_image(bytes: &[u8]) -> Result<DecodedImage, DecodeError> {
+    let registered = REGISTERED_DECODERS.read().unwrap();
+    let built_in: [&dyn ImageDecoder; 2] = [&GifDecoder, &WebPDecoder];
+
+    let decoder = registered
+        .iter()
+        .rev()
+        .map(|decoder| decoder.as_ref())
+        .chain(built_in)
+        .find(|decoder| decoder.can_decode(bytes))
+        .ok_or(DecodeError::UnsupportedFormat)?;
+    decoder.decode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::codecs::gif::GifEncoder;
+    use image::{Frame, Rgba, RgbaImage};
+
+    use super::*;
+
+    /// Animation of two frames of 2x1 pixels, the first one red and green
+    fn animated_gif() -> Vec<u8> {
+        let frame = |left, right| {
+            Frame::new(RgbaImage::from_fn(
+                2,
+                1,
+                |x, _| if x == 0 { left } else { right },
+            ))
+        };
+        let mut bytes = Vec::new();
+        GifEncoder::new(&mut bytes)
+            .encode_frames(vec![
+                frame(Rgba([255, 0, 0, 255]), Rgba([0, 255, 0, 255])),
+                frame(Rgba([0, 0, 255, 255]), Rgba([0, 0, 255, 255])),
+            ])
+            .unwrap();
+        bytes
+    }
+
+    struct FakeDecoder;
+
+    impl ImageDecoder for FakeDecoder {
+        fn can_decode(&self, bytes: &[u8]) -> bool {
+            bytes.starts_with(b"FAKE")
+        }
+
+        fn decode(&self, _: &[u8]) -> Result<DecodedImage, DecodeError> {
+            Ok(DecodedImage {
+                width: 1,
+                height: 1,
+                data: vec![0, 0, 255, 255],
+            })
+        }
+    }
+
+    #[test]
+    fn test_decode_gif_first_frame() {
+        let image = decode_image(&animated_gif()).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        // The encoder quantizes the colors of the palette
+        let expected = [255, 0, 0, 255, 0, 255, 0, 255];
+        assert!(image
+            .data
+            .iter()
+            .zip(expected)
+            .all(|(actual, expected)| (*actual as i16 - expected).abs() <= 8));
+    }
+
+    #[test]
+    fn test_registered_decoder() {
+        assert!(matches!(
+            decode_image(b"FAKE image"),
+            Err(DecodeError::UnsupportedFormat)
+        ));
+        register_image_decoder(Arc::new(FakeDecoder));
+        assert_eq!(
+            decode_image(b"FAKE image").unwrap().data,
+            vec![0, 0, 255, 255]
+        );
+    }
+
+    #[test]
+    fn test_invalid_webp() {
+        assert!(WebPDecoder.can_decode(b"RIFF\x04\x00\x00\x00WEBP"));
+        assert!(matches!(
+            decode_image(b"RIFF\x04\x00\x00\x00WEBP"),
+            Err(DecodeError::InvalidData(_))
+        ));
+    }
+}
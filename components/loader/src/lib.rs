@@ -1,3 +1,15 @@
+mod http_cache;
+mod image_decoder;
+mod load_progress;
 mod resource_loader;
+mod resource_policy;
+mod scheme_handler;
 
+pub use http_cache::{CacheLookup, CacheStats, HttpCache};
+pub use image_decoder::*;
+pub use load_progress::LoadProgress;
+pub use net::cookie::{cookie_jar, Cookie, CookieJar};
+pub use net::http::NetworkSettings;
 pub use resource_loader::*;
+pub use resource_policy::{Blocklist, ResourcePolicy, ResourceType};
+pub use scheme_handler::{may_load_from_document, DataUrlHandler, FileHandler, SchemeHandler};
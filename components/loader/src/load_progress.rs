@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+/// Bytes received by the loads counted since the progress was reset
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    pub loaded_bytes: u64,
+    /// Bytes expected in total. A response without `Content-Length` is
+    /// expected to be as long as what was received of it, and a response
+    /// whose headers haven't arrived adds nothing.
+    pub total_bytes: u64,
+    /// Number of resources requested, loaded or not
+    pub resource_count: usize,
+}
+
+struct ResourceProgress {
+    loaded: u64,
+    total: Option<u64>,
+    is_finished: bool,
+}
+
+/// Progress of each resource by cache key, so that the prefetch and the
+/// loads of a resource are counted once
+#[derive(Default)]
+pub struct ProgressTracker {
+    resources: HashMap<String, ResourceProgress>,
+}
+
+impl ProgressTracker {
+    /// Forget the loads counted so far. The loads still in progress aren't
+    /// counted anymore, unless their resource is requested again.
+    pub fn reset(&mut self) {
+        self.resources.clear();
+    }
+
+    /// Count a request of the resource, if it isn't counted already
+    pub fn start(&mut self, key: &str) {
+        self.resources
+            .entry(key.to_string())
+            .or_insert(ResourceProgress {
+                loaded: 0,
+                total: None,
+                is_finished: false,
+            });
+    }
+
+    /// Part of the body of the resource was received
+    pub fn receive(&mut self, key: &str, loaded: u64, total: Option<u64>) {
+        if let Some(resource) = self.resources.get_mut(key) {
+            if !resource.is_finished {
+                resource.loaded = loaded;
+                resource.total = total;
+            }
+        }
+    }
+
+    /// The resource was loaded with `loaded` bytes, or none if it failed
+    pub fn finish(&mut self, key: &str, loaded: u64) {
+        if let Some(resource) = self.resources.get_mut(key) {
+            if !resource.is_finished {
+                resource.loaded = loaded;
+                resource.total = Some(loaded);
+                resource.is_finished = true;
+            }
+        }
+    }
+
+    pub fn progress(&self) -> LoadProgress {
+        let mut progress = LoadProgress {
+            resource_count: self.resources.len(),
+            ..Default::default()
+        };
+        for resource in self.resources.values() {
+            progress.loaded_bytes += resource.loaded;
+            progress.total_bytes += resource.total.unwrap_or(0).max(resource.loaded);
+        }
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_progress() {
+        let mut tracker = ProgressTracker::default();
+        tracker.start("https://moon/style.css");
+        tracker.start("https://moon/image.png");
+        tracker.receive("https://moon/style.css", 100, Some(400));
+        assert_eq!(
+            tracker.progress(),
+            LoadProgress {
+                loaded_bytes: 100,
+                total_bytes: 400,
+                resource_count: 2,
+            }
+        );
+
+        // The load of a prefetched resource isn't counted again
+        tracker.finish("https://moon/style.css", 400);
+        tracker.start("https://moon/style.css");
+        tracker.receive("https://moon/style.css", 0, Some(400));
+        // Without a length, the response is as long as what was received
+        tracker.receive("https://moon/image.png", 250, None);
+        assert_eq!(
+            tracker.progress(),
+            LoadProgress {
+                loaded_bytes: 650,
+                total_bytes: 650,
+                resource_count: 2,
+            }
+        );
+
+        // Loads started before a reset are left out
+        tracker.reset();
+        tracker.finish("https://moon/image.png", 300);
+        assert_eq!(tracker.progress(), LoadProgress::default());
+    }
+}
@@ -1,12 +1,32 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
 use flume::{unbounded, Sender};
-use net::http::HttpResponse;
+use net::http::NetworkSettings;
 use url::{parser::URLParser, Url};
 
-#[derive(Debug)]
+use crate::http_cache::{CacheLookup, CacheStats, HttpCache};
+use crate::image_decoder::{decode_image, DecodedImage};
+use crate::load_progress::{LoadProgress, ProgressTracker};
+use crate::resource_policy::{ResourcePolicy, ResourceType};
+use crate::scheme_handler::{
+    default_scheme_handlers, may_load_from_document, SchemeHandler, SchemeHandlers,
+};
+
+#[derive(Debug, Clone)]
 pub enum LoadError {
     UnsupportedProtocol(String),
     IOError(String),
     InvalidURL(String),
+    InvalidImage(String),
+    /// The resource policy doesn't allow the URL
+    Blocked(String),
+    /// There is no local resource at the URL
+    NotFound(String),
+    /// The server answered with an error status and nothing to show
+    HttpStatus(u16),
 }
 
 type Bytes = Vec<u8>;
@@ -28,25 +48,51 @@ impl LoadError {
             LoadError::InvalidURL(error) => {
                 format!("Unable to load resource from invalid URL: {}", error)
             }
+            LoadError::InvalidImage(error) => format!("Unable to decode image: {}", error),
+            LoadError::Blocked(url) => format!("Loading the resource was blocked: {}", url),
+            LoadError::NotFound(path) => format!("Unable to find resource: {}", path),
+            LoadError::HttpStatus(status) => format!("The server answered with status {}", status),
         }
     }
 }
 
 pub struct LoadRequest {
     url: Url,
+    /// Content type and body of a `POST` request
+    body: Option<(String, Bytes)>,
+    /// Nobody waits for the response, which is only fetched into the cache
+    is_prefetch: bool,
     response_tx: Sender<Result<Bytes, LoadError>>,
 }
 
+/// Senders of the loads waiting for each prefetch in progress, by cache key
+type Prefetches = Arc<Mutex<HashMap<String, Vec<Sender<Result<Bytes, LoadError>>>>>>;
+
 static mut RESOURCE_LOADER: Option<ResourceLoader> = None;
 
 #[derive(Clone)]
-pub struct ResourceLoader(Sender<LoadRequest>);
+pub struct ResourceLoader {
+    requests: Sender<LoadRequest>,
+    cache: Arc<Mutex<HttpCache>>,
+    policy: Arc<RwLock<Option<Arc<dyn ResourcePolicy>>>>,
+    scheme_handlers: Arc<RwLock<SchemeHandlers>>,
+    progress: Arc<Mutex<ProgressTracker>>,
+}
 
 impl ResourceLoader {
     pub fn init() -> Self {
         let (request_tx, request_rx) = unbounded();
+        let cache = Arc::new(Mutex::new(HttpCache::new()));
+        let scheme_handlers = Arc::new(RwLock::new(default_scheme_handlers()));
+        let progress = Arc::new(Mutex::new(ProgressTracker::default()));
 
-        let loader = ResourceLoader(request_tx);
+        let loader = ResourceLoader {
+            requests: request_tx,
+            cache: cache.clone(),
+            policy: Arc::new(RwLock::new(None)),
+            scheme_handlers: scheme_handlers.clone(),
+            progress: progress.clone(),
+        };
 
         unsafe {
             if RESOURCE_LOADER.is_none() {
@@ -56,30 +102,76 @@ impl ResourceLoader {
 
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
+            let prefetches: Prefetches = Arc::default();
 
-            fn load(url: &Url, rt: &tokio::runtime::Runtime) -> Result<Vec<u8>, LoadError> {
-                let load_result = match url.scheme.as_str() {
-                    "file" => std::fs::read(url.path.as_str())
-                        .map_err(|e| LoadError::IOError(e.to_string())),
-                    "http" | "https" => match rt.block_on(net::http::request("GET", &url.as_str()))
-                    {
-                        HttpResponse::Success(bytes) => Ok(bytes),
-                        HttpResponse::Failure(err) => Err(LoadError::IOError(err)),
-                    },
+            fn load(
+                url: &Url,
+                rt: &tokio::runtime::Runtime,
+                cache: &Mutex<HttpCache>,
+                scheme_handlers: &RwLock<SchemeHandlers>,
+                progress: &Mutex<ProgressTracker>,
+            ) -> Result<Vec<u8>, LoadError> {
+                let handler = scheme_handlers.read().unwrap().get(&url.scheme).cloned();
+                if let Some(handler) = handler {
+                    return handler.load(url);
+                }
+                match url.scheme.as_str() {
+                    "http" | "https" => load_http(url, rt, cache, progress),
                     "view-source" => {
                         let target_url = URLParser::parse(&url.path.as_str(), None)
                             .ok_or_else(|| LoadError::InvalidURL(url.as_str()))?;
-                        load(&target_url, rt)
+                        load(&target_url, rt, cache, scheme_handlers, progress)
                     }
                     protocol => Err(LoadError::UnsupportedProtocol(protocol.to_string())),
-                };
-                load_result
+                }
             }
 
             loop {
                 let request = request_rx.recv().unwrap();
                 let url = request.url;
-                let response = load(&url, &rt);
+                let key = cache_key(&url);
+                progress.lock().unwrap().start(&key);
+                let is_http = (url.scheme == "http" || url.scheme == "https")
+                    && !scheme_handlers.read().unwrap().contains_key(&url.scheme);
+                if is_http && request.body.is_none() {
+                    let mut in_progress = prefetches.lock().unwrap();
+                    // The response of the prefetch answers the load
+                    if let Some(waiting) = in_progress.get_mut(&key) {
+                        if !request.is_prefetch {
+                            waiting.push(request.response_tx);
+                        }
+                        continue;
+                    }
+                    if request.is_prefetch {
+                        in_progress.insert(key.clone(), Vec::new());
+                        let cache = cache.clone();
+                        let prefetches = prefetches.clone();
+                        let progress = progress.clone();
+                        rt.spawn(async move {
+                            let response = fetch_http(&url, &cache, &progress).await;
+                            progress
+                                .lock()
+                                .unwrap()
+                                .finish(&key, loaded_bytes(&response));
+                            let waiting = prefetches.lock().unwrap().remove(&key);
+                            for response_tx in waiting.unwrap_or_default() {
+                                let _ = response_tx.send(response.clone());
+                            }
+                        });
+                        continue;
+                    }
+                }
+
+                let response = match request.body {
+                    Some((content_type, body)) => {
+                        post_http(&url, content_type, body, &rt, &cache, &progress)
+                    }
+                    None => load(&url, &rt, &cache, &scheme_handlers, &progress),
+                };
+                progress
+                    .lock()
+                    .unwrap()
+                    .finish(&key, loaded_bytes(&response));
 
                 request.response_tx.send(response).unwrap();
             }
@@ -93,13 +185,284 @@ impl ResourceLoader {
     }
 
     pub fn load(&self, url: &Url) -> Result<Bytes, LoadError> {
+        self.load_resource(url, ResourceType::Other)
+    }
+
+    /// Load a resource if the resource policy allows it
+    pub fn load_resource(
+        &self,
+        url: &Url,
+        resource_type: ResourceType,
+    ) -> Result<Bytes, LoadError> {
+        self.check_policy(url, resource_type)?;
         let (tx, rx) = flume::bounded(1);
-        self.0
+        self.requests
             .send(LoadRequest {
                 url: url.clone(),
+                body: None,
+                is_prefetch: false,
                 response_tx: tx,
             })
             .unwrap();
         rx.recv().unwrap()
     }
+
+    /// Load a subresource of the document at `document_url`, like a
+    /// stylesheet or an image, if the resource policy allows it. Local files
+    /// are only loaded for local documents, remote pages can't read them.
+    pub fn load_subresource(
+        &self,
+        url: &Url,
+        resource_type: ResourceType,
+        document_url: Option<&Url>,
+    ) -> Result<Bytes, LoadError> {
+        if !may_load_from_document(url, document_url) {
+            log::info!(
+                "Blocked local {:?} resource of a remote document: {}",
+                resource_type,
+                url
+            );
+            return Err(LoadError::Blocked(url.as_str()));
+        }
+        self.load_resource(url, resource_type)
+    }
+
+    /// Start loading an HTTP resource in the background, without waiting
+    /// for it. A load of the URL made before the response arrives gets the
+    /// same response, a later one gets it from the cache if it can be
+    /// cached. Resources of other schemes are local and aren't prefetched.
+    pub fn prefetch(&self, url: &Url, resource_type: ResourceType) {
+        if url.scheme != "http" && url.scheme != "https" {
+            return;
+        }
+        if self.check_policy(url, resource_type).is_err() {
+            return;
+        }
+        let (tx, _) = flume::bounded(1);
+        self.requests
+            .send(LoadRequest {
+                url: url.clone(),
+                body: None,
+                is_prefetch: true,
+                response_tx: tx,
+            })
+            .unwrap();
+    }
+
+    /// Send `body` to an HTTP URL with a `POST` request, like a form
+    /// submission, and return the body of the response
+    pub fn post(&self, url: &Url, content_type: &str, body: Bytes) -> Result<Bytes, LoadError> {
+        self.check_policy(url, ResourceType::Document)?;
+        let (tx, rx) = flume::bounded(1);
+        self.requests
+            .send(LoadRequest {
+                url: url.clone(),
+                body: Some((content_type.to_string(), body)),
+                is_prefetch: false,
+                response_tx: tx,
+            })
+            .unwrap();
+        rx.recv().unwrap()
+    }
+
+    /// Load an image of the document at `document_url` and decode it with
+    /// the decoder of its format
+    pub fn load_image(
+        &self,
+        url: &Url,
+        document_url: Option<&Url>,
+    ) -> Result<DecodedImage, LoadError> {
+        let bytes = self.load_subresource(url, ResourceType::Image, document_url)?;
+        decode_image(&bytes).map_err(|e| LoadError::InvalidImage(e.to_string()))
+    }
+
+    /// Decide which resources are loaded from now on, or load every resource
+    /// with `None`
+    pub fn set_policy(&self, policy: Option<Arc<dyn ResourcePolicy>>) {
+        *self.policy.write().unwrap() = policy;
+    }
+
+    fn check_policy(&self, url: &Url, resource_type: ResourceType) -> Result<(), LoadError> {
+        // The source of a page is loaded from the URL of the page
+        if url.scheme == "view-source" {
+            if let Some(target_url) = URLParser::parse(&url.path.as_str(), None) {
+                self.check_policy(&target_url, resource_type)?;
+            }
+        }
+        match self.policy.read().unwrap().as_ref() {
+            Some(policy) if !policy.allows(url, resource_type) => {
+                log::info!("Blocked {:?} resource: {}", resource_type, url);
+                Err(LoadError::Blocked(url.as_str()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the proxy, the trusted certificates and the user agent of the
+    /// HTTP requests. The previous settings are kept if they are invalid.
+    pub fn configure_network(&self, settings: &NetworkSettings) -> Result<(), String> {
+        net::http::configure(settings)
+    }
+
+    /// Load the URLs of the scheme with the handler, in place of the
+    /// handler registered for it before, built in or not
+    pub fn register_scheme(&self, scheme: &str, handler: Arc<dyn SchemeHandler>) {
+        self.scheme_handlers
+            .write()
+            .unwrap()
+            .insert(scheme.to_ascii_lowercase(), handler);
+    }
+
+    /// Keep cached HTTP responses in `dir` too, so they are reused after
+    /// a restart
+    pub fn enable_disk_cache(&self, dir: PathBuf) {
+        self.cache.lock().unwrap().set_disk_dir(dir);
+    }
+
+    /// Count the progress of the loads from now on, like when a page
+    /// starts loading. The loads made so far are left out.
+    pub fn reset_progress(&self) {
+        self.progress.lock().unwrap().reset();
+    }
+
+    /// Progress of the loads of every page since the last
+    /// `reset_progress`. A resource loaded several times is counted once.
+    pub fn progress(&self) -> LoadProgress {
+        self.progress.lock().unwrap().progress()
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.lock().unwrap().stats()
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+/// Load an HTTP resource, from the cache when the cached response is fresh
+/// or the server confirms it is still valid
+fn load_http(
+    url: &Url,
+    rt: &tokio::runtime::Runtime,
+    cache: &Mutex<HttpCache>,
+    progress: &Mutex<ProgressTracker>,
+) -> Result<Bytes, LoadError> {
+    rt.block_on(fetch_http(url, cache, progress))
+}
+
+async fn fetch_http(
+    url: &Url,
+    cache: &Mutex<HttpCache>,
+    progress: &Mutex<ProgressTracker>,
+) -> Result<Bytes, LoadError> {
+    let key = cache_key(url);
+    let on_progress = |loaded, total| progress.lock().unwrap().receive(&key, loaded, total);
+    let lookup = cache.lock().unwrap().lookup(&key, SystemTime::now());
+    let validators = match lookup {
+        CacheLookup::Fresh(bytes) => return Ok(bytes),
+        CacheLookup::Stale(validators) => validators,
+        CacheLookup::Miss => Vec::new(),
+    };
+
+    let mut response = net::http::fetch_with_progress("GET", &key, &validators, None, &on_progress)
+        .await
+        .map_err(LoadError::IOError)?;
+    if response.status == 304 {
+        let revalidated = cache
+            .lock()
+            .unwrap()
+            .revalidated(&key, &response, SystemTime::now());
+        if let Some(bytes) = revalidated {
+            return Ok(bytes);
+        }
+        // The response was evicted from the cache in the meantime
+        response = net::http::fetch_with_progress("GET", &key, &[], None, &on_progress)
+            .await
+            .map_err(LoadError::IOError)?;
+    }
+
+    // Error pages of the server are shown, but there is nothing to show for
+    // an error without a body
+    if response.status >= 400 && response.body.is_empty() {
+        return Err(LoadError::HttpStatus(response.status));
+    }
+    cache
+        .lock()
+        .unwrap()
+        .store(&key, &response, SystemTime::now());
+    Ok(response.body)
+}
+
+/// Send a `POST` request. Its response isn't cached, and the cached response
+/// of the URL is dropped since the request may have changed the resource.
+fn post_http(
+    url: &Url,
+    content_type: String,
+    body: Bytes,
+    rt: &tokio::runtime::Runtime,
+    cache: &Mutex<HttpCache>,
+    progress: &Mutex<ProgressTracker>,
+) -> Result<Bytes, LoadError> {
+    if url.scheme != "http" && url.scheme != "https" {
+        return Err(LoadError::UnsupportedProtocol(url.scheme.clone()));
+    }
+
+    let key = cache_key(url);
+    cache.lock().unwrap().invalidate(&key);
+    let headers = [("content-type".to_string(), content_type)];
+    let on_progress = |loaded, total| progress.lock().unwrap().receive(&key, loaded, total);
+    let response = rt
+        .block_on(net::http::fetch_with_progress(
+            "POST",
+            &key,
+            &headers,
+            Some(body),
+            &on_progress,
+        ))
+        .map_err(LoadError::IOError)?;
+    Ok(response.body)
+}
+
+/// Bytes of the response counted by the progress of the loads
+fn loaded_bytes(response: &Result<Bytes, LoadError>) -> u64 {
+    response.as_ref().map_or(0, |bytes| bytes.len() as u64)
+}
+
+/// The URL without its fragment, which isn't sent to the server
+fn cache_key(url: &Url) -> String {
+    Url {
+        fragment: None,
+        ..url.clone()
+    }
+    .as_str()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_local_files_of_remote_documents() {
+        let dir =
+            std::env::temp_dir().join(format!("moon subresource test {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("secret.txt");
+        std::fs::write(&path, "secret").unwrap();
+
+        let loader = ResourceLoader::init();
+        let url = |url: &str| URLParser::parse(url, None).unwrap();
+        let file = url(&format!("file://{}", path.display()).replace(' ', "%20"));
+        let load = |document_url: Option<&Url>| {
+            loader.load_subresource(&file, ResourceType::Stylesheet, document_url)
+        };
+
+        let remote = url("https://example.com/index.html");
+        assert!(matches!(load(Some(&remote)), Err(LoadError::Blocked(_))));
+        assert!(matches!(load(None), Err(LoadError::Blocked(_))));
+        let local =
+            url(&format!("file://{}", dir.join("index.html").display()).replace(' ', "%20"));
+        assert_eq!(load(Some(&local)).unwrap(), b"secret");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
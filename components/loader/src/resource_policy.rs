@@ -0,0 +1,126 @@
+//! Policies deciding which resources the engine may load, in the spirit of
+//! `Content-Security-Policy` but set by the embedder instead of the page.
+//! https://www.w3.org/TR/CSP3/#framework-directives
+
+use url::Url;
+
+/// What a resource is loaded for, so that policies can tell a page from the
+/// subresources it loads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceType {
+    /// A page, a submitted form or the document of an iframe
+    Document,
+    Stylesheet,
+    Image,
+    Font,
+    Other,
+}
+
+/// Decide which resources are loaded, like an ad blocker or a policy that
+/// restricts the engine to local content by only allowing `file` URLs.
+/// Blocked loads fail with `LoadError::Blocked` before any request is made.
+pub trait ResourcePolicy: Send + Sync {
+    fn allows(&self, url: &Url, resource_type: ResourceType) -> bool;
+}
+
+/// Policy that blocks the resources of some hosts and the URLs starting with
+/// some prefixes, like the lists used to block ads and trackers. Documents
+/// are blocked too unless `allow_documents` is set, so that only the
+/// subresources the pages load are filtered.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    hosts: Vec<String>,
+    prefixes: Vec<String>,
+    allows_documents: bool,
+}
+
+impl Blocklist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Block the host and its subdomains
+    pub fn block_host(mut self, host: &str) -> Self {
+        self.hosts.push(host.trim_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    /// Block the URLs starting with the prefix
+    pub fn block_prefix(mut self, prefix: &str) -> Self {
+        self.prefixes.push(prefix.to_string());
+        self
+    }
+
+    /// Only block the subresources, never the pages themselves
+    pub fn allow_documents(mut self) -> Self {
+        self.allows_documents = true;
+        self
+    }
+
+    fn is_blocked_host(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.hosts.iter().any(|blocked| {
+            host == *blocked
+                || host
+                    .strip_suffix(blocked.as_str())
+                    .is_some_and(|subdomain| subdomain.ends_with('.'))
+        })
+    }
+}
+
+impl ResourcePolicy for Blocklist {
+    fn allows(&self, url: &Url, resource_type: ResourceType) -> bool {
+        if self.allows_documents && resource_type == ResourceType::Document {
+            return true;
+        }
+        if url
+            .host
+            .as_deref()
+            .is_some_and(|host| self.is_blocked_host(host))
+        {
+            return false;
+        }
+        let url = url.as_str();
+        !self.prefixes.iter().any(|prefix| url.starts_with(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::parser::URLParser;
+
+    fn url(url: &str) -> Url {
+        URLParser::parse(url, None).unwrap()
+    }
+
+    #[test]
+    fn block_hosts_and_prefixes() {
+        let blocklist = Blocklist::new()
+            .block_host("ads.example.com")
+            .block_prefix("https://example.com/track/");
+
+        let blocked = [
+            "https://ads.example.com/banner.png",
+            "http://cdn.ADS.example.com/script.js",
+            "https://example.com/track/pixel.gif",
+        ];
+        for blocked in blocked {
+            assert!(!blocklist.allows(&url(blocked), ResourceType::Image));
+        }
+        let allowed = [
+            "https://example.com/index.html",
+            "https://badads.example.com/banner.png",
+            "https://example.com/tracking.css",
+        ];
+        for allowed in allowed {
+            assert!(blocklist.allows(&url(allowed), ResourceType::Image));
+        }
+
+        let page = url("https://ads.example.com/");
+        assert!(!blocklist.allows(&page, ResourceType::Document));
+        let blocklist = blocklist.allow_documents();
+        assert!(blocklist.allows(&page, ResourceType::Document));
+        assert!(!blocklist.allows(&page, ResourceType::Stylesheet));
+    }
+}
@@ -0,0 +1,174 @@
+//! Loading of the URLs that aren't fetched from the network. Handlers are
+//! registered by scheme, `data` and `file` are built in and embedders can
+//! add their own, like `app` for resources bundled with the application.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use url::{percent_decode, Url};
+
+use crate::resource_loader::LoadError;
+
+pub trait SchemeHandler: Send + Sync {
+    fn load(&self, url: &Url) -> Result<Vec<u8>, LoadError>;
+}
+
+/// Handlers of the loader by scheme, in lowercase
+pub(crate) type SchemeHandlers = HashMap<String, Arc<dyn SchemeHandler>>;
+
+pub(crate) fn default_scheme_handlers() -> SchemeHandlers {
+    let mut handlers: SchemeHandlers = HashMap::new();
+    handlers.insert("data".to_string(), Arc::new(DataUrlHandler));
+    handlers.insert("file".to_string(), Arc::new(FileHandler));
+    handlers
+}
+
+/// Whether the document at `document_url` may load the URL or navigate to
+/// it. Local files are only loaded for local documents, remote pages can't
+/// read them nor become local documents by navigating to them.
+pub fn may_load_from_document(url: &Url, document_url: Option<&Url>) -> bool {
+    url.scheme != "file" || document_url.is_some_and(|document_url| document_url.scheme == "file")
+}
+
+/// `data:` URLs, with a percent-encoded or base64 body
+/// https://fetch.spec.whatwg.org/#data-urls
+pub struct DataUrlHandler;
+
+impl SchemeHandler for DataUrlHandler {
+    fn load(&self, url: &Url) -> Result<Vec<u8>, LoadError> {
+        let mut input = url.path.as_str();
+        // The fragment isn't part of the data but the query is
+        if let Some(query) = &url.query {
+            input.push('?');
+            input.push_str(query);
+        }
+        decode_data_url(&input).ok_or_else(|| LoadError::InvalidURL(url.as_str()))
+    }
+}
+
+/// Body of a data URL, without its `data:` scheme
+/// https://fetch.spec.whatwg.org/#data-url-processor
+fn decode_data_url(input: &str) -> Option<Vec<u8>> {
+    let (mime_type, body) = input.split_once(',')?;
+    let body = percent_decode(body.as_bytes());
+    // The media type itself isn't needed, resources are sniffed by their
+    // consumers
+    let mime_type = mime_type.trim_matches(|c: char| c.is_ascii_whitespace());
+    let is_base64 = mime_type.rsplit_once(';').is_some_and(|(_, parameter)| {
+        parameter
+            .trim_start_matches(|c: char| c.is_ascii_whitespace())
+            .eq_ignore_ascii_case("base64")
+    });
+    if is_base64 {
+        forgiving_base64_decode(&body)
+    } else {
+        Some(body)
+    }
+}
+
+/// https://infra.spec.whatwg.org/#forgiving-base64-decode
+fn forgiving_base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    let mut input: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if input.len() % 4 == 0 {
+        for _ in 0..2 {
+            if input.last() == Some(&b'=') {
+                input.pop();
+            }
+        }
+    }
+    if input.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Some(output)
+}
+
+/// `file:` URLs of local paths
+pub struct FileHandler;
+
+impl SchemeHandler for FileHandler {
+    fn load(&self, url: &Url) -> Result<Vec<u8>, LoadError> {
+        let path = String::from_utf8(percent_decode(url.path.as_str().as_bytes()))
+            .map_err(|_| LoadError::InvalidURL(url.as_str()))?;
+        std::fs::read(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => LoadError::NotFound(path),
+            _ => LoadError::IOError(e.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use url::parser::URLParser;
+
+    fn load(url: &str) -> Result<Vec<u8>, LoadError> {
+        let url = URLParser::parse(url, None).unwrap();
+        default_scheme_handlers()[&url.scheme].load(&url)
+    }
+
+    #[test]
+    fn load_data_urls() {
+        assert_eq!(load("data:,Hello%2C%20World%21").unwrap(), b"Hello, World!");
+        assert_eq!(
+            load("data:text/plain;charset=utf-8,a?b#fragment").unwrap(),
+            b"a?b"
+        );
+        assert_eq!(
+            load("data:text/plain;base64,SGVsbG8s IFdvcmxkIQ==").unwrap(),
+            b"Hello, World!"
+        );
+        assert_eq!(load("data:;BASE64,SGk").unwrap(), b"Hi");
+        assert!(load("data:text/plain;base64,S").is_err());
+        assert!(load("data:text/plain").is_err());
+    }
+
+    #[test]
+    fn load_percent_encoded_file_path() {
+        let dir = std::env::temp_dir().join(format!("moon loader test {}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.html");
+        std::fs::write(&path, "<p>moon</p>").unwrap();
+
+        let url = format!("file://{}", path.display()).replace(' ', "%20");
+        assert_eq!(load(&url).unwrap(), b"<p>moon</p>");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_local_documents_load_files() {
+        let url = |url: &str| URLParser::parse(url, None).unwrap();
+        let file = url("file:///etc/hosts");
+        let local = url("file:///home/moon/index.html");
+        let remote = url("https://example.com/");
+
+        assert!(may_load_from_document(&file, Some(&local)));
+        assert!(!may_load_from_document(&file, Some(&remote)));
+        assert!(!may_load_from_document(&file, None));
+        assert!(may_load_from_document(&remote, Some(&local)));
+        assert!(may_load_from_document(&remote, None));
+    }
+}
@@ -0,0 +1,537 @@
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use url::{Url, UrlPath};
+
+static COOKIE_JAR: CookieJar = CookieJar::new();
+
+/// Longest lifetime a `Max-Age` or an `Expires` attribute can give a
+/// cookie, 400 days as in RFC 6265bis
+const MAX_COOKIE_AGE: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Cookies shared by every request of the process
+pub fn cookie_jar() -> &'static CookieJar {
+    &COOKIE_JAR
+}
+
+/// A cookie stored by a `Set-Cookie` header, see RFC 6265
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Domain of the cookie, without a leading dot
+    pub domain: String,
+    /// The cookie is only sent to `domain` itself, not to its subdomains,
+    /// because it was set without a `Domain` attribute
+    pub host_only: bool,
+    pub path: String,
+    /// `None` for session cookies, which only live until the session ends
+    pub expires: Option<SystemTime>,
+    /// The cookie is only sent over secure connections
+    pub secure: bool,
+    /// The cookie isn't exposed to scripts
+    pub http_only: bool,
+    created: SystemTime,
+}
+
+impl Cookie {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = match &url.host {
+            Some(host) => host.to_lowercase(),
+            None => return false,
+        };
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_matches
+            && path_matches(&request_path(url), &self.path)
+            && (!self.secure || url.scheme == "https")
+    }
+}
+
+/// Store of the cookies set by servers
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    pub const fn new() -> Self {
+        Self {
+            cookies: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Store the cookie of a `Set-Cookie` header received from `url`.
+    /// Invalid cookies, and cookies the server isn't allowed to set, are
+    /// ignored.
+    pub fn set_cookie(&self, url: &Url, header: &str, now: SystemTime) {
+        let cookie = match parse_set_cookie(url, header, now) {
+            Some(cookie) => cookie,
+            None => {
+                log::debug!("Ignoring cookie from {}: {}", url, header);
+                return;
+            }
+        };
+
+        let mut cookies = self.cookies.lock().unwrap();
+        let existing = cookies.iter().position(|existing| {
+            existing.name == cookie.name
+                && existing.domain == cookie.domain
+                && existing.path == cookie.path
+        });
+        let cookie = match existing {
+            Some(index) => Cookie {
+                created: cookies.remove(index).created,
+                ..cookie
+            },
+            None => cookie,
+        };
+        // Setting an expired cookie is how servers delete a cookie
+        if !cookie.is_expired(now) {
+            cookies.push(cookie);
+        }
+    }
+
+    /// Value of the `Cookie` header of a request to `url`, if any cookie
+    /// should be sent with it
+    pub fn cookie_header(&self, url: &Url, now: SystemTime) -> Option<String> {
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|cookie| !cookie.is_expired(now));
+
+        let mut matching: Vec<&Cookie> = cookies.iter().filter(|c| c.matches(url)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        // Cookies with longer paths are listed first, then older cookies
+        matching.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Cookies that haven't expired
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        let cookies = self.cookies.lock().unwrap();
+        cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Delete the cookies of `domain` and of its subdomains
+    pub fn remove_domain(&self, domain: &str) {
+        let domain = domain.trim_start_matches('.').to_lowercase();
+        self.cookies
+            .lock()
+            .unwrap()
+            .retain(|cookie| !domain_matches(&cookie.domain, &domain));
+    }
+
+    /// Delete the cookies without an expiration date, like when a browsing
+    /// session ends
+    pub fn clear_session_cookies(&self) {
+        self.cookies
+            .lock()
+            .unwrap()
+            .retain(|cookie| cookie.expires.is_some());
+    }
+
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+}
+
+impl Default for CookieJar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse a `Set-Cookie` header following section 5.2 of RFC 6265, and
+/// check the cookie against `url` following section 5.3
+fn parse_set_cookie(url: &Url, header: &str, now: SystemTime) -> Option<Cookie> {
+    let host = url.host.as_ref()?.to_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.trim().to_string(),
+        domain: host.clone(),
+        host_only: true,
+        path: default_path(url),
+        expires: None,
+        secure: false,
+        http_only: false,
+        created: now,
+    };
+    let mut max_age = None;
+    let mut expires = None;
+
+    for attribute in parts {
+        let (attribute, value) = match attribute.split_once('=') {
+            Some((attribute, value)) => (attribute.trim(), value.trim()),
+            None => (attribute.trim(), ""),
+        };
+        match attribute.to_ascii_lowercase().as_str() {
+            "expires" => expires = parse_cookie_date(value).or(expires),
+            "max-age" => {
+                if let Ok(seconds) = value.parse::<i64>() {
+                    max_age = Some(seconds);
+                }
+            }
+            "domain" if !value.is_empty() => {
+                cookie.domain = value.trim_start_matches('.').to_lowercase();
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            _ => {}
+        }
+    }
+
+    // Max-Age takes precedence over Expires
+    cookie.expires = match max_age {
+        Some(seconds) if seconds <= 0 => Some(UNIX_EPOCH),
+        // A time that can't be represented keeps the cookie for the session
+        Some(seconds) => now.checked_add(Duration::from_secs(seconds as u64).min(MAX_COOKIE_AGE)),
+        None => match (expires, now.checked_add(MAX_COOKIE_AGE)) {
+            (Some(expires), Some(latest)) => Some(expires.min(latest)),
+            (expires, _) => expires,
+        },
+    };
+
+    if !cookie.host_only {
+        if !domain_matches(&host, &cookie.domain) {
+            return None;
+        }
+        // A public suffix is only accepted as the domain of the host
+        // itself, which can't share the cookie with its subdomains
+        if is_public_suffix(&cookie.domain) {
+            if cookie.domain != host {
+                return None;
+            }
+            cookie.host_only = true;
+        }
+    }
+    // Insecure origins can't set secure cookies
+    if cookie.secure && url.scheme != "https" {
+        return None;
+    }
+    Some(cookie)
+}
+
+/// Whether the domain is a suffix under which anyone can register a name,
+/// so cookies for it would be shared between unrelated sites.
+///
+/// There is no public suffix list, so only single labels like "com" and a
+/// few well-known multi-label suffixes like "co.uk" are recognized. Cookies
+/// can still be set for other public suffixes, such as "github.io".
+fn is_public_suffix(domain: &str) -> bool {
+    !domain.contains('.') || KNOWN_PUBLIC_SUFFIXES.contains(&domain)
+}
+
+/// Public suffixes of more than one label that are commonly used
+const KNOWN_PUBLIC_SUFFIXES: [&str; 16] = [
+    "co.uk", "org.uk", "ac.uk", "gov.uk", "com.au", "net.au", "org.au", "co.jp", "ne.jp", "or.jp",
+    "co.nz", "co.in", "co.kr", "com.br", "com.cn", "com.mx",
+];
+
+/// Whether `host` is `domain` or one of its subdomains
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    let is_ip_address = host.parse::<std::net::IpAddr>().is_ok();
+    !is_ip_address && host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.')
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+fn request_path(url: &Url) -> String {
+    match &url.path {
+        UrlPath::List(_) => format!("/{}", url.path),
+        UrlPath::Opaque(path) => path.clone(),
+    }
+}
+
+/// The directory of the request path, used when a cookie has no `Path`
+fn default_path(url: &Url) -> String {
+    let path = request_path(url);
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// Parse a date following the lenient algorithm of section 5.1.1 of
+/// RFC 6265, which accepts the many formats used by servers
+fn parse_cookie_date(date: &str) -> Option<SystemTime> {
+    let is_delimiter = |c: char| {
+        c == '\t'
+            || (' '..='/').contains(&c)
+            || (';'..='@').contains(&c)
+            || ('['..='`').contains(&c)
+            || ('{'..='~').contains(&c)
+    };
+
+    let mut time = None;
+    let mut day = None;
+    let mut month = None;
+    let mut year = None;
+    for token in date.split(is_delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() {
+            if let Some(parsed) = leading_number(token, 1, 2) {
+                day = Some(parsed);
+                continue;
+            }
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or_default().to_ascii_lowercase();
+            if let Some(index) = MONTHS.iter().position(|month| *month == prefix) {
+                month = Some(index as u64 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(parsed) = leading_number(token, 2, 4) {
+                year = Some(parsed);
+            }
+        }
+    }
+
+    let (hours, minutes, seconds) = time?;
+    let day = day?;
+    let month = month?;
+    let year = match year? {
+        year @ 70..=99 => year + 1900,
+        year @ 0..=69 => year + 2000,
+        year => year,
+    };
+    if !(1..=31).contains(&day) || year < 1970 || hours > 23 || minutes > 59 || seconds > 59 {
+        return None;
+    }
+
+    let days = days_since_epoch(year, month, day);
+    Some(UNIX_EPOCH + Duration::from_secs(days * 86400 + hours * 3600 + minutes * 60 + seconds))
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// `hh:mm:ss`, where each field has one or two digits
+fn parse_time(token: &str) -> Option<(u64, u64, u64)> {
+    let mut fields = token.splitn(3, ':');
+    let hours = fields
+        .next()
+        .and_then(|field| leading_number(field, 1, 2))?;
+    let minutes = fields
+        .next()
+        .and_then(|field| leading_number(field, 1, 2))?;
+    let seconds = fields
+        .next()
+        .and_then(|field| leading_number(field, 1, 2))?;
+    Some((hours, minutes, seconds))
+}
+
+/// Number made of the `min` to `max` digits at the start of `token`,
+/// which may be followed by non-digits
+fn leading_number(token: &str, min: usize, max: usize) -> Option<u64> {
+    let digits = token.chars().take_while(char::is_ascii_digit).count();
+    if digits < min || digits > max {
+        return None;
+    }
+    token[..digits].parse().ok()
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar
+fn days_since_epoch(year: u64, month: u64, day: u64) -> u64 {
+    // Years start in March so that the leap day is at the end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_from_march = (month + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use url::parser::URLParser;
+
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        URLParser::parse(url, None).unwrap()
+    }
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_parse_cookie_date() {
+        let expected = Some(time(1445412480));
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), expected);
+        assert_eq!(
+            parse_cookie_date("Wednesday, 21-Oct-15 07:28:00 GMT"),
+            expected
+        );
+        assert_eq!(parse_cookie_date("Wed Oct 21 07:28:00 2015"), expected);
+        assert_eq!(
+            parse_cookie_date("Thu, 29 Feb 2024 00:00:00 GMT"),
+            Some(time(1709164800))
+        );
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015"), None);
+        assert_eq!(parse_cookie_date("Wed, 32 Oct 2015 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn test_cookie_matching() {
+        let jar = CookieJar::new();
+        let page = url("https://www.example.com/account/settings");
+        jar.set_cookie(&page, "session=abc; Secure; HttpOnly", time(0));
+        jar.set_cookie(&page, "theme=dark; Domain=.example.com; Path=/", time(1));
+        jar.set_cookie(&page, "tab=privacy", time(2));
+
+        assert_eq!(
+            jar.cookie_header(&url("https://www.example.com/account/"), time(3)),
+            Some("session=abc; tab=privacy; theme=dark".to_string())
+        );
+        // Secure cookies aren't sent over plain HTTP
+        assert_eq!(
+            jar.cookie_header(&url("http://www.example.com/account"), time(3)),
+            Some("tab=privacy; theme=dark".to_string())
+        );
+        // Only domain cookies are sent to subdomains
+        assert_eq!(
+            jar.cookie_header(&url("https://static.example.com/account"), time(3)),
+            Some("theme=dark".to_string())
+        );
+        assert_eq!(
+            jar.cookie_header(&url("https://www.example.com/accounts"), time(3)),
+            Some("theme=dark".to_string())
+        );
+        assert_eq!(
+            jar.cookie_header(&url("https://example.org/"), time(3)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rejected_cookies() {
+        let jar = CookieJar::new();
+        let page = url("http://www.example.com/");
+        jar.set_cookie(&page, "a=1; Domain=other.com", time(0));
+        jar.set_cookie(&page, "b=1; Domain=com", time(0));
+        jar.set_cookie(&page, "c=1; Secure", time(0));
+        jar.set_cookie(&page, "no value", time(0));
+        jar.set_cookie(&page, "=1", time(0));
+        assert!(jar.cookies().is_empty());
+
+        // Public suffixes can't be the domain of cookies of their subdomains
+        let page = url("http://shop.example.co.uk/");
+        jar.set_cookie(&page, "d=1; Domain=co.uk", time(0));
+        jar.set_cookie(&page, "e=1; Domain=uk", time(0));
+        assert!(jar.cookies().is_empty());
+        jar.set_cookie(&page, "f=1; Domain=example.co.uk", time(0));
+        assert_eq!(jar.cookies().len(), 1);
+        assert!(!jar.cookies()[0].host_only);
+    }
+
+    #[test]
+    fn test_cookie_expiration() {
+        let jar = CookieJar::new();
+        let page = url("http://example.com/");
+        jar.set_cookie(
+            &page,
+            "a=1; Max-Age=60; Expires=Wed, 21 Oct 2015 07:28:00 GMT",
+            time(0),
+        );
+        jar.set_cookie(&page, "b=1; Expires=Thu, 01 Jan 1970 00:01:00 GMT", time(0));
+        jar.set_cookie(&page, "c=1", time(0));
+
+        assert_eq!(
+            jar.cookie_header(&page, time(59)),
+            Some("a=1; b=1; c=1".to_string())
+        );
+        assert_eq!(jar.cookie_header(&page, time(60)), Some("c=1".to_string()));
+
+        // Replacing a cookie with an expired one deletes it
+        jar.set_cookie(&page, "c=2; Max-Age=0", time(61));
+        assert_eq!(jar.cookie_header(&page, time(61)), None);
+    }
+
+    #[test]
+    fn test_cap_max_age() {
+        let page = url("http://example.com/");
+        let cookie = parse_set_cookie(&page, "a=1; Max-Age=9223372036854775807", time(0));
+        assert_eq!(cookie.unwrap().expires, Some(time(0) + MAX_COOKIE_AGE));
+    }
+
+    #[test]
+    fn test_cap_expires() {
+        let page = url("http://example.com/");
+        let cookie = parse_set_cookie(&page, "a=1; Expires=Fri, 31 Dec 9999 23:59:59 GMT", time(0));
+        assert_eq!(cookie.unwrap().expires, Some(time(0) + MAX_COOKIE_AGE));
+
+        // Earlier dates are kept
+        let cookie = parse_set_cookie(&page, "a=1; Expires=Thu, 01 Jan 1970 00:01:00 GMT", time(0));
+        assert_eq!(cookie.unwrap().expires, Some(time(60)));
+    }
+
+    #[test]
+    fn test_clear_cookies() {
+        let jar = CookieJar::new();
+        jar.set_cookie(&url("http://a.example.com/"), "session=1", time(0));
+        jar.set_cookie(
+            &url("http://a.example.com/"),
+            "id=1; Max-Age=3600",
+            SystemTime::now(),
+        );
+        jar.set_cookie(
+            &url("http://example.org/"),
+            "id=2; Max-Age=3600",
+            SystemTime::now(),
+        );
+
+        jar.clear_session_cookies();
+        assert_eq!(jar.cookies().len(), 2);
+        jar.remove_domain("example.com");
+        assert_eq!(jar.cookies()[0].value, "2");
+        jar.clear();
+        assert!(jar.cookies().is_empty());
+    }
+}
@@ -1,35 +1,373 @@
-use reqwest::{Client, Method};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use reqwest::{redirect::Policy, Certificate, Client, Method, Proxy};
+use url::parser::URLParser;
+
+use crate::cookie::cookie_jar;
+
+/// Longest chain of redirects that is followed
+const MAX_REDIRECTS: usize = 10;
+
+/// Headers that make a request conditional on the validators of a cached
+/// response
+const CONDITIONAL_HEADERS: [&str; 5] = [
+    "if-none-match",
+    "if-modified-since",
+    "if-match",
+    "if-unmodified-since",
+    "if-range",
+];
 
 pub enum HttpResponse {
     Success(Vec<u8>),
     Failure(String),
 }
 
-static mut HTTP_CLIENT: Option<Client> = None;
+/// Response of `fetch` with its status and headers
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    /// Header names are in lowercase
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
 
-fn get_http_client() -> &'static Client {
-    unsafe {
-        if HTTP_CLIENT.is_none() {
-            HTTP_CLIENT = Some(Client::new());
-        }
-        HTTP_CLIENT.as_ref().expect("Unable to obtain http client")
+impl Response {
+    /// Value of the first header named `name`, which must be in lowercase
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
     }
 }
 
+/// Settings of the connections made by `fetch`
+#[derive(Debug, Clone, Default)]
+pub struct NetworkSettings {
+    /// Proxy every request is sent through, like `http://proxy:3128`. The
+    /// proxies of the `HTTP_PROXY` and `HTTPS_PROXY` environment variables
+    /// are used when it is `None`.
+    pub proxy: Option<String>,
+    /// PEM encoded certificates trusted in addition to the system roots,
+    /// like the one of a local server with a self-signed certificate
+    pub root_certificates: Vec<Vec<u8>>,
+    /// Accept any certificate, even expired or for another host. Only meant
+    /// for testing.
+    pub accept_invalid_certificates: bool,
+    /// Value of the `User-Agent` header, none is sent when it is `None`
+    pub user_agent: Option<String>,
+}
+
+static HTTP_CLIENT: Mutex<Option<Client>> = Mutex::new(None);
+
+fn build_http_client(settings: &NetworkSettings) -> Result<Client, String> {
+    // Redirects are followed by `fetch`, to store the cookies set by each
+    // response of the chain
+    let mut builder = Client::builder()
+        .redirect(Policy::none())
+        .danger_accept_invalid_certs(settings.accept_invalid_certificates);
+    if let Some(proxy) = &settings.proxy {
+        let proxy = Proxy::all(proxy.as_str()).map_err(|e| format!("Invalid proxy: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+    for certificate in &settings.root_certificates {
+        let certificate = Certificate::from_pem(certificate)
+            .map_err(|e| format!("Invalid root certificate: {}", e))?;
+        builder = builder.add_root_certificate(certificate);
+    }
+    if let Some(user_agent) = &settings.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn get_http_client() -> Client {
+    let mut client = HTTP_CLIENT.lock().unwrap();
+    client
+        .get_or_insert_with(|| {
+            build_http_client(&NetworkSettings::default()).expect("Unable to build http client")
+        })
+        .clone()
+}
+
+/// Send the next requests with the settings. The previous settings are kept
+/// if the proxy or a certificate is invalid.
+pub fn configure(settings: &NetworkSettings) -> Result<(), String> {
+    let client = build_http_client(settings)?;
+    *HTTP_CLIENT.lock().unwrap() = Some(client);
+    Ok(())
+}
+
 pub async fn request(method: &str, url: &str) -> HttpResponse {
+    match fetch(method, url, &[], None).await {
+        Ok(response) => HttpResponse::Success(response.body),
+        Err(e) => HttpResponse::Failure(e),
+    }
+}
+
+/// Send a request with extra headers, such as the validators of a cached
+/// response, and an optional body. Cookies of the jar are sent with the
+/// request and cookies set by the response are stored.
+pub async fn fetch(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<Vec<u8>>,
+) -> Result<Response, String> {
+    fetch_with_progress(method, url, headers, body, &|_, _| {}).await
+}
+
+/// `fetch`, calling `on_progress` with the bytes of the body received so
+/// far and the length the server announced, if any, as each part of the
+/// body arrives
+pub async fn fetch_with_progress(
+    method: &str,
+    url: &str,
+    headers: &[(String, String)],
+    body: Option<Vec<u8>>,
+    on_progress: &(dyn Fn(u64, Option<u64>) + Sync),
+) -> Result<Response, String> {
     let client = get_http_client();
-    let method = match method.to_lowercase().as_str() {
+    let mut method = match method.to_lowercase().as_str() {
         "get" => Method::GET,
-        _ => return HttpResponse::Failure(format!("Unsupported method: {}", method)),
+        "post" => Method::POST,
+        _ => return Err(format!("Unsupported method: {}", method)),
     };
+    let mut headers = headers.to_vec();
+    let mut body = body;
+    let mut url = URLParser::parse(url, None).ok_or_else(|| format!("Invalid URL: {}", url))?;
 
-    let response = match client.request(method, url).send().await {
-        Ok(response) => match response.bytes().await {
-            Ok(bytes) => HttpResponse::Success(bytes.to_vec()),
-            Err(e) => HttpResponse::Failure(e.to_string()),
-        },
-        Err(e) => HttpResponse::Failure(e.to_string()),
-    };
+    for _ in 0..=MAX_REDIRECTS {
+        // The fragment isn't sent to the server
+        let request_url = url::Url {
+            fragment: None,
+            ..url.clone()
+        };
+        let mut request = client.request(method.clone(), request_url.as_str());
+        for (name, value) in &headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = &body {
+            request = request.body(body.clone());
+        }
+        if let Some(cookies) = cookie_jar().cookie_header(&url, SystemTime::now()) {
+            request = request.header("cookie", cookies);
+        }
+
+        let mut response = request.send().await.map_err(|e| e.to_string())?;
+        for set_cookie in response.headers().get_all("set-cookie") {
+            if let Ok(set_cookie) = set_cookie.to_str() {
+                cookie_jar().set_cookie(&url, set_cookie, SystemTime::now());
+            }
+        }
+
+        let location = response
+            .headers()
+            .get("location")
+            .and_then(|location| location.to_str().ok())
+            .filter(|_| response.status().is_redirection());
+        if let Some(location) = location {
+            let target = URLParser::parse(location, Some(url.clone()))
+                .ok_or_else(|| format!("Invalid redirect URL: {}", location))?;
+            // Headers of the caller, like credentials, are meant for the
+            // origin it sent them to
+            if !is_same_origin(&url, &target) {
+                headers.clear();
+            }
+            url = target;
+            // The validators belong to the response cached for the URL that
+            // redirected, so the target must not answer 304 for them
+            headers.retain(|(name, _)| {
+                !CONDITIONAL_HEADERS
+                    .iter()
+                    .any(|conditional| name.eq_ignore_ascii_case(conditional))
+            });
+            // Except for 307 and 308, the request is sent again as a GET
+            // without its body
+            let status = response.status().as_u16();
+            if method == Method::POST && status != 307 && status != 308 {
+                method = Method::GET;
+                body = None;
+            }
+            if body.is_none() {
+                headers.retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+            }
+            continue;
+        }
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .to_str()
+                    .ok()
+                    .map(|value| (name.as_str().to_lowercase(), value.to_string()))
+            })
+            .collect();
+        let total = response.content_length();
+        let mut body = Vec::new();
+        on_progress(0, total);
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            body.extend_from_slice(&chunk);
+            on_progress(body.len() as u64, total);
+        }
+
+        return Ok(Response {
+            status,
+            headers,
+            body,
+        });
+    }
+    Err(format!("Too many redirects: {}", url))
+}
+
+fn is_same_origin(a: &url::Url, b: &url::Url) -> bool {
+    let host = |url: &url::Url| url.host.as_ref().map(|host| host.to_lowercase());
+    a.scheme == b.scheme && host(a) == host(b) && a.port == b.port
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    /// Serve each response to one connection on a local port, and return
+    /// the URL of the server with the requests it received
+    fn serve(responses: Vec<String>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|response| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    // The body of the request, if any, is ignored
+                    while !request.windows(4).any(|end| end == b"\r\n\r\n") {
+                        let length = stream.read(&mut buffer).unwrap();
+                        request.extend_from_slice(&buffer[..length]);
+                    }
+                    stream.write_all(response.as_bytes()).unwrap();
+                    String::from_utf8(request).unwrap().to_lowercase()
+                })
+                .collect()
+        });
+        (url, server)
+    }
+
+    fn redirect_to(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            location
+        )
+    }
+
+    fn ok(body: &str) -> String {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_redirect_drops_validators() {
+        let (url, server) = serve(vec![redirect_to("/moved"), ok("moved")]);
+        let validators = [
+            ("if-none-match".to_string(), "\"v1\"".to_string()),
+            ("accept".to_string(), "text/html".to_string()),
+        ];
+        let response = block_on(fetch("GET", &format!("{}/page", url), &validators, None)).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"moved");
 
-    response
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("if-none-match: \"v1\""));
+        assert!(requests[1].starts_with("get /moved "));
+        assert!(!requests[1].contains("if-none-match"));
+        assert!(requests[1].contains("accept: text/html"));
+    }
+
+    #[test]
+    fn test_cross_origin_redirect_drops_headers() {
+        let (other_url, other_server) = serve(vec![ok("other")]);
+        let (url, server) = serve(vec![redirect_to(&format!("{}/landing", other_url))]);
+        let headers = [
+            ("authorization".to_string(), "Bearer secret".to_string()),
+            ("content-type".to_string(), "text/plain".to_string()),
+        ];
+        let response = block_on(fetch(
+            "POST",
+            &format!("{}/login", url),
+            &headers,
+            Some(b"name=moon".to_vec()),
+        ))
+        .unwrap();
+        assert_eq!(response.body, b"other");
+
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("authorization: bearer secret"));
+        let requests = other_server.join().unwrap();
+        assert!(requests[0].starts_with("get /landing "));
+        assert!(!requests[0].contains("authorization"));
+        assert!(!requests[0].contains("content-type"));
+    }
+
+    #[test]
+    fn test_fetch_progress() {
+        let (url, server) = serve(vec![ok("moon")]);
+        let reports = Mutex::new(Vec::new());
+        let response = block_on(fetch_with_progress(
+            "GET",
+            &url,
+            &[],
+            None,
+            &|loaded, total| reports.lock().unwrap().push((loaded, total)),
+        ))
+        .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.body, b"moon");
+        let reports = reports.into_inner().unwrap();
+        assert_eq!(reports.first(), Some(&(0, Some(4))));
+        assert_eq!(reports.last(), Some(&(4, Some(4))));
+    }
+
+    #[test]
+    fn test_configure_user_agent() {
+        let settings = NetworkSettings {
+            root_certificates: vec![b"not a certificate".to_vec()],
+            ..Default::default()
+        };
+        assert!(configure(&settings).is_err());
+
+        let settings = NetworkSettings {
+            user_agent: Some("Moon/1.0".to_string()),
+            ..Default::default()
+        };
+        configure(&settings).unwrap();
+        let (url, server) = serve(vec![ok("page")]);
+        let response = block_on(fetch("GET", &url, &[], None));
+        configure(&NetworkSettings::default()).unwrap();
+
+        assert_eq!(response.unwrap().body, b"page");
+        let requests = server.join().unwrap();
+        assert!(requests[0].contains("user-agent: moon/1.0"));
+    }
 }
@@ -1 +1,2 @@
+pub mod cookie;
 pub mod http;
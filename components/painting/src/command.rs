@@ -0,0 +1,309 @@
+use async_trait::async_trait;
+use gfx::{FontIndex, Graphics};
+use serde::{Deserialize, Serialize};
+use shared::{
+    color::Color,
+    image::ImageBitmap,
+    primitive::{Point, RRect, Rect, Size},
+};
+
+/// Version of the serialized form of display lists. Bumped whenever a
+/// command is added or changed, so that tools reading recorded lists can
+/// tell which commands to expect.
+pub const DISPLAY_LIST_VERSION: u32 = 1;
+
+/// The draws of a frame, in the order they are painted. Every draw is in
+/// CSS pixels, relative to the top left corner of the viewport. Layers are
+/// flattened, so that replaying the list paints the frame on graphics that
+/// don't composite layers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayList {
+    pub version: u32,
+    /// Size of the viewport the list was painted for, in CSS pixels
+    pub viewport: Size,
+    pub commands: Vec<Command>,
+}
+
+/// A call to the `Graphics` of the painter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    FillRect {
+        rect: Rect,
+        color: Color,
+    },
+    #[serde(rename = "fill_rrect")]
+    FillRRect {
+        rect: RRect,
+        color: Color,
+    },
+    FillText {
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+        /// Runs from the top to the bottom of its bounds
+        is_vertical: bool,
+    },
+    FillPolygon {
+        points: Vec<Point>,
+        color: Color,
+    },
+    DrawImage {
+        bounds: Rect,
+        image: Image,
+    },
+    FillShadow {
+        rect: Rect,
+        blur: f32,
+        color: Color,
+        caster: RRect,
+    },
+    FillInsetShadow {
+        bounds: RRect,
+        hole: Rect,
+        blur: f32,
+        color: Color,
+    },
+    SetClip {
+        clip: Option<Rect>,
+    },
+}
+
+/// Pixels of a drawn image in RGBA8, row by row
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl DisplayList {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Display lists are serializable")
+    }
+
+    /// Read a list serialized by `to_json`. Lists of another version are
+    /// rejected.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let list: DisplayList = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if list.version != DISPLAY_LIST_VERSION {
+            return Err(format!(
+                "Unsupported display list version {}, expected {}",
+                list.version, DISPLAY_LIST_VERSION
+            ));
+        }
+        Ok(list)
+    }
+
+    /// Paint the list again onto the graphics, which are expected to be
+    /// sized for the viewport of the list
+    pub fn replay<G: Graphics>(&self, gfx: &mut G) {
+        for command in &self.commands {
+            match command.clone() {
+                Command::FillRect { rect, color } => gfx.fill_rect(rect, color),
+                Command::FillRRect { rect, color } => gfx.fill_rrect(rect, color),
+                Command::FillText {
+                    content,
+                    bounds,
+                    color,
+                    size,
+                    font,
+                    is_vertical: false,
+                } => gfx.fill_text(content, bounds, color, size, font),
+                Command::FillText {
+                    content,
+                    bounds,
+                    color,
+                    size,
+                    font,
+                    is_vertical: true,
+                } => gfx.fill_vertical_text(content, bounds, color, size, font),
+                Command::FillPolygon { points, color } => gfx.fill_polygon(points, color),
+                Command::DrawImage { bounds, image } => {
+                    match ImageBitmap::from_rgba(image.width, image.height, image.pixels) {
+                        Some(bitmap) => gfx.draw_image(bounds, bitmap),
+                        None => log::warn!("Skipping image with invalid pixels"),
+                    }
+                }
+                Command::FillShadow {
+                    rect,
+                    blur,
+                    color,
+                    caster,
+                } => gfx.fill_shadow(rect, blur, color, &caster),
+                Command::FillInsetShadow {
+                    bounds,
+                    hole,
+                    blur,
+                    color,
+                } => gfx.fill_inset_shadow(&bounds, hole, blur, color),
+                Command::SetClip { clip } => gfx.set_clip(clip),
+            }
+        }
+    }
+}
+
+/// Graphics that record the draws of the painter into a display list
+pub struct DisplayListRecorder {
+    viewport: Size,
+    commands: Vec<Command>,
+}
+
+impl DisplayListRecorder {
+    pub fn new() -> Self {
+        Self {
+            viewport: Size::default(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// The draws recorded since the last call
+    pub fn take_display_list(&mut self) -> DisplayList {
+        DisplayList {
+            version: DISPLAY_LIST_VERSION,
+            viewport: self.viewport.clone(),
+            commands: std::mem::take(&mut self.commands),
+        }
+    }
+}
+
+impl Default for DisplayListRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait(?Send)]
+impl Graphics for DisplayListRecorder {
+    fn fill_rect(&mut self, rect: Rect, color: Color) {
+        self.commands.push(Command::FillRect { rect, color });
+    }
+
+    fn fill_rrect(&mut self, rect: RRect, color: Color) {
+        self.commands.push(Command::FillRRect { rect, color });
+    }
+
+    fn fill_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        self.commands.push(Command::FillText {
+            content,
+            bounds,
+            color,
+            size,
+            font,
+            is_vertical: false,
+        });
+    }
+
+    fn fill_vertical_text(
+        &mut self,
+        content: String,
+        bounds: Rect,
+        color: Color,
+        size: f32,
+        font: FontIndex,
+    ) {
+        self.commands.push(Command::FillText {
+            content,
+            bounds,
+            color,
+            size,
+            font,
+            is_vertical: true,
+        });
+    }
+
+    fn fill_polygon(&mut self, points: Vec<Point>, color: Color) {
+        self.commands.push(Command::FillPolygon { points, color });
+    }
+
+    fn draw_image(&mut self, bounds: Rect, bitmap: ImageBitmap) {
+        let image = Image {
+            width: bitmap.width(),
+            height: bitmap.height(),
+            pixels: bitmap.data().to_vec(),
+        };
+        self.commands.push(Command::DrawImage { bounds, image });
+    }
+
+    fn fill_shadow(&mut self, rect: Rect, blur: f32, color: Color, caster: &RRect) {
+        self.commands.push(Command::FillShadow {
+            rect,
+            blur,
+            color,
+            caster: caster.clone(),
+        });
+    }
+
+    fn fill_inset_shadow(&mut self, bounds: &RRect, hole: Rect, blur: f32, color: Color) {
+        self.commands.push(Command::FillInsetShadow {
+            bounds: bounds.clone(),
+            hole,
+            blur,
+            color,
+        });
+    }
+
+    fn set_clip(&mut self, clip: Option<Rect>) {
+        self.commands.push(Command::SetClip { clip });
+    }
+
+    fn resize(&mut self, size: Size) {
+        self.viewport = size;
+    }
+
+    /// Draws are recorded in CSS pixels whatever the scale
+    fn set_scale(&mut self, _: f32) {}
+
+    /// The recorded display list, serialized
+    async fn output(&mut self) -> Vec<u8> {
+        self.take_display_list().to_json().into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_display_list() {
+        let mut recorder = DisplayListRecorder::new();
+        recorder.resize(Size::new(100., 50.));
+        recorder.set_clip(Some(Rect::new(0., 0., 50., 50.)));
+        recorder.fill_rect(Rect::new(1., 2., 3., 4.), Color::new(255, 0, 0, 255));
+        recorder.fill_vertical_text(
+            "Hi".to_string(),
+            Rect::new(0., 0., 10., 20.),
+            Color::new(0, 0, 0, 255),
+            16.,
+            0,
+        );
+        recorder.draw_image(
+            Rect::new(0., 0., 1., 1.),
+            ImageBitmap::from_rgba(1, 1, vec![1, 2, 3, 4]).unwrap(),
+        );
+        let list = recorder.take_display_list();
+        assert_eq!(list.commands.len(), 4);
+
+        let json = list.to_json();
+        assert!(json.starts_with(r#"{"version":1,"viewport":{"width":100.0,"height":50.0}"#));
+        assert!(json.contains(r#"{"type":"fill_rect","rect":{"width":3.0,"height":4.0,"x":1.0"#));
+        assert_eq!(DisplayList::from_json(&json), Ok(list.clone()));
+
+        let mut replayed = DisplayListRecorder::new();
+        replayed.resize(list.viewport.clone());
+        list.replay(&mut replayed);
+        assert_eq!(replayed.take_display_list(), list);
+
+        let future_version = json.replacen(r#""version":1"#, r#""version":2"#, 1);
+        assert!(DisplayList::from_json(&future_version).is_err());
+    }
+}
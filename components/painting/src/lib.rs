@@ -1,5 +1,8 @@
+pub mod command;
+mod overlay;
 mod painter;
 mod request_builder;
 mod utils;
 
+pub use overlay::*;
 pub use painter::*;
@@ -0,0 +1,57 @@
+use shared::{color::Color, primitive::Rect};
+
+/// A highlighted region painted on top of the page content
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pub rect: Rect,
+    pub color: Color,
+}
+
+/// A rectangular frame painted on top of the page content, such as the
+/// outline of an inspected node
+#[derive(Debug, Clone)]
+pub struct Outline {
+    /// Outer edge of the outline
+    pub rect: Rect,
+    pub width: f32,
+    pub color: Color,
+}
+
+/// Paint layer for decorations that are not part of the layout tree,
+/// such as find-in-page highlights. Rects are in absolute page coordinates.
+#[derive(Debug, Default)]
+pub struct OverlayLayer {
+    highlights: Vec<Highlight>,
+    outlines: Vec<Outline>,
+}
+
+impl OverlayLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_highlight(&mut self, rect: Rect, color: Color) {
+        self.highlights.push(Highlight { rect, color });
+    }
+
+    pub fn add_outline(&mut self, rect: Rect, width: f32, color: Color) {
+        self.outlines.push(Outline { rect, width, color });
+    }
+
+    pub fn clear(&mut self) {
+        self.highlights.clear();
+        self.outlines.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.highlights.is_empty() && self.outlines.is_empty()
+    }
+
+    pub fn highlights(&self) -> &[Highlight] {
+        &self.highlights
+    }
+
+    pub fn outlines(&self) -> &[Outline] {
+        &self.outlines
+    }
+}
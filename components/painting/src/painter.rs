@@ -1,12 +1,29 @@
-use crate::request_builder::{PaintBox, PaintBoxBorders, PaintText, RectOrRRect, RequestBuilder};
+use crate::overlay::OverlayLayer;
+use crate::request_builder::{
+    PaintArrow, PaintBox, PaintBoxBorder, PaintBoxBorders, PaintImage, PaintMarker, PaintOutline,
+    PaintRequest, PaintText, RectOrRRect, RequestBuilder,
+};
 use gfx::Graphics;
 use layout::layout_box::LayoutBoxPtr;
-use shared::primitive::{Point, Rect, Size};
-use style_types::values::prelude::BorderStyle;
+use shared::{
+    color::Color,
+    primitive::{Corners, Point, RRect, Radii, Rect, Size},
+};
+use style_types::values::prelude::{BorderStyle, ListStyleType};
+
+/// Number of segments used to approximate a circle marker
+const CIRCLE_MARKER_SEGMENTS: usize = 16;
+/// Number of directions around a run that the copies approximating the
+/// blur of its shadows and its stroke are painted in
+const TEXT_PASS_DIRECTIONS: usize = 8;
 
 pub struct Painter<G: Graphics> {
     gfx: G,
     canvas_size: Size,
+    scroll_offset: Point,
+    scale: f32,
+    /// Clip of the graphics, set for the items of nested documents
+    clip: Option<Rect>,
 }
 
 impl<G: Graphics> Painter<G> {
@@ -14,6 +31,9 @@ impl<G: Graphics> Painter<G> {
         Self {
             gfx,
             canvas_size: Size::default(),
+            scroll_offset: Point::default(),
+            scale: 1.,
+            clip: None,
         }
     }
 
@@ -22,219 +42,742 @@ impl<G: Graphics> Painter<G> {
         self.canvas_size = size;
     }
 
+    /// Set the number of device pixels per CSS pixel
+    pub fn set_scale(&mut self, scale: f32) {
+        self.gfx.set_scale(scale);
+        self.scale = scale;
+    }
+
+    pub fn set_scroll_offset(&mut self, offset: Point) {
+        self.scroll_offset = offset;
+    }
+
+    pub fn graphics(&self) -> &G {
+        &self.gfx
+    }
+
+    pub fn graphics_mut(&mut self) -> &mut G {
+        &mut self.gfx
+    }
+
     pub async fn output(&mut self) -> Vec<u8> {
         let result = self.gfx.output().await;
         result
     }
 
     pub fn paint(&mut self, layout_box: &LayoutBoxPtr) {
-        let request = RequestBuilder::new(&self.canvas_size).build(layout_box);
+        let viewport_size = Size::new(
+            self.canvas_size.width / self.scale,
+            self.canvas_size.height / self.scale,
+        );
+        let gfx = &self.gfx;
+        let can_composite = |content_size: &Size| gfx.can_composite_layer(content_size);
+        let request = RequestBuilder::new(&viewport_size, &self.scroll_offset)
+            .with_layers(&can_composite)
+            .build(layout_box);
 
         log::info!("Number of boxes to paint: {}", request.boxes.len());
         log::info!("Number of texts to paint: {}", request.texts.len());
+        log::info!("Number of layers to composite: {}", request.layers.len());
+        self.paint_request(request);
+    }
+
+    /// Paint the content of the layers again in the next paint, instead of
+    /// compositing what they kept
+    pub fn invalidate_layers(&mut self) {
+        self.gfx.invalidate_layers();
+    }
 
+    fn paint_request(&mut self, request: PaintRequest) {
         for current_box in request.boxes {
+            self.set_clip(&current_box.clip);
             self.paint_box(current_box);
         }
 
+        for marker in request.markers {
+            self.set_clip(&marker.clip);
+            self.paint_marker(marker);
+        }
+
+        for arrow in request.arrows {
+            self.set_clip(&arrow.clip);
+            self.paint_arrow(arrow);
+        }
+
+        // Images are composited over the boxes and under the text
+        for image in request.images {
+            self.set_clip(&image.clip);
+            self.paint_image(image);
+        }
+
         for text in request.texts {
+            self.set_clip(&text.clip);
             self.paint_text(text);
         }
+
+        // Outlines are drawn over the content of every box
+        for outline in request.outlines {
+            self.set_clip(&outline.clip);
+            self.paint_outline(outline);
+        }
+
+        self.set_clip(&None);
+
+        // Layers that kept their content are only composited again
+        for layer in request.layers {
+            if self.gfx.begin_layer(layer.layer) {
+                self.paint_request(layer.content);
+            }
+            self.gfx.end_layer();
+        }
     }
 
-    fn paint_text(&mut self, paint_text: PaintText) {
-        self.gfx.fill_text(
-            paint_text.content,
-            paint_text.rect,
-            paint_text.color,
-            paint_text.font_size,
-        );
+    fn set_clip(&mut self, clip: &Option<Rect>) {
+        if &self.clip != clip {
+            self.clip = clip.clone();
+            self.gfx.set_clip(clip.clone());
+        }
     }
 
-    fn paint_box(&mut self, paint_box: PaintBox) {
-        match paint_box.rect {
-            RectOrRRect::Rect(rect) => {
-                self.paint_borders(&rect, &paint_box.border_rect, &paint_box.borders);
-                self.gfx.fill_rect(rect, paint_box.background_color);
-            }
-            RectOrRRect::RRect(rrect) => {
-                self.gfx.fill_rrect(rrect, paint_box.background_color);
-            }
+    pub fn paint_overlay(&mut self, overlay: &OverlayLayer) {
+        for highlight in overlay.highlights() {
+            let mut rect = highlight.rect.clone();
+            rect.translate(-self.scroll_offset.x, -self.scroll_offset.y);
+            self.gfx.fill_rect(rect, highlight.color.clone());
+        }
+        for outline in overlay.outlines() {
+            let mut rect = outline.rect.clone();
+            rect.translate(-self.scroll_offset.x, -self.scroll_offset.y);
+            self.stroke_rect(rect, outline.width, outline.color.clone());
         }
     }
 
-    fn paint_borders(&mut self, box_rect: &Rect, border_rect: &Rect, borders: &PaintBoxBorders) {
-        self.paint_border_edges(box_rect, border_rect, borders);
-        self.paint_border_corners(box_rect, border_rect, borders);
+    fn paint_outline(&mut self, outline: PaintOutline) {
+        self.stroke_rect(outline.rect, outline.width, outline.color);
     }
 
-    fn paint_border_corners(
-        &mut self,
-        box_rect: &Rect,
-        border_rect: &Rect,
-        borders: &PaintBoxBorders,
-    ) {
-        if let (Some(border_top), Some(border_left)) = (&borders.top, &borders.left) {
-            assert!(matches!(border_top.style, BorderStyle::Solid));
-            assert!(matches!(border_left.style, BorderStyle::Solid));
+    /// Fill a frame of `width` inside the edges of the rect
+    fn stroke_rect(&mut self, rect: Rect, width: f32, color: Color) {
+        let inner_height = rect.height - 2. * width;
+        let edges = [
+            Rect::new(rect.x, rect.y, rect.width, width),
+            Rect::new(rect.x, rect.y + rect.height - width, rect.width, width),
+            Rect::new(rect.x, rect.y + width, width, inner_height),
+            Rect::new(
+                rect.x + rect.width - width,
+                rect.y + width,
+                width,
+                inner_height,
+            ),
+        ];
+        for edge in edges {
+            self.gfx.fill_rect(edge, color.clone());
+        }
+    }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x, border_rect.y),
-                    Point::new(box_rect.x, border_rect.y),
-                    Point::new(box_rect.x, box_rect.y),
-                ],
-                border_top.color.clone(),
-            );
+    fn paint_image(&mut self, image: PaintImage) {
+        self.gfx.draw_image(image.rect, image.bitmap);
+    }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x, border_rect.y),
-                    Point::new(box_rect.x, box_rect.y),
-                    Point::new(border_rect.x, box_rect.y),
-                ],
-                border_left.color.clone(),
-            );
+    /// Paint the text over its stroke, over its shadows. Graphics have no
+    /// blur nor stroke for glyphs, so both are approximated by copies of the
+    /// run painted around it, beneath the fill.
+    fn paint_text(&mut self, text: PaintText) {
+        for shadow in &text.shadows {
+            let offset = shadow.offset.clone();
+            if shadow.blur <= 0. {
+                self.fill_text_run(&text, &offset, shadow.color.clone());
+                continue;
+            }
+            // The copies are spread over the standard deviation of the blur,
+            // half its radius
+            let color = blur_pass_color(&shadow.color);
+            self.fill_text_run(&text, &offset, color.clone());
+            for direction in pass_offsets(shadow.blur / 2.) {
+                let pass_offset = Point::new(offset.x + direction.x, offset.y + direction.y);
+                self.fill_text_run(&text, &pass_offset, color.clone());
+            }
         }
 
-        if let (Some(border_left), Some(border_bottom)) = (&borders.left, &borders.bottom) {
-            assert!(matches!(border_bottom.style, BorderStyle::Solid));
-            assert!(matches!(border_left.style, BorderStyle::Solid));
+        // Only the outer half of the stroke shows from beneath the fill
+        if let Some(stroke) = &text.stroke {
+            for offset in pass_offsets(stroke.width / 2.) {
+                self.fill_text_run(&text, &offset, stroke.color.clone());
+            }
+        }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x, border_rect.y + border_rect.height),
-                    Point::new(box_rect.x, box_rect.y + box_rect.height),
-                    Point::new(box_rect.x, border_rect.y + border_rect.height),
-                ],
-                border_bottom.color.clone(),
-            );
+        self.fill_text_run(&text, &Point::new(0., 0.), text.color.clone());
+    }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x, border_rect.y + border_rect.height),
-                    Point::new(box_rect.x, box_rect.y + box_rect.height),
-                    Point::new(border_rect.x, box_rect.y + box_rect.height),
-                ],
-                border_left.color.clone(),
-            );
+    /// Fill the text moved by the offset, in the color
+    fn fill_text_run(&mut self, text: &PaintText, offset: &Point, color: Color) {
+        let mut rect = text.rect.clone();
+        rect.translate(offset.x, offset.y);
+        let content = text.content.clone();
+        match text.is_vertical {
+            true => self
+                .gfx
+                .fill_vertical_text(content, rect, color, text.font_size, text.font),
+            false => self
+                .gfx
+                .fill_text(content, rect, color, text.font_size, text.font),
         }
+    }
 
-        if let (Some(border_right), Some(border_bottom)) = (&borders.right, &borders.bottom) {
-            assert!(matches!(border_bottom.style, BorderStyle::Solid));
-            assert!(matches!(border_right.style, BorderStyle::Solid));
+    fn paint_marker(&mut self, marker: PaintMarker) {
+        let rect = marker.rect;
+        let radius = rect.width / 2.;
 
-            self.gfx.fill_polygon(
+        match marker.style_type {
+            ListStyleType::Disc => {
+                let radii = || Radii::new(radius, radius);
+                let corners = Corners::new(radii(), radii(), radii(), radii());
+                self.gfx.fill_rrect(RRect { rect, corners }, marker.color);
+            }
+            ListStyleType::Square => self.gfx.fill_rect(rect, marker.color),
+            ListStyleType::Circle => {
+                // Stroke the circle with quads between the outer and the inner edge
+                let center = Point::new(rect.x + radius, rect.y + radius);
+                let inner_radius = f32::max(radius - 1., 0.);
+                let point_at = |r: f32, segment: usize| {
+                    let angle =
+                        segment as f32 * std::f32::consts::TAU / CIRCLE_MARKER_SEGMENTS as f32;
+                    Point::new(center.x + r * angle.cos(), center.y + r * angle.sin())
+                };
+
+                for segment in 0..CIRCLE_MARKER_SEGMENTS {
+                    self.gfx.fill_polygon(
+                        vec![
+                            point_at(radius, segment),
+                            point_at(radius, segment + 1),
+                            point_at(inner_radius, segment + 1),
+                            point_at(inner_radius, segment),
+                        ],
+                        marker.color.clone(),
+                    );
+                }
+            }
+            ListStyleType::DisclosureOpen => self.gfx.fill_polygon(
                 vec![
-                    Point::new(
-                        border_rect.x + border_rect.width,
-                        border_rect.y + border_rect.height,
-                    ),
-                    Point::new(box_rect.x + box_rect.width, box_rect.y + box_rect.height),
-                    Point::new(
-                        box_rect.x + box_rect.width,
-                        border_rect.y + border_rect.height,
-                    ),
+                    Point::new(rect.x, rect.y),
+                    Point::new(rect.x + rect.width, rect.y),
+                    Point::new(rect.x + radius, rect.y + rect.height),
                 ],
-                border_bottom.color.clone(),
-            );
-
-            self.gfx.fill_polygon(
+                marker.color,
+            ),
+            ListStyleType::DisclosureClosed => self.gfx.fill_polygon(
                 vec![
-                    Point::new(
-                        border_rect.x + border_rect.width,
-                        border_rect.y + border_rect.height,
-                    ),
-                    Point::new(box_rect.x + box_rect.width, box_rect.y + box_rect.height),
-                    Point::new(
-                        border_rect.x + border_rect.width,
-                        box_rect.y + box_rect.height,
-                    ),
+                    Point::new(rect.x, rect.y),
+                    Point::new(rect.x + rect.width, rect.y + radius),
+                    Point::new(rect.x, rect.y + rect.height),
                 ],
-                border_right.color.clone(),
-            );
+                marker.color,
+            ),
+            _ => {}
         }
+    }
 
-        if let (Some(border_right), Some(border_top)) = (&borders.right, &borders.top) {
-            assert!(matches!(border_top.style, BorderStyle::Solid));
-            assert!(matches!(border_right.style, BorderStyle::Solid));
+    fn paint_arrow(&mut self, arrow: PaintArrow) {
+        let rect = arrow.rect;
+        self.gfx.fill_polygon(
+            vec![
+                Point::new(rect.x, rect.y),
+                Point::new(rect.x + rect.width, rect.y),
+                Point::new(rect.x + rect.width / 2., rect.y + rect.height),
+            ],
+            arrow.color,
+        );
+    }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x + border_rect.width, border_rect.y),
-                    Point::new(box_rect.x + box_rect.width, box_rect.y),
-                    Point::new(box_rect.x + box_rect.width, border_rect.y),
-                ],
-                border_top.color.clone(),
+    fn paint_box(&mut self, paint_box: PaintBox) {
+        // Outer shadows are painted around the box, never beneath it
+        let corners = match &paint_box.rect {
+            RectOrRRect::Rect(_) => {
+                let radii = || Radii::new(0., 0.);
+                Corners::new(radii(), radii(), radii(), radii())
+            }
+            RectOrRRect::RRect(rrect) => rrect.corners.clone(),
+        };
+        let border_box = RRect {
+            rect: paint_box.border_rect.clone(),
+            corners,
+        };
+        for shadow in paint_box.shadows.iter().filter(|shadow| !shadow.inset) {
+            self.gfx.fill_shadow(
+                shadow.rect.clone(),
+                shadow.blur,
+                shadow.color.clone(),
+                &border_box,
             );
+        }
 
-            self.gfx.fill_polygon(
-                vec![
-                    Point::new(border_rect.x + border_rect.width, border_rect.y),
-                    Point::new(box_rect.x + box_rect.width, box_rect.y),
-                    Point::new(border_rect.x + border_rect.width, box_rect.y),
-                ],
-                border_right.color.clone(),
+        let padding_box = match paint_box.rect {
+            RectOrRRect::Rect(rect) => {
+                self.paint_borders(&rect, &paint_box.border_rect, &paint_box.borders);
+                self.gfx.fill_rect(rect.clone(), paint_box.background_color);
+                RRect {
+                    rect,
+                    corners: border_box.corners,
+                }
+            }
+            RectOrRRect::RRect(rrect) => {
+                self.gfx
+                    .fill_rrect(rrect.clone(), paint_box.background_color);
+                rrect
+            }
+        };
+
+        // Inner shadows are painted above the background
+        for shadow in paint_box.shadows.iter().filter(|shadow| shadow.inset) {
+            self.gfx.fill_inset_shadow(
+                &padding_box,
+                shadow.rect.clone(),
+                shadow.blur,
+                shadow.color.clone(),
             );
         }
     }
 
-    fn paint_border_edges(
-        &mut self,
+    /// Paint each border as the trapezoid between the edges of the border
+    /// box and of the box, mitred at the corners it shares with another
+    /// border
+    fn paint_borders(&mut self, box_rect: &Rect, border_rect: &Rect, borders: &PaintBoxBorders) {
+        let sides = [
+            (BorderSide::Top, &borders.top),
+            (BorderSide::Right, &borders.right),
+            (BorderSide::Bottom, &borders.bottom),
+            (BorderSide::Left, &borders.left),
+        ];
+        for (side, border) in sides {
+            if let Some(border) = border {
+                let edge = BorderEdge::new(side, box_rect, border_rect, borders);
+                self.paint_border_edge(&edge, border);
+            }
+        }
+    }
+
+    /// https://www.w3.org/TR/css-backgrounds-3/#border-style
+    fn paint_border_edge(&mut self, edge: &BorderEdge, border: &PaintBoxBorder) {
+        if edge.width <= 0. {
+            return;
+        }
+
+        match border.style {
+            BorderStyle::Dashed => {
+                for (start, end) in edge.dashes(3. * edge.width) {
+                    self.gfx
+                        .fill_polygon(edge.quad((start, end), (0., 1.)), border.color.clone());
+                }
+            }
+            BorderStyle::Dotted => {
+                let radius = edge.width / 2.;
+                for (start, end) in edge.dashes(edge.width) {
+                    let center = edge.point((start + end) / 2., 0.5);
+                    self.gfx
+                        .fill_polygon(circle_points(&center, radius), border.color.clone());
+                }
+            }
+            // Two lines with a gap between them, all a third of the width.
+            // Thinner borders have no room for the gap.
+            BorderStyle::Double if edge.width >= 3. => {
+                for across in [(0., 1. / 3.), (2. / 3., 1.)] {
+                    self.gfx
+                        .fill_polygon(edge.quad((0., 1.), across), border.color.clone());
+                }
+            }
+            BorderStyle::Inset | BorderStyle::Outset => {
+                let raised = border.style == BorderStyle::Outset;
+                self.gfx.fill_polygon(
+                    edge.quad((0., 1.), (0., 1.)),
+                    edge.shade(&border.color, raised),
+                );
+            }
+            // The outer half is shaded the opposite way of the inner half
+            BorderStyle::Groove | BorderStyle::Ridge => {
+                let raised = border.style == BorderStyle::Ridge;
+                self.gfx.fill_polygon(
+                    edge.quad((0., 1.), (0., 0.5)),
+                    edge.shade(&border.color, raised),
+                );
+                self.gfx.fill_polygon(
+                    edge.quad((0., 1.), (0.5, 1.)),
+                    edge.shade(&border.color, !raised),
+                );
+            }
+            _ => self
+                .gfx
+                .fill_polygon(edge.quad((0., 1.), (0., 1.)), border.color.clone()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum BorderSide {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/// A border between the outer edge on the border box and the inner edge on
+/// the box, both running clockwise. It reaches the corner of the border box
+/// at the ends where it meets another border.
+struct BorderEdge {
+    side: BorderSide,
+    outer: (Point, Point),
+    inner: (Point, Point),
+    width: f32,
+}
+
+impl BorderEdge {
+    fn new(
+        side: BorderSide,
         box_rect: &Rect,
         border_rect: &Rect,
         borders: &PaintBoxBorders,
-    ) {
-        if let Some(border) = &borders.top {
-            assert!(matches!(border.style, BorderStyle::Solid));
-            self.gfx.fill_rect(
-                Rect::new(
-                    box_rect.x,
-                    border_rect.y,
-                    box_rect.width,
-                    box_rect.y - border_rect.y,
-                ),
-                border.color.clone(),
-            );
-        }
+    ) -> Self {
+        let (left, top) = (box_rect.x, box_rect.y);
+        let (right, bottom) = (box_rect.x + box_rect.width, box_rect.y + box_rect.height);
+        let outer_left = match borders.left {
+            Some(_) => border_rect.x,
+            None => left,
+        };
+        let outer_top = match borders.top {
+            Some(_) => border_rect.y,
+            None => top,
+        };
+        let outer_right = match borders.right {
+            Some(_) => border_rect.x + border_rect.width,
+            None => right,
+        };
+        let outer_bottom = match borders.bottom {
+            Some(_) => border_rect.y + border_rect.height,
+            None => bottom,
+        };
 
-        if let Some(border) = &borders.right {
-            assert!(matches!(border.style, BorderStyle::Solid));
-            self.gfx.fill_rect(
-                Rect::new(
-                    box_rect.x + box_rect.width,
-                    box_rect.y,
-                    (border_rect.x + border_rect.width) - (box_rect.x + box_rect.width),
-                    box_rect.height,
+        let (outer, inner, width) = match side {
+            BorderSide::Top => (
+                (
+                    Point::new(outer_left, border_rect.y),
+                    Point::new(outer_right, border_rect.y),
                 ),
-                border.color.clone(),
-            );
-        }
-
-        if let Some(border) = &borders.bottom {
-            assert!(matches!(border.style, BorderStyle::Solid));
-            self.gfx.fill_rect(
-                Rect::new(
-                    box_rect.x,
-                    box_rect.y + box_rect.height,
-                    box_rect.width,
-                    (border_rect.y + border_rect.height) - (box_rect.y + box_rect.height),
+                (Point::new(left, top), Point::new(right, top)),
+                top - border_rect.y,
+            ),
+            BorderSide::Right => (
+                (
+                    Point::new(border_rect.x + border_rect.width, outer_top),
+                    Point::new(border_rect.x + border_rect.width, outer_bottom),
                 ),
-                border.color.clone(),
-            );
+                (Point::new(right, top), Point::new(right, bottom)),
+                border_rect.x + border_rect.width - right,
+            ),
+            BorderSide::Bottom => (
+                (
+                    Point::new(outer_right, border_rect.y + border_rect.height),
+                    Point::new(outer_left, border_rect.y + border_rect.height),
+                ),
+                (Point::new(right, bottom), Point::new(left, bottom)),
+                border_rect.y + border_rect.height - bottom,
+            ),
+            BorderSide::Left => (
+                (
+                    Point::new(border_rect.x, outer_bottom),
+                    Point::new(border_rect.x, outer_top),
+                ),
+                (Point::new(left, bottom), Point::new(left, top)),
+                left - border_rect.x,
+            ),
+        };
+
+        Self {
+            side,
+            outer,
+            inner,
+            width,
         }
+    }
 
-        if let Some(border) = &borders.left {
-            assert!(matches!(border.style, BorderStyle::Solid));
-            self.gfx.fill_rect(
-                Rect::new(
-                    border_rect.x,
-                    box_rect.y,
-                    box_rect.x - border_rect.x,
-                    box_rect.height,
-                ),
-                border.color.clone(),
-            );
+    /// Point at the fractions `along` the edge and `across` it, from its
+    /// outer to its inner edge
+    fn point(&self, along: f32, across: f32) -> Point {
+        let lerp = |from: &Point, to: &Point, t: f32| {
+            Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t)
+        };
+        let outer = lerp(&self.outer.0, &self.outer.1, along);
+        let inner = lerp(&self.inner.0, &self.inner.1, along);
+        lerp(&outer, &inner, across)
+    }
+
+    /// Part of the edge between the fractions along and across it
+    fn quad(&self, along: (f32, f32), across: (f32, f32)) -> Vec<Point> {
+        vec![
+            self.point(along.0, across.0),
+            self.point(along.1, across.0),
+            self.point(along.1, across.1),
+            self.point(along.0, across.1),
+        ]
+    }
+
+    /// Fractions along the edge of the dashes about `dash_length` long, with
+    /// gaps as long between them. The edge starts and ends with a dash.
+    fn dashes(&self, dash_length: f32) -> impl Iterator<Item = (f32, f32)> {
+        let start = self.point(0., 0.5);
+        let end = self.point(1., 0.5);
+        let length = (end.x - start.x).hypot(end.y - start.y);
+        let count = f32::max(((length / dash_length + 1.) / 2.).round(), 1.) as usize;
+        let step = 1. / (2 * count - 1) as f32;
+        (0..count).map(move |dash| ((2 * dash) as f32 * step, (2 * dash + 1) as f32 * step))
+    }
+
+    /// Color of the edge of a sunken or `raised` border. The top and left
+    /// edges of a sunken border are a third darker, as are the bottom and
+    /// right edges of a raised border.
+    fn shade(&self, color: &Color, raised: bool) -> Color {
+        let is_top_left = matches!(self.side, BorderSide::Top | BorderSide::Left);
+        if is_top_left == raised {
+            return color.clone();
         }
+        let darken = |channel: u8| (channel as f32 * 2. / 3.).round() as u8;
+        Color::new(darken(color.r), darken(color.g), darken(color.b), color.a)
+    }
+}
+
+/// Polygon approximating a circle
+fn circle_points(center: &Point, radius: f32) -> Vec<Point> {
+    (0..CIRCLE_MARKER_SEGMENTS)
+        .map(|segment| {
+            let angle = segment as f32 * std::f32::consts::TAU / CIRCLE_MARKER_SEGMENTS as f32;
+            Point::new(
+                center.x + radius * angle.cos(),
+                center.y + radius * angle.sin(),
+            )
+        })
+        .collect()
+}
+
+/// Offsets of the copies of a run at the distance around it
+fn pass_offsets(distance: f32) -> impl Iterator<Item = Point> {
+    (0..TEXT_PASS_DIRECTIONS).map(move |direction| {
+        let angle = direction as f32 * std::f32::consts::TAU / TEXT_PASS_DIRECTIONS as f32;
+        Point::new(distance * angle.cos(), distance * angle.sin())
+    })
+}
+
+/// Color of each of the copies blurring a shadow. They blend close to the
+/// color of the shadow where they all overlap and fade where few do.
+fn blur_pass_color(color: &Color) -> Color {
+    let passes = (TEXT_PASS_DIRECTIONS + 1) as f32;
+    Color {
+        a: (color.a as f32 * f32::min(2. / passes, 1.)).round() as u8,
+        ..color.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{Command, DisplayListRecorder};
+    use crate::request_builder::{PaintTextShadow, PaintTextStroke};
+
+    #[test]
+    fn test_paint_text_over_stroke_and_shadows() {
+        let black = Color::new(0, 0, 0, 255);
+        let red = Color::new(255, 0, 0, 255);
+        let text = PaintText {
+            content: "Moon".to_string(),
+            font_size: 16.,
+            font: 0,
+            color: black.clone(),
+            rect: Rect::new(10., 10., 40., 20.),
+            is_vertical: false,
+            clip: None,
+            shadows: vec![
+                PaintTextShadow {
+                    offset: Point::new(2., 3.),
+                    blur: 0.,
+                    color: red.clone(),
+                },
+                PaintTextShadow {
+                    offset: Point::new(0., 0.),
+                    blur: 4.,
+                    color: red.clone(),
+                },
+            ],
+            stroke: Some(PaintTextStroke {
+                width: 2.,
+                color: red.clone(),
+            }),
+        };
+        let mut painter = Painter::new(DisplayListRecorder::new());
+        painter.resize(Size::new(100., 100.));
+        painter.paint_request(PaintRequest {
+            boxes: Vec::new(),
+            texts: vec![text],
+            markers: Vec::new(),
+            arrows: Vec::new(),
+            images: Vec::new(),
+            outlines: Vec::new(),
+            layers: Vec::new(),
+        });
+
+        let passes: Vec<(Rect, Color)> = painter
+            .graphics_mut()
+            .take_display_list()
+            .commands
+            .into_iter()
+            .filter_map(|command| match command {
+                Command::FillText { bounds, color, .. } => Some((bounds, color)),
+                _ => None,
+            })
+            .collect();
+        // A sharp shadow, a blurred shadow, the stroke and the fill
+        let blur_passes = TEXT_PASS_DIRECTIONS + 1;
+        assert_eq!(passes.len(), 1 + blur_passes + TEXT_PASS_DIRECTIONS + 1);
+        assert_eq!(passes[0], (Rect::new(12., 13., 40., 20.), red.clone()));
+        assert!(passes[1..1 + blur_passes]
+            .iter()
+            .all(|(_, color)| color.a > 0 && color.a < 255));
+        assert_eq!(passes[1 + blur_passes].0, Rect::new(11., 10., 40., 20.));
+        assert_eq!(passes[1 + blur_passes].1, red);
+        assert_eq!(passes.last(), Some(&(Rect::new(10., 10., 40., 20.), black)));
+    }
+    /// Polygons painted for borders 3px wide around a 24px square box
+    fn paint_borders(borders: PaintBoxBorders) -> Vec<(Vec<Point>, Color)> {
+        let paint_box = PaintBox {
+            rect: RectOrRRect::Rect(Rect::new(3., 3., 24., 24.)),
+            background_color: Color::default(),
+            borders,
+            border_rect: Rect::new(0., 0., 30., 30.),
+            shadows: Vec::new(),
+            clip: None,
+        };
+        let mut painter = Painter::new(DisplayListRecorder::new());
+        painter.resize(Size::new(100., 100.));
+        painter.paint_request(PaintRequest {
+            boxes: vec![paint_box],
+            texts: Vec::new(),
+            markers: Vec::new(),
+            arrows: Vec::new(),
+            images: Vec::new(),
+            outlines: Vec::new(),
+            layers: Vec::new(),
+        });
+
+        painter
+            .graphics_mut()
+            .take_display_list()
+            .commands
+            .into_iter()
+            .filter_map(|command| match command {
+                Command::FillPolygon { points, color } => Some((points, color)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn border(style: BorderStyle, color: &Color) -> Option<PaintBoxBorder> {
+        Some(PaintBoxBorder {
+            style,
+            color: color.clone(),
+        })
+    }
+
+    #[test]
+    fn test_paint_solid_border_mitred_at_corners() {
+        let red = Color::new(255, 0, 0, 255);
+        let polygons = paint_borders(PaintBoxBorders {
+            top: border(BorderStyle::Solid, &red),
+            right: None,
+            bottom: None,
+            left: border(BorderStyle::Solid, &red),
+        });
+
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(
+            polygons[0],
+            (
+                vec![
+                    Point::new(0., 0.),
+                    Point::new(27., 0.),
+                    Point::new(27., 3.),
+                    Point::new(3., 3.),
+                ],
+                red.clone()
+            )
+        );
+        assert_eq!(
+            polygons[1].0,
+            vec![
+                Point::new(0., 27.),
+                Point::new(0., 0.),
+                Point::new(3., 3.),
+                Point::new(3., 27.),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paint_double_and_shaded_borders() {
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        let polygons = paint_borders(PaintBoxBorders {
+            top: border(BorderStyle::Double, &red),
+            right: border(BorderStyle::Outset, &blue),
+            bottom: border(BorderStyle::Inset, &blue),
+            left: border(BorderStyle::Groove, &blue),
+        });
+
+        // Two lines a pixel wide at the outer and the inner edge
+        assert_eq!(polygons[0].0[0], Point::new(0., 0.));
+        assert_eq!(polygons[0].0[3], Point::new(1., 1.));
+        assert_eq!(polygons[1].0[0], Point::new(2., 2.));
+        assert_eq!(polygons[1].0[3], Point::new(3., 3.));
+        assert_eq!(polygons[1].1, red);
+
+        let dark_blue = Color::new(0, 0, 170, 255);
+        // The right edge of a raised border and the bottom edge of a sunken
+        // one are darker and lighter
+        assert_eq!(polygons[2].1, dark_blue);
+        assert_eq!(polygons[3].1, blue);
+        // A groove sinks at its outer half and rises at its inner half
+        assert_eq!(polygons.len(), 6);
+        assert_eq!(polygons[4].1, dark_blue);
+        assert_eq!(polygons[5].1, blue);
+    }
+
+    #[test]
+    fn test_paint_dashed_and_dotted_borders() {
+        let red = Color::new(255, 0, 0, 255);
+        let blue = Color::new(0, 0, 255, 255);
+        let polygons = paint_borders(PaintBoxBorders {
+            top: border(BorderStyle::Dashed, &red),
+            right: None,
+            bottom: border(BorderStyle::Dotted, &blue),
+            left: None,
+        });
+
+        // Dashes about three times as long as the width, with the 24px edge
+        // starting and ending with one
+        let dashes: Vec<_> = polygons.iter().filter(|(_, color)| color == &red).collect();
+        assert_eq!(dashes.len(), 2);
+        assert_eq!(dashes[0].0[0], Point::new(3., 0.));
+        assert_eq!(dashes[0].0[1], Point::new(11., 0.));
+        assert_eq!(dashes[1].0[1], Point::new(27., 0.));
+
+        // Round dots 3px wide in the middle of the edge, from its right end
+        let dots: Vec<_> = polygons
+            .iter()
+            .filter(|(_, color)| color == &blue)
+            .collect();
+        assert_eq!(dots.len(), 5);
+        assert!(dots
+            .iter()
+            .all(|(points, _)| points.len() == CIRCLE_MARKER_SEGMENTS));
+        let points = &dots[0].0;
+        let center_x = points.iter().map(|point| point.x).sum::<f32>() / points.len() as f32;
+        let center_y = points.iter().map(|point| point.y).sum::<f32>() / points.len() as f32;
+        assert!((center_x - (27. - 24. / 18.)).abs() < 1e-3);
+        assert!((center_y - 28.5).abs() < 1e-3);
     }
 }
@@ -1,25 +1,81 @@
-use layout::{flow::line_box::LineFragmentData, layout_box::LayoutBoxPtr};
+use dom::{node::NodePtr, select, textarea};
+use gfx::{FontIndex, Layer, LayerId, TextMeasure, DEFAULT_FONT};
+use layout::{
+    flow::{line_box::LineFragmentData, math::MathFragmentData},
+    layout_box::LayoutBoxPtr,
+    list_marker::marker_owner_of_first_line,
+    replaced::{current_image, object_fit, object_fit_rect, select_arrow_width, video_frame},
+    scroll::{
+        is_scroll_container, is_user_scrollable, max_scroll_offset, overflow_clip, scroll_offset,
+        scrollable_overflow,
+    },
+    text_control::TextControlLayout,
+    text_fragments::{font_of, TextSpacing},
+};
 use shared::{
     color::Color,
-    primitive::{Corners, RRect, Rect, Size},
+    image::ImageBitmap,
+    primitive::{Corners, EdgeSizes, Point, RRect, Rect, Size},
 };
 use style_types::{
-    values::{color::Color as CSSColor, prelude::BorderStyle},
+    values::{
+        color::Color as CSSColor,
+        prelude::{BorderStyle, BoxShadow, ListStylePosition, ListStyleType, TextShadow},
+    },
     Property, Value,
 };
 
-use crate::utils::{color_from_value, is_zero, to_radii};
+use crate::utils::{color_from_value, is_zero, to_radii, ColorContext};
+
+/// Width of the scrollbar thumbs of scroll containers
+const SCROLLBAR_THICKNESS: f32 = 6.;
+const SCROLLBAR_MIN_THUMB_LENGTH: f32 = 16.;
+const SCROLLBAR_THUMB_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 128,
+};
 
 pub struct RequestBuilder<'a> {
     boxes: Vec<PaintBox>,
     texts: Vec<PaintText>,
+    markers: Vec<PaintMarker>,
+    arrows: Vec<PaintArrow>,
+    images: Vec<PaintImage>,
+    outlines: Vec<PaintOutline>,
     root_element_use_body_background: bool,
     canvas_size: &'a Size,
+    scroll_offset: &'a Point,
+    /// Position of the canvas in the viewport. Documents nested in iframes
+    /// are painted onto the canvas of the iframe.
+    canvas_origin: Point,
+    /// Clip of the iframes the document is nested in, in viewport
+    /// coordinates
+    clip: Option<Rect>,
+    layers: Vec<PaintLayer>,
+    /// Whether content of the size can be painted into a layer. Scroll
+    /// containers are painted in place without it.
+    can_composite: Option<&'a dyn Fn(&Size) -> bool>,
 }
 
 pub struct PaintRequest {
     pub boxes: Vec<PaintBox>,
     pub texts: Vec<PaintText>,
+    pub markers: Vec<PaintMarker>,
+    pub arrows: Vec<PaintArrow>,
+    pub images: Vec<PaintImage>,
+    pub outlines: Vec<PaintOutline>,
+    /// Layers composited over the rest of the request in order, with the
+    /// layers nested in another one after it
+    pub layers: Vec<PaintLayer>,
+}
+
+/// The content of a scroll container painted into a layer of its own, in
+/// coordinates relative to the top left corner of its scrollable overflow
+pub struct PaintLayer {
+    pub layer: Layer,
+    pub content: PaintRequest,
 }
 
 pub struct PaintBox {
@@ -27,6 +83,19 @@ pub struct PaintBox {
     pub background_color: Color,
     pub borders: PaintBoxBorders,
     pub border_rect: Rect,
+    /// Shadows from the bottom-most to the top-most
+    pub shadows: Vec<PaintShadow>,
+    pub clip: Option<Rect>,
+}
+
+#[derive(Debug)]
+pub struct PaintShadow {
+    /// Border box of an outer shadow or padding box of an inner shadow,
+    /// moved by the shadow offset and resized by its spread
+    pub rect: Rect,
+    pub blur: f32,
+    pub color: Color,
+    pub inset: bool,
 }
 
 #[derive(Debug)]
@@ -46,8 +115,69 @@ pub struct PaintBoxBorder {
 pub struct PaintText {
     pub content: String,
     pub font_size: f32,
+    pub font: FontIndex,
     pub color: Color,
     pub rect: Rect,
+    /// Whether the text runs from the top to the bottom of its rect, in a
+    /// vertical writing mode
+    pub is_vertical: bool,
+    pub clip: Option<Rect>,
+    /// Shadows of `text-shadow` from the bottom-most to the top-most,
+    /// painted beneath the text
+    pub shadows: Vec<PaintTextShadow>,
+    /// Outline of the glyphs from `-webkit-text-stroke`
+    pub stroke: Option<PaintTextStroke>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaintTextShadow {
+    pub offset: Point,
+    pub blur: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone)]
+pub struct PaintTextStroke {
+    pub width: f32,
+    pub color: Color,
+}
+
+/// A list marker that is painted as a shape
+pub struct PaintMarker {
+    pub style_type: ListStyleType,
+    pub color: Color,
+    /// Bounds of the bullet
+    pub rect: Rect,
+    pub clip: Option<Rect>,
+}
+
+/// A triangle pointing down, like the arrow of a select element
+pub struct PaintArrow {
+    pub color: Color,
+    /// Bounds of the triangle
+    pub rect: Rect,
+    pub clip: Option<Rect>,
+}
+
+/// The content of a replaced element that is a bitmap, like the frame of a
+/// video
+pub struct PaintImage {
+    pub bitmap: ImageBitmap,
+    /// Rect the bitmap is stretched to, placed by `object-fit`
+    pub rect: Rect,
+    /// Clip of the image, which doesn't overflow the content box
+    pub clip: Option<Rect>,
+}
+
+/// An outline around the border box. Outlines don't take up space and are
+/// painted on top of the content.
+#[derive(Debug)]
+pub struct PaintOutline {
+    /// Outer edge of the outline
+    pub rect: Rect,
+    pub width: f32,
+    pub color: Color,
+    pub clip: Option<Rect>,
 }
 
 #[derive(Debug)]
@@ -57,65 +187,282 @@ pub enum RectOrRRect {
 }
 
 impl<'a> RequestBuilder<'a> {
-    pub fn new(canvas_size: &'a Size) -> Self {
+    pub fn new(canvas_size: &'a Size, scroll_offset: &'a Point) -> Self {
         Self {
             boxes: Vec::new(),
             texts: Vec::new(),
+            markers: Vec::new(),
+            arrows: Vec::new(),
+            images: Vec::new(),
+            outlines: Vec::new(),
             root_element_use_body_background: false,
             canvas_size,
+            scroll_offset,
+            canvas_origin: Point::new(0., 0.),
+            clip: None,
+            layers: Vec::new(),
+            can_composite: None,
         }
     }
 
+    /// Paint the content of scroll containers into layers when
+    /// `can_composite` accepts its size
+    pub fn with_layers(mut self, can_composite: &'a dyn Fn(&Size) -> bool) -> Self {
+        self.can_composite = Some(can_composite);
+        self
+    }
+
     pub fn build(mut self, layout_box: &LayoutBoxPtr) -> PaintRequest {
         self.process(layout_box);
+        self.into_request()
+    }
+
+    fn into_request(self) -> PaintRequest {
         PaintRequest {
             boxes: self.boxes,
             texts: self.texts,
+            markers: self.markers,
+            arrows: self.arrows,
+            images: self.images,
+            outlines: self.outlines,
+            layers: self.layers,
         }
     }
 
     fn process(&mut self, layout_box: &LayoutBoxPtr) {
+        // Inline-level replaced boxes are painted with their line
+        let is_painted_with_line = layout_box.is_inline() && !layout_box.is_non_replaced();
+        if is_painted_with_line {
+            return;
+        }
+
         if let Some(paint_box) = self.build_paint_box(layout_box, None) {
             self.boxes.push(paint_box);
         }
+        self.build_column_rules(layout_box);
+        if !layout_box.is_non_replaced() {
+            self.build_replaced_content(layout_box, layout_box.padding_box_absolute());
+        }
+        // Outlines of inline-level boxes are built with their line
+        if !layout_box.is_inline() {
+            self.build_outline(layout_box, None);
+        }
+
+        if is_scroll_container(layout_box) && self.build_layer(layout_box) {
+            return;
+        }
+
+        // The content of a box that clips its overflow is clipped to its
+        // padding box
+        let outer_clip = self.clip.clone();
+        if let Some(overflow_clip) = overflow_clip(layout_box) {
+            let overflow_clip = self.to_viewport(overflow_clip);
+            self.clip = Some(match &outer_clip {
+                Some(clip) => clip.intersection(&overflow_clip),
+                None => overflow_clip,
+            });
+        }
+
+        self.process_content(layout_box);
+
+        self.clip = outer_clip;
+        if is_user_scrollable(layout_box) {
+            for thumb in self.scrollbar_thumbs(layout_box) {
+                self.boxes.push(PaintBox {
+                    rect: RectOrRRect::Rect(thumb.clone()),
+                    background_color: SCROLLBAR_THUMB_COLOR,
+                    borders: PaintBoxBorders {
+                        top: None,
+                        right: None,
+                        bottom: None,
+                        left: None,
+                    },
+                    border_rect: thumb,
+                    shadows: Vec::new(),
+                    clip: self.clip.clone(),
+                });
+            }
+        }
+    }
 
+    fn process_content(&mut self, layout_box: &LayoutBoxPtr) {
         if layout_box.is_block() && layout_box.children_are_inline() {
             self.process_lines(layout_box);
         }
 
-        layout_box.for_each_child(|child| self.process(&LayoutBoxPtr(child)));
+        if layout_box.is_block() {
+            self.process_nested_document(layout_box, layout_box.absolute_location());
+            self.process_math(layout_box, layout_box.content_origin());
+        }
+
+        layout_box.for_each_child(|child| self.process(&child));
+    }
+
+    /// Paint the content of a scroll container into a layer, placed where
+    /// it is without scrolling so that scrolling only moves the layer.
+    /// Returns false if the content can't be composited.
+    fn build_layer(&mut self, layout_box: &LayoutBoxPtr) -> bool {
+        let (can_composite, node) = match (self.can_composite, layout_box.node()) {
+            (Some(can_composite), Some(node)) => (can_composite, node),
+            _ => return false,
+        };
+        let content_size = scrollable_overflow(layout_box);
+        if !can_composite(&content_size) {
+            return false;
+        }
+
+        let padding_box = layout_box.padding_box_absolute();
+        let offset = scroll_offset(layout_box);
+        let origin = Point::new(padding_box.x - offset.x, padding_box.y - offset.y);
+        let mut builder = RequestBuilder::new(&content_size, &origin);
+        builder.can_composite = self.can_composite;
+        builder.process_content(layout_box);
+        let mut content = builder.into_request();
+
+        let viewport_box = self.to_viewport(padding_box);
+        let position = Point::new(viewport_box.x - offset.x, viewport_box.y - offset.y);
+        let clip = match &self.clip {
+            Some(clip) => clip.intersection(&viewport_box),
+            None => viewport_box,
+        };
+        let scrollbars = match is_user_scrollable(layout_box) {
+            true => self.scrollbar_thumbs(layout_box),
+            false => Vec::new(),
+        };
+        let nested_layers = std::mem::take(&mut content.layers);
+        self.layers.push(PaintLayer {
+            layer: Layer {
+                id: LayerId(node.id().0),
+                content_size,
+                position: position.clone(),
+                clip: clip.clone(),
+                scrollbars,
+            },
+            content,
+        });
+
+        // Layers nested in the content are moved with it
+        for mut nested in nested_layers {
+            let layer = &mut nested.layer;
+            layer.position.translate(position.x, position.y);
+            layer.clip.translate(position.x, position.y);
+            layer.clip = layer.clip.intersection(&clip);
+            for thumb in &mut layer.scrollbars {
+                thumb.translate(position.x, position.y);
+            }
+            self.layers.push(nested);
+        }
+        true
+    }
+
+    /// Thumbs along the right and bottom edges of the padding box of a
+    /// scroll container, for each axis its content overflows
+    fn scrollbar_thumbs(&self, layout_box: &LayoutBoxPtr) -> Vec<Rect> {
+        let padding_box = self.to_viewport(layout_box.padding_box_absolute());
+        let overflow = scrollable_overflow(layout_box);
+        let offset = scroll_offset(layout_box);
+        let max = max_scroll_offset(layout_box);
+
+        let mut thumbs = Vec::new();
+        if max.y > 0. {
+            let length = f32::max(
+                padding_box.height * padding_box.height / overflow.height,
+                SCROLLBAR_MIN_THUMB_LENGTH,
+            );
+            thumbs.push(Rect::new(
+                padding_box.x + padding_box.width - SCROLLBAR_THICKNESS,
+                padding_box.y + offset.y / max.y * (padding_box.height - length),
+                SCROLLBAR_THICKNESS,
+                length,
+            ));
+        }
+        if max.x > 0. {
+            let length = f32::max(
+                padding_box.width * padding_box.width / overflow.width,
+                SCROLLBAR_MIN_THUMB_LENGTH,
+            );
+            thumbs.push(Rect::new(
+                padding_box.x + offset.x / max.x * (padding_box.width - length),
+                padding_box.y + padding_box.height - SCROLLBAR_THICKNESS,
+                length,
+                SCROLLBAR_THICKNESS,
+            ));
+        }
+
+        thumbs
+            .into_iter()
+            .map(|thumb| thumb.intersection(&padding_box))
+            .collect()
+    }
+
+    /// Paint the document loaded in an iframe at the content box of the
+    /// iframe, clipped to it
+    fn process_nested_document(&mut self, layout_box: &LayoutBoxPtr, location: Point) {
+        let nested_document = match layout_box.nested_document() {
+            Some(nested_document) => nested_document,
+            None => return,
+        };
+        let canvas_size = layout_box.content_size();
+        let scroll_offset = Point::new(
+            self.scroll_offset.x - location.x,
+            self.scroll_offset.y - location.y,
+        );
+        let mut builder = RequestBuilder::new(&canvas_size, &scroll_offset);
+        builder.can_composite = self.can_composite;
+        builder.canvas_origin = Point::new(
+            location.x - self.scroll_offset.x,
+            location.y - self.scroll_offset.y,
+        );
+        let content_rect = Rect::from((builder.canvas_origin.clone(), canvas_size.clone()));
+        builder.clip = Some(match &self.clip {
+            Some(clip) => clip.intersection(&content_rect),
+            None => content_rect,
+        });
+        let request = builder.build(&nested_document);
+
+        self.boxes.extend(request.boxes);
+        self.texts.extend(request.texts);
+        self.markers.extend(request.markers);
+        self.arrows.extend(request.arrows);
+        self.images.extend(request.images);
+        self.outlines.extend(request.outlines);
+        self.layers.extend(request.layers);
     }
 
     fn process_lines(&mut self, containing_block: &LayoutBoxPtr) {
         assert!(containing_block.is_block() && containing_block.children_are_inline());
 
+        self.process_outside_marker(containing_block);
+
         for line in containing_block.lines().borrow().iter() {
             for fragment in &line.fragments {
                 match &fragment.data {
                     LineFragmentData::Box(layout_box) if !layout_box.is_anonymous() => {
-                        let mut rect = Rect::from((
-                            containing_block.absolute_location(),
-                            fragment.size.clone(),
-                        ));
+                        let mut rect =
+                            Rect::from((containing_block.content_origin(), fragment.size.clone()));
                         rect.translate(fragment.offset.x, fragment.offset.y);
-                        self.build_paint_box(layout_box, Some(rect));
+                        let paint_box = self.build_paint_box(layout_box, Some(rect.clone()));
+                        self.build_outline(layout_box, Some(rect.clone()));
+                        if !layout_box.is_non_replaced() {
+                            self.boxes.extend(paint_box);
+                            self.build_replaced_content(layout_box, rect.clone());
+                            self.process_nested_document(layout_box, Point::new(rect.x, rect.y));
+                        }
+                        self.process_math(layout_box, Point::new(rect.x, rect.y));
                     }
+                    LineFragmentData::Marker(list_item) => {
+                        let mut rect =
+                            Rect::from((containing_block.content_origin(), fragment.size.clone()));
+                        rect.translate(fragment.offset.x, fragment.offset.y);
+                        self.build_marker(list_item, rect);
+                    }
+                    LineFragmentData::Text(layout_box, _) if !layout_box.is_visible() => {}
                     LineFragmentData::Text(layout_box, content) => {
                         let node = layout_box.node().unwrap();
-                        let mut text_rect = Rect::from((
-                            containing_block.absolute_location(),
-                            fragment.size.clone(),
-                        ));
+                        let mut text_rect =
+                            Rect::from((containing_block.content_origin(), fragment.size.clone()));
                         text_rect.translate(fragment.offset.x, fragment.offset.y);
-                        let color = color_from_value(&node.get_style(&Property::Color));
-                        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
-
-                        self.texts.push(PaintText {
-                            content: content.to_string(),
-                            color,
-                            font_size,
-                            rect: text_rect,
-                        });
+                        self.build_text(&node, content, text_rect, line.is_vertical);
                     }
                     _ => {}
                 }
@@ -123,18 +470,322 @@ impl<'a> RequestBuilder<'a> {
         }
     }
 
+    /// Paint text of the node at `rect`, relative to the canvas
+    fn build_text(&mut self, node: &NodePtr, content: &str, rect: Rect, is_vertical: bool) {
+        let color = ColorContext::new(&node.get_style(&Property::Color)).current_color;
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let text = PaintText {
+            content: content.to_string(),
+            color,
+            font_size,
+            font: font_of(node),
+            rect: self.to_viewport(rect),
+            is_vertical,
+            clip: self.clip.clone(),
+            shadows: self.compute_text_shadows(node),
+            stroke: self.compute_text_stroke(node),
+        };
+
+        let spacing = TextSpacing::of(node);
+        if spacing.is_zero() {
+            self.texts.push(text);
+        } else {
+            self.build_spaced_text(text, &spacing);
+        }
+    }
+
+    /// Paint the text and fraction bars of a math box whose content box is
+    /// at `origin`
+    fn process_math(&mut self, layout_box: &LayoutBoxPtr, origin: Point) {
+        let math = match layout_box.math() {
+            Some(math) => math,
+            None => return,
+        };
+        for fragment in math.fragments {
+            let mut rect = fragment.rect;
+            rect.translate(origin.x, origin.y);
+            match fragment.data {
+                MathFragmentData::Text(text_box, content) => {
+                    if text_box.is_visible() {
+                        self.build_text(&text_box.node().unwrap(), &content, rect, false);
+                    }
+                }
+                MathFragmentData::FractionBar(mfrac) => {
+                    if !mfrac.is_visible() {
+                        continue;
+                    }
+                    let node = mfrac.node().unwrap();
+                    let rect = self.to_viewport(rect);
+                    self.boxes.push(PaintBox {
+                        rect: RectOrRRect::Rect(rect.clone()),
+                        background_color: ColorContext::new(&node.get_style(&Property::Color))
+                            .current_color,
+                        borders: PaintBoxBorders {
+                            top: None,
+                            right: None,
+                            bottom: None,
+                            left: None,
+                        },
+                        border_rect: rect,
+                        shadows: Vec::new(),
+                        clip: self.clip.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Split text with letter or word spacing into characters, each placed
+    /// after the spacing of the characters before it
+    fn build_spaced_text(&mut self, text: PaintText, spacing: &TextSpacing) {
+        let mut text_measurer = TextMeasure::new();
+        let mut prefix = String::new();
+
+        for ch in text.content.chars() {
+            let advance = text_measurer
+                .measure_with_font(&prefix, text.font_size, text.font)
+                .width
+                + spacing.extra_width(&prefix);
+            prefix.push(ch);
+
+            if ch.is_whitespace() {
+                continue;
+            }
+            let content = ch.to_string();
+            let width = text_measurer
+                .measure_with_font(&content, text.font_size, text.font)
+                .width;
+            let rect = match text.is_vertical {
+                true => Rect::new(text.rect.x, text.rect.y + advance, text.rect.width, width),
+                false => Rect::new(text.rect.x + advance, text.rect.y, width, text.rect.height),
+            };
+            self.texts.push(PaintText {
+                content,
+                color: text.color.clone(),
+                font_size: text.font_size,
+                font: text.font,
+                rect,
+                is_vertical: text.is_vertical,
+                clip: text.clip.clone(),
+                shadows: text.shadows.clone(),
+                stroke: text.stroke.clone(),
+            });
+        }
+    }
+
+    /// Place the outside marker of a list item to the left of its first line
+    fn process_outside_marker(&mut self, containing_block: &LayoutBoxPtr) {
+        let list_item = match marker_owner_of_first_line(containing_block) {
+            Some(list_item) => list_item,
+            None => return,
+        };
+        let marker = list_item.marker().unwrap();
+        if marker.position != ListStylePosition::Outside {
+            return;
+        }
+
+        let font_size = list_item
+            .node()
+            .unwrap()
+            .get_style(&Property::FontSize)
+            .to_absolute_px();
+        let mut marker_size = marker.size(font_size, &mut TextMeasure::new());
+
+        if let Some(first_line) = containing_block.lines().borrow().first() {
+            marker_size.height = f32::max(marker_size.height, first_line.size.height);
+        }
+
+        let mut rect = Rect::from((containing_block.content_origin(), marker_size));
+        rect.translate(-rect.width, 0.);
+        self.build_marker(&list_item, rect);
+    }
+
+    /// Paint the content of replaced elements, which have no boxes of their
+    /// own for it
+    fn build_replaced_content(&mut self, layout_box: &LayoutBoxPtr, padding_rect: Rect) {
+        let node = match layout_box.node() {
+            Some(node) if node.is_element() && layout_box.is_visible() => node,
+            _ => return,
+        };
+        let padding = layout_box.box_model().borrow().padding.clone();
+        let content_rect = Rect::new(
+            padding_rect.x + padding.left,
+            padding_rect.y + padding.top,
+            padding_rect.width - padding.left - padding.right,
+            padding_rect.height - padding.top - padding.bottom,
+        );
+
+        match node.as_element().tag_name().as_str() {
+            "select" => self.build_select(&node, content_rect),
+            "textarea" => self.build_textarea(&node, content_rect),
+            "video" => {
+                if let Some(frame) = video_frame(&node) {
+                    self.build_bitmap(&node, content_rect, frame);
+                }
+            }
+            "img" => {
+                if let Some(image) = current_image(&node) {
+                    self.build_bitmap(&node, content_rect, image);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Paint the current frame of a video or the image of an `img`, fitted
+    /// to the content box by `object-fit` and clipped to it. Nothing is
+    /// painted until the embedder supplies a frame or the image is loaded.
+    fn build_bitmap(&mut self, node: &NodePtr, content_rect: Rect, bitmap: ImageBitmap) {
+        let content_rect = self.to_viewport(content_rect);
+        let frame_size = Size::new(bitmap.width() as f32, bitmap.height() as f32);
+        let rect = object_fit_rect(&object_fit(node), &content_rect, &frame_size);
+        let clip = match &self.clip {
+            Some(clip) => clip.intersection(&content_rect),
+            None => content_rect,
+        };
+        self.images.push(PaintImage {
+            bitmap,
+            rect,
+            clip: Some(clip),
+        });
+    }
+
+    /// Paint the label of the option shown by a select element, followed by
+    /// its arrow
+    fn build_select(&mut self, node: &NodePtr, content_rect: Rect) {
+        let color = ColorContext::new(&node.get_style(&Property::Color)).current_color;
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let arrow_width = select_arrow_width(font_size);
+
+        if let Some(option) = select::selected_options(node).first() {
+            let label_rect = Rect::new(
+                content_rect.x,
+                content_rect.y,
+                f32::max(content_rect.width - arrow_width, 0.),
+                content_rect.height,
+            );
+            self.texts.push(PaintText {
+                content: select::option_label(option),
+                color: color.clone(),
+                font_size,
+                font: font_of(node),
+                rect: self.to_viewport(label_rect),
+                is_vertical: false,
+                clip: self.clip.clone(),
+                shadows: self.compute_text_shadows(node),
+                stroke: self.compute_text_stroke(node),
+            });
+        }
+
+        // The arrow is half as wide as its area, centered in it
+        let arrow_size = Size::new(arrow_width / 2., arrow_width / 4.);
+        let arrow_rect = Rect::new(
+            content_rect.x + content_rect.width - (arrow_width + arrow_size.width) / 2.,
+            content_rect.y + (content_rect.height - arrow_size.height) / 2.,
+            arrow_size.width,
+            arrow_size.height,
+        );
+        self.arrows.push(PaintArrow {
+            color,
+            rect: self.to_viewport(arrow_rect),
+            clip: self.clip.clone(),
+        });
+    }
+
+    /// Paint the lines of a textarea that are visible at its scroll position
+    fn build_textarea(&mut self, node: &NodePtr, content_rect: Rect) {
+        let text = textarea::value(node);
+        let scroll_top = textarea::scroll_top(node);
+        let text_layout =
+            TextControlLayout::new(node, &text, content_rect.width, &mut TextMeasure::new());
+        let color = ColorContext::new(&node.get_style(&Property::Color)).current_color;
+
+        for (index, line) in text_layout.lines.iter().enumerate() {
+            let top = index as f32 * text_layout.line_height - scroll_top;
+            // Text is not clipped, so partially visible lines are not painted
+            let is_visible = top >= 0. && top + text_layout.line_height <= content_rect.height;
+            if !is_visible || line.is_empty() {
+                continue;
+            }
+            let line_rect = Rect::new(
+                content_rect.x,
+                content_rect.y + top,
+                content_rect.width,
+                text_layout.line_height,
+            );
+            self.texts.push(PaintText {
+                content: text[line.clone()].to_string(),
+                color: color.clone(),
+                font_size: text_layout.font_size,
+                font: text_layout.font,
+                rect: self.to_viewport(line_rect),
+                is_vertical: false,
+                clip: self.clip.clone(),
+                shadows: self.compute_text_shadows(node),
+                stroke: self.compute_text_stroke(node),
+            });
+        }
+    }
+
+    fn build_marker(&mut self, list_item: &LayoutBoxPtr, marker_rect: Rect) {
+        if !list_item.is_visible() {
+            return;
+        }
+        let marker = list_item.marker().unwrap();
+        let node = list_item.node().unwrap();
+        let color = ColorContext::new(&node.get_style(&Property::Color)).current_color;
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let marker_rect = self.to_viewport(marker_rect);
+
+        match marker.text() {
+            Some(content) => self.texts.push(PaintText {
+                content,
+                color,
+                font_size,
+                font: DEFAULT_FONT,
+                rect: marker_rect,
+                is_vertical: false,
+                clip: self.clip.clone(),
+                shadows: self.compute_text_shadows(&node),
+                stroke: self.compute_text_stroke(&node),
+            }),
+            None => {
+                let bullet_size = marker.bullet_size(font_size);
+                let rect = Rect::new(
+                    marker_rect.x,
+                    marker_rect.y + (marker_rect.height - bullet_size) / 2.,
+                    bullet_size,
+                    bullet_size,
+                );
+                self.markers.push(PaintMarker {
+                    style_type: marker.style_type.clone(),
+                    color,
+                    rect,
+                    clip: self.clip.clone(),
+                });
+            }
+        }
+    }
+
     fn build_paint_box(
         &mut self,
         layout_box: &LayoutBoxPtr,
         override_rect: Option<Rect>,
     ) -> Option<PaintBox> {
-        if layout_box.is_anonymous() {
+        if layout_box.is_anonymous() || !layout_box.is_visible() {
             return None;
         }
 
         let node = layout_box.node().unwrap();
-        let mut rect = override_rect.unwrap_or(layout_box.padding_box_absolute());
-        let background_color = color_from_value(&node.get_style(&Property::BackgroundColor));
+        let padding_rect = self.to_viewport(
+            override_rect
+                .clone()
+                .unwrap_or_else(|| layout_box.padding_box_absolute()),
+        );
+        let mut rect = padding_rect.clone();
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+        let background_color =
+            color_from_value(&node.get_style(&Property::BackgroundColor), &color_context);
 
         if layout_box.is_root_element() {
             self.root_element_use_body_background = {
@@ -156,7 +807,12 @@ impl<'a> RequestBuilder<'a> {
         if layout_box.is_body_element() && self.root_element_use_body_background {
             // Render the canvas for the root element if has been delegated.
             if self.root_element_use_body_background {
-                rect = Rect::new(0., 0., self.canvas_size.width, self.canvas_size.height);
+                rect = Rect::new(
+                    self.canvas_origin.x,
+                    self.canvas_origin.y,
+                    self.canvas_size.width,
+                    self.canvas_size.height,
+                );
             }
         }
 
@@ -169,16 +825,111 @@ impl<'a> RequestBuilder<'a> {
         };
 
         let borders = self.compute_borders(layout_box);
-        let border_rect = layout_box.border_box_absolute();
+        let border_rect = self.border_rect(layout_box, override_rect);
+        let shadows = self.compute_shadows(layout_box, &border_rect, &padding_rect);
 
         Some(PaintBox {
             rect,
             background_color,
             borders,
             border_rect,
+            shadows,
+            clip: self.clip.clone(),
         })
     }
 
+    /// Build the outline of the box. `override_rect` is the padding box of
+    /// boxes that are placed in lines.
+    fn build_outline(&mut self, layout_box: &LayoutBoxPtr, override_rect: Option<Rect>) {
+        if layout_box.is_anonymous() || !layout_box.is_visible() {
+            return;
+        }
+
+        let node = layout_box.node().unwrap();
+        if let Value::BorderStyle(BorderStyle::None) = node.get_style(&Property::OutlineStyle) {
+            return;
+        }
+        let width = node.get_style(&Property::OutlineWidth).to_px(0.);
+        if width <= 0. {
+            return;
+        }
+
+        // The outline is drawn outside of the border box, `outline-offset`
+        // away from its edges
+        let outset = width + node.get_style(&Property::OutlineOffset).to_px(0.);
+        let rect = self
+            .border_rect(layout_box, override_rect)
+            .add_outer_edges(&EdgeSizes::uniform(outset));
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+        let color = color_from_value(&node.get_style(&Property::OutlineColor), &color_context);
+
+        self.outlines.push(PaintOutline {
+            rect,
+            width,
+            color,
+            clip: self.clip.clone(),
+        });
+    }
+
+    /// Build the rules of a multi-column container in the middle of the
+    /// gaps between its columns, above its background and below its
+    /// content. Rules are painted as solid lines.
+    /// https://www.w3.org/TR/css-multicol-1/#column-gaps-and-rules
+    fn build_column_rules(&mut self, layout_box: &LayoutBoxPtr) {
+        let columns = layout_box.columns();
+        if columns.len() < 2 || layout_box.is_anonymous() || !layout_box.is_visible() {
+            return;
+        }
+
+        let node = layout_box.node().unwrap();
+        let width = node.get_style(&Property::ColumnRuleWidth).to_px(0.);
+        if width <= 0. {
+            return;
+        }
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+        let color = color_from_value(&node.get_style(&Property::ColumnRuleColor), &color_context);
+        let location = layout_box.absolute_location();
+
+        for pair in columns.windows(2) {
+            let (before, after) = (&pair[0], &pair[1]);
+            let gap_center = (before.x + before.width + after.x) / 2.;
+            let rect = self.to_viewport(Rect::new(
+                location.x + gap_center - width / 2.,
+                location.y + before.y,
+                width,
+                before.height,
+            ));
+            self.boxes.push(PaintBox {
+                rect: RectOrRRect::Rect(rect.clone()),
+                background_color: color.clone(),
+                borders: PaintBoxBorders {
+                    top: None,
+                    right: None,
+                    bottom: None,
+                    left: None,
+                },
+                border_rect: rect,
+                shadows: Vec::new(),
+                clip: self.clip.clone(),
+            });
+        }
+    }
+
+    fn border_rect(&self, layout_box: &LayoutBoxPtr, override_rect: Option<Rect>) -> Rect {
+        match override_rect {
+            Some(padding_rect) => {
+                let border_box = layout_box.box_model().borrow().border_box();
+                self.to_viewport(padding_rect.add_outer_edges(&border_box))
+            }
+            None => self.to_viewport(layout_box.border_box_absolute()),
+        }
+    }
+
+    fn to_viewport(&self, mut rect: Rect) -> Rect {
+        rect.translate(-self.scroll_offset.x, -self.scroll_offset.y);
+        rect
+    }
+
     fn compute_borders(&self, layout_box: &LayoutBoxPtr) -> PaintBoxBorders {
         if layout_box.is_anonymous() {
             return PaintBoxBorders {
@@ -189,14 +940,15 @@ impl<'a> RequestBuilder<'a> {
             };
         }
         let node = layout_box.node().unwrap();
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
 
         macro_rules! compute_border {
             ($style:ident, $color:ident) => {
                 match node.get_style(&Property::$style) {
-                    Value::BorderStyle(BorderStyle::None) => None,
+                    Value::BorderStyle(BorderStyle::None | BorderStyle::Hidden) => None,
                     Value::BorderStyle(style) => Some(PaintBoxBorder {
-                        color: color_from_value(&node.get_style(&Property::$color)),
                         style,
+                        color: color_from_value(&node.get_style(&Property::$color), &color_context),
                     }),
                     _ => None,
                 }
@@ -211,6 +963,83 @@ impl<'a> RequestBuilder<'a> {
         }
     }
 
+    /// Compute the shadows of `box-shadow`. The shadows have square
+    /// corners, only the box they are clipped to is rounded.
+    fn compute_shadows(
+        &self,
+        layout_box: &LayoutBoxPtr,
+        border_rect: &Rect,
+        padding_rect: &Rect,
+    ) -> Vec<PaintShadow> {
+        let node = layout_box.node().unwrap();
+        let shadows = match node.get_style(&Property::BoxShadow) {
+            Value::BoxShadow(BoxShadow(shadows)) => shadows,
+            _ => return Vec::new(),
+        };
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+
+        shadows
+            .iter()
+            .rev()
+            .map(|shadow| {
+                // The spread grows outer shadows and shrinks inner shadows
+                let spread = shadow.spread_distance.resolve(font_size);
+                let mut rect = match shadow.inset {
+                    true => padding_rect.add_outer_edges(&EdgeSizes::uniform(-spread)),
+                    false => border_rect.add_outer_edges(&EdgeSizes::uniform(spread)),
+                };
+                rect.width = f32::max(rect.width, 0.);
+                rect.height = f32::max(rect.height, 0.);
+                rect.translate(
+                    shadow.offset_x.resolve(font_size),
+                    shadow.offset_y.resolve(font_size),
+                );
+
+                PaintShadow {
+                    rect,
+                    blur: shadow.blur_radius.resolve(font_size),
+                    color: color_from_value(&Value::Color(shadow.color.clone()), &color_context),
+                    inset: shadow.inset,
+                }
+            })
+            .collect()
+    }
+
+    /// Compute the shadows of `text-shadow`, relative to the text
+    fn compute_text_shadows(&self, node: &NodePtr) -> Vec<PaintTextShadow> {
+        let shadows = match node.get_style(&Property::TextShadow) {
+            Value::TextShadow(TextShadow(shadows)) => shadows,
+            _ => return Vec::new(),
+        };
+        let font_size = node.get_style(&Property::FontSize).to_absolute_px();
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+
+        shadows
+            .iter()
+            .rev()
+            .map(|shadow| PaintTextShadow {
+                offset: Point::new(
+                    shadow.offset_x.resolve(font_size),
+                    shadow.offset_y.resolve(font_size),
+                ),
+                blur: shadow.blur_radius.resolve(font_size),
+                color: color_from_value(&Value::Color(shadow.color.clone()), &color_context),
+            })
+            .filter(|shadow| shadow.color.a > 0)
+            .collect()
+    }
+
+    fn compute_text_stroke(&self, node: &NodePtr) -> Option<PaintTextStroke> {
+        let width = node.get_style(&Property::TextStrokeWidth).to_px(0.);
+        if width <= 0. {
+            return None;
+        }
+        let color_context = ColorContext::new(&node.get_style(&Property::Color));
+        let color = color_from_value(&node.get_style(&Property::TextStrokeColor), &color_context);
+        Some(PaintTextStroke { width, color })
+    }
+
     fn compute_border_radius_corner(&self, layout_box: &LayoutBoxPtr) -> Option<Corners> {
         if layout_box.is_anonymous() {
             return None;
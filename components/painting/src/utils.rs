@@ -1,6 +1,9 @@
 use shared::{color::Color, primitive::Radii};
 use style_types::{
-    values::prelude::{BorderRadius, LengthPercentage},
+    values::{
+        color::Color as CSSColor,
+        prelude::{BorderRadius, LengthPercentage},
+    },
     Value,
 };
 
@@ -13,8 +16,26 @@ pub fn is_zero(value: &Value) -> bool {
     }
 }
 
-pub fn color_from_value(color: &Value) -> Color {
+/// Context that color values of an element are resolved in
+pub struct ColorContext {
+    /// Used value of the element's `color`, which `currentColor` resolves to
+    pub current_color: Color,
+}
+
+impl ColorContext {
+    /// Create the context from the computed `color` of the element
+    pub fn new(color: &Value) -> Self {
+        let current_color = match color {
+            Value::Color(c) => c.into(),
+            _ => Color::default(),
+        };
+        Self { current_color }
+    }
+}
+
+pub fn color_from_value(color: &Value, context: &ColorContext) -> Color {
     match color {
+        Value::Color(CSSColor::CurrentColor) => context.current_color.clone(),
         Value::Color(c) => c.into(),
         _ => Color {
             r: 0,
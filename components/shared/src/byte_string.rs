@@ -1,6 +1,11 @@
 use std::str::Chars;
 
-use encoding::{all::UTF_8, decode};
+use encoding::label::encoding_from_whatwg_label;
+use encoding::{all::UTF_8, decode, DecoderTrap, EncodingRef};
+
+/// Number of bytes of a document searched for its `<meta charset>`
+/// https://html.spec.whatwg.org/multipage/parsing.html#prescan-a-byte-stream-to-determine-its-encoding
+const PRESCAN_LENGTH: usize = 1024;
 
 pub struct ByteString {
     content: String,
@@ -21,6 +26,20 @@ impl ByteString {
         }
     }
 
+    /// Decode an HTML document with the encoding of its byte order mark or
+    /// of the `<meta charset>` at its start, and UTF-8 otherwise
+    pub fn from_html(bytes: &[u8]) -> Self {
+        let encoding = match prescan_charset(&bytes[..bytes.len().min(PRESCAN_LENGTH)]) {
+            Some(encoding) => encoding,
+            None => return Self::new(bytes),
+        };
+        // A byte order mark has precedence over the meta
+        match decode(bytes, DecoderTrap::Replace, encoding) {
+            (Ok(content), _) => Self { content },
+            (Err(_), _) => Self::new(bytes),
+        }
+    }
+
     pub fn chars(&self) -> Chars {
         self.content.chars()
     }
@@ -29,3 +48,123 @@ impl ByteString {
         self.content.clone()
     }
 }
+
+/// Encoding declared by the first `<meta charset>` or
+/// `<meta http-equiv=content-type>`. Comments aren't skipped, and the
+/// encoding of a meta in a comment is used too.
+fn prescan_charset(bytes: &[u8]) -> Option<EncodingRef> {
+    let lowercase = bytes.to_ascii_lowercase();
+    let mut position = 0;
+    while let Some(start) = find(&lowercase[position..], b"<meta") {
+        position += start + b"<meta".len();
+        let is_tag = lowercase
+            .get(position)
+            .is_some_and(|byte| byte.is_ascii_whitespace() || *byte == b'/');
+        if !is_tag {
+            continue;
+        }
+        let attributes = meta_attributes(&bytes[position..]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let label = match (attribute("charset"), attribute("http-equiv")) {
+            (Some(charset), _) => Some(charset.to_string()),
+            (None, Some(http_equiv)) if http_equiv.eq_ignore_ascii_case("content-type") => {
+                attribute("content").and_then(charset_of_content_type)
+            }
+            _ => None,
+        };
+        if let Some(encoding) = label.as_deref().and_then(encoding_from_whatwg_label) {
+            // Bytes read as ASCII to find the meta can't be UTF-16
+            return match encoding.whatwg_name() {
+                Some("utf-16le" | "utf-16be") => Some(UTF_8),
+                _ => Some(encoding),
+            };
+        }
+    }
+    None
+}
+
+/// Attributes of a tag until its `>`, with lowercase names
+fn meta_attributes(bytes: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut attributes = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars
+            .peek()
+            .is_some_and(|c| c.is_ascii_whitespace() || *c == '/')
+        {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace() && !"=>/".contains(*c)) {
+            name.push(c.to_ascii_lowercase());
+        }
+        if name.is_empty() {
+            return attributes;
+        }
+        while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+            match chars.next_if(|c| *c == '"' || *c == '\'') {
+                Some(quote) => value.extend(chars.by_ref().take_while(|c| *c != quote)),
+                None => {
+                    while let Some(c) = chars.next_if(|c| !c.is_ascii_whitespace() && *c != '>') {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+        attributes.push((name, value));
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/urls-and-fetching.html#algorithm-for-extracting-a-character-encoding-from-a-meta-element
+fn charset_of_content_type(content: &str) -> Option<String> {
+    let content = content.to_ascii_lowercase();
+    let start = content.find("charset")? + "charset".len();
+    let value = content[start..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value
+            .split(|c: char| c.is_ascii_whitespace() || c == ';')
+            .next()?,
+    };
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_html_with_meta_charset() {
+        // "café" in windows-1252
+        let html = b"<html><head><META CHARSET='windows-1252'></head>caf\xe9";
+        assert!(ByteString::from_html(html)
+            .to_string()
+            .ends_with("caf\u{e9}"));
+
+        let html = b"<meta http-equiv=Content-Type content=\"text/html; charset=ISO-8859-1\">\xe9";
+        assert!(ByteString::from_html(html).to_string().ends_with('\u{e9}'));
+
+        let html = "<meta charset=utf-16><metadata>é".as_bytes();
+        assert!(ByteString::from_html(html).to_string().ends_with('é'));
+        let html = "<metadata charset=latin1>é".as_bytes();
+        assert!(ByteString::from_html(html).to_string().ends_with('é'));
+    }
+}
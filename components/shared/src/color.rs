@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -29,3 +29,98 @@ impl Into<[f32; 4]> for Color {
         ]
     }
 }
+
+impl Color {
+    pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Convert an HSL color with the hue in degrees and the other components
+    /// between 0 and 1
+    pub fn from_hsla(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        let (r, g, b) = hsl_to_rgb(hue, saturation, lightness);
+        let to_u8 = |value: f32| (value.clamp(0., 1.) * 255.).round() as u8;
+        Self::new(to_u8(r), to_u8(g), to_u8(b), to_u8(alpha))
+    }
+
+    /// Components between 0 and 1, with the RGB channels converted from
+    /// sRGB, the color space of CSS colors, to linear light. Blending in
+    /// sRGB render targets happens in linear light.
+    pub fn to_linear_rgba(&self) -> [f32; 4] {
+        let channel = |value: u8| srgb_to_linear(value as f32 / 255.);
+        [
+            channel(self.r),
+            channel(self.g),
+            channel(self.b),
+            self.a as f32 / 255.,
+        ]
+    }
+}
+
+/// Linear light value of an sRGB encoded channel, both between 0 and 1
+/// https://www.w3.org/TR/css-color-4/#color-conversion-code
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// sRGB encoded value of a linear light channel, both between 0 and 1
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Convert HSL to RGB components between 0 and 1. The hue is in degrees,
+/// saturation and lightness are between 0 and 1.
+/// https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+pub fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (f32, f32, f32) {
+    let hue = hue.rem_euclid(360.);
+    let saturation = saturation.clamp(0., 1.);
+    let lightness = lightness.clamp(0., 1.);
+
+    let channel = |n: f32| {
+        let k = (n + hue / 30.) % 12.;
+        let a = saturation * f32::min(lightness, 1. - lightness);
+        lightness - a * f32::min(k - 3., 9. - k).clamp(-1., 1.)
+    };
+
+    (channel(0.), channel(8.), channel(4.))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(color: Color) -> (u8, u8, u8, u8) {
+        (color.r, color.g, color.b, color.a)
+    }
+
+    #[test]
+    fn test_hsl_to_rgb() {
+        assert_eq!(rgb(Color::from_hsla(0., 1., 0.5, 1.)), (255, 0, 0, 255));
+        assert_eq!(rgb(Color::from_hsla(120., 1., 0.25, 1.)), (0, 128, 0, 255));
+        assert_eq!(rgb(Color::from_hsla(-120., 1., 0.5, 0.5)), (0, 0, 255, 128));
+        assert_eq!(
+            rgb(Color::from_hsla(200., 0., 1., 1.)),
+            (255, 255, 255, 255)
+        );
+    }
+
+    #[test]
+    fn test_srgb_transfer() {
+        assert_eq!(
+            Color::new(0, 255, 0, 51).to_linear_rgba(),
+            [0., 1., 0., 0.2]
+        );
+        // Mid gray in sRGB is about a fifth of the light of white
+        let gray = srgb_to_linear(0.5);
+        assert!((gray - 0.214).abs() < 0.001);
+        assert!((linear_to_srgb(gray) - 0.5).abs() < 0.0001);
+    }
+}
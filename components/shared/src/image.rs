@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+/// Pixels of an image in RGBA8 without premultiplied alpha, row by row from
+/// the top left corner. The pixels are shared, so cloning a bitmap is cheap
+/// and clones are the same image to the caches of the painters.
+#[derive(Debug, Clone)]
+pub struct ImageBitmap {
+    width: u32,
+    height: u32,
+    data: Arc<Vec<u8>>,
+}
+
+impl ImageBitmap {
+    /// `None` if the image is empty or `data` doesn't hold exactly 4 bytes
+    /// per pixel
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> Option<Self> {
+        let expected_len = width as usize * height as usize * 4;
+        if expected_len == 0 || data.len() != expected_len {
+            return None;
+        }
+        Some(Self {
+            width,
+            height,
+            data: Arc::new(data),
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Whether both bitmaps share their pixels
+    pub fn ptr_eq(&self, other: &ImageBitmap) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
+    /// Identifies the pixels as long as a bitmap sharing them is alive
+    pub fn key(&self) -> usize {
+        Arc::as_ptr(&self.data) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rgba_checks_the_data_length() {
+        assert!(ImageBitmap::from_rgba(2, 1, vec![0; 8]).is_some());
+        assert!(ImageBitmap::from_rgba(2, 1, vec![0; 6]).is_none());
+        assert!(ImageBitmap::from_rgba(0, 1, Vec::new()).is_none());
+
+        let bitmap = ImageBitmap::from_rgba(1, 1, vec![0; 4]).unwrap();
+        assert!(bitmap.ptr_eq(&bitmap.clone()));
+        assert!(!bitmap.ptr_eq(&ImageBitmap::from_rgba(1, 1, vec![0; 4]).unwrap()));
+    }
+}
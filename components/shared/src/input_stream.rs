@@ -35,6 +35,16 @@ where
         }
     }
 
+    /// Make sure the buffer holds at least `n` items, if the source has them
+    fn fill_buffer(&mut self, n: usize) {
+        while self.buffer.len() < n {
+            match self.source.next() {
+                Some(item) => self.buffer.push_back(item),
+                None => break,
+            }
+        }
+    }
+
     pub fn next(&mut self) -> Option<I> {
         let is_reconsume = self.is_reconsume;
         self.is_reconsume = false;
@@ -61,7 +71,7 @@ where
     }
 
     pub fn peek_next(&mut self, n: usize) -> Option<Vec<I>> {
-        self.consume_source_to_buffer();
+        self.fill_buffer(n);
 
         if self.buffer.len() < n {
             return None;
@@ -86,7 +96,7 @@ where
     }
 
     pub fn peek_next_as<S: FromIterator<I>>(&mut self, n: usize) -> Option<S> {
-        self.consume_source_to_buffer();
+        self.fill_buffer(n);
 
         if self.buffer.len() < n {
             return None;
@@ -1,6 +1,9 @@
 pub mod byte_string;
+pub mod cancellation;
 pub mod color;
 pub mod data_stream;
+pub mod image;
 pub mod input_stream;
+pub mod memory;
 pub mod primitive;
 pub mod tree_node;
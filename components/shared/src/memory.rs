@@ -0,0 +1,21 @@
+use std::ops::AddAssign;
+
+/// Number of objects of a kind and the bytes they hold
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+impl MemoryUsage {
+    pub fn new(count: usize, bytes: usize) -> Self {
+        Self { count, bytes }
+    }
+}
+
+impl AddAssign for MemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
@@ -25,6 +25,16 @@ impl Default for EdgeSizes {
 }
 
 impl EdgeSizes {
+    /// Edges that all have the same size
+    pub fn uniform(size: f32) -> Self {
+        Self {
+            top: size,
+            right: size,
+            bottom: size,
+            left: size,
+        }
+    }
+
     pub fn add_edge_sizes(&self, other: &EdgeSizes) -> Self {
         Self {
             top: self.top + other.top,
@@ -24,6 +24,25 @@ impl Rect {
         self.y += dy;
     }
 
+    /// Check if the point is inside the rect. The right and bottom edges are
+    /// exclusive so that adjacent rects don't overlap.
+    pub fn contains(&self, point: &Point) -> bool {
+        point.x >= self.x
+            && point.x < self.x + self.width
+            && point.y >= self.y
+            && point.y < self.y + self.height
+    }
+
+    /// The part of the rect inside of `other`, empty at the edge of
+    /// `other` when they don't overlap
+    pub fn intersection(&self, other: &Rect) -> Rect {
+        let left = self.x.clamp(other.x, other.x + other.width);
+        let top = self.y.clamp(other.y, other.y + other.height);
+        let right = (self.x + self.width).clamp(left, other.x + other.width);
+        let bottom = (self.y + self.height).clamp(top, other.y + other.height);
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
     pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
         Self {
             x,
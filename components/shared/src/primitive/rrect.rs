@@ -1,13 +1,13 @@
 use super::Rect;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RRect {
     pub rect: Rect,
     pub corners: Corners,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Corners {
     pub top_left: Radii,
     pub top_right: Radii,
@@ -15,7 +15,7 @@ pub struct Corners {
     pub bottom_right: Radii,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Radii(f32, f32);
 
 impl RRect {
@@ -1,5 +1,6 @@
 use std::{
     cell::RefCell,
+    collections::HashMap,
     fmt::Debug,
     ops::Deref,
     rc::{Rc, Weak},
@@ -15,6 +16,16 @@ pub type NullableNode<T> = Option<TreeNode<T>>;
 pub trait TreeNodeHooks<T: TreeNodeHooks<T> + Debug> {
     fn on_inserted(&self, current: TreeNode<T>, parent: TreeNode<T>) {}
     fn on_children_updated(&self, current: TreeNode<T>) {}
+    /// Called after the node is detached from its parent
+    fn on_removed(&self, current: TreeNode<T>, parent: TreeNode<T>) {}
+    /// Called before the node is torn down, to release the references the
+    /// data holds to other nodes
+    fn on_teardown(&self) {}
+}
+
+thread_local! {
+    /// Number of nodes alive on the thread, per type of data
+    static LIVE_NODES: RefCell<HashMap<&'static str, usize>> = RefCell::new(HashMap::new());
 }
 
 pub struct Node<T: TreeNodeHooks<T> + Debug> {
@@ -39,11 +50,39 @@ impl<T: TreeNodeHooks<T> + Debug> Node<T> {
     }
 }
 
+impl<T: TreeNodeHooks<T> + Debug> Drop for Node<T> {
+    fn drop(&mut self) {
+        LIVE_NODES.with(|counts| {
+            if let Some(count) = counts.borrow_mut().get_mut(std::any::type_name::<T>()) {
+                *count -= 1;
+            }
+        });
+    }
+}
+
 impl<T: TreeNodeHooks<T> + Debug> TreeNode<T> {
     pub fn new(data: T) -> Self {
+        LIVE_NODES.with(|counts| {
+            *counts
+                .borrow_mut()
+                .entry(std::any::type_name::<T>())
+                .or_default() += 1;
+        });
         Self(Rc::new(Node::new(data)))
     }
 
+    /// Number of nodes of this type alive on the current thread, to find
+    /// trees that are never freed
+    pub fn live_count() -> usize {
+        LIVE_NODES.with(|counts| {
+            counts
+                .borrow()
+                .get(std::any::type_name::<T>())
+                .copied()
+                .unwrap_or(0)
+        })
+    }
+
     /// First child of the node
     pub fn first_child(&self) -> NullableNode<T> {
         self.first_child.borrow().clone()
@@ -85,13 +124,15 @@ impl<T: TreeNodeHooks<T> + Debug> TreeNode<T> {
                 .prev_sibling
                 .replace(self.prev_sibling.borrow().clone());
         }
-        if let Some(parent) = self.parent() {
+        let parent = self.parent();
+        if let Some(parent) = &parent {
             let first_child = parent.first_child().unwrap();
             let last_child = parent.last_child().unwrap();
 
             if Rc::ptr_eq(&self, &first_child) {
                 parent.first_child.replace(self.next_sibling());
-            } else if Rc::ptr_eq(&self, &last_child) {
+            }
+            if Rc::ptr_eq(&self, &last_child) {
                 parent.last_child.replace(self.prev_sibling());
             }
         }
@@ -99,6 +140,26 @@ impl<T: TreeNodeHooks<T> + Debug> TreeNode<T> {
         self.parent_node.replace(None);
         self.prev_sibling.replace(None);
         self.next_sibling.replace(None);
+
+        if let Some(parent) = parent {
+            self.data.on_removed(self.clone(), parent);
+        }
+    }
+
+    /// Unlink the node and its descendants from each other and release the
+    /// references their data holds, so that every node of the subtree is
+    /// freed once the last outside reference to it is dropped, even if the
+    /// data of the nodes refer to each other.
+    pub fn teardown(&self) {
+        self.data.on_teardown();
+        self.last_child.replace(None);
+        let mut maybe_child = self.first_child.take();
+        while let Some(child) = maybe_child {
+            maybe_child = child.next_sibling.take();
+            child.parent_node.replace(None);
+            child.prev_sibling.replace(None);
+            child.teardown();
+        }
     }
 
     pub fn for_each_child<F>(&self, mut callback: F)
@@ -240,6 +301,10 @@ impl<T: TreeNodeHooks<T> + Debug> TreeNode<T> {
                 .prev_sibling
                 .replace(Some(WeakTreeNode::from(child.clone())));
             child.next_sibling.replace(Some(ref_child));
+
+            // trigger hook callback
+            child.data.on_inserted(child.clone(), self.clone());
+            self.data.on_children_updated(self.clone());
         } else {
             self.append_child(child);
         }
@@ -468,6 +533,52 @@ mod test {
         assert_node_eq(child.parent(), Some(new_parent.clone()));
     }
 
+    #[test]
+    fn detach_only_child() {
+        let parent = TreeNode::new(TestNode);
+        let child = TreeNode::new(TestNode);
+
+        parent.append_child(child.clone());
+        child.detach();
+
+        assert_node_eq(parent.first_child(), None);
+        assert_node_eq(parent.last_child(), None);
+    }
+
+    #[derive(Debug)]
+    pub struct CyclicNode {
+        other: RefCell<Option<TreeNode<CyclicNode>>>,
+    }
+    impl TreeNodeHooks<CyclicNode> for CyclicNode {
+        fn on_teardown(&self) {
+            self.other.replace(None);
+        }
+    }
+
+    #[test]
+    fn teardown() {
+        let parent = TreeNode::new(CyclicNode {
+            other: RefCell::new(None),
+        });
+        let child = TreeNode::new(CyclicNode {
+            other: RefCell::new(None),
+        });
+
+        parent.append_child(child.clone());
+        parent.append_child(TreeNode::new(CyclicNode {
+            other: RefCell::new(None),
+        }));
+        // The child holds its parent, which holds the child
+        child.other.replace(Some(parent.clone()));
+        drop(child);
+        assert_eq!(TreeNode::<CyclicNode>::live_count(), 3);
+
+        parent.teardown();
+        assert!(parent.has_no_child());
+        drop(parent);
+        assert_eq!(TreeNode::<CyclicNode>::live_count(), 0);
+    }
+
     #[test]
     fn for_each_child() {
         let parent = TreeNode::new(TestNode);
@@ -0,0 +1,183 @@
+//! Bloom filter of the tag names, ids and classes of the ancestors of the
+//! element being styled. Selectors requiring an ancestor that is missing
+//! from the filter are rejected without walking up the tree.
+//! https://doc.servo.org/selectors/bloom/index.html
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use css::selector::structs::{Combinator, Selector, SimpleSelectorType};
+use dom::node::NodePtr;
+
+/// Number of bits of a hash used to index the counters
+const KEY_BITS: u32 = 12;
+const KEY_MASK: u32 = (1 << KEY_BITS) - 1;
+const COUNTERS: usize = 1 << KEY_BITS;
+
+/// Counting bloom filter, so that the ancestors can be removed when the
+/// traversal leaves them. Two counters are set for each key, taken from the
+/// low and high bits of its hash.
+pub struct AncestorFilter {
+    counters: Box<[u8; COUNTERS]>,
+    /// Keys added for each ancestor, from the root
+    ancestors: Vec<Vec<u32>>,
+}
+
+impl AncestorFilter {
+    pub fn new() -> Self {
+        Self {
+            counters: Box::new([0; COUNTERS]),
+            ancestors: Vec::new(),
+        }
+    }
+
+    /// Add the node as the parent of the next nodes to be styled. Nodes
+    /// that aren't elements are pushed too so that pushes and pops pair up.
+    pub fn push(&mut self, node: &NodePtr) {
+        let keys = element_keys(node);
+        for key in &keys {
+            self.adjust(*key, |counter| counter.saturating_add(1));
+        }
+        self.ancestors.push(keys);
+    }
+
+    /// Remove the last node pushed, once its children are styled
+    pub fn pop(&mut self) {
+        if let Some(keys) = self.ancestors.pop() {
+            for key in keys {
+                self.adjust(key, |counter| counter.saturating_sub(1));
+            }
+        }
+    }
+
+    /// Check if the selector requires an ancestor tag name, id or class
+    /// that none of the ancestors has. False positives are possible, so a
+    /// selector that isn't rejected still has to be matched.
+    pub fn rejects(&self, selector: &Selector) -> bool {
+        selector
+            .values()
+            .iter()
+            .filter(|(_, combinator)| {
+                matches!(
+                    combinator,
+                    Some(Combinator::Descendant) | Some(Combinator::Child)
+                )
+            })
+            .flat_map(|(sequence, _)| sequence.values())
+            .filter_map(|simple_selector| {
+                let value = simple_selector.value().as_deref()?;
+                match simple_selector.selector_type() {
+                    SimpleSelectorType::Type => {
+                        Some(key(KeyKind::Tag, &value.to_ascii_lowercase()))
+                    }
+                    SimpleSelectorType::ID => Some(key(KeyKind::Id, value)),
+                    SimpleSelectorType::Class => Some(key(KeyKind::Class, value)),
+                    _ => None,
+                }
+            })
+            .any(|key| !self.might_contain(key))
+    }
+
+    fn might_contain(&self, key: u32) -> bool {
+        self.counters[first_slot(key)] != 0 && self.counters[second_slot(key)] != 0
+    }
+
+    fn adjust(&mut self, key: u32, f: impl Fn(u8) -> u8) {
+        // A saturated counter is never decremented since it may be shared
+        // by more ancestors than it can count
+        for slot in [first_slot(key), second_slot(key)] {
+            if self.counters[slot] != u8::MAX {
+                self.counters[slot] = f(self.counters[slot]);
+            }
+        }
+    }
+}
+
+#[derive(Hash)]
+enum KeyKind {
+    Tag,
+    Id,
+    Class,
+}
+
+fn key(kind: KeyKind, value: &str) -> u32 {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    value.hash(&mut hasher);
+    hasher.finish() as u32
+}
+
+fn first_slot(key: u32) -> usize {
+    (key & KEY_MASK) as usize
+}
+
+fn second_slot(key: u32) -> usize {
+    ((key >> KEY_BITS) & KEY_MASK) as usize
+}
+
+fn element_keys(node: &NodePtr) -> Vec<u32> {
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return Vec::new(),
+    };
+    // Tag names are lowercased since HTML type selectors ignore case
+    let mut keys = vec![key(KeyKind::Tag, &element.tag_name().to_ascii_lowercase())];
+    if let Some(id) = element.id() {
+        keys.push(key(KeyKind::Id, &id));
+    }
+    let class_list = element.class_list();
+    let class_list = class_list.borrow();
+    keys.extend((0..class_list.length()).filter_map(|index| {
+        class_list
+            .item(index)
+            .map(|class| key(KeyKind::Class, &class))
+    }));
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use css::cssom::css_rule::CSSRule;
+    use css::parser::Parser;
+    use css::tokenizer::token::Token;
+    use css::tokenizer::Tokenizer;
+    use dom::create_element;
+    use shared::tree_node::WeakTreeNode;
+    use test_utils::dom_creator::document;
+
+    fn selector(css: &str) -> Selector {
+        let tokens = Tokenizer::new(css.chars()).run();
+        let stylesheet = Parser::<Token>::new(tokens).parse_a_css_stylesheet();
+        match stylesheet.first().unwrap() {
+            CSSRule::Style(style) => style.selectors[0].clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn reject_missing_ancestors() {
+        let doc = document();
+        let article = create_element(WeakTreeNode::from(&doc.0), "article");
+        article.as_element().set_attribute("class", "post featured");
+        let section = create_element(WeakTreeNode::from(&doc.0), "section");
+        section.as_element().set_attribute("id", "main");
+
+        let mut filter = AncestorFilter::new();
+        filter.push(&doc);
+        filter.push(&article);
+        filter.push(&section);
+
+        assert!(!filter.rejects(&selector("article p { color: red; }")));
+        assert!(!filter.rejects(&selector(".featured > #main p { color: red; }")));
+        assert!(!filter.rejects(&selector("nav + p { color: red; }")));
+        assert!(filter.rejects(&selector("nav p { color: red; }")));
+        assert!(filter.rejects(&selector("article.draft p { color: red; }")));
+
+        filter.pop();
+        assert!(filter.rejects(&selector("#main p { color: red; }")));
+        assert!(!filter.rejects(&selector(".post p { color: red; }")));
+    }
+}
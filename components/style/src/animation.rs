@@ -0,0 +1,414 @@
+use std::collections::{HashMap, HashSet};
+
+use css::cssom::keyframes_rule::KeyframesRule;
+use dom::node::NodePtr;
+use style_types::{
+    values::prelude::{
+        AnimationDirection, AnimationFillMode, AnimationName, Color, IterationCount, Length,
+        Percentage, TimingFunction,
+    },
+    Property, Value,
+};
+
+use crate::cascade::parse_declaration;
+
+/// Timing of the animation of an element, from its `animation-*` properties
+/// https://www.w3.org/TR/css-animations-1/#animations
+struct AnimationTiming {
+    /// Duration of an iteration, in milliseconds
+    duration: f64,
+    delay: f64,
+    iterations: f64,
+    direction: AnimationDirection,
+    fill_mode: AnimationFillMode,
+}
+
+/// Replace the values of the properties animated by the `@keyframes` rule
+/// named by `animation-name` with their values at the current animation
+/// time of the document. Important declarations take precedence over the
+/// animations.
+pub fn apply_animations(
+    node: &NodePtr,
+    parent: &Option<NodePtr>,
+    important: &HashSet<Property>,
+    styles: &mut HashMap<Property, Value>,
+) {
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return,
+    };
+    let document = match node.owner_document() {
+        Some(document) => NodePtr(document),
+        None => return,
+    };
+    let document = document.as_document();
+    let keyframes = match styles.get(&Property::AnimationName) {
+        Some(Value::AnimationName(AnimationName::Name(name))) => document
+            .keyframes(name)
+            .map(|keyframes| (name.clone(), keyframes)),
+        _ => None,
+    };
+    let (name, keyframes) = match keyframes {
+        Some(keyframes) => keyframes,
+        None => {
+            element.cancel_animation();
+            return;
+        }
+    };
+
+    let now = document.animation_time();
+    let start = element.animation_start(&name, now);
+    let timing = AnimationTiming::from_styles(styles);
+    let elapsed = now - start;
+    if elapsed < timing.delay + timing.active_duration() {
+        document.set_has_running_animations(true);
+    }
+    let progress = match timing.progress(elapsed) {
+        Some(progress) => progress,
+        None => return,
+    };
+
+    let timing_function = match styles.get(&Property::AnimationTimingFunction) {
+        Some(Value::TimingFunction(timing_function)) => timing_function.clone(),
+        _ => TimingFunction::Linear,
+    };
+    for (property, frames) in property_keyframes(&keyframes, &timing_function) {
+        if important.contains(&property) {
+            continue;
+        }
+        let underlying = match styles.get(&property) {
+            Some(value) => value.clone(),
+            None => Value::initial(&property),
+        };
+        let resolve = |value: Option<&Value>| match value {
+            Some(Value::Inherit) => match parent {
+                Some(parent) => parent.get_style(&property),
+                None => Value::initial(&property),
+            },
+            Some(Value::Initial) => Value::initial(&property),
+            Some(Value::Unset) if property.inheritable() => match parent {
+                Some(parent) => parent.get_style(&property),
+                None => Value::initial(&property),
+            },
+            Some(Value::Unset) => Value::initial(&property),
+            Some(value) => value.clone(),
+            // Keyframes missing at the start or the end of the animation
+            // use the value the property would have without it
+            None => underlying.clone(),
+        };
+
+        // The segment between the last keyframe at or before the progress
+        // and the one after it
+        let from = frames.iter().rposition(|(offset, ..)| *offset <= progress);
+        let value = match from {
+            None => {
+                let (offset, value, _) = &frames[0];
+                let local = timing_function.ease(progress / offset);
+                interpolate(&resolve(None), &resolve(Some(value)), local)
+            }
+            Some(index) if index + 1 < frames.len() => {
+                let (from_offset, from, timing_function) = &frames[index];
+                let (to_offset, to, _) = &frames[index + 1];
+                let local =
+                    timing_function.ease((progress - from_offset) / (to_offset - from_offset));
+                interpolate(&resolve(Some(from)), &resolve(Some(to)), local)
+            }
+            Some(index) => {
+                let (offset, value, timing_function) = &frames[index];
+                if *offset >= 1. {
+                    resolve(Some(value))
+                } else {
+                    let local = timing_function.ease((progress - offset) / (1. - offset));
+                    interpolate(&resolve(Some(value)), &resolve(None), local)
+                }
+            }
+        };
+        styles.insert(property, value);
+    }
+}
+
+impl AnimationTiming {
+    fn from_styles(styles: &HashMap<Property, Value>) -> Self {
+        let time = |property: &Property| match styles.get(property) {
+            Some(Value::Time(time)) => time.to_ms() as f64,
+            _ => 0.,
+        };
+        let iterations = match styles.get(&Property::AnimationIterationCount) {
+            Some(Value::IterationCount(IterationCount::Infinite)) => f64::INFINITY,
+            Some(Value::IterationCount(IterationCount::Count(count))) => **count as f64,
+            _ => 1.,
+        };
+        let direction = match styles.get(&Property::AnimationDirection) {
+            Some(Value::AnimationDirection(direction)) => direction.clone(),
+            _ => AnimationDirection::Normal,
+        };
+        let fill_mode = match styles.get(&Property::AnimationFillMode) {
+            Some(Value::AnimationFillMode(fill_mode)) => fill_mode.clone(),
+            _ => AnimationFillMode::None,
+        };
+        Self {
+            duration: time(&Property::AnimationDuration).max(0.),
+            delay: time(&Property::AnimationDelay),
+            iterations,
+            direction,
+            fill_mode,
+        }
+    }
+
+    fn active_duration(&self) -> f64 {
+        // An animation without a duration ends right away, however many
+        // iterations it has
+        if self.duration == 0. || self.iterations == 0. {
+            return 0.;
+        }
+        self.duration * self.iterations
+    }
+
+    /// Progress through the keyframes `elapsed` milliseconds after the
+    /// animation started, `None` when the animation doesn't apply
+    /// https://www.w3.org/TR/web-animations-1/#calculating-the-directed-progress
+    fn progress(&self, elapsed: f64) -> Option<f32> {
+        let active_time = elapsed - self.delay;
+        let (iteration, progress) = if active_time < 0. {
+            if !matches!(
+                self.fill_mode,
+                AnimationFillMode::Backwards | AnimationFillMode::Both
+            ) {
+                return None;
+            }
+            (0., 0.)
+        } else if active_time >= self.active_duration() {
+            if !matches!(
+                self.fill_mode,
+                AnimationFillMode::Forwards | AnimationFillMode::Both
+            ) {
+                return None;
+            }
+            // An animation that ends at the end of an iteration stays at
+            // the end of that iteration instead of the start of the next
+            let iterations = self.iterations;
+            match iterations.fract() == 0. && iterations > 0. {
+                true => (iterations - 1., 1.),
+                false => (iterations.floor(), iterations.fract()),
+            }
+        } else {
+            let iteration = (active_time / self.duration).floor();
+            (iteration, active_time / self.duration - iteration)
+        };
+
+        let is_reversed = match self.direction {
+            AnimationDirection::Normal => false,
+            AnimationDirection::Reverse => true,
+            AnimationDirection::Alternate => iteration % 2. == 1.,
+            AnimationDirection::AlternateReverse => iteration % 2. == 0.,
+        };
+        let progress = progress as f32;
+        Some(if is_reversed { 1. - progress } else { progress })
+    }
+}
+
+/// The keyframes of each property animated by the rule, with their offset
+/// and the timing function of the segment that starts at them
+fn property_keyframes(
+    keyframes: &KeyframesRule,
+    timing_function: &TimingFunction,
+) -> HashMap<Property, Vec<(f32, Value, TimingFunction)>> {
+    let mut result: HashMap<Property, Vec<(f32, Value, TimingFunction)>> = HashMap::new();
+    for keyframe in &keyframes.keyframes {
+        let values = keyframe
+            .declarations
+            .iter()
+            // Important declarations are ignored in keyframes
+            .filter(|declaration| !declaration.important)
+            .flat_map(parse_declaration)
+            .collect::<Vec<_>>();
+        let keyframe_timing_function = values
+            .iter()
+            .find_map(|(property, value)| match (property, value) {
+                (Property::AnimationTimingFunction, Value::TimingFunction(timing_function)) => {
+                    Some(timing_function.clone())
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| timing_function.clone());
+
+        for (property, value) in values {
+            if is_animation_property(&property) {
+                continue;
+            }
+            let frames = result.entry(property).or_default();
+            // A later keyframe at the same offset takes precedence
+            if let Some(last) = frames.last_mut() {
+                if last.0 == keyframe.offset {
+                    *last = (keyframe.offset, value, keyframe_timing_function.clone());
+                    continue;
+                }
+            }
+            frames.push((keyframe.offset, value, keyframe_timing_function.clone()));
+        }
+    }
+    result
+}
+
+fn is_animation_property(property: &Property) -> bool {
+    matches!(
+        property,
+        Property::AnimationName
+            | Property::AnimationDuration
+            | Property::AnimationDelay
+            | Property::AnimationIterationCount
+            | Property::AnimationDirection
+            | Property::AnimationFillMode
+            | Property::AnimationTimingFunction
+    )
+}
+
+/// Value `progress` of the way from `from` to `to`. Values that can't be
+/// interpolated swap halfway through.
+/// https://www.w3.org/TR/css-values-4/#combining-values
+fn interpolate(from: &Value, to: &Value, progress: f32) -> Value {
+    let lerp = |from: f32, to: f32| from + (to - from) * progress;
+    match (from, to) {
+        (Value::Length(from), Value::Length(to)) if from.unit == to.unit => Value::Length(Length {
+            value: lerp(*from.value, *to.value).into(),
+            unit: from.unit.clone(),
+        }),
+        (Value::Percentage(from), Value::Percentage(to)) => {
+            Value::Percentage(Percentage(lerp(*from.0, *to.0).into()))
+        }
+        (Value::Color(from_color), Value::Color(to_color)) => {
+            match (rgba(from_color), rgba(to_color)) {
+                (Some(from), Some(to)) => {
+                    // Colors are interpolated with premultiplied alpha, so
+                    // that a transparent end doesn't tint the other one
+                    let alpha = lerp(from[3], to[3]);
+                    let channel = |index: usize| match alpha > 0. {
+                        true => lerp(from[index] * from[3], to[index] * to[3]) / alpha,
+                        false => 0.,
+                    };
+                    Value::Color(Color::Rgba(
+                        channel(0).into(),
+                        channel(1).into(),
+                        channel(2).into(),
+                        alpha.into(),
+                    ))
+                }
+                _ => discrete(from, to, progress),
+            }
+        }
+        _ => discrete(from, to, progress),
+    }
+}
+
+fn discrete<T: Clone>(from: &T, to: &T, progress: f32) -> T {
+    match progress < 0.5 {
+        true => from.clone(),
+        false => to.clone(),
+    }
+}
+
+fn rgba(color: &Color) -> Option<[f32; 4]> {
+    match color {
+        Color::Rgba(r, g, b, a) => Some([**r, **g, **b, **a]),
+        Color::Transparent => Some([0.; 4]),
+        Color::CurrentColor => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use style_types::{values::prelude::Time, CSSLocation, CascadeOrigin, ContextualStyleSheet};
+    use test_utils::{
+        css::parse_stylesheet,
+        dom_creator::{document, element},
+    };
+
+    use super::*;
+    use crate::compute::compute_styles;
+
+    #[test]
+    fn test_animation_progress() {
+        let timing = AnimationTiming {
+            duration: 1000.,
+            delay: 500.,
+            iterations: 2.,
+            direction: AnimationDirection::Alternate,
+            fill_mode: AnimationFillMode::Forwards,
+        };
+        assert_eq!(timing.progress(0.), None);
+        assert_eq!(timing.progress(750.), Some(0.25));
+        // The second iteration runs backward
+        assert_eq!(timing.progress(1750.), Some(0.75));
+        // and the animation stays where it ended
+        assert_eq!(timing.progress(5000.), Some(0.));
+        assert_eq!(
+            Value::Time(Time(1500.0.into())),
+            Value::parse(&Property::AnimationDelay, &parse_value("1.5s"))
+                .expect("time should parse")
+        );
+    }
+
+    fn parse_value(value: &str) -> Vec<css::parser::structs::ComponentValue> {
+        let stylesheet = parse_stylesheet(&format!("p {{ animation-delay: {}; }}", value));
+        match &stylesheet[0] {
+            css::cssom::css_rule::CSSRule::Style(style) => style.declarations[0].value.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_apply_animation() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        document
+            .as_document()
+            .append_stylesheet(ContextualStyleSheet::new(
+                parse_stylesheet(
+                    r#"
+                p { margin-left: 10px; animation: slide 1s linear; }
+                @keyframes slide {
+                    to { margin-left: 110px; color: #00000000; width: 50%; }
+                }
+                "#,
+                ),
+                CascadeOrigin::Author,
+                CSSLocation::Embedded,
+            ));
+        let rules = document.as_document().style_rules();
+        let style = |property: &Property| {
+            for node in [&document, &paragraph] {
+                node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+            }
+            paragraph.get_style(property)
+        };
+
+        assert_eq!(
+            style(&Property::MarginLeft),
+            Value::Length(Length::new_px(10.))
+        );
+        assert!(document.as_document().has_running_animations());
+
+        document.as_document().set_animation_time(250.);
+        assert_eq!(
+            style(&Property::MarginLeft),
+            Value::Length(Length::new_px(35.))
+        );
+        // The width has no value to interpolate from, so it swaps halfway
+        assert_eq!(style(&Property::Width), Value::Auto);
+        document.as_document().set_animation_time(600.);
+        assert_eq!(
+            style(&Property::Width),
+            Value::Percentage(Percentage(50.0.into()))
+        );
+
+        // Without a fill mode the animation stops applying when it ends
+        document.as_document().set_has_running_animations(false);
+        document.as_document().set_animation_time(1000.);
+        assert_eq!(
+            style(&Property::MarginLeft),
+            Value::Length(Length::new_px(10.))
+        );
+        assert!(!document.as_document().has_running_animations());
+    }
+}
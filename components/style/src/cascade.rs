@@ -1,15 +1,19 @@
-use super::selector_matching::is_match_selectors;
+use super::ancestor_filter::AncestorFilter;
+use super::selector_matching::{
+    is_match_pseudo_element_selectors, is_match_selectors_with_ancestors,
+};
 use css::parser::structs::ComponentValue;
 use css::parser::structs::Declaration;
 use css::selector::structs::Specificity;
 use css::tokenizer::token::Token;
 use dom::node::NodePtr;
 use std::cmp::{Ord, Ordering};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use style_types::CSSLocation;
 use style_types::CascadeOrigin;
 use style_types::ContextualRule;
 use style_types::Property;
+use style_types::PseudoElement;
 use style_types::Value;
 
 use super::expand::prelude::*;
@@ -28,25 +32,66 @@ pub struct PropertyDeclaration {
     pub specificity: Specificity,
 }
 
-pub fn collect_cascaded_values(node: &NodePtr, rules: &[ContextualRule]) -> Properties {
+/// Values winning the cascade for a node
+pub struct CascadedValues {
+    pub values: Properties,
+    /// Properties whose winning declaration is important, which animations
+    /// don't override
+    pub important: HashSet<Property>,
+}
+
+/// Collect the cascaded values of the node. The filter of the ancestors of
+/// the node, when given, skips the rules that can't match.
+pub fn collect_cascaded_values(
+    node: &NodePtr,
+    rules: &[ContextualRule],
+    ancestors: Option<&AncestorFilter>,
+) -> CascadedValues {
+    collect_cascaded_values_for(node, None, rules, ancestors)
+}
+
+/// Collect the cascaded values of a pseudo-element of the node
+pub fn collect_pseudo_element_cascaded_values(
+    node: &NodePtr,
+    pseudo_element: &PseudoElement,
+    rules: &[ContextualRule],
+) -> Properties {
+    collect_cascaded_values_for(node, Some(pseudo_element), rules, None).values
+}
+
+fn collect_cascaded_values_for(
+    node: &NodePtr,
+    pseudo_element: Option<&PseudoElement>,
+    rules: &[ContextualRule],
+    ancestors: Option<&AncestorFilter>,
+) -> CascadedValues {
     // https://www.w3.org/TR/css3-cascade/#value-stages
     // Step 1
-    let mut declared_values = collect_declared_values(&node, rules);
+    let mut declared_values = collect_declared_values(&node, pseudo_element, rules, ancestors);
 
     // Step 2
-    let cascade_values = declared_values
-        .iter_mut()
-        .map(|(property, values)| (property.clone(), cascade(values)))
-        .collect::<Properties>();
+    let mut cascaded_values = CascadedValues {
+        values: Properties::new(),
+        important: HashSet::new(),
+    };
+    for (property, values) in declared_values.iter_mut() {
+        let winner = cascade(values);
+        if winner.important {
+            cascaded_values.important.insert(property.clone());
+        }
+        cascaded_values
+            .values
+            .insert(property.clone(), winner.value.clone());
+    }
 
-    cascade_values
+    cascaded_values
 }
 
 /// Cascade sort the property declarations
-/// for a property and get the wining value
-fn cascade(declared_values: &mut Vec<PropertyDeclaration>) -> Value {
+/// for a property and get the wining declaration
+fn cascade(declared_values: &mut Vec<PropertyDeclaration>) -> &PropertyDeclaration {
     declared_values.sort();
-    declared_values.last().unwrap().value.clone()
+    declared_values.last().unwrap()
 }
 
 /// Get a short-hand property expander
@@ -65,13 +110,99 @@ fn get_expander_shorthand_property(
         "border-right" => Some(&expand_border_right),
         "border-bottom" => Some(&expand_border_bottom),
         "border-left" => Some(&expand_border_left),
+        "list-style" => Some(&expand_list_style),
+        "outline" => Some(&expand_outline),
+        "grid-column" => Some(&expand_grid_column),
+        "grid-row" => Some(&expand_grid_row),
+        "gap" | "grid-gap" => Some(&expand_gap),
+        "columns" => Some(&expand_columns),
+        "column-rule" => Some(&expand_column_rule),
+        "overflow" => Some(&expand_overflow),
+        "animation" => Some(&expand_animation),
+        "-webkit-text-stroke" => Some(&expand_text_stroke),
         _ => None,
     }
 }
 
+/// Longhand properties set by a shorthand property, which all take the
+/// CSS-wide keyword the shorthand is set to
+fn get_shorthand_longhands(property: &str) -> &'static [Property] {
+    use Property::*;
+    match property {
+        "margin" => &[MarginTop, MarginRight, MarginBottom, MarginLeft],
+        "padding" => &[PaddingTop, PaddingRight, PaddingBottom, PaddingLeft],
+        "border" => &[
+            BorderTopStyle,
+            BorderRightStyle,
+            BorderBottomStyle,
+            BorderLeftStyle,
+            BorderTopWidth,
+            BorderRightWidth,
+            BorderBottomWidth,
+            BorderLeftWidth,
+            BorderTopColor,
+            BorderRightColor,
+            BorderBottomColor,
+            BorderLeftColor,
+        ],
+        "border-style" => &[
+            BorderTopStyle,
+            BorderRightStyle,
+            BorderBottomStyle,
+            BorderLeftStyle,
+        ],
+        "border-width" => &[
+            BorderTopWidth,
+            BorderRightWidth,
+            BorderBottomWidth,
+            BorderLeftWidth,
+        ],
+        "border-color" => &[
+            BorderTopColor,
+            BorderRightColor,
+            BorderBottomColor,
+            BorderLeftColor,
+        ],
+        "border-radius" => &[
+            BorderTopLeftRadius,
+            BorderTopRightRadius,
+            BorderBottomRightRadius,
+            BorderBottomLeftRadius,
+        ],
+        "border-top" => &[BorderTopStyle, BorderTopWidth, BorderTopColor],
+        "border-right" => &[BorderRightStyle, BorderRightWidth, BorderRightColor],
+        "border-bottom" => &[BorderBottomStyle, BorderBottomWidth, BorderBottomColor],
+        "border-left" => &[BorderLeftStyle, BorderLeftWidth, BorderLeftColor],
+        "list-style" => &[ListStyleType, ListStylePosition],
+        "outline" => &[OutlineStyle, OutlineWidth, OutlineColor],
+        "grid-column" => &[GridColumnStart, GridColumnEnd],
+        "grid-row" => &[GridRowStart, GridRowEnd],
+        "gap" | "grid-gap" => &[RowGap, ColumnGap],
+        "columns" => &[ColumnWidth, ColumnCount],
+        "column-rule" => &[ColumnRuleStyle, ColumnRuleWidth, ColumnRuleColor],
+        "overflow" => &[OverflowX, OverflowY],
+        "-webkit-text-stroke" => &[TextStrokeWidth, TextStrokeColor],
+        "animation" => &[
+            AnimationName,
+            AnimationDuration,
+            AnimationTimingFunction,
+            AnimationDelay,
+            AnimationIterationCount,
+            AnimationDirection,
+            AnimationFillMode,
+        ],
+        _ => &[],
+    }
+}
+
 /// Collect declared values for each property
 /// found in each style rule
-fn collect_declared_values(node: &NodePtr, rules: &[ContextualRule]) -> DeclaredValuesMap {
+fn collect_declared_values(
+    node: &NodePtr,
+    pseudo_element: Option<&PseudoElement>,
+    rules: &[ContextualRule],
+    ancestors: Option<&AncestorFilter>,
+) -> DeclaredValuesMap {
     let mut result: DeclaredValuesMap = HashMap::new();
 
     if !node.is_element() {
@@ -80,7 +211,12 @@ fn collect_declared_values(node: &NodePtr, rules: &[ContextualRule]) -> Declared
 
     let matched_rules = rules
         .iter()
-        .filter(|rule| is_match_selectors(node, &rule.inner.selectors))
+        .filter(|rule| match pseudo_element {
+            Some(pseudo_element) => {
+                is_match_pseudo_element_selectors(node, pseudo_element, &rule.inner.selectors)
+            }
+            None => is_match_selectors_with_ancestors(node, &rule.inner.selectors, ancestors),
+        })
         .collect::<Vec<&ContextualRule>>();
 
     let mut insert_declaration =
@@ -101,34 +237,8 @@ fn collect_declared_values(node: &NodePtr, rules: &[ContextualRule]) -> Declared
 
     for rule in matched_rules {
         for declaration in &rule.inner.declarations {
-            if let Some(expand) = get_expander_shorthand_property(&declaration.name) {
-                // process short hand property
-                let tokens = declaration
-                    .value
-                    .split(|val| match val {
-                        ComponentValue::PerservedToken(Token::Whitespace) => true,
-                        _ => false,
-                    })
-                    .collect::<Vec<&[ComponentValue]>>();
-
-                if let Some(values) = expand(&tokens) {
-                    for (property, value) in values {
-                        if let Some(v) = value {
-                            insert_declaration(v, property, rule, declaration);
-                        }
-                    }
-                }
-            } else {
-                // process long hand css property
-                let property = Property::parse(&declaration.name);
-                if let Some(property) = property {
-                    let values = &declaration.value;
-                    let value = Value::parse(&property, values);
-
-                    if let Some(value) = value {
-                        insert_declaration(value, property, rule, declaration);
-                    }
-                }
+            for (property, value) in parse_declaration(declaration) {
+                insert_declaration(value, property, rule, declaration);
             }
         }
     }
@@ -136,23 +246,58 @@ fn collect_declared_values(node: &NodePtr, rules: &[ContextualRule]) -> Declared
     result
 }
 
+/// Values of the longhand properties set by the declaration. Invalid
+/// declarations set nothing.
+pub(crate) fn parse_declaration(declaration: &Declaration) -> Vec<(Property, Value)> {
+    if let Some(expand) = get_expander_shorthand_property(&declaration.name) {
+        if let Some(keyword) = Value::parse_css_wide_keyword(&declaration.value) {
+            return get_shorthand_longhands(&declaration.name)
+                .iter()
+                .map(|property| (property.clone(), keyword.clone()))
+                .collect();
+        }
+
+        // process short hand property
+        let tokens = declaration
+            .value
+            .split(|val| match val {
+                ComponentValue::PerservedToken(Token::Whitespace) => true,
+                _ => false,
+            })
+            .collect::<Vec<&[ComponentValue]>>();
+
+        return expand(&tokens)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(property, value)| Some((property, value?)))
+            .collect();
+    }
+
+    // process long hand css property
+    Property::parse(&declaration.name)
+        .and_then(|property| {
+            let value = Value::parse(&property, &declaration.value)?;
+            Some(vec![(property, value)])
+        })
+        .unwrap_or_default()
+}
+
 /// The implementation for ordering for cascade sort
+/// https://www.w3.org/TR/css-cascade-4/#cascade-sort
 ///
 /// These are the steps to compare the order:
-/// 1. Comparing the location of the property declaration (Inline, Embedded, etc.)
-/// 2. If step 1 result in equal ordering compare the cascade origin
+/// 1. Comparing the cascade origin and importance
+/// 2. If step 1 result in equal ordering, declarations of the style
+///    attribute of the element win over the rules of stylesheets
 /// 3. If step 2 result in equal ordering compare the specificity
+///
+/// Declarations that are still equal are in the order of appearance, which
+/// the stable sort of the cascade keeps.
 impl Ord for PropertyDeclaration {
     fn cmp(&self, other: &Self) -> Ordering {
-        match cmp_location(self, other) {
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Less => Ordering::Less,
-            Ordering::Equal => match cmp_cascade_origin(self, other) {
-                Ordering::Greater => Ordering::Greater,
-                Ordering::Less => Ordering::Less,
-                Ordering::Equal => self.specificity.cmp(&other.specificity),
-            },
-        }
+        cmp_cascade_origin(self, other)
+            .then_with(|| cmp_location(self, other))
+            .then_with(|| self.specificity.cmp(&other.specificity))
     }
 }
 
@@ -163,15 +308,8 @@ impl PartialOrd for PropertyDeclaration {
 }
 
 fn cmp_location(a: &PropertyDeclaration, b: &PropertyDeclaration) -> Ordering {
-    match (&a.location, &b.location) {
-        (CSSLocation::Inline, CSSLocation::Embedded)
-        | (CSSLocation::Inline, CSSLocation::External)
-        | (CSSLocation::Embedded, CSSLocation::External) => Ordering::Greater,
-        (CSSLocation::Inline, CSSLocation::Inline)
-        | (CSSLocation::Embedded, CSSLocation::Embedded)
-        | (CSSLocation::External, CSSLocation::External) => Ordering::Equal,
-        _ => Ordering::Less,
-    }
+    let is_inline = |declaration: &PropertyDeclaration| declaration.location == CSSLocation::Inline;
+    is_inline(a).cmp(&is_inline(b))
 }
 
 /// Comparing cascade origin and importance
@@ -254,7 +392,7 @@ mod tests {
         let mut declared = vec![a.clone(), b.clone(), c.clone()];
 
         let win = cascade(&mut declared);
-        assert_eq!(win, c.value);
+        assert_eq!(win.value, c.value);
     }
 
     #[test]
@@ -301,6 +439,38 @@ mod tests {
         let mut declared = vec![b.clone(), a.clone()];
 
         let win = cascade(&mut declared);
-        assert_eq!(win, b.value);
+        assert_eq!(win.value, b.value);
+    }
+
+    #[test]
+    fn cascade_origin_before_location() {
+        let declaration =
+            |origin: CascadeOrigin, location: CSSLocation, important: bool| PropertyDeclaration {
+                location,
+                origin,
+                important,
+                value: Value::Color(Color::black()),
+                specificity: Specificity::new(0, 0, 1),
+            };
+        let user_agent = declaration(CascadeOrigin::UserAgent, CSSLocation::External, false);
+        let author = declaration(CascadeOrigin::Author, CSSLocation::External, false);
+        let inline = declaration(CascadeOrigin::Author, CSSLocation::Inline, false);
+        let important_author = declaration(CascadeOrigin::Author, CSSLocation::External, true);
+        let important_user_agent =
+            declaration(CascadeOrigin::UserAgent, CSSLocation::Embedded, true);
+
+        assert!(author > user_agent);
+        assert!(inline > author);
+        assert!(important_author > inline);
+        assert!(important_user_agent > important_author);
+        // Stylesheets of the same origin are ordered by appearance only
+        assert_eq!(
+            author.cmp(&declaration(
+                CascadeOrigin::Author,
+                CSSLocation::Embedded,
+                false
+            )),
+            Ordering::Equal
+        );
     }
 }
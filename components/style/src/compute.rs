@@ -1,34 +1,144 @@
 use std::collections::HashMap;
 
-use dom::node::NodePtr;
+use dom::{element::Element, flat_tree, node::NodePtr};
 use style_types::{
     values::{
+        display::{InnerDisplayType, OuterDisplayType},
         length::LengthUnit,
-        prelude::{BorderStyle, Color, Length, Percentage},
+        prelude::{
+            BorderStyle, Color, Direction, Display, Length, LineHeight, Percentage, TrackList,
+            TrackSize, UnicodeBidi,
+        },
     },
-    ContextualRule, Property, Value,
+    ContextualRule, Property, PseudoElement, Value,
 };
 
-use crate::cascade::collect_cascaded_values;
+use crate::ancestor_filter::AncestorFilter;
+use crate::animation::apply_animations;
+use crate::cascade::{collect_cascaded_values, collect_pseudo_element_cascaded_values};
 
 pub fn compute_styles(node: NodePtr, rules: &[ContextualRule]) -> HashMap<Property, Value> {
-    let mut styles = collect_cascaded_values(&node, rules);
+    compute_styles_for(node, rules, None)
+}
+
+/// Compute the styles of the node during a traversal of the tree that keeps
+/// the filter of the ancestors of the node up to date
+pub fn compute_styles_with_ancestors(
+    node: NodePtr,
+    rules: &[ContextualRule],
+    ancestors: &AncestorFilter,
+) -> HashMap<Property, Value> {
+    compute_styles_for(node, rules, Some(ancestors))
+}
 
-    compute_default_values(&node, &mut styles);
-    compute_absolute_values(&node, &mut styles);
+fn compute_styles_for(
+    node: NodePtr,
+    rules: &[ContextualRule],
+    ancestors: Option<&AncestorFilter>,
+) -> HashMap<Property, Value> {
+    let cascaded_values = collect_cascaded_values(&node, rules, ancestors);
+    let mut styles = cascaded_values.values;
+    apply_presentational_hints(&node, &mut styles);
+    // Elements inherit from their parent in the flat tree, so children of a
+    // shadow host inherit from the slot they are rendered in
+    let parent = flat_tree::parent(&node);
+
+    compute_default_values(&parent, &mut styles);
+    // The root element inherits the default font size of the user from the
+    // document
+    if let Some(document) = node.as_document_opt() {
+        styles.insert(
+            Property::FontSize,
+            Value::Length(Length::new_px(document.default_font_size())),
+        );
+    }
+    apply_animations(&node, &parent, &cascaded_values.important, &mut styles);
+    compute_absolute_values(&node, &parent, &mut styles);
     styles
 }
 
-fn compute_absolute_values(node: &NodePtr, styles: &mut HashMap<Property, Value>) {
-    let base_font_size = 16.;
-    let parent_font_size = node
-        .parent()
-        .map(|parent| {
-            NodePtr(parent)
-                .get_style(&Property::FontSize)
-                .to_absolute_px()
-        })
-        .unwrap_or(base_font_size);
+/// Compute the styles of a pseudo-element of the element.
+/// The pseudo-element inherits from its originating element.
+pub fn compute_pseudo_element_styles(
+    element: &NodePtr,
+    pseudo_element: &PseudoElement,
+    rules: &[ContextualRule],
+) -> HashMap<Property, Value> {
+    let mut styles = collect_pseudo_element_cascaded_values(element, pseudo_element, rules);
+    let parent = Some(element.clone());
+
+    compute_default_values(&parent, &mut styles);
+    compute_absolute_values(element, &parent, &mut styles);
+    styles
+}
+
+/// Styles from attributes of the element. Any declaration in a stylesheet
+/// takes precedence over them.
+fn apply_presentational_hints(node: &NodePtr, styles: &mut HashMap<Property, Value>) {
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return,
+    };
+    apply_dir_hint(element, styles);
+    apply_math_display_hint(element, styles);
+}
+
+/// https://html.spec.whatwg.org/multipage/rendering.html#bidi-rendering
+fn apply_dir_hint(element: &Element, styles: &mut HashMap<Property, Value>) {
+    let dir = element.attributes().borrow().get_str("dir");
+    let (direction, unicode_bidi) = match dir.to_ascii_lowercase().as_str() {
+        "ltr" => (Some(Direction::Ltr), UnicodeBidi::Isolate),
+        "rtl" => (Some(Direction::Rtl), UnicodeBidi::Isolate),
+        // The direction is detected from the text
+        "auto" => (None, UnicodeBidi::Plaintext),
+        _ => return,
+    };
+
+    if let Some(direction) = direction {
+        styles
+            .entry(Property::Direction)
+            .or_insert(Value::Direction(direction));
+    }
+    styles
+        .entry(Property::UnicodeBidi)
+        .or_insert(Value::UnicodeBidi(unicode_bidi));
+}
+
+/// `<math display="block">` is laid out as a block instead of inline. The
+/// attribute only replaces the display of `math` from the user-agent styles.
+/// https://w3c.github.io/mathml-core/#the-top-level-math-element
+fn apply_math_display_hint(element: &Element, styles: &mut HashMap<Property, Value>) {
+    let is_block = element.tag_name() == "math"
+        && element
+            .attributes()
+            .borrow()
+            .get_str("display")
+            .eq_ignore_ascii_case("block");
+    let inline_math = Value::Display(Display::Full(
+        OuterDisplayType::Inline,
+        InnerDisplayType::Math,
+    ));
+    if is_block && styles.get(&Property::Display) == Some(&inline_math) {
+        styles.insert(
+            Property::Display,
+            Value::Display(Display::Full(
+                OuterDisplayType::Block,
+                InnerDisplayType::Math,
+            )),
+        );
+    }
+}
+
+fn compute_absolute_values(
+    node: &NodePtr,
+    parent: &Option<NodePtr>,
+    styles: &mut HashMap<Property, Value>,
+) {
+    let initial_font_size = Value::initial(&Property::FontSize).to_absolute_px();
+    let parent_font_size = parent
+        .as_ref()
+        .map(|parent| parent.get_style(&Property::FontSize).to_absolute_px())
+        .unwrap_or(initial_font_size);
 
     let root_font_size = node
         .owner_document()
@@ -37,63 +147,65 @@ fn compute_absolute_values(node: &NodePtr, styles: &mut HashMap<Property, Value>
                 .get_style(&Property::FontSize)
                 .to_absolute_px()
         })
-        .unwrap_or(base_font_size);
+        .unwrap_or(initial_font_size);
+
+    let absolute_length = |length: &Length, font_size: f32| match length {
+        Length {
+            value,
+            unit: LengthUnit::Em,
+        } => Some(Length::new_px(value.0 * font_size)),
+        Length {
+            value,
+            unit: LengthUnit::Rem,
+        } => Some(Length::new_px(value.0 * root_font_size)),
+        _ => None,
+    };
+
+    // `em` in `font-size` is relative to the font size of the parent, and in
+    // the other properties to the computed font size of the element
+    let font_size = match styles.get(&Property::FontSize) {
+        Some(Value::Length(length)) => absolute_length(length, parent_font_size),
+        Some(Value::Percentage(Percentage(value))) => {
+            Some(Length::new_px(value.0 * parent_font_size / 100.))
+        }
+        _ => None,
+    };
+    if let Some(font_size) = font_size {
+        styles.insert(Property::FontSize, Value::Length(font_size));
+    }
+    let font_size = styles
+        .get(&Property::FontSize)
+        .map(|font_size| font_size.to_absolute_px())
+        .unwrap_or(parent_font_size);
 
     let mut updates = Vec::new();
     for (property, value) in styles.iter() {
         match value {
-            Value::Length(length) => match length {
-                Length {
-                    value,
-                    unit: LengthUnit::Em,
-                } => {
-                    let abs_length = Length::new_px(value.0 * parent_font_size);
-                    updates.push((property.clone(), Value::Length(abs_length)));
-                }
-                Length {
-                    value,
-                    unit: LengthUnit::Rem,
-                } => {
-                    let abs_length = Length::new_px(value.0 * root_font_size);
+            Value::Length(length) => {
+                if let Some(abs_length) = absolute_length(length, font_size) {
                     updates.push((property.clone(), Value::Length(abs_length)));
                 }
-                _ => {}
-            },
-            Value::Percentage(percentage) => match percentage {
-                Percentage(value) if matches!(property, Property::FontSize) => {
-                    let abs_length = Length::new_px(value.0 * parent_font_size / 100.);
-                    updates.push((property.clone(), Value::Length(abs_length)));
-                }
-                _ => {}
-            },
-            Value::Color(color) => match color {
-                Color::CurrentColor => {
-                    let color = node
-                        .parent()
-                        .map(|p| p.get_style(&Property::Color))
-                        .unwrap_or(Value::initial(&Property::Color));
-                    updates.push((property.clone(), color));
-                }
-                _ => {}
-            },
-            Value::BorderWidth(_) => {
-                let border_style = match &property {
-                    Property::BorderTopWidth => Property::BorderTopStyle,
-                    Property::BorderLeftWidth => Property::BorderLeftStyle,
-                    Property::BorderBottomWidth => Property::BorderBottomStyle,
-                    Property::BorderRightWidth => Property::BorderRightStyle,
-                    _ => unreachable!(),
-                };
-
-                let border_style = styles.get(&border_style).unwrap();
-                match border_style {
-                    Value::BorderStyle(BorderStyle::None)
-                    | Value::BorderStyle(BorderStyle::Hidden) => {
-                        let value = Value::Length(Length::zero());
-                        updates.push((property.clone(), value));
-                    }
-                    _ => {}
-                }
+            }
+            Value::TrackList(TrackList(tracks)) => {
+                let tracks = tracks
+                    .iter()
+                    .map(|track| match track {
+                        TrackSize::Length(length) => absolute_length(length, font_size)
+                            .map(TrackSize::Length)
+                            .unwrap_or_else(|| track.clone()),
+                        _ => track.clone(),
+                    })
+                    .collect();
+                updates.push((property.clone(), Value::TrackList(TrackList(tracks))));
+            }
+            // `currentColor` in other properties computes to itself and is
+            // resolved against the element's `color` at used-value time
+            Value::Color(Color::CurrentColor) if matches!(property, Property::Color) => {
+                let color = parent
+                    .as_ref()
+                    .map(|p| p.get_style(&Property::Color))
+                    .unwrap_or(Value::initial(&Property::Color));
+                updates.push((property.clone(), color));
             }
             _ => {}
         }
@@ -102,12 +214,56 @@ fn compute_absolute_values(node: &NodePtr, styles: &mut HashMap<Property, Value>
     for (property, value) in updates {
         styles.insert(property, value);
     }
+
+    compute_line_widths(styles);
+    compute_line_height(styles, font_size, root_font_size);
+}
+
+/// Lengths and percentages of `line-height` are relative to the element's
+/// own font size. Numbers are inherited as is.
+fn compute_line_height(styles: &mut HashMap<Property, Value>, font_size: f32, root_font_size: f32) {
+    let line_height = match styles.get(&Property::LineHeight) {
+        Some(Value::LineHeight(LineHeight::Percentage(percentage))) => {
+            Length::new_px(percentage.to_px(font_size))
+        }
+        Some(Value::LineHeight(LineHeight::Length(length))) => match length.unit {
+            LengthUnit::Em => Length::new_px(*length.value * font_size),
+            LengthUnit::Rem => Length::new_px(*length.value * root_font_size),
+            _ => return,
+        },
+        _ => return,
+    };
+    styles.insert(
+        Property::LineHeight,
+        Value::LineHeight(LineHeight::Length(line_height)),
+    );
+}
+
+/// Border and outline widths compute to zero when their style is `none` or
+/// `hidden`
+fn compute_line_widths(styles: &mut HashMap<Property, Value>) {
+    let lines = [
+        (Property::BorderTopWidth, Property::BorderTopStyle),
+        (Property::BorderRightWidth, Property::BorderRightStyle),
+        (Property::BorderBottomWidth, Property::BorderBottomStyle),
+        (Property::BorderLeftWidth, Property::BorderLeftStyle),
+        (Property::OutlineWidth, Property::OutlineStyle),
+        (Property::ColumnRuleWidth, Property::ColumnRuleStyle),
+    ];
+
+    for (width, style) in lines {
+        if let Some(Value::BorderStyle(BorderStyle::None | BorderStyle::Hidden)) =
+            styles.get(&style)
+        {
+            styles.insert(width, Value::Length(Length::zero()));
+        }
+    }
 }
 
-fn compute_default_values(node: &NodePtr, styles: &mut HashMap<Property, Value>) {
+fn compute_default_values(parent: &Option<NodePtr>, styles: &mut HashMap<Property, Value>) {
     // get inherit value for a property
     let inherit = |property: Property| {
-        if let Some(parent) = &node.parent() {
+        if let Some(parent) = parent {
             return (property.clone(), parent.get_style(&property));
         }
         // if there's no parent
@@ -150,3 +306,244 @@ fn compute_default_values(node: &NodePtr, styles: &mut HashMap<Property, Value>)
         styles.insert(property, value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use css::cssom::css_rule::CSSRule;
+    use style_types::{CSSLocation, CascadeOrigin};
+    use test_utils::{
+        css::parse_stylesheet,
+        dom_creator::{document, element},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_current_color() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        let span = element("span", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        paragraph.append_child(span.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { color: red; border-top-color: currentColor; }
+            span { color: currentColor; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        for node in [&document, &paragraph, &span] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        let red = paragraph.get_style(&Property::Color);
+        assert_ne!(red, Value::initial(&Property::Color));
+        assert_eq!(span.get_style(&Property::Color), red);
+        assert_eq!(
+            paragraph.get_style(&Property::BorderTopColor),
+            Value::Color(Color::CurrentColor)
+        );
+        assert_eq!(
+            span.get_style(&Property::BorderLeftColor),
+            Value::Color(Color::CurrentColor)
+        );
+    }
+
+    #[test]
+    fn test_outline() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        let span = element("span", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        paragraph.append_child(span.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { outline: 2px dashed red; outline-offset: 4px; }
+            span { outline-width: 5px; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        for node in [&document, &paragraph, &span] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        assert_eq!(
+            paragraph.get_style(&Property::OutlineStyle),
+            Value::BorderStyle(BorderStyle::Dashed)
+        );
+        assert_eq!(
+            paragraph.get_style(&Property::OutlineWidth),
+            Value::Length(Length::new_px(2.))
+        );
+        assert_eq!(
+            paragraph.get_style(&Property::OutlineOffset),
+            Value::Length(Length::new_px(4.))
+        );
+        // Outlines are not inherited and have no width without a style
+        assert_eq!(
+            span.get_style(&Property::OutlineWidth),
+            Value::Length(Length::zero())
+        );
+    }
+
+    #[test]
+    fn test_text_stroke_and_shadow() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        let span = element("span", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        paragraph.append_child(span.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { -webkit-text-stroke: 2px; text-shadow: 1px 1px red; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        for node in [&document, &paragraph, &span] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        // Both are inherited, the omitted stroke color is `currentColor`
+        assert_eq!(
+            span.get_style(&Property::TextStrokeWidth),
+            Value::Length(Length::new_px(2.))
+        );
+        assert_eq!(
+            span.get_style(&Property::TextStrokeColor),
+            Value::Color(Color::CurrentColor)
+        );
+        assert_eq!(
+            span.get_style(&Property::TextShadow).to_string(),
+            "rgb(255, 0, 0) 1px 1px 0px"
+        );
+    }
+
+    #[test]
+    fn test_line_height() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        let span = element("span", document.clone(), vec![]);
+        let link = element("a", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        paragraph.append_child(span.0.clone());
+        paragraph.append_child(link.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { font-size: 20px; line-height: 150%; }
+            span { font-size: 10px; }
+            a { font-size: 10px; line-height: 2em; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        for node in [&document, &paragraph, &span, &link] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        let line_height = |px: f32| Value::LineHeight(LineHeight::Length(Length::new_px(px)));
+        assert_eq!(paragraph.get_style(&Property::LineHeight), line_height(30.));
+        // Percentages are inherited as lengths
+        assert_eq!(span.get_style(&Property::LineHeight), line_height(30.));
+        assert_eq!(link.get_style(&Property::LineHeight), line_height(20.));
+    }
+
+    #[test]
+    fn test_css_wide_keywords() {
+        let document = document();
+        let paragraph = element("p", document.clone(), vec![]);
+        let span = element("span", document.clone(), vec![]);
+        document.append_child(paragraph.0.clone());
+        paragraph.append_child(span.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { color: red; margin: 4px; text-align: center; font-size: 20px; width: 2em; }
+            span { color: blue; color: inherit; margin: inherit; text-align: initial; }
+            span { font-size: unset; width: inherit; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        for node in [&document, &paragraph, &span] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        // `em` is relative to the font size of the element itself
+        assert_eq!(
+            paragraph.get_style(&Property::Width),
+            Value::Length(Length::new_px(40.))
+        );
+        assert_eq!(
+            span.get_style(&Property::Color),
+            paragraph.get_style(&Property::Color)
+        );
+        for property in [Property::MarginTop, Property::MarginLeft, Property::Width] {
+            assert_eq!(span.get_style(&property), paragraph.get_style(&property));
+        }
+        assert_eq!(
+            span.get_style(&Property::TextAlign),
+            Value::initial(&Property::TextAlign)
+        );
+        assert_eq!(
+            span.get_style(&Property::FontSize),
+            Value::Length(Length::new_px(20.))
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+pub fn expand_animation(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let mut duration = None;
+    let mut delay = None;
+    let mut timing_function = None;
+    let mut iteration_count = None;
+    let mut direction = None;
+    let mut fill_mode = None;
+    let mut name = None;
+
+    for tokens in values {
+        // the first time is the duration, the second one the delay
+        if let Some(value) = Value::parse(&Property::AnimationDuration, tokens) {
+            if duration.is_none() {
+                duration = Some(value);
+                continue;
+            }
+            if delay.is_none() {
+                delay = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::AnimationTimingFunction, tokens) {
+            if timing_function.is_none() {
+                timing_function = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::AnimationIterationCount, tokens) {
+            if iteration_count.is_none() {
+                iteration_count = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::AnimationDirection, tokens) {
+            if direction.is_none() {
+                direction = Some(value);
+                continue;
+            }
+        }
+        if let Some(value) = Value::parse(&Property::AnimationFillMode, tokens) {
+            if fill_mode.is_none() {
+                fill_mode = Some(value);
+                continue;
+            }
+        }
+        // keywords of the other longhands are taken first, so a name like
+        // `none` only names the animation when those are already set
+        if let Some(value) = Value::parse(&Property::AnimationName, tokens) {
+            if name.is_none() {
+                name = Some(value);
+                continue;
+            }
+        }
+        return None;
+    }
+
+    // omitted longhands are reset to their initial values
+    Some(vec![
+        (
+            Property::AnimationDuration,
+            Some(duration.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationDelay,
+            Some(delay.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationTimingFunction,
+            Some(timing_function.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationIterationCount,
+            Some(iteration_count.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationDirection,
+            Some(direction.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationFillMode,
+            Some(fill_mode.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::AnimationName,
+            Some(name.unwrap_or(Value::Initial)),
+        ),
+    ])
+}
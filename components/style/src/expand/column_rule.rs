@@ -0,0 +1,50 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+pub fn expand_column_rule(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let mut style = None;
+    let mut width = None;
+    let mut color = None;
+
+    for tokens in values {
+        if let Some(value) = Value::parse(&Property::ColumnRuleStyle, tokens) {
+            if style.is_none() {
+                style = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::ColumnRuleWidth, tokens) {
+            if width.is_none() {
+                width = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::ColumnRuleColor, tokens) {
+            if color.is_none() {
+                color = Some(value);
+                continue;
+            }
+            return None;
+        }
+        return None;
+    }
+
+    // omitted longhands are reset to their initial values
+    Some(vec![
+        (
+            Property::ColumnRuleStyle,
+            Some(style.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::ColumnRuleWidth,
+            Some(width.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::ColumnRuleColor,
+            Some(color.unwrap_or(Value::Initial)),
+        ),
+    ])
+}
@@ -0,0 +1,41 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+/// Expand `<column-width> || <column-count>` in any order. Omitted
+/// longhands are reset to `auto`.
+pub fn expand_columns(values: &[&[ComponentValue]]) -> ExpandOutput {
+    if values.len() > 2 {
+        return None;
+    }
+    let mut width = None;
+    let mut count = None;
+
+    for tokens in values {
+        match Value::parse(&Property::ColumnWidth, tokens) {
+            // Both longhands are `auto` unless set by another value
+            Some(Value::Auto) => continue,
+            Some(value @ Value::Length(_)) if width.is_none() => {
+                width = Some(value);
+                continue;
+            }
+            _ => {}
+        }
+        match Value::parse(&Property::ColumnCount, tokens) {
+            Some(value @ Value::ColumnCount(_)) if count.is_none() => count = Some(value),
+            // CSS-wide keywords set both longhands
+            Some(keyword) if values.len() == 1 => {
+                return Some(vec![
+                    (Property::ColumnWidth, Some(keyword.clone())),
+                    (Property::ColumnCount, Some(keyword)),
+                ])
+            }
+            _ => return None,
+        }
+    }
+
+    Some(vec![
+        (Property::ColumnWidth, Some(width.unwrap_or(Value::Initial))),
+        (Property::ColumnCount, Some(count.unwrap_or(Value::Initial))),
+    ])
+}
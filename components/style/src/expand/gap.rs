@@ -0,0 +1,20 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+/// Expand `<row-gap> [ <column-gap> ]`. An omitted column gap is the same
+/// as the row gap.
+pub fn expand_gap(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let (row_gap, column_gap) = match values {
+        [gap] => (gap, gap),
+        [row_gap, column_gap] => (row_gap, column_gap),
+        _ => return None,
+    };
+    let row_gap = Value::parse(&Property::RowGap, row_gap)?;
+    let column_gap = Value::parse(&Property::ColumnGap, column_gap)?;
+
+    Some(vec![
+        (Property::RowGap, Some(row_gap)),
+        (Property::ColumnGap, Some(column_gap)),
+    ])
+}
@@ -0,0 +1,35 @@
+use super::ExpandOutput;
+use css::{parser::structs::ComponentValue, tokenizer::token::Token};
+use style_types::{values::prelude::GridLine, Property, Value};
+
+pub fn expand_grid_column(values: &[&[ComponentValue]]) -> ExpandOutput {
+    expand_grid_lines(values, Property::GridColumnStart, Property::GridColumnEnd)
+}
+
+pub fn expand_grid_row(values: &[&[ComponentValue]]) -> ExpandOutput {
+    expand_grid_lines(values, Property::GridRowStart, Property::GridRowEnd)
+}
+
+/// Expand `<start> [ / <end> ]`. An omitted end is `auto`.
+fn expand_grid_lines(values: &[&[ComponentValue]], start: Property, end: Property) -> ExpandOutput {
+    let tokens = values.concat();
+    let mut lines =
+        tokens.split(|value| matches!(value, ComponentValue::PerservedToken(Token::Delim('/'))));
+
+    let start_value = Value::parse(&start, lines.next()?)?;
+    let end_value = match (lines.next(), &start_value) {
+        (None, Value::GridLine(_)) => Value::GridLine(GridLine::Auto),
+        // CSS-wide keywords set both lines
+        (None, keyword) => keyword.clone(),
+        (Some(tokens), Value::GridLine(_)) => match Value::parse(&end, tokens)? {
+            line @ Value::GridLine(_) => line,
+            _ => return None,
+        },
+        (Some(_), _) => return None,
+    };
+    if lines.next().is_some() {
+        return None;
+    }
+
+    Some(vec![(start, Some(start_value)), (end, Some(end_value))])
+}
@@ -0,0 +1,38 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+pub fn expand_list_style(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let mut style_type = None;
+    let mut position = None;
+
+    for tokens in values {
+        if let Some(value) = Value::parse(&Property::ListStyleType, tokens) {
+            if style_type.is_none() {
+                style_type = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::ListStylePosition, tokens) {
+            if position.is_none() {
+                position = Some(value);
+                continue;
+            }
+            return None;
+        }
+        return None;
+    }
+
+    // omitted longhands are reset to their initial values
+    Some(vec![
+        (
+            Property::ListStyleType,
+            Some(style_type.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::ListStylePosition,
+            Some(position.unwrap_or(Value::Initial)),
+        ),
+    ])
+}
@@ -2,21 +2,39 @@ use style_types::{Property, Value};
 
 pub type ExpandOutput = Option<Vec<(Property, Option<Value>)>>;
 
+mod animation;
 mod border;
 mod border_color;
 mod border_radius;
 mod border_style;
 mod border_width;
+mod column_rule;
+mod columns;
+mod gap;
+mod grid;
+mod list_style;
 mod margin;
+mod outline;
+mod overflow;
 mod padding;
+mod text_stroke;
 
 pub(crate) mod prelude {
+    pub use super::animation::expand_animation;
     pub use super::border::*;
     pub use super::border_color::expand_border_color;
     pub use super::border_radius::expand_border_radius;
     pub use super::border_style::expand_border_style;
     pub use super::border_width::expand_border_width;
+    pub use super::column_rule::expand_column_rule;
+    pub use super::columns::expand_columns;
+    pub use super::gap::expand_gap;
+    pub use super::grid::{expand_grid_column, expand_grid_row};
+    pub use super::list_style::expand_list_style;
     pub use super::margin::expand_margin;
+    pub use super::outline::expand_outline;
+    pub use super::overflow::expand_overflow;
     pub use super::padding::expand_padding;
+    pub use super::text_stroke::expand_text_stroke;
     pub use super::ExpandOutput;
 }
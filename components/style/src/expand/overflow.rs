@@ -0,0 +1,20 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+/// Expand `<overflow-x> [ <overflow-y> ]`. An omitted `overflow-y` is the
+/// same as `overflow-x`.
+pub fn expand_overflow(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let (overflow_x, overflow_y) = match values {
+        [overflow] => (overflow, overflow),
+        [overflow_x, overflow_y] => (overflow_x, overflow_y),
+        _ => return None,
+    };
+    let overflow_x = Value::parse(&Property::OverflowX, overflow_x)?;
+    let overflow_y = Value::parse(&Property::OverflowY, overflow_y)?;
+
+    Some(vec![
+        (Property::OverflowX, Some(overflow_x)),
+        (Property::OverflowY, Some(overflow_y)),
+    ])
+}
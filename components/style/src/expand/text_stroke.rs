@@ -0,0 +1,38 @@
+use super::ExpandOutput;
+use css::parser::structs::ComponentValue;
+use style_types::{Property, Value};
+
+pub fn expand_text_stroke(values: &[&[ComponentValue]]) -> ExpandOutput {
+    let mut width = None;
+    let mut color = None;
+
+    for tokens in values {
+        if let Some(value) = Value::parse(&Property::TextStrokeWidth, tokens) {
+            if width.is_none() {
+                width = Some(value);
+                continue;
+            }
+            return None;
+        }
+        if let Some(value) = Value::parse(&Property::TextStrokeColor, tokens) {
+            if color.is_none() {
+                color = Some(value);
+                continue;
+            }
+            return None;
+        }
+        return None;
+    }
+
+    // omitted longhands are reset to their initial values
+    Some(vec![
+        (
+            Property::TextStrokeWidth,
+            Some(width.unwrap_or(Value::Initial)),
+        ),
+        (
+            Property::TextStrokeColor,
+            Some(color.unwrap_or(Value::Initial)),
+        ),
+    ])
+}
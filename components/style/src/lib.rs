@@ -1,4 +1,8 @@
+pub mod ancestor_filter;
+pub mod animation;
 pub mod cascade;
 pub mod compute;
 pub mod expand;
+pub mod pseudo_element;
 pub mod selector_matching;
+pub mod user_agent;
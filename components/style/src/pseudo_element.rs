@@ -0,0 +1,123 @@
+use dom::{
+    create_element,
+    node::{Node, NodeData, NodePtr},
+    text::Text,
+};
+use shared::tree_node::{TreeNode, WeakTreeNode};
+use style_types::{
+    values::{display::DisplayBox, prelude::Content, prelude::Display},
+    ContextualRule, Property, PseudoElement, Value,
+};
+
+use crate::compute::{compute_pseudo_element_styles, compute_styles};
+
+/// Generate the `::before` and `::after` pseudo-elements of the element.
+///
+/// A pseudo-element is a detached element that holds the computed styles of
+/// the pseudo-element and a text node with its `content`. It is only
+/// generated when its `content` is made of strings or counters and it is
+/// displayed.
+pub fn update_pseudo_elements(element: &NodePtr, rules: &[ContextualRule]) {
+    if !element.is_element() {
+        return;
+    }
+
+    for pseudo_element in PseudoElement::all() {
+        let node = generate_pseudo_element(element, &pseudo_element, rules);
+        element.set_pseudo_element(pseudo_element, node);
+    }
+}
+
+fn generate_pseudo_element(
+    element: &NodePtr,
+    pseudo_element: &PseudoElement,
+    rules: &[ContextualRule],
+) -> Option<NodePtr> {
+    let styles = compute_pseudo_element_styles(element, pseudo_element, rules);
+
+    let content = match styles.get(&Property::Content) {
+        Some(Value::Content(Content::Text(content))) => content.clone(),
+        // The text of counters is filled in when the box tree is built
+        Some(Value::Content(Content::Items(_))) => String::new(),
+        _ => return None,
+    };
+
+    if let Some(Value::Display(Display::Box(DisplayBox::None))) = styles.get(&Property::Display) {
+        return None;
+    }
+
+    let document = WeakTreeNode::from(&element.owner_document()?);
+    let tag_name = format!("::{}", pseudo_element.name());
+    let node = create_element(document.clone(), &tag_name);
+    node.set_computed_styles(styles);
+
+    let text = NodePtr(TreeNode::new(Node::new(NodeData::Text(Text::new(content)))));
+    text.set_document(document);
+    node.append_child(text.0.clone());
+    text.set_computed_styles(compute_styles(text.clone(), rules));
+
+    Some(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use css::cssom::css_rule::CSSRule;
+    use style_types::{CSSLocation, CascadeOrigin};
+    use test_utils::{
+        css::parse_stylesheet,
+        dom_creator::{document, element},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_generate_pseudo_elements() {
+        let document = document();
+        let node = element("p.note", document.clone(), vec![]);
+        document.append_child(node.0.clone());
+
+        let stylesheet = parse_stylesheet(
+            r#"
+            p { color: red; }
+            .note::before { content: "Note: "; }
+            p::after { content: none; }
+            "#,
+        );
+        let rules = stylesheet
+            .iter()
+            .filter_map(|rule| match rule {
+                CSSRule::Style(style) => Some(ContextualRule {
+                    inner: style.clone(),
+                    location: CSSLocation::Embedded,
+                    origin: CascadeOrigin::Author,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<ContextualRule>>();
+
+        document.set_computed_styles(compute_styles(document.clone(), &rules));
+        node.set_computed_styles(compute_styles(node.clone(), &rules));
+        update_pseudo_elements(&node, &rules);
+
+        assert!(node.pseudo_element(&PseudoElement::After).is_none());
+
+        let before = node.pseudo_element(&PseudoElement::Before).unwrap();
+        assert_eq!(
+            before.get_style(&Property::Content),
+            Value::Content(Content::Text("Note: ".to_string()))
+        );
+        // inherited from the originating element
+        assert_eq!(
+            before.get_style(&Property::Color),
+            node.get_style(&Property::Color)
+        );
+        // the originating element itself is not affected
+        assert_eq!(
+            node.get_style(&Property::Content),
+            Value::Content(Content::Normal)
+        );
+
+        let text = NodePtr(before.first_child().unwrap());
+        assert_eq!(text.as_text().get_data(), "Note: ");
+    }
+}
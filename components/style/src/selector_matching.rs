@@ -1,5 +1,12 @@
 use css::selector::structs::*;
-use dom::{element::Element, node::NodePtr};
+use dom::{
+    constraint_validation, details,
+    element::{Element, Namespace},
+    node::NodePtr,
+};
+use style_types::PseudoElement;
+
+use crate::ancestor_filter::AncestorFilter;
 
 fn get_parent(el: &NodePtr) -> Option<NodePtr> {
     let parent = el.parent();
@@ -11,14 +18,46 @@ fn get_parent(el: &NodePtr) -> Option<NodePtr> {
     None
 }
 
+/// Previous element sibling, skipping text and comments between them
 fn get_prev_sibling(el: &NodePtr) -> Option<NodePtr> {
-    el.prev_sibling().map(|node| NodePtr(node))
+    let mut sibling = el.prev_sibling();
+    while let Some(node) = sibling {
+        if node.is_element() {
+            return Some(NodePtr(node));
+        }
+        sibling = node.prev_sibling();
+    }
+    None
 }
 
 pub fn is_match_selectors(element: &NodePtr, selectors: &Vec<Selector>) -> bool {
-    selectors
-        .iter()
-        .any(|selector| is_match_selector(element.clone(), selector))
+    is_match_selectors_with_ancestors(element, selectors, None)
+}
+
+/// Check if the selectors match the element, skipping the selectors that the
+/// filter of the ancestors of the element rejects
+pub fn is_match_selectors_with_ancestors(
+    element: &NodePtr,
+    selectors: &[Selector],
+    ancestors: Option<&AncestorFilter>,
+) -> bool {
+    selectors.iter().any(|selector| {
+        selector.pseudo_element().is_none()
+            && !ancestors.is_some_and(|ancestors| ancestors.rejects(selector))
+            && is_match_selector(element.clone(), selector)
+    })
+}
+
+/// Check if the selectors match a pseudo-element of the element
+pub fn is_match_pseudo_element_selectors(
+    element: &NodePtr,
+    pseudo_element: &PseudoElement,
+    selectors: &[Selector],
+) -> bool {
+    selectors.iter().any(|selector| {
+        selector.pseudo_element() == Some(pseudo_element.name())
+            && is_match_selector(element.clone(), selector)
+    })
 }
 
 pub fn is_match_selector(element: NodePtr, selector: &Selector) -> bool {
@@ -35,16 +74,19 @@ pub fn is_match_selector(element: NodePtr, selector: &Selector) -> bool {
                     }
                     current_element = parent;
                 }
-                Some(Combinator::Descendant) => loop {
-                    let parent = get_parent(&el);
-                    if let Some(p) = &parent {
+                Some(Combinator::Descendant) => {
+                    let mut ancestor = get_parent(&el);
+                    while let Some(p) = &ancestor {
                         if is_match_simple_selector_seq(p, selector_seq) {
-                            current_element = parent;
                             break;
                         }
+                        ancestor = get_parent(p);
+                    }
+                    if ancestor.is_none() {
+                        return false;
                     }
-                    return false;
-                },
+                    current_element = ancestor;
+                }
                 Some(Combinator::NextSibling) => {
                     let sibling = get_prev_sibling(&el);
                     if let Some(sibling) = &sibling {
@@ -54,16 +96,19 @@ pub fn is_match_selector(element: NodePtr, selector: &Selector) -> bool {
                     }
                     current_element = sibling;
                 }
-                Some(Combinator::SubsequentSibling) => loop {
-                    let sibling = get_prev_sibling(&el);
-                    if let Some(s) = &sibling {
+                Some(Combinator::SubsequentSibling) => {
+                    let mut sibling = get_prev_sibling(&el);
+                    while let Some(s) = &sibling {
                         if is_match_simple_selector_seq(s, selector_seq) {
-                            current_element = sibling;
                             break;
                         }
+                        sibling = get_prev_sibling(s);
+                    }
+                    if sibling.is_none() {
+                        return false;
                     }
-                    return false;
-                },
+                    current_element = sibling;
+                }
                 None => {
                     if !is_match_simple_selector_seq(&el, selector_seq) {
                         return false;
@@ -78,23 +123,23 @@ pub fn is_match_selector(element: NodePtr, selector: &Selector) -> bool {
 }
 
 fn is_match_simple_selector_seq(element: &NodePtr, sequence: &SimpleSelectorSequence) -> bool {
-    if let Some(element) = element.as_element_opt() {
+    if let Some(el) = element.as_element_opt() {
         return sequence
             .values()
             .iter()
-            .all(|selector| is_match_simple_selector(element, selector));
+            .all(|selector| is_match_simple_selector(element, el, selector));
     }
 
     log::warn!("Trying to match selector on non-element: {:?}", element);
     false
 }
 
-fn is_match_simple_selector(element: &Element, selector: &SimpleSelector) -> bool {
+fn is_match_simple_selector(node: &NodePtr, element: &Element, selector: &SimpleSelector) -> bool {
     match selector.selector_type() {
         SimpleSelectorType::Universal => true,
         SimpleSelectorType::Type => {
             if let Some(type_name) = selector.value() {
-                return element.tag_name() == *type_name;
+                return is_match_type(element, type_name);
             }
             false
         }
@@ -110,10 +155,59 @@ fn is_match_simple_selector(element: &Element, selector: &SimpleSelector) -> boo
             }
             false
         }
+        SimpleSelectorType::Pseudo => match selector.value().as_deref() {
+            Some(pseudo_class) => is_match_pseudo_class(node, element, pseudo_class),
+            None => false,
+        },
+        // The pseudo-element is checked before matching the selector
+        SimpleSelectorType::PseudoElement => true,
+        _ => false,
+    }
+}
+
+/// Type selectors match HTML elements case-insensitively, and elements in
+/// other namespaces, like SVG `foreignObject`, by their exact tag name
+/// https://www.w3.org/TR/selectors-4/#type-selectors
+fn is_match_type(element: &Element, type_name: &str) -> bool {
+    match element.namespace() {
+        Namespace::Html => element.tag_name().eq_ignore_ascii_case(type_name),
+        _ => element.tag_name() == type_name,
+    }
+}
+
+fn is_match_pseudo_class(node: &NodePtr, element: &Element, pseudo_class: &str) -> bool {
+    let is_validated = || match element.tag_name().as_str() {
+        "form" => true,
+        _ => constraint_validation::is_candidate_for_constraint_validation(node),
+    };
+    let is_form_control =
+        || element.is_html("input") || element.is_html("textarea") || element.is_html("select");
+
+    match pseudo_class {
+        "required" => is_form_control() && element.has_attribute("required"),
+        "optional" => is_form_control() && !element.has_attribute("required"),
+        "valid" => is_validated() && constraint_validation::check_validity(node),
+        "invalid" => is_validated() && !constraint_validation::check_validity(node),
+        "open" => element.is_html("details") && details::is_open(node),
+        "first-of-type" => is_first_of_type(node, element),
         _ => false,
     }
 }
 
+fn is_first_of_type(node: &NodePtr, element: &Element) -> bool {
+    let mut sibling = get_prev_sibling(node);
+    while let Some(node) = sibling {
+        let is_same_type = node.as_element_opt().is_some_and(|sibling| {
+            sibling.namespace() == element.namespace() && sibling.tag_name() == element.tag_name()
+        });
+        if is_same_type {
+            return false;
+        }
+        sibling = get_prev_sibling(&node);
+    }
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,9 +215,9 @@ mod tests {
     use css::parser::Parser;
     use css::tokenizer::token::Token;
     use css::tokenizer::Tokenizer;
-    use dom::create_element;
+    use dom::{create_element, create_element_ns};
     use shared::tree_node::WeakTreeNode;
-    use test_utils::dom_creator::document;
+    use test_utils::dom_creator::{document, text};
 
     #[test]
     fn match_simple_type() {
@@ -142,6 +236,7 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(is_match_selectors(&element, selectors));
             }
+            _ => unreachable!(),
         }
     }
 
@@ -163,6 +258,7 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(is_match_selectors(&element_node, selectors));
             }
+            _ => unreachable!(),
         }
     }
 
@@ -187,9 +283,73 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(is_match_selectors(&child, selectors));
             }
+            _ => unreachable!(),
         }
     }
 
+    #[test]
+    fn match_distant_descendant() {
+        let doc = document();
+        let article = create_element(WeakTreeNode::from(&doc.0), "article");
+        let section = create_element(WeakTreeNode::from(&doc.0), "section");
+        let button = create_element(WeakTreeNode::from(&doc.0), "button");
+        article.append_child(section.0.clone());
+        section.append_child(button.0.clone());
+
+        let css = "article button { color: red; }";
+
+        let tokenizer = Tokenizer::new(css.chars());
+        let tokens = tokenizer.run();
+        let mut parser = Parser::<Token>::new(tokens);
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        let rule = stylesheet.first().unwrap();
+
+        match rule {
+            CSSRule::Style(style) => {
+                let selectors = &style.selectors;
+                assert!(is_match_selectors(&button, selectors));
+                assert!(!is_match_selectors(&section, selectors));
+
+                // The filter is trusted to hold every ancestor
+                let mut ancestors = AncestorFilter::new();
+                ancestors.push(&section);
+                assert!(!is_match_selectors_with_ancestors(
+                    &button,
+                    selectors,
+                    Some(&ancestors)
+                ));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn match_type_by_namespace() {
+        let doc = document();
+        let div = create_element(WeakTreeNode::from(&doc.0), "div");
+        let foreign_object =
+            create_element_ns(WeakTreeNode::from(&doc.0), "foreignObject", Namespace::Svg);
+        let selectors = |css: &str| {
+            let tokens = Tokenizer::new(css.chars()).run();
+            let stylesheet = Parser::<Token>::new(tokens).parse_a_css_stylesheet();
+            match stylesheet.first().unwrap() {
+                CSSRule::Style(style) => style.selectors.clone(),
+                _ => unreachable!(),
+            }
+        };
+
+        assert!(is_match_selectors(&div, &selectors("DIV { color: red; }")));
+        assert!(is_match_selectors(
+            &foreign_object,
+            &selectors("foreignObject { color: red; }")
+        ));
+        assert!(!is_match_selectors(
+            &foreign_object,
+            &selectors("foreignobject { color: red; }")
+        ));
+    }
+
     #[test]
     fn match_simple_child() {
         let doc = document();
@@ -211,6 +371,35 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(is_match_selectors(&child, selectors));
             }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn match_next_sibling_across_text() {
+        let doc = document();
+        let parent = create_element(WeakTreeNode::from(&doc.0), "msup");
+        let base = create_element(WeakTreeNode::from(&doc.0), "mi");
+        let superscript = create_element(WeakTreeNode::from(&doc.0), "mn");
+        parent.append_child(base.0.clone());
+        parent.append_child(text(" ", doc.clone()).0);
+        parent.append_child(superscript.0.clone());
+
+        let css = "mi + mn { color: red; }";
+
+        let tokenizer = Tokenizer::new(css.chars());
+        let tokens = tokenizer.run();
+        let mut parser = Parser::<Token>::new(tokens);
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        let rule = stylesheet.first().unwrap();
+
+        match rule {
+            CSSRule::Style(style) => {
+                let selectors = &style.selectors;
+                assert!(is_match_selectors(&superscript, selectors));
+            }
+            _ => unreachable!(),
         }
     }
 
@@ -235,6 +424,7 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(!is_match_selectors(&child, selectors));
             }
+            _ => unreachable!(),
         }
     }
 
@@ -259,6 +449,38 @@ mod tests {
                 let selectors = &style.selectors;
                 assert!(!is_match_selectors(&child, selectors));
             }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn match_validity_pseudo_classes() {
+        let doc = document();
+        let form = create_element(WeakTreeNode::from(&doc.0), "form");
+        let input = create_element(WeakTreeNode::from(&doc.0), "input");
+        input.as_element().set_attribute("required", "");
+        form.append_child(input.0.clone());
+
+        let css = "input:required:invalid, form:invalid { color: red; }";
+
+        let tokenizer = Tokenizer::new(css.chars());
+        let tokens = tokenizer.run();
+        let mut parser = Parser::<Token>::new(tokens);
+        let stylesheet = parser.parse_a_css_stylesheet();
+
+        let rule = stylesheet.first().unwrap();
+
+        match rule {
+            CSSRule::Style(style) => {
+                let selectors = &style.selectors;
+                assert!(is_match_selectors(&input, selectors));
+                assert!(is_match_selectors(&form, selectors));
+
+                input.as_element().set_attribute("value", "moon");
+                assert!(!is_match_selectors(&input, selectors));
+                assert!(!is_match_selectors(&form, selectors));
+            }
+            _ => unreachable!(),
         }
     }
 
@@ -284,6 +506,7 @@ mod tests {
                 assert!(is_match_selectors(&child, selectors));
                 assert!(is_match_selectors(&parent, selectors));
             }
+            _ => unreachable!(),
         }
     }
 }
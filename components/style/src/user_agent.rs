@@ -0,0 +1,115 @@
+use std::rc::Rc;
+
+use css::{parser::Parser, tokenizer::token::Token, tokenizer::Tokenizer};
+use style_types::{CSSLocation, CascadeOrigin, ContextualStyleSheet};
+
+/// Default styles of HTML elements
+/// https://html.spec.whatwg.org/multipage/rendering.html
+const USER_AGENT_STYLES: &str = include_str!("./user_agent.css");
+
+thread_local! {
+    static USER_AGENT_STYLESHEET: Rc<ContextualStyleSheet> = parse_user_agent_stylesheet();
+}
+
+/// The user-agent stylesheet. It is parsed on first use and shared by every
+/// document afterwards.
+pub fn user_agent_stylesheet() -> Rc<ContextualStyleSheet> {
+    USER_AGENT_STYLESHEET.with(|stylesheet| stylesheet.clone())
+}
+
+/// Default styles that depend on the preferences of the user: the size of
+/// monospace text relative to the size of the surrounding text, and the
+/// quotation marks of `q` elements for the language of the user
+pub fn preferences_stylesheet(
+    monospace_font_scale: f32,
+    quotes: (&str, &str),
+) -> Rc<ContextualStyleSheet> {
+    let styles = format!(
+        r#"
+        code, kbd, samp, tt, pre, listing, plaintext, xmp {{
+            font-size: {}em;
+        }}
+        q::before {{
+            content: "{}";
+        }}
+        q::after {{
+            content: "{}";
+        }}
+        "#,
+        monospace_font_scale, quotes.0, quotes.1
+    );
+    Rc::new(parse_user_agent_styles(&styles))
+}
+
+fn parse_user_agent_stylesheet() -> Rc<ContextualStyleSheet> {
+    Rc::new(parse_user_agent_styles(USER_AGENT_STYLES))
+}
+
+fn parse_user_agent_styles(styles: &str) -> ContextualStyleSheet {
+    let tokenizer = Tokenizer::new(styles.chars());
+    let mut parser = Parser::<Token>::new(tokenizer.run());
+    let stylesheet = parser.parse_a_css_stylesheet();
+
+    ContextualStyleSheet::new(stylesheet, CascadeOrigin::UserAgent, CSSLocation::External)
+}
+
+#[cfg(test)]
+mod tests {
+    use dom::node::NodePtr;
+    use style_types::{values::length::Length, Property, Value};
+    use test_utils::dom_creator::{document, element};
+
+    use super::*;
+    use crate::compute::compute_styles;
+
+    #[test]
+    fn test_user_agent_stylesheet_is_parsed_once() {
+        let first = user_agent_stylesheet();
+        let second = user_agent_stylesheet();
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(first.origin, CascadeOrigin::UserAgent);
+    }
+
+    #[test]
+    fn test_user_agent_defaults() {
+        let document = document();
+        let heading = element("h1", document.clone(), vec![]);
+        document.append_child(heading.0.clone());
+
+        document
+            .as_document()
+            .insert_user_agent_stylesheet(user_agent_stylesheet());
+        let rules = document.as_document().style_rules();
+
+        for node in [&document, &heading] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        assert_eq!(
+            heading.get_style(&Property::FontSize),
+            Value::Length(Length::new_px(32.))
+        );
+    }
+
+    #[test]
+    fn test_preferences_stylesheet() {
+        let document = document();
+        let code = element("code", document.clone(), vec![]);
+        document.append_child(code.0.clone());
+
+        document.as_document().set_default_font_size(20.);
+        document
+            .as_document()
+            .insert_user_agent_stylesheet(preferences_stylesheet(0.8, ("«", "»")));
+        let rules = document.as_document().style_rules();
+
+        for node in [&document, &code] {
+            node.set_computed_styles(compute_styles(NodePtr::clone(node), &rules));
+        }
+
+        assert_eq!(
+            code.get_style(&Property::FontSize),
+            Value::Length(Length::new_px(16.))
+        );
+    }
+}
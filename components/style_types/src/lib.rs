@@ -1,7 +1,9 @@
 pub mod contextual_style;
 pub mod property;
+pub mod pseudo_element;
 pub mod value;
 pub mod values;
 pub use contextual_style::*;
 pub use property::*;
+pub use pseudo_element::*;
 pub use value::*;
@@ -33,6 +33,11 @@ pub enum Property {
     BorderTopRightRadius,
     BorderBottomLeftRadius,
     BorderBottomRightRadius,
+    OutlineStyle,
+    OutlineWidth,
+    OutlineColor,
+    OutlineOffset,
+    BoxShadow,
     Position,
     Float,
     Left,
@@ -40,8 +45,52 @@ pub enum Property {
     Top,
     Bottom,
     Direction,
+    UnicodeBidi,
     FontSize,
+    LineHeight,
+    FontFamily,
     TextAlign,
+    TextTransform,
+    TextShadow,
+    TextStrokeWidth,
+    TextStrokeColor,
+    LetterSpacing,
+    WordSpacing,
+    WhiteSpace,
+    TabSize,
+    WritingMode,
+    GridTemplateColumns,
+    GridTemplateRows,
+    GridColumnStart,
+    GridColumnEnd,
+    GridRowStart,
+    GridRowEnd,
+    RowGap,
+    ColumnGap,
+    ColumnCount,
+    ColumnWidth,
+    ColumnRuleStyle,
+    ColumnRuleWidth,
+    ColumnRuleColor,
+    ListStyleType,
+    ListStylePosition,
+    Content,
+    CounterReset,
+    CounterIncrement,
+    Visibility,
+    BreakBefore,
+    BreakAfter,
+    AspectRatio,
+    ObjectFit,
+    OverflowX,
+    OverflowY,
+    AnimationName,
+    AnimationDuration,
+    AnimationDelay,
+    AnimationIterationCount,
+    AnimationDirection,
+    AnimationFillMode,
+    AnimationTimingFunction,
 }
 
 impl Property {
@@ -67,14 +116,67 @@ impl Property {
             "top" => Some(Property::Top),
             "bottom" => Some(Property::Bottom),
             "direction" => Some(Property::Direction),
+            "unicode-bidi" => Some(Property::UnicodeBidi),
             "border-top-left-radius" => Some(Property::BorderTopLeftRadius),
             "border-top-right-radius" => Some(Property::BorderTopRightRadius),
             "border-bottom-left-radius" => Some(Property::BorderBottomLeftRadius),
             "border-bottom-right-radius" => Some(Property::BorderBottomRightRadius),
+            "outline-style" => Some(Property::OutlineStyle),
+            "outline-width" => Some(Property::OutlineWidth),
+            "outline-color" => Some(Property::OutlineColor),
+            "outline-offset" => Some(Property::OutlineOffset),
+            "box-shadow" => Some(Property::BoxShadow),
             "font-size" => Some(Property::FontSize),
+            "line-height" => Some(Property::LineHeight),
+            "font-family" => Some(Property::FontFamily),
+            "break-before" | "page-break-before" => Some(Property::BreakBefore),
+            "break-after" | "page-break-after" => Some(Property::BreakAfter),
             "margin-block-start" => Some(Property::MarginTop),
             "margin-block-end" => Some(Property::MarginBottom),
+            "margin-inline-start" => Some(Property::MarginLeft),
+            "margin-inline-end" => Some(Property::MarginRight),
+            "padding-inline-start" => Some(Property::PaddingLeft),
+            "padding-inline-end" => Some(Property::PaddingRight),
             "text-align" => Some(Property::TextAlign),
+            "text-transform" => Some(Property::TextTransform),
+            "text-shadow" => Some(Property::TextShadow),
+            "-webkit-text-stroke-width" => Some(Property::TextStrokeWidth),
+            "-webkit-text-stroke-color" => Some(Property::TextStrokeColor),
+            "letter-spacing" => Some(Property::LetterSpacing),
+            "word-spacing" => Some(Property::WordSpacing),
+            "white-space" => Some(Property::WhiteSpace),
+            "tab-size" => Some(Property::TabSize),
+            "writing-mode" => Some(Property::WritingMode),
+            "grid-template-columns" => Some(Property::GridTemplateColumns),
+            "grid-template-rows" => Some(Property::GridTemplateRows),
+            "grid-column-start" => Some(Property::GridColumnStart),
+            "grid-column-end" => Some(Property::GridColumnEnd),
+            "grid-row-start" => Some(Property::GridRowStart),
+            "grid-row-end" => Some(Property::GridRowEnd),
+            "row-gap" | "grid-row-gap" => Some(Property::RowGap),
+            "column-gap" | "grid-column-gap" => Some(Property::ColumnGap),
+            "column-count" => Some(Property::ColumnCount),
+            "column-width" => Some(Property::ColumnWidth),
+            "column-rule-style" => Some(Property::ColumnRuleStyle),
+            "column-rule-width" => Some(Property::ColumnRuleWidth),
+            "column-rule-color" => Some(Property::ColumnRuleColor),
+            "list-style-type" => Some(Property::ListStyleType),
+            "list-style-position" => Some(Property::ListStylePosition),
+            "content" => Some(Property::Content),
+            "counter-reset" => Some(Property::CounterReset),
+            "counter-increment" => Some(Property::CounterIncrement),
+            "visibility" => Some(Property::Visibility),
+            "aspect-ratio" => Some(Property::AspectRatio),
+            "object-fit" => Some(Property::ObjectFit),
+            "overflow-x" => Some(Property::OverflowX),
+            "overflow-y" => Some(Property::OverflowY),
+            "animation-name" => Some(Property::AnimationName),
+            "animation-duration" => Some(Property::AnimationDuration),
+            "animation-delay" => Some(Property::AnimationDelay),
+            "animation-iteration-count" => Some(Property::AnimationIterationCount),
+            "animation-direction" => Some(Property::AnimationDirection),
+            "animation-fill-mode" => Some(Property::AnimationFillMode),
+            "animation-timing-function" => Some(Property::AnimationTimingFunction),
             _ => {
                 log::debug!("Unsupported CSS property: {}", property);
                 None
@@ -82,6 +184,99 @@ impl Property {
         }
     }
 
+    /// Name of the property in CSS
+    pub fn name(&self) -> &'static str {
+        match self {
+            Property::BackgroundColor => "background-color",
+            Property::Color => "color",
+            Property::Display => "display",
+            Property::Width => "width",
+            Property::Height => "height",
+            Property::MarginTop => "margin-top",
+            Property::MarginRight => "margin-right",
+            Property::MarginBottom => "margin-bottom",
+            Property::MarginLeft => "margin-left",
+            Property::PaddingTop => "padding-top",
+            Property::PaddingRight => "padding-right",
+            Property::PaddingBottom => "padding-bottom",
+            Property::PaddingLeft => "padding-left",
+            Property::BorderTopWidth => "border-top-width",
+            Property::BorderRightWidth => "border-right-width",
+            Property::BorderBottomWidth => "border-bottom-width",
+            Property::BorderLeftWidth => "border-left-width",
+            Property::BorderBottomStyle => "border-bottom-style",
+            Property::BorderLeftStyle => "border-left-style",
+            Property::BorderRightStyle => "border-right-style",
+            Property::BorderTopStyle => "border-top-style",
+            Property::BorderTopColor => "border-top-color",
+            Property::BorderRightColor => "border-right-color",
+            Property::BorderBottomColor => "border-bottom-color",
+            Property::BorderLeftColor => "border-left-color",
+            Property::BorderTopLeftRadius => "border-top-left-radius",
+            Property::BorderTopRightRadius => "border-top-right-radius",
+            Property::BorderBottomLeftRadius => "border-bottom-left-radius",
+            Property::BorderBottomRightRadius => "border-bottom-right-radius",
+            Property::OutlineStyle => "outline-style",
+            Property::OutlineWidth => "outline-width",
+            Property::OutlineColor => "outline-color",
+            Property::OutlineOffset => "outline-offset",
+            Property::BoxShadow => "box-shadow",
+            Property::Position => "position",
+            Property::Float => "float",
+            Property::Left => "left",
+            Property::Right => "right",
+            Property::Top => "top",
+            Property::Bottom => "bottom",
+            Property::Direction => "direction",
+            Property::UnicodeBidi => "unicode-bidi",
+            Property::FontSize => "font-size",
+            Property::LineHeight => "line-height",
+            Property::FontFamily => "font-family",
+            Property::TextAlign => "text-align",
+            Property::TextTransform => "text-transform",
+            Property::TextShadow => "text-shadow",
+            Property::TextStrokeWidth => "-webkit-text-stroke-width",
+            Property::TextStrokeColor => "-webkit-text-stroke-color",
+            Property::LetterSpacing => "letter-spacing",
+            Property::WordSpacing => "word-spacing",
+            Property::WhiteSpace => "white-space",
+            Property::TabSize => "tab-size",
+            Property::WritingMode => "writing-mode",
+            Property::GridTemplateColumns => "grid-template-columns",
+            Property::GridTemplateRows => "grid-template-rows",
+            Property::GridColumnStart => "grid-column-start",
+            Property::GridColumnEnd => "grid-column-end",
+            Property::GridRowStart => "grid-row-start",
+            Property::GridRowEnd => "grid-row-end",
+            Property::RowGap => "row-gap",
+            Property::ColumnGap => "column-gap",
+            Property::ColumnCount => "column-count",
+            Property::ColumnWidth => "column-width",
+            Property::ColumnRuleStyle => "column-rule-style",
+            Property::ColumnRuleWidth => "column-rule-width",
+            Property::ColumnRuleColor => "column-rule-color",
+            Property::ListStyleType => "list-style-type",
+            Property::ListStylePosition => "list-style-position",
+            Property::Content => "content",
+            Property::CounterReset => "counter-reset",
+            Property::CounterIncrement => "counter-increment",
+            Property::Visibility => "visibility",
+            Property::BreakBefore => "break-before",
+            Property::BreakAfter => "break-after",
+            Property::AspectRatio => "aspect-ratio",
+            Property::ObjectFit => "object-fit",
+            Property::OverflowX => "overflow-x",
+            Property::OverflowY => "overflow-y",
+            Property::AnimationName => "animation-name",
+            Property::AnimationDuration => "animation-duration",
+            Property::AnimationDelay => "animation-delay",
+            Property::AnimationIterationCount => "animation-iteration-count",
+            Property::AnimationDirection => "animation-direction",
+            Property::AnimationFillMode => "animation-fill-mode",
+            Property::AnimationTimingFunction => "animation-timing-function",
+        }
+    }
+
     pub fn all() -> Box<dyn Iterator<Item = Self>> {
         Box::new(Property::iter())
     }
@@ -89,7 +284,23 @@ impl Property {
     pub fn inheritable(&self) -> bool {
         match self {
             Self::FontSize => true,
+            Self::LineHeight => true,
+            Self::FontFamily => true,
             Self::Color => true,
+            Self::Direction => true,
+            Self::TextAlign => true,
+            Self::TextTransform => true,
+            Self::TextShadow => true,
+            Self::TextStrokeWidth => true,
+            Self::TextStrokeColor => true,
+            Self::LetterSpacing => true,
+            Self::WordSpacing => true,
+            Self::WhiteSpace => true,
+            Self::TabSize => true,
+            Self::WritingMode => true,
+            Self::ListStyleType => true,
+            Self::ListStylePosition => true,
+            Self::Visibility => true,
             _ => false,
         }
     }
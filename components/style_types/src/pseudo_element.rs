@@ -0,0 +1,20 @@
+/// Pseudo-elements that generate boxes
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+}
+
+impl PseudoElement {
+    pub fn all() -> [PseudoElement; 2] {
+        [PseudoElement::Before, PseudoElement::After]
+    }
+
+    /// Name of the pseudo-element as written in selectors
+    pub fn name(&self) -> &'static str {
+        match self {
+            PseudoElement::Before => "before",
+            PseudoElement::After => "after",
+        }
+    }
+}
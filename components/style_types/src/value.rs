@@ -1,6 +1,14 @@
 use css::{parser::structs::ComponentValue, tokenizer::token::Token};
 
-use crate::{property::Property, values::length::LengthUnit};
+use std::fmt;
+
+use crate::{
+    property::Property,
+    values::{
+        display::{DisplayBox, InnerDisplayType, OuterDisplayType},
+        length::LengthUnit,
+    },
+};
 
 use super::values::prelude::*;
 
@@ -12,23 +20,9 @@ macro_rules! parse_value {
             None
         }
     }};
-    (Inherit; $tokens:ident) => {{
-        if parse_keyword($tokens, "inherit") {
-            Some(Value::Inherit)
-        } else {
-            None
-        }
-    }};
-    (Initial; $tokens:ident) => {{
-        if parse_keyword($tokens, "initial") {
-            Some(Value::Initial)
-        } else {
-            None
-        }
-    }};
-    (Unset; $tokens:ident) => {{
-        if parse_keyword($tokens, "unset") {
-            Some(Value::Unset)
+    (Normal; $tokens:ident) => {{
+        if parse_keyword($tokens, "normal") {
+            Some(Value::Normal)
         } else {
             None
         }
@@ -56,14 +50,42 @@ pub enum Value {
     Display(Display),
     Length(Length),
     Percentage(Percentage),
+    LineHeight(LineHeight),
     BorderStyle(BorderStyle),
     BorderWidth(BorderWidth),
     Float(Float),
     Position(Position),
     Direction(Direction),
+    UnicodeBidi(UnicodeBidi),
     BorderRadius(BorderRadius),
+    BoxShadow(BoxShadow),
     TextAlign(TextAlign),
+    TextTransform(TextTransform),
+    TextShadow(TextShadow),
+    ListStyleType(ListStyleType),
+    ListStylePosition(ListStylePosition),
+    Content(Content),
+    CounterList(CounterList),
+    Visibility(Visibility),
+    BreakBetween(BreakBetween),
+    FontFamily(FontFamily),
+    WhiteSpace(WhiteSpace),
+    TabSize(TabSize),
+    WritingMode(WritingMode),
+    TrackList(TrackList),
+    GridLine(GridLine),
+    ColumnCount(ColumnCount),
+    AspectRatio(AspectRatio),
+    ObjectFit(ObjectFit),
+    Overflow(Overflow),
+    AnimationName(AnimationName),
+    Time(Time),
+    IterationCount(IterationCount),
+    AnimationDirection(AnimationDirection),
+    AnimationFillMode(AnimationFillMode),
+    TimingFunction(TimingFunction),
     Auto,
+    Normal,
     Inherit,
     Initial,
     Unset,
@@ -71,162 +93,374 @@ pub enum Value {
 
 impl Value {
     pub fn parse(property: &Property, tokens: &[ComponentValue]) -> Option<Self> {
+        // The CSS-wide keywords are valid for every property, and take
+        // precedence over the keywords of the property itself
+        if let Some(keyword) = Value::parse_css_wide_keyword(tokens) {
+            return Some(keyword);
+        }
         match property {
             Property::BackgroundColor => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::Color => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::Display => parse_value!(
-                Display | Inherit | Initial | Unset;
+                Display;
                 tokens
             ),
             Property::Width => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::Height => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::MarginTop => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::MarginRight => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::MarginBottom => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::MarginLeft => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::PaddingTop => parse_value!(
-                Length | Percentage | Inherit | Initial | Unset;
+                Length | Percentage;
                 tokens
             ),
             Property::PaddingRight => parse_value!(
-                Length | Percentage | Inherit | Initial | Unset;
+                Length | Percentage;
                 tokens
             ),
             Property::PaddingBottom => parse_value!(
-                Length | Percentage | Inherit | Initial | Unset;
+                Length | Percentage;
                 tokens
             ),
             Property::PaddingLeft => parse_value!(
-                Length | Percentage | Inherit | Initial | Unset;
+                Length | Percentage;
                 tokens
             ),
             Property::BorderTopStyle => parse_value!(
-                BorderStyle | Inherit | Initial | Unset;
+                BorderStyle;
                 tokens
             ),
             Property::BorderRightStyle => parse_value!(
-                BorderStyle | Inherit | Initial | Unset;
+                BorderStyle;
                 tokens
             ),
             Property::BorderBottomStyle => parse_value!(
-                BorderStyle | Inherit | Initial | Unset;
+                BorderStyle;
                 tokens
             ),
             Property::BorderLeftStyle => parse_value!(
-                BorderStyle | Inherit | Initial | Unset;
+                BorderStyle;
                 tokens
             ),
             Property::BorderTopWidth => parse_value!(
-                BorderWidth | Length | Inherit | Initial | Unset;
+                BorderWidth | Length;
                 tokens
             ),
             Property::BorderRightWidth => parse_value!(
-                BorderWidth | Length | Inherit | Initial | Unset;
+                BorderWidth | Length;
                 tokens
             ),
             Property::BorderBottomWidth => parse_value!(
-                BorderWidth | Length | Inherit | Initial | Unset;
+                BorderWidth | Length;
                 tokens
             ),
             Property::BorderLeftWidth => parse_value!(
-                BorderWidth | Length | Inherit | Initial | Unset;
+                BorderWidth | Length;
                 tokens
             ),
             Property::BorderTopColor => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::BorderRightColor => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::BorderBottomColor => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::BorderLeftColor => parse_value!(
-                Color | Inherit | Initial | Unset;
+                Color;
                 tokens
             ),
             Property::Float => parse_value!(
-                Float | Inherit | Initial | Unset;
+                Float;
                 tokens
             ),
             Property::Position => parse_value!(
-                Position | Inherit | Initial | Unset;
+                Position;
                 tokens
             ),
             Property::Top => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::Right => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::Bottom => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::Left => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
                 tokens
             ),
             Property::Direction => parse_value!(
-                Direction | Inherit | Initial | Unset;
+                Direction;
+                tokens
+            ),
+            Property::UnicodeBidi => parse_value!(
+                UnicodeBidi;
                 tokens
             ),
             Property::BorderTopLeftRadius => parse_value!(
-                BorderRadius | Inherit | Initial | Unset;
+                BorderRadius;
                 tokens
             ),
             Property::BorderTopRightRadius => parse_value!(
-                BorderRadius | Inherit | Initial | Unset;
+                BorderRadius;
                 tokens
             ),
             Property::BorderBottomLeftRadius => parse_value!(
-                BorderRadius | Inherit | Initial | Unset;
+                BorderRadius;
                 tokens
             ),
             Property::BorderBottomRightRadius => parse_value!(
-                BorderRadius | Inherit | Initial | Unset;
+                BorderRadius;
                 tokens
             ),
             Property::FontSize => parse_value!(
-                Length | Percentage | Auto | Inherit | Initial | Unset;
+                Length | Percentage | Auto;
+                tokens
+            ),
+            Property::LineHeight => parse_value!(
+                LineHeight;
                 tokens
             ),
             Property::TextAlign => parse_value!(
-                TextAlign | Inherit;
+                TextAlign;
+                tokens
+            ),
+            Property::ListStyleType => parse_value!(
+                ListStyleType;
+                tokens
+            ),
+            Property::ListStylePosition => parse_value!(
+                ListStylePosition;
+                tokens
+            ),
+            Property::Content => parse_value!(
+                Content;
+                tokens
+            ),
+            Property::CounterReset => CounterList::parse(tokens, 0).map(Value::CounterList),
+            Property::CounterIncrement => CounterList::parse(tokens, 1).map(Value::CounterList),
+            Property::Visibility => parse_value!(
+                Visibility;
+                tokens
+            ),
+            Property::BreakBefore => parse_value!(
+                BreakBetween;
+                tokens
+            ),
+            Property::BreakAfter => parse_value!(
+                BreakBetween;
+                tokens
+            ),
+            // keywords first, since they are also valid family names
+            Property::OutlineStyle => parse_value!(
+                BorderStyle;
+                tokens
+            ),
+            Property::OutlineWidth => parse_value!(
+                BorderWidth | Length;
+                tokens
+            ),
+            Property::OutlineColor => parse_value!(
+                Color;
+                tokens
+            ),
+            Property::OutlineOffset => parse_value!(
+                Length;
+                tokens
+            ),
+            Property::TextTransform => parse_value!(
+                TextTransform;
+                tokens
+            ),
+            Property::TextShadow => parse_value!(
+                TextShadow;
+                tokens
+            ),
+            Property::TextStrokeWidth => parse_value!(
+                BorderWidth | Length;
+                tokens
+            ),
+            Property::TextStrokeColor => parse_value!(
+                Color;
+                tokens
+            ),
+            // `normal` spacing is the same as no extra spacing
+            Property::LetterSpacing => parse_value!(
+                Length;
+                tokens
+            )
+            .or_else(|| parse_normal_spacing(tokens)),
+            Property::WordSpacing => parse_value!(
+                Length;
+                tokens
+            )
+            .or_else(|| parse_normal_spacing(tokens)),
+            Property::WhiteSpace => parse_value!(
+                WhiteSpace;
+                tokens
+            ),
+            Property::TabSize => parse_value!(
+                TabSize;
+                tokens
+            ),
+            Property::WritingMode => parse_value!(
+                WritingMode;
+                tokens
+            ),
+            Property::GridTemplateColumns => parse_value!(
+                TrackList;
+                tokens
+            ),
+            Property::GridTemplateRows => parse_value!(
+                TrackList;
+                tokens
+            ),
+            Property::GridColumnStart => parse_value!(
+                GridLine;
+                tokens
+            ),
+            Property::GridColumnEnd => parse_value!(
+                GridLine;
+                tokens
+            ),
+            Property::GridRowStart => parse_value!(
+                GridLine;
+                tokens
+            ),
+            Property::GridRowEnd => parse_value!(
+                GridLine;
+                tokens
+            ),
+            Property::RowGap => parse_value!(
+                Length | Percentage | Normal;
+                tokens
+            ),
+            Property::ColumnGap => parse_value!(
+                Length | Percentage | Normal;
+                tokens
+            ),
+            Property::ColumnCount => parse_value!(
+                ColumnCount | Auto;
+                tokens
+            ),
+            Property::ColumnWidth => parse_value!(
+                Length | Auto;
+                tokens
+            ),
+            Property::ColumnRuleStyle => parse_value!(
+                BorderStyle;
+                tokens
+            ),
+            Property::ColumnRuleWidth => parse_value!(
+                BorderWidth | Length;
+                tokens
+            ),
+            Property::ColumnRuleColor => parse_value!(
+                Color;
+                tokens
+            ),
+            Property::BoxShadow => parse_value!(
+                BoxShadow;
+                tokens
+            ),
+            Property::FontFamily => parse_value!(
+                FontFamily;
+                tokens
+            ),
+            // the ratio first, since it can start with `auto`
+            Property::AspectRatio => parse_value!(
+                AspectRatio | Auto;
+                tokens
+            ),
+            Property::ObjectFit => parse_value!(
+                ObjectFit;
+                tokens
+            ),
+            Property::OverflowX | Property::OverflowY => parse_value!(
+                Overflow;
+                tokens
+            ),
+            Property::AnimationName => parse_value!(
+                AnimationName;
+                tokens
+            ),
+            Property::AnimationDuration | Property::AnimationDelay => parse_value!(
+                Time;
+                tokens
+            ),
+            Property::AnimationIterationCount => parse_value!(
+                IterationCount;
+                tokens
+            ),
+            Property::AnimationDirection => parse_value!(
+                AnimationDirection;
+                tokens
+            ),
+            Property::AnimationFillMode => parse_value!(
+                AnimationFillMode;
+                tokens
+            ),
+            Property::AnimationTimingFunction => parse_value!(
+                TimingFunction;
                 tokens
             ),
         }
     }
 
+    /// https://www.w3.org/TR/css3-cascade/#defaulting-keywords
+    pub fn parse_css_wide_keyword(tokens: &[ComponentValue]) -> Option<Self> {
+        let mut tokens = tokens
+            .iter()
+            .filter(|token| !matches!(token, ComponentValue::PerservedToken(Token::Whitespace)));
+        let tokens = match (tokens.next(), tokens.next()) {
+            (Some(token), None) => std::slice::from_ref(token),
+            _ => return None,
+        };
+        if parse_keyword(tokens, "inherit") {
+            Some(Value::Inherit)
+        } else if parse_keyword(tokens, "initial") {
+            Some(Value::Initial)
+        } else if parse_keyword(tokens, "unset") {
+            Some(Value::Unset)
+        } else {
+            None
+        }
+    }
+
     pub fn initial(property: &Property) -> Value {
         match property {
             Property::BackgroundColor => Value::Color(Color::transparent()),
@@ -250,10 +484,10 @@ impl Value {
             Property::BorderRightWidth => Value::BorderWidth(BorderWidth::Medium),
             Property::BorderBottomWidth => Value::BorderWidth(BorderWidth::Medium),
             Property::BorderLeftWidth => Value::BorderWidth(BorderWidth::Medium),
-            Property::BorderTopColor => Value::Color(Color::black()),
-            Property::BorderRightColor => Value::Color(Color::black()),
-            Property::BorderBottomColor => Value::Color(Color::black()),
-            Property::BorderLeftColor => Value::Color(Color::black()),
+            Property::BorderTopColor => Value::Color(Color::CurrentColor),
+            Property::BorderRightColor => Value::Color(Color::CurrentColor),
+            Property::BorderBottomColor => Value::Color(Color::CurrentColor),
+            Property::BorderLeftColor => Value::Color(Color::CurrentColor),
             Property::Float => Value::Float(Float::None),
             Property::Position => Value::Position(Position::Static),
             Property::Left => Value::Auto,
@@ -261,13 +495,66 @@ impl Value {
             Property::Bottom => Value::Auto,
             Property::Top => Value::Auto,
             Property::Direction => Value::Direction(Direction::Ltr),
+            Property::UnicodeBidi => Value::UnicodeBidi(UnicodeBidi::Normal),
             Property::BorderTopLeftRadius => Value::BorderRadius(BorderRadius::zero()),
             Property::BorderTopRightRadius => Value::BorderRadius(BorderRadius::zero()),
             Property::BorderBottomLeftRadius => Value::BorderRadius(BorderRadius::zero()),
             Property::BorderBottomRightRadius => Value::BorderRadius(BorderRadius::zero()),
+            Property::OutlineStyle => Value::BorderStyle(BorderStyle::None),
+            Property::OutlineWidth => Value::BorderWidth(BorderWidth::Medium),
+            Property::OutlineColor => Value::Color(Color::CurrentColor),
+            Property::OutlineOffset => Value::Length(Length::zero()),
+            Property::BoxShadow => Value::BoxShadow(BoxShadow(Vec::new())),
             // TODO: replace with `medium` when we support absolute size
             Property::FontSize => Value::Length(Length::new_px(16.)),
-            Property::TextAlign => Value::TextAlign(TextAlign::Left),
+            Property::LineHeight => Value::LineHeight(LineHeight::Normal),
+            Property::TextAlign => Value::TextAlign(TextAlign::Start),
+            Property::TextTransform => Value::TextTransform(TextTransform::None),
+            Property::TextShadow => Value::TextShadow(TextShadow(Vec::new())),
+            Property::TextStrokeWidth => Value::Length(Length::zero()),
+            Property::TextStrokeColor => Value::Color(Color::CurrentColor),
+            Property::LetterSpacing => Value::Length(Length::zero()),
+            Property::WordSpacing => Value::Length(Length::zero()),
+            Property::WhiteSpace => Value::WhiteSpace(WhiteSpace::Normal),
+            Property::TabSize => Value::TabSize(TabSize(8)),
+            Property::WritingMode => Value::WritingMode(WritingMode::HorizontalTb),
+            Property::GridTemplateColumns => Value::TrackList(TrackList(Vec::new())),
+            Property::GridTemplateRows => Value::TrackList(TrackList(Vec::new())),
+            Property::GridColumnStart => Value::GridLine(GridLine::Auto),
+            Property::GridColumnEnd => Value::GridLine(GridLine::Auto),
+            Property::GridRowStart => Value::GridLine(GridLine::Auto),
+            Property::GridRowEnd => Value::GridLine(GridLine::Auto),
+            Property::RowGap => Value::Normal,
+            Property::ColumnGap => Value::Normal,
+            Property::ColumnCount => Value::Auto,
+            Property::ColumnWidth => Value::Auto,
+            Property::ColumnRuleStyle => Value::BorderStyle(BorderStyle::None),
+            Property::ColumnRuleWidth => Value::BorderWidth(BorderWidth::Medium),
+            Property::ColumnRuleColor => Value::Color(Color::CurrentColor),
+            Property::ListStyleType => Value::ListStyleType(ListStyleType::Disc),
+            Property::ListStylePosition => Value::ListStylePosition(ListStylePosition::Outside),
+            Property::Content => Value::Content(Content::Normal),
+            Property::CounterReset => Value::CounterList(CounterList(Vec::new())),
+            Property::CounterIncrement => Value::CounterList(CounterList(Vec::new())),
+            Property::Visibility => Value::Visibility(Visibility::Visible),
+            Property::BreakBefore => Value::BreakBetween(BreakBetween::Auto),
+            Property::BreakAfter => Value::BreakBetween(BreakBetween::Auto),
+            Property::FontFamily => Value::FontFamily(FontFamily(Vec::new())),
+            Property::AspectRatio => Value::Auto,
+            Property::ObjectFit => Value::ObjectFit(ObjectFit::Fill),
+            Property::OverflowX => Value::Overflow(Overflow::Visible),
+            Property::OverflowY => Value::Overflow(Overflow::Visible),
+            Property::AnimationName => Value::AnimationName(AnimationName::None),
+            Property::AnimationDuration => Value::Time(Time(0.0.into())),
+            Property::AnimationDelay => Value::Time(Time(0.0.into())),
+            Property::AnimationIterationCount => {
+                Value::IterationCount(IterationCount::Count(1.0.into()))
+            }
+            Property::AnimationDirection => Value::AnimationDirection(AnimationDirection::Normal),
+            Property::AnimationFillMode => Value::AnimationFillMode(AnimationFillMode::None),
+            Property::AnimationTimingFunction => Value::TimingFunction(
+                TimingFunction::CubicBezier(0.25.into(), 0.1.into(), 0.25.into(), 1.0.into()),
+            ),
         }
     }
 
@@ -299,6 +586,263 @@ impl Value {
     }
 }
 
+/// Serialize the value as CSS text
+/// https://www.w3.org/TR/cssom-1/#serializing-css-values
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Color(color) => write_color(f, color),
+            Value::Display(display) => write_display(f, display),
+            Value::Length(length) => write_length(f, length),
+            Value::Percentage(percentage) => write!(f, "{}%", *percentage.0),
+            Value::BorderRadius(BorderRadius(horizontal, vertical)) => {
+                write_length_percentage(f, horizontal)?;
+                if horizontal != vertical {
+                    write!(f, " ")?;
+                    write_length_percentage(f, vertical)?;
+                }
+                Ok(())
+            }
+            Value::BoxShadow(BoxShadow(shadows)) => write_shadows(f, shadows, true),
+            Value::TextShadow(TextShadow(shadows)) => write_shadows(f, shadows, false),
+            Value::Content(Content::Text(text)) => write!(f, "{:?}", text),
+            Value::Content(Content::Items(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    write_content_item(f, item)?;
+                }
+                Ok(())
+            }
+            Value::CounterList(CounterList(counters)) if counters.is_empty() => write!(f, "none"),
+            Value::CounterList(CounterList(counters)) => {
+                let counters = counters
+                    .iter()
+                    .map(|(name, value)| format!("{} {}", name, value))
+                    .collect::<Vec<String>>();
+                write!(f, "{}", counters.join(" "))
+            }
+            Value::FontFamily(family) => {
+                let names = family
+                    .names()
+                    .iter()
+                    .map(|name| match name.contains(' ') {
+                        true => format!("{:?}", name),
+                        false => name.clone(),
+                    })
+                    .collect::<Vec<String>>();
+                write!(f, "{}", names.join(", "))
+            }
+            Value::LineHeight(LineHeight::Normal) => write!(f, "normal"),
+            Value::LineHeight(LineHeight::Number(number)) => write!(f, "{}", **number),
+            Value::LineHeight(LineHeight::Length(length)) => write_length(f, length),
+            Value::LineHeight(LineHeight::Percentage(percentage)) => {
+                write!(f, "{}%", *percentage.0)
+            }
+            Value::BorderStyle(value) => write_keyword(f, value),
+            Value::BorderWidth(value) => write_keyword(f, value),
+            Value::Float(value) => write_keyword(f, value),
+            Value::Position(value) => write_keyword(f, value),
+            Value::Direction(value) => write_keyword(f, value),
+            Value::UnicodeBidi(value) => write_keyword(f, value),
+            Value::TextAlign(value) => write_keyword(f, value),
+            Value::TextTransform(value) => write_keyword(f, value),
+            Value::ListStyleType(value) => write_keyword(f, value),
+            Value::ListStylePosition(value) => write_keyword(f, value),
+            Value::Content(value) => write_keyword(f, value),
+            Value::Visibility(value) => write_keyword(f, value),
+            Value::WhiteSpace(value) => write_keyword(f, value),
+            Value::TabSize(TabSize(value)) => write!(f, "{}", value),
+            Value::WritingMode(value) => write_keyword(f, value),
+            Value::TrackList(TrackList(tracks)) if tracks.is_empty() => write!(f, "none"),
+            Value::TrackList(TrackList(tracks)) => {
+                for (index, track) in tracks.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " ")?;
+                    }
+                    match track {
+                        TrackSize::Length(length) => write_length(f, length)?,
+                        TrackSize::Fraction(fraction) => write!(f, "{}fr", **fraction)?,
+                        TrackSize::Auto => write!(f, "auto")?,
+                    }
+                }
+                Ok(())
+            }
+            Value::GridLine(GridLine::Auto) => write!(f, "auto"),
+            Value::GridLine(GridLine::Line(line)) => write!(f, "{}", line),
+            Value::GridLine(GridLine::Span(span)) => write!(f, "span {}", span),
+            Value::BreakBetween(value) => write_keyword(f, value),
+            Value::ColumnCount(ColumnCount(count)) => write!(f, "{}", count),
+            Value::AspectRatio(ratio) => {
+                if ratio.prefers_natural {
+                    write!(f, "auto ")?;
+                }
+                write!(f, "{} / {}", *ratio.width, *ratio.height)
+            }
+            Value::ObjectFit(value) => write_keyword(f, value),
+            Value::Overflow(value) => write_keyword(f, value),
+            Value::AnimationName(AnimationName::None) => write!(f, "none"),
+            Value::AnimationName(AnimationName::Name(name)) => write!(f, "{}", name),
+            Value::Time(time) => write!(f, "{}s", time.to_ms() / 1000.),
+            Value::IterationCount(IterationCount::Infinite) => write!(f, "infinite"),
+            Value::IterationCount(IterationCount::Count(count)) => write!(f, "{}", **count),
+            Value::AnimationDirection(value) => write_keyword(f, value),
+            Value::AnimationFillMode(value) => write_keyword(f, value),
+            Value::TimingFunction(TimingFunction::Linear) => write!(f, "linear"),
+            Value::TimingFunction(TimingFunction::CubicBezier(x1, y1, x2, y2)) => {
+                write!(f, "cubic-bezier({}, {}, {}, {})", **x1, **y1, **x2, **y2)
+            }
+            Value::Auto => write!(f, "auto"),
+            Value::Normal => write!(f, "normal"),
+            Value::Inherit => write!(f, "inherit"),
+            Value::Initial => write!(f, "initial"),
+            Value::Unset => write!(f, "unset"),
+        }
+    }
+}
+
+/// Write a keyword variant, named in CamelCase, in kebab-case
+fn write_keyword<T: fmt::Debug>(f: &mut fmt::Formatter<'_>, keyword: &T) -> fmt::Result {
+    let name = format!("{:?}", keyword);
+    for (index, ch) in name.chars().enumerate() {
+        if ch.is_ascii_uppercase() && index > 0 {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", ch.to_ascii_lowercase())?;
+    }
+    Ok(())
+}
+
+/// Serialize a list of shadows, without the spread distances of text
+/// shadows which have none
+fn write_shadows(f: &mut fmt::Formatter<'_>, shadows: &[Shadow], with_spread: bool) -> fmt::Result {
+    if shadows.is_empty() {
+        return write!(f, "none");
+    }
+    for (index, shadow) in shadows.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write_shadow(f, shadow, with_spread)?;
+    }
+    Ok(())
+}
+
+/// Shadows are serialized like browsers do, with the color first
+fn write_shadow(f: &mut fmt::Formatter<'_>, shadow: &Shadow, with_spread: bool) -> fmt::Result {
+    write_color(f, &shadow.color)?;
+    let lengths = [&shadow.offset_x, &shadow.offset_y, &shadow.blur_radius];
+    let spread = with_spread.then_some(&shadow.spread_distance);
+    for length in lengths.into_iter().chain(spread) {
+        write!(f, " ")?;
+        write_length(f, length)?;
+    }
+    if shadow.inset {
+        write!(f, " inset")?;
+    }
+    Ok(())
+}
+
+fn write_content_item(f: &mut fmt::Formatter<'_>, item: &ContentItem) -> fmt::Result {
+    match item {
+        ContentItem::Text(text) => write!(f, "{:?}", text),
+        ContentItem::Counter { name, style } => {
+            write!(f, "counter({}", name)?;
+            if *style != ListStyleType::Decimal {
+                write!(f, ", ")?;
+                write_keyword(f, style)?;
+            }
+            write!(f, ")")
+        }
+        ContentItem::Counters {
+            name,
+            separator,
+            style,
+        } => {
+            write!(f, "counters({}, {:?}", name, separator)?;
+            if *style != ListStyleType::Decimal {
+                write!(f, ", ")?;
+                write_keyword(f, style)?;
+            }
+            write!(f, ")")
+        }
+    }
+}
+
+fn write_length(f: &mut fmt::Formatter<'_>, length: &Length) -> fmt::Result {
+    write!(f, "{}", *length.value)?;
+    write_keyword(f, &length.unit)
+}
+
+fn write_length_percentage(f: &mut fmt::Formatter<'_>, value: &LengthPercentage) -> fmt::Result {
+    match value {
+        LengthPercentage::Length(length) => write_length(f, length),
+        LengthPercentage::Percentage(percentage) => write!(f, "{}%", *percentage.0),
+    }
+}
+
+fn write_color(f: &mut fmt::Formatter<'_>, color: &Color) -> fmt::Result {
+    match color {
+        Color::CurrentColor => write!(f, "currentcolor"),
+        Color::Transparent => write!(f, "rgba(0, 0, 0, 0)"),
+        Color::Rgba(r, g, b, a) if a.as_u8() == 255 => {
+            write!(f, "rgb({}, {}, {})", r.as_u8(), g.as_u8(), b.as_u8())
+        }
+        Color::Rgba(r, g, b, a) => {
+            // alpha is rounded to two decimals like browsers do
+            let alpha = (a.as_u8() as f32 / 255. * 100.).round() / 100.;
+            write!(
+                f,
+                "rgba({}, {}, {}, {})",
+                r.as_u8(),
+                g.as_u8(),
+                b.as_u8(),
+                alpha
+            )
+        }
+    }
+}
+
+fn write_display(f: &mut fmt::Formatter<'_>, display: &Display) -> fmt::Result {
+    match display {
+        Display::Box(DisplayBox::None) => write!(f, "none"),
+        Display::Box(DisplayBox::Contents) => write!(f, "contents"),
+        Display::Full(OuterDisplayType::Inline, InnerDisplayType::FlowRoot) => {
+            write!(f, "inline-block")
+        }
+        Display::Full(OuterDisplayType::Inline, InnerDisplayType::Grid) => {
+            write!(f, "inline-grid")
+        }
+        Display::Full(OuterDisplayType::Inline, InnerDisplayType::Math) => write!(f, "math"),
+        Display::Full(OuterDisplayType::Block, InnerDisplayType::Math) => write!(f, "block math"),
+        Display::Full(outer, InnerDisplayType::Flow) => write_keyword(f, outer),
+        Display::Full(OuterDisplayType::Block, inner) => write_keyword(f, inner),
+        Display::Full(outer, inner) => {
+            write_keyword(f, outer)?;
+            write!(f, " ")?;
+            write_keyword(f, inner)
+        }
+        Display::ListItem(OuterDisplayType::Block, InnerDisplayType::Flow) => {
+            write!(f, "list-item")
+        }
+        Display::ListItem(outer, inner) => {
+            write_keyword(f, outer)?;
+            write!(f, " ")?;
+            write_keyword(f, inner)?;
+            write!(f, " list-item")
+        }
+    }
+}
+
+fn parse_normal_spacing(tokens: &[ComponentValue]) -> Option<Value> {
+    if parse_keyword(tokens, "normal") {
+        Some(Value::Length(Length::zero()))
+    } else {
+        None
+    }
+}
+
 fn parse_keyword(tokens: &[ComponentValue], keyword: &str) -> bool {
     match tokens.iter().next() {
         Some(ComponentValue::PerservedToken(Token::Ident(word))) => {
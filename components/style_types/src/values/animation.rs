@@ -0,0 +1,228 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+use super::number::Number;
+
+/// Name of the `@keyframes` rule an element is animated with
+/// https://www.w3.org/TR/css-animations-1/#animation-name
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AnimationName {
+    None,
+    Name(String),
+}
+
+/// A duration, in milliseconds
+/// https://www.w3.org/TR/css-values-4/#time
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Time(pub Number);
+
+/// https://www.w3.org/TR/css-animations-1/#animation-iteration-count
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum IterationCount {
+    Infinite,
+    Count(Number),
+}
+
+/// https://www.w3.org/TR/css-animations-1/#animation-direction
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AnimationDirection {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+/// https://www.w3.org/TR/css-animations-1/#animation-fill-mode
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum AnimationFillMode {
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+/// How the progress of an animation is eased between keyframes
+/// https://www.w3.org/TR/css-easing-1/#easing-functions
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum TimingFunction {
+    Linear,
+    /// Control points of a cubic Bézier curve from (0, 0) to (1, 1)
+    CubicBezier(Number, Number, Number, Number),
+}
+
+impl AnimationName {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(name))] => {
+                match name.to_ascii_lowercase().as_str() {
+                    "none" => Some(AnimationName::None),
+                    // CSS-wide keywords can't name animations
+                    "inherit" | "initial" | "unset" | "default" => None,
+                    _ => Some(AnimationName::Name(name.clone())),
+                }
+            }
+            [ComponentValue::PerservedToken(Token::Str(name))] => {
+                Some(AnimationName::Name(name.clone()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Time {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Dimension { value, unit, .. })] => {
+                match unit.to_ascii_lowercase().as_str() {
+                    "s" => Some(Time((value * 1000.).into())),
+                    "ms" => Some(Time((*value).into())),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_ms(&self) -> f32 {
+        *self.0
+    }
+}
+
+impl IterationCount {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(keyword))]
+                if keyword.eq_ignore_ascii_case("infinite") =>
+            {
+                Some(IterationCount::Infinite)
+            }
+            [ComponentValue::PerservedToken(Token::Number { value, .. })] if *value >= 0. => {
+                Some(IterationCount::Count((*value).into()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AnimationDirection {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(value))] => match value {
+                v if v.eq_ignore_ascii_case("normal") => Some(AnimationDirection::Normal),
+                v if v.eq_ignore_ascii_case("reverse") => Some(AnimationDirection::Reverse),
+                v if v.eq_ignore_ascii_case("alternate") => Some(AnimationDirection::Alternate),
+                v if v.eq_ignore_ascii_case("alternate-reverse") => {
+                    Some(AnimationDirection::AlternateReverse)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl AnimationFillMode {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(value))] => match value {
+                v if v.eq_ignore_ascii_case("none") => Some(AnimationFillMode::None),
+                v if v.eq_ignore_ascii_case("forwards") => Some(AnimationFillMode::Forwards),
+                v if v.eq_ignore_ascii_case("backwards") => Some(AnimationFillMode::Backwards),
+                v if v.eq_ignore_ascii_case("both") => Some(AnimationFillMode::Both),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl TimingFunction {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let bezier = |x1: f32, y1: f32, x2: f32, y2: f32| {
+            TimingFunction::CubicBezier(x1.into(), y1.into(), x2.into(), y2.into())
+        };
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(keyword))] => {
+                match keyword.to_ascii_lowercase().as_str() {
+                    "linear" => Some(TimingFunction::Linear),
+                    "ease" => Some(bezier(0.25, 0.1, 0.25, 1.)),
+                    "ease-in" => Some(bezier(0.42, 0., 1., 1.)),
+                    "ease-out" => Some(bezier(0., 0., 0.58, 1.)),
+                    "ease-in-out" => Some(bezier(0.42, 0., 0.58, 1.)),
+                    _ => None,
+                }
+            }
+            [ComponentValue::Function(function)]
+                if function.name.eq_ignore_ascii_case("cubic-bezier") =>
+            {
+                let numbers = function
+                    .value
+                    .iter()
+                    .filter_map(|value| match value {
+                        ComponentValue::PerservedToken(Token::Number { value, .. }) => {
+                            Some(Some(*value))
+                        }
+                        ComponentValue::PerservedToken(Token::Whitespace | Token::Comma) => None,
+                        _ => Some(None),
+                    })
+                    .collect::<Option<Vec<f32>>>()?;
+                match numbers[..] {
+                    // The curve has to be a function of time
+                    [x1, y1, x2, y2] if (0. ..=1.).contains(&x1) && (0. ..=1.).contains(&x2) => {
+                        Some(bezier(x1, y1, x2, y2))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Eased progress at `progress`, both usually between 0 and 1
+    pub fn ease(&self, progress: f32) -> f32 {
+        let (x1, y1, x2, y2) = match self {
+            TimingFunction::Linear => return progress,
+            TimingFunction::CubicBezier(x1, y1, x2, y2) => (**x1, **y1, **x2, **y2),
+        };
+        if progress <= 0. || progress >= 1. {
+            return progress;
+        }
+        let bezier = |a: f32, b: f32, t: f32| {
+            3. * a * t * (1. - t).powi(2) + 3. * b * t.powi(2) * (1. - t) + t.powi(3)
+        };
+
+        // The x of the curve grows with t, so t is found by bisection
+        let (mut low, mut high) = (0., 1.);
+        let mut t = progress;
+        for _ in 0..32 {
+            let x = bezier(x1, x2, t);
+            if (x - progress).abs() < 1e-5 {
+                break;
+            }
+            if x < progress {
+                low = t;
+            } else {
+                high = t;
+            }
+            t = (low + high) / 2.;
+        }
+        bezier(y1, y2, t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_functions() {
+        let ease_in_out =
+            TimingFunction::CubicBezier(0.42.into(), 0.0.into(), 0.58.into(), 1.0.into());
+        assert_eq!(TimingFunction::Linear.ease(0.3), 0.3);
+        // The curve is symmetric around its middle
+        assert!((ease_in_out.ease(0.5) - 0.5).abs() < 0.001);
+        assert!(ease_in_out.ease(0.2) < 0.2);
+        assert!((ease_in_out.ease(0.2) + ease_in_out.ease(0.8) - 1.).abs() < 0.001);
+        assert_eq!(ease_in_out.ease(1.), 1.);
+    }
+}
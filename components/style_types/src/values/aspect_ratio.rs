@@ -0,0 +1,100 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+use super::number::Number;
+
+/// Preferred aspect ratio of a box, `auto` alone is `Value::Auto`
+/// https://www.w3.org/TR/css-sizing-4/#aspect-ratio
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct AspectRatio {
+    pub width: Number,
+    pub height: Number,
+    /// `auto && <ratio>`: the natural aspect ratio of a replaced element is
+    /// used if it has one
+    pub prefers_natural: bool,
+}
+
+impl AspectRatio {
+    /// Parse `auto || <ratio>`, where `<ratio>` is `<number> [ / <number> ]?`
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let values = values
+            .iter()
+            .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+            .collect::<Vec<&ComponentValue>>();
+
+        let is_auto = |value: &ComponentValue| matches!(value, ComponentValue::PerservedToken(Token::Ident(keyword)) if keyword.eq_ignore_ascii_case("auto"));
+        let (ratio, prefers_natural) = match values.as_slice() {
+            [first, ratio @ ..] if is_auto(first) => (ratio, true),
+            [ratio @ .., last] if is_auto(last) => (ratio, true),
+            ratio => (ratio, false),
+        };
+
+        let (width, height) = match ratio {
+            [width] => (parse_number(width)?, 1.),
+            [width, ComponentValue::PerservedToken(Token::Delim('/')), height] => {
+                (parse_number(width)?, parse_number(height)?)
+            }
+            _ => return None,
+        };
+
+        Some(AspectRatio {
+            width: width.into(),
+            height: height.into(),
+            prefers_natural,
+        })
+    }
+
+    /// Width divided by height, `None` for a degenerate ratio like `0 / 1`,
+    /// which behaves as `auto`
+    pub fn ratio(&self) -> Option<f32> {
+        if *self.width > 0. && *self.height > 0. {
+            Some(*self.width / *self.height)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_number(value: &ComponentValue) -> Option<f32> {
+    match value {
+        ComponentValue::PerservedToken(Token::Number { value, .. }) if *value >= 0. => Some(*value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    use super::*;
+
+    fn parse(css: &str) -> Option<AspectRatio> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        AspectRatio::parse(&parser.parse_a_list_of_component_values())
+    }
+
+    fn ratio(width: f32, height: f32, prefers_natural: bool) -> Option<AspectRatio> {
+        Some(AspectRatio {
+            width: width.into(),
+            height: height.into(),
+            prefers_natural,
+        })
+    }
+
+    #[test]
+    fn test_parse_aspect_ratio() {
+        assert_eq!(parse("16 / 9"), ratio(16., 9., false));
+        assert_eq!(parse("16/9"), ratio(16., 9., false));
+        assert_eq!(parse("1.5"), ratio(1.5, 1., false));
+        assert_eq!(parse("auto 4 / 3"), ratio(4., 3., true));
+        assert_eq!(parse("4 / 3 auto"), ratio(4., 3., true));
+        assert_eq!(parse("auto"), None);
+        assert_eq!(parse("-1 / 2"), None);
+        assert_eq!(parse("16 / 9 / 2"), None);
+
+        assert_eq!(parse("2 / 1").unwrap().ratio(), Some(2.));
+        assert_eq!(parse("0 / 1").unwrap().ratio(), None);
+    }
+}
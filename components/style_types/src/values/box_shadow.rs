@@ -0,0 +1,168 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+use super::{color::Color, length::Length};
+
+/// The shadows of `box-shadow`, from the top-most to the bottom-most.
+/// `none` is an empty list.
+/// https://www.w3.org/TR/css-backgrounds-3/#box-shadow
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct BoxShadow(pub Vec<Shadow>);
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Shadow {
+    pub offset_x: Length,
+    pub offset_y: Length,
+    pub blur_radius: Length,
+    pub spread_distance: Length,
+    /// `currentColor` when the color is omitted
+    pub color: Color,
+    pub inset: bool,
+}
+
+impl BoxShadow {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        parse_shadow_list(values, Shadow::parse).map(BoxShadow)
+    }
+}
+
+/// Parse a list of shadows separated by commas, or `none` for an empty list
+pub(crate) fn parse_shadow_list(
+    values: &[ComponentValue],
+    parse_shadow: fn(&[&ComponentValue]) -> Option<Shadow>,
+) -> Option<Vec<Shadow>> {
+    let shadows = values
+        .split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)))
+        .map(|shadow| {
+            shadow
+                .iter()
+                .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+                .collect::<Vec<&ComponentValue>>()
+        })
+        .collect::<Vec<Vec<&ComponentValue>>>();
+
+    if let [shadow] = shadows.as_slice() {
+        if let [ComponentValue::PerservedToken(Token::Ident(value))] = shadow.as_slice() {
+            if value.eq_ignore_ascii_case("none") {
+                return Some(Vec::new());
+            }
+        }
+    }
+
+    shadows
+        .iter()
+        .map(|shadow| parse_shadow(shadow))
+        .collect::<Option<Vec<Shadow>>>()
+}
+
+impl Shadow {
+    /// Parse a shadow made of 2 to 4 lengths, an optional color and an
+    /// optional `inset` keyword. The lengths must be next to each other.
+    fn parse(values: &[&ComponentValue]) -> Option<Self> {
+        Self::parse_with_lengths(values, 4)
+    }
+
+    /// Parse the shadow of text, which has no spread distance and can't be
+    /// inset
+    pub(crate) fn parse_text_shadow(values: &[&ComponentValue]) -> Option<Self> {
+        Self::parse_with_lengths(values, 3).filter(|shadow| !shadow.inset)
+    }
+
+    fn parse_with_lengths(values: &[&ComponentValue], max_lengths: usize) -> Option<Self> {
+        let mut lengths: Vec<Length> = Vec::new();
+        let mut color = None;
+        let mut inset = false;
+        // Whether the lengths are followed by another component
+        let mut lengths_ended = false;
+
+        for value in values {
+            let value = [(*value).clone()];
+            if let Some(length) = Length::parse(&value) {
+                if lengths_ended || lengths.len() == max_lengths {
+                    return None;
+                }
+                lengths.push(length);
+                continue;
+            }
+
+            lengths_ended = !lengths.is_empty();
+            match &value[0] {
+                ComponentValue::PerservedToken(Token::Ident(keyword))
+                    if keyword.eq_ignore_ascii_case("inset") && !inset =>
+                {
+                    inset = true;
+                }
+                _ if color.is_none() => color = Some(Color::parse(&value)?),
+                _ => return None,
+            }
+        }
+
+        if lengths.len() < 2 {
+            return None;
+        }
+        let mut lengths = lengths.into_iter();
+        let offset_x = lengths.next().unwrap();
+        let offset_y = lengths.next().unwrap();
+        let blur_radius = lengths.next().unwrap_or_else(Length::zero);
+        let spread_distance = lengths.next().unwrap_or_else(Length::zero);
+
+        if *blur_radius.value < 0. {
+            return None;
+        }
+
+        Some(Shadow {
+            offset_x,
+            offset_y,
+            blur_radius,
+            spread_distance,
+            color: color.unwrap_or(Color::CurrentColor),
+            inset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    use super::*;
+
+    fn parse(css: &str) -> Option<BoxShadow> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        BoxShadow::parse(&parser.parse_a_list_of_component_values())
+    }
+
+    #[test]
+    fn parse_box_shadows() {
+        assert_eq!(
+            parse("2px 4px 6px -1px red, 0 0 10px inset"),
+            Some(BoxShadow(vec![
+                Shadow {
+                    offset_x: Length::new_px(2.),
+                    offset_y: Length::new_px(4.),
+                    blur_radius: Length::new_px(6.),
+                    spread_distance: Length::new_px(-1.),
+                    color: Color::parse(&[ComponentValue::PerservedToken(Token::Ident(
+                        "red".to_string()
+                    ))])
+                    .unwrap(),
+                    inset: false,
+                },
+                Shadow {
+                    offset_x: Length::zero(),
+                    offset_y: Length::zero(),
+                    blur_radius: Length::new_px(10.),
+                    spread_distance: Length::zero(),
+                    color: Color::CurrentColor,
+                    inset: true,
+                },
+            ]))
+        );
+        assert_eq!(parse("none"), Some(BoxShadow(Vec::new())));
+        assert_eq!(parse("1px red"), None);
+        assert_eq!(parse("1px 1px -2px red"), None);
+        assert_eq!(parse("1px red 1px inset"), None);
+    }
+}
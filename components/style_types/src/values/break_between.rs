@@ -0,0 +1,60 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// Value of `break-before` and `break-after`. Region breaks are ignored.
+/// https://www.w3.org/TR/css-break-3/#break-between
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum BreakBetween {
+    Auto,
+    Avoid,
+    AvoidPage,
+    Page,
+    Left,
+    Right,
+    Recto,
+    Verso,
+    AvoidColumn,
+    Column,
+}
+
+impl BreakBetween {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("auto") => Some(BreakBetween::Auto),
+                v if v.eq_ignore_ascii_case("avoid") => Some(BreakBetween::Avoid),
+                v if v.eq_ignore_ascii_case("avoid-page") => Some(BreakBetween::AvoidPage),
+                // `always` is the value of the legacy `page-break-*` properties
+                v if v.eq_ignore_ascii_case("page") || v.eq_ignore_ascii_case("always") => {
+                    Some(BreakBetween::Page)
+                }
+                v if v.eq_ignore_ascii_case("left") => Some(BreakBetween::Left),
+                v if v.eq_ignore_ascii_case("right") => Some(BreakBetween::Right),
+                v if v.eq_ignore_ascii_case("recto") => Some(BreakBetween::Recto),
+                v if v.eq_ignore_ascii_case("verso") => Some(BreakBetween::Verso),
+                v if v.eq_ignore_ascii_case("avoid-column") => Some(BreakBetween::AvoidColumn),
+                v if v.eq_ignore_ascii_case("column") => Some(BreakBetween::Column),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether the value forces a page break
+    pub fn is_forced_page_break(&self) -> bool {
+        matches!(
+            self,
+            BreakBetween::Page
+                | BreakBetween::Left
+                | BreakBetween::Right
+                | BreakBetween::Recto
+                | BreakBetween::Verso
+        )
+    }
+    /// Whether the value forces a column break. Page breaks also break
+    /// columns, since they break every fragmentation context inside the
+    /// page.
+    pub fn is_forced_column_break(&self) -> bool {
+        *self == BreakBetween::Column || self.is_forced_page_break()
+    }
+}
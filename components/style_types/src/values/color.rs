@@ -50,11 +50,13 @@ impl From<&Color> for shared::color::Color {
 impl Color {
     pub fn parse(values: &[ComponentValue]) -> Option<Self> {
         match values.iter().next() {
-            Some(ComponentValue::Function(function)) => match function.name.as_ref() {
-                "rgba" => Color::parse_rgba_function(function, true),
-                "rgb" => Color::parse_rgba_function(function, false),
-                _ => None,
-            },
+            Some(ComponentValue::Function(function)) => {
+                match function.name.to_ascii_lowercase().as_str() {
+                    "rgb" | "rgba" => Color::parse_rgb_function(function),
+                    "hsl" | "hsla" => Color::parse_hsl_function(function),
+                    _ => None,
+                }
+            }
             Some(ComponentValue::PerservedToken(Token::Ident(keyword))) => {
                 Color::parse_color_keyword(&keyword)
             }
@@ -97,6 +99,13 @@ impl Color {
                 _ => return None,
             };
             Some(Color::Rgba(r.into(), g.into(), b.into(), 255.0.into()))
+        } else if hex.len() == 4 {
+            let mut digits = [0; 4];
+            for digit in digits.iter_mut() {
+                *digit = parse_digit(&mut chars)? * 0x11;
+            }
+            let [r, g, b, a] = digits;
+            Some(Color::Rgba(r.into(), g.into(), b.into(), a.into()))
         } else if hex.len() == 6 {
             let r = match parse_pair_digit(&mut chars) {
                 Some(d) => d,
@@ -111,6 +120,13 @@ impl Color {
                 _ => return None,
             };
             Some(Color::Rgba(r.into(), g.into(), b.into(), 255.0.into()))
+        } else if hex.len() == 8 {
+            let mut pairs = [0; 4];
+            for pair in pairs.iter_mut() {
+                *pair = parse_pair_digit(&mut chars)?;
+            }
+            let [r, g, b, a] = pairs;
+            Some(Color::Rgba(r.into(), g.into(), b.into(), a.into()))
         } else {
             None
         }
@@ -210,43 +226,96 @@ impl Color {
                 255.0.into(),
                 255.0.into(),
                 255.0.into(),
-            )
+            ),
+            // System colors, with the values of a light color scheme
+            // https://www.w3.org/TR/css-color-4/#css-system-colors
+            "Canvas" => Color::rgb(255, 255, 255),
+            "CanvasText" => Color::black(),
+            "LinkText" => Color::rgb(0, 0, 238),
+            "VisitedText" => Color::rgb(85, 26, 139),
+            "ActiveText" => Color::rgb(255, 0, 0),
+            "ButtonFace" => Color::rgb(239, 239, 239),
+            "ButtonText" => Color::black(),
+            "ButtonBorder" => Color::rgb(118, 118, 118),
+            "Field" => Color::rgb(255, 255, 255),
+            "FieldText" => Color::black(),
+            "Highlight" => Color::rgb(181, 213, 255),
+            "HighlightText" => Color::black(),
+            "SelectedItem" => Color::rgb(0, 117, 255),
+            "SelectedItemText" => Color::rgb(255, 255, 255),
+            "Mark" => Color::rgb(255, 255, 0),
+            "MarkText" => Color::black(),
+            "GrayText" => Color::rgb(128, 128, 128),
+            "AccentColor" => Color::rgb(0, 117, 255),
+            "AccentColorText" => Color::rgb(255, 255, 255)
         })
     }
 
-    fn parse_rgba_function(function: &Function, with_alpha: bool) -> Option<Self> {
-        let mut rgba: [f32; 4] = if !with_alpha {
-            [0.0, 0.0, 0.0, 255.0]
-        } else {
-            [0.0, 0.0, 0.0, 0.0]
-        };
+    /// Parse `rgb()` and `rgba()`, which are aliases. Channels are numbers
+    /// between 0 and 255 or percentages.
+    fn parse_rgb_function(function: &Function) -> Option<Self> {
+        let (channels, alpha) = color_function_arguments(function)?;
 
-        let mut index = 0;
-        let max_length = if !with_alpha { 3 } else { 4 };
-
-        for value in &function.value {
-            match value {
-                ComponentValue::PerservedToken(Token::Number { value, .. }) => {
-                    if index == max_length {
-                        return None;
-                    }
-                    rgba[index] = *value;
-                    index += 1;
-                }
-                ComponentValue::PerservedToken(Token::Whitespace) => {}
-                ComponentValue::PerservedToken(Token::Comma) => {}
-                _ => return None, // invalid character
+        let mut rgb = [0.; 3];
+        for (channel, token) in rgb.iter_mut().zip(channels.iter()) {
+            *channel = match token {
+                Token::Number { value, .. } => *value,
+                Token::Percentage(value) => value * 2.55,
+                _ => return None,
             }
+            .clamp(0., 255.)
+            .round();
+        }
+        let [r, g, b] = rgb;
+
+        Some(Color::Rgba(r.into(), g.into(), b.into(), alpha.into()))
+    }
+
+    /// Parse `hsl()` and `hsla()`. The hue is an angle, or a number of degrees.
+    fn parse_hsl_function(function: &Function) -> Option<Self> {
+        let (components, alpha) = color_function_arguments(function)?;
+
+        let hue = match &components[0] {
+            Token::Number { value, .. } => *value,
+            Token::Dimension { value, unit, .. } => match unit.to_ascii_lowercase().as_str() {
+                "deg" => *value,
+                "grad" => value * 0.9,
+                "rad" => value.to_degrees(),
+                "turn" => value * 360.,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let mut saturation_lightness = [0.; 2];
+        for (component, token) in saturation_lightness.iter_mut().zip(&components[1..]) {
+            *component = match token {
+                Token::Percentage(value) => value / 100.,
+                _ => return None,
+            };
         }
+        let [saturation, lightness] = saturation_lightness;
+
+        let (r, g, b) = shared::color::hsl_to_rgb(hue, saturation, lightness);
+        let to_channel = |value: f32| (value * 255.).round();
 
         Some(Color::Rgba(
-            rgba[0].into(),
-            rgba[1].into(),
-            rgba[2].into(),
-            rgba[3].into(),
+            to_channel(r).into(),
+            to_channel(g).into(),
+            to_channel(b).into(),
+            alpha.into(),
         ))
     }
 
+    fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color::Rgba(
+            (r as f32).into(),
+            (g as f32).into(),
+            (b as f32).into(),
+            255.0.into(),
+        )
+    }
+
     pub fn transparent() -> Self {
         Color::Transparent
     }
@@ -255,3 +324,90 @@ impl Color {
         Color::Rgba(0.0.into(), 0.0.into(), 0.0.into(), 255.0.into())
     }
 }
+
+/// Split the arguments of a color function into its three components and
+/// its alpha between 0 and 255. Both the legacy comma-separated syntax and
+/// the space-separated syntax with an optional `/ alpha` are supported.
+/// https://www.w3.org/TR/css-color-4/#rgb-functions
+fn color_function_arguments(function: &Function) -> Option<(Vec<Token>, f32)> {
+    let mut tokens = Vec::new();
+    for value in &function.value {
+        match value {
+            ComponentValue::PerservedToken(Token::Whitespace) => {}
+            ComponentValue::PerservedToken(token) => tokens.push(token.clone()),
+            _ => return None,
+        }
+    }
+
+    let (components, alpha) = if tokens.contains(&Token::Comma) {
+        let arguments = tokens
+            .split(|token| *token == Token::Comma)
+            .collect::<Vec<&[Token]>>();
+        if arguments.len() > 4 || arguments.iter().any(|argument| argument.len() != 1) {
+            return None;
+        }
+        let components = arguments.iter().take(3).map(|argument| argument[0].clone());
+        (
+            components.collect::<Vec<Token>>(),
+            arguments.get(3).copied(),
+        )
+    } else {
+        let mut arguments = tokens.split(|token| *token == Token::Delim('/'));
+        let components = arguments.next()?.to_vec();
+        let alpha = arguments.next();
+        if arguments.next().is_some() {
+            return None;
+        }
+        (components, alpha)
+    };
+
+    if components.len() != 3 {
+        return None;
+    }
+
+    let alpha = match alpha {
+        None => 255.,
+        Some([Token::Number { value, .. }]) => value.clamp(0., 1.) * 255.,
+        Some([Token::Percentage(value)]) => value.clamp(0., 100.) * 2.55,
+        Some(_) => return None,
+    };
+
+    Some((components, alpha.round()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    fn parse(color: &str) -> Option<shared::color::Color> {
+        let tokenizer = Tokenizer::new(color.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        Color::parse(&parser.parse_a_list_of_component_values())
+            .map(|color| shared::color::Color::from(&color))
+    }
+
+    fn rgba(color: &str) -> Option<(u8, u8, u8, u8)> {
+        parse(color).map(|color| (color.r, color.g, color.b, color.a))
+    }
+
+    #[test]
+    fn test_parse_color_functions() {
+        assert_eq!(rgba("rgb(255, 0, 128)"), Some((255, 0, 128, 255)));
+        assert_eq!(rgba("rgba(0, 0, 0, 0.5)"), Some((0, 0, 0, 128)));
+        assert_eq!(rgba("RGB(100% 50% 0% / 25%)"), Some((255, 128, 0, 64)));
+        assert_eq!(rgba("hsl(120, 100%, 25%)"), Some((0, 128, 0, 255)));
+        assert_eq!(rgba("hsla(0.5turn 100% 50% / 0)"), Some((0, 255, 255, 0)));
+        assert_eq!(rgba("rgb(1 2, 3)"), None);
+        assert_eq!(rgba("hsl(10, 20, 30)"), None);
+    }
+
+    #[test]
+    fn test_parse_hex_and_keywords() {
+        assert_eq!(rgba("#f008"), Some((255, 0, 0, 136)));
+        assert_eq!(rgba("#00ff0080"), Some((0, 255, 0, 128)));
+        assert_eq!(rgba("#12345"), None);
+        assert_eq!(rgba("LinkText"), Some((0, 0, 238, 255)));
+    }
+}
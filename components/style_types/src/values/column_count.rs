@@ -0,0 +1,19 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::{NumberType, Token};
+
+/// Number of columns of a multi-column container, `auto` is `Value::Auto`
+/// https://www.w3.org/TR/css-multicol-1/#cc
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct ColumnCount(pub u32);
+
+impl ColumnCount {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Number {
+                value,
+                type_: NumberType::Integer,
+            })) if *value >= 1. => Some(ColumnCount(*value as u32)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+use super::list_style_type::ListStyleType;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Content {
+    Normal,
+    None,
+    /// Concatenation of the strings in the `content` value
+    Text(String),
+    /// Strings and counters. Counters are resolved when the box tree is built.
+    Items(Vec<ContentItem>),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ContentItem {
+    Text(String),
+    /// `counter(name, style)`, the value of the innermost counter
+    Counter {
+        name: String,
+        style: ListStyleType,
+    },
+    /// `counters(name, separator, style)`, the values of all the nested
+    /// counters joined by the separator
+    Counters {
+        name: String,
+        separator: String,
+        style: ListStyleType,
+    },
+}
+
+impl Content {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("normal") => Some(Content::Normal),
+                v if v.eq_ignore_ascii_case("none") => Some(Content::None),
+                _ => None,
+            },
+            Some(ComponentValue::PerservedToken(Token::Str(_)))
+            | Some(ComponentValue::Function(_)) => {
+                let mut items: Vec<ContentItem> = Vec::new();
+                for value in values {
+                    match value {
+                        ComponentValue::PerservedToken(Token::Str(data)) => {
+                            match items.last_mut() {
+                                Some(ContentItem::Text(text)) => text.push_str(data),
+                                _ => items.push(ContentItem::Text(data.clone())),
+                            }
+                        }
+                        ComponentValue::PerservedToken(Token::Whitespace) => {}
+                        ComponentValue::Function(function) => {
+                            items.push(parse_counter(&function.name, &function.value)?)
+                        }
+                        _ => return None,
+                    }
+                }
+
+                match items.as_slice() {
+                    [] => Some(Content::Text(String::new())),
+                    [ContentItem::Text(text)] => Some(Content::Text(text.clone())),
+                    _ => Some(Content::Items(items)),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Parse the arguments of `counter()` or `counters()`
+fn parse_counter(function_name: &str, arguments: &[ComponentValue]) -> Option<ContentItem> {
+    let arguments = arguments
+        .split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)))
+        .map(|argument| {
+            argument
+                .iter()
+                .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+                .collect::<Vec<&ComponentValue>>()
+        })
+        .collect::<Vec<Vec<&ComponentValue>>>();
+
+    let name = match arguments.first()?.as_slice() {
+        [ComponentValue::PerservedToken(Token::Ident(name))] => name.clone(),
+        _ => return None,
+    };
+    let parse_style = |argument: Option<&Vec<&ComponentValue>>| match argument {
+        Some(argument) => match argument.as_slice() {
+            [value] => ListStyleType::parse(&[(*value).clone()]),
+            _ => None,
+        },
+        None => Some(ListStyleType::Decimal),
+    };
+
+    if function_name.eq_ignore_ascii_case("counter") && arguments.len() <= 2 {
+        return Some(ContentItem::Counter {
+            name,
+            style: parse_style(arguments.get(1))?,
+        });
+    }
+    if function_name.eq_ignore_ascii_case("counters") && arguments.len() <= 3 {
+        let separator = match arguments.get(1)?.as_slice() {
+            [ComponentValue::PerservedToken(Token::Str(separator))] => separator.clone(),
+            _ => return None,
+        };
+        return Some(ContentItem::Counters {
+            name,
+            separator,
+            style: parse_style(arguments.get(2))?,
+        });
+    }
+    None
+}
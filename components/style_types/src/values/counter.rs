@@ -0,0 +1,79 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::{NumberType, Token};
+
+/// Counters with their values, the value of `counter-reset` and
+/// `counter-increment`. `none` is an empty list.
+/// https://www.w3.org/TR/css-lists-3/#counter-properties
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CounterList(pub Vec<(String, i32)>);
+
+impl CounterList {
+    /// Parse a list of counter names, each optionally followed by an
+    /// integer. Counters without an integer get `default_value`.
+    pub fn parse(values: &[ComponentValue], default_value: i32) -> Option<Self> {
+        let mut tokens = values.iter().filter_map(|value| match value {
+            ComponentValue::PerservedToken(Token::Whitespace) => None,
+            ComponentValue::PerservedToken(token) => Some(token),
+            _ => Some(&Token::EOF),
+        });
+        let mut counters: Vec<(String, i32)> = Vec::new();
+        // Whether the last token is a counter name that can take a value
+        let mut after_name = false;
+
+        while let Some(token) = tokens.next() {
+            match token {
+                Token::Ident(name) if name.eq_ignore_ascii_case("none") && counters.is_empty() => {
+                    return match tokens.next() {
+                        None => Some(CounterList(Vec::new())),
+                        Some(_) => None,
+                    };
+                }
+                Token::Ident(name) => {
+                    counters.push((name.clone(), default_value));
+                    after_name = true;
+                }
+                Token::Number {
+                    value,
+                    type_: NumberType::Integer,
+                } if after_name => {
+                    counters.last_mut().unwrap().1 = *value as i32;
+                    after_name = false;
+                }
+                _ => return None,
+            }
+        }
+
+        if counters.is_empty() {
+            return None;
+        }
+        Some(CounterList(counters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    use super::*;
+
+    fn parse(css: &str, default_value: i32) -> Option<CounterList> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        CounterList::parse(&parser.parse_a_list_of_component_values(), default_value)
+    }
+
+    #[test]
+    fn parse_counter_lists() {
+        assert_eq!(
+            parse("chapter section 2", 1),
+            Some(CounterList(vec![
+                ("chapter".to_string(), 1),
+                ("section".to_string(), 2)
+            ]))
+        );
+        assert_eq!(parse("none", 0), Some(CounterList(Vec::new())));
+        assert_eq!(parse("3", 0), None);
+        assert_eq!(parse("", 0), None);
+    }
+}
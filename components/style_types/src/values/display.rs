@@ -4,6 +4,8 @@ use css::tokenizer::token::Token;
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum Display {
     Full(OuterDisplayType, InnerDisplayType),
+    /// A box that generates a `::marker` in addition to its principal box
+    ListItem(OuterDisplayType, InnerDisplayType),
     Box(DisplayBox),
 }
 
@@ -21,6 +23,9 @@ pub enum InnerDisplayType {
     Table,
     Flex,
     Grid,
+    /// MathML layout of the `<math>` element and its descendants
+    /// https://w3c.github.io/mathml-core/#new-display-math-value
+    Math,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -51,9 +56,31 @@ impl Display {
                 "contents" => Display::Box(DisplayBox::Contents),
                 "block" => Self::new_block(),
                 "inline" => Self::new_inline(),
-                "inline-block" => Display::Full(OuterDisplayType::Inline, InnerDisplayType::FlowRoot)
+                "inline-block" => Display::Full(OuterDisplayType::Inline, InnerDisplayType::FlowRoot),
+                "grid" => Display::Full(OuterDisplayType::Block, InnerDisplayType::Grid),
+                "inline-grid" => Display::Full(OuterDisplayType::Inline, InnerDisplayType::Grid),
+                "list-item" => Self::new_list_item(),
+                "math" => Display::Full(OuterDisplayType::Inline, InnerDisplayType::Math)
             }),
-            _ => None,
+            _ => {
+                let keywords = values
+                    .iter()
+                    .filter(|value| {
+                        !matches!(value, ComponentValue::PerservedToken(Token::Whitespace))
+                    })
+                    .collect::<Vec<_>>();
+                match keywords[..] {
+                    [outer, ComponentValue::PerservedToken(Token::Ident(inner))]
+                        if inner.eq_ignore_ascii_case("math") =>
+                    {
+                        match_ident!(outer, {
+                            "block" => Display::Full(OuterDisplayType::Block, InnerDisplayType::Math),
+                            "inline" => Display::Full(OuterDisplayType::Inline, InnerDisplayType::Math)
+                        })
+                    }
+                    _ => None,
+                }
+            }
         }
     }
 
@@ -64,4 +91,8 @@ impl Display {
     pub fn new_inline() -> Self {
         Display::Full(OuterDisplayType::Inline, InnerDisplayType::Flow)
     }
+
+    pub fn new_list_item() -> Self {
+        Display::ListItem(OuterDisplayType::Block, InnerDisplayType::Flow)
+    }
 }
@@ -0,0 +1,48 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// Font families in order of preference. Generic families like `serif` are
+/// kept as names and resolve to the default font.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct FontFamily(pub Vec<String>);
+
+impl FontFamily {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let mut families = Vec::new();
+
+        for family in
+            values.split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)))
+        {
+            families.push(parse_family_name(family)?);
+        }
+
+        Some(FontFamily(families))
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.0
+    }
+}
+
+/// Parse a quoted family name or a sequence of identifiers
+fn parse_family_name(values: &[ComponentValue]) -> Option<String> {
+    let tokens = values
+        .iter()
+        .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+        .collect::<Vec<&ComponentValue>>();
+
+    match tokens.as_slice() {
+        [ComponentValue::PerservedToken(Token::Str(name))] => Some(name.clone()),
+        [] => None,
+        idents => {
+            let mut names = Vec::new();
+            for ident in idents {
+                match ident {
+                    ComponentValue::PerservedToken(Token::Ident(name)) => names.push(name.clone()),
+                    _ => return None,
+                }
+            }
+            Some(names.join(" "))
+        }
+    }
+}
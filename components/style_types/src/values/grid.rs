@@ -0,0 +1,196 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::{NumberType, Token};
+
+use super::length::Length;
+use super::number::Number;
+
+/// Size of a grid track
+/// https://www.w3.org/TR/css-grid-1/#track-sizing
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum TrackSize {
+    Length(Length),
+    /// Share of the space left by the other tracks, in `fr`
+    Fraction(Number),
+    Auto,
+}
+
+/// Explicit tracks of a grid, the value of `grid-template-columns` and
+/// `grid-template-rows`. `none` is an empty list.
+/// https://www.w3.org/TR/css-grid-1/#track-sizing
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct TrackList(pub Vec<TrackSize>);
+
+/// Start or end of a grid item on an axis, the value of the
+/// `grid-row-*` and `grid-column-*` properties. Lines are numbered from
+/// 1, negative numbers count from the end of the explicit grid.
+/// https://www.w3.org/TR/css-grid-1/#line-placement
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum GridLine {
+    Auto,
+    Line(i32),
+    Span(u32),
+}
+
+impl TrackSize {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values {
+            [ComponentValue::PerservedToken(Token::Ident(name))]
+                if name.eq_ignore_ascii_case("auto") =>
+            {
+                Some(TrackSize::Auto)
+            }
+            [ComponentValue::PerservedToken(Token::Dimension { value, unit, .. })]
+                if unit.eq_ignore_ascii_case("fr") && *value >= 0. =>
+            {
+                Some(TrackSize::Fraction((*value).into()))
+            }
+            [_] => Length::parse(values)
+                .filter(|length| *length.value >= 0.)
+                .map(TrackSize::Length),
+            _ => None,
+        }
+    }
+}
+
+impl TrackList {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let values = without_whitespace(values);
+        if let [ComponentValue::PerservedToken(Token::Ident(name))] = values.as_slice() {
+            if name.eq_ignore_ascii_case("none") {
+                return Some(TrackList(Vec::new()));
+            }
+        }
+
+        let mut tracks = Vec::new();
+        for value in values {
+            match value {
+                ComponentValue::Function(function)
+                    if function.name.eq_ignore_ascii_case("repeat") =>
+                {
+                    tracks.extend(parse_repeat(&function.value)?)
+                }
+                _ => tracks.push(TrackSize::parse(std::slice::from_ref(&value))?),
+            }
+        }
+        if tracks.is_empty() {
+            return None;
+        }
+        Some(TrackList(tracks))
+    }
+}
+
+/// Parse the arguments of `repeat()` with a number of repetitions
+fn parse_repeat(arguments: &[ComponentValue]) -> Option<Vec<TrackSize>> {
+    let mut arguments =
+        arguments.split(|value| matches!(value, ComponentValue::PerservedToken(Token::Comma)));
+    let count = match without_whitespace(arguments.next()?).as_slice() {
+        [ComponentValue::PerservedToken(Token::Number {
+            value,
+            type_: NumberType::Integer,
+        })] if *value >= 1. => *value as usize,
+        _ => return None,
+    };
+    let tracks = without_whitespace(arguments.next()?)
+        .into_iter()
+        .map(|value| TrackSize::parse(&[value]))
+        .collect::<Option<Vec<TrackSize>>>()?;
+    if tracks.is_empty() || arguments.next().is_some() {
+        return None;
+    }
+    Some((0..count).flat_map(|_| tracks.clone()).collect())
+}
+
+impl GridLine {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        let values = without_whitespace(values);
+        let is_keyword = |value: &ComponentValue, keyword: &str| matches!(value, ComponentValue::PerservedToken(Token::Ident(name)) if name.eq_ignore_ascii_case(keyword));
+        let integer = |value: &ComponentValue| match value {
+            ComponentValue::PerservedToken(Token::Number {
+                value,
+                type_: NumberType::Integer,
+            }) => Some(*value as i32),
+            _ => None,
+        };
+
+        match values.as_slice() {
+            [value] if is_keyword(value, "auto") => Some(GridLine::Auto),
+            [value] if is_keyword(value, "span") => Some(GridLine::Span(1)),
+            [value] => integer(value).filter(|line| *line != 0).map(GridLine::Line),
+            // `span` and the number of tracks come in any order
+            [first, second] => {
+                let span = if is_keyword(first, "span") {
+                    integer(second)?
+                } else if is_keyword(second, "span") {
+                    integer(first)?
+                } else {
+                    return None;
+                };
+                match span >= 1 {
+                    true => Some(GridLine::Span(span as u32)),
+                    false => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn without_whitespace(values: &[ComponentValue]) -> Vec<ComponentValue> {
+    values
+        .iter()
+        .filter(|value| !matches!(value, ComponentValue::PerservedToken(Token::Whitespace)))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    use super::super::length::LengthUnit;
+    use super::*;
+
+    fn parse_values(css: &str) -> Vec<ComponentValue> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        parser.parse_a_list_of_component_values()
+    }
+
+    #[test]
+    fn parse_track_lists() {
+        let parse = |css: &str| TrackList::parse(&parse_values(css));
+        assert_eq!(
+            parse("100px 1fr auto"),
+            Some(TrackList(vec![
+                TrackSize::Length(Length::new_px(100.)),
+                TrackSize::Fraction(1.0.into()),
+                TrackSize::Auto,
+            ]))
+        );
+        assert_eq!(
+            parse("2em repeat(2, 1fr 0.5fr)"),
+            Some(TrackList(vec![
+                TrackSize::Length(Length::new(2., LengthUnit::Em)),
+                TrackSize::Fraction(1.0.into()),
+                TrackSize::Fraction(0.5.into()),
+                TrackSize::Fraction(1.0.into()),
+                TrackSize::Fraction(0.5.into()),
+            ]))
+        );
+        assert_eq!(parse("none"), Some(TrackList(Vec::new())));
+        assert_eq!(parse("repeat(0, 1fr)"), None);
+        assert_eq!(parse("-1fr"), None);
+    }
+
+    #[test]
+    fn parse_grid_lines() {
+        let parse = |css: &str| GridLine::parse(&parse_values(css));
+        assert_eq!(parse("auto"), Some(GridLine::Auto));
+        assert_eq!(parse("3"), Some(GridLine::Line(3)));
+        assert_eq!(parse("span 2"), Some(GridLine::Span(2)));
+        assert_eq!(parse("2 span"), Some(GridLine::Span(2)));
+        assert_eq!(parse("0"), None);
+        assert_eq!(parse("span 0"), None);
+    }
+}
@@ -0,0 +1,77 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+use super::{length::Length, number::Number, percentage::Percentage};
+
+/// https://www.w3.org/TR/CSS2/visudet.html#propdef-line-height
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum LineHeight {
+    Normal,
+    /// Multiplied by the font size of each element. Unlike lengths and
+    /// percentages, it is inherited as a number.
+    Number(Number),
+    Length(Length),
+    Percentage(Percentage),
+}
+
+impl LineHeight {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.first() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value)))
+                if value.eq_ignore_ascii_case("normal") =>
+            {
+                Some(LineHeight::Normal)
+            }
+            Some(ComponentValue::PerservedToken(Token::Number { value, .. })) if *value >= 0. => {
+                Some(LineHeight::Number((*value).into()))
+            }
+            Some(ComponentValue::PerservedToken(Token::Percentage(value))) if *value >= 0. => {
+                Percentage::parse(values).map(LineHeight::Percentage)
+            }
+            _ => Length::parse(values)
+                .filter(|length| *length.value >= 0.)
+                .map(LineHeight::Length),
+        }
+    }
+
+    /// Height of a line of text in the font size. `normal` is the line
+    /// height recommended by the font.
+    pub fn to_px(&self, font_size: f32, normal: f32) -> f32 {
+        match self {
+            LineHeight::Normal => normal,
+            LineHeight::Number(number) => **number * font_size,
+            LineHeight::Length(length) => length.to_px(),
+            LineHeight::Percentage(percentage) => percentage.to_px(font_size),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::Tokenizer;
+
+    use super::*;
+
+    fn parse(css: &str) -> Option<LineHeight> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        LineHeight::parse(&parser.parse_a_list_of_component_values())
+    }
+
+    #[test]
+    fn parse_line_heights() {
+        assert_eq!(parse("normal"), Some(LineHeight::Normal));
+        assert_eq!(parse("1.5"), Some(LineHeight::Number(1.5.into())));
+        assert_eq!(
+            parse("150%"),
+            Some(LineHeight::Percentage(Percentage(150.0.into())))
+        );
+        assert_eq!(parse("-1"), None);
+        assert_eq!(parse("auto"), None);
+        assert_eq!(
+            LineHeight::Number(1.5.into()).to_px(20., 0.),
+            LineHeight::Percentage(Percentage(150.0.into())).to_px(20., 0.)
+        );
+    }
+}
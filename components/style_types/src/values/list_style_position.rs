@@ -0,0 +1,21 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ListStylePosition {
+    Inside,
+    Outside,
+}
+
+impl ListStylePosition {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("inside") => Some(ListStylePosition::Inside),
+                v if v.eq_ignore_ascii_case("outside") => Some(ListStylePosition::Outside),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
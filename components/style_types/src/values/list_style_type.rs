@@ -0,0 +1,37 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ListStyleType {
+    Disc,
+    Circle,
+    Square,
+    Decimal,
+    /// Triangle of an open `details` element, pointing down
+    DisclosureOpen,
+    /// Triangle of a closed `details` element, pointing to the inline end
+    DisclosureClosed,
+    None,
+}
+
+impl ListStyleType {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("disc") => Some(ListStyleType::Disc),
+                v if v.eq_ignore_ascii_case("circle") => Some(ListStyleType::Circle),
+                v if v.eq_ignore_ascii_case("square") => Some(ListStyleType::Square),
+                v if v.eq_ignore_ascii_case("decimal") => Some(ListStyleType::Decimal),
+                v if v.eq_ignore_ascii_case("disclosure-open") => {
+                    Some(ListStyleType::DisclosureOpen)
+                }
+                v if v.eq_ignore_ascii_case("disclosure-closed") => {
+                    Some(ListStyleType::DisclosureClosed)
+                }
+                v if v.eq_ignore_ascii_case("none") => Some(ListStyleType::None),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
@@ -1,30 +1,74 @@
+pub mod animation;
+pub mod aspect_ratio;
 pub mod border_radius;
 pub mod border_style;
 pub mod border_width;
+pub mod box_shadow;
+pub mod break_between;
 pub mod color;
+pub mod column_count;
+pub mod content;
+pub mod counter;
 pub mod direction;
 pub mod display;
 pub mod float;
+pub mod font_family;
+pub mod grid;
 pub mod length;
 pub mod length_percentage;
+pub mod line_height;
+pub mod list_style_position;
+pub mod list_style_type;
 pub mod number;
+pub mod object_fit;
+pub mod overflow;
 pub mod percentage;
 pub mod position;
+pub mod tab_size;
 pub mod text_align;
+pub mod text_shadow;
+pub mod text_transform;
+pub mod unicode_bidi;
+pub mod visibility;
+pub mod white_space;
+pub mod writing_mode;
 
 // Let this pub because in the future we may want to use this in other places.
 // Just maybe....
 pub mod prelude {
+    pub use super::animation::{
+        AnimationDirection, AnimationFillMode, AnimationName, IterationCount, Time, TimingFunction,
+    };
+    pub use super::aspect_ratio::AspectRatio;
     pub use super::border_radius::BorderRadius;
     pub use super::border_style::BorderStyle;
     pub use super::border_width::BorderWidth;
+    pub use super::box_shadow::{BoxShadow, Shadow};
+    pub use super::break_between::BreakBetween;
     pub use super::color::Color;
+    pub use super::column_count::ColumnCount;
+    pub use super::content::{Content, ContentItem};
+    pub use super::counter::CounterList;
     pub use super::direction::Direction;
     pub use super::display::Display;
     pub use super::float::Float;
+    pub use super::font_family::FontFamily;
+    pub use super::grid::{GridLine, TrackList, TrackSize};
     pub use super::length::Length;
     pub use super::length_percentage::LengthPercentage;
+    pub use super::line_height::LineHeight;
+    pub use super::list_style_position::ListStylePosition;
+    pub use super::list_style_type::ListStyleType;
+    pub use super::object_fit::ObjectFit;
+    pub use super::overflow::Overflow;
     pub use super::percentage::Percentage;
     pub use super::position::Position;
+    pub use super::tab_size::TabSize;
     pub use super::text_align::TextAlign;
+    pub use super::text_shadow::TextShadow;
+    pub use super::text_transform::TextTransform;
+    pub use super::unicode_bidi::UnicodeBidi;
+    pub use super::visibility::Visibility;
+    pub use super::white_space::WhiteSpace;
+    pub use super::writing_mode::WritingMode;
 }
@@ -0,0 +1,29 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// How the content of a replaced element is fitted to its content box
+/// https://www.w3.org/TR/css-images-3/#the-object-fit
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ObjectFit {
+    Fill,
+    Contain,
+    Cover,
+    None,
+    ScaleDown,
+}
+
+impl ObjectFit {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("fill") => Some(ObjectFit::Fill),
+                v if v.eq_ignore_ascii_case("contain") => Some(ObjectFit::Contain),
+                v if v.eq_ignore_ascii_case("cover") => Some(ObjectFit::Cover),
+                v if v.eq_ignore_ascii_case("none") => Some(ObjectFit::None),
+                v if v.eq_ignore_ascii_case("scale-down") => Some(ObjectFit::ScaleDown),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// How the content of a box that overflows it is shown
+/// https://www.w3.org/TR/css-overflow-3/#overflow-properties
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Overflow {
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("visible") => Some(Overflow::Visible),
+                v if v.eq_ignore_ascii_case("hidden") => Some(Overflow::Hidden),
+                v if v.eq_ignore_ascii_case("clip") => Some(Overflow::Clip),
+                v if v.eq_ignore_ascii_case("scroll") => Some(Overflow::Scroll),
+                v if v.eq_ignore_ascii_case("auto") => Some(Overflow::Auto),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// Distance between tab stops, in advances of the space character
+/// https://www.w3.org/TR/css-text-3/#tab-size-property
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub struct TabSize(pub u32);
+
+impl TabSize {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Number { value, .. }))
+                if *value >= 0. && value.fract() == 0. =>
+            {
+                Some(TabSize(*value as u32))
+            }
+            _ => None,
+        }
+    }
+}
@@ -3,6 +3,9 @@ use css::tokenizer::token::Token;
 
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub enum TextAlign {
+    /// The start of the line in the direction of the text
+    Start,
+    End,
     Left,
     Center,
     Right,
@@ -13,6 +16,8 @@ impl TextAlign {
     pub fn parse(values: &[ComponentValue]) -> Option<Self> {
         match values.iter().next() {
             Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("start") => Some(TextAlign::Start),
+                v if v.eq_ignore_ascii_case("end") => Some(TextAlign::End),
                 v if v.eq_ignore_ascii_case("left") => Some(TextAlign::Left),
                 v if v.eq_ignore_ascii_case("center") => Some(TextAlign::Center),
                 v if v.eq_ignore_ascii_case("right") => Some(TextAlign::Right),
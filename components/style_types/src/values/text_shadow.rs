@@ -0,0 +1,63 @@
+use css::parser::structs::ComponentValue;
+
+use super::box_shadow::{parse_shadow_list, Shadow};
+
+/// The shadows of `text-shadow`, from the top-most to the bottom-most.
+/// Their spread distance is always zero and none are inset. `none` is an
+/// empty list.
+/// https://www.w3.org/TR/css-text-decor-3/#text-shadow-property
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TextShadow(pub Vec<Shadow>);
+
+impl TextShadow {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        parse_shadow_list(values, Shadow::parse_text_shadow).map(TextShadow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use css::parser::Parser;
+    use css::tokenizer::token::Token;
+    use css::tokenizer::Tokenizer;
+
+    use super::*;
+    use crate::values::prelude::{Color, Length};
+
+    fn parse(css: &str) -> Option<TextShadow> {
+        let tokenizer = Tokenizer::new(css.chars());
+        let mut parser = Parser::<Token>::new(tokenizer.run());
+        TextShadow::parse(&parser.parse_a_list_of_component_values())
+    }
+
+    #[test]
+    fn parse_text_shadows() {
+        assert_eq!(
+            parse("blue 1px 2px 3px, 0 0"),
+            Some(TextShadow(vec![
+                Shadow {
+                    offset_x: Length::new_px(1.),
+                    offset_y: Length::new_px(2.),
+                    blur_radius: Length::new_px(3.),
+                    spread_distance: Length::zero(),
+                    color: Color::parse(&[ComponentValue::PerservedToken(Token::Ident(
+                        "blue".to_string()
+                    ))])
+                    .unwrap(),
+                    inset: false,
+                },
+                Shadow {
+                    offset_x: Length::zero(),
+                    offset_y: Length::zero(),
+                    blur_radius: Length::zero(),
+                    spread_distance: Length::zero(),
+                    color: Color::CurrentColor,
+                    inset: false,
+                },
+            ]))
+        );
+        assert_eq!(parse("none"), Some(TextShadow(Vec::new())));
+        assert_eq!(parse("1px 1px 1px 1px red"), None);
+        assert_eq!(parse("1px 1px inset"), None);
+    }
+}
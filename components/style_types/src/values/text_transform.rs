@@ -0,0 +1,48 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum TextTransform {
+    None,
+    Uppercase,
+    Lowercase,
+    Capitalize,
+}
+
+impl TextTransform {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("none") => Some(TextTransform::None),
+                v if v.eq_ignore_ascii_case("uppercase") => Some(TextTransform::Uppercase),
+                v if v.eq_ignore_ascii_case("lowercase") => Some(TextTransform::Lowercase),
+                v if v.eq_ignore_ascii_case("capitalize") => Some(TextTransform::Capitalize),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Transform the case of the text. `capitalize` uppercases the first
+    /// letter of every word.
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            TextTransform::None => text.to_string(),
+            TextTransform::Uppercase => text.to_uppercase(),
+            TextTransform::Lowercase => text.to_lowercase(),
+            TextTransform::Capitalize => {
+                let mut result = String::with_capacity(text.len());
+                let mut is_word_start = true;
+                for ch in text.chars() {
+                    if is_word_start && ch.is_alphabetic() {
+                        result.extend(ch.to_uppercase());
+                    } else {
+                        result.push(ch);
+                    }
+                    is_word_start = ch.is_whitespace();
+                }
+                result
+            }
+        }
+    }
+}
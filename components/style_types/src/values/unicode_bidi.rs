@@ -0,0 +1,32 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// https://www.w3.org/TR/css-writing-modes-3/#unicode-bidi
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum UnicodeBidi {
+    Normal,
+    Embed,
+    Isolate,
+    BidiOverride,
+    IsolateOverride,
+    Plaintext,
+}
+
+impl UnicodeBidi {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("normal") => Some(UnicodeBidi::Normal),
+                v if v.eq_ignore_ascii_case("embed") => Some(UnicodeBidi::Embed),
+                v if v.eq_ignore_ascii_case("isolate") => Some(UnicodeBidi::Isolate),
+                v if v.eq_ignore_ascii_case("bidi-override") => Some(UnicodeBidi::BidiOverride),
+                v if v.eq_ignore_ascii_case("isolate-override") => {
+                    Some(UnicodeBidi::IsolateOverride)
+                }
+                v if v.eq_ignore_ascii_case("plaintext") => Some(UnicodeBidi::Plaintext),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
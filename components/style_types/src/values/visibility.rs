@@ -0,0 +1,23 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Visibility {
+    Visible,
+    Hidden,
+    Collapse,
+}
+
+impl Visibility {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("visible") => Some(Visibility::Visible),
+                v if v.eq_ignore_ascii_case("hidden") => Some(Visibility::Hidden),
+                v if v.eq_ignore_ascii_case("collapse") => Some(Visibility::Collapse),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// https://www.w3.org/TR/CSS2/text.html#white-space-prop
+#[derive(Debug, Clone, PartialEq, Hash, Eq)]
+pub enum WhiteSpace {
+    Normal,
+    Pre,
+    Nowrap,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("normal") => Some(WhiteSpace::Normal),
+                v if v.eq_ignore_ascii_case("pre") => Some(WhiteSpace::Pre),
+                v if v.eq_ignore_ascii_case("nowrap") => Some(WhiteSpace::Nowrap),
+                v if v.eq_ignore_ascii_case("pre-wrap") => Some(WhiteSpace::PreWrap),
+                v if v.eq_ignore_ascii_case("pre-line") => Some(WhiteSpace::PreLine),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether spaces and tabs are rendered as they are instead of being
+    /// collapsed into a single space
+    pub fn preserves_spaces(&self) -> bool {
+        matches!(self, WhiteSpace::Pre | WhiteSpace::PreWrap)
+    }
+
+    /// Whether line feeds in the text start new lines
+    pub fn preserves_line_breaks(&self) -> bool {
+        matches!(
+            self,
+            WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::PreLine
+        )
+    }
+
+    /// Whether lines wrap to fit the width of their block
+    pub fn wraps(&self) -> bool {
+        !matches!(self, WhiteSpace::Pre | WhiteSpace::Nowrap)
+    }
+}
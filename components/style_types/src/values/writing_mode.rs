@@ -0,0 +1,29 @@
+use css::parser::structs::ComponentValue;
+use css::tokenizer::token::Token;
+
+/// https://www.w3.org/TR/css-writing-modes-3/#block-flow
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+pub enum WritingMode {
+    HorizontalTb,
+    VerticalRl,
+    VerticalLr,
+}
+
+impl WritingMode {
+    pub fn parse(values: &[ComponentValue]) -> Option<Self> {
+        match values.iter().next() {
+            Some(ComponentValue::PerservedToken(Token::Ident(value))) => match value {
+                v if v.eq_ignore_ascii_case("horizontal-tb") => Some(WritingMode::HorizontalTb),
+                v if v.eq_ignore_ascii_case("vertical-rl") => Some(WritingMode::VerticalRl),
+                v if v.eq_ignore_ascii_case("vertical-lr") => Some(WritingMode::VerticalLr),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Whether lines run from top to bottom and stack horizontally
+    pub fn is_vertical(&self) -> bool {
+        *self != WritingMode::HorizontalTb
+    }
+}
@@ -34,6 +34,32 @@ impl URLPercentEncode {
     }
 }
 
+/// Replace the `%` followed by two hex digits with the byte they encode.
+/// Other `%` are kept as they are.
+/// https://url.spec.whatwg.org/#percent-decode
+pub fn percent_decode(input: &[u8]) -> Vec<u8> {
+    let hex_value = |byte: u8| (byte as char).to_digit(16).map(|value| value as u8);
+    let mut output = Vec::with_capacity(input.len());
+    let mut index = 0;
+    while index < input.len() {
+        let encoded = match input.get(index..index + 3) {
+            Some([b'%', high, low]) => hex_value(*high).zip(hex_value(*low)),
+            _ => None,
+        };
+        match encoded {
+            Some((high, low)) => {
+                output.push(high << 4 | low);
+                index += 3;
+            }
+            None => {
+                output.push(input[index]);
+                index += 1;
+            }
+        }
+    }
+    output
+}
+
 impl PercentEncodeSet {
     pub fn contains(&self, c: u8) -> bool {
         match self {
@@ -103,4 +129,12 @@ mod tests {
             "%E2%80%BD"
         );
     }
+
+    #[test]
+    fn utf8_percent_decode() {
+        assert_eq!(percent_decode(b"hello%20world"), b"hello world");
+        assert_eq!(percent_decode(b"%E2%89%a1"), "≡".as_bytes());
+        assert_eq!(percent_decode(b"100%"), b"100%");
+        assert_eq!(percent_decode(b"%zz%4"), b"%zz%4");
+    }
 }
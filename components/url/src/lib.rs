@@ -3,4 +3,5 @@ mod helper;
 mod host_parser;
 pub mod parser;
 mod url;
+pub use crate::encode::percent_decode;
 pub use crate::url::*;
@@ -612,6 +612,19 @@ mod tests {
         assert_eq!(url.path, "index.php");
     }
 
+    #[test]
+    fn origin() {
+        let origin = |input| URLParser::parse(input, None).unwrap().origin();
+
+        assert_eq!(
+            origin("https://google.com:443/search?q=moon"),
+            "https://google.com"
+        );
+        assert_eq!(origin("http://localhost:8080/"), "http://localhost:8080");
+        assert_eq!(origin("file:///home/index.html"), "null");
+        assert_eq!(origin("data:text/html,moon"), "null");
+    }
+
     #[test]
     fn empty_path() {
         let input_url = "http://google.com";
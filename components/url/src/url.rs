@@ -54,6 +54,19 @@ impl Url {
         }
     }
 
+    /// ASCII serialization of the origin of the URL, `null` for URLs with
+    /// an opaque origin
+    /// https://html.spec.whatwg.org/multipage/browsers.html#ascii-serialisation-of-an-origin
+    pub fn origin(&self) -> String {
+        match (self.scheme.as_str(), &self.host) {
+            ("http" | "https" | "ws" | "wss" | "ftp", Some(host)) => match self.port {
+                Some(port) => format!("{}://{}:{}", self.scheme, host, port),
+                None => format!("{}://{}", self.scheme, host),
+            },
+            _ => "null".to_string(),
+        }
+    }
+
     pub fn as_str(&self) -> String {
         let mut result = String::new();
         result.push_str(&format!("{}://", self.scheme));
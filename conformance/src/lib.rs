@@ -0,0 +1,10 @@
+//! Runs WPT-style reftests headlessly: each test and its references are
+//! rendered on the CPU and their pixels compared.
+//! https://web-platform-tests.org/writing-tests/reftests.html
+
+pub mod manifest;
+pub mod report;
+pub mod runner;
+
+pub use report::{Outcome, Report, TestResult};
+pub use runner::Runner;
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use conformance::{manifest, Report, Runner};
+
+const USAGE: &str = "Usage: conformance <wpt-root> [<test or directory>...] [--report <file>]";
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let root = match args.next() {
+        Some(root) => PathBuf::from(root),
+        None => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut paths = Vec::new();
+    let mut report_path = None;
+    while let Some(arg) = args.next() {
+        if arg == "--report" {
+            report_path = args.next().map(PathBuf::from);
+        } else {
+            paths.push(root.join(arg));
+        }
+    }
+    if paths.is_empty() {
+        paths.push(root.clone());
+    }
+
+    let tests = manifest::discover(&paths);
+    let results = Runner::new(&root).run(&tests);
+    let report = Report { results: &results }.to_string();
+    match report_path {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, &report) {
+                eprintln!("Unable to write report to {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        }
+        None => print!("{}", report),
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::{Path, PathBuf};
+
+use dom::node::NodePtr;
+
+/// Directories of a test suite that hold references and resources of the
+/// tests instead of tests
+const NON_TEST_DIRECTORIES: [&str; 5] = ["reference", "support", "resources", "tools", "fonts"];
+
+/// What a test is compared against, read from the `<link>` and `<meta>`
+/// elements of the test
+/// https://web-platform-tests.org/writing-tests/reftests.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestMetadata {
+    pub references: Vec<Reference>,
+    pub fuzzy: Fuzzy,
+    /// The test waits for script to finish before it is compared, which the
+    /// runner doesn't support
+    pub is_waiting: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub path: PathBuf,
+    pub relation: Relation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Relation {
+    /// The test passes if it renders like the reference
+    Match,
+    /// The test passes if it renders differently from the reference
+    Mismatch,
+}
+
+/// How much a test can differ from its references and still match them
+/// https://web-platform-tests.org/writing-tests/reftests.html#fuzzy-matching
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Fuzzy {
+    /// Largest difference of a color channel of a pixel
+    pub max_difference: u8,
+    /// Number of pixels that can differ
+    pub total_pixels: usize,
+}
+
+/// Test files under the paths, in the order they are sorted. References and
+/// resources of the tests are skipped.
+pub fn discover(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut tests = Vec::new();
+    for path in paths {
+        collect_tests(path, &mut tests);
+    }
+    tests.sort();
+    tests.dedup();
+    tests
+}
+
+fn collect_tests(path: &Path, tests: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        if is_test_file(path) {
+            tests.push(path.to_path_buf());
+        }
+        return;
+    }
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::error!("Unable to read test directory: {} ({})", e, path.display());
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry_path.is_dir() && NON_TEST_DIRECTORIES.contains(&name.as_str()) {
+            continue;
+        }
+        collect_tests(&entry_path, tests);
+    }
+}
+
+/// HTML files that aren't named like references
+fn is_test_file(path: &Path) -> bool {
+    let is_html = matches!(
+        path.extension().and_then(|extension| extension.to_str()),
+        Some("html" | "htm")
+    );
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let is_reference =
+        stem.ends_with("-ref") || stem.ends_with("-notref") || stem.starts_with("ref-");
+    is_html && !is_reference
+}
+
+impl TestMetadata {
+    /// Read the metadata of the test document, whose references are
+    /// relative to `directory`
+    pub fn of_document(document: &NodePtr, directory: &Path) -> Self {
+        let mut metadata = TestMetadata {
+            references: Vec::new(),
+            fuzzy: Fuzzy::default(),
+            is_waiting: false,
+        };
+        collect_metadata(document, directory, &mut metadata);
+        metadata
+    }
+}
+
+fn collect_metadata(node: &NodePtr, directory: &Path, metadata: &mut TestMetadata) {
+    if let Some(element) = node.as_element_opt() {
+        let attributes = element.attributes();
+        let attributes = attributes.borrow();
+        match element.tag_name().as_str() {
+            "html" => {
+                metadata.is_waiting = element.class_list().borrow().contains("reftest-wait");
+            }
+            "link" => {
+                let relation = match attributes.get_str("rel").to_ascii_lowercase().as_str() {
+                    "match" => Some(Relation::Match),
+                    "mismatch" => Some(Relation::Mismatch),
+                    _ => None,
+                };
+                if let Some(relation) = relation {
+                    metadata.references.push(Reference {
+                        path: directory.join(attributes.get_str("href")),
+                        relation,
+                    });
+                }
+            }
+            "meta" if attributes.get_str("name").eq_ignore_ascii_case("fuzzy") => {
+                if let Some(fuzzy) = Fuzzy::parse(&attributes.get_str("content")) {
+                    metadata.fuzzy = fuzzy;
+                }
+            }
+            _ => {}
+        }
+    }
+    node.for_each_child(|child| collect_metadata(&NodePtr(child), directory, metadata));
+}
+
+impl Fuzzy {
+    /// Parse `maxDifference=0-2;totalPixels=0-100`, where the names are
+    /// optional. A reference the values are limited to is ignored, and
+    /// ranges are read as their upper bound.
+    pub fn parse(content: &str) -> Option<Self> {
+        let values = match content.rsplit_once(':') {
+            Some((_, values)) => values,
+            None => content,
+        };
+        let mut fuzzy = Fuzzy::default();
+        for (index, value) in values.split(';').enumerate() {
+            let (name, range) = match value.split_once('=') {
+                Some((name, range)) => (name.trim(), range),
+                None if index == 0 => ("maxDifference", value),
+                None => ("totalPixels", value),
+            };
+            let upper_bound = range.rsplit('-').next()?.trim();
+            match name {
+                "maxDifference" => fuzzy.max_difference = upper_bound.parse().ok()?,
+                "totalPixels" => fuzzy.total_pixels = upper_bound.parse().ok()?,
+                _ => return None,
+            }
+        }
+        Some(fuzzy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fuzzy() {
+        let fuzzy = |max_difference, total_pixels| Fuzzy {
+            max_difference,
+            total_pixels,
+        };
+        assert_eq!(
+            Fuzzy::parse("maxDifference=0-2;totalPixels=0-100"),
+            Some(fuzzy(2, 100))
+        );
+        assert_eq!(Fuzzy::parse("3;5-20"), Some(fuzzy(3, 20)));
+        assert_eq!(
+            Fuzzy::parse("box-ref.html:totalPixels=10;maxDifference=1"),
+            Some(fuzzy(1, 10))
+        );
+        assert_eq!(Fuzzy::parse("maxDifference=many"), None);
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        assert!(is_test_file(Path::new("css/box-001.html")));
+        assert!(!is_test_file(Path::new("css/box-001-ref.html")));
+        assert!(!is_test_file(Path::new("css/box-001.xht")));
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Pass,
+    /// The test doesn't render like its references, and why
+    Fail(String),
+    /// The test or one of its references couldn't be loaded
+    Error(String),
+    /// The test can't be run by the runner, and why
+    Skip(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct TestResult {
+    /// Path of the test relative to the root of the suite
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
+
+/// Results of a run, one line per test followed by a summary. Tests are
+/// listed in a stable order so that reports of runs can be diffed to see
+/// what changed.
+pub struct Report<'a> {
+    pub results: &'a [TestResult],
+}
+
+impl Report<'_> {
+    fn count<F: Fn(&Outcome) -> bool>(&self, filter: F) -> usize {
+        self.results
+            .iter()
+            .filter(|result| filter(&result.outcome))
+            .count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(|outcome| *outcome == Outcome::Pass)
+    }
+
+    /// Number of tests that were run, all but the skipped ones
+    pub fn run(&self) -> usize {
+        self.count(|outcome| !matches!(outcome, Outcome::Skip(_)))
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in self.results {
+            let path = result.path.display();
+            match &result.outcome {
+                Outcome::Pass => writeln!(f, "PASS  {}", path)?,
+                Outcome::Fail(reason) => writeln!(f, "FAIL  {} ({})", path, reason)?,
+                Outcome::Error(reason) => writeln!(f, "ERROR {} ({})", path, reason)?,
+                Outcome::Skip(reason) => writeln!(f, "SKIP  {} ({})", path, reason)?,
+            }
+        }
+
+        let (passed, run) = (self.passed(), self.run());
+        let percentage = match run {
+            0 => 0.,
+            _ => passed as f32 / run as f32 * 100.,
+        };
+        writeln!(
+            f,
+            "\nPassed {} of {} tests ({:.1}%), {} failed, {} errors, {} skipped",
+            passed,
+            run,
+            percentage,
+            self.count(|outcome| matches!(outcome, Outcome::Fail(_))),
+            self.count(|outcome| matches!(outcome, Outcome::Error(_))),
+            self.results.len() - run,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let result = |path: &str, outcome| TestResult {
+            path: PathBuf::from(path),
+            outcome,
+        };
+        let results = [
+            result("a.html", Outcome::Pass),
+            result("b.html", Outcome::Fail("12 pixels differ".to_string())),
+            result("c.html", Outcome::Skip("not a reftest".to_string())),
+        ];
+        assert_eq!(
+            Report { results: &results }.to_string(),
+            "PASS  a.html\n\
+             FAIL  b.html (12 pixels differ)\n\
+             SKIP  c.html (not a reftest)\n\
+             \n\
+             Passed 1 of 2 tests (50.0%), 1 failed, 0 errors, 1 skipped\n"
+        );
+    }
+}
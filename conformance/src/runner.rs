@@ -0,0 +1,244 @@
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use dom::node::NodePtr;
+use futures::executor::block_on;
+use gfx::RasterGraphics;
+use loader::ResourceLoader;
+use painting::Painter;
+use render::EngineContext;
+use shared::primitive::Size;
+use url::parser::URLParser;
+
+use crate::manifest::{Fuzzy, Relation, TestMetadata};
+use crate::report::{Outcome, TestResult};
+
+/// Size of the viewport tests are rendered in, in CSS pixels
+pub const VIEWPORT_WIDTH: f32 = 800.;
+pub const VIEWPORT_HEIGHT: f32 = 600.;
+
+/// Renders reftests and their references on the CPU and compares their
+/// pixels
+pub struct Runner {
+    /// Root of the test suite, which results are reported relative to
+    root: PathBuf,
+    context: EngineContext,
+    painter: Painter<RasterGraphics>,
+}
+
+/// How two renderings differ
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// Number of pixels that differ
+    pub pixels: usize,
+    /// Largest difference of a color channel of a pixel
+    pub max_difference: u8,
+}
+
+impl Runner {
+    /// Create a runner for the test suite at `root`. The Ahem font of the
+    /// suite, which many layout tests are written with, is made available
+    /// to the tests, and their stylesheets are loaded from their files.
+    pub fn new(root: &Path) -> Self {
+        ResourceLoader::init();
+
+        let ahem = root.join("fonts").join("Ahem.ttf");
+        match std::fs::read(&ahem) {
+            // Test files are loaded from file URLs, whose origin is opaque
+            Ok(data) => {
+                if let Err(e) = gfx::register_font("Ahem", data, "null") {
+                    log::error!("Unable to register Ahem: {}", e);
+                }
+            }
+            Err(_) => log::warn!("Ahem not found at {}", ahem.display()),
+        }
+
+        Self {
+            root: root.to_path_buf(),
+            context: EngineContext::new(),
+            painter: new_painter(),
+        }
+    }
+
+    pub fn run(&mut self, tests: &[PathBuf]) -> Vec<TestResult> {
+        tests
+            .iter()
+            .map(|test| TestResult {
+                path: test.strip_prefix(&self.root).unwrap_or(test).to_path_buf(),
+                outcome: self.run_caught(test),
+            })
+            .collect()
+    }
+
+    /// Run a test, counting a panic of the engine as an error of the test
+    /// instead of ending the run
+    fn run_caught(&mut self, test: &Path) -> Outcome {
+        match catch_unwind(AssertUnwindSafe(|| self.run_test(test))) {
+            Ok(outcome) => outcome,
+            Err(payload) => {
+                // The painter may have stopped in the middle of a frame
+                self.painter = new_painter();
+                Outcome::Error(format!("panicked: {}", panic_message(payload.as_ref())))
+            }
+        }
+    }
+
+    fn run_test(&mut self, test: &Path) -> Outcome {
+        let document = match self.load(test) {
+            Ok(document) => document,
+            Err(e) => return Outcome::Error(e),
+        };
+        let directory = test.parent().unwrap_or_else(|| Path::new(""));
+        let metadata = TestMetadata::of_document(&document, directory);
+        if metadata.references.is_empty() {
+            return Outcome::Skip("not a reftest".to_string());
+        }
+        if metadata.is_waiting {
+            return Outcome::Skip("waits for script".to_string());
+        }
+
+        let rendering = self.render(&document);
+        for reference in &metadata.references {
+            let reference_rendering = match self.load(&reference.path) {
+                Ok(document) => self.render(&document),
+                Err(e) => return Outcome::Error(e),
+            };
+            let difference = compare(&rendering, &reference_rendering);
+            let is_match = is_within(&difference, &metadata.fuzzy);
+            let name = reference
+                .path
+                .strip_prefix(&self.root)
+                .unwrap_or(&reference.path)
+                .display();
+            match (&reference.relation, is_match) {
+                (Relation::Match, false) => {
+                    return Outcome::Fail(format!(
+                        "{} pixels differ from {}, by up to {}",
+                        difference.pixels, name, difference.max_difference
+                    ));
+                }
+                (Relation::Mismatch, true) => {
+                    return Outcome::Fail(format!("renders like {}", name));
+                }
+                _ => {}
+            }
+        }
+        Outcome::Pass
+    }
+
+    fn load(&self, path: &Path) -> Result<NodePtr, String> {
+        let html = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+        let absolute_path = std::fs::canonicalize(path)
+            .map_err(|e| format!("unable to resolve {}: {}", path.display(), e))?;
+        let url = format!("file://{}", absolute_path.display());
+        let base_url =
+            URLParser::parse(&url, None).ok_or_else(|| format!("invalid test URL: {}", url))?;
+        Ok(render::parse_document(&html, base_url, None, &self.context))
+    }
+
+    /// Pixels of the document in RGBA8
+    fn render(&mut self, document: &NodePtr) -> Vec<u8> {
+        render::calculate_styles(document.clone());
+        let viewport = Size::new(VIEWPORT_WIDTH, VIEWPORT_HEIGHT);
+        if let Some(layout_tree) = render::calculate_layout(document.clone(), &viewport) {
+            self.painter.paint(&layout_tree);
+        }
+        block_on(self.painter.output())
+    }
+}
+
+fn new_painter() -> Painter<RasterGraphics> {
+    let mut painter = Painter::new(RasterGraphics::new());
+    painter.resize(Size::new(VIEWPORT_WIDTH, VIEWPORT_HEIGHT));
+    painter
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .unwrap_or("unknown panic"),
+    }
+}
+
+/// Compare two renderings of the same size, pixel by pixel
+pub fn compare(a: &[u8], b: &[u8]) -> Difference {
+    let mut difference = Difference {
+        pixels: 0,
+        max_difference: 0,
+    };
+    for (pixel_a, pixel_b) in a.chunks(4).zip(b.chunks(4)) {
+        let max_difference = pixel_a
+            .iter()
+            .zip(pixel_b)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        if max_difference > 0 {
+            difference.pixels += 1;
+            difference.max_difference = difference.max_difference.max(max_difference);
+        }
+    }
+    difference
+}
+
+fn is_within(difference: &Difference, fuzzy: &Fuzzy) -> bool {
+    difference.pixels == 0
+        || (difference.max_difference <= fuzzy.max_difference
+            && difference.pixels <= fuzzy.total_pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::discover;
+
+    #[test]
+    fn test_compare() {
+        let white = [255, 255, 255, 255];
+        let a = [white, white, [0, 0, 0, 255]].concat();
+        let b = [white, [255, 253, 255, 255], [0, 0, 10, 255]].concat();
+        assert_eq!(
+            compare(&a, &b),
+            Difference {
+                pixels: 2,
+                max_difference: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = catch_unwind(|| panic!("unable to {}", "paint")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "unable to paint");
+        let payload = catch_unwind(|| panic!("unable to paint")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "unable to paint");
+    }
+
+    #[test]
+    fn test_run_fixtures() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/wpt");
+        let mut runner = Runner::new(&root);
+        let results = runner.run(&discover(std::slice::from_ref(&root)));
+
+        let outcome = |name: &str| {
+            results
+                .iter()
+                .find(|result| result.path == Path::new("css").join(name))
+                .map(|result| result.outcome.clone())
+                .unwrap_or_else(|| panic!("{} was not run", name))
+        };
+        assert_eq!(results.len(), 7);
+        assert_eq!(outcome("box-001.html"), Outcome::Pass);
+        assert_eq!(outcome("box-002.html"), Outcome::Pass);
+        assert!(matches!(outcome("box-003.html"), Outcome::Fail(_)));
+        assert_eq!(outcome("fuzzy-001.html"), Outcome::Pass);
+        assert!(matches!(outcome("no-reference.html"), Outcome::Skip(_)));
+        assert!(matches!(outcome("reftest-wait.html"), Outcome::Skip(_)));
+        assert_eq!(outcome("stylesheet-001.html"), Outcome::Pass);
+    }
+}
@@ -0,0 +1,197 @@
+/*
+ * C API of the Moon engine, to embed it as an HTML view.
+ *
+ * Link with libmoon_embed, built by `cargo build -p embed --release`.
+ *
+ * An engine renders on its own thread. Use each engine from a single
+ * thread of the host: send it input, then poll it for frames, typically
+ * once per display refresh.
+ */
+
+#ifndef MOON_H
+#define MOON_H
+
+/* Generated by cbindgen from embed/src/lib.rs, don't edit it by hand */
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define MOON_MOUSE_DOWN 0
+
+#define MOON_MOUSE_MOVE 1
+
+#define MOON_MOUSE_UP 2
+
+#define MOON_KEY_ENTER 0
+
+#define MOON_KEY_BACKSPACE 1
+
+#define MOON_KEY_DELETE 2
+
+#define MOON_KEY_ARROW_LEFT 3
+
+#define MOON_KEY_ARROW_RIGHT 4
+
+#define MOON_KEY_ARROW_UP 5
+
+#define MOON_KEY_ARROW_DOWN 6
+
+#define MOON_KEY_HOME 7
+
+#define MOON_KEY_END 8
+
+/*
+ * A typed character, passed as a Unicode code point
+ */
+#define MOON_KEY_CHARACTER 9
+
+#define MOON_MODIFIER_SHIFT 1
+
+#define MOON_MODIFIER_CTRL (1 << 1)
+
+/*
+ * Render engine running on its own thread, driven by the host through the
+ * C functions of the crate
+ */
+typedef struct MoonEngine MoonEngine;
+
+/*
+ * A frame taken by the host, in RGBA8
+ */
+typedef struct MoonFrame MoonFrame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ * Start an engine with a viewport of the size in device pixels. Returns
+ * NULL if the engine failed to start.
+ */
+MoonEngine *moon_engine_create(uint32_t width, uint32_t height, float device_pixel_ratio);
+
+/*
+ * Stop the engine. Frames taken from it stay valid until released.
+ *
+ * # Safety
+ * `engine` is NULL or was returned by `moon_engine_create` and isn't used
+ * afterwards.
+ */
+void moon_engine_destroy(MoonEngine *engine);
+
+/*
+ * Load a page from its source. Relative URLs of the page are resolved
+ * against `base_url`. Returns false if the strings aren't valid.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`, and the strings are
+ * NUL-terminated UTF-8.
+ */
+bool moon_engine_load_html(MoonEngine *engine, const char *html, const char *base_url);
+
+/*
+ * Fetch a page then load it. Blocks until the page is fetched. Returns
+ * false if the page couldn't be fetched, see `moon_engine_last_error`.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`, and `url` is a
+ * NUL-terminated UTF-8 string.
+ */
+bool moon_engine_load_url(MoonEngine *engine, const char *url);
+
+/*
+ * Resize the viewport, in device pixels
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+void moon_engine_resize(MoonEngine *engine, uint32_t width, uint32_t height);
+
+/*
+ * Send a `MOON_MOUSE_*` event at the point in viewport coordinates, in
+ * device pixels
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+void moon_engine_send_mouse(MoonEngine *engine, int kind, float x, float y);
+
+/*
+ * Scroll by the distance at the point in viewport coordinates, both in
+ * device pixels
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+void moon_engine_send_scroll(MoonEngine *engine, float x, float y, float delta_x, float delta_y);
+
+/*
+ * Send a `MOON_KEY_*` key pressed with the `MOON_MODIFIER_*` flags. The
+ * character is the Unicode code point typed with `MOON_KEY_CHARACTER`.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+void moon_engine_send_key(MoonEngine *engine, int key, uint32_t character, uint32_t modifiers);
+
+/*
+ * Take the latest frame rendered since the last poll, or NULL if there is
+ * none. The frame is released with `moon_frame_release`.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+MoonFrame *moon_engine_poll_frame(MoonEngine *engine);
+
+/*
+ * Title of the page, or NULL if it has none. The string is valid until
+ * the next call with the engine.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+const char *moon_engine_title(MoonEngine *engine);
+
+/*
+ * Why the last page couldn't be loaded or the engine stopped, or NULL.
+ * The string is valid until the next call with the engine.
+ *
+ * # Safety
+ * `engine` was returned by `moon_engine_create`.
+ */
+const char *moon_engine_last_error(MoonEngine *engine);
+
+/*
+ * Pixels of the frame in RGBA8, row by row, `width * height * 4` bytes
+ *
+ * # Safety
+ * `frame` was returned by `moon_engine_poll_frame` and isn't released.
+ */
+const uint8_t *moon_frame_pixels(const MoonFrame *frame);
+
+/*
+ * # Safety
+ * `frame` was returned by `moon_engine_poll_frame` and isn't released.
+ */
+uint32_t moon_frame_width(const MoonFrame *frame);
+
+/*
+ * # Safety
+ * `frame` was returned by `moon_engine_poll_frame` and isn't released.
+ */
+uint32_t moon_frame_height(const MoonFrame *frame);
+
+/*
+ * Give the buffer of the frame back to the engine
+ *
+ * # Safety
+ * `frame` is NULL or was returned by `moon_engine_poll_frame` and isn't
+ * used afterwards.
+ */
+void moon_frame_release(MoonFrame *frame);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* MOON_H */
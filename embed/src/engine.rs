@@ -0,0 +1,173 @@
+use std::ffi::{CStr, CString};
+
+use flume::{Receiver, Sender};
+use loader::{ResourceLoader, ResourceType};
+use render::{
+    error_page, ChannelConfig, FrameMailbox, InputEvent, OutputEvent, RenderEngine, SharedFrame,
+};
+use shared::byte_string::ByteString;
+use shared::primitive::Size;
+use url::Url;
+
+/// Render engine running on its own thread, driven by the host through the
+/// C functions of the crate
+pub struct Engine {
+    events: Sender<InputEvent>,
+    output: Receiver<OutputEvent>,
+    frames: FrameMailbox,
+    /// Size of the viewport in device pixels, which frames have to match
+    width: u32,
+    height: u32,
+    /// Kept as C strings for the host to borrow
+    title: Option<CString>,
+    last_error: Option<CString>,
+}
+
+/// A frame taken by the host, in RGBA8
+pub struct Frame {
+    pub bitmap: SharedFrame,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Engine {
+    /// Start an engine and wait until it's ready. Returns `None` if it
+    /// failed to start.
+    pub fn start(width: u32, height: u32, device_pixel_ratio: f32) -> Option<Self> {
+        // The host isn't expected to poll events, so output events are
+        // only drained when it polls frames
+        let channels = ChannelConfig::default().create();
+        let (input_tx, input_rx) = channels.input;
+        let (output_tx, output_rx) = channels.output;
+        let frames = channels.frames;
+        let engine_frames = frames.clone();
+        let (ready_tx, ready_rx) = flume::bounded(1);
+
+        let spawn_result = std::thread::Builder::new()
+            .name("render engine".to_string())
+            .spawn(move || {
+                let rt = match tokio::runtime::Runtime::new() {
+                    Ok(rt) => rt,
+                    Err(e) => {
+                        log::error!("Unable to start render engine runtime: {}", e);
+                        let _ = ready_tx.send(false);
+                        return;
+                    }
+                };
+                rt.block_on(async {
+                    let viewport = Size::new(width as f32, height as f32);
+                    let engine =
+                        match RenderEngine::new_with_dpr(viewport, device_pixel_ratio).await {
+                            Ok(engine) => engine,
+                            Err(e) => {
+                                log::error!("Unable to start render engine: {}", e);
+                                let _ = ready_tx.send(false);
+                                return;
+                            }
+                        };
+                    let _ = ready_tx.send(true);
+                    if let Err(e) = engine.run(input_rx, output_tx.clone(), engine_frames).await {
+                        let message = format!("Render engine exited with error: {}", e);
+                        log::error!("{}", message);
+                        let _ = output_tx.send(OutputEvent::EngineError(message));
+                    }
+                });
+            });
+        if let Err(e) = spawn_result {
+            log::error!("Unable to spawn render engine thread: {}", e);
+            return None;
+        }
+        if !ready_rx.recv().unwrap_or(false) {
+            return None;
+        }
+
+        Some(Self {
+            events: input_tx,
+            output: output_rx,
+            frames,
+            width,
+            height,
+            title: None,
+            last_error: None,
+        })
+    }
+
+    pub fn send(&self, event: InputEvent) {
+        if self.events.send(event).is_err() {
+            log::warn!("Render engine is not running");
+        }
+    }
+
+    pub fn load_html(&self, html: String, base_url: Url) {
+        self.send(InputEvent::LoadHTML { html, base_url });
+    }
+
+    /// Fetch the page at the URL then load it. Blocks until the page is
+    /// fetched, a page that can't be fetched is reported as the last error
+    /// and an error page is loaded instead.
+    pub fn load_url(&mut self, url: Url) -> bool {
+        match ResourceLoader::current().load_resource(&url, ResourceType::Document) {
+            Ok(bytes) => {
+                self.load_html(ByteString::from_html(&bytes).to_string(), url);
+                true
+            }
+            Err(e) => {
+                self.last_error = to_c_string(e.get_friendly_message());
+                self.load_html(error_page(&url, &e), url);
+                false
+            }
+        }
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        // Frames of the previous size are of no use to the host anymore
+        self.frames.discard();
+        self.send(InputEvent::ViewportResize(Size::new(
+            width as f32,
+            height as f32,
+        )));
+    }
+
+    /// Take the latest frame if one was rendered since the last poll
+    pub fn poll_frame(&mut self) -> Option<Frame> {
+        self.drain_output();
+        let frame_length = self.width as usize * self.height as usize * 4;
+        self.frames
+            .take()
+            .filter(|bitmap| bitmap.len() == frame_length)
+            .map(|bitmap| Frame {
+                bitmap,
+                width: self.width,
+                height: self.height,
+            })
+    }
+
+    pub fn title(&mut self) -> Option<&CStr> {
+        self.drain_output();
+        self.title.as_deref()
+    }
+
+    pub fn last_error(&mut self) -> Option<&CStr> {
+        self.drain_output();
+        self.last_error.as_deref()
+    }
+
+    /// Keep what the host can ask for from the output events and drop the
+    /// others
+    fn drain_output(&mut self) {
+        for event in self.output.try_iter() {
+            match event {
+                OutputEvent::TitleChanged(title) => self.title = to_c_string(title),
+                OutputEvent::EngineError(message) => self.last_error = to_c_string(message),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Strings with a NUL can't be passed to C and are dropped
+fn to_c_string(string: String) -> Option<CString> {
+    CString::new(string).ok()
+}
@@ -0,0 +1,359 @@
+//! C API to embed the engine in C, C++ or Swift applications as an HTML
+//! view. The functions are declared in `include/moon.h`, which is generated
+//! from this file by cbindgen with `cargo make header`.
+//!
+//! An engine renders on its own thread. The host sends it input and polls
+//! it for frames, from a single thread.
+
+mod engine;
+
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use render::{InputEvent, Key, Modifiers, MouseEvent};
+use shared::primitive::Point;
+use url::{parser::URLParser, Url};
+
+pub use engine::{Engine, Frame};
+
+pub const MOON_MOUSE_DOWN: c_int = 0;
+pub const MOON_MOUSE_MOVE: c_int = 1;
+pub const MOON_MOUSE_UP: c_int = 2;
+
+pub const MOON_KEY_ENTER: c_int = 0;
+pub const MOON_KEY_BACKSPACE: c_int = 1;
+pub const MOON_KEY_DELETE: c_int = 2;
+pub const MOON_KEY_ARROW_LEFT: c_int = 3;
+pub const MOON_KEY_ARROW_RIGHT: c_int = 4;
+pub const MOON_KEY_ARROW_UP: c_int = 5;
+pub const MOON_KEY_ARROW_DOWN: c_int = 6;
+pub const MOON_KEY_HOME: c_int = 7;
+pub const MOON_KEY_END: c_int = 8;
+/// A typed character, passed as a Unicode code point
+pub const MOON_KEY_CHARACTER: c_int = 9;
+
+pub const MOON_MODIFIER_SHIFT: u32 = 1;
+pub const MOON_MODIFIER_CTRL: u32 = 1 << 1;
+
+/// Start an engine with a viewport of the size in device pixels. Returns
+/// NULL if the engine failed to start.
+#[no_mangle]
+pub extern "C" fn moon_engine_create(
+    width: u32,
+    height: u32,
+    device_pixel_ratio: f32,
+) -> *mut Engine {
+    match Engine::start(width, height, device_pixel_ratio) {
+        Some(engine) => Box::into_raw(Box::new(engine)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Stop the engine. Frames taken from it stay valid until released.
+///
+/// # Safety
+/// `engine` is NULL or was returned by `moon_engine_create` and isn't used
+/// afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_destroy(engine: *mut Engine) {
+    if !engine.is_null() {
+        // Dropping the input channel stops the engine
+        drop(Box::from_raw(engine));
+    }
+}
+
+/// Load a page from its source. Relative URLs of the page are resolved
+/// against `base_url`. Returns false if the strings aren't valid.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`, and the strings are
+/// NUL-terminated UTF-8.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_load_html(
+    engine: *mut Engine,
+    html: *const c_char,
+    base_url: *const c_char,
+) -> bool {
+    let (engine, html) = match (engine.as_mut(), to_str(html)) {
+        (Some(engine), Some(html)) => (engine, html),
+        _ => return false,
+    };
+    match to_url(base_url) {
+        Some(base_url) => {
+            engine.load_html(html.to_string(), base_url);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Fetch a page then load it. Blocks until the page is fetched. Returns
+/// false if the page couldn't be fetched, see `moon_engine_last_error`.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`, and `url` is a
+/// NUL-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_load_url(engine: *mut Engine, url: *const c_char) -> bool {
+    match (engine.as_mut(), to_url(url)) {
+        (Some(engine), Some(url)) => engine.load_url(url),
+        _ => false,
+    }
+}
+
+/// Resize the viewport, in device pixels
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_resize(engine: *mut Engine, width: u32, height: u32) {
+    if let Some(engine) = engine.as_mut() {
+        engine.resize(width, height);
+    }
+}
+
+/// Send a `MOON_MOUSE_*` event at the point in viewport coordinates, in
+/// device pixels
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_send_mouse(engine: *mut Engine, kind: c_int, x: f32, y: f32) {
+    let point = Point::new(x, y);
+    let event = match kind {
+        MOON_MOUSE_DOWN => MouseEvent::Down(point),
+        MOON_MOUSE_MOVE => MouseEvent::Move(point),
+        MOON_MOUSE_UP => MouseEvent::Up(point),
+        _ => return,
+    };
+    if let Some(engine) = engine.as_ref() {
+        engine.send(InputEvent::Mouse(event));
+    }
+}
+
+/// Scroll by the distance at the point in viewport coordinates, both in
+/// device pixels
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_send_scroll(
+    engine: *mut Engine,
+    x: f32,
+    y: f32,
+    delta_x: f32,
+    delta_y: f32,
+) {
+    if let Some(engine) = engine.as_ref() {
+        engine.send(InputEvent::Scroll {
+            x,
+            y,
+            delta_x,
+            delta_y,
+        });
+    }
+}
+
+/// Send a `MOON_KEY_*` key pressed with the `MOON_MODIFIER_*` flags. The
+/// character is the Unicode code point typed with `MOON_KEY_CHARACTER`.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_send_key(
+    engine: *mut Engine,
+    key: c_int,
+    character: u32,
+    modifiers: u32,
+) {
+    let key = match to_key(key, character) {
+        Some(key) => key,
+        None => return,
+    };
+    let modifiers = Modifiers {
+        shift: modifiers & MOON_MODIFIER_SHIFT != 0,
+        ctrl: modifiers & MOON_MODIFIER_CTRL != 0,
+    };
+    if let Some(engine) = engine.as_ref() {
+        engine.send(InputEvent::KeyDown { key, modifiers });
+    }
+}
+
+/// Take the latest frame rendered since the last poll, or NULL if there is
+/// none. The frame is released with `moon_frame_release`.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_poll_frame(engine: *mut Engine) -> *mut Frame {
+    match engine.as_mut().and_then(|engine| engine.poll_frame()) {
+        Some(frame) => Box::into_raw(Box::new(frame)),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Title of the page, or NULL if it has none. The string is valid until
+/// the next call with the engine.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_title(engine: *mut Engine) -> *const c_char {
+    match engine.as_mut().and_then(|engine| engine.title()) {
+        Some(title) => title.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Why the last page couldn't be loaded or the engine stopped, or NULL.
+/// The string is valid until the next call with the engine.
+///
+/// # Safety
+/// `engine` was returned by `moon_engine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn moon_engine_last_error(engine: *mut Engine) -> *const c_char {
+    match engine.as_mut().and_then(|engine| engine.last_error()) {
+        Some(error) => error.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Pixels of the frame in RGBA8, row by row, `width * height * 4` bytes
+///
+/// # Safety
+/// `frame` was returned by `moon_engine_poll_frame` and isn't released.
+#[no_mangle]
+pub unsafe extern "C" fn moon_frame_pixels(frame: *const Frame) -> *const u8 {
+    match frame.as_ref() {
+        Some(frame) => frame.bitmap.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// # Safety
+/// `frame` was returned by `moon_engine_poll_frame` and isn't released.
+#[no_mangle]
+pub unsafe extern "C" fn moon_frame_width(frame: *const Frame) -> u32 {
+    frame.as_ref().map(|frame| frame.width).unwrap_or(0)
+}
+
+/// # Safety
+/// `frame` was returned by `moon_engine_poll_frame` and isn't released.
+#[no_mangle]
+pub unsafe extern "C" fn moon_frame_height(frame: *const Frame) -> u32 {
+    frame.as_ref().map(|frame| frame.height).unwrap_or(0)
+}
+
+/// Give the buffer of the frame back to the engine
+///
+/// # Safety
+/// `frame` is NULL or was returned by `moon_engine_poll_frame` and isn't
+/// used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn moon_frame_release(frame: *mut Frame) {
+    if !frame.is_null() {
+        drop(Box::from_raw(frame));
+    }
+}
+
+unsafe fn to_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok()
+}
+
+unsafe fn to_url(string: *const c_char) -> Option<Url> {
+    URLParser::parse(to_str(string)?, None)
+}
+
+fn to_key(key: c_int, character: u32) -> Option<Key> {
+    Some(match key {
+        MOON_KEY_ENTER => Key::Enter,
+        MOON_KEY_BACKSPACE => Key::Backspace,
+        MOON_KEY_DELETE => Key::Delete,
+        MOON_KEY_ARROW_LEFT => Key::ArrowLeft,
+        MOON_KEY_ARROW_RIGHT => Key::ArrowRight,
+        MOON_KEY_ARROW_UP => Key::ArrowUp,
+        MOON_KEY_ARROW_DOWN => Key::ArrowDown,
+        MOON_KEY_HOME => Key::Home,
+        MOON_KEY_END => Key::End,
+        MOON_KEY_CHARACTER => Key::Character(char::from_u32(character)?),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = include_str!("../include/moon.h");
+
+    #[test]
+    fn test_header_declares_functions() {
+        let source = include_str!("lib.rs");
+        let functions = source
+            .lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|declaration| declaration.split('(').next())
+            .collect::<Vec<_>>();
+        assert_eq!(functions.len(), 15);
+        for function in functions {
+            assert!(
+                HEADER.contains(&format!(" {}(", function))
+                    || HEADER.contains(&format!("*{}(", function)),
+                "{} is missing from moon.h",
+                function
+            );
+        }
+    }
+
+    #[test]
+    fn test_header_constants() {
+        let constants = [
+            ("MOON_MOUSE_UP", MOON_MOUSE_UP as u32),
+            ("MOON_KEY_ENTER", MOON_KEY_ENTER as u32),
+            ("MOON_KEY_CHARACTER", MOON_KEY_CHARACTER as u32),
+            ("MOON_MODIFIER_CTRL", MOON_MODIFIER_CTRL),
+        ];
+        for (name, value) in constants {
+            let definition = HEADER
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("#define {} ", name)))
+                .unwrap_or_else(|| panic!("{} is missing from moon.h", name));
+            let header_value = match definition
+                .trim_matches(|c| c == '(' || c == ')')
+                .split_once(" << ")
+            {
+                Some((value, shift)) => {
+                    value.parse::<u32>().unwrap() << shift.parse::<u32>().unwrap()
+                }
+                None => definition.parse().unwrap(),
+            };
+            assert_eq!(header_value, value, "{} doesn't match moon.h", name);
+        }
+    }
+
+    #[test]
+    fn test_to_key() {
+        assert!(matches!(to_key(MOON_KEY_HOME, 0), Some(Key::Home)));
+        assert!(matches!(
+            to_key(MOON_KEY_CHARACTER, 'é' as u32),
+            Some(Key::Character('é'))
+        ));
+        assert!(to_key(MOON_KEY_CHARACTER, 0xD800).is_none());
+        assert!(to_key(42, 0).is_none());
+    }
+
+    #[test]
+    fn test_null_handles() {
+        unsafe {
+            assert!(!moon_engine_load_url(ptr::null_mut(), ptr::null()));
+            assert!(moon_engine_poll_frame(ptr::null_mut()).is_null());
+            assert!(moon_frame_pixels(ptr::null()).is_null());
+            moon_engine_destroy(ptr::null_mut());
+            moon_frame_release(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::{self, BufReader, BufWriter};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use painting::command::DisplayList;
+use shared::color::Color;
+
+use crate::protocol::{read_message, write_message, Request, Response};
+
+#[derive(Debug)]
+pub enum GpuProcessError {
+    /// The GPU process couldn't be started or failed to start its graphics
+    Spawn(String),
+    /// The GPU process exited or broke the protocol. It's started again
+    /// for the next frame.
+    Crashed(String),
+    /// The GPU process couldn't rasterize the frame but keeps running
+    Raster(String),
+}
+
+impl std::fmt::Display for GpuProcessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GpuProcessError::Spawn(error) => {
+                write!(f, "Unable to start the GPU process: {}", error)
+            }
+            GpuProcessError::Crashed(error) => write!(f, "The GPU process crashed: {}", error),
+            GpuProcessError::Raster(error) => write!(f, "Unable to rasterize frame: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GpuProcessError {}
+
+/// A frame rasterized by the GPU process, in RGBA8 row by row
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// The renderer side of the protocol, running `moon-gpu` as a child
+/// process. The process is started again after it crashes, with the fonts
+/// registered by pages so far.
+pub struct GpuProcess {
+    program: PathBuf,
+    args: Vec<String>,
+    connection: Option<Connection>,
+}
+
+struct Connection {
+    child: Child,
+    requests: BufWriter<ChildStdin>,
+    responses: BufReader<ChildStdout>,
+    /// Number of fonts registered by pages that the process has
+    synced_fonts: usize,
+}
+
+impl GpuProcess {
+    /// Run `program` as the GPU process and wait until its graphics are
+    /// started
+    pub fn spawn<P: Into<PathBuf>>(program: P, args: &[&str]) -> Result<Self, GpuProcessError> {
+        let mut process = Self {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            connection: None,
+        };
+        process.connection = Some(process.connect()?);
+        Ok(process)
+    }
+
+    /// Rasterize the display list over the background color, with `scale`
+    /// device pixels per CSS pixel
+    pub fn raster(
+        &mut self,
+        display_list: &DisplayList,
+        scale: f32,
+        background: Color,
+    ) -> Result<Frame, GpuProcessError> {
+        if self.connection.is_none() {
+            self.connection = Some(self.connect()?);
+        }
+        let connection = self
+            .connection
+            .as_mut()
+            .expect("The GPU process is started");
+        let result = connection.raster(display_list, scale, background);
+        if let Err(GpuProcessError::Crashed(_)) = result {
+            self.connection = None;
+        }
+        result
+    }
+
+    fn connect(&self) -> Result<Connection, GpuProcessError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| GpuProcessError::Spawn(e.to_string()))?;
+        let mut connection = Connection {
+            requests: BufWriter::new(child.stdin.take().expect("stdin is piped")),
+            responses: BufReader::new(child.stdout.take().expect("stdout is piped")),
+            child,
+            synced_fonts: 0,
+        };
+        match read_message(&mut connection.responses) {
+            Ok(Some((Response::Ready, _))) => Ok(connection),
+            _ => Err(GpuProcessError::Spawn(connection.stop())),
+        }
+    }
+}
+
+impl Connection {
+    fn raster(
+        &mut self,
+        display_list: &DisplayList,
+        scale: f32,
+        background: Color,
+    ) -> Result<Frame, GpuProcessError> {
+        self.sync_fonts()?;
+        let request = Request::Raster {
+            display_list: display_list.clone(),
+            scale,
+            background,
+        };
+        match self.request(&request, &[])? {
+            (Response::Frame { width, height }, pixels)
+                if pixels.len() == (width * height * 4) as usize =>
+            {
+                Ok(Frame {
+                    width,
+                    height,
+                    pixels,
+                })
+            }
+            (Response::Error(error), _) => Err(GpuProcessError::Raster(error)),
+            (response, _) => Err(self.crashed(format!("Unexpected response {:?}", response))),
+        }
+    }
+
+    /// Register the fonts registered by pages since the last frame. They
+    /// get the same indexes in the process, as long as they are registered
+    /// in the same order.
+    fn sync_fonts(&mut self) -> Result<(), GpuProcessError> {
+        for font in gfx::registered_fonts_from(self.synced_fonts) {
+            let request = Request::RegisterFont {
+                family: font.family.clone(),
+                origin: font.origin.clone(),
+            };
+            match self.request(&request, &font.data)? {
+                (Response::FontRegistered(_), _) => {}
+                (Response::Error(error), _) => {
+                    log::warn!("GPU process can't register font {}: {}", font.family, error)
+                }
+                (response, _) => {
+                    return Err(self.crashed(format!("Unexpected response {:?}", response)))
+                }
+            }
+            self.synced_fonts += 1;
+        }
+        Ok(())
+    }
+
+    fn request(
+        &mut self,
+        request: &Request,
+        payload: &[u8],
+    ) -> Result<(Response, Vec<u8>), GpuProcessError> {
+        let result = write_message(&mut self.requests, request, payload)
+            .and_then(|_| read_message(&mut self.responses))
+            .and_then(|response| {
+                response.ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "exited"))
+            });
+        result.map_err(|e| self.crashed(e.to_string()))
+    }
+
+    fn crashed(&mut self, error: String) -> GpuProcessError {
+        GpuProcessError::Crashed(format!("{}, {}", error, self.stop()))
+    }
+
+    /// Stop the process and describe how it exited
+    fn stop(&mut self) -> String {
+        let _ = self.child.kill();
+        match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::primitive::Size;
+
+    #[test]
+    fn test_process_fails_to_start() {
+        let error = GpuProcess::spawn("sh", &["-c", "exit 3"]).err().unwrap();
+        assert!(
+            matches!(&error, GpuProcessError::Spawn(status) if status.contains('3')),
+            "{}",
+            error
+        );
+        assert!(matches!(
+            GpuProcess::spawn("./missing-moon-gpu", &[]),
+            Err(GpuProcessError::Spawn(_))
+        ));
+    }
+
+    #[test]
+    fn test_process_crash() {
+        // Says it's ready, then exits on the first request
+        let ready = "printf '\\007\\000\\000\\000\"Ready\"\\000\\000\\000\\000'";
+        let mut process =
+            GpuProcess::spawn("sh", &["-c", &format!("{}; head -c 1", ready)]).unwrap();
+        let display_list = DisplayList {
+            version: painting::command::DISPLAY_LIST_VERSION,
+            viewport: Size::new(1., 1.),
+            commands: Vec::new(),
+        };
+        let white = Color::new(255, 255, 255, 255);
+        assert!(matches!(
+            process.raster(&display_list, 1., white.clone()),
+            Err(GpuProcessError::Crashed(_))
+        ));
+        // The process is started again for the next frame
+        assert!(process.connection.is_none());
+        assert!(matches!(
+            process.raster(&display_list, 1., white),
+            Err(GpuProcessError::Crashed(_))
+        ));
+    }
+}
@@ -0,0 +1,15 @@
+//! Rasterization in a separate GPU process. The renderer records the draws
+//! of its frames into display lists and sends them to the `moon-gpu`
+//! process, which owns the graphics device and sends the rasterized frames
+//! back. A crash of the graphics driver only takes the GPU process down,
+//! and the renderer starts it again.
+//!
+//! Messages go through the standard input and output of the process, see
+//! `protocol`.
+
+mod client;
+pub mod protocol;
+mod server;
+
+pub use client::{Frame, GpuProcess, GpuProcessError};
+pub use server::{Backend, GpuServer};
@@ -0,0 +1,36 @@
+use std::io::{self, BufReader, BufWriter};
+
+use gfx::{Canvas, GfxConfig, RasterGraphics};
+use gpu::{Backend, GpuServer};
+
+const USAGE: &str = "Usage: moon-gpu [--software]";
+
+/// Rasterize the display lists sent to the standard input, with the GPU or
+/// on the CPU with `--software`. Logs go to the standard error, since the
+/// standard output carries the frames.
+fn main() {
+    let result = match std::env::args().nth(1).as_deref() {
+        None => match futures::executor::block_on(Canvas::new(GfxConfig::default())) {
+            Ok(canvas) => serve(canvas),
+            Err(e) => {
+                eprintln!("Unable to start graphics: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some("--software") => serve(RasterGraphics::new()),
+        Some(_) => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Unable to talk to the renderer: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn serve<G: Backend>(gfx: G) -> io::Result<()> {
+    let mut requests = BufReader::new(io::stdin());
+    let mut responses = BufWriter::new(io::stdout());
+    futures::executor::block_on(GpuServer::new(gfx).serve(&mut requests, &mut responses))
+}
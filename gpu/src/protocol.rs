@@ -0,0 +1,114 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+
+use gfx::FontIndex;
+use painting::command::DisplayList;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shared::color::Color;
+
+/// Sent by the renderer to the GPU process
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    /// Register a font used by the display lists that follow. The font file
+    /// is the payload of the message.
+    RegisterFont { family: String, origin: String },
+    /// Rasterize the display list over the background color, with `scale`
+    /// device pixels per CSS pixel
+    Raster {
+        display_list: DisplayList,
+        scale: f32,
+        background: Color,
+    },
+}
+
+/// Sent by the GPU process in reply to each request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    /// Sent once when the process has started its graphics, before any
+    /// request
+    Ready,
+    FontRegistered(FontIndex),
+    /// A rasterized frame, whose RGBA8 pixels are the payload of the message
+    Frame {
+        width: u32,
+        height: u32,
+    },
+    /// The request couldn't be handled, the process keeps running
+    Error(String),
+}
+
+/// Write a message: its length and JSON, then the length of its payload
+/// and the payload bytes. Lengths are little-endian `u32`s.
+pub fn write_message<W: Write, T: Serialize>(
+    writer: &mut W,
+    message: &T,
+    payload: &[u8],
+) -> io::Result<()> {
+    let json = serde_json::to_vec(message)?;
+    write_chunk(writer, &json)?;
+    write_chunk(writer, payload)?;
+    writer.flush()
+}
+
+/// Read a message written by `write_message`. Returns `None` when the
+/// other end closed the stream between messages.
+pub fn read_message<R: Read, T: DeserializeOwned>(
+    reader: &mut R,
+) -> io::Result<Option<(T, Vec<u8>)>> {
+    // The stream may only end before the first byte of a message
+    let mut first_byte = [0; 1];
+    if reader.read(&mut first_byte)? == 0 {
+        return Ok(None);
+    }
+    let json = read_chunk(&mut first_byte.as_ref().chain(&mut *reader))?;
+    let message =
+        serde_json::from_slice(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let payload = read_chunk(reader)?;
+    Ok(Some((message, payload)))
+}
+
+fn write_chunk<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    let length = u32::try_from(bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message is too large"))?;
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_chunk<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let mut bytes = vec![0; u32::from_le_bytes(length) as usize];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut stream = Vec::new();
+        let request = Request::RegisterFont {
+            family: "Test Sans".to_string(),
+            origin: "https://fonts.test".to_string(),
+        };
+        write_message(&mut stream, &request, &[1, 2, 3]).unwrap();
+        write_message(&mut stream, &Response::FontRegistered(2), &[]).unwrap();
+
+        let mut reader = stream.as_slice();
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some((request, vec![1, 2, 3]))
+        );
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some((Response::FontRegistered(2), vec![]))
+        );
+        assert_eq!(read_message::<_, Response>(&mut reader).unwrap(), None);
+
+        // A stream cut in the middle of a message is an error
+        let mut truncated = &stream[..6];
+        assert!(read_message::<_, Request>(&mut truncated).is_err());
+    }
+}
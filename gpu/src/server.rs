@@ -0,0 +1,151 @@
+use std::io::{self, Read, Write};
+
+use gfx::{Canvas, Graphics, RasterGraphics};
+use shared::{color::Color, primitive::Size};
+
+use crate::protocol::{read_message, write_message, Request, Response};
+
+/// Graphics the GPU process rasterizes display lists with
+pub trait Backend: Graphics {
+    /// Set the color the frames are painted over
+    fn set_background(&mut self, color: Color);
+    /// Size the frame for a viewport in CSS pixels, with `scale` device
+    /// pixels per CSS pixel. Returns the size of the frame in device pixels.
+    fn resize_frame(&mut self, viewport: &Size, scale: f32) -> (u32, u32);
+}
+
+impl Backend for Canvas<'_> {
+    fn set_background(&mut self, color: Color) {
+        Canvas::set_background(self, color);
+    }
+
+    fn resize_frame(&mut self, viewport: &Size, scale: f32) -> (u32, u32) {
+        let size = (
+            (viewport.width * scale).round() as u32,
+            (viewport.height * scale).round() as u32,
+        );
+        self.resize(size);
+        self.set_scale(scale);
+        size
+    }
+}
+
+impl Backend for RasterGraphics {
+    fn set_background(&mut self, color: Color) {
+        RasterGraphics::set_background(self, color);
+    }
+
+    fn resize_frame(&mut self, viewport: &Size, scale: f32) -> (u32, u32) {
+        Graphics::resize(self, viewport.clone());
+        self.set_scale(scale);
+        self.bitmap_size()
+    }
+}
+
+/// The GPU process side of the protocol
+pub struct GpuServer<G: Backend> {
+    gfx: G,
+}
+
+impl<G: Backend> GpuServer<G> {
+    pub fn new(gfx: G) -> Self {
+        Self { gfx }
+    }
+
+    /// Handle the requests of the renderer until it closes the stream
+    pub async fn serve<R: Read, W: Write>(
+        &mut self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        write_message(writer, &Response::Ready, &[])?;
+        while let Some((request, payload)) = read_message(reader)? {
+            let (response, payload) = self.handle(request, payload).await;
+            write_message(writer, &response, &payload)?;
+        }
+        Ok(())
+    }
+
+    async fn handle(&mut self, request: Request, payload: Vec<u8>) -> (Response, Vec<u8>) {
+        match request {
+            Request::RegisterFont { family, origin } => {
+                match gfx::register_font(&family, payload, &origin) {
+                    Ok(index) => (Response::FontRegistered(index), Vec::new()),
+                    Err(e) => (Response::Error(e.to_string()), Vec::new()),
+                }
+            }
+            Request::Raster {
+                display_list,
+                scale,
+                background,
+            } => {
+                if !(scale > 0. && scale.is_finite()) {
+                    let error = format!("Invalid scale {}", scale);
+                    return (Response::Error(error), Vec::new());
+                }
+                let (width, height) = self.gfx.resize_frame(&display_list.viewport, scale);
+                if width == 0 || height == 0 {
+                    return (Response::Frame { width, height }, Vec::new());
+                }
+                self.gfx.set_background(background);
+                display_list.replay(&mut self.gfx);
+                let pixels = self.gfx.output().await;
+                (Response::Frame { width, height }, pixels)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use painting::command::DisplayListRecorder;
+    use shared::primitive::Rect;
+
+    #[test]
+    fn test_raster_display_list() {
+        let mut recorder = DisplayListRecorder::new();
+        recorder.resize(Size::new(4., 2.));
+        recorder.fill_rect(Rect::new(0., 0., 1., 1.), Color::new(255, 0, 0, 255));
+        let display_list = recorder.take_display_list();
+
+        let mut requests = Vec::new();
+        let font = Request::RegisterFont {
+            family: "Broken".to_string(),
+            origin: "null".to_string(),
+        };
+        write_message(&mut requests, &font, &[0; 16]).unwrap();
+        let raster = Request::Raster {
+            display_list,
+            scale: 2.,
+            background: Color::new(255, 255, 255, 255),
+        };
+        write_message(&mut requests, &raster, &[]).unwrap();
+
+        let mut responses = Vec::new();
+        let mut server = GpuServer::new(RasterGraphics::new());
+        futures::executor::block_on(server.serve(&mut requests.as_slice(), &mut responses))
+            .unwrap();
+
+        let mut responses = responses.as_slice();
+        let mut next = || {
+            read_message::<_, Response>(&mut responses)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(next().0, Response::Ready);
+        // The process keeps handling requests after one failed
+        assert!(matches!(next().0, Response::Error(_)));
+        let (frame, pixels) = next();
+        assert_eq!(
+            frame,
+            Response::Frame {
+                width: 8,
+                height: 4
+            }
+        );
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        assert_eq!(pixel(1, 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(2, 1), [255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Render a page to a PNG without opening a window, for CI screenshots,
+//! reftests and thumbnails.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use flume::{select::SelectError, Selector};
+use loader::{NetworkSettings, ResourceLoader, ResourceType};
+use main::RenderClient;
+use render::OutputEvent;
+use shared::byte_string::ByteString;
+use shared::primitive::Size;
+use url::{parser::URLParser, Url};
+
+const USAGE: &str = "Usage: moon-headless <file or URL> <output.png> [--size <width>x<height>] \
+[--timeout <ms>] [--proxy <URL>] [--ca-cert <PEM file>] [--user-agent <user agent>]";
+
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+
+/// How long to wait for the page to render at most
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The page is considered loaded once no frame was rendered for that long,
+/// so that the web fonts and images it loads are part of the screenshot
+const SETTLE_DURATION: Duration = Duration::from_millis(500);
+
+struct Options {
+    url: Url,
+    output_path: String,
+    size: (u32, u32),
+    timeout: Duration,
+    network: NetworkSettings,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = render(&options) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let input = args.next().ok_or("Missing the file or URL to render")?;
+    let output_path = args.next().ok_or("Missing the output path")?;
+    let mut size = DEFAULT_SIZE;
+    let mut timeout = DEFAULT_TIMEOUT;
+    let mut network = NetworkSettings::default();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing the value of {}", arg))?;
+        match arg.as_str() {
+            "--size" => size = parse_size(&value).ok_or("Invalid size")?,
+            "--timeout" => {
+                let millis = value.parse().map_err(|_| "Invalid timeout")?;
+                timeout = Duration::from_millis(millis);
+            }
+            "--proxy" => network.proxy = Some(value),
+            "--ca-cert" => {
+                let certificate = std::fs::read(&value)
+                    .map_err(|e| format!("Unable to read {}: {}", value, e))?;
+                network.root_certificates.push(certificate);
+            }
+            "--user-agent" => network.user_agent = Some(value),
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+    Ok(Options {
+        url: parse_input(&input)?,
+        output_path,
+        size,
+        timeout,
+        network,
+    })
+}
+
+/// URLs are rendered as they are, anything else is a path to a file
+fn parse_input(input: &str) -> Result<Url, String> {
+    if let Some(url) = URLParser::parse(input, None) {
+        if matches!(url.scheme.as_str(), "http" | "https" | "file") {
+            return Ok(url);
+        }
+    }
+    let path = std::fs::canonicalize(Path::new(input))
+        .map_err(|e| format!("Unable to open {}: {}", input, e))?;
+    let url = format!("file://{}", path.display());
+    URLParser::parse(&url, None).ok_or_else(|| format!("Invalid file URL: {}", url))
+}
+
+fn parse_size(raw_size: &str) -> Option<(u32, u32)> {
+    let (width, height) = raw_size.split_once('x')?;
+    let size = (width.parse().ok()?, height.parse().ok()?);
+    match size {
+        (0, _) | (_, 0) => None,
+        size => Some(size),
+    }
+}
+
+fn render(options: &Options) -> Result<(), String> {
+    let (width, height) = options.size;
+    let client = RenderClient::new(1.);
+    client.wait_till_ready();
+    client.resize(Size::new(width as f32, height as f32));
+    ResourceLoader::current().configure_network(&options.network)?;
+
+    let bytes = ResourceLoader::current()
+        .load_resource(&options.url, ResourceType::Document)
+        .map_err(|e| e.get_friendly_message())?;
+    client.load_html(
+        ByteString::from_html(&bytes).to_string(),
+        options.url.clone(),
+    );
+
+    let frame_length = (width * height * 4) as usize;
+    let events = client.events();
+    let frames = client.frames();
+    let deadline = Instant::now() + options.timeout;
+    let mut bitmap = None;
+
+    enum Event {
+        Output(OutputEvent),
+        FramePosted,
+    }
+
+    loop {
+        // Wait for the first frame until the deadline, then until the page
+        // settles
+        let wait_until = match bitmap {
+            Some(_) => deadline.min(Instant::now() + SETTLE_DURATION),
+            None => deadline,
+        };
+        let event = Selector::new()
+            .recv(&events, |event| event.map(Event::Output))
+            .recv(frames.notifications(), |_| Ok(Event::FramePosted))
+            .wait_deadline(wait_until);
+
+        match event {
+            Ok(Ok(Event::Output(OutputEvent::EngineError(e)))) => return Err(e),
+            Ok(Ok(Event::Output(_))) => {}
+            Ok(Ok(Event::FramePosted)) => {
+                // Frames of the viewport before it was resized are skipped
+                if let Some(frame) = frames.take().filter(|frame| frame.len() == frame_length) {
+                    bitmap = Some(frame);
+                }
+            }
+            Ok(Err(_)) => return Err("Render engine stopped".to_string()),
+            Err(SelectError::Timeout) => break,
+        }
+    }
+
+    let bitmap = bitmap.ok_or("The page didn't render before the timeout")?;
+    image::save_buffer(
+        &options.output_path,
+        &bitmap[..frame_length],
+        width,
+        height,
+        image::ColorType::Rgba8,
+    )
+    .map_err(|e| format!("Unable to write {}: {}", options.output_path, e))
+}
@@ -6,6 +6,8 @@ mod render_client;
 mod state;
 mod ui;
 
+pub use render_client::RenderClient;
+
 pub fn start_main() {
     let app = Application::builder()
         .application_id("org.moon.MoonBrowser")
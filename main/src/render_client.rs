@@ -1,63 +1,160 @@
 use flume::{Receiver, Sender};
-use shared::primitive::Size;
+use shared::primitive::{Point, Size};
 
-use render::{InputEvent, OutputEvent, RenderEngine};
+use render::{
+    ChannelConfig, FrameMailbox, InputEvent, MouseEvent, NavigationPolicy, OutputEvent,
+    RenderEngine,
+};
 use url::Url;
 
+/// Number of output events buffered before the engine waits for the tab.
+/// Input events are sent from the thread that receives output events, so
+/// the input channel is left unbounded.
+const OUTPUT_CHANNEL_CAPACITY: usize = 8;
+
 pub struct RenderClient {
     event_sender: Sender<InputEvent>,
     event_receiver: Receiver<OutputEvent>,
+    frames: FrameMailbox,
     ready_receiver: Receiver<()>,
 }
 
 impl RenderClient {
-    pub fn new() -> Self {
-        let (render_input_tx, render_input_rx) = flume::unbounded();
-        let (render_output_tx, render_output_rx) = flume::unbounded();
+    pub fn new(device_pixel_ratio: f32) -> Self {
+        let channels = ChannelConfig {
+            input_capacity: None,
+            output_capacity: Some(OUTPUT_CHANNEL_CAPACITY),
+        }
+        .create();
+        let (render_input_tx, render_input_rx) = channels.input;
+        let (render_output_tx, render_output_rx) = channels.output;
+        let frames = channels.frames;
+        let engine_frames = frames.clone();
 
         let (ready_tx, ready_rx) = flume::bounded(1);
 
         // spawn a new thread to run render engine
-        let _ = std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(async {
-                let render_engine = RenderEngine::new(Size::new(1., 1.)).await;
+        let engine_output_tx = render_output_tx.clone();
+        let spawn_result = std::thread::Builder::new()
+            .name("render engine".to_string())
+            .spawn(move || {
+                let report_error = |message: String| {
+                    log::error!("{}", message);
+                    let _ = engine_output_tx.send(OutputEvent::EngineError(message));
+                };
+
+                let rt = match tokio::runtime::Runtime::new() {
+                    Ok(rt) => rt,
+                    Err(e) => {
+                        report_error(format!("Unable to start render engine runtime: {}", e));
+                        let _ = ready_tx.send(());
+                        return;
+                    }
+                };
+
+                rt.block_on(async {
+                    let render_engine =
+                        match RenderEngine::new_with_dpr(Size::new(1., 1.), device_pixel_ratio)
+                            .await
+                        {
+                            Ok(render_engine) => render_engine,
+                            Err(e) => {
+                                report_error(format!("Unable to start render engine: {}", e));
+                                let _ = ready_tx.send(());
+                                return;
+                            }
+                        };
 
-                ready_tx.send(()).unwrap();
+                    let _ = ready_tx.send(());
 
-                // run render engine (this is an infinite loop)
-                if let Err(e) = render_engine.run(render_input_rx, render_output_tx).await {
-                    log::error!("Render Engine exited with error: {}", e.to_string());
-                }
+                    // run render engine (this is an infinite loop)
+                    if let Err(e) = render_engine
+                        .run(render_input_rx, engine_output_tx.clone(), engine_frames)
+                        .await
+                    {
+                        report_error(format!("Render engine exited with error: {}", e));
+                    }
+                });
             });
-        });
+
+        if let Err(e) = spawn_result {
+            let message = format!("Unable to spawn render engine thread: {}", e);
+            log::error!("{}", message);
+            let _ = render_output_tx.send(OutputEvent::EngineError(message));
+        }
 
         Self {
             event_sender: render_input_tx,
             event_receiver: render_output_rx,
+            frames,
             ready_receiver: ready_rx,
         }
     }
 
+    /// Wait for the render engine to start. Startup failures are reported
+    /// as an `EngineError` event.
     pub fn wait_till_ready(&self) {
-        self.ready_receiver
-            .recv()
-            .expect("Error while waiting for render client to be ready")
+        if self.ready_receiver.recv().is_err() {
+            log::error!("Render engine stopped before it was ready");
+        }
     }
 
     pub fn events(&self) -> Receiver<OutputEvent> {
         self.event_receiver.clone()
     }
 
+    /// Mailbox of the latest frame rendered by the engine
+    pub fn frames(&self) -> &FrameMailbox {
+        &self.frames
+    }
+
     pub fn load_html(&self, html: String, base_url: Url) {
-        self.event_sender
-            .send(InputEvent::LoadHTML { html, base_url })
-            .expect("Unable to load HTML");
+        self.send(InputEvent::LoadHTML { html, base_url });
     }
 
     pub fn resize(&self, size: Size) {
-        self.event_sender
-            .send(InputEvent::ViewportResize(size))
-            .expect("Unable to load HTML");
+        self.send(InputEvent::ViewportResize(size));
+    }
+
+    pub fn set_zoom(&self, zoom: f32) {
+        self.send(InputEvent::SetZoom(zoom));
+    }
+
+    pub fn set_device_pixel_ratio(&self, device_pixel_ratio: f32) {
+        self.send(InputEvent::DevicePixelRatioChanged(device_pixel_ratio));
+    }
+
+    pub fn find_text(&self, query: String, forward: bool) {
+        self.send(InputEvent::FindText { query, forward });
+    }
+
+    pub fn mouse(&self, event: MouseEvent) {
+        self.send(InputEvent::Mouse(event));
+    }
+
+    /// Scroll by `delta` at the point, both in device pixels
+    pub fn scroll(&self, point: Point, delta: Point) {
+        self.send(InputEvent::Scroll {
+            x: point.x,
+            y: point.y,
+            delta_x: delta.x,
+            delta_y: delta.y,
+        });
+    }
+
+    pub fn copy_selection(&self) {
+        self.send(InputEvent::CopySelection);
+    }
+
+    pub fn resolve_navigation(&self, policy: NavigationPolicy) {
+        self.send(InputEvent::ResolveNavigation(policy));
+    }
+
+    /// Send an event to the render engine. Events are dropped if the engine
+    /// failed to start, which has already been reported as an `EngineError`.
+    fn send(&self, event: InputEvent) {
+        if self.event_sender.send(event).is_err() {
+            log::warn!("Render engine is not running");
+        }
     }
 }
@@ -1,7 +1,8 @@
 use std::path::Path;
 
 use flume::{Receiver, Sender};
-use shared::primitive::Size;
+use render::{MouseEvent, MAX_ZOOM, MIN_ZOOM};
+use shared::primitive::{Point, Size};
 use url::{parser::URLParser, Url};
 
 use crate::app::get_app_runtime;
@@ -17,6 +18,8 @@ fn start_tab(tab: BrowserTab) -> TabHandler {
     handler
 }
 
+const ZOOM_STEP: f32 = 0.1;
+
 type BrowserAction = Box<dyn FnOnce(&mut Browser) + Send>;
 
 pub struct BrowserHandler(Sender<BrowserAction>);
@@ -29,6 +32,60 @@ impl BrowserHandler {
         });
     }
 
+    /// Change the zoom of the active tab by `steps` zoom steps
+    pub fn zoom_by(&self, steps: i32) {
+        self.update(move |browser| {
+            let active_tab = browser.get_active_tab();
+            let zoom = *active_tab.info().zoom.lock().unwrap();
+            let new_zoom = (zoom + steps as f32 * ZOOM_STEP).clamp(MIN_ZOOM, MAX_ZOOM);
+            if let Err(e) = active_tab.set_zoom(new_zoom) {
+                log::error!("Unable to zoom the active tab: {}", e);
+            }
+        });
+    }
+
+    pub fn reset_zoom(&self) {
+        self.update(|browser| {
+            let active_tab = browser.get_active_tab();
+            if let Err(e) = active_tab.set_zoom(1.) {
+                log::error!("Unable to reset the zoom of the active tab: {}", e);
+            }
+        });
+    }
+
+    /// Update the device pixel ratio of every tab, e.g. when the window
+    /// moves to a monitor with a different scale factor
+    pub fn set_device_pixel_ratio(&self, device_pixel_ratio: f32) {
+        self.update(move |browser| {
+            for tab in &browser.tab_handlers {
+                if let Err(e) = tab.set_device_pixel_ratio(device_pixel_ratio) {
+                    log::error!("Unable to update the device pixel ratio of a tab: {}", e);
+                }
+            }
+        });
+    }
+
+    pub fn mouse(&self, event: MouseEvent) {
+        self.update(move |browser| {
+            let active_tab = browser.get_active_tab();
+            active_tab.mouse(event).unwrap();
+        });
+    }
+
+    pub fn scroll(&self, point: Point, delta: Point) {
+        self.update(move |browser| {
+            let active_tab = browser.get_active_tab();
+            active_tab.scroll(point, delta).unwrap();
+        });
+    }
+
+    pub fn copy_selection(&self) {
+        self.update(|browser| {
+            let active_tab = browser.get_active_tab();
+            active_tab.copy_selection().unwrap();
+        });
+    }
+
     pub fn view_source_current_tab(&self) {
         self.update(|browser| {
             let active_tab = browser.get_active_tab();
@@ -79,7 +136,7 @@ pub struct Browser {
 }
 
 impl Browser {
-    pub fn new() -> Self {
+    pub fn new(device_pixel_ratio: f32) -> Self {
         let workspace_root = Path::new(env!("CARGO_MANIFEST_DIR"))
             .parent()
             .unwrap()
@@ -92,7 +149,7 @@ impl Browser {
         )
         .unwrap();
 
-        let initial_tab = BrowserTab::new(home_url.clone());
+        let initial_tab = BrowserTab::new(home_url.clone(), device_pixel_ratio);
         let initial_tab_handler = start_tab(initial_tab);
 
         Self {
@@ -155,6 +212,14 @@ impl Browser {
                         TabEvent::TitleChanged(title) if is_active_tab => {
                             get_app_runtime().update_state(move |state| state.ui.set_title(&title));
                         }
+                        TabEvent::EngineError(message) if is_active_tab => {
+                            get_app_runtime()
+                                .update_state(move |state| state.ui.show_error_dialog(&message));
+                        }
+                        TabEvent::CopyToClipboard(text) if is_active_tab => {
+                            get_app_runtime()
+                                .update_state(move |state| state.ui.set_clipboard_text(&text));
+                        }
                         _ => {}
                     }
                 }
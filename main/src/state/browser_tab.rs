@@ -2,22 +2,31 @@ use std::sync::{Arc, Mutex};
 
 use crate::render_client::RenderClient;
 use flume::{Receiver, Selector, Sender};
-use loader::ResourceLoader;
-use render::OutputEvent;
+use loader::{ResourceLoader, ResourceType};
+use render::{MouseEvent, NavigationPolicy, OutputEvent, SharedFrame};
 use shared::byte_string::ByteString;
-use shared::primitive::Size;
+use shared::primitive::{Point, Size};
 use url::Url;
 
 pub enum TabAction {
     Resize(Size),
     Goto(Url),
     ShowError { title: String, body: String },
+    Find { query: String, forward: bool },
+    SetZoom(f32),
+    SetDevicePixelRatio(f32),
+    Mouse(MouseEvent),
+    Scroll { point: Point, delta: Point },
+    CopySelection,
 }
 
 pub enum TabEvent {
     URLChanged(Url),
-    FrameReceived(Vec<u8>),
+    FrameReceived(SharedFrame),
     TitleChanged(String),
+    FindResult { current: usize, total: usize },
+    CopyToClipboard(String),
+    EngineError(String),
 }
 
 pub struct TabHandler {
@@ -42,6 +51,38 @@ impl TabHandler {
         Ok(())
     }
 
+    pub fn find(&self, query: String, forward: bool) -> anyhow::Result<()> {
+        self.sender.send(TabAction::Find { query, forward })?;
+        Ok(())
+    }
+
+    pub fn set_zoom(&self, zoom: f32) -> anyhow::Result<()> {
+        *self.info.zoom.lock().unwrap() = zoom;
+        self.sender.send(TabAction::SetZoom(zoom))?;
+        Ok(())
+    }
+
+    pub fn set_device_pixel_ratio(&self, device_pixel_ratio: f32) -> anyhow::Result<()> {
+        self.sender
+            .send(TabAction::SetDevicePixelRatio(device_pixel_ratio))?;
+        Ok(())
+    }
+
+    pub fn mouse(&self, event: MouseEvent) -> anyhow::Result<()> {
+        self.sender.send(TabAction::Mouse(event))?;
+        Ok(())
+    }
+
+    pub fn scroll(&self, point: Point, delta: Point) -> anyhow::Result<()> {
+        self.sender.send(TabAction::Scroll { point, delta })?;
+        Ok(())
+    }
+
+    pub fn copy_selection(&self) -> anyhow::Result<()> {
+        self.sender.send(TabAction::CopySelection)?;
+        Ok(())
+    }
+
     pub fn events(&self) -> &Receiver<TabEvent> {
         &self.receiver
     }
@@ -53,6 +94,7 @@ impl TabHandler {
 
 pub struct TabInfo {
     pub url: Mutex<Url>,
+    pub zoom: Mutex<f32>,
 }
 
 pub struct BrowserTab {
@@ -63,12 +105,13 @@ pub struct BrowserTab {
 }
 
 impl BrowserTab {
-    pub fn new(url: Url) -> Self {
-        let client = RenderClient::new();
+    pub fn new(url: Url, device_pixel_ratio: f32) -> Self {
+        let client = RenderClient::new(device_pixel_ratio);
         client.wait_till_ready();
 
         let info = TabInfo {
             url: Mutex::new(url),
+            zoom: Mutex::new(1.),
         };
 
         Self {
@@ -82,23 +125,43 @@ impl BrowserTab {
     pub fn run(self) -> anyhow::Result<()> {
         let (_, tab_action_rx) = &self.action_channel;
         let render_engine_events = self.client.events();
+        let frames = self.client.frames();
 
         enum Event {
             TabAction(TabAction),
             RenderEngineEvent(OutputEvent),
+            FramePosted,
+            RenderEngineStopped,
         }
 
+        let mut is_render_engine_running = true;
+
         loop {
-            let event = Selector::new()
-                .recv(&tab_action_rx, |event| event.map(|e| Event::TabAction(e)))
-                .recv(&render_engine_events, |event| {
-                    event.map(|e| Event::RenderEngineEvent(e))
-                })
-                .wait()?;
-
-            match event {
+            let selector =
+                Selector::new().recv(&tab_action_rx, |event| event.map(|e| Event::TabAction(e)));
+
+            // Keep the tab responsive after the render engine has stopped
+            let selector = if is_render_engine_running {
+                selector
+                    .recv(&render_engine_events, |event| {
+                        Ok(event
+                            .map(|e| Event::RenderEngineEvent(e))
+                            .unwrap_or(Event::RenderEngineStopped))
+                    })
+                    .recv(frames.notifications(), |_| Ok(Event::FramePosted))
+            } else {
+                selector
+            };
+
+            match selector.wait()? {
                 Event::TabAction(event) => self.handle_tab_action(event)?,
                 Event::RenderEngineEvent(event) => self.handle_render_engine_event(event)?,
+                Event::FramePosted => {
+                    if let Some(frame) = frames.take() {
+                        self.emit_event(TabEvent::FrameReceived(frame))?
+                    }
+                }
+                Event::RenderEngineStopped => is_render_engine_running = false,
             }
         }
     }
@@ -124,14 +187,57 @@ impl BrowserTab {
             TabAction::Resize(new_size) => self.client.resize(new_size),
             TabAction::Goto(url) => self.goto(url)?,
             TabAction::ShowError { title, body } => self.load_error(&title, &body),
+            TabAction::Find { query, forward } => self.client.find_text(query, forward),
+            TabAction::SetZoom(zoom) => self.client.set_zoom(zoom),
+            TabAction::SetDevicePixelRatio(device_pixel_ratio) => {
+                self.client.set_device_pixel_ratio(device_pixel_ratio)
+            }
+            TabAction::Mouse(event) => self.client.mouse(event),
+            TabAction::Scroll { point, delta } => self.client.scroll(point, delta),
+            TabAction::CopySelection => self.client.copy_selection(),
         }
         Ok(())
     }
 
     fn handle_render_engine_event(&self, event: OutputEvent) -> anyhow::Result<()> {
         match event {
-            OutputEvent::FrameRendered(frame) => self.emit_event(TabEvent::FrameReceived(frame))?,
             OutputEvent::TitleChanged(title) => self.emit_event(TabEvent::TitleChanged(title))?,
+            OutputEvent::FindResult { current, total } => {
+                self.emit_event(TabEvent::FindResult { current, total })?
+            }
+            OutputEvent::MetadataChanged(_) => {}
+            OutputEvent::SnapshotCaptured(_) => {}
+            OutputEvent::ValidationFailed(messages) => {
+                log::info!("Form submission blocked: {}", messages.join(", "))
+            }
+            OutputEvent::SelectionChanged(_) => {}
+            OutputEvent::CaretMoved { .. } => {}
+            OutputEvent::SelectionRects(_) => {}
+            OutputEvent::FrameStats { .. } => {}
+            OutputEvent::MemoryReport(_) => {}
+            OutputEvent::NetworkSettingsRejected(_) => {}
+            OutputEvent::NodeInspected(_) => {}
+            OutputEvent::HitTestResult(_) => {}
+            OutputEvent::VisibilityResult(_) => {}
+            OutputEvent::LoadStateChanged(_) => {}
+            OutputEvent::LoadProgress { .. } => {}
+            OutputEvent::PdfPrinted(_) => {}
+            OutputEvent::FaviconLoaded { .. } => {}
+            OutputEvent::ShowDropdown { .. } => {}
+            OutputEvent::NavigationRequested { .. } => {
+                self.client.resolve_navigation(NavigationPolicy::Allow)
+            }
+            OutputEvent::NewPageRequested { url, .. } => {
+                log::info!("Opening links in a new tab is not supported: {}", url)
+            }
+            OutputEvent::Navigated(url) => {
+                *self.info.url.lock().unwrap() = url.clone();
+                self.change_url(url)?
+            }
+            OutputEvent::CopyToClipboard(text) => {
+                self.emit_event(TabEvent::CopyToClipboard(text))?
+            }
+            OutputEvent::EngineError(message) => self.emit_event(TabEvent::EngineError(message))?,
         }
 
         Ok(())
@@ -154,7 +260,7 @@ impl BrowserTab {
 
     fn load(&self, url: &Url) -> anyhow::Result<()> {
         match url.scheme.as_str() {
-            "http" | "https" | "file" => self.load_html(),
+            "http" | "https" | "file" | "data" | "about" => self.load_html(),
             "view-source" => self.load_source(),
             _ => self.load_not_supported(),
         }
@@ -197,22 +303,23 @@ impl BrowserTab {
 
     fn load_html(&self) {
         let current_url = self.info.url.lock().unwrap().clone();
-        match ResourceLoader::current().load(&current_url) {
+        match ResourceLoader::current().load_resource(&current_url, ResourceType::Document) {
             Ok(bytes) => {
-                let html = ByteString::new(&bytes);
+                let html = ByteString::from_html(&bytes);
                 self.client.load_html(html.to_string(), current_url);
             }
             Err(e) => {
-                self.load_error("Aw, Snap!", &e.get_friendly_message());
+                let error_page = render::error_page(&current_url, &e);
+                self.client.load_html(error_page, current_url);
             }
         }
     }
 
     fn load_source(&self) {
         let current_url = self.info.url.lock().unwrap().clone();
-        match ResourceLoader::current().load(&current_url) {
+        match ResourceLoader::current().load_resource(&current_url, ResourceType::Document) {
             Ok(bytes) => {
-                let raw_html_string = ByteString::new(&bytes).to_string();
+                let raw_html_string = ByteString::from_html(&bytes).to_string();
                 let raw_html = html_escape::encode_text(&raw_html_string);
                 let source_html = format!("<html><pre>{}</pre></html>", raw_html);
 
@@ -15,7 +15,7 @@ pub struct AppState {
 
 impl AppState {
     pub fn new(ui: UI, runtime: AppRuntime) -> Self {
-        let browser = Browser::new();
+        let browser = Browser::new(ui.content_area.scale_factor() as f32);
         let handler = browser.handler();
         let _ = std::thread::spawn(move || {
             browser.run().expect("Browser crashed");
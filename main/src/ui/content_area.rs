@@ -6,16 +6,20 @@ use std::{
 };
 
 use gtk::{
-    gdk::EventMask,
+    gdk::{EventMask, ScrollDirection},
     gdk_pixbuf::Pixbuf,
     prelude::{GdkContextExt, GtkMenuExtManual},
     traits::{ContainerExt, GtkMenuItemExt, MenuShellExt, WidgetExt},
     DrawingArea, Inhibit,
 };
-use shared::primitive::Size;
+use render::MouseEvent;
+use shared::primitive::{Point, Size};
 
 use crate::{app::get_app_runtime, delayed_task::DelayedTask};
 
+/// Distance scrolled by a step of the mouse wheel, in logical pixels
+const SCROLL_STEP: f64 = 40.;
+
 pub struct ContentArea {
     render_area: DrawingArea,
     web_content_pixbuf: Rc<RefCell<Option<Pixbuf>>>,
@@ -26,15 +30,24 @@ impl ContentArea {
         let render_area = DrawingArea::builder()
             .hexpand(true)
             .vexpand(true)
-            .events(EventMask::BUTTON_PRESS_MASK)
+            .events(
+                EventMask::BUTTON_PRESS_MASK
+                    | EventMask::BUTTON_RELEASE_MASK
+                    | EventMask::BUTTON1_MOTION_MASK
+                    | EventMask::SCROLL_MASK
+                    | EventMask::SMOOTH_SCROLL_MASK,
+            )
             .build();
 
         let web_content_pixbuf: Rc<RefCell<Option<Pixbuf>>> = Rc::new(RefCell::new(None));
 
         let web_content_clone = web_content_pixbuf.clone();
 
-        render_area.connect_draw(move |_, context| {
+        render_area.connect_draw(move |widget, context| {
             if let Some(pixbuf) = &*web_content_clone.borrow() {
+                // The pixbuf is rendered in device pixels
+                let scale = 1. / widget.scale_factor() as f64;
+                context.scale(scale, scale);
                 context.set_source_pixbuf(pixbuf, 0., 0.);
                 context.paint().unwrap();
                 return Inhibit(false);
@@ -43,6 +56,16 @@ impl ContentArea {
             Inhibit(true)
         });
 
+        render_area.connect_scale_factor_notify(|widget| {
+            let device_pixel_ratio = widget.scale_factor() as f32;
+            get_app_runtime().update_state(move |state| {
+                let (width, height) = state.ui.content_area.render_area_size();
+                let new_size = Size::new(width as f32, height as f32);
+                state.browser().set_device_pixel_ratio(device_pixel_ratio);
+                state.browser().resize(new_size);
+            });
+        });
+
         let debouncer: Arc<Mutex<Option<DelayedTask>>> = Arc::new(Mutex::new(None));
 
         render_area.connect_size_allocate(move |_, _| {
@@ -61,10 +84,26 @@ impl ContentArea {
                 }));
         });
 
-        render_area.connect_button_press_event(|_, event| {
+        render_area.connect_button_press_event(|widget, event| {
+            let left_button = 1;
             let right_button = 3;
+            if event.button() == left_button {
+                let point = event_point(widget, event.position());
+                get_app_runtime().update_state(move |state| {
+                    state.browser().mouse(MouseEvent::Down(point));
+                });
+            }
             if event.button() == right_button {
                 let menu = gtk::Menu::new();
+
+                let copy_item = gtk::MenuItem::with_label("Copy");
+                copy_item.connect_activate(|_| {
+                    get_app_runtime().update_state(|state| {
+                        state.browser().copy_selection();
+                    });
+                });
+                menu.append(&copy_item);
+
                 let item = gtk::MenuItem::with_label("View Source");
 
                 item.connect_activate(|_| {
@@ -81,6 +120,41 @@ impl ContentArea {
             Inhibit(true)
         });
 
+        render_area.connect_motion_notify_event(|widget, event| {
+            let point = event_point(widget, event.position());
+            get_app_runtime().update_state(move |state| {
+                state.browser().mouse(MouseEvent::Move(point));
+            });
+            Inhibit(true)
+        });
+
+        render_area.connect_button_release_event(|widget, event| {
+            let left_button = 1;
+            if event.button() == left_button {
+                let point = event_point(widget, event.position());
+                get_app_runtime().update_state(move |state| {
+                    state.browser().mouse(MouseEvent::Up(point));
+                });
+            }
+            Inhibit(true)
+        });
+
+        render_area.connect_scroll_event(|widget, event| {
+            let (delta_x, delta_y) = match event.direction() {
+                ScrollDirection::Up => (0., -1.),
+                ScrollDirection::Down => (0., 1.),
+                ScrollDirection::Left => (-1., 0.),
+                ScrollDirection::Right => (1., 0.),
+                _ => event.delta(),
+            };
+            let point = event_point(widget, event.position());
+            let delta = event_point(widget, (delta_x * SCROLL_STEP, delta_y * SCROLL_STEP));
+            get_app_runtime().update_state(move |state| {
+                state.browser().scroll(point, delta);
+            });
+            Inhibit(true)
+        });
+
         container.add(&render_area);
 
         Self {
@@ -89,14 +163,27 @@ impl ContentArea {
         }
     }
 
+    /// Size of the render area in device pixels
     pub fn render_area_size(&self) -> (i32, i32) {
-        let width = self.render_area.allocated_width();
-        let height = self.render_area.allocated_height();
+        let scale_factor = self.scale_factor();
+        let width = self.render_area.allocated_width() * scale_factor;
+        let height = self.render_area.allocated_height() * scale_factor;
         (width, height)
     }
 
+    /// Number of device pixels per logical pixel of the render area
+    pub fn scale_factor(&self) -> i32 {
+        self.render_area.scale_factor()
+    }
+
     pub fn set_content_pixbuf(&mut self, content: Pixbuf) {
         self.web_content_pixbuf.borrow_mut().replace(content);
         self.render_area.queue_draw();
     }
 }
+
+/// Convert an event position to device pixels
+fn event_point(widget: &DrawingArea, (x, y): (f64, f64)) -> Point {
+    let scale_factor = widget.scale_factor() as f32;
+    Point::new(x as f32 * scale_factor, y as f32 * scale_factor)
+}
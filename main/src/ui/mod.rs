@@ -1,11 +1,14 @@
 mod content_area;
 mod primary_bar;
 
-use gtk::gdk::EventMask;
+use gtk::gdk::{keys, EventMask, ModifierType};
 use gtk::gdk_pixbuf::{Colorspace, Pixbuf};
 use gtk::glib::Bytes;
-use gtk::{prelude::*, Orientation};
+use gtk::{prelude::*, Inhibit, Orientation};
 use gtk::{Application, ApplicationWindow};
+use render::SharedFrame;
+
+use crate::app::get_app_runtime;
 
 use self::content_area::ContentArea;
 use self::primary_bar::PrimaryBar;
@@ -35,6 +38,24 @@ impl UI {
 
         window.add(&container);
 
+        window.connect_key_press_event(|_, event| {
+            if !event.state().contains(ModifierType::CONTROL_MASK) {
+                return Inhibit(false);
+            }
+
+            let key = event.keyval();
+            if key == keys::constants::plus || key == keys::constants::equal {
+                get_app_runtime().update_state(|state| state.browser().zoom_by(1));
+            } else if key == keys::constants::minus {
+                get_app_runtime().update_state(|state| state.browser().zoom_by(-1));
+            } else if key == keys::constants::_0 {
+                get_app_runtime().update_state(|state| state.browser().reset_zoom());
+            } else {
+                return Inhibit(false);
+            }
+            Inhibit(true)
+        });
+
         Self {
             app,
             window,
@@ -51,7 +72,23 @@ impl UI {
         self.primary_bar.url_entry.set_text(url);
     }
 
-    pub fn set_web_content_bitmap(&mut self, bitmap: Vec<u8>) {
+    pub fn show_error_dialog(&mut self, message: &str) {
+        let dialog = gtk::MessageDialog::new(
+            Some(&self.window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Error,
+            gtk::ButtonsType::Close,
+            message,
+        );
+        dialog.connect_response(|dialog, _| dialog.close());
+        dialog.show_all();
+    }
+
+    pub fn set_clipboard_text(&mut self, text: &str) {
+        gtk::Clipboard::get(&gtk::gdk::SELECTION_CLIPBOARD).set_text(text);
+    }
+
+    pub fn set_web_content_bitmap(&mut self, bitmap: SharedFrame) {
         let (width, height) = self.content_area.render_area_size();
 
         if (width * height * 4) as usize > bitmap.len() {
@@ -0,0 +1,158 @@
+//! Documents built into the engine: the `about:` pages and the pages shown
+//! in place of a document that couldn't be loaded
+
+use loader::{LoadError, SchemeHandler};
+use url::Url;
+
+/// Serves `about:blank` and `about:version`
+pub struct AboutHandler;
+
+impl SchemeHandler for AboutHandler {
+    fn load(&self, url: &Url) -> Result<Vec<u8>, LoadError> {
+        match url.path.as_str().to_ascii_lowercase().as_str() {
+            "blank" => Ok(Vec::new()),
+            "version" => Ok(version_page().into_bytes()),
+            _ => Err(LoadError::NotFound(url.as_str())),
+        }
+    }
+}
+
+fn version_page() -> String {
+    let rows = [
+        ("Engine", format!("Moon {}", env!("CARGO_PKG_VERSION"))),
+        (
+            "Platform",
+            format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        ),
+    ];
+    let rows: String = rows
+        .iter()
+        .map(|(name, value)| format!("<tr><th>{}</th><td>{}</td></tr>", name, escape_html(value)))
+        .collect();
+    page(
+        "About Moon",
+        &format!("<h1>About Moon</h1><table>{}</table>", rows),
+    )
+}
+
+/// Page shown in place of the document at `url` that failed to load
+pub fn error_page(url: &Url, error: &LoadError) -> String {
+    let host = url.host.clone().unwrap_or_else(|| url.as_str());
+    let (title, message) = match error {
+        LoadError::IOError(message) if is_dns_failure(message) => (
+            "Server not found",
+            format!("The address of {} couldn't be found.", host),
+        ),
+        LoadError::IOError(message) if is_tls_error(message) => (
+            "Connection not secure",
+            format!(
+                "A secure connection to {} couldn't be established, its certificate may be invalid.",
+                host
+            ),
+        ),
+        LoadError::NotFound(_) | LoadError::HttpStatus(404) => (
+            "Page not found",
+            format!("There is no page at {}.", url),
+        ),
+        LoadError::HttpStatus(status) => (
+            "Server error",
+            format!("{} answered with the error status {}.", host, status),
+        ),
+        LoadError::Blocked(_) => (
+            "Page blocked",
+            format!("Loading {} isn't allowed.", url),
+        ),
+        error => ("Unable to load the page", error.get_friendly_message()),
+    };
+    page(
+        title,
+        &format!(
+            "<h1>{}</h1><p>{}</p><p class=\"url\">{}</p>",
+            title,
+            escape_html(&message),
+            escape_html(&url.as_str())
+        ),
+    )
+}
+
+fn is_dns_failure(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("dns error") || message.contains("failed to lookup address")
+}
+
+fn is_tls_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    ["certificate", "tls", "ssl"]
+        .iter()
+        .any(|keyword| message.contains(keyword))
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<title>{}</title>
+<style>
+body {{ margin: 0; background-color: #f4f4f6; color: #202124; font-family: sans-serif; }}
+main {{ max-width: 560px; margin: 80px auto 0; padding: 0 24px; }}
+h1 {{ font-size: 24px; font-weight: normal; }}
+p {{ line-height: 1.5; }}
+.url {{ color: #5f6368; font-size: 13px; }}
+th {{ padding-right: 16px; text-align: left; }}
+</style>
+</head>
+<body><main>{}</main></body>
+</html>",
+        title, body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use url::parser::URLParser;
+
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        URLParser::parse(url, None).unwrap()
+    }
+
+    #[test]
+    fn test_about_pages() {
+        assert!(AboutHandler.load(&url("about:blank")).unwrap().is_empty());
+        let version = String::from_utf8(AboutHandler.load(&url("about:version")).unwrap());
+        assert!(version.unwrap().contains(env!("CARGO_PKG_VERSION")));
+        assert!(matches!(
+            AboutHandler.load(&url("about:unknown")),
+            Err(LoadError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_error_pages() {
+        let page_url = url("https://example.com/a");
+        let dns_error = LoadError::IOError(
+            "error trying to connect: dns error: failed to lookup address information".to_string(),
+        );
+        let page = error_page(&page_url, &dns_error);
+        assert!(page.contains("<title>Server not found</title>"));
+        assert!(page.contains("The address of example.com couldn't be found."));
+
+        let tls_error = LoadError::IOError("invalid certificate: UnknownIssuer".to_string());
+        assert!(error_page(&page_url, &tls_error).contains("Connection not secure"));
+        assert!(error_page(&page_url, &LoadError::HttpStatus(404)).contains("Page not found"));
+        assert!(error_page(&page_url, &LoadError::HttpStatus(503)).contains("503"));
+        assert_eq!(
+            escape_html("<a href=\"?a&b\">"),
+            "&lt;a href=&quot;?a&amp;b&quot;&gt;"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::{Arc, Mutex};
+
+use flume::{Receiver, Sender};
+
+use crate::{InputEvent, OutputEvent, SharedFrame};
+
+/// Capacity of the channels between an embedder and the render engine.
+/// `None` means unbounded.
+///
+/// When the input channel is full, sending an event blocks the embedder
+/// until the engine catches up. Use `Sender::try_send` instead for events
+/// that can be dropped, like mouse moves.
+///
+/// Frames don't go through the output channel but through a `FrameMailbox`,
+/// which only keeps the latest frame. Output events block the engine until
+/// there is room, so an embedder that sends and receives from the same
+/// thread should keep the input channel unbounded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelConfig {
+    pub input_capacity: Option<usize>,
+    pub output_capacity: Option<usize>,
+}
+
+pub struct EngineChannels {
+    pub input: (Sender<InputEvent>, Receiver<InputEvent>),
+    pub output: (Sender<OutputEvent>, Receiver<OutputEvent>),
+    pub frames: FrameMailbox,
+}
+
+impl ChannelConfig {
+    pub fn create(&self) -> EngineChannels {
+        EngineChannels {
+            input: create_channel(self.input_capacity),
+            output: create_channel(self.output_capacity),
+            frames: FrameMailbox::new(),
+        }
+    }
+}
+
+/// Single slot holding the latest frame rendered by the engine. Posting a
+/// frame replaces the one the embedder hasn't taken yet, so stale frames
+/// are dropped instead of queued.
+#[derive(Clone)]
+pub struct FrameMailbox {
+    slot: Arc<Mutex<Option<SharedFrame>>>,
+    notifier: Sender<()>,
+    notifications: Receiver<()>,
+}
+
+impl FrameMailbox {
+    pub fn new() -> Self {
+        let (notifier, notifications) = flume::bounded(1);
+        Self {
+            slot: Arc::new(Mutex::new(None)),
+            notifier,
+            notifications,
+        }
+    }
+
+    /// Replace the frame that hasn't been taken yet
+    pub fn post(&self, frame: SharedFrame) {
+        self.slot.lock().unwrap().replace(frame);
+        // A notification still pending covers this frame too
+        let _ = self.notifier.try_send(());
+    }
+
+    /// Drop the frame that hasn't been taken yet, so that its buffer can be
+    /// reused for the next frame
+    pub fn discard(&self) {
+        self.slot.lock().unwrap().take();
+    }
+
+    pub fn take(&self) -> Option<SharedFrame> {
+        self.slot.lock().unwrap().take()
+    }
+
+    /// Receives a message when a frame is posted, then the frame can be
+    /// taken with `take`. The frame may have been discarded in the meantime.
+    pub fn notifications(&self) -> &Receiver<()> {
+        &self.notifications
+    }
+}
+
+impl Default for FrameMailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create_channel<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>) {
+    match capacity {
+        Some(capacity) => flume::bounded(capacity),
+        None => flume::unbounded(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_capacity() {
+        let channels = ChannelConfig {
+            input_capacity: None,
+            output_capacity: Some(2),
+        }
+        .create();
+
+        assert_eq!(channels.input.0.capacity(), None);
+        assert_eq!(channels.output.0.capacity(), Some(2));
+    }
+
+    #[test]
+    fn test_mailbox_keeps_latest_frame() {
+        let mut pool = crate::FramePool::new();
+        let mailbox = FrameMailbox::new();
+
+        mailbox.post(pool.publish(&[1]).unwrap());
+        mailbox.post(pool.publish(&[2]).unwrap());
+        assert_eq!(mailbox.notifications().len(), 1);
+        assert!(mailbox.notifications().try_recv().is_ok());
+        assert_eq!(&*mailbox.take().unwrap(), &[2]);
+        assert!(mailbox.take().is_none());
+
+        // The stale frame released its buffer
+        pool.publish(&[3]).unwrap();
+        assert_eq!(pool.buffer_count(), 2);
+
+        mailbox.post(pool.publish(&[4]).unwrap());
+        mailbox.discard();
+        assert!(mailbox.take().is_none());
+    }
+}
@@ -0,0 +1,41 @@
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+use style_types::ContextualStyleSheet;
+
+use crate::font_loader::FontLoader;
+
+/// State shared by the documents that the engine shows. Nothing in it leaks
+/// between documents: the user-agent stylesheet is never modified and web
+/// fonts are registered for the origin of the document that loaded them.
+/// Styles, layout and the rest of the state of a document stay with it.
+pub struct EngineContext {
+    user_agent_stylesheet: Rc<ContextualStyleSheet>,
+    font_loader: RefCell<FontLoader>,
+}
+
+impl EngineContext {
+    pub fn new() -> Self {
+        Self {
+            user_agent_stylesheet: style::user_agent::user_agent_stylesheet(),
+            font_loader: RefCell::new(FontLoader::new()),
+        }
+    }
+
+    /// Default styles of HTML elements, added to every document
+    pub fn user_agent_stylesheet(&self) -> Rc<ContextualStyleSheet> {
+        self.user_agent_stylesheet.clone()
+    }
+
+    /// Loader of the web fonts of every document, which loads the fonts of
+    /// an origin once
+    pub(crate) fn font_loader(&self) -> RefMut<'_, FontLoader> {
+        self.font_loader.borrow_mut()
+    }
+}
+
+impl Default for EngineContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
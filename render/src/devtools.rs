@@ -0,0 +1,355 @@
+//! Remote devtools server.
+//!
+//! The server speaks newline-delimited JSON over TCP. Every request is an
+//! object with an `id`, a `method` and optional `params`, and is answered
+//! with an object holding the same `id` and either a `result` or an `error`:
+//!
+//! ```text
+//! -> {"id": 1, "method": "getComputedStyle", "params": {"nodeId": 12}}
+//! <- {"id": 1, "result": {"color": "rgb(0, 0, 0)", ...}}
+//! ```
+//!
+//! Nodes are identified by the numeric ids returned by `getDocument`. An id
+//! keeps referring to the same node while the document changes.
+//!
+//! Methods: `listPages`, `getDocument`, `getComputedStyle`,
+//! `highlightNode` (`nodeId` may be `null` to clear), `captureScreenshot`,
+//! which returns a base64-encoded PNG, and `getCacheStats`, the counters of
+//! the HTTP cache.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+
+use dom::{
+    node::NodePtr,
+    node_id::NodeId,
+    snapshot::{DomSnapshot, SnapshotNode, SnapshotNodeData},
+};
+use flume::Sender;
+use loader::ResourceLoader;
+use serde_json::{json, Value};
+
+use crate::page::Page;
+use crate::InputEvent;
+
+#[derive(Debug, PartialEq)]
+pub enum DevtoolsCommand {
+    ListPages,
+    GetDocument,
+    GetComputedStyle { node_id: NodeId },
+    HighlightNode { node_id: Option<NodeId> },
+    CaptureScreenshot,
+    GetCacheStats,
+}
+
+/// A devtools command sent to the render engine, answered with a JSON result
+pub struct DevtoolsRequest {
+    pub(crate) command: DevtoolsCommand,
+    reply_tx: Sender<Result<Value, String>>,
+}
+
+impl DevtoolsRequest {
+    pub(crate) fn reply(self, result: Result<Value, String>) {
+        // the client might have disconnected in the meantime
+        let _ = self.reply_tx.send(result);
+    }
+}
+
+/// Start listening for devtools clients. Commands are forwarded to the
+/// render engine through `engine`. Returns the address the server is bound
+/// to, which is useful when binding to port 0.
+///
+/// The protocol has no authentication and exposes the content of the page,
+/// so only loopback addresses are accepted.
+pub fn start_server<A: ToSocketAddrs>(
+    address: A,
+    engine: Sender<InputEvent>,
+) -> io::Result<SocketAddr> {
+    let (loopback, other): (Vec<SocketAddr>, Vec<SocketAddr>) = address
+        .to_socket_addrs()?
+        .partition(|address| address.ip().is_loopback());
+    if loopback.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Devtools server can only listen on a loopback address",
+        ));
+    }
+    if !other.is_empty() {
+        log::warn!(
+            "Devtools server ignores non-loopback addresses: {:?}",
+            other
+        );
+    }
+
+    let listener = TcpListener::bind(&loopback[..])?;
+    let local_address = listener.local_addr()?;
+    log::info!("Devtools server listening on: {}", local_address);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let engine = engine.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, engine) {
+                            log::warn!("Devtools connection closed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log::error!("Unable to accept devtools connection: {}", e),
+            }
+        }
+    });
+
+    Ok(local_address)
+}
+
+fn handle_connection(stream: TcpStream, engine: Sender<InputEvent>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let reader = BufReader::new(stream);
+
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message = serde_json::from_str::<Value>(&line).unwrap_or(Value::Null);
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let result = match parse_command(&message) {
+            Ok(command) => send_to_engine(command, &engine),
+            Err(e) => Err(e),
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "id": id, "result": result }),
+            Err(error) => json!({ "id": id, "error": error }),
+        };
+        writeln!(writer, "{}", response)?;
+    }
+    Ok(())
+}
+
+fn send_to_engine(command: DevtoolsCommand, engine: &Sender<InputEvent>) -> Result<Value, String> {
+    let (reply_tx, reply_rx) = flume::bounded(1);
+    let request = DevtoolsRequest { command, reply_tx };
+
+    let not_running = || "Render engine is not running".to_string();
+    engine
+        .send(InputEvent::Devtools(request))
+        .map_err(|_| not_running())?;
+    reply_rx.recv().map_err(|_| not_running())?
+}
+
+fn parse_command(message: &Value) -> Result<DevtoolsCommand, String> {
+    let method = message
+        .get("method")
+        .and_then(Value::as_str)
+        .ok_or_else(|| "Invalid request".to_string())?;
+    let node_id = || match message.pointer("/params/nodeId") {
+        Some(Value::Null) | None => Ok(None),
+        Some(id) => id
+            .as_u64()
+            .map(|id| Some(NodeId(id)))
+            .ok_or_else(|| "Invalid node id".to_string()),
+    };
+
+    match method {
+        "listPages" => Ok(DevtoolsCommand::ListPages),
+        "getDocument" => Ok(DevtoolsCommand::GetDocument),
+        "getComputedStyle" => match node_id()? {
+            Some(node_id) => Ok(DevtoolsCommand::GetComputedStyle { node_id }),
+            None => Err("Missing node id".to_string()),
+        },
+        "highlightNode" => Ok(DevtoolsCommand::HighlightNode {
+            node_id: node_id()?,
+        }),
+        "captureScreenshot" => Ok(DevtoolsCommand::CaptureScreenshot),
+        "getCacheStats" => Ok(DevtoolsCommand::GetCacheStats),
+        method => Err(format!("Unknown method: {}", method)),
+    }
+}
+
+/// Run a devtools command against the page on the render thread
+pub(crate) async fn handle_command(
+    page: &mut Page<'_>,
+    command: &DevtoolsCommand,
+) -> Result<Value, String> {
+    match command {
+        DevtoolsCommand::ListPages => {
+            let url = page
+                .document()
+                .and_then(|document| document.as_document().base())
+                .map(|url| url.as_str());
+            Ok(json!([{ "id": 0, "title": page.title(), "url": url }]))
+        }
+        DevtoolsCommand::GetDocument => match page.snapshot() {
+            Some(snapshot) => Ok(serialize_document(&snapshot)),
+            None => Err("No document is loaded".to_string()),
+        },
+        DevtoolsCommand::GetComputedStyle { node_id } => {
+            let node = find_node(page, *node_id)?;
+            Ok(json!(node.computed_style_snapshot()))
+        }
+        DevtoolsCommand::HighlightNode { node_id } => {
+            let node = match node_id {
+                Some(node_id) => Some(find_node(page, *node_id)?),
+                None => None,
+            };
+            page.highlight_node(node).await;
+            Ok(Value::Null)
+        }
+        DevtoolsCommand::CaptureScreenshot => {
+            let size = page.size();
+            let bitmap = page
+                .bitmap()
+                .ok_or_else(|| "No frame has been rendered".to_string())?;
+            let png = encode_png(bitmap, size.width as u32, size.height as u32)?;
+            Ok(json!({
+                "width": size.width as u32,
+                "height": size.height as u32,
+                "data": base64::encode(png),
+            }))
+        }
+        DevtoolsCommand::GetCacheStats => {
+            let stats = ResourceLoader::current().cache_stats();
+            Ok(json!({
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "revalidations": stats.revalidations,
+                "entries": stats.entries,
+                "size": stats.size,
+            }))
+        }
+    }
+}
+
+fn find_node(page: &Page<'_>, node_id: NodeId) -> Result<NodePtr, String> {
+    page.document()
+        .and_then(|document| document.node_by_id(node_id))
+        .ok_or_else(|| format!("Unable to find node: {}", node_id))
+}
+
+fn serialize_document(snapshot: &DomSnapshot) -> Value {
+    serialize_node(snapshot.root())
+}
+
+fn serialize_node(node: &SnapshotNode) -> Value {
+    let children = node
+        .children
+        .iter()
+        .map(|child| serialize_node(child))
+        .collect();
+
+    let mut value = match &node.data {
+        SnapshotNodeData::Document { title } => json!({ "type": "document", "title": title }),
+        SnapshotNodeData::Element {
+            tag_name,
+            attributes,
+            ..
+        } => json!({ "type": "element", "tagName": tag_name, "attributes": attributes }),
+        SnapshotNodeData::Text(text) => json!({ "type": "text", "data": text }),
+        SnapshotNodeData::Comment(comment) => json!({ "type": "comment", "data": comment }),
+        SnapshotNodeData::ShadowRoot => json!({ "type": "shadow-root" }),
+    };
+    value["nodeId"] = json!(node.id.0);
+    value["children"] = Value::Array(children);
+    value
+}
+
+fn encode_png(bitmap: &[u8], width: u32, height: u32) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    writer.write_image_data(bitmap).map_err(|e| e.to_string())?;
+    drop(writer);
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(connection: &mut BufReader<TcpStream>, request: &str) -> Value {
+        writeln!(connection.get_mut(), "{}", request).unwrap();
+        let mut response = String::new();
+        connection.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let parse = |message: &str| parse_command(&serde_json::from_str(message).unwrap());
+
+        assert_eq!(
+            parse(r#"{"id": 1, "method": "getComputedStyle", "params": {"nodeId": 12}}"#),
+            Ok(DevtoolsCommand::GetComputedStyle {
+                node_id: NodeId(12)
+            })
+        );
+        assert_eq!(
+            parse(r#"{"id": 2, "method": "highlightNode", "params": {"nodeId": null}}"#),
+            Ok(DevtoolsCommand::HighlightNode { node_id: None })
+        );
+        assert!(parse(r#"{"id": 3, "method": "getComputedStyle"}"#).is_err());
+        assert!(
+            parse(r#"{"id": 3, "method": "getComputedStyle", "params": {"nodeId": [0, 2]}}"#)
+                .is_err()
+        );
+        assert_eq!(
+            parse(r#"{"id": 4, "method": "getCacheStats"}"#),
+            Ok(DevtoolsCommand::GetCacheStats)
+        );
+        assert!(parse(r#"{"id": 5, "method": "reload"}"#).is_err());
+    }
+
+    #[test]
+    fn test_server_only_listens_on_loopback() {
+        let (engine_tx, _engine_rx) = flume::unbounded();
+        let error = start_server("0.0.0.0:0", engine_tx.clone()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(start_server("[::]:0", engine_tx.clone()).is_err());
+
+        let address = start_server("localhost:0", engine_tx).unwrap();
+        assert!(address.ip().is_loopback());
+    }
+
+    #[test]
+    fn test_server_forwards_commands() {
+        let (engine_tx, engine_rx) = flume::unbounded();
+        let address = start_server("127.0.0.1:0", engine_tx).unwrap();
+
+        // Stand-in for the render engine
+        std::thread::spawn(move || {
+            for event in engine_rx.iter() {
+                if let InputEvent::Devtools(request) = event {
+                    let result = match &request.command {
+                        DevtoolsCommand::ListPages => Ok(json!([{ "id": 0 }])),
+                        _ => Err("Unsupported".to_string()),
+                    };
+                    request.reply(result);
+                }
+            }
+        });
+
+        let mut connection = BufReader::new(TcpStream::connect(address).unwrap());
+        assert_eq!(
+            request(&mut connection, r#"{"id": 1, "method": "listPages"}"#),
+            json!({ "id": 1, "result": [{ "id": 0 }] })
+        );
+        assert_eq!(
+            request(
+                &mut connection,
+                r#"{"id": 2, "method": "captureScreenshot"}"#
+            ),
+            json!({ "id": 2, "error": "Unsupported" })
+        );
+        assert_eq!(
+            request(&mut connection, "not json"),
+            json!({ "id": null, "error": "Invalid request" })
+        );
+    }
+}
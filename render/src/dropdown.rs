@@ -0,0 +1,28 @@
+use dom::{node::NodePtr, select};
+
+/// An option of the select element shown by `OutputEvent::ShowDropdown`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropdownOption {
+    pub label: String,
+    /// Disabled options are shown but can't be chosen
+    pub disabled: bool,
+    pub selected: bool,
+}
+
+impl DropdownOption {
+    /// Options of the select element, in the order of their index
+    pub fn options_of(select: &NodePtr) -> Vec<Self> {
+        let selected: Vec<_> = select::selected_options(select)
+            .iter()
+            .map(|option| option.id())
+            .collect();
+        select::options(select)
+            .iter()
+            .map(|option| Self {
+                label: select::option_label(option),
+                disabled: select::is_option_disabled(option),
+                selected: selected.contains(&option.id()),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{Key, Modifiers};
+
+/// Commands run in editable controls, the result of key presses
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditingCommand {
+    InsertText(String),
+    InsertLineBreak,
+    DeleteBackward,
+    DeleteForward,
+    SelectAll,
+    /// Move the caret, extending the selection to it if `extend` is true
+    MoveCaret {
+        movement: CaretMovement,
+        extend: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaretMovement {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Up,
+    Down,
+    LineStart,
+    LineEnd,
+    TextStart,
+    TextEnd,
+}
+
+impl EditingCommand {
+    /// Command of the key with the modifiers. Ctrl moves the caret by words
+    /// with the arrows and to the ends of the text with Home and End.
+    pub fn from_key(key: &Key, modifiers: &Modifiers) -> Option<Self> {
+        let movement = match (key, modifiers.ctrl) {
+            (Key::ArrowLeft, false) => CaretMovement::Left,
+            (Key::ArrowLeft, true) => CaretMovement::WordLeft,
+            (Key::ArrowRight, false) => CaretMovement::Right,
+            (Key::ArrowRight, true) => CaretMovement::WordRight,
+            (Key::ArrowUp, _) => CaretMovement::Up,
+            (Key::ArrowDown, _) => CaretMovement::Down,
+            (Key::Home, false) => CaretMovement::LineStart,
+            (Key::Home, true) => CaretMovement::TextStart,
+            (Key::End, false) => CaretMovement::LineEnd,
+            (Key::End, true) => CaretMovement::TextEnd,
+            (Key::Character('a' | 'A'), true) => return Some(EditingCommand::SelectAll),
+            // Other characters typed with Ctrl are shortcuts of the embedder
+            (Key::Character(ch), false) if !ch.is_control() => {
+                return Some(EditingCommand::InsertText(ch.to_string()))
+            }
+            (Key::Enter, false) => return Some(EditingCommand::InsertLineBreak),
+            (Key::Backspace, _) => return Some(EditingCommand::DeleteBackward),
+            (Key::Delete, _) => return Some(EditingCommand::DeleteForward),
+            _ => return None,
+        };
+        Some(EditingCommand::MoveCaret {
+            movement,
+            extend: modifiers.shift,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_from_key() {
+        let ctrl = Modifiers {
+            ctrl: true,
+            ..Modifiers::default()
+        };
+        let shift = Modifiers {
+            shift: true,
+            ..Modifiers::default()
+        };
+
+        assert_eq!(
+            EditingCommand::from_key(&Key::Character('x'), &shift),
+            Some(EditingCommand::InsertText("x".to_string()))
+        );
+        assert_eq!(EditingCommand::from_key(&Key::Character('x'), &ctrl), None);
+        assert_eq!(
+            EditingCommand::from_key(&Key::ArrowLeft, &ctrl),
+            Some(EditingCommand::MoveCaret {
+                movement: CaretMovement::WordLeft,
+                extend: false,
+            })
+        );
+        assert_eq!(
+            EditingCommand::from_key(&Key::End, &shift),
+            Some(EditingCommand::MoveCaret {
+                movement: CaretMovement::LineEnd,
+                extend: true,
+            })
+        );
+    }
+}
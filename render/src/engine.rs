@@ -1,68 +1,960 @@
+use super::channel::FrameMailbox;
+use super::context::EngineContext;
+use super::dropdown::DropdownOption;
+use super::frame_pool::FramePool;
+use super::frame_stats::FrameStats;
+use super::inspector::{HitTestResult, InspectedNode};
+use super::load_progress::ProgressReporter;
+use super::memory::MemoryReport;
+use super::metadata::PageMetadata;
+use super::navigation::{NavigationPolicy, NavigationRequest, NavigationTarget};
 use super::page::Page;
-use flume::{Receiver, Sender};
-use gfx::Bitmap;
-use shared::primitive::Size;
+use super::preferences::Preferences;
+use super::print::PrintOptions;
+use super::scheduler::{CaretBlink, FirstPaint, FramePacing, RepaintScheduler};
+use super::touch::{Fling, TouchGesture, TouchPoint, TouchTracker};
+use super::visibility::ElementVisibility;
+use dom::{
+    form_submission::FormSubmission, lifecycle::DocumentReadyState, node_id::NodeId,
+    snapshot::DomSnapshot,
+};
+use flume::{Receiver, RecvTimeoutError, Sender};
+use gfx::GfxError;
+use gpu::GpuProcess;
+use loader::{NetworkSettings, ResourceLoader, ResourceType};
+use shared::{
+    cancellation::CancellationToken,
+    color::Color,
+    image::ImageBitmap,
+    primitive::{Point, Rect, Size},
+};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use url::Url;
 
+/// How often the engine checks for web fonts and images that finished
+/// loading
+const RESOURCE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a frame is held back when every frame buffer is in use
+const FRAME_RETRY_INTERVAL: Duration = Duration::from_millis(4);
+
+/// How long the caret of text controls is shown, then hidden
+const CARET_BLINK_INTERVAL: Duration = Duration::from_millis(530);
+
+/// How long the frames of a loading page are held back until the page is
+/// ready to be shown
+const FIRST_PAINT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often the page is styled again while CSS animations run
+const ANIMATION_FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
 pub enum InputEvent {
     ViewportResize(Size),
-    LoadHTML { html: String, base_url: Url },
+    /// Load a page, cancelling the load in progress if there is one
+    LoadHTML {
+        html: String,
+        base_url: Url,
+    },
+    /// Cancel the page load in progress and the fetches of the current page
+    StopLoading,
+    FindText {
+        query: String,
+        forward: bool,
+    },
+    CaptureSnapshot,
+    SubmitForm {
+        index: usize,
+    },
+    Mouse(MouseEvent),
+    /// Fingers touched the screen. Dragging a single finger scrolls, with
+    /// momentum once it is lifted, pinching zooms and tapping clicks.
+    TouchStart(Vec<TouchPoint>),
+    /// Fingers on the screen moved
+    TouchMove(Vec<TouchPoint>),
+    /// Fingers were lifted from the screen
+    TouchEnd(Vec<TouchPoint>),
+    /// Choose the option at `index` of the dropdown shown by
+    /// `OutputEvent::ShowDropdown`, or close it without a choice with `None`
+    ChooseDropdownOption {
+        index: Option<usize>,
+    },
+    /// Answer `OutputEvent::NavigationRequested`. A navigation that isn't
+    /// answered is dropped when the page requests another one.
+    ResolveNavigation(NavigationPolicy),
+    /// A key pressed while the page has keyboard focus
+    KeyDown {
+        key: Key,
+        modifiers: Modifiers,
+    },
+    CopySelection,
+    /// Ask for the `OutputEvent::SelectionRects` of the selected text
+    RequestSelectionRects,
+    /// A wheel or touchpad scroll at the point in viewport coordinates. The
+    /// point and the scrolled distance are in device pixels.
+    Scroll {
+        x: f32,
+        y: f32,
+        delta_x: f32,
+        delta_y: f32,
+    },
+    /// Find the element at the point in viewport coordinates, in device pixels
+    InspectNode {
+        x: f32,
+        y: f32,
+    },
+    /// Find the element at the point in viewport coordinates, in device
+    /// pixels, for context menus of the embedder
+    HitTest {
+        x: f32,
+        y: f32,
+    },
+    /// Ask where the elements with the ids are relative to the viewport,
+    /// answered by `OutputEvent::VisibilityResult`
+    QueryVisibility {
+        node_ids: Vec<NodeId>,
+    },
+    SetZoom(f32),
+    /// Move the focus between links and form controls with the arrow keys,
+    /// for embedders without a pointer. The focused element gets a focus
+    /// ring and Enter follows the focused link.
+    SetSpatialNavigation(bool),
+    DevicePixelRatioChanged(f32),
+    /// Paint the page over the color instead of white. The frames of a
+    /// transparent color have straight alpha.
+    SetBackgroundColor(Color),
+    /// Follow the settings of the user, such as their color scheme, in
+    /// media queries and default styles
+    SetPreferences(Preferences),
+    /// Connect through a proxy, trust more certificates or send another
+    /// user agent in the next requests of every page
+    SetNetworkSettings(NetworkSettings),
+    /// Ask for the `OutputEvent::FrameStats` of the latest frame
+    RequestFrameStats,
+    /// Ask for the `OutputEvent::MemoryReport` of the page
+    RequestMemoryReport,
+    /// Show `bitmap` as the current frame of the video element with the id.
+    /// Videos are decoded by the embedder, the engine lays out and composites
+    /// the frames it supplies.
+    UpdateVideoFrame {
+        node_id: NodeId,
+        bitmap: ImageBitmap,
+    },
+    /// Lay the page out on pages of paper and print it to a PDF document
+    PrintToPdf {
+        options: PrintOptions,
+    },
+    #[cfg(feature = "devtools")]
+    Devtools(crate::devtools::DevtoolsRequest),
+}
+
+/// Mouse events in viewport coordinates, in device pixels
+pub enum MouseEvent {
+    Down(Point),
+    Move(Point),
+    Up(Point),
+}
+
+/// Keys handled by the page
+pub enum Key {
+    /// Submits the form of the focused text field, or starts a new line in
+    /// a textarea
+    Enter,
+    Backspace,
+    Delete,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    ArrowDown,
+    Home,
+    End,
+    /// A character typed in a text control
+    Character(char),
+}
+
+/// Modifier keys held while a key is pressed
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Modifiers {
+    /// Extends the selection while moving the caret
+    pub shift: bool,
+    /// Moves the caret by words instead of characters
+    pub ctrl: bool,
 }
 
 pub enum OutputEvent {
-    FrameRendered(Bitmap),
     TitleChanged(String),
+    /// Description of the loaded page from its head, for previews like link
+    /// cards. Sent after each page load, with the `TitleChanged` of the page.
+    MetadataChanged(PageMetadata),
+    /// `current` is the 1-based index of the active match, 0 if there is no match
+    FindResult {
+        current: usize,
+        total: usize,
+    },
+    SnapshotCaptured(DomSnapshot),
+    ValidationFailed(Vec<String>),
+    SelectionChanged(String),
+    /// The caret of the focused text control moved, to `rect` in viewport
+    /// coordinates in device pixels, for the embedder to place the candidate
+    /// window of an input method. `None` once there is no caret, like when
+    /// the text control loses focus or text is selected in it.
+    CaretMoved {
+        rect: Option<Rect>,
+    },
+    /// Bounds of the selected text, a rect per line in viewport coordinates
+    /// in device pixels, sent in response to
+    /// `InputEvent::RequestSelectionRects`. Embedders place touch selection
+    /// handles or a magnifier at them.
+    SelectionRects(Vec<Rect>),
+    CopyToClipboard(String),
+    /// Time spent in each pipeline stage to produce the latest frame, in
+    /// milliseconds. Sent in response to `InputEvent::RequestFrameStats`.
+    FrameStats {
+        parse_ms: f32,
+        style_ms: f32,
+        layout_ms: f32,
+        paint_ms: f32,
+        raster_ms: f32,
+        /// Time from the start of the page load to its first frame, `None`
+        /// until the page has been painted
+        first_contentful_paint_ms: Option<f32>,
+    },
+    /// Memory held by the page, sent in response to
+    /// `InputEvent::RequestMemoryReport`
+    MemoryReport(MemoryReport),
+    /// The element found by `InputEvent::InspectNode`, if there is one
+    NodeInspected(Option<InspectedNode>),
+    /// The element found by `InputEvent::HitTest`, if there is one
+    HitTestResult(Option<HitTestResult>),
+    /// The elements of `InputEvent::QueryVisibility` that are in the document
+    VisibilityResult(Vec<ElementVisibility>),
+    /// A select element was clicked. The embedder presents its options in a
+    /// popup at `rect`, in viewport coordinates in device pixels, then sends
+    /// `InputEvent::ChooseDropdownOption`.
+    ShowDropdown {
+        options: Vec<DropdownOption>,
+        rect: Rect,
+    },
+    /// The page wants to navigate, like when a link is clicked. Nothing is
+    /// loaded until the embedder sends `InputEvent::ResolveNavigation`.
+    NavigationRequested {
+        url: Url,
+        target: NavigationTarget,
+        /// The navigation was started by the user
+        user_gesture: bool,
+    },
+    /// An allowed navigation targets another page than the current one. The
+    /// embedder opens a new page for `name: None` and otherwise the page with
+    /// the name, creating it if there is none. With `no_opener`, the new
+    /// page mustn't be able to reach the page that opened it.
+    NewPageRequested {
+        url: Url,
+        name: Option<String>,
+        no_opener: bool,
+    },
+    /// The page loaded another document by itself, like the response of a
+    /// submitted form
+    Navigated(Url),
+    /// The PDF document printed by `InputEvent::PrintToPdf`
+    PdfPrinted(Vec<u8>),
+    /// The page started loading a document, parsed it, or loaded its
+    /// resources. A cancelled load goes back to the state of the document
+    /// that is still shown.
+    LoadStateChanged(DocumentReadyState),
+    /// Bytes received so far by the loads of the page that is loading,
+    /// its document and subresources together. `total_bytes` grows as the
+    /// page requests more resources and their lengths become known.
+    LoadProgress {
+        loaded_bytes: u64,
+        total_bytes: u64,
+        resource_count: usize,
+    },
+    /// The icon of the loaded page, still encoded in its image format
+    FaviconLoaded {
+        url: Url,
+        data: Vec<u8>,
+    },
+    /// The settings of `InputEvent::SetNetworkSettings` are invalid, like a
+    /// proxy URL that can't be parsed. The previous settings are kept.
+    NetworkSettingsRejected(String),
+    /// The engine failed to start or stopped unexpectedly
+    EngineError(String),
 }
 
 pub struct RenderEngine<'a> {
     page: Page<'a>,
+    scheduler: RepaintScheduler,
+    caret_blink: CaretBlink,
+    first_paint: FirstPaint,
+    frame_pool: FramePool,
+    frame_stats: FrameStats,
+    /// Navigation waiting for `InputEvent::ResolveNavigation`
+    pending_navigation: Option<NavigationRequest>,
+    /// Caret last reported by `OutputEvent::CaretMoved`
+    caret_rect: Option<Rect>,
+    /// Ready state last reported by `OutputEvent::LoadStateChanged`
+    ready_state: DocumentReadyState,
+    /// Cancelled by the `StopLoading` and `LoadHTML` events received after
+    /// the event being handled, so it cancels the page loads it starts,
+    /// like a followed link or a submitted form
+    load_cancellation: CancellationToken,
+    load_progress: ProgressReporter,
+    touch_tracker: TouchTracker,
+    /// Scrolling that goes on after a finger was lifted while moving
+    fling: Option<Fling>,
+    /// When the last frame of the CSS animations was rendered
+    last_animation_frame: Instant,
 }
 
 impl<'a> RenderEngine<'a> {
-    pub async fn new(viewport: Size) -> RenderEngine<'a> {
-        let page = Page::new(viewport).await;
-        Self { page }
+    pub async fn new(viewport: Size) -> Result<RenderEngine<'a>, GfxError> {
+        Self::new_with_dpr(viewport, 1.).await
+    }
+
+    /// Create a render engine for a display with `device_pixel_ratio` device
+    /// pixels per CSS pixel. The viewport size is in device pixels.
+    pub async fn new_with_dpr(
+        viewport: Size,
+        device_pixel_ratio: f32,
+    ) -> Result<RenderEngine<'a>, GfxError> {
+        let context = Rc::new(EngineContext::new());
+        let page = Page::new_with_context(viewport, device_pixel_ratio, context).await?;
+        Ok(Self::with_page(page))
+    }
+
+    /// Create a render engine that has its frames rasterized by the GPU
+    /// process, see `new_with_dpr`
+    pub fn new_with_gpu_process(
+        viewport: Size,
+        device_pixel_ratio: f32,
+        process: GpuProcess,
+    ) -> RenderEngine<'a> {
+        let context = Rc::new(EngineContext::new());
+        let page = Page::new_with_gpu_process(viewport, device_pixel_ratio, context, process);
+        Self::with_page(page)
+    }
+
+    fn with_page(page: Page<'a>) -> RenderEngine<'a> {
+        Self {
+            page,
+            scheduler: RepaintScheduler::new(FramePacing::default()),
+            caret_blink: CaretBlink::new(CARET_BLINK_INTERVAL),
+            first_paint: FirstPaint::new(FIRST_PAINT_TIMEOUT),
+            frame_pool: FramePool::new(),
+            frame_stats: FrameStats::default(),
+            pending_navigation: None,
+            caret_rect: None,
+            ready_state: DocumentReadyState::Complete,
+            load_cancellation: CancellationToken::new(),
+            load_progress: ProgressReporter::new(),
+            touch_tracker: TouchTracker::new(),
+            fling: None,
+            last_animation_frame: Instant::now(),
+        }
+    }
+
+    /// Limit how often frames are emitted. Use `FramePacing::Immediate`
+    /// to emit a frame after every event that changes the page.
+    pub fn with_frame_pacing(mut self, pacing: FramePacing) -> Self {
+        self.scheduler.set_pacing(pacing);
+        self
     }
 
+    /// Run the engine until one of its channels is disconnected. The channels can be
+    /// bounded, see `ChannelConfig` for how the engine handles a full channel.
+    /// Rendered frames are posted to `frames`.
     pub async fn run(
         mut self,
         event_receiver: Receiver<InputEvent>,
         event_emitter: Sender<OutputEvent>,
+        frames: FrameMailbox,
     ) -> anyhow::Result<()> {
+        let event_receiver = spawn_load_watcher(event_receiver);
+        self.load_progress.spawn(event_emitter.clone());
+
         loop {
-            let event = event_receiver.recv()?;
-            self.handle_event(event, &event_emitter).await?;
+            // Wait for the next event, but no longer than until the pending frame is due,
+            // until loaded fonts should be checked, until the caret blinks or until
+            // the next frame of the animations. The frames of a page that isn't ready
+            // are due once it is, or once its first paint times out.
+            let is_first_paint_held = self
+                .first_paint
+                .is_held(self.page.is_ready_to_paint(), Instant::now());
+            let mut deadline = match is_first_paint_held {
+                true => self.first_paint.deadline(),
+                false => self.scheduler.next_frame_deadline(),
+            };
+            if self.page.is_loading_resources() {
+                let resource_poll = Instant::now() + RESOURCE_POLL_INTERVAL;
+                deadline =
+                    Some(deadline.map_or(resource_poll, |deadline| deadline.min(resource_poll)));
+            }
+            if let Some(toggle) = self.caret_blink.next_toggle(Instant::now()) {
+                deadline = Some(deadline.map_or(toggle, |deadline| deadline.min(toggle)));
+            }
+            if self.page.has_running_animations() || self.fling.is_some() {
+                let tick = self.last_animation_frame + ANIMATION_FRAME_INTERVAL;
+                deadline = Some(deadline.map_or(tick, |deadline| deadline.min(tick)));
+            }
+
+            let event = match deadline {
+                Some(deadline) => match event_receiver.recv_deadline(deadline) {
+                    Ok(event) => Some(event),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(e) => return Err(e.into()),
+                },
+                None => Some(event_receiver.recv()?),
+            };
+
+            if let Some((event, load_cancellation)) = event {
+                // The caret stays visible while the user types or clicks
+                let shows_caret = matches!(
+                    event,
+                    InputEvent::KeyDown { .. } | InputEvent::Mouse(MouseEvent::Down(_))
+                );
+                self.handle_event(event, load_cancellation, &event_emitter)
+                    .await?;
+                self.emit_caret_moved(&event_emitter)?;
+                if !self.page.has_caret() {
+                    self.caret_blink.stop();
+                } else if shows_caret {
+                    self.caret_blink.restart(Instant::now());
+                }
+            }
+
+            if let Some(is_visible) = self.caret_blink.poll(Instant::now()) {
+                if self.page.set_caret_visible(is_visible).await {
+                    self.scheduler.invalidate();
+                }
+            }
+
+            if self.page.update_loaded_resources().await {
+                self.scheduler.invalidate();
+            }
+
+            if self.page.has_running_animations()
+                && self.last_animation_frame.elapsed() >= ANIMATION_FRAME_INTERVAL
+            {
+                self.page.update_animations().await;
+                self.last_animation_frame = Instant::now();
+                self.scheduler.invalidate();
+            }
+
+            if let Some(fling) = &mut self.fling {
+                let is_scrolled = match fling.tick(Instant::now()) {
+                    Some(delta) => {
+                        let point = fling.point.clone();
+                        self.page
+                            .scroll(point, Point::new(-delta.x, -delta.y))
+                            .await
+                    }
+                    None => false,
+                };
+                // The fling stops at the end of what it scrolls
+                if is_scrolled {
+                    self.scheduler.invalidate();
+                } else {
+                    self.fling = None;
+                }
+            }
+
+            // Scrolling and layout bring lazily loaded iframes near the viewport
+            if self.page.load_lazy_frames().await {
+                self.scheduler.invalidate();
+            }
+            self.page.load_image_sources();
+            self.page.update_ready_state();
+            self.emit_ready_state(self.page.ready_state(), &event_emitter)?;
+
+            let now = Instant::now();
+            let is_first_paint_held = self.first_paint.is_held(self.page.is_ready_to_paint(), now);
+            if self.scheduler.is_frame_due(now) && !is_first_paint_held {
+                // The consumer is behind when it still holds every frame buffer.
+                // The latest frame is emitted once a buffer is free, dropping the
+                // frames rendered until then.
+                if self.emit_new_frame(&frames) {
+                    self.scheduler.frame_emitted(now);
+                } else {
+                    self.scheduler.defer(now + FRAME_RETRY_INTERVAL);
+                }
+            }
         }
     }
 
     async fn handle_event(
         &mut self,
         event: InputEvent,
+        load_cancellation: CancellationToken,
         event_emitter: &Sender<OutputEvent>,
     ) -> anyhow::Result<()> {
+        self.load_cancellation = load_cancellation;
         match event {
             InputEvent::ViewportResize(new_size) => {
                 self.page.resize(new_size).await;
-                self.emit_new_frame(event_emitter)?;
+                self.scheduler.invalidate();
+            }
+            InputEvent::SetZoom(zoom) => {
+                self.page.set_zoom(zoom).await;
+                self.scheduler.invalidate();
+            }
+            InputEvent::SetSpatialNavigation(enabled) => {
+                self.page.set_spatial_navigation(enabled).await;
+                self.scheduler.invalidate();
+            }
+            InputEvent::DevicePixelRatioChanged(device_pixel_ratio) => {
+                self.page.set_device_pixel_ratio(device_pixel_ratio).await;
+                self.scheduler.invalidate();
+            }
+            InputEvent::SetBackgroundColor(color) => {
+                self.page.set_background_color(color).await;
+                self.scheduler.invalidate();
+            }
+            InputEvent::SetPreferences(preferences) => {
+                self.page.set_preferences(preferences).await;
+                self.scheduler.invalidate();
+            }
+            InputEvent::SetNetworkSettings(settings) => {
+                if let Err(e) = ResourceLoader::current().configure_network(&settings) {
+                    log::error!("Invalid network settings: {}", e);
+                    event_emitter.send(OutputEvent::NetworkSettingsRejected(e))?;
+                }
+            }
+            InputEvent::RequestFrameStats => {
+                let stats = &self.frame_stats;
+                event_emitter.send(OutputEvent::FrameStats {
+                    parse_ms: stats.parse_ms,
+                    style_ms: stats.style_ms,
+                    layout_ms: stats.layout_ms,
+                    paint_ms: stats.paint_ms,
+                    raster_ms: stats.raster_ms,
+                    first_contentful_paint_ms: stats.first_contentful_paint_ms,
+                })?;
+            }
+            InputEvent::RequestMemoryReport => {
+                event_emitter.send(OutputEvent::MemoryReport(self.page.memory_report()))?;
             }
             InputEvent::LoadHTML { html, base_url } => {
-                self.page.load_html(html, base_url).await;
-                self.emit_new_frame(event_emitter)?;
-                self.emit_new_title(event_emitter)?;
+                let cancellation = self.load_cancellation.clone();
+                let load_start = Instant::now();
+                self.emit_ready_state(DocumentReadyState::Loading, event_emitter)?;
+                if self
+                    .page
+                    .load_html(html, base_url, cancellation.clone())
+                    .await
+                {
+                    self.scheduler.invalidate();
+                    self.first_paint.start(load_start);
+                    self.emit_new_title(event_emitter)?;
+                    self.emit_metadata(event_emitter)?;
+                    self.load_favicon(cancellation, event_emitter);
+                }
+                self.emit_ready_state(self.page.ready_state(), event_emitter)?;
+            }
+            // The load in progress has been cancelled by the load watcher already
+            InputEvent::StopLoading => self.page.stop_loading(),
+            InputEvent::FindText { query, forward } => {
+                let (current, total) = self.page.find_text(query, forward).await;
+                self.scheduler.invalidate();
+                event_emitter.send(OutputEvent::FindResult { current, total })?;
+            }
+            InputEvent::CaptureSnapshot => {
+                if let Some(snapshot) = self.page.snapshot() {
+                    event_emitter.send(OutputEvent::SnapshotCaptured(snapshot))?;
+                }
+            }
+            InputEvent::SubmitForm { index } => {
+                let submission = self.page.submit_form(index);
+                self.submit_form(submission, event_emitter).await?;
+            }
+            InputEvent::Mouse(event) => self.handle_mouse_event(event, event_emitter).await?,
+            InputEvent::TouchStart(points) => {
+                // Touching the screen catches the page scrolling by itself
+                self.fling = None;
+                self.touch_tracker.touch_start(&points, Instant::now());
+            }
+            InputEvent::TouchMove(points) => {
+                let gesture = self.touch_tracker.touch_move(&points, Instant::now());
+                self.handle_touch_gesture(gesture, event_emitter).await?;
+            }
+            InputEvent::TouchEnd(points) => {
+                let gesture = self.touch_tracker.touch_end(&points, Instant::now());
+                self.handle_touch_gesture(gesture, event_emitter).await?;
+            }
+            InputEvent::ChooseDropdownOption { index } => {
+                if self.page.choose_dropdown_option(index).await {
+                    self.scheduler.invalidate();
+                }
+            }
+            InputEvent::ResolveNavigation(policy) => {
+                let request = match self.pending_navigation.take() {
+                    Some(request) => request,
+                    None => return Ok(()),
+                };
+                let url = match policy {
+                    NavigationPolicy::Allow => request.url.clone(),
+                    NavigationPolicy::Redirect(url) => url,
+                    NavigationPolicy::Deny => return Ok(()),
+                };
+                self.navigate(request, url, event_emitter).await?;
+            }
+            InputEvent::KeyDown { key, modifiers } => {
+                if self.page.handle_key(key, modifiers).await {
+                    self.scheduler.invalidate();
+                    let selected_text = self.page.selected_text();
+                    event_emitter.send(OutputEvent::SelectionChanged(selected_text))?;
+                }
+                self.request_navigation(event_emitter)?;
+                let submission = self.page.take_form_submission();
+                self.submit_form(submission, event_emitter).await?;
+            }
+            InputEvent::InspectNode { x, y } => {
+                let node = self.page.inspect_node(Point::new(x, y));
+                event_emitter.send(OutputEvent::NodeInspected(node))?;
+            }
+            InputEvent::Scroll {
+                x,
+                y,
+                delta_x,
+                delta_y,
+            } => {
+                if self
+                    .page
+                    .scroll(Point::new(x, y), Point::new(delta_x, delta_y))
+                    .await
+                {
+                    self.scheduler.invalidate();
+                }
+            }
+            InputEvent::HitTest { x, y } => {
+                let result = self.page.hit_test(Point::new(x, y));
+                event_emitter.send(OutputEvent::HitTestResult(result))?;
+            }
+            InputEvent::QueryVisibility { node_ids } => {
+                let result = self.page.query_visibility(&node_ids);
+                event_emitter.send(OutputEvent::VisibilityResult(result))?;
+            }
+            InputEvent::UpdateVideoFrame { node_id, bitmap } => {
+                if self.page.update_video_frame(node_id, bitmap).await {
+                    self.scheduler.invalidate();
+                } else {
+                    log::warn!("Unable to find video element: {}", node_id);
+                }
+            }
+            InputEvent::PrintToPdf { options } => match self.page.print_to_pdf(&options).await {
+                Some(pdf) => event_emitter.send(OutputEvent::PdfPrinted(pdf))?,
+                None => log::warn!("Unable to print: no document is loaded"),
+            },
+            #[cfg(feature = "devtools")]
+            InputEvent::Devtools(request) => {
+                use crate::devtools::{handle_command, DevtoolsCommand};
+
+                let is_highlight = matches!(request.command, DevtoolsCommand::HighlightNode { .. });
+                let result = handle_command(&mut self.page, &request.command).await;
+                if is_highlight {
+                    self.scheduler.invalidate();
+                }
+                request.reply(result);
+            }
+            InputEvent::RequestSelectionRects => {
+                event_emitter.send(OutputEvent::SelectionRects(self.page.selection_rects()))?;
+            }
+            InputEvent::CopySelection => {
+                let selected_text = self.page.selected_text();
+                if !selected_text.is_empty() {
+                    event_emitter.send(OutputEvent::CopyToClipboard(selected_text))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn handle_mouse_event(
+        &mut self,
+        event: MouseEvent,
+        event_emitter: &Sender<OutputEvent>,
+    ) -> anyhow::Result<()> {
+        if self.page.handle_mouse_event(event).await {
+            self.scheduler.invalidate();
+            let selected_text = self.page.selected_text();
+            event_emitter.send(OutputEvent::SelectionChanged(selected_text))?;
+        }
+        if let Some((options, rect)) = self.page.take_dropdown() {
+            event_emitter.send(OutputEvent::ShowDropdown { options, rect })?;
+        }
+        self.request_navigation(event_emitter)?;
+        let submission = self.page.take_form_submission();
+        self.submit_form(submission, event_emitter).await?;
+        Ok(())
+    }
+
+    /// Ask the embedder about the navigation started by the last input
+    /// event, like a followed link
+    fn request_navigation(&mut self, event_emitter: &Sender<OutputEvent>) -> anyhow::Result<()> {
+        if let Some(request) = self.page.take_navigation_request() {
+            event_emitter.send(OutputEvent::NavigationRequested {
+                url: request.url.clone(),
+                target: request.target.clone(),
+                user_gesture: request.user_gesture,
+            })?;
+            self.pending_navigation = Some(request);
+        }
+        Ok(())
+    }
+
+    /// Scroll for a pan or a fling, zoom for a pinch and click for a tap
+    async fn handle_touch_gesture(
+        &mut self,
+        gesture: Option<TouchGesture>,
+        event_emitter: &Sender<OutputEvent>,
+    ) -> anyhow::Result<()> {
+        match gesture {
+            // The content follows the finger
+            Some(TouchGesture::Pan { point, delta }) => {
+                if self
+                    .page
+                    .scroll(point, Point::new(-delta.x, -delta.y))
+                    .await
+                {
+                    self.scheduler.invalidate();
+                }
+            }
+            Some(TouchGesture::Fling { point, velocity }) => {
+                self.fling = Some(Fling::new(point, velocity, Instant::now()));
+            }
+            Some(TouchGesture::Pinch { center, scale }) => {
+                let zoom = self.page.zoom() * scale;
+                self.page.zoom_at(zoom, center).await;
+                self.scheduler.invalidate();
             }
+            Some(TouchGesture::Tap(point)) => {
+                self.handle_mouse_event(MouseEvent::Down(point.clone()), event_emitter)
+                    .await?;
+                self.handle_mouse_event(MouseEvent::Up(point), event_emitter)
+                    .await?;
+            }
+            None => {}
         }
         Ok(())
     }
 
+    /// Navigate to the response of a form submission, or report why the
+    /// form couldn't be submitted
+    async fn submit_form(
+        &mut self,
+        submission: Result<Option<FormSubmission>, Vec<String>>,
+        event_emitter: &Sender<OutputEvent>,
+    ) -> anyhow::Result<()> {
+        match submission {
+            Ok(Some(submission)) => {
+                let url = submission.url.clone();
+                let cancellation = self.load_cancellation.clone();
+                let load_start = Instant::now();
+                self.emit_ready_state(DocumentReadyState::Loading, event_emitter)?;
+                if self
+                    .page
+                    .navigate_to_submission(submission, cancellation.clone())
+                    .await
+                {
+                    self.scheduler.invalidate();
+                    self.first_paint.start(load_start);
+                    event_emitter.send(OutputEvent::Navigated(url))?;
+                    self.emit_new_title(event_emitter)?;
+                    self.emit_metadata(event_emitter)?;
+                    self.load_favicon(cancellation, event_emitter);
+                }
+                self.emit_ready_state(self.page.ready_state(), event_emitter)?;
+            }
+            Ok(None) => {}
+            Err(messages) => event_emitter.send(OutputEvent::ValidationFailed(messages))?,
+        }
+        Ok(())
+    }
+
+    /// Follow an allowed navigation to `url`, in place of the current page or
+    /// by asking the embedder for another page
+    async fn navigate(
+        &mut self,
+        request: NavigationRequest,
+        url: Url,
+        event_emitter: &Sender<OutputEvent>,
+    ) -> anyhow::Result<()> {
+        let name = match request.target {
+            NavigationTarget::CurrentPage => {
+                let cancellation = self.load_cancellation.clone();
+                let load_start = Instant::now();
+                self.emit_ready_state(DocumentReadyState::Loading, event_emitter)?;
+                if self
+                    .page
+                    .navigate_to(url.clone(), cancellation.clone())
+                    .await
+                {
+                    self.scheduler.invalidate();
+                    self.first_paint.start(load_start);
+                    event_emitter.send(OutputEvent::Navigated(url))?;
+                    self.emit_new_title(event_emitter)?;
+                    self.emit_metadata(event_emitter)?;
+                    self.load_favicon(cancellation, event_emitter);
+                }
+                self.emit_ready_state(self.page.ready_state(), event_emitter)?;
+                return Ok(());
+            }
+            NavigationTarget::NewPage => None,
+            NavigationTarget::Named(name) => Some(name),
+        };
+        event_emitter.send(OutputEvent::NewPageRequested {
+            url,
+            name,
+            no_opener: request.no_opener,
+        })?;
+        Ok(())
+    }
+
     fn emit_new_title(&self, event_emitter: &Sender<OutputEvent>) -> anyhow::Result<()> {
         event_emitter.send(OutputEvent::TitleChanged(self.page.title()))?;
         Ok(())
     }
 
-    fn emit_new_frame(&self, event_emitter: &Sender<OutputEvent>) -> anyhow::Result<()> {
-        if let Some(frame) = self.page.bitmap() {
-            event_emitter.send(OutputEvent::FrameRendered(frame.clone()))?;
+    fn emit_metadata(&self, event_emitter: &Sender<OutputEvent>) -> anyhow::Result<()> {
+        event_emitter.send(OutputEvent::MetadataChanged(self.page.metadata()))?;
+        Ok(())
+    }
+
+    /// Report the ready state when it changed since it was last reported
+    fn emit_ready_state(
+        &mut self,
+        ready_state: DocumentReadyState,
+        event_emitter: &Sender<OutputEvent>,
+    ) -> anyhow::Result<()> {
+        if ready_state != self.ready_state {
+            self.ready_state = ready_state;
+            match ready_state {
+                DocumentReadyState::Loading => self.load_progress.start(),
+                DocumentReadyState::Interactive => {}
+                DocumentReadyState::Complete => self.load_progress.finish(event_emitter),
+            }
+            event_emitter.send(OutputEvent::LoadStateChanged(ready_state))?;
         }
         Ok(())
     }
+
+    /// Report the caret when it moved since it was last reported
+    fn emit_caret_moved(&mut self, event_emitter: &Sender<OutputEvent>) -> anyhow::Result<()> {
+        let rect = self.page.caret_rect();
+        if rect != self.caret_rect {
+            self.caret_rect = rect.clone();
+            event_emitter.send(OutputEvent::CaretMoved { rect })?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the icon of the page in the background. It isn't emitted if
+    /// another page is loaded in the meantime.
+    fn load_favicon(&self, cancellation: CancellationToken, event_emitter: &Sender<OutputEvent>) {
+        let url = match self.page.favicon_url() {
+            Some(url) => url,
+            None => return,
+        };
+        let document_url = self.page.url();
+        let event_emitter = event_emitter.clone();
+        std::thread::spawn(move || {
+            match ResourceLoader::current().load_subresource(
+                &url,
+                ResourceType::Image,
+                document_url.as_ref(),
+            ) {
+                Ok(data) if !cancellation.is_cancelled() => {
+                    let _ = event_emitter.send(OutputEvent::FaviconLoaded { url, data });
+                }
+                Ok(_) => {}
+                Err(e) => log::info!("Unable to load favicon: {} ({})", e, url),
+            }
+        });
+    }
+
+    /// Returns false if there is no free frame buffer to emit the frame in
+    fn emit_new_frame(&mut self, frames: &FrameMailbox) -> bool {
+        if let Some(bitmap) = self.page.bitmap() {
+            // The frame not taken yet is stale, its buffer can be reused
+            frames.discard();
+            let frame = match self.frame_pool.publish(bitmap) {
+                Some(frame) => frame,
+                None => return false,
+            };
+            frames.post(frame);
+            self.first_paint.frame_emitted(Instant::now());
+            self.frame_stats = self.page.take_frame_stats();
+            self.frame_stats.first_contentful_paint_ms = self.first_paint.first_paint_ms();
+        }
+        true
+    }
+}
+
+/// Forward input events from a separate thread, so that a navigation or
+/// `StopLoading` cancels the page load in progress right away instead of
+/// after the engine is done with it. Every event is forwarded with the token
+/// that cancels the page loads it starts, which the next `LoadHTML` or
+/// `StopLoading` cancels.
+fn spawn_load_watcher(events: Receiver<InputEvent>) -> Receiver<(InputEvent, CancellationToken)> {
+    let (forward_tx, forward_rx) = match events.capacity() {
+        Some(capacity) => flume::bounded(capacity),
+        None => flume::unbounded(),
+    };
+
+    std::thread::spawn(move || {
+        let mut current_load = CancellationToken::new();
+        for event in events.iter() {
+            if matches!(event, InputEvent::LoadHTML { .. } | InputEvent::StopLoading) {
+                current_load.cancel();
+                current_load = CancellationToken::new();
+            }
+            if forward_tx.send((event, current_load.clone())).is_err() {
+                break;
+            }
+        }
+    });
+
+    forward_rx
+}
+
+#[cfg(test)]
+mod tests {
+    use url::parser::URLParser;
+
+    use super::*;
+
+    #[test]
+    fn test_stop_loading_cancels_navigation() {
+        let (event_tx, event_rx) = flume::unbounded();
+        let forwarded = spawn_load_watcher(event_rx);
+        let url = |url: &str| URLParser::parse(url, None).unwrap();
+        let events = [
+            InputEvent::LoadHTML {
+                html: String::new(),
+                base_url: url("https://example.com/"),
+            },
+            InputEvent::ResolveNavigation(NavigationPolicy::Allow),
+            InputEvent::StopLoading,
+            InputEvent::SubmitForm { index: 0 },
+            InputEvent::LoadHTML {
+                html: String::new(),
+                base_url: url("https://example.org/"),
+            },
+        ];
+        for event in events {
+            event_tx.send(event).unwrap();
+        }
+        let tokens: Vec<CancellationToken> = forwarded
+            .iter()
+            .take(5)
+            .map(|(_, cancellation)| cancellation)
+            .collect();
+
+        // The navigation followed after the page was loaded is stopped like
+        // the page load itself, so the page it would replace stays in place
+        assert!(tokens[0].is_cancelled());
+        assert!(tokens[1].is_cancelled());
+        // So is a form submission, by the next page load
+        assert!(tokens[3].is_cancelled());
+        assert!(!tokens[4].is_cancelled());
+    }
 }
@@ -0,0 +1,91 @@
+use layout::{layout_box::LayoutBoxPtr, text_search::find_text};
+use painting::OverlayLayer;
+use shared::{color::Color, primitive::Rect};
+
+/// Matches are highlighted over the content, so the colors are translucent
+/// to keep the matched text visible
+const MATCH_HIGHLIGHT_COLOR: Color = Color {
+    r: 255,
+    g: 255,
+    b: 0,
+    a: 140,
+};
+
+const ACTIVE_MATCH_HIGHLIGHT_COLOR: Color = Color {
+    r: 255,
+    g: 150,
+    b: 50,
+    a: 160,
+};
+
+/// State of an ongoing find-in-page search
+pub struct FindSession {
+    query: String,
+    matches: Vec<Rect>,
+    current: Option<usize>,
+}
+
+impl FindSession {
+    pub fn new(query: String) -> Self {
+        Self {
+            query,
+            matches: Vec::new(),
+            current: None,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Search the layout tree again, keeping the current match index if still valid
+    pub fn refresh(&mut self, layout_root: Option<LayoutBoxPtr>) {
+        self.matches = layout_root
+            .map(|root| find_text(&root, &self.query))
+            .unwrap_or_default();
+
+        self.current = match self.current {
+            _ if self.matches.is_empty() => None,
+            Some(index) => Some(index.min(self.matches.len() - 1)),
+            None => None,
+        };
+    }
+
+    /// Move to the next (or previous) match, wrapping around at both ends
+    pub fn step(&mut self, forward: bool) {
+        let total = self.matches.len();
+
+        if total == 0 {
+            self.current = None;
+            return;
+        }
+
+        self.current = Some(match (self.current, forward) {
+            (None, true) => 0,
+            (None, false) => total - 1,
+            (Some(index), true) => (index + 1) % total,
+            (Some(index), false) => (index + total - 1) % total,
+        });
+    }
+
+    pub fn current_match(&self) -> Option<&Rect> {
+        self.current.and_then(|index| self.matches.get(index))
+    }
+
+    /// The 1-based index of the current match (0 when there is none) and the total
+    pub fn result(&self) -> (usize, usize) {
+        let current = self.current.map(|index| index + 1).unwrap_or(0);
+        (current, self.matches.len())
+    }
+
+    pub fn paint_highlights(&self, overlay: &mut OverlayLayer) {
+        for (index, rect) in self.matches.iter().enumerate() {
+            let color = if Some(index) == self.current {
+                ACTIVE_MATCH_HIGHLIGHT_COLOR
+            } else {
+                MATCH_HIGHLIGHT_COLOR
+            };
+            overlay.add_highlight(rect.clone(), color);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+
+use css::cssom::font_face_rule::FontFaceSource;
+use dom::document::Document;
+use flume::{Receiver, Sender};
+use loader::{ResourceLoader, ResourceType};
+use url::parser::URLParser;
+
+/// Font formats that the text subsystem can decode
+const SUPPORTED_FORMATS: [&str; 2] = ["truetype", "opentype"];
+const SUPPORTED_EXTENSIONS: [&str; 3] = [".ttf", ".otf", ".ttc"];
+
+/// Origin of the document, family name and URL of a requested font
+type FontKey = (String, String, String);
+
+struct LoadedFont {
+    family: String,
+    data: Vec<u8>,
+}
+
+/// Loads the fonts of `@font-face` rules in the background and registers
+/// them with the text subsystem once they arrive.
+pub struct FontLoader {
+    loaded_tx: Sender<(FontKey, Option<LoadedFont>)>,
+    loaded_rx: Receiver<(FontKey, Option<LoadedFont>)>,
+    /// Fonts that have been requested
+    requested: HashSet<FontKey>,
+    /// Fonts that haven't finished loading
+    pending: HashSet<FontKey>,
+}
+
+impl FontLoader {
+    pub fn new() -> Self {
+        let (loaded_tx, loaded_rx) = flume::unbounded();
+        Self {
+            loaded_tx,
+            loaded_rx,
+            requested: HashSet::new(),
+            pending: HashSet::new(),
+        }
+    }
+
+    /// Stop waiting for the fonts that are still loading. They are requested
+    /// again by the next page that uses them.
+    pub fn cancel(&mut self) {
+        let (loaded_tx, loaded_rx) = flume::unbounded();
+        self.loaded_tx = loaded_tx;
+        self.loaded_rx = loaded_rx;
+
+        let pending = std::mem::take(&mut self.pending);
+        self.requested.retain(|key| !pending.contains(key));
+    }
+
+    /// Start loading the fonts of the rules of the document. Fonts that have
+    /// been loaded for its origin before are registered already and are not
+    /// requested again.
+    pub fn load_font_faces(&mut self, document: &Document) {
+        let origin = document.origin();
+        for font_face in document.font_faces() {
+            let source = match font_face.sources.iter().find(|source| is_supported(source)) {
+                Some(source) => source,
+                None => {
+                    log::warn!(
+                        "No supported font format for font family: {}",
+                        font_face.family
+                    );
+                    continue;
+                }
+            };
+
+            let url = match URLParser::parse(&source.url, None) {
+                Some(url) => url,
+                None => {
+                    log::warn!("Invalid font URL: {}", source.url);
+                    continue;
+                }
+            };
+
+            let key = (
+                origin.to_string(),
+                font_face.family.clone(),
+                source.url.clone(),
+            );
+            if !self.requested.insert(key.clone()) {
+                continue;
+            }
+
+            log::info!("Loading font {} from: {}", font_face.family, url);
+            self.pending.insert(key.clone());
+            let loaded_tx = self.loaded_tx.clone();
+            let family = font_face.family;
+            let resource_loader = ResourceLoader::current();
+            let document_url = document.base();
+
+            std::thread::spawn(move || {
+                let font = match resource_loader.load_subresource(
+                    &url,
+                    ResourceType::Font,
+                    document_url.as_ref(),
+                ) {
+                    Ok(data) => Some(LoadedFont { family, data }),
+                    Err(e) => {
+                        log::error!("Unable to load font: {} ({})", e, url);
+                        None
+                    }
+                };
+                // the page might have been closed or the load cancelled in the meantime
+                let _ = loaded_tx.send((key, font));
+            });
+        }
+    }
+
+    /// Whether there are fonts that haven't finished loading
+    pub fn is_loading(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Register the fonts that finished loading since the last call.
+    /// Returns true if a font was registered and the page needs a relayout.
+    pub fn register_loaded_fonts(&mut self) -> bool {
+        let mut has_new_fonts = false;
+
+        for (key, font) in self.loaded_rx.try_iter() {
+            self.pending.remove(&key);
+            let font = match font {
+                Some(font) => font,
+                None => continue,
+            };
+            match gfx::register_font(&font.family, font.data, &key.0) {
+                Ok(_) => has_new_fonts = true,
+                Err(e) => log::error!("{} (font family: {})", e, font.family),
+            }
+        }
+
+        has_new_fonts
+    }
+}
+
+/// WOFF and WOFF2 fonts are skipped so that the next source of the rule is used
+fn is_supported(source: &FontFaceSource) -> bool {
+    match &source.format {
+        Some(format) => SUPPORTED_FORMATS.contains(&format.as_str()),
+        None => {
+            let path = source.url.to_ascii_lowercase();
+            let path = path.split(['?', '#']).next().unwrap_or_default();
+            SUPPORTED_EXTENSIONS
+                .iter()
+                .any(|extension| path.ends_with(extension))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(url: &str, format: Option<&str>) -> FontFaceSource {
+        FontFaceSource {
+            url: url.to_string(),
+            format: format.map(|format| format.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_supported_font_sources() {
+        assert!(is_supported(&source("/font.ttf?v=2", None)));
+        assert!(is_supported(&source("/font", Some("opentype"))));
+        assert!(!is_supported(&source("/font.woff2", None)));
+        assert!(!is_supported(&source("/font.ttf", Some("woff2"))));
+    }
+}
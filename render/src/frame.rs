@@ -1,21 +1,178 @@
-use dom::node::NodePtr;
-use gfx::Bitmap;
-use shared::primitive::Size;
+use css::media_query::structs::MediaType;
+use dom::{
+    details,
+    elements::ElementData,
+    form_submission,
+    mutation::{MutationObserver, MutationObserverInit},
+    node::NodePtr,
+    node_id::NodeId,
+    select,
+    snapshot::DomSnapshot,
+    srcset::select_image_source,
+};
+use gfx::{Bitmap, TextMeasure};
+use layout::hit_test::{hit_test, node_box, node_rects, union};
+use layout::layout_box::LayoutBoxPtr;
+use layout::scroll::{scroll_by, scroll_target};
+use layout::text_fragments::{collect_text_fragments, hit_test_text, TextFragment, TextPosition};
+use painting::command::{DisplayList, DisplayListRecorder};
+use painting::{OverlayLayer, Painter};
+use shared::{
+    color::Color,
+    image::ImageBitmap,
+    primitive::{EdgeSizes, Point, Rect, Size},
+};
 
-use crate::pipeline::{Pipeline, PipelineRunOptions};
+use crate::editing::EditingCommand;
+use crate::find::FindSession;
+use crate::image_loader::LoadedImage;
+use crate::inspector::HitTestResult;
+use crate::invalidation::Invalidation;
+use crate::metadata::ViewportMeta;
+use crate::navigation::{self, NavigationRequest};
+use crate::pipeline::{calculate_layout, calculate_styles, Pipeline, PipelineRunOptions};
+use crate::preferences::Preferences;
+use crate::print::{render_pdf, PrintOptions};
+use crate::selection::Selection;
+use crate::spatial_navigation::{self, FocusDirection};
+use crate::text_control::TextControl;
+use crate::visibility::{is_lazy_loaded, ElementVisibility, VisibilityTracker};
+use crate::{Key, Modifiers};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Instant;
+use style_types::ContextualStyleSheet;
+use url::{parser::URLParser, Url};
+
+/// Smallest zoom that pages are shown at
+pub const MIN_ZOOM: f32 = 0.25;
+/// Largest zoom that pages are shown at
+pub const MAX_ZOOM: f32 = 5.;
+
+const NODE_HIGHLIGHT_COLOR: Color = Color {
+    r: 111,
+    g: 168,
+    b: 220,
+    a: 166,
+};
+
+const FOCUS_RING_COLOR: Color = Color {
+    r: 16,
+    g: 108,
+    b: 240,
+    a: 255,
+};
+/// Width of the focus ring in CSS pixels, drawn around the focused element
+const FOCUS_RING_WIDTH: f32 = 2.;
+/// Distance in CSS pixels the arrow keys scroll when there is no element
+/// to move the focus to in their direction
+const SPATIAL_NAVIGATION_SCROLL_STEP: f32 = 40.;
 
 pub struct Frame {
     document: Option<NodePtr>,
     size: Size,
+    zoom: f32,
+    device_pixel_ratio: f32,
     bitmap: Option<Bitmap>,
+    scroll_offset: Point,
+    overlay: OverlayLayer,
+    find_session: Option<FindSession>,
+    snapshot: Option<DomSnapshot>,
+    selection: Option<Selection>,
+    is_selecting: bool,
+    highlighted_node: Option<NodePtr>,
+    /// Form control receiving the keyboard input
+    focused_element: Option<NodePtr>,
+    /// Text of the focused element if it is a textarea
+    text_control: Option<TextControl>,
+    is_dragging_caret: bool,
+    /// Whether the caret is in its shown phase of blinking
+    is_caret_visible: bool,
+    /// Whether the arrow keys move the focus between links and form
+    /// controls, which then show a focus ring
+    spatial_navigation: bool,
+    /// Element under the mouse when the button was pressed
+    pressed_element: Option<NodePtr>,
+    /// Form submitted by the last input event, with its submitter
+    submit_request: Option<(NodePtr, Option<NodePtr>)>,
+    /// Select element clicked by the last input event
+    dropdown_request: Option<NodePtr>,
+    /// Navigation started by the last input event, like a clicked link
+    navigation_request: Option<NavigationRequest>,
+    /// Select element whose options are shown by the embedder
+    open_dropdown: Option<NodePtr>,
+    /// Viewport asked for by the shown document
+    viewport_meta: Option<ViewportMeta>,
+    /// When the document was shown, CSS animations are timed from it
+    timeline_origin: Instant,
+    preferences: Preferences,
+    /// User-agent styles for the preferences, shared by the documents shown
+    preferences_stylesheet: Rc<ContextualStyleSheet>,
+    /// Observer of the shown document, which sets `has_dom_mutations` when
+    /// the document changes
+    mutation_observer: MutationObserver,
+    has_dom_mutations: Rc<Cell<bool>>,
+    /// Elements in the viewport, updated after each layout and scroll
+    visibility: VisibilityTracker,
+    /// Source chosen for each image of the document, by node id
+    image_sources: HashMap<NodeId, String>,
+    /// Sources chosen since the last call to `take_image_requests`
+    image_requests: Vec<(NodeId, Url)>,
+    /// Content out of the viewport changed without being painted, so the
+    /// content that layers kept is stale
+    has_offscreen_damage: bool,
 }
 
 impl Frame {
-    pub fn new(init_size: Size) -> Self {
+    pub fn new(init_size: Size, device_pixel_ratio: f32) -> Self {
+        let device_pixel_ratio = if is_valid_device_pixel_ratio(device_pixel_ratio) {
+            device_pixel_ratio
+        } else {
+            log::warn!(
+                "Invalid device pixel ratio {}, falling back to 1",
+                device_pixel_ratio
+            );
+            1.
+        };
+
+        let has_dom_mutations = Rc::new(Cell::new(false));
+        let mutated = has_dom_mutations.clone();
+        let mutation_observer = MutationObserver::new(move |_| mutated.set(true));
+
         Self {
             document: None,
             size: init_size,
+            zoom: 1.,
+            device_pixel_ratio,
             bitmap: None,
+            scroll_offset: Point::default(),
+            overlay: OverlayLayer::new(),
+            find_session: None,
+            snapshot: None,
+            selection: None,
+            is_selecting: false,
+            highlighted_node: None,
+            focused_element: None,
+            text_control: None,
+            is_dragging_caret: false,
+            is_caret_visible: true,
+            spatial_navigation: false,
+            pressed_element: None,
+            viewport_meta: None,
+            timeline_origin: Instant::now(),
+            preferences_stylesheet: Preferences::default().stylesheet(),
+            preferences: Preferences::default(),
+            submit_request: None,
+            dropdown_request: None,
+            navigation_request: None,
+            open_dropdown: None,
+            mutation_observer,
+            has_dom_mutations,
+            visibility: VisibilityTracker::new(),
+            image_sources: HashMap::new(),
+            image_requests: Vec::new(),
+            has_offscreen_damage: false,
         }
     }
 
@@ -23,40 +180,1294 @@ impl Frame {
         self.size.clone()
     }
 
+    /// Size of the viewport in CSS pixels
+    pub fn viewport_size(&self) -> Size {
+        let scale = self.scale();
+        Size::new(self.size.width / scale, self.size.height / scale)
+    }
+
+    /// Number of device pixels per CSS pixel
+    pub fn scale(&self) -> f32 {
+        self.zoom * self.device_pixel_ratio * self.viewport_scale()
+    }
+
+    /// Scale asked for by the `<meta name=viewport>` of the document, which
+    /// the zoom of the user applies on top of
+    fn viewport_scale(&self) -> f32 {
+        match &self.viewport_meta {
+            Some(viewport) => viewport.scale(self.size.width / self.device_pixel_ratio),
+            None => 1.,
+        }
+    }
+
     pub async fn resize(&mut self, new_size: Size, pipeline: &mut Pipeline<'_>) {
-        self.size = new_size.clone();
+        self.size = new_size;
+        self.relayout(pipeline).await;
+    }
+
+    pub async fn set_zoom(&mut self, zoom: f32, pipeline: &mut Pipeline<'_>) {
+        if self.update_zoom(zoom) {
+            self.relayout(pipeline).await;
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Zoom keeping the part of the page at the point, in viewport
+    /// coordinates in device pixels, under it, like for a pinch
+    pub async fn zoom_at(&mut self, zoom: f32, point: &Point, pipeline: &mut Pipeline<'_>) {
+        let page_point = self.to_page_point(point);
+        if !self.update_zoom(zoom) {
+            return;
+        }
+        self.relayout(pipeline).await;
+
+        let root = match pipeline.layout_tree() {
+            Some(root) => root,
+            None => return,
+        };
+        let scale = self.scale();
+        let delta = Point::new(
+            page_point.x - point.x / scale - self.scroll_offset.x,
+            page_point.y - point.y / scale - self.scroll_offset.y,
+        );
+        if self.scroll_viewport_by(&root, &delta) {
+            self.update_overlay(pipeline);
+            self.recomposite(pipeline).await;
+        }
+    }
+
+    /// Clamp the zoom to the supported range. Returns false if the zoom is
+    /// not a number, which is ignored.
+    fn update_zoom(&mut self, zoom: f32) -> bool {
+        if !zoom.is_finite() {
+            log::warn!("Ignoring invalid zoom: {}", zoom);
+            return false;
+        }
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        true
+    }
+
+    /// Render the document with the settings of the user
+    pub async fn set_preferences(&mut self, preferences: Preferences, pipeline: &mut Pipeline<'_>) {
+        if preferences == self.preferences {
+            return;
+        }
+        let previous_stylesheet =
+            std::mem::replace(&mut self.preferences_stylesheet, preferences.stylesheet());
+        self.preferences = preferences;
+        self.apply_preferences_stylesheet(Some(&previous_stylesheet));
+        self.update_media_environment();
+        // The default font size and the stylesheet of the preferences change
+        // the styles whatever the media queries of the document
+        self.update_layout(true, pipeline).await;
+    }
+
+    /// Add the stylesheet of the preferences to the document, in place of
+    /// `previous` if it was added before
+    fn apply_preferences_stylesheet(&self, previous: Option<&Rc<ContextualStyleSheet>>) {
+        let document = match &self.document {
+            Some(document) => document.as_document(),
+            None => return,
+        };
+        if let Some(previous) = previous {
+            document.remove_stylesheet(previous);
+        }
+        document.insert_user_agent_stylesheet(self.preferences_stylesheet.clone());
+        document.set_default_font_size(self.preferences.default_font_size);
+    }
+
+    pub async fn set_device_pixel_ratio(
+        &mut self,
+        device_pixel_ratio: f32,
+        pipeline: &mut Pipeline<'_>,
+    ) {
+        if self.update_device_pixel_ratio(device_pixel_ratio) {
+            self.relayout(pipeline).await;
+        }
+    }
+
+    /// Returns false if the ratio is not a positive number, which is ignored.
+    fn update_device_pixel_ratio(&mut self, device_pixel_ratio: f32) -> bool {
+        if !is_valid_device_pixel_ratio(device_pixel_ratio) {
+            log::warn!(
+                "Ignoring invalid device pixel ratio: {}",
+                device_pixel_ratio
+            );
+            return false;
+        }
+        self.device_pixel_ratio = device_pixel_ratio;
+        true
+    }
+
+    /// Show the document. Returns false if its loading was cancelled while
+    /// rendering, in which case the frame keeps showing the previous document.
+    pub async fn set_document(&mut self, document: NodePtr, pipeline: &mut Pipeline<'_>) -> bool {
+        let previous_viewport_meta =
+            std::mem::replace(&mut self.viewport_meta, ViewportMeta::of(&document));
+        let previous_document = self.document.replace(document);
+        let previous_scroll_offset = std::mem::take(&mut self.scroll_offset);
+        let previous_timeline_origin = std::mem::replace(&mut self.timeline_origin, Instant::now());
+        self.overlay.clear();
+        self.apply_preferences_stylesheet(None);
+        self.update_media_environment();
+
+        let is_rendered = self
+            .render_frame(
+                pipeline,
+                PipelineRunOptions {
+                    skip_style_calculation: false,
+                    skip_layout_calculation: false,
+                    skip_layer_painting: false,
+                },
+            )
+            .await;
+
+        if !is_rendered {
+            // The previous layout and bitmap are untouched
+            if let Some(document) = std::mem::replace(&mut self.document, previous_document) {
+                document.teardown();
+            }
+            self.scroll_offset = previous_scroll_offset;
+            self.viewport_meta = previous_viewport_meta;
+            self.timeline_origin = previous_timeline_origin;
+            self.update_overlay(pipeline);
+            return false;
+        }
+
+        self.find_session = None;
+        self.snapshot = None;
+        self.selection = None;
+        self.is_selecting = false;
+        self.highlighted_node = None;
+        self.focused_element = None;
+        self.text_control = None;
+        self.is_dragging_caret = false;
+        self.pressed_element = None;
+        self.submit_request = None;
+        self.dropdown_request = None;
+        self.navigation_request = None;
+        self.open_dropdown = None;
+        self.mutation_observer.disconnect();
+        if let Some(document) = &self.document {
+            self.mutation_observer
+                .observe(document, MutationObserverInit::all());
+        }
+        self.has_dom_mutations.set(false);
+        // Free the unloaded document, whose nodes refer to each other
+        if let Some(previous_document) = previous_document {
+            if !Rc::ptr_eq(&previous_document, self.document.as_ref().unwrap()) {
+                previous_document.teardown();
+            }
+        }
+        true
+    }
+
+    /// Search the frame for `query`, moving to the next or previous match if the
+    /// query is unchanged. Returns the 1-based current match index and the total.
+    pub async fn find_text(
+        &mut self,
+        query: String,
+        forward: bool,
+        pipeline: &mut Pipeline<'_>,
+    ) -> (usize, usize) {
+        if query.is_empty() {
+            self.find_session = None;
+        } else {
+            let session = match self.find_session.take() {
+                Some(session) if session.query() == query => session,
+                _ => {
+                    let mut session = FindSession::new(query);
+                    session.refresh(pipeline.layout_tree());
+                    session
+                }
+            };
+            self.find_session = Some(session);
+        }
+
+        let mut current_match = None;
+        if let Some(session) = &mut self.find_session {
+            session.step(forward);
+            current_match = session.current_match().cloned();
+        }
+        if let Some(rect) = current_match {
+            self.scroll_into_view(&rect);
+        }
+
+        self.update_overlay(pipeline);
+        self.repaint(pipeline).await;
+
+        self.find_session
+            .as_ref()
+            .map(|session| session.result())
+            .unwrap_or((0, 0))
+    }
+
+    /// Start a new selection at the point, or move the caret of the
+    /// textarea under it. Returns true if a previous non-empty selection has
+    /// been cleared or if a caret is shown or hidden.
+    pub async fn mouse_down(&mut self, point: Point, pipeline: &mut Pipeline<'_>) -> bool {
+        let had_selection = self
+            .selection
+            .take()
+            .map(|selection| !selection.is_collapsed())
+            .unwrap_or(false);
+        let had_text_control = self.text_control.is_some();
+
+        if let Some(position) = self.hit_test_text(&point, pipeline) {
+            self.selection = Some(Selection::new(position));
+            self.is_selecting = true;
+        }
+
+        self.pressed_element = self.element_at(&point, pipeline);
+        self.focused_element = self
+            .pressed_element
+            .as_ref()
+            .and_then(|element| find_ancestor_or_self(element, &is_focusable));
+        self.update_text_control(pipeline);
+
+        let page_point = self.to_page_point(&point);
+        if let Some(text_control) = &mut self.text_control {
+            // Dragging selects the text of the textarea instead of the page
+            self.selection = None;
+            self.is_selecting = false;
+            self.is_dragging_caret = true;
+            self.is_caret_visible = true;
+            text_control.move_caret_to_point(&page_point, false);
+        }
+
+        let is_changed = had_selection || had_text_control || self.text_control.is_some();
+        if is_changed {
+            self.update_overlay(pipeline);
+            self.repaint(pipeline).await;
+        }
+        is_changed
+    }
+
+    /// Extend the selection being dragged. Returns true if the selection changed.
+    pub async fn mouse_move(&mut self, point: Point, pipeline: &mut Pipeline<'_>) -> bool {
+        if self.is_dragging_caret {
+            let page_point = self.to_page_point(&point);
+            if let Some(text_control) = &mut self.text_control {
+                text_control.move_caret_to_point(&page_point, true);
+                self.update_overlay(pipeline);
+                self.repaint(pipeline).await;
+                return true;
+            }
+        }
+        if !self.is_selecting {
+            return false;
+        }
+
+        let position = self.hit_test_text(&point, pipeline);
+        match (&mut self.selection, position) {
+            (Some(selection), Some(position)) => selection.set_focus(position),
+            _ => return false,
+        }
+
+        self.update_overlay(pipeline);
+        self.repaint(pipeline).await;
+        true
+    }
+
+    pub async fn mouse_up(&mut self, point: Point, pipeline: &mut Pipeline<'_>) -> bool {
+        let is_changed = self.mouse_move(point.clone(), pipeline).await;
+        self.is_selecting = false;
+        self.is_dragging_caret = false;
+
+        // A click is a press and a release on the same element
+        let pressed_element = self.pressed_element.take();
+        let released_element = self.element_at(&point, pipeline);
+        let clicked = |predicate: &dyn Fn(&NodePtr) -> bool| {
+            let pressed = find_ancestor_or_self(pressed_element.as_ref()?, predicate)?;
+            let released = find_ancestor_or_self(released_element.as_ref()?, predicate)?;
+            let is_clicked =
+                pressed.id() == released.id() && !form_submission::is_disabled(&released);
+            is_clicked.then_some(released)
+        };
+
+        if let Some(button) = clicked(&form_submission::is_submit_button) {
+            self.submit_request =
+                form_submission::form_owner(&button).map(|form| (form, Some(button)));
+        }
+        self.dropdown_request = clicked(&is_drop_down_select);
+        if let Some(link) = clicked(&navigation::is_link) {
+            self.navigation_request = NavigationRequest::from_link(&link, true);
+        }
+
+        if let Some(summary) = clicked(&is_details_summary) {
+            self.toggle_details(&summary, pipeline).await;
+            return true;
+        }
+        is_changed
+    }
+
+    /// Handle a key pressed while the frame has keyboard focus. Returns
+    /// true if the text or the caret of the focused textarea changed, or if
+    /// the focus moved.
+    pub async fn key_down(
+        &mut self,
+        key: Key,
+        modifiers: Modifiers,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        let direction = FocusDirection::from_key(&key).filter(|_| self.spatial_navigation);
+        if let Some(text_control) = &mut self.text_control {
+            let command = match EditingCommand::from_key(&key, &modifiers) {
+                Some(command) => command,
+                None => return false,
+            };
+            let caret = text_control.caret_rect();
+            text_control.execute(&command);
+            // Arrow keys that can't move the caret further move the focus
+            // out of the textarea
+            let is_caret_stuck = direction.is_some() && text_control.caret_rect() == caret;
+            if !is_caret_stuck {
+                self.is_caret_visible = true;
+                self.update_overlay(pipeline);
+                self.repaint(pipeline).await;
+                return true;
+            }
+        }
+        if let Some(direction) = direction {
+            return self.move_focus(direction, pipeline).await;
+        }
+
+        let element = match (key, self.focused_element.clone()) {
+            (Key::Enter, Some(element)) => element,
+            _ => return false,
+        };
+        if is_details_summary(&element) {
+            self.toggle_details(&element, pipeline).await;
+            return true;
+        }
+        if navigation::is_link(&element) {
+            self.navigation_request = NavigationRequest::from_link(&element, true);
+            return false;
+        }
+        self.submit_request = form_submission::implicit_submission(&element);
+        false
+    }
+
+    /// Move the arrow keys between links and form controls instead of
+    /// scrolling or moving the caret
+    pub async fn set_spatial_navigation(&mut self, enabled: bool, pipeline: &mut Pipeline<'_>) {
+        if self.spatial_navigation == enabled {
+            return;
+        }
+        self.spatial_navigation = enabled;
+        if self.focused_element.is_some() {
+            self.update_overlay(pipeline);
+            self.repaint(pipeline).await;
+        }
+    }
+
+    /// Focus the link or form control nearest to the focused element in the
+    /// direction, or to the edge of the viewport if nothing is focused. The
+    /// viewport scrolls when there is none. Returns true if the frame changed.
+    async fn move_focus(&mut self, direction: FocusDirection, pipeline: &mut Pipeline<'_>) -> bool {
+        let (document, root) = match (self.document.clone(), pipeline.layout_tree()) {
+            (Some(document), Some(root)) => (document, root),
+            _ => return false,
+        };
+        let candidates = spatial_navigation::collect_candidates(&document, &root, &|node| {
+            is_focusable(node) || navigation::is_link(node)
+        });
+        let from = self
+            .focused_element
+            .as_ref()
+            .and_then(|element| {
+                candidates
+                    .iter()
+                    .find(|(candidate, _)| candidate.id() == element.id())
+            })
+            .map(|(_, rect)| rect.clone())
+            .unwrap_or_else(|| self.viewport_edge(direction));
+        let rects: Vec<Rect> = candidates.iter().map(|(_, rect)| rect.clone()).collect();
+
+        match spatial_navigation::find_nearest(&from, direction, &rects) {
+            Some(index) => {
+                let (element, rect) = candidates[index].clone();
+                self.focused_element = Some(element);
+                self.update_text_control(pipeline);
+                self.is_caret_visible = true;
+                self.scroll_into_view(&rect);
+            }
+            None => {
+                let step = SPATIAL_NAVIGATION_SCROLL_STEP;
+                let delta = match direction {
+                    FocusDirection::Left => Point::new(-step, 0.),
+                    FocusDirection::Right => Point::new(step, 0.),
+                    FocusDirection::Up => Point::new(0., -step),
+                    FocusDirection::Down => Point::new(0., step),
+                };
+                if !self.scroll_viewport_by(&root, &delta) {
+                    return false;
+                }
+            }
+        }
+        self.update_overlay(pipeline);
+        self.repaint(pipeline).await;
+        true
+    }
+
+    /// Edge of the viewport that the focus enters from when moving in the
+    /// direction, as an empty rect in page coordinates
+    fn viewport_edge(&self, direction: FocusDirection) -> Rect {
+        let viewport = self.viewport_size();
+        let (x, y) = (self.scroll_offset.x, self.scroll_offset.y);
+        match direction {
+            FocusDirection::Left => Rect::new(x + viewport.width, y, 0., viewport.height),
+            FocusDirection::Right => Rect::new(x, y, 0., viewport.height),
+            FocusDirection::Up => Rect::new(x, y + viewport.height, viewport.width, 0.),
+            FocusDirection::Down => Rect::new(x, y, viewport.width, 0.),
+        }
+    }
+
+    /// Open or close the details element of the summary
+    async fn toggle_details(&mut self, summary: &NodePtr, pipeline: &mut Pipeline<'_>) {
+        if let Some(details) = details::details_of_summary(summary) {
+            details::toggle(&details);
+            self.update_layout(true, pipeline).await;
+        }
+    }
+
+    pub fn has_caret(&self) -> bool {
+        self.text_control.is_some()
+    }
+
+    /// Show or hide the caret of the focused textarea. Returns true if the
+    /// frame changed.
+    pub async fn set_caret_visible(
+        &mut self,
+        is_visible: bool,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        if self.is_caret_visible == is_visible {
+            return false;
+        }
+        self.is_caret_visible = is_visible;
+        if self.text_control.is_none() {
+            return false;
+        }
+        self.update_overlay(pipeline);
+        self.repaint(pipeline).await;
+        true
+    }
+
+    /// Bounds of the caret of the focused textarea in viewport coordinates,
+    /// in device pixels, whether it is in its shown phase of blinking or
+    /// not. `None` without a caret, like when text is selected.
+    pub fn caret_rect(&mut self) -> Option<Rect> {
+        let rect = self.text_control.as_mut()?.caret_rect()?;
+        Some(self.to_viewport_rect(&rect))
+    }
+
+    /// Bounds of the selected text, a rect per line, in viewport
+    /// coordinates, in device pixels
+    pub fn selection_rects(&mut self, pipeline: &Pipeline<'_>) -> Vec<Rect> {
+        let rects = if let Some(text_control) = &mut self.text_control {
+            text_control.selection_rects()
+        } else if let Some(selection) = &self.selection {
+            selection.rects(&self.text_fragments(pipeline))
+        } else {
+            Vec::new()
+        };
+        rects
+            .iter()
+            .map(|rect| self.to_viewport_rect(rect))
+            .collect()
+    }
+
+    /// Form submitted by the last input event, with its submitter
+    pub fn take_submit_request(&mut self) -> Option<(NodePtr, Option<NodePtr>)> {
+        self.submit_request.take()
+    }
+
+    /// Navigation started by the last input event
+    pub fn take_navigation_request(&mut self) -> Option<NavigationRequest> {
+        self.navigation_request.take()
+    }
+
+    /// Select element clicked by the last input event, with its border box
+    /// in viewport coordinates, in device pixels. The select element is
+    /// expected to show its options until `choose_dropdown_option`.
+    pub fn take_dropdown_request(&mut self, pipeline: &Pipeline<'_>) -> Option<(NodePtr, Rect)> {
+        let select = self.dropdown_request.take()?;
+        let root = pipeline.layout_tree()?;
+        let bounds = node_rects(&root, &select)
+            .into_iter()
+            .reduce(|a, b| union(&a, &b))?;
+        self.open_dropdown = Some(select.clone());
+        Some((select, self.to_viewport_rect(&bounds)))
+    }
+
+    /// Choose the option at `index` of the select element showing its
+    /// options, or close them without a choice. Returns true if the
+    /// selected option changed.
+    pub async fn choose_dropdown_option(
+        &mut self,
+        index: Option<usize>,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        let select = match self.open_dropdown.take() {
+            Some(select) => select,
+            None => return false,
+        };
+        let previous = select::selected_options(&select);
+        let is_changed = match index {
+            Some(index) => {
+                select::select_option(&select, index)
+                    && previous.first().map(|option| option.id())
+                        != select::options(&select)
+                            .get(index)
+                            .map(|option| option.id())
+            }
+            None => false,
+        };
+
+        if is_changed {
+            // The select element is as wide as its widest option whichever
+            // is selected, so only the label needs to be painted again
+            self.repaint(pipeline).await;
+        }
+        is_changed
+    }
+
+    pub fn selected_text(&self, pipeline: &Pipeline<'_>) -> String {
+        if let Some(text_control) = &self.text_control {
+            return text_control.selected_text();
+        }
+        match &self.selection {
+            Some(selection) => selection.text(&self.text_fragments(pipeline)),
+            None => String::new(),
+        }
+    }
+
+    /// Show the frame in the video element with the id. The document is laid
+    /// out again when the frame size changes, since the video may be sized by
+    /// it, and only painted again otherwise. Returns false if the document
+    /// has no such video element.
+    pub async fn update_video_frame(
+        &mut self,
+        node_id: NodeId,
+        frame: ImageBitmap,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        let video = match self
+            .document()
+            .and_then(|document| document.node_by_id(node_id))
+        {
+            Some(video) => video,
+            None => return false,
+        };
+        let video_data = match video.as_element_opt().map(|element| element.data()) {
+            Some(ElementData::Video(video_data)) => video_data,
+            _ => return false,
+        };
+        let is_resized = !video_data
+            .current_frame()
+            .is_some_and(|current| is_same_size(&current, &frame));
+        video_data.set_current_frame(Some(frame));
+
+        let invalidation = match is_resized {
+            true => Invalidation::Relayout,
+            false => self.repaint_region(&video, pipeline),
+        };
+        self.invalidate(invalidation, pipeline).await;
+        true
+    }
+
+    /// Show the image loaded for an `img` element, unless another source
+    /// has been chosen for the element since. An image that failed to load
+    /// leaves the previous one shown.
+    pub fn image_loaded(&mut self, image: LoadedImage, pipeline: &Pipeline<'_>) -> Invalidation {
+        if self.image_sources.get(&image.node_id) != Some(&image.source) {
+            return Invalidation::None;
+        }
+        let element = match self
+            .document()
+            .and_then(|document| document.node_by_id(image.node_id))
+        {
+            Some(element) => element,
+            None => return Invalidation::None,
+        };
+        let (image_data, bitmap) = match (element.as_element().data(), image.bitmap) {
+            (ElementData::Image(image_data), Some(bitmap)) => (image_data, bitmap),
+            _ => return Invalidation::None,
+        };
+        let is_resized = !image_data
+            .current_image()
+            .is_some_and(|current| is_same_size(&current, &bitmap));
+        image_data.set_current_image(Some(bitmap));
+
+        match is_resized {
+            true => Invalidation::Relayout,
+            false => self.repaint_region(&element, pipeline),
+        }
+    }
+
+    /// Update the rendering for content that changed in the background.
+    /// Content out of the viewport is painted once it is scrolled into
+    /// view. Returns true if the frame was rendered again.
+    pub async fn invalidate(
+        &mut self,
+        invalidation: Invalidation,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        if !invalidation.is_visible_in(&self.viewport_rect()) {
+            self.has_offscreen_damage |= invalidation != Invalidation::None;
+            return false;
+        }
+        match invalidation {
+            Invalidation::Relayout => self.relayout(pipeline).await,
+            _ => self.repaint(pipeline).await,
+        }
+        true
+    }
+
+    /// Region of the boxes of the element, which has to be painted again
+    fn repaint_region(&self, element: &NodePtr, pipeline: &Pipeline<'_>) -> Invalidation {
+        let region = pipeline.layout_tree().and_then(|root| {
+            node_rects(&root, element)
+                .into_iter()
+                .reduce(|a, b| union(&a, &b))
+        });
+        match region {
+            Some(region) => Invalidation::Repaint(region),
+            None => Invalidation::None,
+        }
+    }
+
+    /// Scroll by `delta` the innermost scroll container at the point that
+    /// can scroll in that direction, or the viewport if there is none. The
+    /// point and `delta` are in device pixels. Returns true if anything
+    /// scrolled.
+    pub async fn scroll(
+        &mut self,
+        point: &Point,
+        delta: &Point,
+        pipeline: &mut Pipeline<'_>,
+    ) -> bool {
+        let root = match pipeline.layout_tree() {
+            Some(root) => root,
+            None => return false,
+        };
+        let scale = self.scale();
+        let delta = Point::new(delta.x / scale, delta.y / scale);
+
+        let is_scrolled = match scroll_target(&root, &self.to_page_point(point), &delta) {
+            Some(scroll_container) => scroll_by(&scroll_container, &delta) != Point::default(),
+            None => self.scroll_viewport_by(&root, &delta),
+        };
+        if is_scrolled {
+            // Boxes in scroll containers are placed by their scroll offsets,
+            // so the layout doesn't change, and neither does the content
+            // of the layers of scroll containers
+            self.visibility.update(&root, &self.viewport_rect());
+            self.select_image_sources(&root);
+            self.update_overlay(pipeline);
+            self.recomposite(pipeline).await;
+        }
+        is_scrolled
+    }
+
+    /// Where the elements with the ids are relative to the viewport. Ids of
+    /// nodes that aren't elements of the document are left out.
+    pub fn query_visibility(
+        &self,
+        node_ids: &[NodeId],
+        pipeline: &Pipeline<'_>,
+    ) -> Vec<ElementVisibility> {
+        let document = match self.document() {
+            Some(document) => document,
+            None => return Vec::new(),
+        };
+        let root = pipeline.layout_tree();
+        node_ids
+            .iter()
+            .filter_map(|node_id| document.node_by_id(*node_id))
+            .filter(|node| node.is_element())
+            .map(|element| {
+                let bounds = root.as_ref().and_then(|root| {
+                    node_rects(root, &element)
+                        .into_iter()
+                        .reduce(|a, b| union(&a, &b))
+                });
+                ElementVisibility {
+                    node_id: element.id(),
+                    is_visible: self.visibility.is_visible(&element.id()),
+                    is_near_viewport: self.visibility.is_near_viewport(&element.id()),
+                    bounds: bounds.map(|bounds| self.to_viewport_rect(&bounds)),
+                }
+            })
+            .collect()
+    }
+
+    /// Lazily loaded elements that came near the viewport, whose resources
+    /// should be loaded now
+    pub fn take_lazy_loads(&mut self) -> Vec<NodePtr> {
+        self.visibility.take_lazy_loads()
+    }
+
+    /// Sources of images that were chosen since the last call and should
+    /// be loaded, with the ids of their elements
+    pub fn take_image_requests(&mut self) -> Vec<(NodeId, Url)> {
+        std::mem::take(&mut self.image_requests)
+    }
+
+    /// Choose the source of each image of the document from its `srcset`
+    /// for the current scale, viewport and layout width, and request the
+    /// sources that changed. `loading=lazy` images wait until they are
+    /// near the viewport.
+    fn select_image_sources(&mut self, root: &LayoutBoxPtr) {
+        let document = match self.document() {
+            Some(document) => document,
+            None => return,
+        };
+        let environment = document.as_document().media_environment();
+        let base = document.as_document().base();
+        let scale = self.scale();
+
+        let mut previous_sources = std::mem::take(&mut self.image_sources);
+        for image in document.get_elements_by_tag_name("img") {
+            let id = image.id();
+            let previous_source = previous_sources.remove(&id);
+            if is_lazy_loaded(&image) && !self.visibility.is_near_viewport(&id) {
+                if let Some(source) = previous_source {
+                    self.image_sources.insert(id, source);
+                }
+                continue;
+            }
+
+            // Finding the box is only worth it for `sizes=auto`
+            let has_auto_size = image
+                .as_element()
+                .get_attribute("sizes")
+                .is_some_and(|sizes| sizes.to_ascii_lowercase().contains("auto"));
+            let layout_width = match has_auto_size {
+                true => node_box(root, &image).map(|image_box| image_box.content_size().width),
+                false => None,
+            };
+            let url = select_image_source(&image, &environment, scale, layout_width)
+                .and_then(|source| URLParser::parse(&source, base.clone()));
+            let url = match url {
+                Some(url) => url,
+                None => continue,
+            };
+            let source = url.as_str();
+            if previous_source.as_ref() != Some(&source) {
+                self.image_requests.push((id, url));
+            }
+            self.image_sources.insert(id, source);
+        }
+    }
+
+    /// Highlight the boxes of `node`, or remove the highlight if `None`
+    pub async fn highlight_node(&mut self, node: Option<NodePtr>, pipeline: &mut Pipeline<'_>) {
+        self.highlighted_node = node;
+        self.update_overlay(pipeline);
+        self.repaint(pipeline).await;
+    }
+
+    pub fn document(&self) -> Option<NodePtr> {
+        self.document.clone()
+    }
+
+    pub fn bitmap(&self) -> Option<&Bitmap> {
+        self.bitmap.as_ref()
+    }
+
+    /// Draws of the page at its current scroll offset, in CSS pixels
+    pub fn display_list(&self, pipeline: &Pipeline<'_>) -> Option<DisplayList> {
+        let layout_tree = pipeline.layout_tree()?;
+        let mut painter = Painter::new(DisplayListRecorder::new());
+        painter.resize(self.viewport_size());
+        painter.set_scroll_offset(self.scroll_offset.clone());
+        painter.paint(&layout_tree);
+        Some(painter.graphics_mut().take_display_list())
+    }
+
+    /// Thread-safe snapshot of the document, reused until the document changes
+    pub fn snapshot(&mut self) -> Option<DomSnapshot> {
+        let document = self.document()?;
+
+        let is_up_to_date =
+            matches!(&self.snapshot, Some(snapshot) if !snapshot.is_stale(&document));
+        if !is_up_to_date {
+            self.snapshot = Some(DomSnapshot::capture(&document));
+        }
+
+        self.snapshot.clone()
+    }
+
+    /// Lay the document out on pages with its print styles and paint them
+    /// as a PDF document. The screen layout is left untouched.
+    pub async fn print_to_pdf(&self, options: &PrintOptions) -> Option<Vec<u8>> {
+        let document = self.document()?;
+        let screen_environment = document.as_document().media_environment();
+
+        let content_size = options.content_size();
+        let mut print_environment = screen_environment.clone();
+        print_environment.media_type = MediaType::Print;
+        print_environment.viewport_width = content_size.width;
+        print_environment.viewport_height = content_size.height;
+        document
+            .as_document()
+            .set_media_environment(print_environment);
+        calculate_styles(document.clone());
+
+        let pdf = match calculate_layout(document.clone(), &content_size) {
+            Some(layout_tree) => {
+                let pdf = render_pdf(&layout_tree, options).await;
+                layout_tree.teardown();
+                Some(pdf)
+            }
+            None => None,
+        };
+
+        // The screen layout reads the computed styles of the nodes
+        document
+            .as_document()
+            .set_media_environment(screen_environment);
+        calculate_styles(document);
+        pdf
+    }
+
+    fn scroll_into_view(&mut self, rect: &Rect) {
+        let viewport_height = self.viewport_size().height;
+        let viewport_top = self.scroll_offset.y;
+        let viewport_bottom = viewport_top + viewport_height;
+
+        if rect.y < viewport_top || rect.y + rect.height > viewport_bottom {
+            // Center the rect vertically in the viewport
+            self.scroll_offset.y = f32::max(rect.y - (viewport_height - rect.height) / 2., 0.);
+        }
+    }
+
+    /// Scroll the viewport by `delta` in CSS pixels, within the document.
+    /// Returns true if it scrolled.
+    fn scroll_viewport_by(&mut self, root: &LayoutBoxPtr, delta: &Point) -> bool {
+        let viewport = self.viewport_size();
+        let max_x = f32::max(root.margin_box_width() - viewport.width, 0.);
+        let max_y = f32::max(root.margin_box_height() - viewport.height, 0.);
+        let scroll_offset = Point::new(
+            (self.scroll_offset.x + delta.x).clamp(0., max_x),
+            (self.scroll_offset.y + delta.y).clamp(0., max_y),
+        );
+        let is_scrolled = scroll_offset != self.scroll_offset;
+        self.scroll_offset = scroll_offset;
+        is_scrolled
+    }
+
+    fn text_fragments(&self, pipeline: &Pipeline<'_>) -> Vec<TextFragment> {
+        pipeline
+            .layout_tree()
+            .map(|root| collect_text_fragments(&root))
+            .unwrap_or_default()
+    }
+
+    /// Element at the point in viewport coordinates, in device pixels
+    pub fn element_at(&self, point: &Point, pipeline: &Pipeline<'_>) -> Option<NodePtr> {
+        let root = pipeline.layout_tree()?;
+        hit_test(&root, &self.to_page_point(point))
+    }
+
+    /// Element at the point in viewport coordinates, in device pixels, with
+    /// its bounds in the same coordinates
+    pub fn hit_test(&self, point: &Point, pipeline: &Pipeline<'_>) -> Option<HitTestResult> {
+        let root = pipeline.layout_tree()?;
+        let element = hit_test(&root, &self.to_page_point(point))?;
+        let bounds = node_rects(&root, &element)
+            .into_iter()
+            .reduce(|a, b| union(&a, &b))
+            .unwrap_or_else(|| Rect::new(0., 0., 0., 0.));
+        Some(HitTestResult::new(&element, self.to_viewport_rect(&bounds)))
+    }
+
+    fn hit_test_text(&self, point: &Point, pipeline: &Pipeline<'_>) -> Option<TextPosition> {
+        let fragments = self.text_fragments(pipeline);
+        hit_test_text(
+            &fragments,
+            &self.to_page_point(point),
+            &mut TextMeasure::new(),
+        )
+    }
+
+    /// The viewport in page coordinates, in CSS pixels
+    fn viewport_rect(&self) -> Rect {
+        let viewport = self.viewport_size();
+        Rect::new(
+            self.scroll_offset.x,
+            self.scroll_offset.y,
+            viewport.width,
+            viewport.height,
+        )
+    }
+
+    /// Convert a point in the viewport, in device pixels, to page coordinates
+    fn to_page_point(&self, point: &Point) -> Point {
+        let scale = self.scale();
+        let mut page_point = Point::new(point.x / scale, point.y / scale);
+        page_point.translate(self.scroll_offset.x, self.scroll_offset.y);
+        page_point
+    }
+
+    /// Convert a rect in page coordinates to the viewport, in device pixels
+    fn to_viewport_rect(&self, rect: &Rect) -> Rect {
+        let scale = self.scale();
+        Rect::new(
+            (rect.x - self.scroll_offset.x) * scale,
+            (rect.y - self.scroll_offset.y) * scale,
+            rect.width * scale,
+            rect.height * scale,
+        )
+    }
+
+    fn update_overlay(&mut self, pipeline: &Pipeline<'_>) {
+        self.overlay.clear();
+        if let Some(selection) = &self.selection {
+            let fragments = self.text_fragments(pipeline);
+            selection.paint_highlights(&fragments, &mut self.overlay);
+        }
+        if let Some(session) = &self.find_session {
+            session.paint_highlights(&mut self.overlay);
+        }
+        if let (Some(node), Some(root)) = (&self.highlighted_node, pipeline.layout_tree()) {
+            for rect in node_rects(&root, node) {
+                self.overlay.add_highlight(rect, NODE_HIGHLIGHT_COLOR);
+            }
+        }
+        if let (true, Some(element), Some(root)) = (
+            self.spatial_navigation,
+            &self.focused_element,
+            pipeline.layout_tree(),
+        ) {
+            let edges = EdgeSizes::uniform(FOCUS_RING_WIDTH);
+            for rect in node_rects(&root, element) {
+                self.overlay.add_outline(
+                    rect.add_outer_edges(&edges),
+                    FOCUS_RING_WIDTH,
+                    FOCUS_RING_COLOR,
+                );
+            }
+        }
+        if let Some(text_control) = &mut self.text_control {
+            text_control.paint_selection(&mut self.overlay);
+            if self.is_caret_visible {
+                text_control.paint_caret(&mut self.overlay);
+            }
+        }
+    }
+
+    /// Edit the text of the focused element if it is a textarea
+    fn update_text_control(&mut self, pipeline: &Pipeline<'_>) {
+        self.text_control = match (&self.focused_element, pipeline.layout_tree()) {
+            (Some(element), Some(root)) => TextControl::new(element, &root),
+            _ => None,
+        };
+    }
+
+    pub async fn relayout(&mut self, pipeline: &mut Pipeline<'_>) {
+        let needs_restyle = self.update_media_environment() | self.take_dom_mutations();
+        self.update_layout(needs_restyle, pipeline).await;
+    }
+
+    /// Style and lay the document out again, like when an iframe got its
+    /// document
+    pub async fn restyle(&mut self, pipeline: &mut Pipeline<'_>) {
+        self.update_layout(true, pipeline).await;
+    }
+
+    /// Lay the document out again, after computing its styles again if
+    /// `needs_restyle` is true
+    async fn update_layout(&mut self, needs_restyle: bool, pipeline: &mut Pipeline<'_>) {
+        // Selection positions refer to the text fragments of the old layout
+        self.selection = None;
+        self.is_selecting = false;
+        self.update_overlay(pipeline);
+        self.render_frame(
+            pipeline,
+            PipelineRunOptions {
+                skip_style_calculation: !needs_restyle,
+                skip_layout_calculation: false,
+                skip_layer_painting: false,
+            },
+        )
+        .await;
+
+        // Match and highlight rects are stale after relayout, and so are the
+        // lines of the textarea
+        if let Some(session) = &mut self.find_session {
+            session.refresh(pipeline.layout_tree());
+        }
+        self.update_text_control(pipeline);
+        self.is_dragging_caret = false;
+        if self.find_session.is_some()
+            || self.highlighted_node.is_some()
+            || self.text_control.is_some()
+            || (self.spatial_navigation && self.focused_element.is_some())
+        {
+            self.update_overlay(pipeline);
+            self.repaint(pipeline).await;
+        }
+    }
+
+    /// Whether the document has CSS animations in progress
+    pub fn has_running_animations(&self) -> bool {
+        self.document
+            .as_ref()
+            .is_some_and(|document| document.as_document().has_running_animations())
+    }
+
+    /// Style and lay the document out again at the current time of its
+    /// animations
+    pub async fn update_animations(&mut self, pipeline: &mut Pipeline<'_>) {
+        self.update_layout(true, pipeline).await;
+    }
+
+    async fn repaint(&mut self, pipeline: &mut Pipeline<'_>) {
         self.render_frame(
             pipeline,
             PipelineRunOptions {
                 skip_style_calculation: true,
+                skip_layout_calculation: true,
+                skip_layer_painting: false,
             },
         )
         .await;
     }
 
-    pub async fn set_document(&mut self, document: NodePtr, pipeline: &mut Pipeline<'_>) {
-        self.document = Some(document.clone());
+    /// Paint the frame with the layers of scroll containers moved to their
+    /// scroll offsets, compositing the content they kept unless content
+    /// changed out of the viewport since they were painted
+    pub async fn recomposite(&mut self, pipeline: &mut Pipeline<'_>) {
         self.render_frame(
             pipeline,
             PipelineRunOptions {
-                skip_style_calculation: false,
+                skip_style_calculation: true,
+                skip_layout_calculation: true,
+                skip_layer_painting: !self.has_offscreen_damage,
             },
         )
         .await;
     }
 
-    pub fn document(&self) -> Option<NodePtr> {
-        self.document.clone()
+    /// Whether the document changed since the last call, in which case it
+    /// needs to be restyled
+    fn take_dom_mutations(&self) -> bool {
+        if let Some(document) = &self.document {
+            document.as_document().notify_mutation_observers();
+        }
+        self.has_dom_mutations.replace(false)
     }
 
-    pub fn bitmap(&self) -> Option<&Bitmap> {
-        self.bitmap.as_ref()
+    /// Evaluate the media queries of the document against the current
+    /// viewport. Returns true if the document needs to be restyled.
+    fn update_media_environment(&self) -> bool {
+        let document = match &self.document {
+            Some(document) => document.as_document(),
+            None => return false,
+        };
+        let viewport = self.viewport_size();
+        let mut environment = document.media_environment();
+        environment.viewport_width = viewport.width;
+        environment.viewport_height = viewport.height;
+        self.preferences.apply_to_environment(&mut environment);
+        document.set_media_environment(environment)
     }
 
-    async fn render_frame(&mut self, pipeline: &mut Pipeline<'_>, opts: PipelineRunOptions) {
+    /// Returns false if the rendering was cancelled
+    async fn render_frame(
+        &mut self,
+        pipeline: &mut Pipeline<'_>,
+        opts: PipelineRunOptions,
+    ) -> bool {
         if let Some(document) = self.document() {
-            let bitmap = pipeline.run(document, &self.size(), opts).await;
-            self.bitmap = Some(bitmap);
+            let is_laid_out = !opts.skip_layout_calculation;
+            let is_layer_painted = !opts.skip_layer_painting;
+            let animation_time = self.timeline_origin.elapsed().as_secs_f64() * 1000.;
+            document.as_document().set_animation_time(animation_time);
+            let bitmap = pipeline
+                .run(
+                    document,
+                    &self.size(),
+                    self.scale(),
+                    &self.scroll_offset,
+                    &self.overlay,
+                    opts,
+                )
+                .await;
+            match bitmap {
+                Some(bitmap) => self.bitmap = Some(bitmap),
+                None => return false,
+            }
+            if is_layer_painted {
+                self.has_offscreen_damage = false;
+            }
+
+            // Boxes move when the document is laid out or scrolled
+            let viewport = self.viewport_rect();
+            if is_laid_out || self.visibility.viewport() != Some(&viewport) {
+                if let Some(root) = pipeline.layout_tree() {
+                    self.visibility.update(&root, &viewport);
+                    // The scale, the viewport and the widths of images
+                    // decide their sources
+                    self.select_image_sources(&root);
+                }
+            }
         }
+        true
+    }
+}
+
+/// Elements that receive keyboard input when clicked
+fn is_focusable(node: &NodePtr) -> bool {
+    match node.as_element_opt() {
+        Some(element) => {
+            let is_form_control = matches!(
+                element.tag_name().as_str(),
+                "input" | "textarea" | "select" | "button"
+            ) && !form_submission::is_disabled(node);
+            is_form_control || is_details_summary(node)
+        }
+        None => false,
+    }
+}
+
+fn is_details_summary(node: &NodePtr) -> bool {
+    details::details_of_summary(node).is_some()
+}
+
+fn is_drop_down_select(node: &NodePtr) -> bool {
+    node.as_element_opt()
+        .is_some_and(|element| element.tag_name() == "select")
+        && select::is_drop_down(node)
+}
+
+fn find_ancestor_or_self(node: &NodePtr, predicate: &dyn Fn(&NodePtr) -> bool) -> Option<NodePtr> {
+    let mut current = Some(node.clone());
+    while let Some(node) = current {
+        if predicate(&node) {
+            return Some(node);
+        }
+        current = node.parent().map(NodePtr);
+    }
+    None
+}
+
+fn is_same_size(a: &ImageBitmap, b: &ImageBitmap) -> bool {
+    a.width() == b.width() && a.height() == b.height()
+}
+
+fn is_valid_device_pixel_ratio(device_pixel_ratio: f32) -> bool {
+    device_pixel_ratio.is_finite() && device_pixel_ratio > 0.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_scales_viewport_and_mouse() {
+        let mut frame = Frame::new(Size::new(800., 600.), 2.);
+        assert!(frame.update_zoom(1.5));
+        assert_eq!(frame.scale(), 3.);
+        assert_eq!(frame.viewport_size(), Size::new(800. / 3., 200.));
+        assert_eq!(
+            frame.to_page_point(&Point::new(300., 150.)),
+            Point::new(100., 50.)
+        );
+        assert_eq!(
+            frame.to_viewport_rect(&Rect::new(100., 50., 10., 10.)),
+            Rect::new(300., 150., 30., 30.)
+        );
+
+        assert!(frame.update_zoom(100.));
+        assert_eq!(frame.zoom, MAX_ZOOM);
+        assert!(!frame.update_zoom(f32::NAN));
+        assert!(!frame.update_zoom(f32::INFINITY));
+        assert_eq!(frame.zoom, MAX_ZOOM);
+    }
+
+    #[test]
+    fn test_device_pixel_ratio_scales_viewport() {
+        let mut frame = Frame::new(Size::new(800., 600.), 2.);
+        assert_eq!(frame.viewport_size(), Size::new(400., 300.));
+
+        assert!(frame.update_device_pixel_ratio(1.5));
+        assert_eq!(frame.scale(), 1.5);
+        assert_eq!(
+            frame.to_page_point(&Point::new(300., 150.)),
+            Point::new(200., 100.)
+        );
+
+        assert!(!frame.update_device_pixel_ratio(0.));
+        assert!(!frame.update_device_pixel_ratio(-1.));
+        assert!(!frame.update_device_pixel_ratio(f32::NAN));
+        assert!(!frame.update_device_pixel_ratio(f32::INFINITY));
+        assert_eq!(frame.scale(), 1.5);
+
+        let frame = Frame::new(Size::new(800., 600.), 0.);
+        assert_eq!(frame.scale(), 1.);
+    }
+
+    #[test]
+    fn test_viewport_meta_scales_viewport() {
+        let mut frame = Frame::new(Size::new(800., 600.), 2.);
+        frame.viewport_meta = Some(ViewportMeta::parse("width=800"));
+        assert_eq!(frame.scale(), 1.);
+        assert_eq!(frame.viewport_size(), Size::new(800., 600.));
+
+        assert!(frame.update_zoom(2.));
+        assert_eq!(frame.viewport_size(), Size::new(400., 300.));
+
+        frame.viewport_meta = Some(ViewportMeta::parse("width=device-width, initial-scale=1"));
+        assert_eq!(frame.scale(), 4.);
+    }
+
+    #[test]
+    fn test_select_image_sources_for_scale() {
+        let url = URLParser::parse("http://example.com/", None).unwrap();
+        let document = crate::frame_loader::parse_document(
+            "<img srcset='a.png 1x, b.png 2x'>\
+             <img src=photo.png srcset='400.jpg 400w, 800.jpg 800w' sizes='400px'>\
+             <img loading=lazy srcset='lazy.png'>",
+            url,
+            None,
+            &crate::context::EngineContext::new(),
+        );
+        let mut frame = Frame::new(Size::new(800., 600.), 1.);
+        frame.document = Some(document.clone());
+        frame.update_media_environment();
+        calculate_styles(document.clone());
+        let root = calculate_layout(document, &frame.viewport_size()).unwrap();
+        let sources = |frame: &mut Frame| -> Vec<String> {
+            frame
+                .take_image_requests()
+                .iter()
+                .map(|(_, url)| url.as_str())
+                .collect()
+        };
+
+        // The lazy image isn't near the viewport before it is tracked
+        frame.select_image_sources(&root);
+        assert_eq!(
+            sources(&mut frame),
+            ["http://example.com/a.png", "http://example.com/400.jpg"]
+        );
+        frame.select_image_sources(&root);
+        assert!(sources(&mut frame).is_empty());
+
+        // Denser displays fetch denser sources
+        frame.update_device_pixel_ratio(2.);
+        frame.update_media_environment();
+        frame.select_image_sources(&root);
+        assert_eq!(
+            sources(&mut frame),
+            ["http://example.com/b.png", "http://example.com/800.jpg"]
+        );
     }
 }
@@ -0,0 +1,206 @@
+use dom::{
+    document::Document,
+    elements::ElementData,
+    lifecycle::DocumentReadyState,
+    node::{Node, NodeData, NodePtr},
+};
+use loader::{LoadError, ResourceLoader, ResourceType};
+use shared::{byte_string::ByteString, cancellation::CancellationToken, tree_node::TreeNode};
+use url::{parser::URLParser, Url};
+
+use crate::about::error_page;
+use crate::context::EngineContext;
+use crate::preload_scanner::prefetch_subresources;
+use crate::visibility::is_lazy_loaded;
+
+/// Maximum number of nested iframes whose documents are loaded
+const MAX_FRAME_DEPTH: usize = 8;
+
+/// Parse an HTML document. Its stylesheets are loaded as they are parsed.
+pub fn parse_document(
+    html: &str,
+    base_url: Url,
+    cancellation: Option<CancellationToken>,
+    context: &EngineContext,
+) -> NodePtr {
+    let document = NodePtr(TreeNode::new(Node::new(
+        NodeData::Document(Document::new()),
+    )));
+
+    document
+        .as_document()
+        .insert_user_agent_stylesheet(context.user_agent_stylesheet());
+
+    log::debug!("Base URL: {}", base_url);
+    document.as_document().set_base(Some(base_url));
+    document.as_document().set_load_cancellation(cancellation);
+
+    let tokenizer = html::tokenizer::Tokenizer::new(html.chars());
+    let tree_builder = html::tree_builder::TreeBuilder::new(tokenizer, document);
+    tree_builder.run()
+}
+
+/// Load the documents of the iframes of the document and of the documents
+/// they load, each from the `src` URL of its iframe. The iframes of the
+/// document with `loading=lazy` are left for `load_lazy_frame`.
+pub fn load_nested_documents(
+    document: &NodePtr,
+    cancellation: &CancellationToken,
+    context: &EngineContext,
+) {
+    load_frames(document, cancellation, context, 0);
+}
+
+/// Load the document of a lazily loaded iframe of the shown document, once
+/// it is near the viewport. Its own iframes are loaded right away.
+pub fn load_lazy_frame(iframe: &NodePtr, context: &EngineContext) {
+    let base = iframe
+        .owner_document()
+        .and_then(|document| NodePtr(document).as_document().base());
+    load_frame(iframe, base, &CancellationToken::default(), context, 0);
+}
+
+fn load_frames(
+    document: &NodePtr,
+    cancellation: &CancellationToken,
+    context: &EngineContext,
+    depth: usize,
+) {
+    let base = document.as_document().base();
+    let mut iframes = Vec::new();
+    collect_iframes(document, &mut iframes);
+
+    for iframe in iframes {
+        if cancellation.is_cancelled() {
+            return;
+        }
+        // Only the shown document tracks which of its elements are near
+        // the viewport
+        if depth == 0 && is_lazy_loaded(&iframe) {
+            continue;
+        }
+        load_frame(&iframe, base.clone(), cancellation, context, depth);
+    }
+}
+
+fn load_frame(
+    iframe: &NodePtr,
+    base: Option<Url>,
+    cancellation: &CancellationToken,
+    context: &EngineContext,
+    depth: usize,
+) {
+    let element = iframe.as_element();
+    let src = element.attributes().borrow().get_str("src");
+    let url = match URLParser::parse(&src, base.clone()) {
+        Some(url) => url,
+        None => {
+            log::info!("Empty or invalid iframe URL, ignoring");
+            return;
+        }
+    };
+    if depth >= MAX_FRAME_DEPTH {
+        log::warn!("Ignoring iframe nested too deeply: {}", url);
+        return;
+    }
+
+    // The base is the URL of the document the iframe is in
+    let html = match ResourceLoader::current().load_subresource(
+        &url,
+        ResourceType::Document,
+        base.as_ref(),
+    ) {
+        Ok(bytes) => ByteString::from_html(&bytes).to_string(),
+        // Blocked frames, like ads, are left empty
+        Err(e @ LoadError::Blocked(_)) => {
+            log::info!("Iframe blocked: {} ({})", e, url);
+            return;
+        }
+        Err(e) => {
+            log::error!("Unable to load iframe: {} ({})", e, url);
+            error_page(&url, &e)
+        }
+    };
+
+    log::info!("Loading iframe from: {}", url);
+    prefetch_subresources(&html, &url);
+    let content_document = parse_document(&html, url, None, context);
+    load_frames(&content_document, cancellation, context, depth + 1);
+    // Only the iframes of nested documents are waited for
+    content_document
+        .as_document()
+        .lifecycle()
+        .advance_to(DocumentReadyState::Complete);
+
+    if let ElementData::IFrame(iframe) = element.data() {
+        iframe.set_content_document(Some(content_document));
+    }
+}
+
+/// URL of the icon of the document, from its first `<link rel="icon">` or
+/// `/favicon.ico` at the root of its origin
+pub fn favicon_url(document: &NodePtr) -> Option<Url> {
+    let base = document.as_document().base();
+    let icon_link = document
+        .get_elements_by_tag_name("link")
+        .into_iter()
+        .find(|link| {
+            let rel = link.as_element().attributes().borrow().get_str("rel");
+            rel.split_ascii_whitespace()
+                .any(|keyword| keyword.eq_ignore_ascii_case("icon"))
+        });
+
+    match icon_link {
+        Some(link) => {
+            let href = link.as_element().attributes().borrow().get_str("href");
+            URLParser::parse(&href, base)
+        }
+        None => match base {
+            Some(base) if base.scheme == "http" || base.scheme == "https" => {
+                URLParser::parse("/favicon.ico", Some(base))
+            }
+            _ => None,
+        },
+    }
+}
+
+fn collect_iframes(node: &NodePtr, iframes: &mut Vec<NodePtr>) {
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        if let Some(ElementData::IFrame(_)) = child.as_element_opt().map(|e| e.data()) {
+            iframes.push(child.clone());
+        }
+        collect_iframes(&child, iframes);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn favicon(html: &str, base_url: &str) -> Option<String> {
+        let document = parse_document(
+            html,
+            URLParser::parse(base_url, None).unwrap(),
+            None,
+            &EngineContext::new(),
+        );
+        favicon_url(&document).map(|url| url.as_str())
+    }
+
+    #[test]
+    fn test_favicon_url() {
+        assert_eq!(
+            favicon(
+                "<link rel='Shortcut Icon' href='/icons/site.png'>",
+                "http://example.com/docs/page.html"
+            ),
+            Some("http://example.com/icons/site.png".to_string())
+        );
+        assert_eq!(
+            favicon("<p>No icon</p>", "http://example.com/docs/page.html"),
+            Some("http://example.com/favicon.ico".to_string())
+        );
+        assert_eq!(favicon("<p>No icon</p>", "file:///tmp/page.html"), None);
+    }
+}
@@ -0,0 +1,147 @@
+use std::ops::Deref;
+use std::sync::Arc;
+
+use gfx::Bitmap;
+
+/// Handle to a frame in a `FramePool`.
+///
+/// Cloning the handle doesn't copy the pixels. The underlying buffer is
+/// reused by the pool once every handle to it has been dropped.
+#[derive(Clone)]
+pub struct SharedFrame(Arc<Bitmap>);
+
+impl Deref for SharedFrame {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for SharedFrame {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Number of buffers kept once they are free again
+const PREFERRED_BUFFER_COUNT: usize = 2;
+
+/// Number of buffers allocated at most, so that a consumer holding on to
+/// frames can't make the pool grow without limit
+const MAX_BUFFER_COUNT: usize = 4;
+
+/// Pool of frame buffers shared with the consumers of the render engine.
+///
+/// Usually two buffers are enough: one is held by the consumer for display
+/// while the other receives the next frame. A new buffer is only allocated
+/// when every buffer is still in use, up to `MAX_BUFFER_COUNT`. The extra
+/// buffers are released once they are free again.
+pub struct FramePool {
+    buffers: Vec<Arc<Bitmap>>,
+}
+
+impl FramePool {
+    pub fn new() -> Self {
+        Self {
+            buffers: Vec::with_capacity(2),
+        }
+    }
+
+    /// Copy the bitmap into a free buffer and return a handle to it. Returns
+    /// `None` if every buffer is still in use and the pool is full, in which
+    /// case the frame should be published again later.
+    pub fn publish(&mut self, bitmap: &[u8]) -> Option<SharedFrame> {
+        self.trim();
+
+        let free_buffer = self.buffers.iter().position(is_free);
+
+        let index = match free_buffer {
+            Some(index) => index,
+            None if self.buffers.len() >= MAX_BUFFER_COUNT => {
+                log::debug!("Every frame buffer is in use, dropping frame");
+                return None;
+            }
+            None => {
+                log::debug!("Allocating frame buffer #{}", self.buffers.len() + 1);
+                self.buffers
+                    .push(Arc::new(Bitmap::with_capacity(bitmap.len())));
+                self.buffers.len() - 1
+            }
+        };
+
+        let buffer = &mut self.buffers[index];
+        let data = Arc::get_mut(buffer).expect("Frame buffer is still in use");
+        data.clear();
+        data.extend_from_slice(bitmap);
+
+        Some(SharedFrame(buffer.clone()))
+    }
+
+    /// Release the free buffers allocated beyond `PREFERRED_BUFFER_COUNT`
+    fn trim(&mut self) {
+        let mut excess = self.buffers.len().saturating_sub(PREFERRED_BUFFER_COUNT);
+        self.buffers.retain(|buffer| {
+            if excess > 0 && is_free(buffer) {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    pub fn buffer_count(&self) -> usize {
+        self.buffers.len()
+    }
+}
+
+fn is_free(buffer: &Arc<Bitmap>) -> bool {
+    Arc::strong_count(buffer) == 1
+}
+
+impl Default for FramePool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let mut pool = FramePool::new();
+
+        let first = pool.publish(&[1, 2, 3]).unwrap();
+        let second = pool.publish(&[4, 5, 6]).unwrap();
+        assert_eq!(&*first, &[1, 2, 3]);
+        assert_eq!(&*second, &[4, 5, 6]);
+        assert_eq!(pool.buffer_count(), 2);
+
+        let first_ptr = first.as_ptr();
+        drop(first);
+
+        let third = pool.publish(&[7, 8, 9]).unwrap();
+        assert_eq!(&*third, &[7, 8, 9]);
+        assert_eq!(third.as_ptr(), first_ptr);
+        assert_eq!(pool.buffer_count(), 2);
+    }
+
+    #[test]
+    fn test_pool_is_capped_and_trimmed() {
+        let mut pool = FramePool::new();
+
+        let mut frames: Vec<_> = (0..MAX_BUFFER_COUNT as u8)
+            .map(|i| pool.publish(&[i]).unwrap())
+            .collect();
+        assert_eq!(pool.buffer_count(), MAX_BUFFER_COUNT);
+        assert!(pool.publish(&[42]).is_none());
+        assert_eq!(pool.buffer_count(), MAX_BUFFER_COUNT);
+
+        frames.truncate(1);
+        let latest = pool.publish(&[42]).unwrap();
+        assert_eq!(&*latest, &[42]);
+        assert_eq!(pool.buffer_count(), PREFERRED_BUFFER_COUNT);
+    }
+}
@@ -0,0 +1,63 @@
+use std::time::Instant;
+
+/// Time spent in each stage of the rendering pipeline, in milliseconds
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    pub parse_ms: f32,
+    pub style_ms: f32,
+    pub layout_ms: f32,
+    pub paint_ms: f32,
+    pub raster_ms: f32,
+    /// Time from the start of the page load to its first frame, `None`
+    /// until the page has been painted
+    pub first_contentful_paint_ms: Option<f32>,
+}
+
+impl FrameStats {
+    pub fn total_ms(&self) -> f32 {
+        self.parse_ms + self.style_ms + self.layout_ms + self.paint_ms + self.raster_ms
+    }
+
+    /// Add the time of another pipeline run. A frame can be the result of
+    /// several runs when invalidations are coalesced.
+    pub fn add(&mut self, other: &FrameStats) {
+        self.parse_ms += other.parse_ms;
+        self.style_ms += other.style_ms;
+        self.layout_ms += other.layout_ms;
+        self.paint_ms += other.paint_ms;
+        self.raster_ms += other.raster_ms;
+        self.first_contentful_paint_ms = self
+            .first_contentful_paint_ms
+            .or(other.first_contentful_paint_ms);
+    }
+}
+
+/// Milliseconds elapsed since `start`
+pub(crate) fn elapsed_ms(start: Instant) -> f32 {
+    start.elapsed().as_secs_f32() * 1000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_of_coalesced_runs() {
+        let mut stats = FrameStats {
+            parse_ms: 4.,
+            style_ms: 2.,
+            ..Default::default()
+        };
+        stats.add(&FrameStats {
+            style_ms: 1.,
+            layout_ms: 3.,
+            paint_ms: 0.5,
+            raster_ms: 1.5,
+            ..Default::default()
+        });
+
+        assert_eq!(stats.style_ms, 3.);
+        assert_eq!(stats.layout_ms, 3.);
+        assert_eq!(stats.total_ms(), 12.);
+    }
+}
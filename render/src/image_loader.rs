@@ -0,0 +1,78 @@
+use dom::node_id::NodeId;
+use flume::{Receiver, Sender};
+use loader::ResourceLoader;
+use shared::image::ImageBitmap;
+use url::Url;
+
+/// An image that finished loading for an `img` element
+pub struct LoadedImage {
+    pub node_id: NodeId,
+    /// Source the image was loaded from
+    pub source: String,
+    /// `None` if the image couldn't be loaded or decoded
+    pub bitmap: Option<ImageBitmap>,
+}
+
+/// Loads and decodes the images of a page in the background
+pub struct ImageLoader {
+    loaded_tx: Sender<LoadedImage>,
+    loaded_rx: Receiver<LoadedImage>,
+    /// Number of images that haven't finished loading
+    pending: usize,
+}
+
+impl ImageLoader {
+    pub fn new() -> Self {
+        let (loaded_tx, loaded_rx) = flume::unbounded();
+        Self {
+            loaded_tx,
+            loaded_rx,
+            pending: 0,
+        }
+    }
+
+    /// Stop waiting for the images that are still loading, like when
+    /// another page is shown
+    pub fn cancel(&mut self) {
+        let (loaded_tx, loaded_rx) = flume::unbounded();
+        self.loaded_tx = loaded_tx;
+        self.loaded_rx = loaded_rx;
+        self.pending = 0;
+    }
+
+    /// Start loading the image of the element from the URL, for the document
+    /// at `document_url`
+    pub fn load(&mut self, node_id: NodeId, url: Url, document_url: Option<Url>) {
+        self.pending += 1;
+        let loaded_tx = self.loaded_tx.clone();
+        let resource_loader = ResourceLoader::current();
+
+        std::thread::spawn(move || {
+            let bitmap = match resource_loader.load_image(&url, document_url.as_ref()) {
+                Ok(image) => ImageBitmap::from_rgba(image.width, image.height, image.data),
+                Err(e) => {
+                    log::error!("Unable to load image: {} ({})", e, url);
+                    None
+                }
+            };
+            // the page might have been closed or the load cancelled in the meantime
+            let _ = loaded_tx.send(LoadedImage {
+                node_id,
+                source: url.as_str(),
+                bitmap,
+            });
+        });
+    }
+
+    /// Whether there are images that haven't finished loading
+    pub fn is_loading(&self) -> bool {
+        self.pending > 0
+    }
+
+    /// Images that finished loading since the last call
+    pub fn take_loaded(&mut self) -> Vec<LoadedImage> {
+        let loaded: Vec<LoadedImage> = self.loaded_rx.try_iter().collect();
+        self.pending = self.pending.saturating_sub(loaded.len());
+        loaded
+    }
+}
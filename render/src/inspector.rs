@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use dom::{node::NodePtr, node_id::NodeId, snapshot::ComputedStyleSnapshot};
+use shared::primitive::Rect;
+use url::parser::URLParser;
+
+/// An element and its resolved styles, as reported to embedders
+#[derive(Debug, Clone)]
+pub struct InspectedNode {
+    pub node_id: NodeId,
+    pub tag_name: String,
+    pub id: Option<String>,
+    pub class_name: String,
+    pub attributes: HashMap<String, String>,
+    pub computed_style: ComputedStyleSnapshot,
+}
+
+impl InspectedNode {
+    pub fn new(element: &NodePtr) -> Self {
+        let element_data = element.as_element();
+        Self {
+            node_id: element.id(),
+            tag_name: element_data.tag_name(),
+            id: element_data.id(),
+            class_name: element_data.class_list().borrow().value(),
+            attributes: HashMap::clone(&element_data.attributes().borrow()),
+            computed_style: element.computed_style_snapshot(),
+        }
+    }
+}
+
+/// The element at a point, as reported by `InputEvent::HitTest`
+#[derive(Debug, Clone)]
+pub struct HitTestResult {
+    /// Id of the element, to look it up with `NodePtr::node_by_id`
+    pub node_id: NodeId,
+    pub tag: String,
+    /// URL of the link that contains the element
+    pub href: Option<String>,
+    /// URL of the image, when the element is an image
+    pub src: Option<String>,
+    /// Border box of the element in viewport coordinates, in device pixels
+    pub bounding_rect: Rect,
+}
+
+impl HitTestResult {
+    pub fn new(element: &NodePtr, bounding_rect: Rect) -> Self {
+        let element_data = element.as_element();
+        let tag = element_data.tag_name();
+        let src = match tag.as_str() {
+            "img" => resolve_url(element, "src"),
+            _ => None,
+        };
+        let href = std::iter::once(element.clone())
+            .chain(std::iter::successors(
+                element.parent().map(NodePtr),
+                |node| node.parent().map(NodePtr),
+            ))
+            .find(|node| {
+                node.as_element_opt().is_some_and(|ancestor| {
+                    ancestor.tag_name() == "a" && ancestor.has_attribute("href")
+                })
+            })
+            .and_then(|link| resolve_url(&link, "href"));
+
+        Self {
+            node_id: element.id(),
+            tag,
+            href,
+            src,
+            bounding_rect,
+        }
+    }
+}
+
+/// Resolve a URL attribute against the base URL of the document
+fn resolve_url(element: &NodePtr, attribute: &str) -> Option<String> {
+    let value = element
+        .as_element()
+        .attributes()
+        .borrow()
+        .get_str(attribute);
+    let base = element
+        .owner_document()
+        .and_then(|document| NodePtr(document).as_document().base());
+    URLParser::parse(&value, base).map(|url| url.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::dom_creator::{document, element};
+    use url::parser::URLParser;
+
+    use super::*;
+
+    #[test]
+    fn test_hit_test_result() {
+        let document = document();
+        document
+            .as_document()
+            .set_base(URLParser::parse("http://example.com/docs/", None));
+        let image = element("img", document.clone(), vec![]);
+        let link = element("a", document.clone(), vec![image.clone()]);
+        let body = element(
+            "body",
+            document.clone(),
+            vec![element("p", document.clone(), vec![]), link.clone()],
+        );
+        document.append_child(body.0.clone());
+        image.as_element().set_attribute("src", "cat.png");
+        link.as_element().set_attribute("href", "/index.html");
+
+        let result = HitTestResult::new(&image, Rect::new(0., 0., 10., 10.));
+        assert_eq!(result.node_id, image.id());
+        assert_eq!(
+            document.node_by_id(result.node_id).map(|node| node.id()),
+            Some(image.id())
+        );
+        assert_eq!(result.tag, "img");
+        assert_eq!(
+            result.href.as_deref(),
+            Some("http://example.com/index.html")
+        );
+        assert_eq!(
+            result.src.as_deref(),
+            Some("http://example.com/docs/cat.png")
+        );
+
+        let result = HitTestResult::new(&body, Rect::new(0., 0., 10., 10.));
+        assert_eq!(result.node_id, body.id());
+        assert_eq!(result.href, None);
+    }
+}
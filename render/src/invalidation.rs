@@ -0,0 +1,75 @@
+//! What the completion of a background load changes in a page, shared by
+//! every resource that arrives after the page is shown: web fonts, images
+//! and the frames of videos. Resources that change the size of their boxes
+//! need a new layout, the others only need their boxes painted again.
+
+use layout::hit_test::union;
+use shared::primitive::Rect;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Invalidation {
+    /// Nothing shown changed
+    None,
+    /// The content of boxes changed but not their size. The rect is the
+    /// region of the boxes in page coordinates.
+    Repaint(Rect),
+    /// The natural size of a box changed, or text is measured differently,
+    /// like after a web font was loaded
+    Relayout,
+}
+
+impl Invalidation {
+    /// Invalidation covering both, so that resources that arrived together
+    /// are shown by a single update
+    pub fn merge(self, other: Invalidation) -> Invalidation {
+        match (self, other) {
+            (Invalidation::Relayout, _) | (_, Invalidation::Relayout) => Invalidation::Relayout,
+            (Invalidation::Repaint(a), Invalidation::Repaint(b)) => {
+                Invalidation::Repaint(union(&a, &b))
+            }
+            (Invalidation::None, other) | (other, Invalidation::None) => other,
+        }
+    }
+
+    /// Whether the region to repaint is in the viewport, in page
+    /// coordinates
+    pub fn is_visible_in(&self, viewport: &Rect) -> bool {
+        match self {
+            Invalidation::None => false,
+            Invalidation::Repaint(region) => {
+                let visible = region.intersection(viewport);
+                visible.width > 0. && visible.height > 0.
+            }
+            Invalidation::Relayout => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_invalidations() {
+        let repaint = |x, y| Invalidation::Repaint(Rect::new(x, y, 10., 10.));
+        assert_eq!(
+            repaint(0., 0.).merge(repaint(20., 5.)),
+            Invalidation::Repaint(Rect::new(0., 0., 30., 15.))
+        );
+        assert_eq!(Invalidation::None.merge(repaint(0., 0.)), repaint(0., 0.));
+        assert_eq!(
+            repaint(0., 0.).merge(Invalidation::Relayout),
+            Invalidation::Relayout
+        );
+        assert_eq!(
+            Invalidation::None.merge(Invalidation::None),
+            Invalidation::None
+        );
+
+        let viewport = Rect::new(0., 100., 800., 600.);
+        assert!(repaint(0., 95.).is_visible_in(&viewport));
+        assert!(!repaint(0., 0.).is_visible_in(&viewport));
+        assert!(!repaint(0., 700.).is_visible_in(&viewport));
+        assert!(Invalidation::Relayout.is_visible_in(&viewport));
+    }
+}
@@ -1,6 +1,60 @@
+mod about;
+mod channel;
+mod context;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+mod dropdown;
+mod editing;
 mod engine;
+mod find;
+mod font_loader;
 mod frame;
+mod frame_loader;
+mod frame_pool;
+mod frame_stats;
+mod image_loader;
+mod inspector;
+mod invalidation;
+mod load_progress;
+mod memory;
+mod metadata;
+mod navigation;
 pub mod page;
 mod pipeline;
+mod preferences;
+mod preload_scanner;
+mod print;
+mod scheduler;
+mod selection;
+mod spatial_navigation;
+mod text_control;
+mod touch;
+mod visibility;
 
+pub use about::error_page;
+pub use channel::{ChannelConfig, EngineChannels, FrameMailbox};
+pub use context::EngineContext;
+pub use css::media_query::structs::ColorScheme;
+pub use dom::lifecycle::DocumentReadyState;
+pub use dom::node_id::NodeId;
+pub use dropdown::DropdownOption;
 pub use engine::*;
+pub use frame::{MAX_ZOOM, MIN_ZOOM};
+pub use frame_loader::parse_document;
+pub use frame_pool::{FramePool, SharedFrame};
+pub use frame_stats::FrameStats;
+pub use gpu::{GpuProcess, GpuProcessError};
+pub use inspector::{HitTestResult, InspectedNode};
+pub use loader::NetworkSettings;
+pub use memory::MemoryReport;
+pub use metadata::PageMetadata;
+pub use navigation::{NavigationPolicy, NavigationRequest, NavigationTarget};
+pub use painting::command::DisplayList;
+pub use pipeline::{calculate_layout, calculate_styles};
+pub use preferences::Preferences;
+pub use print::PrintOptions;
+pub use scheduler::FramePacing;
+pub use shared::image::ImageBitmap;
+pub use shared::memory::MemoryUsage;
+pub use touch::TouchPoint;
+pub use visibility::ElementVisibility;
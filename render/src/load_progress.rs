@@ -0,0 +1,77 @@
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use flume::Sender;
+use loader::{LoadProgress, ResourceLoader};
+
+use super::engine::OutputEvent;
+
+/// How often the progress is checked while a page loads
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reports the bytes received by the loads of a page while it loads. The
+/// reports come from a thread of their own, since the engine waits for
+/// stylesheets and frames while it parses a document.
+pub struct ProgressReporter {
+    /// Progress last reported, `None` when no page is loading
+    reported: Arc<Mutex<Option<LoadProgress>>>,
+}
+
+impl ProgressReporter {
+    pub fn new() -> Self {
+        Self {
+            reported: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Report the progress with `event_emitter` until the reporter is
+    /// dropped or nobody receives the events anymore
+    pub fn spawn(&self, event_emitter: Sender<OutputEvent>) {
+        let reported = Arc::downgrade(&self.reported);
+        std::thread::spawn(move || {
+            let resource_loader = ResourceLoader::current();
+            while !event_emitter.is_disconnected() {
+                std::thread::sleep(PROGRESS_REPORT_INTERVAL);
+                let reported = match Weak::upgrade(&reported) {
+                    Some(reported) => reported,
+                    None => return,
+                };
+                let mut reported = reported.lock().unwrap();
+                if let Some(reported) = reported.as_mut() {
+                    report(reported, &resource_loader, &event_emitter);
+                }
+            }
+        });
+    }
+
+    /// Count the loads from now on, as a page starts loading
+    pub fn start(&self) {
+        ResourceLoader::current().reset_progress();
+        *self.reported.lock().unwrap() = Some(LoadProgress::default());
+    }
+
+    /// Report the last progress of the page, which finished loading
+    pub fn finish(&self, event_emitter: &Sender<OutputEvent>) {
+        if let Some(mut reported) = self.reported.lock().unwrap().take() {
+            report(&mut reported, &ResourceLoader::current(), event_emitter);
+        }
+    }
+}
+
+/// Report the progress when it changed since it was last reported
+fn report(
+    reported: &mut LoadProgress,
+    resource_loader: &ResourceLoader,
+    event_emitter: &Sender<OutputEvent>,
+) {
+    let progress = resource_loader.progress();
+    if progress != *reported {
+        // the embedder might have stopped listening in the meantime
+        let _ = event_emitter.send(OutputEvent::LoadProgress {
+            loaded_bytes: progress.loaded_bytes,
+            total_bytes: progress.total_bytes,
+            resource_count: progress.resource_count,
+        });
+        *reported = progress;
+    }
+}
@@ -0,0 +1,154 @@
+use std::mem::size_of;
+
+use dom::node::NodePtr;
+use layout::layout_box::LayoutBoxPtr;
+use shared::memory::MemoryUsage;
+use style_types::{Property, PseudoElement, Value};
+
+/// Memory held by a page, per subsystem, for embedders to show in pages
+/// like about:memory or to find leaks across navigations. Sizes are
+/// estimates of the allocations of each subsystem, in bytes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryReport {
+    /// Nodes of the document, of its shadow trees and of the documents
+    /// loaded in its iframes, with their text and attributes
+    pub dom_nodes: MemoryUsage,
+    /// Computed values of the properties of the nodes
+    pub computed_styles: MemoryUsage,
+    pub layout_boxes: MemoryUsage,
+    /// Texture the glyphs are rasterized into, on the GPU. Empty when the
+    /// frames are rasterized by a GPU process.
+    pub glyph_atlas: MemoryUsage,
+    /// Textures of the images drawn in the latest frame, on the GPU. Empty
+    /// when the frames are rasterized by a GPU process.
+    pub images: MemoryUsage,
+}
+
+impl MemoryReport {
+    pub fn total_bytes(&self) -> usize {
+        self.dom_nodes.bytes
+            + self.computed_styles.bytes
+            + self.layout_boxes.bytes
+            + self.glyph_atlas.bytes
+            + self.images.bytes
+    }
+
+    /// Add the nodes of the tree and their computed styles
+    pub fn measure_dom(&mut self, node: &NodePtr) {
+        let mut bytes = size_of::<dom::node::Node>();
+        if let Some(text) = node.as_text_opt() {
+            bytes += text.get_data().len();
+        }
+        if let Some(element) = node.as_element_opt() {
+            bytes += element
+                .attributes()
+                .borrow()
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>();
+        }
+        self.dom_nodes += MemoryUsage::new(1, bytes);
+
+        let computed_styles = node.computed_styles();
+        self.computed_styles += MemoryUsage::new(
+            computed_styles.len(),
+            computed_styles.capacity() * size_of::<(Property, Value)>(),
+        );
+        drop(computed_styles);
+
+        for pseudo_element in PseudoElement::all() {
+            if let Some(pseudo_node) = node.pseudo_element(&pseudo_element) {
+                self.measure_dom(&pseudo_node);
+            }
+        }
+        if let Some(shadow_root) = node.as_element_opt().and_then(|e| e.shadow_root()) {
+            self.measure_dom(&shadow_root);
+        }
+        if let Some(content_document) = layout::replaced::content_document(node) {
+            self.measure_dom(&content_document);
+        }
+        node.for_each_child(|child| self.measure_dom(&NodePtr(child)));
+    }
+
+    /// Add the arena of the layout tree, and of the documents loaded in
+    /// its iframes
+    pub fn measure_layout(&mut self, layout_tree: &LayoutBoxPtr) {
+        let arena = layout_tree.arena();
+        self.layout_boxes += MemoryUsage::new(arena.len(), arena.allocated_bytes());
+        for layout_box in arena.boxes() {
+            if let Some(nested_document) = &*layout_box.nested_document.borrow() {
+                self.measure_layout(nested_document);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::EngineContext;
+    use crate::frame_loader::parse_document;
+    use crate::pipeline::{calculate_layout, calculate_styles};
+    use dom::node::Node;
+    use layout::arena::LayoutArena;
+    use shared::primitive::Size;
+    use shared::tree_node::TreeNode;
+    use url::parser::URLParser;
+
+    fn measure(html: &str) -> MemoryReport {
+        let document = parse_document(
+            html,
+            URLParser::parse("http://example.com", None).unwrap(),
+            None,
+            &EngineContext::new(),
+        );
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document.clone(), &Size::new(800., 600.));
+        let mut report = MemoryReport::default();
+        report.measure_dom(&document);
+        report.measure_layout(&layout_tree.unwrap());
+        report
+    }
+
+    #[test]
+    fn test_measure_page() {
+        let small = measure("<p>Hello</p>");
+        let large = measure("<p>Hello</p><p>Hello</p><p class='a'>Hello <b>world</b></p>");
+        // document, html, head, body, p and its text
+        assert_eq!(small.dom_nodes.count, 6);
+        assert_eq!(large.dom_nodes.count, 12);
+        assert!(large.dom_nodes.bytes > small.dom_nodes.bytes);
+        assert!(large.computed_styles.count > small.computed_styles.count);
+        assert!(large.layout_boxes.count > small.layout_boxes.count);
+        assert_eq!(
+            large.total_bytes(),
+            large.dom_nodes.bytes + large.computed_styles.bytes + large.layout_boxes.bytes
+        );
+    }
+
+    #[test]
+    fn test_teardown_frees_page() {
+        let live_nodes = TreeNode::<Node>::live_count;
+        let live_boxes = LayoutArena::live_count;
+        let (nodes_before, boxes_before) = (live_nodes(), live_boxes());
+
+        let document = parse_document(
+            "<style>li { list-style-position: inside; } p::before { content: 'A'; }</style>\
+             <ul><li>Hello <b>world</b></li></ul><p>Hello</p>",
+            URLParser::parse("http://example.com", None).unwrap(),
+            None,
+            &EngineContext::new(),
+        );
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document.clone(), &Size::new(800., 600.)).unwrap();
+        assert!(live_nodes() > nodes_before);
+        assert!(live_boxes() > boxes_before);
+
+        layout_tree.teardown();
+        document.teardown();
+        drop(layout_tree);
+        drop(document);
+        assert_eq!(live_nodes(), nodes_before);
+        assert_eq!(live_boxes(), boxes_before);
+    }
+}
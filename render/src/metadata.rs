@@ -0,0 +1,189 @@
+//! Metadata of the head of a document: the `<meta>` describing the page for
+//! previews, and the viewport it asks to be laid out in
+
+use dom::node::NodePtr;
+
+/// Smallest and largest scale a page can ask for with its viewport
+/// https://drafts.csswg.org/css-viewport/#min-scale-max-scale
+const MIN_VIEWPORT_SCALE: f32 = 0.1;
+const MAX_VIEWPORT_SCALE: f32 = 10.;
+
+/// Description of the page for the previews of the embedder, like link cards
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageMetadata {
+    pub title: String,
+    /// From `<meta name=description>`
+    pub description: Option<String>,
+    /// Open Graph properties like `og:title` or `og:image`, in document
+    /// order. Image URLs are left as the page wrote them.
+    /// https://ogp.me/
+    pub open_graph: Vec<(String, String)>,
+}
+
+impl PageMetadata {
+    pub fn of(document: &NodePtr) -> Self {
+        let mut metadata = Self {
+            title: document.as_document().title(),
+            ..Default::default()
+        };
+        for meta in document.get_elements_by_tag_name("meta") {
+            let element = meta.as_element();
+            let content = match element.get_attribute("content") {
+                Some(content) => content.trim().to_string(),
+                None => continue,
+            };
+            let name = element.get_attribute("name").unwrap_or_default();
+            if name.eq_ignore_ascii_case("description") && metadata.description.is_none() {
+                metadata.description = Some(content);
+                continue;
+            }
+            let property = element.get_attribute("property").unwrap_or_default();
+            if property.to_ascii_lowercase().starts_with("og:") {
+                metadata
+                    .open_graph
+                    .push((property.to_ascii_lowercase(), content));
+            }
+        }
+        metadata
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportWidth {
+    /// The width of the viewport of the embedder, the default
+    DeviceWidth,
+    /// A width in CSS pixels the page is designed for
+    Pixels(f32),
+}
+
+/// Viewport asked for by `<meta name=viewport>`
+/// https://drafts.csswg.org/css-viewport/#viewport-meta
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ViewportMeta {
+    pub width: Option<ViewportWidth>,
+    pub initial_scale: Option<f32>,
+}
+
+impl ViewportMeta {
+    /// Viewport of the last `<meta name=viewport>` of the document
+    pub fn of(document: &NodePtr) -> Option<Self> {
+        document
+            .get_elements_by_tag_name("meta")
+            .into_iter()
+            .filter(|meta| {
+                meta.as_element()
+                    .get_attribute("name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("viewport"))
+            })
+            .filter_map(|meta| meta.as_element().get_attribute("content"))
+            .last()
+            .map(|content| Self::parse(&content))
+    }
+
+    /// Parse the `content` of the meta, a list of `key=value` separated by
+    /// commas, semicolons or spaces. Unknown keys and invalid values are
+    /// ignored.
+    /// https://drafts.csswg.org/css-viewport/#parsing-algorithm
+    pub fn parse(content: &str) -> Self {
+        let mut viewport = Self::default();
+        // `=` is a token of its own so that it can have spaces around it
+        let spaced = content.replace('=', " = ");
+        let tokens: Vec<&str> = spaced
+            .split(|c: char| c == ',' || c == ';' || c.is_ascii_whitespace())
+            .filter(|token| !token.is_empty())
+            .collect();
+        let properties = tokens.windows(3).filter(|window| window[1] == "=");
+        for window in properties {
+            let (key, value) = (window[0], window[2]);
+            match key.to_ascii_lowercase().as_str() {
+                "width" => {
+                    viewport.width = match value.to_ascii_lowercase().as_str() {
+                        "device-width" => Some(ViewportWidth::DeviceWidth),
+                        value => parse_number(value)
+                            .filter(|width| *width >= 1.)
+                            .map(ViewportWidth::Pixels),
+                    }
+                }
+                "initial-scale" => {
+                    viewport.initial_scale = parse_number(value)
+                        .filter(|scale| *scale > 0.)
+                        .map(|scale| scale.clamp(MIN_VIEWPORT_SCALE, MAX_VIEWPORT_SCALE))
+                }
+                _ => {}
+            }
+        }
+        viewport
+    }
+
+    /// Scale the page is shown at in a viewport `device_width` CSS pixels
+    /// wide. Pages designed for a width are scaled to fit the viewport,
+    /// unless they set their initial scale.
+    pub fn scale(&self, device_width: f32) -> f32 {
+        let scale = match (self.initial_scale, self.width) {
+            (Some(initial_scale), _) => initial_scale,
+            (None, Some(ViewportWidth::Pixels(width))) if device_width > 0. => device_width / width,
+            _ => 1.,
+        };
+        scale.clamp(MIN_VIEWPORT_SCALE, MAX_VIEWPORT_SCALE)
+    }
+}
+
+fn parse_number(value: &str) -> Option<f32> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use url::parser::URLParser;
+
+    use super::*;
+    use crate::context::EngineContext;
+    use crate::frame_loader::parse_document;
+
+    #[test]
+    fn test_page_metadata() {
+        let document = parse_document(
+            "<head>\
+             <title>Moon</title>\
+             <meta name='Description' content=' A web browser '>\
+             <meta property='og:title' content='Moon browser'>\
+             <meta property='og:image' content='/moon.png'>\
+             <meta name='viewport' content='width=320'>\
+             <meta name='viewport' content='width = device-width, initial-scale=2'>\
+             </head>",
+            URLParser::parse("http://example.com", None).unwrap(),
+            None,
+            &EngineContext::new(),
+        );
+
+        let metadata = PageMetadata::of(&document);
+        assert_eq!(metadata.title, "Moon");
+        assert_eq!(metadata.description.as_deref(), Some("A web browser"));
+        assert_eq!(
+            metadata.open_graph,
+            vec![
+                ("og:title".to_string(), "Moon browser".to_string()),
+                ("og:image".to_string(), "/moon.png".to_string())
+            ]
+        );
+
+        let viewport = ViewportMeta::of(&document).unwrap();
+        assert_eq!(viewport.width, Some(ViewportWidth::DeviceWidth));
+        assert_eq!(viewport.initial_scale, Some(2.));
+    }
+
+    #[test]
+    fn test_viewport_scale() {
+        let viewport = ViewportMeta::parse("width=980");
+        assert_eq!(viewport.scale(490.), 0.5);
+        let viewport = ViewportMeta::parse("width=980; initial-scale=1.5");
+        assert_eq!(viewport.scale(490.), 1.5);
+        let viewport = ViewportMeta::parse("initial-scale=100, width=abc");
+        assert_eq!(viewport.width, None);
+        assert_eq!(viewport.scale(490.), MAX_VIEWPORT_SCALE);
+        assert_eq!(ViewportMeta::parse("").scale(490.), 1.);
+    }
+}
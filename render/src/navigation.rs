@@ -0,0 +1,128 @@
+use dom::node::NodePtr;
+use url::{parser::URLParser, Url};
+
+/// Browsing context a navigation is made in, from the `target` attribute of
+/// the link
+/// https://html.spec.whatwg.org/multipage/document-sequences.html#valid-navigable-target-name-or-keyword
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationTarget {
+    /// The page of the link. Pages have no parent, so `_parent` and `_top`
+    /// are the page too.
+    CurrentPage,
+    /// A new page, for `_blank`
+    NewPage,
+    /// The page with the name, which the embedder finds or opens
+    Named(String),
+}
+
+impl NavigationTarget {
+    pub fn from_target(target: &str) -> Self {
+        let target = target.trim();
+        match target.to_ascii_lowercase().as_str() {
+            "" | "_self" | "_parent" | "_top" => Self::CurrentPage,
+            "_blank" => Self::NewPage,
+            _ => Self::Named(target.to_string()),
+        }
+    }
+}
+
+/// Answer of the embedder to `OutputEvent::NavigationRequested`
+#[derive(Debug, Clone)]
+pub enum NavigationPolicy {
+    Allow,
+    Deny,
+    /// Navigate to another URL in the requested target
+    Redirect(Url),
+}
+
+/// Navigation started by the page, waiting for the policy of the embedder
+#[derive(Debug, Clone)]
+pub struct NavigationRequest {
+    pub url: Url,
+    pub target: NavigationTarget,
+    /// The navigation was started by the user, like a click on a link
+    pub user_gesture: bool,
+    /// The new page mustn't have access to the page of the link
+    /// https://html.spec.whatwg.org/multipage/links.html#link-type-noopener
+    pub no_opener: bool,
+}
+
+impl NavigationRequest {
+    /// Navigation to the `href` of a link, resolved against the base URL of
+    /// its document. Returns `None` if the URL is invalid.
+    pub fn from_link(link: &NodePtr, user_gesture: bool) -> Option<Self> {
+        let element = link.as_element();
+        let href = element.get_attribute("href")?;
+        let base = link
+            .owner_document()
+            .and_then(|document| NodePtr(document).as_document().base());
+        let url = URLParser::parse(href.trim(), base)?;
+
+        let target =
+            NavigationTarget::from_target(&element.get_attribute("target").unwrap_or_default());
+        let rel = element
+            .get_attribute("rel")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let has_rel = |keyword: &str| rel.split_ascii_whitespace().any(|token| token == keyword);
+        // `_blank` links don't give an opener unless asked to
+        let no_opener = has_rel("noopener")
+            || has_rel("noreferrer")
+            || (target == NavigationTarget::NewPage && !has_rel("opener"));
+
+        Some(Self {
+            url,
+            target,
+            user_gesture,
+            no_opener,
+        })
+    }
+}
+
+/// Links that navigate when clicked
+pub fn is_link(node: &NodePtr) -> bool {
+    node.as_element_opt().is_some_and(|element| {
+        (element.is_html("a") || element.is_html("area")) && element.has_attribute("href")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::dom_creator::{document, element};
+
+    use super::*;
+
+    #[test]
+    fn test_link_navigation_request() {
+        let document = document();
+        document
+            .as_document()
+            .set_base(URLParser::parse("http://example.com/docs/", None));
+        let link = element("a", document.clone(), vec![]);
+        document.append_child(link.0.clone());
+        assert!(!is_link(&link));
+
+        link.as_element().set_attribute("href", "intro.html");
+        assert!(is_link(&link));
+        let request = NavigationRequest::from_link(&link, true).unwrap();
+        assert_eq!(request.url.as_str(), "http://example.com/docs/intro.html");
+        assert_eq!(request.target, NavigationTarget::CurrentPage);
+        assert!(request.user_gesture);
+        assert!(!request.no_opener);
+
+        link.as_element().set_attribute("target", "_blank");
+        let request = NavigationRequest::from_link(&link, true).unwrap();
+        assert_eq!(request.target, NavigationTarget::NewPage);
+        assert!(request.no_opener);
+
+        link.as_element().set_attribute("rel", "Opener");
+        let request = NavigationRequest::from_link(&link, true).unwrap();
+        assert!(!request.no_opener);
+
+        link.as_element().set_attribute("target", "docs");
+        link.as_element().set_attribute("rel", "noreferrer");
+        let request = NavigationRequest::from_link(&link, false).unwrap();
+        assert_eq!(request.target, NavigationTarget::Named("docs".to_string()));
+        assert!(request.no_opener);
+    }
+}
@@ -1,30 +1,96 @@
-use dom::{
-    document::Document,
-    node::{Node, NodeData, NodePtr},
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use dom::elements::ElementData;
+use dom::form_submission::{self, FormMethod, FormSubmission};
+use dom::lifecycle::DocumentReadyState;
+use dom::{constraint_validation, node::NodePtr, node_id::NodeId, snapshot::DomSnapshot};
+use gfx::{Bitmap, GfxError};
+use gpu::GpuProcess;
+use loader::{may_load_from_document, ResourceLoader, ResourceType};
+use painting::command::DisplayList;
+use shared::byte_string::ByteString;
+use shared::image::ImageBitmap;
+use shared::{
+    cancellation::CancellationToken,
+    color::Color,
+    primitive::{Point, Rect, Size},
 };
-use gfx::Bitmap;
-use loader::ResourceLoader;
-use shared::{primitive::Size, tree_node::TreeNode};
-use style_types::{CSSLocation, CascadeOrigin, ContextualStyleSheet};
 use url::Url;
 
+use crate::about::{error_page, AboutHandler};
+use crate::context::EngineContext;
+use crate::dropdown::DropdownOption;
+use crate::frame_loader::{favicon_url, load_lazy_frame, load_nested_documents, parse_document};
+use crate::frame_stats::{elapsed_ms, FrameStats};
+use crate::image_loader::ImageLoader;
+use crate::inspector::{HitTestResult, InspectedNode};
+use crate::invalidation::Invalidation;
+use crate::memory::MemoryReport;
+use crate::metadata::PageMetadata;
+use crate::navigation::NavigationRequest;
 use crate::pipeline::Pipeline;
+use crate::preferences::Preferences;
+use crate::preload_scanner::prefetch_subresources;
+use crate::print::PrintOptions;
+use crate::visibility::ElementVisibility;
+use crate::{Key, Modifiers, MouseEvent};
 
 use super::frame::Frame;
 
-const USER_AGENT_STYLES: &str = include_str!("./html.css");
-
 pub struct Page<'a> {
     main_frame: Frame,
     pipeline: Pipeline<'a>,
+    context: Rc<EngineContext>,
+    image_loader: ImageLoader,
 }
 
 impl<'a> Page<'a> {
-    pub async fn new(init_size: Size) -> Page<'a> {
+    pub async fn new(init_size: Size, device_pixel_ratio: f32) -> Result<Page<'a>, GfxError> {
+        Self::new_with_context(init_size, device_pixel_ratio, Rc::new(EngineContext::new())).await
+    }
+
+    /// Create a page that shares the context with the other pages of the
+    /// engine
+    pub async fn new_with_context(
+        init_size: Size,
+        device_pixel_ratio: f32,
+        context: Rc<EngineContext>,
+    ) -> Result<Page<'a>, GfxError> {
+        let pipeline = Pipeline::new().await?;
+        Ok(Self::with_pipeline(
+            init_size,
+            device_pixel_ratio,
+            context,
+            pipeline,
+        ))
+    }
+
+    /// Create a page that has its frames rasterized by the GPU process
+    pub fn new_with_gpu_process(
+        init_size: Size,
+        device_pixel_ratio: f32,
+        context: Rc<EngineContext>,
+        process: GpuProcess,
+    ) -> Page<'a> {
+        let pipeline = Pipeline::with_gpu_process(process);
+        Self::with_pipeline(init_size, device_pixel_ratio, context, pipeline)
+    }
+
+    fn with_pipeline(
+        init_size: Size,
+        device_pixel_ratio: f32,
+        context: Rc<EngineContext>,
+        pipeline: Pipeline<'a>,
+    ) -> Page<'a> {
         ResourceLoader::init();
+        ResourceLoader::current().register_scheme("about", Arc::new(AboutHandler));
         Page {
-            main_frame: Frame::new(init_size),
-            pipeline: Pipeline::new().await,
+            main_frame: Frame::new(init_size, device_pixel_ratio),
+            pipeline,
+            context,
+            image_loader: ImageLoader::new(),
         }
     }
 
@@ -32,34 +98,462 @@ impl<'a> Page<'a> {
         self.main_frame.resize(size, &mut self.pipeline).await;
     }
 
-    pub async fn load_html(&mut self, html: String, base_url: Url) {
-        let document = NodePtr(TreeNode::new(Node::new(
-            NodeData::Document(Document::new()),
-        )));
+    /// Set the page zoom, the number of device pixels per CSS pixel
+    pub async fn set_zoom(&mut self, zoom: f32) {
+        self.main_frame.set_zoom(zoom, &mut self.pipeline).await;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.main_frame.zoom()
+    }
+
+    pub async fn set_spatial_navigation(&mut self, enabled: bool) {
+        self.main_frame
+            .set_spatial_navigation(enabled, &mut self.pipeline)
+            .await;
+    }
+
+    /// Zoom around the point in viewport coordinates, in device pixels
+    pub async fn zoom_at(&mut self, zoom: f32, point: Point) {
+        self.main_frame
+            .zoom_at(zoom, &point, &mut self.pipeline)
+            .await;
+    }
+
+    pub async fn set_device_pixel_ratio(&mut self, device_pixel_ratio: f32) {
+        self.main_frame
+            .set_device_pixel_ratio(device_pixel_ratio, &mut self.pipeline)
+            .await;
+    }
+
+    /// Set the color the page is painted over, transparent to draw the
+    /// frames over other content of the embedder
+    pub async fn set_background_color(&mut self, color: Color) {
+        self.pipeline.set_background_color(color);
+        self.main_frame.recomposite(&mut self.pipeline).await;
+    }
 
-        let tokenizer = css::tokenizer::Tokenizer::new(USER_AGENT_STYLES.chars());
-        let mut parser = css::parser::Parser::<css::tokenizer::token::Token>::new(tokenizer.run());
-        let stylesheet = parser.parse_a_css_stylesheet();
-        let stylesheet =
-            ContextualStyleSheet::new(stylesheet, CascadeOrigin::UserAgent, CSSLocation::External);
-        document.as_document().append_stylesheet(stylesheet);
+    /// Load the page. Returns false if the load was cancelled, in which case
+    /// the previous page is kept.
+    pub async fn load_html(
+        &mut self,
+        html: String,
+        base_url: Url,
+        cancellation: CancellationToken,
+    ) -> bool {
+        // The subresources are fetched in parallel while the parser waits
+        // for the stylesheets one after the other
+        prefetch_subresources(&html, &base_url);
+        let document = {
+            let _span = tracing::info_span!("parse").entered();
+            let start = Instant::now();
+            let document =
+                parse_document(&html, base_url, Some(cancellation.clone()), &self.context);
+            self.pipeline.record_parse_time(elapsed_ms(start));
+            document
+        };
+        load_nested_documents(&document, &cancellation, &self.context);
 
-        log::debug!("Base URL: {}", base_url);
-        document.as_document().set_base(Some(base_url));
+        if cancellation.is_cancelled() {
+            log::info!("Page load cancelled while parsing");
+            document.teardown();
+            return false;
+        }
+        if !self
+            .main_frame
+            .set_document(document.clone(), &mut self.pipeline)
+            .await
+        {
+            log::info!("Page load cancelled while rendering");
+            return false;
+        }
+        // Later restyles of the loaded document can't be cancelled
+        document.as_document().set_load_cancellation(None);
 
-        let tokenizer = html::tokenizer::Tokenizer::new(html.chars());
-        let tree_builder = html::tree_builder::TreeBuilder::new(tokenizer, document);
-        let document = tree_builder.run();
+        // Images of the previous page aren't shown anymore
+        self.image_loader.cancel();
+        // The page is rendered with fallback fonts until its web fonts arrive
+        self.context
+            .font_loader()
+            .load_font_faces(document.as_document());
+        true
+    }
 
+    /// Render the page with the settings of the user
+    pub async fn set_preferences(&mut self, preferences: Preferences) {
         self.main_frame
-            .set_document(document, &mut self.pipeline)
+            .set_preferences(preferences, &mut self.pipeline)
             .await;
     }
 
+    /// Whether CSS animations of the page are in progress
+    pub fn has_running_animations(&self) -> bool {
+        self.main_frame.has_running_animations()
+    }
+
+    /// Render the next frame of the CSS animations of the page
+    pub async fn update_animations(&mut self) {
+        self.main_frame.update_animations(&mut self.pipeline).await;
+    }
+
+    /// Stop the network fetches of the page that are still in progress
+    pub fn stop_loading(&mut self) {
+        self.context.font_loader().cancel();
+        self.image_loader.cancel();
+    }
+
+    /// Whether web fonts of the page are still loading
+    pub fn is_loading_fonts(&self) -> bool {
+        self.context.font_loader().is_loading()
+    }
+
+    /// Whether the page can be shown. Its stylesheets are applied since they
+    /// are loaded while it is parsed, so only its web fonts are waited for.
+    pub fn is_ready_to_paint(&self) -> bool {
+        !self.is_loading_fonts()
+    }
+
+    /// Whether web fonts or images of the page are still loading
+    pub fn is_loading_resources(&self) -> bool {
+        self.is_loading_fonts() || self.image_loader.is_loading()
+    }
+
+    /// Readiness of the shown document, complete if there is none
+    pub fn ready_state(&self) -> DocumentReadyState {
+        self.main_frame
+            .document()
+            .map_or(DocumentReadyState::Complete, |document| {
+                document.as_document().lifecycle().ready_state()
+            })
+    }
+
+    /// Complete the shown document once its web fonts and images are
+    /// loaded, which fires its `load` event. Lazily loaded images that
+    /// aren't near the viewport aren't waited for.
+    pub fn update_ready_state(&self) {
+        if self.is_loading_resources() {
+            return;
+        }
+        if let Some(document) = self.main_frame.document() {
+            document
+                .as_document()
+                .lifecycle()
+                .advance_to(DocumentReadyState::Complete);
+        }
+    }
+
+    /// Show the web fonts and the images that finished loading. The page is
+    /// laid out again when they change the size of boxes, like the metrics
+    /// of a font do, and the boxes of images are painted again otherwise.
+    /// Returns true if the page changed.
+    pub async fn update_loaded_resources(&mut self) -> bool {
+        let mut invalidation = Invalidation::None;
+        if self.context.font_loader().register_loaded_fonts() {
+            invalidation = Invalidation::Relayout;
+        }
+        for image in self.image_loader.take_loaded() {
+            let image_invalidation = self.main_frame.image_loaded(image, &self.pipeline);
+            invalidation = invalidation.merge(image_invalidation);
+        }
+        self.main_frame
+            .invalidate(invalidation, &mut self.pipeline)
+            .await
+    }
+
+    pub async fn find_text(&mut self, query: String, forward: bool) -> (usize, usize) {
+        self.main_frame
+            .find_text(query, forward, &mut self.pipeline)
+            .await
+    }
+
     pub fn bitmap(&self) -> Option<&Bitmap> {
         self.main_frame.bitmap()
     }
 
+    /// Memory held by the page and by the engine to render it
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        if let Some(document) = self.main_frame.document() {
+            report.measure_dom(&document);
+        }
+        self.pipeline.measure_memory(&mut report);
+        report
+    }
+
+    /// Draws of the page as a display list, serializable with
+    /// `DisplayList::to_json`. None until the page is laid out.
+    pub fn display_list(&self) -> Option<DisplayList> {
+        self.main_frame.display_list(&self.pipeline)
+    }
+
+    /// Time spent rendering since the previous call
+    pub fn take_frame_stats(&mut self) -> FrameStats {
+        self.pipeline.take_stats()
+    }
+
+    /// Size of the page bitmap, in device pixels
+    pub fn size(&self) -> Size {
+        self.main_frame.size()
+    }
+
+    pub fn document(&self) -> Option<NodePtr> {
+        self.main_frame.document()
+    }
+
+    pub async fn highlight_node(&mut self, node: Option<NodePtr>) {
+        self.main_frame
+            .highlight_node(node, &mut self.pipeline)
+            .await;
+    }
+
+    /// Returns true if the text selection has changed
+    pub async fn handle_mouse_event(&mut self, event: MouseEvent) -> bool {
+        match event {
+            MouseEvent::Down(point) => self.main_frame.mouse_down(point, &mut self.pipeline).await,
+            MouseEvent::Move(point) => self.main_frame.mouse_move(point, &mut self.pipeline).await,
+            MouseEvent::Up(point) => self.main_frame.mouse_up(point, &mut self.pipeline).await,
+        }
+    }
+
+    /// Find the element at the point in viewport coordinates, in device pixels
+    pub fn inspect_node(&self, point: Point) -> Option<InspectedNode> {
+        self.main_frame
+            .element_at(&point, &self.pipeline)
+            .map(|element| InspectedNode::new(&element))
+    }
+
+    pub fn hit_test(&self, point: Point) -> Option<HitTestResult> {
+        self.main_frame.hit_test(&point, &self.pipeline)
+    }
+
+    pub fn selected_text(&self) -> String {
+        self.main_frame.selected_text(&self.pipeline)
+    }
+
+    /// Bounds of the caret in viewport coordinates, in device pixels
+    pub fn caret_rect(&mut self) -> Option<Rect> {
+        self.main_frame.caret_rect()
+    }
+
+    /// Bounds of the selected text in viewport coordinates, in device pixels
+    pub fn selection_rects(&mut self) -> Vec<Rect> {
+        self.main_frame.selection_rects(&self.pipeline)
+    }
+
+    /// Options of the select element clicked by the last mouse event, and
+    /// the bounds of the select element in viewport coordinates, in device
+    /// pixels
+    pub fn take_dropdown(&mut self) -> Option<(Vec<DropdownOption>, Rect)> {
+        let (select, rect) = self.main_frame.take_dropdown_request(&self.pipeline)?;
+        Some((DropdownOption::options_of(&select), rect))
+    }
+
+    /// Choose an option of the dropdown returned by `take_dropdown`, or close
+    /// it with `None`. Returns true if the page changed.
+    pub async fn choose_dropdown_option(&mut self, index: Option<usize>) -> bool {
+        self.main_frame
+            .choose_dropdown_option(index, &mut self.pipeline)
+            .await
+    }
+
+    /// Show a frame decoded by the embedder in the video element with the
+    /// id. Returns true if the page changed.
+    pub async fn update_video_frame(&mut self, node_id: NodeId, frame: ImageBitmap) -> bool {
+        self.main_frame
+            .update_video_frame(node_id, frame, &mut self.pipeline)
+            .await
+    }
+
+    /// Scroll by `delta` at the point, both in device pixels. Returns true
+    /// if the page scrolled.
+    pub async fn scroll(&mut self, point: Point, delta: Point) -> bool {
+        self.main_frame
+            .scroll(&point, &delta, &mut self.pipeline)
+            .await
+    }
+
+    /// Where the elements with the ids are relative to the viewport
+    pub fn query_visibility(&self, node_ids: &[NodeId]) -> Vec<ElementVisibility> {
+        self.main_frame.query_visibility(node_ids, &self.pipeline)
+    }
+
+    /// Load the documents of the `loading=lazy` iframes that came near the
+    /// viewport. Returns true if the page changed.
+    pub async fn load_lazy_frames(&mut self) -> bool {
+        let mut is_changed = false;
+        // The loaded documents can resize their iframes and bring more of
+        // them near the viewport
+        loop {
+            let iframes: Vec<NodePtr> = self
+                .main_frame
+                .take_lazy_loads()
+                .into_iter()
+                .filter(|element| {
+                    matches!(element.as_element().data(), ElementData::IFrame(iframe)
+                        if iframe.content_document().is_none())
+                })
+                .collect();
+            if iframes.is_empty() {
+                return is_changed;
+            }
+            for iframe in &iframes {
+                load_lazy_frame(iframe, &self.context);
+            }
+            self.main_frame.restyle(&mut self.pipeline).await;
+            is_changed = true;
+        }
+    }
+
+    /// Start loading the sources chosen for the images of the page since
+    /// the last call, which change with the size of the viewport and the
+    /// scale. They are shown by `update_loaded_resources`.
+    pub fn load_image_sources(&mut self) {
+        let document_url = self.url();
+        for (node_id, url) in self.main_frame.take_image_requests() {
+            self.image_loader.load(node_id, url, document_url.clone());
+        }
+    }
+
+    /// Returns true if the page changed
+    pub async fn handle_key(&mut self, key: Key, modifiers: Modifiers) -> bool {
+        self.main_frame
+            .key_down(key, modifiers, &mut self.pipeline)
+            .await
+    }
+
+    /// Whether a text control has keyboard focus and shows a caret
+    pub fn has_caret(&self) -> bool {
+        self.main_frame.has_caret()
+    }
+
+    /// Show or hide the caret, for blinking. Returns true if the page changed.
+    pub async fn set_caret_visible(&mut self, is_visible: bool) -> bool {
+        self.main_frame
+            .set_caret_visible(is_visible, &mut self.pipeline)
+            .await
+    }
+
+    /// Run interactive validation of the form at `index` in `document.forms`
+    /// and build its submission. Returns the validation messages when the
+    /// submission is blocked.
+    pub fn submit_form(&self, index: usize) -> Result<Option<FormSubmission>, Vec<String>> {
+        let form = self.main_frame.document().and_then(|document| {
+            document
+                .get_elements_by_tag_name("form")
+                .get(index)
+                .cloned()
+        });
+
+        match form {
+            Some(form) => prepare_submission(&form, None),
+            None => {
+                log::warn!("Unable to find form to submit at index: {}", index);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Submission of the form submitted by the last mouse or key event,
+    /// with a submit button or by pressing Enter in a text field
+    pub fn take_form_submission(&mut self) -> Result<Option<FormSubmission>, Vec<String>> {
+        match self.main_frame.take_submit_request() {
+            Some((form, submitter)) => prepare_submission(&form, submitter.as_ref()),
+            None => Ok(None),
+        }
+    }
+
+    /// Navigation started by the last input event, like a clicked link. It
+    /// is only followed once the embedder allows it.
+    pub fn take_navigation_request(&mut self) -> Option<NavigationRequest> {
+        self.main_frame.take_navigation_request()
+    }
+
+    /// Load the document at the URL in place of the current one, or an error
+    /// page if it can't be loaded. Returns false if the page wasn't replaced,
+    /// like when a remote page navigates to a local file.
+    pub async fn navigate_to(&mut self, url: Url, cancellation: CancellationToken) -> bool {
+        if !may_load_from_document(&url, self.url().as_ref()) {
+            log::warn!("Blocked navigation of a remote page to: {}", url);
+            return false;
+        }
+        log::info!("Navigating to: {}", url);
+        let html = match ResourceLoader::current().load_resource(&url, ResourceType::Document) {
+            Ok(bytes) => ByteString::from_html(&bytes).to_string(),
+            Err(e) => {
+                log::error!("Unable to navigate: {} ({})", e, url);
+                error_page(&url, &e)
+            }
+        };
+        self.load_html(html, url, cancellation).await
+    }
+
+    /// Send a form submission and load the response document in place of the
+    /// current one, or an error page if there is no response. Returns false
+    /// if the page wasn't replaced, like when a remote page submits to a
+    /// local file.
+    pub async fn navigate_to_submission(
+        &mut self,
+        submission: FormSubmission,
+        cancellation: CancellationToken,
+    ) -> bool {
+        if !may_load_from_document(&submission.url, self.url().as_ref()) {
+            log::warn!(
+                "Blocked form submission of a remote page to: {}",
+                submission.url
+            );
+            return false;
+        }
+        log::info!("Submitting form to: {}", submission.url);
+        let loader = ResourceLoader::current();
+        let response = match (&submission.method, submission.body) {
+            (FormMethod::Post, Some((content_type, body))) => {
+                loader.post(&submission.url, &content_type, body)
+            }
+            _ => loader.load_resource(&submission.url, ResourceType::Document),
+        };
+
+        let html = match response {
+            Ok(bytes) => ByteString::from_html(&bytes).to_string(),
+            Err(e) => {
+                log::error!("Unable to submit form: {} ({})", e, submission.url);
+                error_page(&submission.url, &e)
+            }
+        };
+        self.load_html(html, submission.url, cancellation).await
+    }
+
+    /// Print the page to a PDF document. Returns `None` if no document is
+    /// loaded.
+    pub async fn print_to_pdf(&self, options: &PrintOptions) -> Option<Vec<u8>> {
+        self.main_frame.print_to_pdf(options).await
+    }
+
+    pub fn snapshot(&mut self) -> Option<DomSnapshot> {
+        self.main_frame.snapshot()
+    }
+
+    /// URL of the shown document
+    pub fn url(&self) -> Option<Url> {
+        self.main_frame
+            .document()
+            .and_then(|document| document.as_document().base())
+    }
+
+    /// URL of the icon of the page, see `frame_loader::favicon_url`
+    pub fn favicon_url(&self) -> Option<Url> {
+        self.main_frame
+            .document()
+            .and_then(|document| favicon_url(&document))
+    }
+
+    /// Title, description and Open Graph properties of the document
+    pub fn metadata(&self) -> PageMetadata {
+        self.main_frame
+            .document()
+            .map(|document| PageMetadata::of(&document))
+            .unwrap_or_default()
+    }
+
     pub fn title(&self) -> String {
         self.main_frame
             .document()
@@ -67,3 +561,17 @@ impl<'a> Page<'a> {
             .unwrap_or_default()
     }
 }
+
+/// Validate the form unless validation is disabled, and build its submission
+fn prepare_submission(
+    form: &NodePtr,
+    submitter: Option<&NodePtr>,
+) -> Result<Option<FormSubmission>, Vec<String>> {
+    if form_submission::should_validate(form, submitter) {
+        let messages = constraint_validation::validation_messages(form);
+        if !messages.is_empty() {
+            return Err(messages);
+        }
+    }
+    Ok(form_submission::form_submission(form, submitter))
+}
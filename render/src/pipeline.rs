@@ -1,90 +1,525 @@
+use std::time::Instant;
+
 use dom::node::NodePtr;
-use gfx::{Bitmap, Canvas};
-use layout::{
-    formatting_context::{establish_context, FormattingContextType, LayoutContext},
-    layout_box::{LayoutBox, LayoutBoxPtr},
-};
-use painting::Painter;
+use gfx::{Bitmap, Canvas, GfxConfig, GfxError, Graphics};
+use gpu::GpuProcess;
+use layout::layout_box::LayoutBoxPtr;
+use painting::command::DisplayListRecorder;
+use painting::{OverlayLayer, Painter};
 use shared::{
-    primitive::{Rect, Size},
-    tree_node::TreeNode,
+    color::Color,
+    primitive::{Point, Size},
 };
-use style_types::ContextualRule;
+use tracing::{info_span, Instrument};
+
+use crate::frame_stats::{elapsed_ms, FrameStats};
+use crate::memory::MemoryReport;
 
 pub struct Pipeline<'a> {
-    painter: Painter<Canvas<'a>>,
+    rasterizer: Rasterizer<'a>,
+    layout_tree: Option<LayoutBoxPtr>,
+    /// Stage timings accumulated since the last call to `take_stats`
+    stats: FrameStats,
+}
+
+/// Where the frames are rasterized
+enum Rasterizer<'a> {
+    /// On the GPU, from this process
+    Canvas(Box<Painter<Canvas<'a>>>),
+    /// By a GPU process, from the display lists of the frames
+    GpuProcess {
+        painter: Box<Painter<DisplayListRecorder>>,
+        process: GpuProcess,
+        background: Color,
+    },
 }
 
 pub struct PipelineRunOptions {
     pub skip_style_calculation: bool,
+    pub skip_layout_calculation: bool,
+    /// Composite the content that layers kept from the previous frame,
+    /// when only their positions changed
+    pub skip_layer_painting: bool,
 }
 
 impl<'a> Pipeline<'a> {
-    pub async fn new() -> Pipeline<'a> {
+    pub async fn new() -> Result<Pipeline<'a>, GfxError> {
+        Ok(Pipeline {
+            rasterizer: Rasterizer::Canvas(Box::new(Painter::new(
+                Canvas::new(GfxConfig::default()).await?,
+            ))),
+            layout_tree: None,
+            stats: FrameStats::default(),
+        })
+    }
+
+    /// A pipeline that has its frames rasterized by the GPU process, so
+    /// that this process never touches the graphics driver
+    pub fn with_gpu_process(process: GpuProcess) -> Pipeline<'a> {
         Pipeline {
-            painter: Painter::new(Canvas::new().await),
+            rasterizer: Rasterizer::GpuProcess {
+                painter: Box::new(Painter::new(DisplayListRecorder::new())),
+                process,
+                background: GfxConfig::default().background,
+            },
+            layout_tree: None,
+            stats: FrameStats::default(),
         }
     }
 
+    /// Render the document. Returns `None` if its loading was cancelled
+    /// while computing styles, in which case the previous layout is kept,
+    /// or if the GPU process failed to rasterize the frame.
     pub async fn run(
         &mut self,
         document_node: NodePtr,
         size: &Size,
+        scale: f32,
+        scroll_offset: &Point,
+        overlay: &OverlayLayer,
         opts: PipelineRunOptions,
-    ) -> Bitmap {
+    ) -> Option<Bitmap> {
+        let mut stats = FrameStats::default();
+
         if !opts.skip_style_calculation {
-            self.calculate_styles(document_node.clone());
+            let _span = info_span!("style").entered();
+            let start = Instant::now();
+            calculate_styles(document_node.clone());
+            stats.style_ms = elapsed_ms(start);
+        }
+        // Layout and painting run to completion once started
+        if document_node.as_document().is_load_cancelled() {
+            log::info!("Rendering cancelled");
+            self.stats.add(&stats);
+            return None;
+        }
+        // Layout works in CSS pixels
+        let viewport_size = Size::new(size.width / scale, size.height / scale);
+        if !opts.skip_layout_calculation || self.layout_tree.is_none() {
+            let _span = info_span!("layout").entered();
+            let start = Instant::now();
+            let layout_tree = calculate_layout(document_node, &viewport_size);
+            if let Some(previous_tree) = std::mem::replace(&mut self.layout_tree, layout_tree) {
+                previous_tree.teardown();
+            }
+            stats.layout_ms = elapsed_ms(start);
+        }
+
+        {
+            let _span = info_span!("paint").entered();
+            let start = Instant::now();
+            let layout_tree = self.layout_tree.as_ref();
+            match &mut self.rasterizer {
+                Rasterizer::Canvas(painter) => {
+                    painter.resize(size.clone());
+                    painter.set_scale(scale);
+                    if !opts.skip_layer_painting {
+                        painter.invalidate_layers();
+                    }
+                    paint(painter, layout_tree, scroll_offset, overlay);
+                }
+                Rasterizer::GpuProcess { painter, .. } => {
+                    // Display lists are in CSS pixels
+                    painter.resize(viewport_size);
+                    paint(painter, layout_tree, scroll_offset, overlay);
+                }
+            }
+            stats.paint_ms = elapsed_ms(start);
         }
-        let layout_node = self.calculate_layout(document_node, size);
 
-        self.painter.resize(size.clone());
-        if let Some(node) = layout_node {
-            self.painter.paint(&node);
+        let start = Instant::now();
+        let bitmap = match &mut self.rasterizer {
+            Rasterizer::Canvas(painter) => painter.output().instrument(info_span!("raster")).await,
+            Rasterizer::GpuProcess {
+                painter,
+                process,
+                background,
+            } => {
+                let _span = info_span!("raster").entered();
+                let display_list = painter.graphics_mut().take_display_list();
+                match process.raster(&display_list, scale, background.clone()) {
+                    Ok(frame) => frame.pixels,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        self.stats.add(&stats);
+                        return None;
+                    }
+                }
+            }
+        };
+        stats.raster_ms = elapsed_ms(start);
+
+        tracing::debug!(
+            style_ms = stats.style_ms,
+            layout_ms = stats.layout_ms,
+            paint_ms = stats.paint_ms,
+            raster_ms = stats.raster_ms,
+            "pipeline run"
+        );
+        self.stats.add(&stats);
+        Some(bitmap)
+    }
+
+    /// Set the color the frames are painted over. Transparent colors give
+    /// frames with an alpha channel.
+    pub fn set_background_color(&mut self, color: Color) {
+        match &mut self.rasterizer {
+            Rasterizer::Canvas(painter) => painter.graphics_mut().set_background(color),
+            Rasterizer::GpuProcess { background, .. } => *background = color,
         }
-        self.painter.output().await
     }
 
-    fn calculate_styles(&self, document_node: NodePtr) {
-        let document = document_node.as_document();
-        let style_rules = document.style_rules();
+    /// Time spent parsing the document, reported with the next frame
+    pub fn record_parse_time(&mut self, parse_ms: f32) {
+        self.stats.parse_ms += parse_ms;
+    }
+
+    /// Stage timings since the previous call
+    pub fn take_stats(&mut self) -> FrameStats {
+        std::mem::take(&mut self.stats)
+    }
 
-        fn compute_styles(element: NodePtr, style_rules: &[ContextualRule]) {
-            let computed_styles = style::compute::compute_styles(element.clone(), &style_rules);
-            element.set_computed_styles(computed_styles);
+    pub fn layout_tree(&self) -> Option<LayoutBoxPtr> {
+        self.layout_tree.clone()
+    }
 
-            element.for_each_child(|child| compute_styles(NodePtr(child), style_rules))
+    /// Add the layout tree and the GPU caches of this process to the report
+    pub fn measure_memory(&self, report: &mut MemoryReport) {
+        if let Some(layout_tree) = &self.layout_tree {
+            report.measure_layout(layout_tree);
+        }
+        if let Rasterizer::Canvas(painter) = &self.rasterizer {
+            report.glyph_atlas = painter.graphics().glyph_atlas_memory();
+            report.images = painter.graphics().image_memory();
         }
+    }
+}
 
-        compute_styles(document_node, &style_rules);
+fn paint<G: Graphics>(
+    painter: &mut Painter<G>,
+    layout_tree: Option<&LayoutBoxPtr>,
+    scroll_offset: &Point,
+    overlay: &OverlayLayer,
+) {
+    painter.set_scroll_offset(scroll_offset.clone());
+    if let Some(node) = layout_tree {
+        painter.paint(node);
     }
+    painter.paint_overlay(overlay);
+}
+
+/// Compute the styles of every node of the document. Stops early if the
+/// loading of the document is cancelled.
+pub fn calculate_styles(document_node: NodePtr) {
+    layout::styles::calculate_styles(document_node);
+}
+
+/// Build and lay out the layout tree of the document for a viewport of
+/// `size` CSS pixels
+pub fn calculate_layout(document_node: NodePtr, size: &Size) -> Option<LayoutBoxPtr> {
+    layout::document::layout_document(document_node, size)
+}
 
-    fn calculate_layout(&self, document_node: NodePtr, size: &Size) -> Option<LayoutBoxPtr> {
-        let layout_tree = layout::tree_builder::TreeBuilder::new().build(document_node);
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use dom::elements::ElementData;
+    use gfx::{FontIndex, Graphics, Layer, LayerId};
+    use shared::{
+        color::Color,
+        image::ImageBitmap,
+        primitive::{RRect, Rect},
+    };
+    use url::parser::URLParser;
 
-        if let Some(root) = &layout_tree {
-            let layout_context = LayoutContext {
-                viewport: Rect {
-                    x: 0.,
-                    y: 0.,
-                    width: size.width,
-                    height: size.height,
-                },
-            };
+    use super::*;
+    use crate::context::EngineContext;
+    use crate::frame_loader::parse_document;
 
-            let initial_block_box = LayoutBoxPtr(TreeNode::new(LayoutBox::new_anonymous(
-                layout::layout_box::BoxData::block_box(),
-            )));
-            initial_block_box.append_child(root.0.clone());
+    /// Graphics that records the clip of every rect, text and image it draws
+    #[derive(Default)]
+    struct ClipRecorder {
+        clip: Option<Rect>,
+        rects: Vec<(Rect, Option<Rect>)>,
+        texts: Vec<(String, Option<Rect>)>,
+        images: Vec<(Rect, Option<Rect>)>,
+    }
 
-            establish_context(
-                FormattingContextType::BlockFormattingContext,
-                initial_block_box.clone(),
-            );
-            initial_block_box
-                .formatting_context()
-                .run(&layout_context, initial_block_box.clone());
+    #[async_trait(?Send)]
+    impl Graphics for ClipRecorder {
+        fn fill_rect(&mut self, rect: Rect, _: Color) {
+            self.rects.push((rect, self.clip.clone()));
+        }
+        fn fill_rrect(&mut self, _: RRect, _: Color) {}
+        fn fill_text(&mut self, content: String, _: Rect, _: Color, _: f32, _: FontIndex) {
+            self.texts.push((content, self.clip.clone()));
+        }
+        fn fill_vertical_text(&mut self, _: String, _: Rect, _: Color, _: f32, _: FontIndex) {}
+        fn fill_polygon(&mut self, _: Vec<Point>, _: Color) {}
+        fn draw_image(&mut self, bounds: Rect, _: ImageBitmap) {
+            self.images.push((bounds, self.clip.clone()));
+        }
+        fn fill_shadow(&mut self, _: Rect, _: f32, _: Color, _: &RRect) {}
+        fn fill_inset_shadow(&mut self, _: &RRect, _: Rect, _: f32, _: Color) {}
+        fn set_clip(&mut self, clip: Option<Rect>) {
+            self.clip = clip;
+        }
+        fn resize(&mut self, _: Size) {}
+        fn set_scale(&mut self, _: f32) {}
+        async fn output(&mut self) -> Vec<u8> {
+            Vec::new()
         }
+    }
+
+    /// Graphics that composites layers, recording them with the texts
+    /// painted into them
+    #[derive(Default)]
+    struct LayerRecorder {
+        /// Layers that kept their content from the previous paint
+        kept: Vec<LayerId>,
+        layers: Vec<Layer>,
+        painted_layers: Vec<LayerId>,
+        texts: Vec<(Option<LayerId>, String, Rect)>,
+    }
+
+    #[async_trait(?Send)]
+    impl Graphics for LayerRecorder {
+        fn fill_rect(&mut self, _: Rect, _: Color) {}
+        fn fill_rrect(&mut self, _: RRect, _: Color) {}
+        fn fill_text(&mut self, content: String, bounds: Rect, _: Color, _: f32, _: FontIndex) {
+            let layer = self.painted_layers.last().cloned();
+            self.texts.push((layer, content, bounds));
+        }
+        fn fill_vertical_text(&mut self, _: String, _: Rect, _: Color, _: f32, _: FontIndex) {}
+        fn fill_polygon(&mut self, _: Vec<Point>, _: Color) {}
+        fn draw_image(&mut self, _: Rect, _: ImageBitmap) {}
+        fn fill_shadow(&mut self, _: Rect, _: f32, _: Color, _: &RRect) {}
+        fn fill_inset_shadow(&mut self, _: &RRect, _: Rect, _: f32, _: Color) {}
+        fn set_clip(&mut self, _: Option<Rect>) {}
+        fn can_composite_layer(&self, _: &Size) -> bool {
+            true
+        }
+        fn begin_layer(&mut self, layer: Layer) -> bool {
+            let is_kept = self.kept.contains(&layer.id);
+            self.painted_layers.push(layer.id);
+            self.layers.push(layer);
+            !is_kept
+        }
+        fn end_layer(&mut self) {
+            self.painted_layers.pop();
+        }
+        fn resize(&mut self, _: Size) {}
+        fn set_scale(&mut self, _: f32) {}
+        async fn output(&mut self) -> Vec<u8> {
+            Vec::new()
+        }
+    }
+
+    #[test]
+    fn test_clip_nested_document_to_iframe() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } iframe { border: none; }</style>\
+             <iframe width='100' height='50'></iframe><p>After</p>",
+            url.clone(),
+            None,
+            &EngineContext::new(),
+        );
+        let nested_document = parse_document(
+            "<style>body { margin: 0; } div { height: 400px; background-color: red; }</style>\
+             <div>Inside</div>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        let iframe = document.get_elements_by_tag_name("iframe").remove(0);
+        if let ElementData::IFrame(iframe) = iframe.as_element().data() {
+            iframe.set_content_document(Some(nested_document));
+        }
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document, &Size::new(800., 600.)).unwrap();
+
+        let mut painter = Painter::new(ClipRecorder::default());
+        painter.resize(Size::new(800., 600.));
+        painter.paint(&layout_tree);
+        let recorder = painter.graphics_mut();
+
+        let iframe_rect = Some(Rect::new(0., 0., 100., 50.));
+        let oversized = recorder
+            .rects
+            .iter()
+            .find(|(rect, _)| rect.height == 400.)
+            .unwrap();
+        assert_eq!(oversized.1, iframe_rect);
+        let clip_of = |content: &str| {
+            recorder
+                .texts
+                .iter()
+                .find(|(text, _)| text.trim() == content)
+                .unwrap()
+                .1
+                .clone()
+        };
+        assert_eq!(clip_of("Inside"), iframe_rect);
+        assert_eq!(clip_of("After"), None);
+        // The clip is removed once the page is painted
+        assert_eq!(recorder.clip, None);
+    }
+
+    #[test]
+    fn test_paint_video_frame_fitted_to_content_box() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } \
+             video { display: block; width: 200px; height: 100px; object-fit: cover; }</style>\
+             <video></video>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        let video = document.get_elements_by_tag_name("video").remove(0);
+        let paint_images = |document: &NodePtr| {
+            calculate_styles(document.clone());
+            let layout_tree = calculate_layout(document.clone(), &Size::new(800., 600.)).unwrap();
+            let mut painter = Painter::new(ClipRecorder::default());
+            painter.resize(Size::new(800., 600.));
+            painter.paint(&layout_tree);
+            std::mem::take(&mut painter.graphics_mut().images)
+        };
+
+        // Nothing is painted until the embedder supplies a frame
+        assert!(paint_images(&document).is_empty());
+
+        if let ElementData::Video(video) = video.as_element().data() {
+            video.set_current_frame(ImageBitmap::from_rgba(10, 10, vec![0; 400]));
+        }
+        assert_eq!(
+            paint_images(&document),
+            vec![(
+                Rect::new(0., -50., 200., 200.),
+                Some(Rect::new(0., 0., 200., 100.))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_paint_image_at_natural_size() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } img { display: block; }</style><img src=a.png>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        let image = document.get_elements_by_tag_name("img").remove(0);
+        let paint_images = |document: &NodePtr| {
+            calculate_styles(document.clone());
+            let layout_tree = calculate_layout(document.clone(), &Size::new(800., 600.)).unwrap();
+            let mut painter = Painter::new(ClipRecorder::default());
+            painter.resize(Size::new(800., 600.));
+            painter.paint(&layout_tree);
+            std::mem::take(&mut painter.graphics_mut().images)
+        };
+
+        // The box has the default object size until the image is loaded
+        assert!(paint_images(&document).is_empty());
+
+        if let ElementData::Image(image) = image.as_element().data() {
+            image.set_current_image(ImageBitmap::from_rgba(40, 20, vec![0; 3200]));
+        }
+        assert_eq!(
+            paint_images(&document),
+            vec![(
+                Rect::new(0., 0., 40., 20.),
+                Some(Rect::new(0., 0., 40., 20.))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_clip_scroll_container_content() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } p { margin: 0; height: 80px; } \
+             div { width: 100px; height: 100px; overflow: auto; }</style>\
+             <div><p>First</p><p>Second</p></div>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document, &Size::new(800., 600.)).unwrap();
+        let mut painter = Painter::new(ClipRecorder::default());
+        painter.resize(Size::new(800., 600.));
+        painter.paint(&layout_tree);
+        let graphics = painter.graphics_mut();
+
+        let padding_box = Some(Rect::new(0., 0., 100., 100.));
+        assert_eq!(
+            graphics.texts,
+            vec![
+                ("First ".to_string(), padding_box.clone()),
+                ("Second ".to_string(), padding_box)
+            ]
+        );
+        // The content overflows vertically, so a thumb is painted at the
+        // right edge, as tall as the visible part of the content
+        assert!(graphics
+            .rects
+            .iter()
+            .any(|(rect, _)| *rect == Rect::new(94., 0., 6., 62.5)));
+    }
+
+    #[test]
+    fn test_paint_scroll_container_into_layer() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } p { margin: 0; height: 80px; } \
+             div { width: 100px; height: 100px; overflow: auto; }</style>\
+             <div><p>First</p><p>Second</p></div>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        let container = document.get_elements_by_tag_name("div").remove(0);
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document, &Size::new(800., 600.)).unwrap();
+        let mut painter = Painter::new(LayerRecorder::default());
+        painter.resize(Size::new(800., 600.));
+        painter.paint(&layout_tree);
+
+        let id = LayerId(container.id().0);
+        let graphics = painter.graphics_mut();
+        assert_eq!(
+            graphics.layers,
+            vec![Layer {
+                id,
+                content_size: Size::new(100., 160.),
+                position: Point::new(0., 0.),
+                clip: Rect::new(0., 0., 100., 100.),
+                scrollbars: vec![Rect::new(94., 0., 6., 62.5)],
+            }]
+        );
+        // The content is painted into the layer where it is without scrolling
+        let texts = std::mem::take(&mut graphics.texts);
+        assert_eq!(texts.len(), 2);
+        assert!(texts.iter().all(|(layer, _, _)| *layer == Some(id)));
+        assert_eq!(texts[1].2.y, 80.);
+
+        // Scrolling moves the layer, which keeps its content
+        container
+            .as_element()
+            .set_scroll_offset(Point::new(0., 30.));
+        graphics.layers.clear();
+        graphics.kept.push(id);
+        painter.paint(&layout_tree);
 
-        layout_tree
+        let graphics = painter.graphics_mut();
+        assert!(graphics.texts.is_empty());
+        assert_eq!(graphics.layers[0].position, Point::new(0., -30.));
+        assert_eq!(
+            graphics.layers[0].scrollbars,
+            vec![Rect::new(94., 18.75, 6., 62.5)]
+        );
     }
 }
@@ -0,0 +1,76 @@
+use std::rc::Rc;
+
+use css::media_query::structs::{ColorScheme, MediaEnvironment, ReducedMotion};
+use style_types::ContextualStyleSheet;
+
+/// Settings of the user that pages are rendered with, usually those of the
+/// operating system. Set with `InputEvent::SetPreferences`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preferences {
+    /// Matched by the `prefers-color-scheme` media feature
+    pub color_scheme: ColorScheme,
+    /// Matched by the `prefers-reduced-motion` media feature
+    pub reduced_motion: bool,
+    /// Size of text in CSS pixels when pages don't set one
+    pub default_font_size: f32,
+    /// Size of monospace text in CSS pixels when pages don't set one
+    pub default_monospace_font_size: f32,
+    /// BCP 47 tag of the language of the user, such as `fr-CA`
+    pub language: String,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            color_scheme: ColorScheme::Light,
+            reduced_motion: false,
+            default_font_size: 16.,
+            default_monospace_font_size: 13.,
+            language: "en-US".to_string(),
+        }
+    }
+}
+
+impl Preferences {
+    pub(crate) fn apply_to_environment(&self, environment: &mut MediaEnvironment) {
+        environment.color_scheme = self.color_scheme.clone();
+        environment.reduced_motion = match self.reduced_motion {
+            true => ReducedMotion::Reduce,
+            false => ReducedMotion::NoPreference,
+        };
+    }
+
+    /// User-agent styles that follow the preferences, added to every
+    /// document shown in the frame
+    pub(crate) fn stylesheet(&self) -> Rc<ContextualStyleSheet> {
+        style::user_agent::preferences_stylesheet(
+            self.default_monospace_font_size / self.default_font_size,
+            quotation_marks(&self.language),
+        )
+    }
+}
+
+/// Opening and closing quotation marks of the language
+fn quotation_marks(language: &str) -> (&'static str, &'static str) {
+    let primary = language.split(['-', '_']).next().unwrap_or_default();
+    match primary.to_ascii_lowercase().as_str() {
+        "fr" => ("« ", " »"),
+        "es" | "it" | "pt" | "ru" | "uk" => ("«", "»"),
+        "de" | "cs" => ("„", "“"),
+        "ja" | "zh" => ("「", "」"),
+        _ => ("“", "”"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quotation_marks() {
+        assert_eq!(quotation_marks("fr-CA"), ("« ", " »"));
+        assert_eq!(quotation_marks("DE"), ("„", "“"));
+        assert_eq!(quotation_marks("en_GB"), ("“", "”"));
+        assert_eq!(quotation_marks(""), ("“", "”"));
+    }
+}
@@ -0,0 +1,199 @@
+//! Discovery of the subresources of a page before it is parsed, so that
+//! their fetches run in parallel instead of one after the other as the
+//! parser reaches them. The markup is only tokenized, switching the
+//! tokenizer to the states the tree builder would for text elements.
+//! https://html.spec.whatwg.org/multipage/parsing.html#speculative-html-parsing
+
+use html::tokenizer::state::State;
+use html::tokenizer::token::Token;
+use html::tokenizer::{Tokenizer, Tokenizing};
+use loader::{ResourceLoader, ResourceType};
+use url::{parser::URLParser, Url};
+
+/// A subresource found in the markup
+#[derive(Debug, Clone)]
+pub struct PreloadRequest {
+    pub url: Url,
+    pub resource_type: ResourceType,
+}
+
+/// Start fetching the subresources of the markup, see `scan`
+pub fn prefetch_subresources(html: &str, base_url: &Url) {
+    let loader = ResourceLoader::current();
+    for request in scan(html, base_url) {
+        loader.prefetch(&request.url, request.resource_type);
+    }
+}
+
+/// Stylesheets, preloads, images and scripts of the markup in document
+/// order. Relative URLs are resolved against the first `<base>` with an
+/// `href`, or `base_url` if there is none. Images with `loading=lazy` and
+/// the content of templates are left out.
+pub fn scan(html: &str, base_url: &Url) -> Vec<PreloadRequest> {
+    let mut tokenizer = Tokenizer::new(html.chars());
+    let mut base = base_url.clone();
+    let mut has_base = false;
+    let mut template_depth = 0usize;
+    let mut requests = Vec::new();
+
+    loop {
+        let token = tokenizer.next_token();
+        let (tag_name, attributes, is_end_tag) = match &token {
+            Token::Tag {
+                tag_name,
+                attributes,
+                is_end_tag,
+                ..
+            } => (tag_name.as_str(), attributes, *is_end_tag),
+            Token::EOF => break,
+            _ => continue,
+        };
+        if is_end_tag {
+            if tag_name == "template" {
+                template_depth = template_depth.saturating_sub(1);
+            }
+            continue;
+        }
+        if let Some(state) = text_state(tag_name) {
+            tokenizer.switch_to(state);
+        }
+        if tag_name == "template" {
+            template_depth += 1;
+        }
+        if template_depth > 0 {
+            continue;
+        }
+
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|attribute| attribute.name == name)
+                .map(|attribute| attribute.value.trim())
+        };
+        let (url, resource_type) = match tag_name {
+            "base" if !has_base => {
+                if let Some(href) = attribute("href") {
+                    has_base = true;
+                    if let Some(url) = URLParser::parse(href, Some(base_url.clone())) {
+                        base = url;
+                    }
+                }
+                continue;
+            }
+            "link" => match link_resource_type(
+                attribute("rel").unwrap_or_default(),
+                attribute("as").unwrap_or_default(),
+            ) {
+                Some(resource_type) => (attribute("href"), resource_type),
+                None => continue,
+            },
+            "img"
+                if attribute("srcset").is_none()
+                    && !attribute("loading").is_some_and(|l| l.eq_ignore_ascii_case("lazy")) =>
+            {
+                (attribute("src"), ResourceType::Image)
+            }
+            "script" => (attribute("src"), ResourceType::Other),
+            _ => continue,
+        };
+
+        let url = url
+            .filter(|url| !url.is_empty())
+            .and_then(|url| URLParser::parse(url, Some(base.clone())));
+        if let Some(url) = url {
+            requests.push(PreloadRequest { url, resource_type });
+        }
+    }
+    requests
+}
+
+/// State the tree builder switches the tokenizer to after the start tag,
+/// for elements whose content is text
+fn text_state(tag_name: &str) -> Option<State> {
+    match tag_name {
+        "title" | "textarea" => Some(State::RCDATA),
+        "style" | "xmp" | "iframe" | "noembed" | "noframes" => Some(State::RAWTEXT),
+        "script" => Some(State::ScriptData),
+        "plaintext" => Some(State::PLAINTEXT),
+        _ => None,
+    }
+}
+
+/// What the `<link>` loads, if it loads anything while the page is parsed
+fn link_resource_type(rel: &str, destination: &str) -> Option<ResourceType> {
+    let has_keyword = |keyword: &str| {
+        rel.split_ascii_whitespace()
+            .any(|token| token.eq_ignore_ascii_case(keyword))
+    };
+    if has_keyword("stylesheet") && !has_keyword("alternate") {
+        return Some(ResourceType::Stylesheet);
+    }
+    if !has_keyword("preload") {
+        return None;
+    }
+    match destination.to_ascii_lowercase().as_str() {
+        "style" => Some(ResourceType::Stylesheet),
+        "image" => Some(ResourceType::Image),
+        "font" => Some(ResourceType::Font),
+        "script" | "fetch" => Some(ResourceType::Other),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_subresources() {
+        let base_url = URLParser::parse("http://example.com/page/", None).unwrap();
+        let requests = scan(
+            "<html><head>\
+             <link rel=stylesheet href=main.css>\
+             <link rel='alternate stylesheet' href=alt.css>\
+             <link rel=preload as=font href=/font.woff2>\
+             <link rel=icon href=icon.png>\
+             <base href=http://cdn.example.com/assets/>\
+             <script src=app.js></script>\
+             <script>document.write('<img src=written.png>')</script>\
+             <style>body { background: url(bg.png) } </style><!-- <img src=comment.png> -->\
+             </head><body>\
+             <textarea><img src=text.png></textarea>\
+             <img src='photo.jpg'><img src=later.jpg loading=lazy><img src=''>\
+             <img src=small.jpg srcset='large.jpg 2x'>\
+             <template><img src=template.png></template>\
+             <img src=https://other.example.com/after.png>",
+            &base_url,
+        );
+
+        let found: Vec<(String, ResourceType)> = requests
+            .into_iter()
+            .map(|request| (request.url.as_str(), request.resource_type))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    "http://example.com/page/main.css".to_string(),
+                    ResourceType::Stylesheet
+                ),
+                (
+                    "http://example.com/font.woff2".to_string(),
+                    ResourceType::Font
+                ),
+                (
+                    "http://cdn.example.com/assets/app.js".to_string(),
+                    ResourceType::Other
+                ),
+                (
+                    "http://cdn.example.com/assets/photo.jpg".to_string(),
+                    ResourceType::Image
+                ),
+                (
+                    "https://other.example.com/after.png".to_string(),
+                    ResourceType::Image
+                ),
+            ]
+        );
+    }
+}
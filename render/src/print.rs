@@ -0,0 +1,89 @@
+use gfx::PdfGraphics;
+use layout::{layout_box::LayoutBoxPtr, pagination::paginate};
+use painting::Painter;
+use shared::primitive::{EdgeSizes, Point, Size};
+
+/// Width of an A4 page in CSS pixels
+const A4_WIDTH: f32 = 793.7;
+/// Height of an A4 page in CSS pixels
+const A4_HEIGHT: f32 = 1122.5;
+/// Half an inch
+const DEFAULT_MARGIN: f32 = 48.;
+
+/// Layout of the pages of `InputEvent::PrintToPdf`, in CSS pixels
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    pub page_size: Size,
+    pub margins: EdgeSizes,
+}
+
+impl PrintOptions {
+    /// Size of a page without its margins, the viewport the document is
+    /// laid out in
+    pub fn content_size(&self) -> Size {
+        Size::new(
+            self.page_size.width - self.margins.left - self.margins.right,
+            self.page_size.height - self.margins.top - self.margins.bottom,
+        )
+    }
+}
+
+impl Default for PrintOptions {
+    /// A4 pages with half an inch of margins
+    fn default() -> Self {
+        Self {
+            page_size: Size::new(A4_WIDTH, A4_HEIGHT),
+            margins: EdgeSizes::uniform(DEFAULT_MARGIN),
+        }
+    }
+}
+
+/// Paint the document laid out at the width of a page as a PDF document
+pub async fn render_pdf(layout_tree: &LayoutBoxPtr, options: &PrintOptions) -> Vec<u8> {
+    let content_size = options.content_size();
+    let mut painter = Painter::new(PdfGraphics::new(
+        options.page_size.clone(),
+        options.margins.clone(),
+    ));
+    painter.resize(content_size.clone());
+
+    for page in paginate(layout_tree, content_size.height) {
+        painter.graphics_mut().begin_page(page.height);
+        painter.set_scroll_offset(Point::new(0., page.top));
+        painter.paint(layout_tree);
+    }
+    painter.output().await
+}
+
+#[cfg(test)]
+mod tests {
+    use url::parser::URLParser;
+
+    use super::*;
+    use crate::context::EngineContext;
+    use crate::frame_loader::parse_document;
+    use crate::pipeline::{calculate_layout, calculate_styles};
+
+    #[test]
+    fn test_render_pdf() {
+        let document = parse_document(
+            "<style>#second { break-before: page; }</style>\
+             <p>First page</p><p id='second'>Second page</p>",
+            URLParser::parse("http://example.com", None).unwrap(),
+            None,
+            &EngineContext::new(),
+        );
+        calculate_styles(document.clone());
+
+        let options = PrintOptions::default();
+        let layout_tree = calculate_layout(document, &options.content_size()).unwrap();
+        let pdf = futures::executor::block_on(render_pdf(&layout_tree, &options));
+
+        assert!(pdf.starts_with(b"%PDF-"));
+        let page_count = pdf
+            .windows(b"/Type /Page\n".len())
+            .filter(|window| *window == b"/Type /Page\n")
+            .count();
+        assert_eq!(page_count, 2);
+    }
+}
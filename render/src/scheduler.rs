@@ -0,0 +1,294 @@
+use std::time::{Duration, Instant};
+
+pub enum FramePacing {
+    /// Emit a frame as soon as the page is invalidated
+    Immediate,
+    /// Emit at most one frame per interval
+    Interval(Duration),
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing::Interval(Duration::from_millis(16))
+    }
+}
+
+/// Coalesces invalidations so that a burst of input events results in a
+/// single frame instead of one frame per event.
+pub struct RepaintScheduler {
+    pacing: FramePacing,
+    is_dirty: bool,
+    last_frame: Option<Instant>,
+    /// The pending frame is held back until then, see `defer`
+    deferred_until: Option<Instant>,
+}
+
+impl RepaintScheduler {
+    pub fn new(pacing: FramePacing) -> Self {
+        Self {
+            pacing,
+            is_dirty: false,
+            last_frame: None,
+            deferred_until: None,
+        }
+    }
+
+    pub fn set_pacing(&mut self, pacing: FramePacing) {
+        self.pacing = pacing;
+    }
+
+    /// Mark the current frame as outdated
+    pub fn invalidate(&mut self) {
+        self.is_dirty = true;
+    }
+
+    /// Hold the pending frame back until `until`, when the consumer is not
+    /// ready to receive it yet. Invalidations in the meantime still result
+    /// in a single frame.
+    pub fn defer(&mut self, until: Instant) {
+        self.deferred_until = Some(until);
+    }
+
+    /// When the pending frame should be emitted, if there is one
+    pub fn next_frame_deadline(&self) -> Option<Instant> {
+        if !self.is_dirty {
+            return None;
+        }
+
+        let deadline = match (&self.pacing, self.last_frame) {
+            (FramePacing::Interval(interval), Some(last_frame)) => last_frame + *interval,
+            _ => Instant::now(),
+        };
+        Some(match self.deferred_until {
+            Some(deferred_until) => deadline.max(deferred_until),
+            None => deadline,
+        })
+    }
+
+    pub fn is_frame_due(&self, now: Instant) -> bool {
+        if !self.is_dirty {
+            return false;
+        }
+        if matches!(self.deferred_until, Some(deferred_until) if now < deferred_until) {
+            return false;
+        }
+
+        match (&self.pacing, self.last_frame) {
+            (FramePacing::Interval(interval), Some(last_frame)) => last_frame + *interval <= now,
+            _ => true,
+        }
+    }
+
+    pub fn frame_emitted(&mut self, now: Instant) {
+        self.is_dirty = false;
+        self.last_frame = Some(now);
+        self.deferred_until = None;
+    }
+}
+
+/// Holds the frames of a loading page back until it is ready to be shown,
+/// so that the embedder keeps the previous frame instead of showing the
+/// page before its fonts are applied. Frames are let through after
+/// `timeout` even if the page isn't ready.
+pub struct FirstPaint {
+    timeout: Duration,
+    /// When the page started loading, until its first frame is emitted
+    load_start: Option<Instant>,
+    /// Time from the start of the load to the first frame of the page
+    first_paint_ms: Option<f32>,
+}
+
+impl FirstPaint {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            load_start: None,
+            first_paint_ms: None,
+        }
+    }
+
+    /// A page started loading at `start`
+    pub fn start(&mut self, start: Instant) {
+        self.load_start = Some(start);
+        self.first_paint_ms = None;
+    }
+
+    /// Whether the frame must be held back, when the page isn't ready and
+    /// the timeout hasn't passed yet
+    pub fn is_held(&self, is_ready: bool, now: Instant) -> bool {
+        !is_ready && matches!(self.deadline(), Some(deadline) if now < deadline)
+    }
+
+    /// When frames are let through at the latest, if they are held back
+    pub fn deadline(&self) -> Option<Instant> {
+        self.load_start.map(|start| start + self.timeout)
+    }
+
+    pub fn frame_emitted(&mut self, now: Instant) {
+        if let Some(start) = self.load_start.take() {
+            let elapsed = now.saturating_duration_since(start);
+            self.first_paint_ms = Some(elapsed.as_secs_f32() * 1000.);
+        }
+    }
+
+    /// Time from the start of the load to the first frame of the page, in
+    /// milliseconds
+    pub fn first_paint_ms(&self) -> Option<f32> {
+        self.first_paint_ms
+    }
+}
+
+/// Shows and hides the caret of the focused text control in turn. The
+/// engine waits for the next toggle like for a pending frame, and repaints
+/// when the visibility changes.
+pub struct CaretBlink {
+    interval: Duration,
+    /// When the caret was last shown, `None` if there is no caret
+    started: Option<Instant>,
+    is_visible: bool,
+}
+
+impl CaretBlink {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            started: None,
+            is_visible: false,
+        }
+    }
+
+    /// Show the caret from now on, after it moved or the text changed
+    pub fn restart(&mut self, now: Instant) {
+        self.started = Some(now);
+        self.is_visible = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.started = None;
+        self.is_visible = false;
+    }
+
+    /// The new visibility of the caret if it changed since the last call
+    pub fn poll(&mut self, now: Instant) -> Option<bool> {
+        let started = self.started?;
+        let phase = now.saturating_duration_since(started).as_millis() / self.interval.as_millis();
+        let is_visible = phase.is_multiple_of(2);
+        if is_visible == self.is_visible {
+            return None;
+        }
+        self.is_visible = is_visible;
+        Some(is_visible)
+    }
+
+    /// When the caret is shown or hidden next, if it is blinking
+    pub fn next_toggle(&self, now: Instant) -> Option<Instant> {
+        let started = self.started?;
+        let elapsed = now.saturating_duration_since(started).as_millis();
+        let interval = self.interval.as_millis();
+        let next = (elapsed / interval + 1) * interval;
+        Some(started + Duration::from_millis(next as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_immediate_pacing() {
+        let mut scheduler = RepaintScheduler::new(FramePacing::Immediate);
+        let now = Instant::now();
+        assert!(!scheduler.is_frame_due(now));
+
+        scheduler.invalidate();
+        assert!(scheduler.is_frame_due(Instant::now()));
+
+        scheduler.frame_emitted(now);
+        assert!(!scheduler.is_frame_due(Instant::now()));
+    }
+
+    #[test]
+    fn test_interval_pacing_coalesces_invalidations() {
+        let interval = Duration::from_millis(16);
+        let mut scheduler = RepaintScheduler::new(FramePacing::Interval(interval));
+
+        scheduler.invalidate();
+        let first_frame = Instant::now();
+        assert!(scheduler.is_frame_due(first_frame));
+        scheduler.frame_emitted(first_frame);
+
+        scheduler.invalidate();
+        scheduler.invalidate();
+        assert!(!scheduler.is_frame_due(first_frame + interval / 2));
+        assert_eq!(
+            scheduler.next_frame_deadline(),
+            Some(first_frame + interval)
+        );
+        assert!(scheduler.is_frame_due(first_frame + interval));
+    }
+
+    #[test]
+    fn test_deferred_frame() {
+        let mut scheduler = RepaintScheduler::new(FramePacing::Immediate);
+        let now = Instant::now();
+        let retry = now + Duration::from_millis(5);
+
+        scheduler.invalidate();
+        scheduler.defer(retry);
+        assert!(!scheduler.is_frame_due(now));
+        assert_eq!(scheduler.next_frame_deadline(), Some(retry));
+        assert!(scheduler.is_frame_due(retry));
+
+        scheduler.frame_emitted(retry);
+        scheduler.invalidate();
+        assert!(scheduler.is_frame_due(retry));
+    }
+
+    #[test]
+    fn test_caret_blink() {
+        let interval = Duration::from_millis(500);
+        let mut blink = CaretBlink::new(interval);
+        let now = Instant::now();
+        assert_eq!(blink.next_toggle(now), None);
+
+        blink.restart(now);
+        assert_eq!(blink.poll(now + interval / 2), None);
+        assert_eq!(blink.next_toggle(now + interval / 2), Some(now + interval));
+        assert_eq!(blink.poll(now + interval), Some(false));
+        assert_eq!(blink.poll(now + interval * 2), Some(true));
+
+        // Moving the caret shows it for a full interval
+        blink.restart(now + interval * 3);
+        assert_eq!(blink.poll(now + interval * 3), None);
+        assert_eq!(
+            blink.next_toggle(now + interval * 3),
+            Some(now + interval * 4)
+        );
+
+        blink.stop();
+        assert_eq!(blink.poll(now + interval * 5), None);
+    }
+
+    #[test]
+    fn test_first_paint_waits_for_page() {
+        let timeout = Duration::from_millis(500);
+        let mut first_paint = FirstPaint::new(timeout);
+        let now = Instant::now();
+        assert!(!first_paint.is_held(false, now));
+
+        first_paint.start(now);
+        assert!(first_paint.is_held(false, now + timeout / 2));
+        assert!(!first_paint.is_held(true, now + timeout / 2));
+        // A page that takes too long is shown anyway
+        assert!(!first_paint.is_held(false, now + timeout));
+        assert_eq!(first_paint.deadline(), Some(now + timeout));
+
+        first_paint.frame_emitted(now + timeout / 5);
+        assert_eq!(first_paint.first_paint_ms(), Some(100.));
+        assert!(!first_paint.is_held(false, now));
+        assert_eq!(first_paint.deadline(), None);
+        // Later frames don't change the time of the first one
+        first_paint.frame_emitted(now + timeout);
+        assert_eq!(first_paint.first_paint_ms(), Some(100.));
+    }
+}
@@ -0,0 +1,179 @@
+use gfx::TextMeasure;
+use layout::text_fragments::{TextFragment, TextPosition};
+use painting::OverlayLayer;
+use shared::{color::Color, primitive::Rect};
+
+/// Highlights are painted over the content, so the color is translucent to
+/// keep the selected text visible. It's light blue over a white background.
+pub const SELECTION_COLOR: Color = Color {
+    r: 26,
+    g: 167,
+    b: 254,
+    a: 140,
+};
+
+/// A text selection between an anchor (where the selection started)
+/// and a focus (where it currently ends)
+pub struct Selection {
+    anchor: TextPosition,
+    focus: TextPosition,
+}
+
+impl Selection {
+    pub fn new(position: TextPosition) -> Self {
+        Self {
+            anchor: position,
+            focus: position,
+        }
+    }
+
+    pub fn set_focus(&mut self, position: TextPosition) {
+        self.focus = position;
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    /// Selected text, with a line break between fragments on different lines
+    pub fn text(&self, fragments: &[TextFragment]) -> String {
+        let mut result = String::new();
+        let mut last_line_y = None;
+
+        for (index, start, end) in self.fragment_ranges(fragments) {
+            let fragment = &fragments[index];
+
+            if let Some(y) = last_line_y {
+                if y != fragment.rect.y {
+                    result.push('\n');
+                }
+            }
+
+            result.push_str(&fragment.substring(start, end));
+            last_line_y = Some(fragment.rect.y);
+        }
+
+        result
+    }
+
+    /// Bounds of the selected text of every fragment, in page coordinates
+    pub fn rects(&self, fragments: &[TextFragment]) -> Vec<Rect> {
+        let mut text_measurer = TextMeasure::new();
+
+        self.fragment_ranges(fragments)
+            .into_iter()
+            .map(|(index, start, end)| fragments[index].range_rect(start, end, &mut text_measurer))
+            .collect()
+    }
+
+    pub fn paint_highlights(&self, fragments: &[TextFragment], overlay: &mut OverlayLayer) {
+        for rect in self.rects(fragments) {
+            overlay.add_highlight(rect, SELECTION_COLOR);
+        }
+    }
+
+    /// The selected character range of every fragment covered by the selection
+    fn fragment_ranges(&self, fragments: &[TextFragment]) -> Vec<(usize, usize, usize)> {
+        let (start, end) = if self.anchor <= self.focus {
+            (self.anchor, self.focus)
+        } else {
+            (self.focus, self.anchor)
+        };
+
+        (start.fragment..=end.fragment)
+            .filter_map(|index| {
+                let fragment = fragments.get(index)?;
+                let range_start = if index == start.fragment {
+                    start.offset
+                } else {
+                    0
+                };
+                let range_end = if index == end.fragment {
+                    end.offset
+                } else {
+                    fragment.char_count()
+                };
+                Some((index, range_start, range_end.min(fragment.char_count())))
+            })
+            .filter(|(_, start, end)| start < end)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gfx::{Graphics, RasterGraphics, DEFAULT_FONT};
+    use layout::text_fragments::TextSpacing;
+    use painting::Painter;
+    use shared::primitive::Size;
+
+    use super::*;
+
+    fn fragment(content: &str, rect: Rect) -> TextFragment {
+        TextFragment {
+            content: content.to_string(),
+            rect,
+            font_size: 16.,
+            font: DEFAULT_FONT,
+            spacing: TextSpacing::default(),
+        }
+    }
+
+    #[test]
+    fn test_selection_rects() {
+        let fragments = [
+            fragment("Hello", Rect::new(10., 0., 40., 20.)),
+            fragment("world", Rect::new(10., 20., 40., 20.)),
+        ];
+        let mut selection = Selection::new(TextPosition {
+            fragment: 1,
+            offset: 3,
+        });
+        assert!(selection.rects(&fragments).is_empty());
+
+        // Selected backward, from the middle of the second line
+        selection.set_focus(TextPosition {
+            fragment: 0,
+            offset: 2,
+        });
+        let rects = selection.rects(&fragments);
+        assert_eq!(rects.len(), 2);
+        assert!(rects[0].x > 10. && rects[0].y == 0.);
+        assert!(rects[1].x == 10. && rects[1].y == 20.);
+        assert_eq!(selection.text(&fragments), "llo\nwor");
+    }
+
+    #[test]
+    fn test_selected_text_stays_visible() {
+        let fragments = [fragment("Hello", Rect::new(0., 0., 60., 20.))];
+        let mut selection = Selection::new(TextPosition {
+            fragment: 0,
+            offset: 0,
+        });
+        selection.set_focus(TextPosition {
+            fragment: 0,
+            offset: 5,
+        });
+
+        let mut painter = Painter::new(RasterGraphics::new());
+        painter.resize(Size::new(60., 20.));
+        painter.graphics_mut().fill_text(
+            "Hello".to_string(),
+            fragments[0].rect.clone(),
+            Color::new(0, 0, 0, 255),
+            16.,
+            DEFAULT_FONT,
+        );
+        let mut overlay = OverlayLayer::new();
+        selection.paint_highlights(&fragments, &mut overlay);
+        painter.paint_overlay(&overlay);
+        let bitmap = futures::executor::block_on(painter.output());
+
+        // The glyphs are still darker than the highlight around them, which
+        // an opaque highlight would paint over with its own color
+        let inked = bitmap.chunks(4).filter(|pixel| pixel[1] < 150).count();
+        assert!(inked > 10);
+        let highlighted = bitmap.chunks(4).filter(|pixel| pixel[1] > 200).count();
+        assert!(highlighted > inked);
+    }
+}
@@ -0,0 +1,166 @@
+//! Moving the focus with the arrow keys to the nearest element in their
+//! direction, for embedders without a pointer like TVs and kiosks
+//! https://drafts.csswg.org/css-nav-1/
+
+use dom::node::NodePtr;
+use layout::hit_test::{node_rects, union};
+use layout::layout_box::LayoutBoxPtr;
+use shared::primitive::Rect;
+
+use crate::Key;
+
+/// How much more the offset across the direction counts than the distance
+/// along it, so that elements in line are preferred
+const ORTHOGONAL_WEIGHT: f32 = 2.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl FocusDirection {
+    pub fn from_key(key: &Key) -> Option<Self> {
+        match key {
+            Key::ArrowLeft => Some(Self::Left),
+            Key::ArrowRight => Some(Self::Right),
+            Key::ArrowUp => Some(Self::Up),
+            Key::ArrowDown => Some(Self::Down),
+            _ => None,
+        }
+    }
+}
+
+/// Elements of the document matching `is_candidate` that are laid out, in
+/// tree order, with their bounds in page coordinates
+pub fn collect_candidates(
+    document: &NodePtr,
+    root: &LayoutBoxPtr,
+    is_candidate: &dyn Fn(&NodePtr) -> bool,
+) -> Vec<(NodePtr, Rect)> {
+    let mut candidates = Vec::new();
+    collect_candidates_of(document, root, is_candidate, &mut candidates);
+    candidates
+}
+
+fn collect_candidates_of(
+    node: &NodePtr,
+    root: &LayoutBoxPtr,
+    is_candidate: &dyn Fn(&NodePtr) -> bool,
+    candidates: &mut Vec<(NodePtr, Rect)>,
+) {
+    node.for_each_child(|child| {
+        let child = NodePtr(child);
+        if is_candidate(&child) {
+            let bounds = node_rects(root, &child)
+                .into_iter()
+                .reduce(|a, b| union(&a, &b));
+            if let Some(bounds) = bounds {
+                candidates.push((child.clone(), bounds));
+            }
+        }
+        collect_candidates_of(&child, root, is_candidate, candidates);
+    });
+}
+
+/// Index of the candidate nearest to `from` in the direction. Only the
+/// candidates whose center is past the edge of `from` in the direction are
+/// considered, the first one in tree order wins ties.
+pub fn find_nearest(from: &Rect, direction: FocusDirection, candidates: &[Rect]) -> Option<usize> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rect)| {
+            distance(from, rect, direction).map(|distance| (index, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// Distance from `from` to `to` in the direction, or `None` if `to` isn't
+/// in the direction
+fn distance(from: &Rect, to: &Rect, direction: FocusDirection) -> Option<f32> {
+    let center_x = to.x + to.width / 2.;
+    let center_y = to.y + to.height / 2.;
+    let is_in_direction = match direction {
+        FocusDirection::Left => center_x < from.x,
+        FocusDirection::Right => center_x > from.x + from.width,
+        FocusDirection::Up => center_y < from.y,
+        FocusDirection::Down => center_y > from.y + from.height,
+    };
+    if !is_in_direction {
+        return None;
+    }
+
+    // The gap between the edges along the direction, and the offset of the
+    // centers across it
+    let (along, across) = match direction {
+        FocusDirection::Left | FocusDirection::Right => (
+            gap(from.x, from.width, to.x, to.width),
+            (center_y - (from.y + from.height / 2.)).abs(),
+        ),
+        FocusDirection::Up | FocusDirection::Down => (
+            gap(from.y, from.height, to.y, to.height),
+            (center_x - (from.x + from.width / 2.)).abs(),
+        ),
+    };
+    Some(along + ORTHOGONAL_WEIGHT * across)
+}
+
+/// Space between two segments on an axis, zero if they overlap
+fn gap(start: f32, length: f32, other_start: f32, other_length: f32) -> f32 {
+    f32::max(
+        f32::max(
+            other_start - (start + length),
+            start - (other_start + other_length),
+        ),
+        0.,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_nearest() {
+        // Two rows of links, the second one shifted to the right
+        let candidates = [
+            Rect::new(0., 0., 100., 20.),
+            Rect::new(120., 0., 100., 20.),
+            Rect::new(240., 0., 100., 20.),
+            Rect::new(150., 40., 100., 20.),
+            Rect::new(270., 40., 100., 20.),
+        ];
+        let nearest =
+            |index: usize, direction| find_nearest(&candidates[index], direction, &candidates);
+
+        assert_eq!(nearest(0, FocusDirection::Right), Some(1));
+        assert_eq!(nearest(1, FocusDirection::Left), Some(0));
+        assert_eq!(nearest(0, FocusDirection::Left), None);
+        assert_eq!(nearest(0, FocusDirection::Up), None);
+        // Down from the first link goes to the closest one below even if
+        // it isn't right under it
+        assert_eq!(nearest(0, FocusDirection::Down), Some(3));
+        assert_eq!(nearest(2, FocusDirection::Down), Some(4));
+        assert_eq!(nearest(4, FocusDirection::Up), Some(2));
+        assert_eq!(nearest(3, FocusDirection::Up), Some(1));
+    }
+
+    #[test]
+    fn test_prefer_elements_in_line() {
+        let from = Rect::new(0., 100., 50., 20.);
+        let candidates = [
+            // Closer but far off to the side
+            Rect::new(60., 0., 50., 20.),
+            // Further but on the same row
+            Rect::new(200., 100., 50., 20.),
+        ];
+        assert_eq!(
+            find_nearest(&from, FocusDirection::Right, &candidates),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use dom::{node::NodePtr, textarea};
+use gfx::TextMeasure;
+use layout::{hit_test::node_box, layout_box::LayoutBoxPtr, text_control::TextControlLayout};
+use painting::OverlayLayer;
+use shared::{
+    color::Color,
+    primitive::{Point, Rect},
+};
+
+use crate::editing::{CaretMovement, EditingCommand};
+use crate::selection::SELECTION_COLOR;
+
+const CARET_COLOR: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+const CARET_WIDTH: f32 = 1.;
+
+/// A rendered textarea, for editing its text with the mouse and the
+/// keyboard
+pub struct TextControl {
+    element: NodePtr,
+    /// Content box of the textarea in page coordinates
+    content_rect: Rect,
+    text: String,
+    text_layout: TextControlLayout,
+    text_measure: TextMeasure,
+}
+
+impl TextControl {
+    /// Returns `None` if the element is not a textarea with a box in the
+    /// layout tree
+    pub fn new(element: &NodePtr, root: &LayoutBoxPtr) -> Option<Self> {
+        textarea::edit(element, |_| ())?;
+        let layout_box = node_box(root, element)?;
+        let mut text_measure = TextMeasure::new();
+        let content_rect = layout_box.absolute_rect();
+        let text = textarea::value(element);
+        let text_layout =
+            TextControlLayout::new(element, &text, content_rect.width, &mut text_measure);
+
+        Some(Self {
+            element: element.clone(),
+            content_rect,
+            text,
+            text_layout,
+            text_measure,
+        })
+    }
+
+    /// Move the caret to the character boundary closest to the point, in
+    /// page coordinates. With `extend`, the selection is extended to it.
+    pub fn move_caret_to_point(&mut self, point: &Point, extend: bool) {
+        let point = self.to_text_point(point);
+        let offset = self
+            .text_layout
+            .offset_at(&self.text, &point, &mut self.text_measure);
+        self.edit(|editor| editor.move_to(offset, extend));
+    }
+
+    pub fn execute(&mut self, command: &EditingCommand) {
+        match command {
+            EditingCommand::InsertText(text) => self.edit(|editor| editor.insert(text)),
+            EditingCommand::InsertLineBreak => self.edit(|editor| editor.insert("\n")),
+            EditingCommand::DeleteBackward => self.edit(|editor| editor.delete_backward()),
+            EditingCommand::DeleteForward => self.edit(|editor| editor.delete_forward()),
+            EditingCommand::SelectAll => self.edit(|editor| editor.select_all()),
+            EditingCommand::MoveCaret { movement, extend } => self.move_caret(*movement, *extend),
+        }
+    }
+
+    pub fn selected_text(&self) -> String {
+        textarea::edit(&self.element, |editor| editor.selected_text().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Bounds of the caret in page coordinates, `None` if text is selected
+    /// or if the caret is scrolled out of view
+    pub fn caret_rect(&mut self) -> Option<Rect> {
+        let (caret, selection, scroll_top) = textarea::edit(&self.element, |editor| {
+            (editor.caret(), editor.selection(), editor.scroll_top())
+        })?;
+        if !selection.is_empty() {
+            return None;
+        }
+
+        let position = self
+            .text_layout
+            .caret_position(&self.text, caret, &mut self.text_measure);
+        let y = position.y - scroll_top;
+        if !self.is_line_visible(y) {
+            return None;
+        }
+        Some(Rect::new(
+            self.content_rect.x + position.x,
+            self.content_rect.y + y,
+            CARET_WIDTH,
+            self.text_layout.line_height,
+        ))
+    }
+
+    pub fn paint_caret(&mut self, overlay: &mut OverlayLayer) {
+        if let Some(rect) = self.caret_rect() {
+            overlay.add_highlight(rect, CARET_COLOR);
+        }
+    }
+
+    /// Bounds of the selected text on the visible lines, in page
+    /// coordinates
+    pub fn selection_rects(&mut self) -> Vec<Rect> {
+        let (selection, scroll_top) = textarea::edit(&self.element, |editor| {
+            (editor.selection(), editor.scroll_top())
+        })
+        .unwrap_or_default();
+        let line_height = self.text_layout.line_height;
+        let mut rects = Vec::new();
+
+        for (index, line) in self.text_layout.lines.iter().enumerate() {
+            let start = usize::max(line.start, selection.start);
+            let end = usize::min(line.end, selection.end);
+            let y = index as f32 * line_height - scroll_top;
+            if start >= end || !self.is_line_visible(y) {
+                continue;
+            }
+            let (text, text_layout) = (&self.text, &self.text_layout);
+            let text_measure = &mut self.text_measure;
+            let mut measure = |range| {
+                text_measure
+                    .measure_with_font(&text[range], text_layout.font_size, text_layout.font)
+                    .width
+            };
+            let x = measure(line.start..start);
+            let width = measure(start..end);
+            rects.push(Rect::new(
+                self.content_rect.x + x,
+                self.content_rect.y + y,
+                width,
+                line_height,
+            ));
+        }
+        rects
+    }
+
+    /// Highlight the selected text on the visible lines
+    pub fn paint_selection(&mut self, overlay: &mut OverlayLayer) {
+        for rect in self.selection_rects() {
+            overlay.add_highlight(rect, SELECTION_COLOR);
+        }
+    }
+
+    /// Whether a line at `y` from the top of the content box is painted.
+    /// Text is not clipped, so partially visible lines are not painted.
+    fn is_line_visible(&self, y: f32) -> bool {
+        y >= 0. && y + self.text_layout.line_height <= self.content_rect.height
+    }
+
+    fn move_caret(&mut self, movement: CaretMovement, extend: bool) {
+        let line_bounds = || {
+            let line = self.text_layout.line_at_offset(self.caret());
+            self.text_layout.line_bounds(&self.text, line)
+        };
+        let offset = match movement {
+            CaretMovement::Left => return self.edit(|editor| editor.move_left(extend)),
+            CaretMovement::Right => return self.edit(|editor| editor.move_right(extend)),
+            CaretMovement::WordLeft => return self.edit(|editor| editor.move_word_left(extend)),
+            CaretMovement::WordRight => return self.edit(|editor| editor.move_word_right(extend)),
+            CaretMovement::Up => return self.move_vertically(-1., extend),
+            CaretMovement::Down => return self.move_vertically(1., extend),
+            CaretMovement::LineStart => line_bounds().start,
+            CaretMovement::LineEnd => line_bounds().end,
+            CaretMovement::TextStart => 0,
+            CaretMovement::TextEnd => self.text.len(),
+        };
+        self.edit(|editor| editor.move_to(offset, extend));
+    }
+
+    fn caret(&self) -> usize {
+        textarea::edit(&self.element, |editor| editor.caret()).unwrap_or_default()
+    }
+
+    /// Move the caret to the closest position on the previous or next line
+    fn move_vertically(&mut self, lines: f32, extend: bool) {
+        let line_height = self.text_layout.line_height;
+        let mut position =
+            self.text_layout
+                .caret_position(&self.text, self.caret(), &mut self.text_measure);
+        position.y += lines * line_height + line_height / 2.;
+
+        let offset = if position.y < 0. {
+            0
+        } else if position.y > self.text_layout.height() {
+            self.text.len()
+        } else {
+            self.text_layout
+                .offset_at(&self.text, &position, &mut self.text_measure)
+        };
+        self.edit(|editor| editor.move_to(offset, extend));
+    }
+
+    /// Apply the edit, then lay the text out again and scroll the caret
+    /// into view
+    fn edit(&mut self, f: impl FnOnce(&mut dom::text_editor::TextEditor)) {
+        let text = textarea::edit(&self.element, |editor| {
+            f(editor);
+            editor.text().to_string()
+        });
+        if let Some(text) = text {
+            if text != self.text {
+                self.text_layout = TextControlLayout::new(
+                    &self.element,
+                    &text,
+                    self.content_rect.width,
+                    &mut self.text_measure,
+                );
+                self.text = text;
+            }
+        }
+        self.scroll_to_caret();
+    }
+
+    /// Scroll by whole lines so that the caret line is visible
+    fn scroll_to_caret(&mut self) {
+        let line_height = self.text_layout.line_height;
+        let visible_height = self.content_rect.height;
+        let caret_y = self.text_layout.line_at_offset(self.caret()) as f32 * line_height;
+        let max_scroll_top = f32::max(self.text_layout.height() - visible_height, 0.);
+
+        textarea::edit(&self.element, |editor| {
+            let mut scroll_top = editor.scroll_top();
+            if caret_y < scroll_top {
+                scroll_top = caret_y;
+            } else if caret_y + line_height > scroll_top + visible_height {
+                let hidden_lines = ((caret_y + line_height - visible_height) / line_height).ceil();
+                scroll_top = hidden_lines * line_height;
+            }
+            let max_scroll_top = (max_scroll_top / line_height).ceil() * line_height;
+            editor.set_scroll_top(f32::min(scroll_top, max_scroll_top));
+        });
+    }
+
+    /// Convert a point in page coordinates to the coordinates of the text
+    fn to_text_point(&self, point: &Point) -> Point {
+        let scroll_top = textarea::scroll_top(&self.element);
+        Point::new(
+            point.x - self.content_rect.x,
+            point.y - self.content_rect.y + scroll_top,
+        )
+    }
+}
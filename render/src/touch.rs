@@ -0,0 +1,314 @@
+//! Gestures of touch screens: dragging a finger scrolls the page, which
+//! keeps scrolling with momentum when the finger is lifted while moving,
+//! pinching zooms it and tapping clicks.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use shared::primitive::Point;
+
+/// Distance in device pixels a finger can move before a tap becomes a drag
+const TAP_SLOP: f32 = 8.;
+/// How far back the movements of a finger are used for its velocity
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+/// Slowest speed, in device pixels per second, that starts or keeps a fling
+const MIN_FLING_SPEED: f32 = 50.;
+/// Rate at which a fling slows down, per second. The velocity is divided
+/// by e every 1 / FLING_FRICTION seconds.
+const FLING_FRICTION: f32 = 4.;
+
+/// A finger on the touch screen, in viewport coordinates in device pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct TouchPoint {
+    /// Identifies the finger from the moment it touches the screen until it
+    /// is lifted
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TouchGesture {
+    /// A single finger moved by `delta` from `point`
+    Pan { point: Point, delta: Point },
+    /// Two fingers moved apart or closer by `scale` around `center`
+    Pinch { center: Point, scale: f32 },
+    /// A finger touched the screen and was lifted without moving
+    Tap(Point),
+    /// A panning finger was lifted while moving at `velocity`, in device
+    /// pixels per second
+    Fling { point: Point, velocity: Point },
+}
+
+/// Recognizes the gestures of the fingers on the screen
+#[derive(Default)]
+pub struct TouchTracker {
+    /// Fingers on the screen, in the order they touched it
+    touches: Vec<(u64, Point)>,
+    /// Where the first finger touched the screen
+    start: Option<Point>,
+    /// Whether the fingers moved past the tap slop or more than one finger
+    /// touched the screen, which isn't a tap anymore
+    is_gesture: bool,
+    /// Positions of the panning finger over the velocity window
+    samples: VecDeque<(Instant, Point)>,
+}
+
+impl TouchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn touch_start(&mut self, points: &[TouchPoint], now: Instant) {
+        for point in points {
+            let position = Point::new(point.x, point.y);
+            self.touches.retain(|(id, _)| *id != point.id);
+            self.touches.push((point.id, position.clone()));
+            self.start.get_or_insert(position);
+        }
+        self.is_gesture |= self.touches.len() > 1;
+        self.reset_samples(now);
+    }
+
+    pub fn touch_move(&mut self, points: &[TouchPoint], now: Instant) -> Option<TouchGesture> {
+        let previous = self.touches.clone();
+        for point in points {
+            if let Some((_, position)) = self.touches.iter_mut().find(|(id, _)| *id == point.id) {
+                *position = Point::new(point.x, point.y);
+            }
+        }
+
+        match (previous.as_slice(), self.touches.as_slice()) {
+            ([(_, from)], [(_, to)]) => {
+                let start = self.start.clone().unwrap_or_else(|| from.clone());
+                if !self.is_gesture && distance(&start, to) <= TAP_SLOP {
+                    return None;
+                }
+                // The first pan covers the slop too, so that the page
+                // doesn't lag behind the finger
+                let from = match self.is_gesture {
+                    true => from,
+                    false => &start,
+                };
+                self.is_gesture = true;
+                self.samples.push_back((now, to.clone()));
+                while self
+                    .samples
+                    .front()
+                    .is_some_and(|(time, _)| now.duration_since(*time) > VELOCITY_WINDOW)
+                {
+                    self.samples.pop_front();
+                }
+                let delta = Point::new(to.x - from.x, to.y - from.y);
+                (delta != Point::default()).then(|| TouchGesture::Pan {
+                    point: to.clone(),
+                    delta,
+                })
+            }
+            ([(_, a), (_, b), ..], [(_, new_a), (_, new_b), ..]) => {
+                let (from, to) = (distance(a, b), distance(new_a, new_b));
+                if from <= 0. || to <= 0. || from == to {
+                    return None;
+                }
+                Some(TouchGesture::Pinch {
+                    center: Point::new((new_a.x + new_b.x) / 2., (new_a.y + new_b.y) / 2.),
+                    scale: to / from,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Lift the fingers. Returns the tap or the fling ending the gesture
+    /// once the last finger is lifted.
+    pub fn touch_end(&mut self, points: &[TouchPoint], now: Instant) -> Option<TouchGesture> {
+        let was_panning = self.touches.len() == 1;
+        self.touches
+            .retain(|(id, _)| !points.iter().any(|point| point.id == *id));
+        if !self.touches.is_empty() {
+            // The remaining finger pans from where it is
+            self.reset_samples(now);
+            return None;
+        }
+
+        let start = self.start.take();
+        let is_gesture = std::mem::take(&mut self.is_gesture);
+        let samples = std::mem::take(&mut self.samples);
+        if !is_gesture {
+            return start.map(TouchGesture::Tap);
+        }
+        if !was_panning {
+            return None;
+        }
+
+        // A finger that stopped before it was lifted doesn't fling
+        let (first_time, first) = samples.front()?;
+        let (last_time, last) = samples.back()?;
+        if now.duration_since(*last_time) > VELOCITY_WINDOW {
+            return None;
+        }
+        let elapsed = last_time.duration_since(*first_time).as_secs_f32();
+        if elapsed <= 0. {
+            return None;
+        }
+        let velocity = Point::new((last.x - first.x) / elapsed, (last.y - first.y) / elapsed);
+        (speed(&velocity) >= MIN_FLING_SPEED).then(|| TouchGesture::Fling {
+            point: last.clone(),
+            velocity,
+        })
+    }
+
+    /// Measure the velocity from the current position of the only finger
+    fn reset_samples(&mut self, now: Instant) {
+        self.samples.clear();
+        if let [(_, position)] = self.touches.as_slice() {
+            self.samples.push_back((now, position.clone()));
+        }
+    }
+}
+
+/// Scrolling that goes on after a fling, slowing down until it stops
+pub struct Fling {
+    /// Where the finger was lifted, which picks the scroll container
+    pub point: Point,
+    /// Velocity of the content, in device pixels per second
+    velocity: Point,
+    last_tick: Instant,
+}
+
+impl Fling {
+    pub fn new(point: Point, velocity: Point, now: Instant) -> Self {
+        Self {
+            point,
+            velocity,
+            last_tick: now,
+        }
+    }
+
+    /// Distance the content moved since the last tick, in device pixels.
+    /// Returns `None` once the fling is too slow to go on.
+    pub fn tick(&mut self, now: Instant) -> Option<Point> {
+        if speed(&self.velocity) < MIN_FLING_SPEED {
+            return None;
+        }
+        let elapsed = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        // The velocity decays exponentially, the distance is its integral
+        // over the elapsed time
+        let decay = (-FLING_FRICTION * elapsed).exp();
+        let travel = (1. - decay) / FLING_FRICTION;
+        let delta = Point::new(self.velocity.x * travel, self.velocity.y * travel);
+        self.velocity = Point::new(self.velocity.x * decay, self.velocity.y * decay);
+        Some(delta)
+    }
+}
+
+fn distance(a: &Point, b: &Point) -> f32 {
+    (b.x - a.x).hypot(b.y - a.y)
+}
+
+fn speed(velocity: &Point) -> f32 {
+    velocity.x.hypot(velocity.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn touch(id: u64, x: f32, y: f32) -> TouchPoint {
+        TouchPoint { id, x, y }
+    }
+
+    fn after(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn test_tap() {
+        let now = Instant::now();
+        let mut tracker = TouchTracker::new();
+        tracker.touch_start(&[touch(1, 10., 10.)], now);
+        assert_eq!(tracker.touch_move(&[touch(1, 13., 12.)], now), None);
+        assert_eq!(
+            tracker.touch_end(&[touch(1, 13., 12.)], now),
+            Some(TouchGesture::Tap(Point::new(10., 10.)))
+        );
+    }
+
+    #[test]
+    fn test_pan_and_fling() {
+        let start = Instant::now();
+        let mut tracker = TouchTracker::new();
+        tracker.touch_start(&[touch(1, 100., 300.)], start);
+        assert_eq!(
+            tracker.touch_move(&[touch(1, 100., 280.)], after(start, 10)),
+            Some(TouchGesture::Pan {
+                point: Point::new(100., 280.),
+                delta: Point::new(0., -20.),
+            })
+        );
+        for (index, y) in [260., 240., 220.].iter().enumerate() {
+            let now = after(start, 20 + index as u64 * 10);
+            assert!(tracker.touch_move(&[touch(1, 100., *y)], now).is_some());
+        }
+
+        match tracker.touch_end(&[touch(1, 100., 220.)], after(start, 45)) {
+            Some(TouchGesture::Fling { point, velocity }) => {
+                assert_eq!(point, Point::new(100., 220.));
+                assert_eq!(velocity.x, 0.);
+                assert!((velocity.y + 2000.).abs() < 1.);
+            }
+            gesture => panic!("Expected a fling, got {:?}", gesture),
+        }
+
+        // A finger held still before it is lifted doesn't fling
+        tracker.touch_start(&[touch(2, 100., 300.)], start);
+        tracker.touch_move(&[touch(2, 100., 200.)], after(start, 10));
+        assert_eq!(
+            tracker.touch_end(&[touch(2, 100., 200.)], after(start, 500)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pinch() {
+        let now = Instant::now();
+        let mut tracker = TouchTracker::new();
+        tracker.touch_start(&[touch(1, 100., 100.), touch(2, 200., 100.)], now);
+        assert_eq!(
+            tracker.touch_move(&[touch(1, 50., 100.), touch(2, 250., 100.)], now),
+            Some(TouchGesture::Pinch {
+                center: Point::new(150., 100.),
+                scale: 2.,
+            })
+        );
+        // Lifting a finger neither taps nor flings, and the other one pans
+        assert_eq!(tracker.touch_end(&[touch(2, 250., 100.)], now), None);
+        assert_eq!(
+            tracker.touch_move(&[touch(1, 60., 100.)], now),
+            Some(TouchGesture::Pan {
+                point: Point::new(60., 100.),
+                delta: Point::new(10., 0.),
+            })
+        );
+        assert_eq!(tracker.touch_end(&[touch(1, 60., 100.)], now), None);
+    }
+
+    #[test]
+    fn test_fling_slows_down() {
+        let start = Instant::now();
+        let mut fling = Fling::new(Point::default(), Point::new(0., 1000.), start);
+        let first = fling.tick(after(start, 16)).unwrap();
+        let second = fling.tick(after(start, 32)).unwrap();
+        assert!(first.y > second.y && second.y > 0.);
+
+        // The fling stops after covering about velocity / friction
+        let mut travel = first.y + second.y;
+        let mut now = after(start, 32);
+        while let Some(delta) = fling.tick(now) {
+            travel += delta.y;
+            now += Duration::from_millis(16);
+        }
+        assert!(travel > 200. && travel < 1000. / FLING_FRICTION);
+    }
+}
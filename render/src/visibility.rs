@@ -0,0 +1,218 @@
+//! Which elements have a box in the viewport, updated after each layout and
+//! scroll. The loading of the resources of `loading=lazy` elements waits
+//! until the elements come near the viewport, and embedders can ask which
+//! elements are shown.
+//! https://html.spec.whatwg.org/multipage/urls-and-fetching.html#lazy-loading-attributes
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use dom::node::NodePtr;
+use dom::node_id::NodeId;
+use layout::flow::line_box::LineFragmentData;
+use layout::layout_box::LayoutBoxPtr;
+use shared::primitive::{EdgeSizes, Rect};
+
+/// Distance in CSS pixels around the viewport within which lazily loaded
+/// resources are fetched, so that they are ready once scrolled into view
+pub const LAZY_LOAD_MARGIN: f32 = 1250.;
+
+/// Where an element is relative to the viewport, sent in response to
+/// `InputEvent::QueryVisibility`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementVisibility {
+    pub node_id: NodeId,
+    /// A box of the element intersects the viewport
+    pub is_visible: bool,
+    /// A box of the element is within `LAZY_LOAD_MARGIN` of the viewport
+    pub is_near_viewport: bool,
+    /// Bounds of the element in viewport coordinates, in device pixels, or
+    /// `None` if the element has no box
+    pub bounds: Option<Rect>,
+}
+
+/// Whether the resource of the element is only loaded once the element is
+/// near the viewport
+pub fn is_lazy_loaded(node: &NodePtr) -> bool {
+    let element = match node.as_element_opt() {
+        Some(element) => element,
+        None => return false,
+    };
+    (element.is_html("iframe") || element.is_html("img"))
+        && element
+            .get_attribute("loading")
+            .is_some_and(|loading| loading.eq_ignore_ascii_case("lazy"))
+}
+
+#[derive(Default)]
+pub struct VisibilityTracker {
+    /// Viewport the boxes were last intersected with, in page coordinates
+    viewport: Option<Rect>,
+    /// Elements with a box intersecting the viewport
+    visible: HashSet<NodeId>,
+    /// Elements with a box within `LAZY_LOAD_MARGIN` of the viewport
+    near: HashSet<NodeId>,
+    /// Lazily loaded elements that came near the viewport, whose resources
+    /// can be loaded
+    lazy_loads: Vec<NodePtr>,
+}
+
+impl VisibilityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn viewport(&self) -> Option<&Rect> {
+        self.viewport.as_ref()
+    }
+
+    /// Intersect the boxes of the layout tree with the viewport, in page
+    /// coordinates. Boxes clipped by their scroll containers still count.
+    pub fn update(&mut self, root: &LayoutBoxPtr, viewport: &Rect) {
+        let near_viewport = viewport.add_outer_edges(&EdgeSizes::uniform(LAZY_LOAD_MARGIN));
+        let mut element_rects = Vec::new();
+        collect_element_rects(root, &mut element_rects);
+
+        let previous_near = std::mem::take(&mut self.near);
+        self.visible.clear();
+        for (element, rect) in element_rects {
+            let id = element.id();
+            if intersects(&rect, viewport) {
+                self.visible.insert(id);
+            }
+            let came_near = intersects(&rect, &near_viewport)
+                && self.near.insert(id)
+                && !previous_near.contains(&id);
+            if came_near && is_lazy_loaded(&element) {
+                self.lazy_loads.push(element);
+            }
+        }
+        self.viewport = Some(viewport.clone());
+    }
+
+    pub fn is_visible(&self, id: &NodeId) -> bool {
+        self.visible.contains(id)
+    }
+
+    pub fn is_near_viewport(&self, id: &NodeId) -> bool {
+        self.near.contains(id)
+    }
+
+    /// Lazily loaded elements that came near the viewport since the last
+    /// call, in tree order of their boxes
+    pub fn take_lazy_loads(&mut self) -> Vec<NodePtr> {
+        std::mem::take(&mut self.lazy_loads)
+    }
+}
+
+/// Whether the rects overlap or touch, so that empty boxes at the edge of
+/// the viewport count as in it
+fn intersects(a: &Rect, b: &Rect) -> bool {
+    a.x <= b.x + b.width && b.x <= a.x + a.width && a.y <= b.y + b.height && b.y <= a.y + a.height
+}
+
+/// Elements with their border boxes in page coordinates. Inline elements
+/// get a rect for each line fragment of their content.
+fn collect_element_rects(layout_box: &LayoutBoxPtr, rects: &mut Vec<(NodePtr, Rect)>) {
+    let is_split_across_lines = layout_box.is_inline() && !layout_box.is_inline_block();
+    if let (Some(node), false) = (layout_box.node(), is_split_across_lines) {
+        if node.is_element() {
+            rects.push((node, layout_box.border_box_absolute()));
+        }
+    }
+
+    if layout_box.is_block() && layout_box.children_are_inline() {
+        let location = layout_box.content_origin();
+        let block_node = layout_box.get_non_anonymous_parent().node();
+        for line in layout_box.lines().borrow().iter() {
+            for fragment in &line.fragments {
+                let fragment_box = match &fragment.data {
+                    LineFragmentData::Box(fragment_box)
+                    | LineFragmentData::Text(fragment_box, _) => fragment_box,
+                    LineFragmentData::Marker(_) => continue,
+                };
+                // Atomic inlines are reported through their own box
+                if fragment_box.is_inline_block() {
+                    continue;
+                }
+
+                let mut rect = Rect::from((location.clone(), fragment.size.clone()));
+                rect.translate(fragment.offset.x, fragment.offset.y);
+                // The fragment is part of every inline element it is in
+                let mut node = fragment_box.node();
+                while let Some(current) = node {
+                    if matches!(&block_node, Some(block_node) if Rc::ptr_eq(block_node, &current)) {
+                        break;
+                    }
+                    if current.is_element() {
+                        rects.push((current.clone(), rect.clone()));
+                    }
+                    node = current.parent().map(NodePtr);
+                }
+            }
+        }
+    }
+
+    layout_box.for_each_child(|child| collect_element_rects(&child, rects));
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::primitive::Size;
+    use url::parser::URLParser;
+
+    use super::*;
+    use crate::context::EngineContext;
+    use crate::frame_loader::parse_document;
+    use crate::pipeline::{calculate_layout, calculate_styles};
+
+    #[test]
+    fn test_track_visible_elements() {
+        let url = URLParser::parse("http://example.com", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0; } div { height: 500px; }</style>\
+             <div id=top><span id=label>Top</span></div>\
+             <div id=middle></div>\
+             <div id=bottom><iframe loading=lazy></iframe><iframe></iframe></div>\
+             <div></div><div></div><div id=far><iframe loading=LAZY></iframe></div>",
+            url,
+            None,
+            &EngineContext::new(),
+        );
+        calculate_styles(document.clone());
+        let layout_tree = calculate_layout(document.clone(), &Size::new(800., 600.)).unwrap();
+        let id = |element_id: &str| {
+            let mut elements = document.get_elements_by_tag_name("div");
+            elements.extend(document.get_elements_by_tag_name("span"));
+            elements
+                .into_iter()
+                .find(|element| element.as_element().id().as_deref() == Some(element_id))
+                .map(|element| element.id())
+                .unwrap()
+        };
+        let iframes = document.get_elements_by_tag_name("iframe");
+
+        let mut tracker = VisibilityTracker::new();
+        tracker.update(&layout_tree, &Rect::new(0., 0., 800., 600.));
+        assert!(tracker.is_visible(&id("top")));
+        assert!(tracker.is_visible(&id("label")));
+        assert!(tracker.is_visible(&id("middle")));
+        assert!(!tracker.is_visible(&id("bottom")));
+        assert!(tracker.is_near_viewport(&id("bottom")));
+        assert!(!tracker.is_near_viewport(&id("far")));
+        // Only the lazy iframe near the viewport is ready to load
+        let lazy_loads = tracker.take_lazy_loads();
+        assert_eq!(lazy_loads.len(), 1);
+        assert_eq!(lazy_loads[0].id(), iframes[0].id());
+
+        // Scrolling down reveals the last iframe, the others aren't loaded
+        // again
+        tracker.update(&layout_tree, &Rect::new(0., 1200., 800., 600.));
+        assert!(!tracker.is_visible(&id("top")));
+        assert!(tracker.is_visible(&id("bottom")));
+        let lazy_loads = tracker.take_lazy_loads();
+        assert_eq!(lazy_loads.len(), 1);
+        assert_eq!(lazy_loads[0].id(), iframes[2].id());
+        assert!(tracker.take_lazy_loads().is_empty());
+    }
+}
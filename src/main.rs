@@ -49,8 +49,15 @@ fn main() {
 
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                let mut page = Page::new(Size::new(width as f32, height as f32)).await;
-                page.load_html(html_code.to_string(), base_url).await;
+                let mut page = match Page::new(Size::new(width as f32, height as f32), 1.).await {
+                    Ok(page) => page,
+                    Err(e) => {
+                        log::error!("Unable to start rendering: {}", e);
+                        return;
+                    }
+                };
+                page.load_html(html_code.to_string(), base_url, Default::default())
+                    .await;
                 let bitmap = page.bitmap().unwrap().clone();
 
                 let buffer = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, bitmap).unwrap();
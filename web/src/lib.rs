@@ -0,0 +1,14 @@
+//! The render pipeline compiled to WebAssembly, to demo and test the engine
+//! in a browser. Pages are parsed, styled and laid out like in the engine,
+//! then painted with WebGPU onto an HTML canvas.
+//!
+//! There is no resource loader in the browser, so pages are loaded from
+//! their source and their stylesheets are inlined.
+
+mod pipeline;
+#[cfg(target_arch = "wasm32")]
+mod view;
+
+pub use pipeline::{calculate_layout, calculate_styles, parse_document};
+#[cfg(target_arch = "wasm32")]
+pub use view::WebView;
@@ -0,0 +1,72 @@
+use css::media_query::structs::MediaEnvironment;
+use dom::{
+    document::Document,
+    node::{Node, NodeData, NodePtr},
+};
+use layout::layout_box::LayoutBoxPtr;
+use shared::{primitive::Size, tree_node::TreeNode};
+use url::Url;
+
+/// Parse the page with the default styles of the engine
+pub fn parse_document(html: &str, base_url: Url) -> NodePtr {
+    let document = NodePtr(TreeNode::new(Node::new(
+        NodeData::Document(Document::new()),
+    )));
+    document
+        .as_document()
+        .insert_user_agent_stylesheet(style::user_agent::user_agent_stylesheet());
+    document.as_document().set_base(Some(base_url));
+
+    let tokenizer = html::tokenizer::Tokenizer::new(html.chars());
+    let tree_builder = html::tree_builder::TreeBuilder::new(tokenizer, document);
+    tree_builder.run()
+}
+
+/// Compute the styles of every node of the document, with its media
+/// queries evaluated for a viewport of `viewport` CSS pixels
+pub fn calculate_styles(document_node: &NodePtr, viewport: &Size) {
+    let document = document_node.as_document();
+    let environment = MediaEnvironment {
+        viewport_width: viewport.width,
+        viewport_height: viewport.height,
+        ..document.media_environment()
+    };
+    document.set_media_environment(environment);
+    layout::styles::calculate_styles(document_node.clone());
+}
+
+/// Build and lay out the layout tree of the document for a viewport of
+/// `viewport` CSS pixels
+pub fn calculate_layout(document_node: &NodePtr, viewport: &Size) -> Option<LayoutBoxPtr> {
+    layout::document::layout_document(document_node.clone(), viewport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use gfx::RasterGraphics;
+    use painting::Painter;
+    use url::parser::URLParser;
+
+    #[test]
+    fn test_render_pipeline() {
+        let base_url = URLParser::parse("https://example.com/", None).unwrap();
+        let document = parse_document(
+            "<style>body { margin: 0 } div { width: 10px; height: 10px; background-color: red }\
+             @media (max-width: 500px) { div { background-color: green } }</style><div></div>",
+            base_url,
+        );
+        let viewport = Size::new(400., 300.);
+        calculate_styles(&document, &viewport);
+        let layout_tree = calculate_layout(&document, &viewport).unwrap();
+
+        let mut painter = Painter::new(RasterGraphics::new());
+        painter.resize(viewport);
+        painter.paint(&layout_tree);
+        let bitmap = block_on(painter.output());
+        let pixel = |x: usize, y: usize| bitmap[(y * 400 + x) * 4..][..4].to_vec();
+        assert_eq!(pixel(5, 5), vec![0, 128, 0, 255]);
+        assert_eq!(pixel(15, 5), vec![255, 255, 255, 255]);
+    }
+}
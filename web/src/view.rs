@@ -0,0 +1,80 @@
+use dom::node::NodePtr;
+use gfx::{Canvas, GfxConfig};
+use painting::Painter;
+use shared::primitive::Size;
+use url::parser::URLParser;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlCanvasElement;
+
+use crate::pipeline::{calculate_layout, calculate_styles, parse_document};
+
+/// A page rendered onto an HTML canvas
+///
+/// ```js
+/// const view = await WebView.create(document.querySelector("canvas"));
+/// view.resize(800, 600, window.devicePixelRatio);
+/// view.load_html("<p>Hello</p>", location.href);
+/// view.render();
+/// ```
+#[wasm_bindgen]
+pub struct WebView {
+    html_canvas: HtmlCanvasElement,
+    painter: Painter<Canvas<'static>>,
+    document: Option<NodePtr>,
+    /// Size of the viewport in CSS pixels
+    viewport: Size,
+    device_pixel_ratio: f32,
+}
+
+#[wasm_bindgen]
+impl WebView {
+    /// Render onto the canvas with WebGPU. Fails if the browser doesn't
+    /// support it.
+    pub async fn create(html_canvas: HtmlCanvasElement) -> Result<WebView, JsValue> {
+        let canvas = Canvas::from_html_canvas(&html_canvas, GfxConfig::default())
+            .await
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let mut view = WebView {
+            painter: Painter::new(canvas),
+            viewport: Size::new(html_canvas.width() as f32, html_canvas.height() as f32),
+            html_canvas,
+            document: None,
+            device_pixel_ratio: 1.,
+        };
+        view.resize(view.viewport.width, view.viewport.height, 1.);
+        Ok(view)
+    }
+
+    /// Load a page from its source, with its relative URLs resolved
+    /// against `base_url`
+    pub fn load_html(&mut self, html: &str, base_url: &str) -> Result<(), JsValue> {
+        let base_url = URLParser::parse(base_url, None)
+            .ok_or_else(|| JsValue::from_str(&format!("Invalid base URL: {}", base_url)))?;
+        self.document = Some(parse_document(html, base_url));
+        Ok(())
+    }
+
+    /// Resize the viewport to the size in CSS pixels. The canvas is sized
+    /// in device pixels.
+    pub fn resize(&mut self, width: f32, height: f32, device_pixel_ratio: f32) {
+        self.viewport = Size::new(width, height);
+        self.device_pixel_ratio = device_pixel_ratio;
+        let device_size = Size::new(width * device_pixel_ratio, height * device_pixel_ratio);
+        self.html_canvas.set_width(device_size.width as u32);
+        self.html_canvas.set_height(device_size.height as u32);
+        self.painter.resize(device_size);
+        self.painter.set_scale(device_pixel_ratio);
+    }
+
+    /// Style, lay out and paint the page onto the canvas
+    pub fn render(&mut self) {
+        let layout_tree = self.document.as_ref().and_then(|document| {
+            calculate_styles(document, &self.viewport);
+            calculate_layout(document, &self.viewport)
+        });
+        if let Some(layout_tree) = layout_tree {
+            self.painter.paint(&layout_tree);
+        }
+        self.painter.graphics_mut().paint();
+    }
+}